@@ -8,13 +8,17 @@ use intmax2_interfaces::api::balance_prover::types::{
     ProveSingleClaimRequest, ProveSingleWithdrawalRequest, ProveSpentRequest, ProveUpdateRequest,
 };
 
-use crate::api::balance_prover::BalanceProver;
+use crate::api::{balance_prover::BalanceProver, limits::RequestLimits};
 
 #[post("/prove-spent")]
 pub async fn prove_spent(
     state: Data<BalanceProver>,
+    limits: Data<RequestLimits>,
     request: Json<ProveSpentRequest>,
 ) -> Result<Json<ProveResponse>, Error> {
+    limits
+        .check_transfers(request.spent_witness.transfers.len())
+        .map_err(actix_web::error::ErrorPayloadTooLarge)?;
     let proof = state
         .prove_spent(&request.spent_witness)
         .map_err(actix_web::error::ErrorInternalServerError)?;
@@ -14,4 +14,6 @@ pub enum BalanceProverError {
     ProveSingleWithdrawalError(String),
     #[error("ProveSingleClaimError: {0}")]
     ProveSingleClaimError(String),
+    #[error("Too many transfers in witness: {count} > {max}")]
+    TooManyTransfers { count: usize, max: usize },
 }
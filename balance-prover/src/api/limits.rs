@@ -0,0 +1,52 @@
+use intmax2_zkp::constants::NUM_TRANSFERS_IN_TX;
+
+use crate::Env;
+
+use super::error::BalanceProverError;
+
+/// Default maximum size of a request body in bytes, the default JSON limit of actix-web. A
+/// send request, which carries the spent, update and previous balance proofs, fits in it.
+pub const DEFAULT_MAX_REQUEST_SIZE: usize = 2 * 1024 * 1024;
+
+/// Default maximum number of transfers in a spent witness. Honest clients always pad the
+/// transfers of a tx to exactly this many.
+pub const DEFAULT_MAX_TRANSFERS_PER_WITNESS: usize = NUM_TRANSFERS_IN_TX;
+
+/// Limits on the requests accepted by the balance prover API, so that a public endpoint can't be
+/// made to allocate or prove arbitrarily large witnesses.
+#[derive(Debug, Clone, Copy)]
+pub struct RequestLimits {
+    pub max_request_size: usize,
+    pub max_transfers_per_witness: usize,
+}
+
+impl Default for RequestLimits {
+    fn default() -> Self {
+        Self {
+            max_request_size: DEFAULT_MAX_REQUEST_SIZE,
+            max_transfers_per_witness: DEFAULT_MAX_TRANSFERS_PER_WITNESS,
+        }
+    }
+}
+
+impl RequestLimits {
+    pub fn from_env(env: &Env) -> Self {
+        let default = Self::default();
+        Self {
+            max_request_size: env.max_request_size.unwrap_or(default.max_request_size),
+            max_transfers_per_witness: env
+                .max_transfers_per_witness
+                .unwrap_or(default.max_transfers_per_witness),
+        }
+    }
+
+    pub fn check_transfers(&self, num_transfers: usize) -> Result<(), BalanceProverError> {
+        if num_transfers > self.max_transfers_per_witness {
+            return Err(BalanceProverError::TooManyTransfers {
+                count: num_transfers,
+                max: self.max_transfers_per_witness,
+            });
+        }
+        Ok(())
+    }
+}
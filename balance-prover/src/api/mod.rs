@@ -2,3 +2,4 @@
 pub mod api;
 pub mod balance_prover;
 pub mod error;
+pub mod limits;
@@ -5,4 +5,8 @@ pub mod api;
 #[derive(Deserialize)]
 pub struct Env {
     pub port: u16,
+
+    // request limits, the defaults are in `api::limits`
+    pub max_request_size: Option<usize>,
+    pub max_transfers_per_witness: Option<usize>,
 }
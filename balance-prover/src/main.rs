@@ -1,12 +1,17 @@
-use actix_cors::Cors;
-use actix_web::{web::Data, App, HttpServer};
+use actix_web::{
+    middleware::from_fn,
+    web::{Data, JsonConfig},
+    App, HttpServer,
+};
 use balance_prover::{
-    api::{api::balance_prover_scope, balance_prover::BalanceProver},
+    api::{api::balance_prover_scope, balance_prover::BalanceProver, limits::RequestLimits},
     Env,
 };
 use server_common::{
+    cors::CorsConfig,
     health_check::{health_check, set_name_and_version},
     logger,
+    metrics::track_requests,
 };
 use std::io::{self};
 use tracing_actix_web::TracingLogger;
@@ -22,12 +27,24 @@ async fn main() -> std::io::Result<()> {
 
     let state = BalanceProver::new().map_err(io::Error::other)?;
     let state = Data::new(state);
+    let limits = RequestLimits::from_env(&env);
+    log::info!(
+        "request limits: max size {} bytes, max {} transfers per witness",
+        limits.max_request_size,
+        limits.max_transfers_per_witness
+    );
+    let cors_config = CorsConfig::from_env().map_err(io::Error::other)?;
+
     HttpServer::new(move || {
-        let cors = Cors::permissive();
+        let cors = cors_config.cors();
         App::new()
             .wrap(cors)
             .wrap(TracingLogger::<logger::CustomRootSpanBuilder>::new())
+            .wrap(from_fn(track_requests))
             .app_data(state.clone())
+            .app_data(Data::new(limits))
+            // oversized bodies are rejected with 413 Payload Too Large
+            .app_data(JsonConfig::default().limit(limits.max_request_size))
             .service(health_check)
             .service(balance_prover_scope())
     })
@@ -0,0 +1,11 @@
+DROP TABLE IF EXISTS block_builder_reserved_nonces;
+DROP TABLE IF EXISTS block_builder_next_nonces;
+DROP TABLE IF EXISTS block_builder_empty_block_posts;
+DROP TABLE IF EXISTS block_builder_block_post_tasks;
+DROP TABLE IF EXISTS block_builder_fee_collection_tasks;
+DROP TABLE IF EXISTS block_builder_expired_requests;
+DROP TABLE IF EXISTS block_builder_signatures;
+DROP TABLE IF EXISTS block_builder_request_blocks;
+DROP TABLE IF EXISTS block_builder_memos;
+DROP TABLE IF EXISTS block_builder_last_processed;
+DROP TABLE IF EXISTS block_builder_tx_requests;
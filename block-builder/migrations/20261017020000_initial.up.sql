@@ -0,0 +1,92 @@
+-- Storage of the block builder when STORAGE_BACKEND=postgres. Every table is partitioned by
+-- cluster_id, so that several clusters can share a database like they share a redis.
+
+CREATE TABLE IF NOT EXISTS block_builder_tx_requests (
+    id BIGSERIAL PRIMARY KEY,
+    cluster_id VARCHAR(255) NOT NULL,
+    is_registration BOOLEAN NOT NULL,
+    request TEXT NOT NULL,
+    created_at BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_block_builder_tx_requests_queue
+ON block_builder_tx_requests (cluster_id, is_registration, id);
+
+CREATE TABLE IF NOT EXISTS block_builder_last_processed (
+    cluster_id VARCHAR(255) NOT NULL,
+    is_registration BOOLEAN NOT NULL,
+    processed_at BIGINT NOT NULL,
+    PRIMARY KEY (cluster_id, is_registration)
+);
+
+CREATE TABLE IF NOT EXISTS block_builder_memos (
+    cluster_id VARCHAR(255) NOT NULL,
+    block_id VARCHAR(255) NOT NULL,
+    memo TEXT NOT NULL,
+    created_at BIGINT NOT NULL,
+    PRIMARY KEY (cluster_id, block_id)
+);
+
+CREATE TABLE IF NOT EXISTS block_builder_request_blocks (
+    cluster_id VARCHAR(255) NOT NULL,
+    request_id VARCHAR(255) NOT NULL,
+    block_id VARCHAR(255) NOT NULL,
+    created_at BIGINT NOT NULL,
+    PRIMARY KEY (cluster_id, request_id)
+);
+
+CREATE TABLE IF NOT EXISTS block_builder_signatures (
+    id BIGSERIAL PRIMARY KEY,
+    cluster_id VARCHAR(255) NOT NULL,
+    block_id VARCHAR(255) NOT NULL,
+    signature TEXT NOT NULL,
+    created_at BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_block_builder_signatures_block_id
+ON block_builder_signatures (cluster_id, block_id);
+
+CREATE TABLE IF NOT EXISTS block_builder_expired_requests (
+    cluster_id VARCHAR(255) NOT NULL,
+    request_id VARCHAR(255) NOT NULL,
+    expired_at BIGINT NOT NULL,
+    PRIMARY KEY (cluster_id, request_id)
+);
+
+CREATE TABLE IF NOT EXISTS block_builder_fee_collection_tasks (
+    id BIGSERIAL PRIMARY KEY,
+    cluster_id VARCHAR(255) NOT NULL,
+    task TEXT NOT NULL,
+    created_at BIGINT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS block_builder_block_post_tasks (
+    id BIGSERIAL PRIMARY KEY,
+    cluster_id VARCHAR(255) NOT NULL,
+    is_high_priority BOOLEAN NOT NULL,
+    task TEXT NOT NULL,
+    created_at BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_block_builder_block_post_tasks_queue
+ON block_builder_block_post_tasks (cluster_id, is_high_priority, id);
+
+CREATE TABLE IF NOT EXISTS block_builder_empty_block_posts (
+    cluster_id VARCHAR(255) PRIMARY KEY,
+    posted_at BIGINT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS block_builder_next_nonces (
+    cluster_id VARCHAR(255) NOT NULL,
+    is_registration BOOLEAN NOT NULL,
+    next_nonce BIGINT NOT NULL,
+    PRIMARY KEY (cluster_id, is_registration)
+);
+
+CREATE TABLE IF NOT EXISTS block_builder_reserved_nonces (
+    cluster_id VARCHAR(255) NOT NULL,
+    is_registration BOOLEAN NOT NULL,
+    nonce BIGINT NOT NULL,
+    reserved_at BIGINT NOT NULL,
+    PRIMARY KEY (cluster_id, is_registration, nonce)
+);
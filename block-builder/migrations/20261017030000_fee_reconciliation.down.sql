@@ -0,0 +1 @@
+DROP TABLE IF EXISTS block_builder_fee_reconciliation;
@@ -0,0 +1,12 @@
+-- Blocks whose fee was collected or whose post failed, to flag the blocks with both once
+-- when RECONCILE_UNPOSTED_FEES=true.
+
+CREATE TABLE IF NOT EXISTS block_builder_fee_reconciliation (
+    cluster_id VARCHAR(255) NOT NULL,
+    block_id VARCHAR(255) NOT NULL,
+    transfers TEXT,
+    failure TEXT,
+    flagged BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at BIGINT NOT NULL,
+    PRIMARY KEY (cluster_id, block_id)
+);
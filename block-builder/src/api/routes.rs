@@ -38,6 +38,7 @@ pub async fn tx_request(
             request.pubkey,
             request.tx,
             &request.fee_proof,
+            request.valid_until,
         )
         .await
         .map_err(actix_web::error::ErrorInternalServerError)?;
@@ -55,7 +56,16 @@ pub async fn query_proposal(
         .query_proposal(&request.request_id)
         .await
         .map_err(actix_web::error::ErrorInternalServerError)?;
-    Ok(Json(QueryProposalResponse { block_proposal }))
+    let is_expired = block_proposal.is_none()
+        && state
+            .block_builder
+            .is_tx_request_expired(&request.request_id)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(Json(QueryProposalResponse {
+        block_proposal,
+        is_expired,
+    }))
 }
 
 #[post("/post-signature")]
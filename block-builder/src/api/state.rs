@@ -43,8 +43,12 @@ mod tests {
         let env = EnvVar {
             port: 9004,
             block_builder_url: "http://localhost:9004".to_string(),
+            storage_backend: None,
             redis_url: Some(format!("redis://localhost:{port}").to_string()),
             cluster_id: Some("1".to_string()),
+            database_url: None,
+            database_max_connections: None,
+            database_timeout: None,
             l2_rpc_url: "http://localhost:8545".to_string(),
             rollup_contract_address: Address::default(),
             block_builder_registry_contract_address: Address::default(),
@@ -64,11 +68,19 @@ mod tests {
             gas_limit_for_block_post: Some(40000),
             heart_beat_interval: 86400,
             nonce_waiting_time: None,
+            nonce_reclaim_max_age: None,
+            fee_priority_ordering: None,
+            min_senders_for_immediate_post: None,
+            min_signatures_to_post: None,
+            max_proposing_block_interval: None,
+            fee_collection_dry_run: None,
+            reconcile_unposted_fees: None,
             beneficiary_pubkey: None,
             registration_fee: Some("0:100,1:2000".to_string()),
             non_registration_fee: Some("0:100,1:2000".to_string()),
             registration_collateral_fee: None,
             non_registration_collateral_fee: None,
+            fee_token_allowlist: None,
         };
 
         // Run docker image
@@ -110,8 +122,12 @@ mod tests {
         let env = EnvVar {
             port: 9004,
             block_builder_url: "http://localhost:9004".to_string(),
+            storage_backend: None,
             redis_url: None,
             cluster_id: Some("1".to_string()),
+            database_url: None,
+            database_max_connections: None,
+            database_timeout: None,
             l2_rpc_url: "http://localhost:8545".to_string(),
             rollup_contract_address: Address::default(),
             block_builder_registry_contract_address: Address::default(),
@@ -131,11 +147,19 @@ mod tests {
             gas_limit_for_block_post: Some(40000),
             heart_beat_interval: 86400,
             nonce_waiting_time: None,
+            nonce_reclaim_max_age: None,
+            fee_priority_ordering: None,
+            min_senders_for_immediate_post: None,
+            min_signatures_to_post: None,
+            max_proposing_block_interval: None,
+            fee_collection_dry_run: None,
+            reconcile_unposted_fees: None,
             beneficiary_pubkey: None,
             registration_fee: Some("0:100,1:2000".to_string()),
             non_registration_fee: Some("0:100,1:2000".to_string()),
             registration_collateral_fee: None,
             non_registration_collateral_fee: None,
+            fee_token_allowlist: None,
         };
 
         // Create new State
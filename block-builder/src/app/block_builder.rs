@@ -28,11 +28,15 @@ use intmax2_zkp::{
         block_builder::{BlockProposal, UserSignature},
         tx::Tx,
     },
+    constants::NUM_SENDERS_IN_BLOCK,
     ethereum_types::{
         account_id::AccountId, address::Address, u256::U256, u32limb_trait::U32LimbTrait,
     },
 };
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 use uuid::Uuid;
 
 use crate::{
@@ -42,8 +46,12 @@ use crate::{
 
 use super::{
     error::BlockBuilderError,
-    fee::{convert_fee_vec, parse_fee_str},
-    storage::{self, config::StorageConfig, Storage},
+    fee::{convert_fee_vec, parse_fee_str, parse_fee_token_allowlist},
+    storage::{
+        self,
+        config::{StorageBackend, StorageConfig},
+        Storage,
+    },
 };
 
 pub const DEFAULT_POST_BLOCK_CHANNEL: u64 = 100;
@@ -58,6 +66,8 @@ pub struct Config {
 
     pub initial_heart_beat_delay: u64,
     pub heart_beat_interval: u64,
+    // reserved nonces older than this (in seconds) are released. Disabled if None.
+    pub nonce_reclaim_max_age: Option<u64>,
 
     // fees
     pub beneficiary_pubkey: Option<U256>,
@@ -67,6 +77,8 @@ pub struct Config {
     pub non_registration_fee: Option<HashMap<u32, U256>>,
     pub registration_collateral_fee: Option<HashMap<u32, U256>>,
     pub non_registration_collateral_fee: Option<HashMap<u32, U256>>,
+    // if set, only fees in these token indices are accepted and advertised
+    pub fee_token_allowlist: Option<HashSet<u32>>,
 }
 
 #[derive(Clone)]
@@ -141,6 +153,28 @@ impl BlockBuilder {
             .as_ref()
             .map(|fee| parse_fee_str(fee))
             .transpose()?;
+        let fee_token_allowlist = env
+            .fee_token_allowlist
+            .as_ref()
+            .map(|allowlist| parse_fee_token_allowlist(allowlist))
+            .transpose()?;
+        let registration_fee =
+            restrict_fee_tokens("registration_fee", registration_fee, &fee_token_allowlist)?;
+        let non_registration_fee = restrict_fee_tokens(
+            "non_registration_fee",
+            non_registration_fee,
+            &fee_token_allowlist,
+        )?;
+        let registration_collateral_fee = restrict_fee_tokens(
+            "registration_collateral_fee",
+            registration_collateral_fee,
+            &fee_token_allowlist,
+        )?;
+        let non_registration_collateral_fee = restrict_fee_tokens(
+            "non_registration_collateral_fee",
+            non_registration_collateral_fee,
+            &fee_token_allowlist,
+        )?;
         let use_fee = registration_fee.is_some() || non_registration_fee.is_some();
         let use_collateral_fee =
             registration_collateral_fee.is_some() || non_registration_collateral_fee.is_some();
@@ -172,6 +206,7 @@ impl BlockBuilder {
         log::info!("eth_allowance_for_block: {eth_allowance_for_block}");
         log::info!("use_fee: {use_fee}");
         log::info!("use_collateral_fee: {use_collateral_fee}");
+        log::info!("fee_token_allowlist: {fee_token_allowlist:?}");
         log::info!(
             "beneficiary_pubkey: {}",
             beneficiary_pubkey.map(|b| b.to_hex()).unwrap_or_default()
@@ -184,6 +219,7 @@ impl BlockBuilder {
             eth_allowance_for_block,
             initial_heart_beat_delay: env.initial_heart_beat_delay,
             heart_beat_interval: env.heart_beat_interval,
+            nonce_reclaim_max_age: env.nonce_reclaim_max_age,
             beneficiary_pubkey,
             use_fee,
             use_collateral: use_collateral_fee,
@@ -191,6 +227,7 @@ impl BlockBuilder {
             non_registration_fee,
             registration_collateral_fee,
             non_registration_collateral_fee,
+            fee_token_allowlist,
         };
         Ok(config)
     }
@@ -201,6 +238,22 @@ impl BlockBuilder {
         config: &Config,
         rollup: RollupContract,
     ) -> Result<Arc<Box<dyn Storage>>, BlockBuilderError> {
+        let backend = env
+            .storage_backend
+            .unwrap_or_else(|| StorageBackend::default_for(env.redis_url.as_deref()));
+        match backend {
+            StorageBackend::Redis if env.redis_url.is_none() => {
+                return Err(BlockBuilderError::InvalidStorageSetting(
+                    "REDIS_URL is required for the redis storage".to_string(),
+                ));
+            }
+            StorageBackend::Postgres if env.database_url.is_none() => {
+                return Err(BlockBuilderError::InvalidStorageSetting(
+                    "DATABASE_URL is required for the postgres storage".to_string(),
+                ));
+            }
+            _ => {}
+        }
         let storage_config = StorageConfig {
             use_fee: config.use_fee,
             use_collateral: config.use_collateral,
@@ -209,10 +262,25 @@ impl BlockBuilder {
             tx_timeout: env.tx_timeout,
             accepting_tx_interval: env.accepting_tx_interval,
             proposing_block_interval: env.proposing_block_interval,
+            min_signatures_to_post: env.min_signatures_to_post.unwrap_or(NUM_SENDERS_IN_BLOCK),
+            max_proposing_block_interval: env
+                .max_proposing_block_interval
+                .unwrap_or(env.proposing_block_interval),
             deposit_check_interval: env.deposit_check_interval,
             nonce_waiting_time: env.nonce_waiting_time.unwrap_or(5),
+            fee_priority_ordering: env.fee_priority_ordering.unwrap_or(false),
+            min_senders_for_immediate_post: env
+                .min_senders_for_immediate_post
+                .unwrap_or(NUM_SENDERS_IN_BLOCK),
+            fee_token_allowlist: config.fee_token_allowlist.clone(),
+            dry_run: env.fee_collection_dry_run.unwrap_or(false),
+            reconcile_unposted_fees: env.reconcile_unposted_fees.unwrap_or(false),
+            backend,
             redis_url: env.redis_url.clone(),
             cluster_id: env.cluster_id.clone(),
+            database_url: env.database_url.clone(),
+            database_max_connections: env.database_max_connections.unwrap_or(10),
+            database_timeout: env.database_timeout.unwrap_or(10),
             block_builder_id: Uuid::new_v4().to_string(),
         };
         let storage = storage::create_storage(&storage_config, rollup).await;
@@ -254,15 +322,26 @@ impl BlockBuilder {
         Ok(())
     }
 
-    /// Send a transaction request by the user
+    /// Send a transaction request by the user. The request is dropped instead of built if it is
+    /// still queued after `valid_until`.
     pub async fn send_tx_request(
         &self,
         is_registration_block: bool,
         pubkey: U256,
         tx: Tx,
         fee_proof: &Option<FeeProof>,
+        valid_until: Option<u64>,
     ) -> Result<String, BlockBuilderError> {
         log::info!("send_tx_request is_registration_block: {is_registration_block}");
+        if let Some(valid_until) = valid_until {
+            let current_time = chrono::Utc::now().timestamp() as u64;
+            if valid_until < current_time {
+                return Err(BlockBuilderError::TxRequestExpired {
+                    valid_until,
+                    current_time,
+                });
+            }
+        }
         // Verify account info
         let account_info = self.validity_prover_client.get_account_info(pubkey).await?;
         self.verify_account_info(is_registration_block, pubkey, &account_info)
@@ -281,6 +360,15 @@ impl BlockBuilder {
             tx,
             fee_proof: fee_proof.clone(),
             request_id: request_id.clone(),
+            fee: fee_proof
+                .as_ref()
+                .map(|p| p.fee_transfer_witness.transfer.amount)
+                .unwrap_or_default(),
+            fee_token_index: fee_proof
+                .as_ref()
+                .map(|p| p.fee_transfer_witness.transfer.token_index)
+                .unwrap_or_default(),
+            valid_until,
         };
         self.storage
             .add_tx(is_registration_block, tx_request)
@@ -333,6 +421,7 @@ impl BlockBuilder {
             self.config.block_builder_address,
             required_fee,
             required_collateral_fee,
+            self.config.fee_token_allowlist.as_ref(),
             pubkey,
             fee_proof,
         )
@@ -350,6 +439,12 @@ impl BlockBuilder {
         Ok(proposal)
     }
 
+    /// Whether the request was dropped because it was still queued after its `valid_until`
+    pub async fn is_tx_request_expired(&self, request_id: &str) -> Result<bool, BlockBuilderError> {
+        let is_expired = self.storage.is_expired(request_id).await?;
+        Ok(is_expired)
+    }
+
     /// Post the signature by the user
     pub async fn post_signature(
         &self,
@@ -362,6 +457,33 @@ impl BlockBuilder {
     }
 }
 
+/// Drop the fee entries in tokens outside of the allowlist, so that they are neither accepted
+/// nor advertised.
+fn restrict_fee_tokens(
+    name: &str,
+    fee: Option<HashMap<u32, U256>>,
+    fee_token_allowlist: &Option<HashSet<u32>>,
+) -> Result<Option<HashMap<u32, U256>>, BlockBuilderError> {
+    let (Some(fee), Some(allowlist)) = (fee.clone(), fee_token_allowlist) else {
+        return Ok(fee);
+    };
+    let (allowed, disallowed): (HashMap<_, _>, HashMap<_, _>) = fee
+        .into_iter()
+        .partition(|(token_index, _)| allowlist.contains(token_index));
+    if !disallowed.is_empty() {
+        log::warn!(
+            "{name}: ignoring fee token indices {:?} outside of the fee token allowlist",
+            disallowed.keys().collect::<Vec<_>>()
+        );
+    }
+    if allowed.is_empty() {
+        return Err(BlockBuilderError::InvalidFeeSetting(format!(
+            "{name} has no token in the fee token allowlist"
+        )));
+    }
+    Ok(Some(allowed))
+}
+
 #[cfg(test)]
 mod tests {
     use alloy::{
@@ -384,14 +506,39 @@ mod tests {
             .connect_mocked_client(provider_asserter)
     }
 
+    #[test]
+    fn test_restrict_fee_tokens() {
+        let fee = parse_fee_str("0:100,1:2000").unwrap();
+        let allowlist = Some(HashSet::from([0]));
+        let restricted = restrict_fee_tokens("fee", Some(fee.clone()), &allowlist)
+            .unwrap()
+            .unwrap();
+        assert_eq!(restricted, HashMap::from([(0, fee[&0])]));
+
+        // without an allowlist, the fee is kept as is
+        let kept = restrict_fee_tokens("fee", Some(fee.clone()), &None).unwrap();
+        assert_eq!(kept, Some(fee.clone()));
+
+        // a fee with no allowed token is a setting error
+        let result = restrict_fee_tokens("fee", Some(fee), &Some(HashSet::from([2])));
+        assert!(matches!(
+            result,
+            Err(BlockBuilderError::InvalidFeeSetting(_))
+        ));
+    }
+
     #[tokio::test]
     async fn test_get_fee_info() {
         // Initialize our own EnvVar
         let env = EnvVar {
             port: 9004,
             block_builder_url: "http://localhost:9004".to_string(),
+            storage_backend: None,
             redis_url: None,
             cluster_id: Some("1".to_string()),
+            database_url: None,
+            database_max_connections: None,
+            database_timeout: None,
             l2_rpc_url: "http://localhost:8545".to_string(),
             rollup_contract_address: AlloyAddress::default(),
             block_builder_registry_contract_address: AlloyAddress::default(),
@@ -411,11 +558,19 @@ mod tests {
             gas_limit_for_block_post: Some(40000),
             heart_beat_interval: 86400,
             nonce_waiting_time: None,
+            nonce_reclaim_max_age: None,
+            fee_priority_ordering: None,
+            min_senders_for_immediate_post: None,
+            min_signatures_to_post: None,
+            max_proposing_block_interval: None,
+            fee_collection_dry_run: None,
+            reconcile_unposted_fees: None,
             beneficiary_pubkey: None,
             registration_fee: Some("0:100,1:2000".to_string()),
             non_registration_fee: Some("0:100,1:2000".to_string()),
             registration_collateral_fee: None,
             non_registration_collateral_fee: None,
+            fee_token_allowlist: None,
         };
 
         let block_builder = BlockBuilder::new(&env, get_provider()).await.unwrap();
@@ -442,8 +597,12 @@ mod tests {
         let env = EnvVar {
             port: 9004,
             block_builder_url: "http://localhost:9004".to_string(),
+            storage_backend: None,
             redis_url: None,
             cluster_id: Some("1".to_string()),
+            database_url: None,
+            database_max_connections: None,
+            database_timeout: None,
             l2_rpc_url: "http://localhost:8545".to_string(),
             rollup_contract_address: AlloyAddress::default(),
             block_builder_registry_contract_address: AlloyAddress::default(),
@@ -463,11 +622,19 @@ mod tests {
             gas_limit_for_block_post: Some(40000),
             heart_beat_interval: 86400,
             nonce_waiting_time: None,
+            nonce_reclaim_max_age: None,
+            fee_priority_ordering: None,
+            min_senders_for_immediate_post: None,
+            min_signatures_to_post: None,
+            max_proposing_block_interval: None,
+            fee_collection_dry_run: None,
+            reconcile_unposted_fees: None,
             beneficiary_pubkey: None,
             registration_fee: Some("0:100,1:2000".to_string()),
             non_registration_fee: Some("0:100,1:2000".to_string()),
             registration_collateral_fee: None,
             non_registration_collateral_fee: None,
+            fee_token_allowlist: None,
         };
 
         let block_builder = BlockBuilder::new(&env, get_provider()).await.unwrap();
@@ -485,8 +652,12 @@ mod tests {
         let env = EnvVar {
             port: 9004,
             block_builder_url: "http://localhost:9004".to_string(),
+            storage_backend: None,
             redis_url: None,
             cluster_id: Some("1".to_string()),
+            database_url: None,
+            database_max_connections: None,
+            database_timeout: None,
             l2_rpc_url: "http://localhost:8545".to_string(),
             rollup_contract_address: AlloyAddress::default(),
             block_builder_registry_contract_address: AlloyAddress::default(),
@@ -506,11 +677,19 @@ mod tests {
             gas_limit_for_block_post: Some(40000),
             heart_beat_interval: 86400,
             nonce_waiting_time: None,
+            nonce_reclaim_max_age: None,
+            fee_priority_ordering: None,
+            min_senders_for_immediate_post: None,
+            min_signatures_to_post: None,
+            max_proposing_block_interval: None,
+            fee_collection_dry_run: None,
+            reconcile_unposted_fees: None,
             beneficiary_pubkey: None,
             registration_fee: Some("0:100,1:2000".to_string()),
             non_registration_fee: Some("0:100,1:2000".to_string()),
             registration_collateral_fee: None,
             non_registration_collateral_fee: None,
+            fee_token_allowlist: None,
         };
 
         let block_builder = BlockBuilder::new(&env, get_provider()).await.unwrap();
@@ -531,8 +710,12 @@ mod tests {
         let env = EnvVar {
             port: 9004,
             block_builder_url: "http://localhost:9004".to_string(),
+            storage_backend: None,
             redis_url: Some(format!("redis://localhost:{port}").to_string()),
             cluster_id: Some("1".to_string()),
+            database_url: None,
+            database_max_connections: None,
+            database_timeout: None,
             l2_rpc_url: "http://localhost:8545".to_string(),
             rollup_contract_address: AlloyAddress::default(),
             block_builder_registry_contract_address: AlloyAddress::default(),
@@ -552,11 +735,19 @@ mod tests {
             gas_limit_for_block_post: Some(40000),
             heart_beat_interval: 86400,
             nonce_waiting_time: None,
+            nonce_reclaim_max_age: None,
+            fee_priority_ordering: None,
+            min_senders_for_immediate_post: None,
+            min_signatures_to_post: None,
+            max_proposing_block_interval: None,
+            fee_collection_dry_run: None,
+            reconcile_unposted_fees: None,
             beneficiary_pubkey: None,
             registration_fee: Some("0:100,1:2000".to_string()),
             non_registration_fee: Some("0:100,1:2000".to_string()),
             registration_collateral_fee: None,
             non_registration_collateral_fee: None,
+            fee_token_allowlist: None,
         };
 
         // Run docker image
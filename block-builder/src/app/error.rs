@@ -26,6 +26,9 @@ pub enum BlockBuilderError {
     #[error("Invalid fee setting: {0}")]
     InvalidFeeSetting(String),
 
+    #[error("Invalid storage setting: {0}")]
+    InvalidStorageSetting(String),
+
     #[error("Validity prover is not synced onchain:{0} validity prover:{1}")]
     ValidityProverIsNotSynced(u32, u32),
 
@@ -38,6 +41,9 @@ pub enum BlockBuilderError {
     #[error("Block already expired")]
     AlreadyExpired,
 
+    #[error("Tx request valid until {valid_until} is not after the current time {current_time}")]
+    TxRequestExpired { valid_until: u64, current_time: u64 },
+
     #[error("Block chain health error: {0}")]
     BlockChainHealthError(String),
 }
@@ -68,6 +74,9 @@ pub enum FeeError {
     #[error("Invalid fee: {0}")]
     InvalidFee(String),
 
+    #[error("Fee token index {0} is not accepted by this block builder")]
+    DisallowedFeeToken(u32),
+
     #[error("Parse error: {0}")]
     ParseError(String),
 
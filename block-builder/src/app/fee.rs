@@ -1,7 +1,9 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use super::{block_post::BlockPostTask, error::FeeError, types::ProposalMemo};
-use intmax2_client_sdk::client::strategy::common::fetch_sender_proof_set;
+use intmax2_client_sdk::client::{
+    fee_payment::validate_fee_contributions, strategy::common::fetch_sender_proof_set,
+};
 use intmax2_interfaces::{
     api::{
         block_builder::interface::{Fee, FeeProof},
@@ -34,12 +36,14 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 /// Validate fee proof
+#[allow(clippy::too_many_arguments)]
 pub async fn validate_fee_proof(
     store_vault_server_client: &dyn StoreVaultClientInterface,
     beneficiary_pubkey: Option<U256>,
     block_builder_address: Address,
     required_fee: Option<&HashMap<u32, U256>>,
     required_collateral_fee: Option<&HashMap<u32, U256>>,
+    fee_token_allowlist: Option<&HashSet<u32>>,
     sender: U256,
     fee_proof: &Option<FeeProof>,
 ) -> Result<(), FeeError> {
@@ -58,6 +62,9 @@ pub async fn validate_fee_proof(
     let beneficiary_pubkey = beneficiary_pubkey.ok_or(FeeError::InvalidFee(
         "Beneficiary pubkey is missing".to_string(),
     ))?;
+    if let Some(fee_token_allowlist) = fee_token_allowlist {
+        validate_fee_tokens(fee_proof, fee_token_allowlist)?;
+    }
 
     let sender_proof_set = fetch_sender_proof_set(
         store_vault_server_client,
@@ -66,13 +73,23 @@ pub async fn validate_fee_proof(
     .await?;
 
     // validate main fee
-    validate_fee_single(
-        beneficiary_pubkey,
-        required_fee,
-        &sender_proof_set,
-        &fee_proof.fee_transfer_witness,
-    )
-    .await?;
+    if fee_proof.additional_fee_transfer_witnesses.is_empty() {
+        validate_fee_single(
+            beneficiary_pubkey,
+            required_fee,
+            &sender_proof_set,
+            &fee_proof.fee_transfer_witness,
+        )
+        .await?;
+    } else {
+        validate_fee_split(
+            beneficiary_pubkey,
+            required_fee,
+            &sender_proof_set,
+            fee_proof,
+        )
+        .await?;
+    }
 
     // validate collateral fee
     if let Some(collateral_fee) = required_collateral_fee {
@@ -143,12 +160,71 @@ pub async fn validate_fee_proof(
     Ok(())
 }
 
+/// Validate a fee that is split across multiple tokens.
+/// The sum of the fee transfers converted into the first transfer's token must cover the fee.
+async fn validate_fee_split(
+    beneficiary_pubkey: U256,
+    required_fee: &HashMap<u32, U256>, // token index -> fee amount
+    sender_proof_set: &SenderProofSet,
+    fee_proof: &FeeProof,
+) -> Result<(), FeeError> {
+    let witnesses = std::iter::once(&fee_proof.fee_transfer_witness)
+        .chain(fee_proof.additional_fee_transfer_witnesses.iter())
+        .collect::<Vec<_>>();
+    let mut transfer_indices = HashSet::new();
+    for witness in witnesses.iter() {
+        if !transfer_indices.insert(witness.transfer_index) {
+            return Err(FeeError::InvalidFee(
+                "Duplicated fee transfer index".to_string(),
+            ));
+        }
+        validate_fee_transfer(beneficiary_pubkey, sender_proof_set, witness).await?;
+    }
+    let contributions = witnesses
+        .iter()
+        .map(|witness| Fee {
+            token_index: witness.transfer.token_index,
+            amount: witness.transfer.amount,
+        })
+        .collect::<Vec<_>>();
+    let fee_list = convert_fee_vec(&Some(required_fee.clone())).unwrap();
+    validate_fee_contributions(&contributions, &fee_list)
+        .map_err(|e| FeeError::InvalidFee(e.to_string()))?;
+    Ok(())
+}
+
 /// common function to validate fee and collateral fee
 async fn validate_fee_single(
     beneficiary_pubkey: U256,
     required_fee: &HashMap<u32, U256>, // token index -> fee amount
     sender_proof_set: &SenderProofSet,
     transfer_witness: &TransferWitness,
+) -> Result<(), FeeError> {
+    validate_fee_transfer(beneficiary_pubkey, sender_proof_set, transfer_witness).await?;
+
+    // make sure that the fee is correct
+    if !required_fee.contains_key(&transfer_witness.transfer.token_index) {
+        return Err(FeeError::InvalidFee(
+            "Fee token index is not correct".to_string(),
+        ));
+    }
+    let requested_fee = required_fee
+        .get(&transfer_witness.transfer.token_index)
+        .unwrap();
+    if transfer_witness.transfer.amount < *requested_fee {
+        return Err(FeeError::InvalidFee(format!(
+            "Transfer amount is not enough: requested_fee: {}, transfer_amount: {}",
+            requested_fee, transfer_witness.transfer.amount
+        )));
+    }
+    Ok(())
+}
+
+/// Validate that the fee transfer is included in the spent tx and is for the beneficiary
+async fn validate_fee_transfer(
+    beneficiary_pubkey: U256,
+    sender_proof_set: &SenderProofSet,
+    transfer_witness: &TransferWitness,
 ) -> Result<(), FeeError> {
     sender_proof_set
         .validate(U256::dummy_pubkey())
@@ -200,25 +276,45 @@ async fn validate_fee_single(
             "Recipient is not the beneficiary".to_string(),
         ));
     }
+    Ok(())
+}
 
-    // make sure that the fee is correct
-    if !required_fee.contains_key(&transfer_witness.transfer.token_index) {
-        return Err(FeeError::InvalidFee(
-            "Fee token index is not correct".to_string(),
-        ));
-    }
-    let requested_fee = required_fee
-        .get(&transfer_witness.transfer.token_index)
-        .unwrap();
-    if transfer_witness.transfer.amount < *requested_fee {
-        return Err(FeeError::InvalidFee(format!(
-            "Transfer amount is not enough: requested_fee: {}, transfer_amount: {}",
-            requested_fee, transfer_witness.transfer.amount
-        )));
+/// Validate that every fee transfer of the fee proof, including the collateral one, is in a
+/// token of the allowlist.
+pub fn validate_fee_tokens(
+    fee_proof: &FeeProof,
+    fee_token_allowlist: &HashSet<u32>,
+) -> Result<(), FeeError> {
+    let token_indices = std::iter::once(&fee_proof.fee_transfer_witness)
+        .chain(fee_proof.additional_fee_transfer_witnesses.iter())
+        .map(|witness| witness.transfer.token_index)
+        .chain(
+            fee_proof
+                .collateral_block
+                .iter()
+                .map(|block| block.fee_transfer_data.transfer.token_index),
+        );
+    for token_index in token_indices {
+        if !fee_token_allowlist.contains(&token_index) {
+            return Err(FeeError::DisallowedFeeToken(token_index));
+        }
     }
     Ok(())
 }
 
+/// Parse a fee token allowlist string into a set of token indices
+// Example: "0,1" -> {0, 1}
+pub fn parse_fee_token_allowlist(allowlist: &str) -> Result<HashSet<u32>, FeeError> {
+    allowlist
+        .split(',')
+        .map(|token_index| {
+            token_index.trim().parse::<u32>().map_err(|e| {
+                FeeError::ParseError(format!("Failed to parse allowed fee token index: {e}"))
+            })
+        })
+        .collect()
+}
+
 /// Parse fee string into a map of token index -> fee amount
 // Example: "0:100,1:200" -> {0: 100, 1: 200}
 pub fn parse_fee_str(fee: &str) -> Result<HashMap<u32, U256>, FeeError> {
@@ -250,6 +346,55 @@ pub struct FeeCollection {
     pub signatures: Vec<UserSignature>,
 }
 
+/// A fee transfer that `collect_fee` would collect
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IntendedFeeTransfer {
+    pub sender: U256,
+    pub token_index: u32,
+    pub amount: U256,
+    /// true if the fee is collected by posting the sender's collateral block
+    pub is_collateral: bool,
+}
+
+/// Compute the fee transfers that `collect_fee` would collect, without saving any transfer data
+/// or creating block post tasks.
+pub fn intended_fee_transfers(fee_collection: &FeeCollection) -> Vec<IntendedFeeTransfer> {
+    let mut transfers = Vec::new();
+    let memo = &fee_collection.memo;
+    for request in memo.tx_requests.iter() {
+        let Some(fee_proof) = request.fee_proof.as_ref() else {
+            continue;
+        };
+        let signed = fee_collection
+            .signatures
+            .iter()
+            .any(|s| s.pubkey == request.pubkey);
+        if signed {
+            let fee_transfer_witnesses = std::iter::once(&fee_proof.fee_transfer_witness)
+                .chain(fee_proof.additional_fee_transfer_witnesses.iter());
+            for fee_transfer_witness in fee_transfer_witnesses {
+                transfers.push(IntendedFeeTransfer {
+                    sender: request.pubkey,
+                    token_index: fee_transfer_witness.transfer.token_index,
+                    amount: fee_transfer_witness.transfer.amount,
+                    is_collateral: false,
+                });
+            }
+        } else if fee_collection.use_collateral {
+            if let Some(collateral_block) = fee_proof.collateral_block.as_ref() {
+                let transfer = &collateral_block.fee_transfer_data.transfer;
+                transfers.push(IntendedFeeTransfer {
+                    sender: request.pubkey,
+                    token_index: transfer.token_index,
+                    amount: transfer.amount,
+                    is_collateral: true,
+                });
+            }
+        }
+    }
+    transfers
+}
+
 /// Collect fee from the senders
 pub async fn collect_fee(
     store_vault_server_client: &dyn StoreVaultClientInterface,
@@ -278,19 +423,23 @@ pub async fn collect_fee(
             .find(|s| s.pubkey == request.pubkey);
         if signature.is_some() {
             // fee will be paid
-            let transfer_data = TransferData {
-                sender_proof_set_ephemeral_key: fee_proof.sender_proof_set_ephemeral_key,
-                sender_proof_set: None,
-                sender: request.pubkey,
-                tx: request.tx,
-                tx_index: proposal.tx_index,
-                tx_merkle_proof: proposal.tx_merkle_proof.clone(),
-                tx_tree_root: proposal.block_sign_payload.tx_tree_root,
-                transfer: fee_proof.fee_transfer_witness.transfer,
-                transfer_index: fee_proof.fee_transfer_witness.transfer_index,
-                transfer_merkle_proof: fee_proof.fee_transfer_witness.transfer_merkle_proof.clone(),
-            };
-            transfer_data_vec.push(transfer_data);
+            let fee_transfer_witnesses = std::iter::once(&fee_proof.fee_transfer_witness)
+                .chain(fee_proof.additional_fee_transfer_witnesses.iter());
+            for fee_transfer_witness in fee_transfer_witnesses {
+                let transfer_data = TransferData {
+                    sender_proof_set_ephemeral_key: fee_proof.sender_proof_set_ephemeral_key,
+                    sender_proof_set: None,
+                    sender: request.pubkey,
+                    tx: request.tx,
+                    tx_index: proposal.tx_index,
+                    tx_merkle_proof: proposal.tx_merkle_proof.clone(),
+                    tx_tree_root: proposal.block_sign_payload.tx_tree_root,
+                    transfer: fee_transfer_witness.transfer,
+                    transfer_index: fee_transfer_witness.transfer_index,
+                    transfer_merkle_proof: fee_transfer_witness.transfer_merkle_proof.clone(),
+                };
+                transfer_data_vec.push(transfer_data);
+            }
             log::info!("sender {}'s fee is collected", request.pubkey);
         } else {
             if !fee_collection.use_collateral {
@@ -375,6 +524,76 @@ pub async fn collect_fee(
     Ok(block_post_tasks)
 }
 
+/// Group the fee transfers collected from `fee_collection` by the id of the block that settles
+/// them: the proposal's block for the senders who signed it, and the collateral block of each
+/// sender who did not. `block_post_tasks` are the collateral blocks returned by `collect_fee`.
+pub fn collected_fee_transfers(
+    fee_collection: &FeeCollection,
+    block_post_tasks: &[BlockPostTask],
+) -> Vec<(String, Vec<IntendedFeeTransfer>)> {
+    let mut tx_fees = Vec::new();
+    let mut collateral_fees = Vec::new();
+    for transfer in intended_fee_transfers(fee_collection) {
+        if !transfer.is_collateral {
+            tx_fees.push(transfer);
+            continue;
+        }
+        // a collateral block has the sender as its only pubkey
+        let Some(task) = block_post_tasks
+            .iter()
+            .find(|task| task.pubkeys.first() == Some(&transfer.sender))
+        else {
+            continue;
+        };
+        collateral_fees.push((task.block_id.clone(), vec![transfer]));
+    }
+    let mut collected = Vec::new();
+    if !tx_fees.is_empty() {
+        collected.push((fee_collection.memo.block_id.clone(), tx_fees));
+    }
+    collected.extend(collateral_fees);
+    collected
+}
+
+/// A block whose fee transfers were saved for the beneficiary but which failed to be posted.
+///
+/// The tx of the block never reaches the chain, so these transfers never settle, and the
+/// beneficiary's store vault holds transfers it cannot receive. The block builder has no key
+/// to move funds with, so these are flagged for manual handling instead of being refunded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UnpostedFeeBlock {
+    pub block_id: String,
+    pub transfers: Vec<IntendedFeeTransfer>,
+    /// error of the failed block post
+    pub reason: String,
+}
+
+impl UnpostedFeeBlock {
+    /// Log the block for manual handling. This is called once per block, by whichever of the
+    /// fee collection and the block post happens last.
+    pub fn report(&self) {
+        log::error!(
+            "fee collected for unposted block {}: {}",
+            self.block_id,
+            self.reason
+        );
+        for transfer in &self.transfers {
+            log::error!(
+                "unposted block {}: {} fee of {} not settled: token {}, amount {}",
+                self.block_id,
+                if transfer.is_collateral {
+                    "collateral"
+                } else {
+                    "tx"
+                },
+                transfer.sender,
+                transfer.token_index,
+                transfer.amount
+            );
+        }
+    }
+}
+
 pub fn convert_fee_vec(fee: &Option<HashMap<u32, U256>>) -> Option<Vec<Fee>> {
     fee.as_ref().map(|fee| {
         fee.iter()
@@ -539,4 +758,16 @@ mod tests {
 
         assert_eq!(result, expected);
     }
+
+    #[test]
+    fn test_parse_fee_token_allowlist() {
+        let result = parse_fee_token_allowlist("0, 1,5").unwrap();
+        assert_eq!(result, HashSet::from([0, 1, 5]));
+
+        let result = parse_fee_token_allowlist("0,usdc");
+        assert!(matches!(
+            result,
+            Err(FeeError::ParseError(msg)) if msg.contains("allowed fee token index")
+        ));
+    }
 }
@@ -5,6 +5,7 @@ use tokio::{task::JoinHandle, time::sleep};
 
 pub const GENERAL_POLLING_INTERVAL: u64 = 2;
 pub const RESTART_JOB_INTERVAL: u64 = 60;
+pub const NONCE_RECLAIM_INTERVAL: u64 = 60;
 
 impl BlockBuilder {
     async fn emit_heart_beat(&self) -> Result<(), BlockBuilderError> {
@@ -105,6 +106,18 @@ impl BlockBuilder {
         })
     }
 
+    fn reclaim_stale_nonces_job(self, max_age: u64) -> JoinHandle<Result<(), BlockBuilderError>> {
+        actix_web::rt::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(NONCE_RECLAIM_INTERVAL));
+            loop {
+                interval.tick().await;
+                self.storage
+                    .reclaim_stale_nonces(Duration::from_secs(max_age))
+                    .await?;
+            }
+        })
+    }
+
     async fn post_block(&self) -> Result<(), BlockBuilderError> {
         let block_post_task = self.storage.dequeue_block_post_task().await?;
         if block_post_task.is_none() {
@@ -112,6 +125,9 @@ impl BlockBuilder {
         }
         let block_post_task = block_post_task.unwrap();
         log::info!("Posting block: {}", block_post_task.block_id);
+        let block_id = block_post_task.block_id.clone();
+        // only blocks with signatures carry a fee
+        let has_fee = !block_post_task.signatures.is_empty();
         match post_block(
             self.config.block_builder_private_key,
             self.config.gas_limit_for_block_post,
@@ -125,6 +141,15 @@ impl BlockBuilder {
             Ok(_) => {}
             Err(e) => {
                 log::error!("Error in posting block: {e}");
+                if has_fee {
+                    let unposted = self
+                        .storage
+                        .record_failed_block_post(&block_id, &e.to_string())
+                        .await?;
+                    if let Some(unposted) = unposted {
+                        unposted.report();
+                    }
+                }
             }
         }
         Ok(())
@@ -193,5 +218,11 @@ impl BlockBuilder {
             |this| this.process_fee_collection_job(),
             "process_fee_collection_job".to_string(),
         );
+        if let Some(max_age) = self.config.nonce_reclaim_max_age {
+            self.clone().run_with_restart(
+                move |this| this.reclaim_stale_nonces_job(max_age),
+                "reclaim_stale_nonces_job".to_string(),
+            );
+        }
     }
 }
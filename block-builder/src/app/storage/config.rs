@@ -1,4 +1,38 @@
-use intmax2_zkp::ethereum_types::{address::Address, u256::U256};
+use std::collections::HashSet;
+
+use intmax2_zkp::{
+    constants::NUM_SENDERS_IN_BLOCK,
+    ethereum_types::{address::Address, u256::U256},
+};
+use serde::Deserialize;
+
+use crate::app::{
+    fee::{intended_fee_transfers, validate_fee_tokens, FeeCollection},
+    types::ProposalMemo,
+};
+
+use super::error::StorageError;
+
+/// Where the block builder keeps its queues, proposals and signatures
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// Lost on restart, and not shared between instances
+    Memory,
+    Redis,
+    Postgres,
+}
+
+impl StorageBackend {
+    /// The backend used when none is configured: redis if a redis url is set, otherwise memory.
+    pub fn default_for(redis_url: Option<&str>) -> Self {
+        if redis_url.is_some() {
+            StorageBackend::Redis
+        } else {
+            StorageBackend::Memory
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct StorageConfig {
@@ -9,11 +43,206 @@ pub struct StorageConfig {
     pub tx_timeout: u64,
     pub accepting_tx_interval: u64,
     pub proposing_block_interval: u64,
+    // once this many signatures of a proposal arrived, it is posted without extending the
+    // signature window
+    pub min_signatures_to_post: usize,
+    // if greater than proposing_block_interval, the signature window of a proposal with fewer
+    // than min_signatures_to_post signatures is extended while signatures keep arriving, up to
+    // this many seconds after the proposal
+    pub max_proposing_block_interval: u64,
     pub deposit_check_interval: Option<u64>,
     pub block_builder_id: String,
     pub nonce_waiting_time: u64,
+    // if true, the highest-fee requests are taken first when the queue exceeds a block
+    pub fee_priority_ordering: bool,
+    // once this many requests are queued, they are processed without waiting for
+    // accepting_tx_interval
+    pub min_senders_for_immediate_post: usize,
+    // if set, only fees in these token indices are accepted
+    pub fee_token_allowlist: Option<HashSet<u32>>,
+    // if true, the fee collection only logs the fees it would collect, without saving the fee
+    // transfers or posting collateral blocks
+    pub dry_run: bool,
+    // if true, blocks whose fee was collected but which failed to be posted are flagged for
+    // manual handling
+    pub reconcile_unposted_fees: bool,
+
+    pub backend: StorageBackend,
 
     // Redis configuration
     pub redis_url: Option<String>,
     pub cluster_id: Option<String>,
+
+    // Postgres configuration
+    pub database_url: Option<String>,
+    pub database_max_connections: u32,
+    pub database_timeout: u64,
+}
+
+impl StorageConfig {
+    /// Returns true if the queued tx requests should be processed into a proposal now.
+    /// This is shared by all storage backends so that they decide identically.
+    pub fn should_process_requests(
+        &self,
+        queue_len: usize,
+        last_processed: u64,
+        current_time: u64,
+    ) -> bool {
+        if queue_len == 0 {
+            return false;
+        }
+        let min_senders = self
+            .min_senders_for_immediate_post
+            .clamp(1, NUM_SENDERS_IN_BLOCK);
+        queue_len >= min_senders || current_time >= last_processed + self.accepting_tx_interval
+    }
+
+    /// Returns true if the signatures collected for a proposal created at `created_at` should be
+    /// posted now. After `proposing_block_interval`, the window is extended as long as the last
+    /// signature arrived within the last `proposing_block_interval`, until all `num_senders` or
+    /// `min_signatures_to_post` signatures arrived, or `max_proposing_block_interval` has passed.
+    /// This is shared by all storage backends so that they decide identically.
+    pub fn should_post_signatures(
+        &self,
+        created_at: u64,
+        num_senders: usize,
+        num_signatures: usize,
+        last_signature_at: Option<u64>,
+        current_time: u64,
+    ) -> bool {
+        if num_signatures == 0 || current_time <= created_at + self.proposing_block_interval {
+            return false;
+        }
+        if num_signatures >= num_senders || num_signatures >= self.min_signatures_to_post.max(1) {
+            return true;
+        }
+        if current_time > created_at + self.max_proposing_block_interval {
+            return true;
+        }
+        // still arriving if the last signature came within the last interval
+        last_signature_at.is_none_or(|last| current_time > last + self.proposing_block_interval)
+    }
+
+    /// Log the fee transfers of `fee_collection` instead of collecting them. Used in dry run mode.
+    /// This is shared by all storage backends so that they log identically.
+    pub fn log_intended_fee_collection(&self, fee_collection: &FeeCollection) {
+        let transfers = intended_fee_transfers(fee_collection);
+        if transfers.is_empty() {
+            log::info!("dry run: no fee to collect");
+        }
+        for transfer in transfers {
+            log::info!(
+                "dry run: would collect {} fee from {}: beneficiary {}, token {}, amount {}",
+                if transfer.is_collateral {
+                    "collateral"
+                } else {
+                    "tx"
+                },
+                transfer.sender,
+                self.fee_beneficiary,
+                transfer.token_index,
+                transfer.amount
+            );
+        }
+    }
+
+    /// Reject the signature of `pubkey` if its fee in `memo` is not in an allowed token.
+    /// This is shared by all storage backends so that they decide identically.
+    pub fn validate_signer_fee_tokens(
+        &self,
+        memo: &ProposalMemo,
+        pubkey: U256,
+    ) -> Result<(), StorageError> {
+        let Some(fee_token_allowlist) = &self.fee_token_allowlist else {
+            return Ok(());
+        };
+        let fee_proof = memo
+            .tx_requests
+            .iter()
+            .find(|request| request.pubkey == pubkey)
+            .and_then(|request| request.fee_proof.as_ref());
+        if let Some(fee_proof) = fee_proof {
+            validate_fee_tokens(fee_proof, fee_token_allowlist)
+                .map_err(|e| StorageError::AddSignatureError(e.to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(min_senders_for_immediate_post: usize) -> StorageConfig {
+        StorageConfig {
+            use_fee: false,
+            use_collateral: false,
+            block_builder_address: Address::default(),
+            fee_beneficiary: U256::default(),
+            tx_timeout: 60,
+            accepting_tx_interval: 10,
+            proposing_block_interval: 10,
+            min_signatures_to_post: NUM_SENDERS_IN_BLOCK,
+            max_proposing_block_interval: 10,
+            deposit_check_interval: None,
+            block_builder_id: "builder1".to_string(),
+            nonce_waiting_time: 5,
+            fee_priority_ordering: false,
+            min_senders_for_immediate_post,
+            fee_token_allowlist: None,
+            dry_run: false,
+            reconcile_unposted_fees: false,
+            backend: StorageBackend::Memory,
+            redis_url: None,
+            cluster_id: None,
+            database_url: None,
+            database_max_connections: 10,
+            database_timeout: 10,
+        }
+    }
+
+    #[test]
+    fn test_should_process_requests_default() {
+        let config = config(NUM_SENDERS_IN_BLOCK);
+        assert!(!config.should_process_requests(0, 0, 100));
+        assert!(!config.should_process_requests(1, 100, 105));
+        assert!(config.should_process_requests(1, 100, 110));
+        assert!(config.should_process_requests(NUM_SENDERS_IN_BLOCK, 100, 105));
+    }
+
+    #[test]
+    fn test_should_process_requests_immediate_post() {
+        let config = config(2);
+        assert!(!config.should_process_requests(0, 100, 105));
+        assert!(!config.should_process_requests(1, 100, 105));
+        assert!(config.should_process_requests(2, 100, 105));
+        // zero is treated as one
+        assert!(self::config(0).should_process_requests(1, 100, 105));
+    }
+
+    #[test]
+    fn test_should_post_signatures_fixed_window() {
+        // the window is not extended by default
+        let config = config(NUM_SENDERS_IN_BLOCK);
+        assert!(!config.should_post_signatures(100, 4, 2, Some(109), 110));
+        assert!(config.should_post_signatures(100, 4, 2, Some(110), 111));
+        assert!(!config.should_post_signatures(100, 4, 0, None, 200));
+    }
+
+    #[test]
+    fn test_should_post_signatures_adaptive_window() {
+        let mut config = config(NUM_SENDERS_IN_BLOCK);
+        config.min_signatures_to_post = 3;
+        config.max_proposing_block_interval = 30;
+        // extended while signatures keep arriving
+        assert!(!config.should_post_signatures(100, 4, 2, Some(108), 111));
+        assert!(!config.should_post_signatures(100, 4, 2, Some(118), 125));
+        // posted once no signature arrived within the interval
+        assert!(config.should_post_signatures(100, 4, 2, Some(108), 119));
+        // posted at the max interval
+        assert!(config.should_post_signatures(100, 4, 2, Some(130), 131));
+        // posted without extension once enough or all senders signed
+        assert!(config.should_post_signatures(100, 4, 3, Some(110), 111));
+        assert!(config.should_post_signatures(100, 2, 2, Some(110), 111));
+    }
 }
@@ -0,0 +1,318 @@
+//! Behavior shared by every `Storage` backend. Each backend runs `run_storage_conformance`
+//! against an instance of its own in its tests, so that the backends cannot drift apart.
+
+use std::time::Duration;
+
+use alloy::{
+    providers::{mock::Asserter, ProviderBuilder},
+    sol_types::SolCall as _,
+};
+use intmax2_client_sdk::external_api::{
+    contract::rollup_contract::{Rollup, RollupContract},
+    in_memory_store_vault::InMemoryStoreVaultClient,
+};
+use intmax2_interfaces::utils::random::default_rng;
+use intmax2_zkp::{
+    common::signature_content::key_set::KeySet,
+    constants::NUM_SENDERS_IN_BLOCK,
+    ethereum_types::{address::Address, u256::U256},
+};
+
+use crate::app::{
+    fee::{IntendedFeeTransfer, UnpostedFeeBlock},
+    types::TxRequest,
+};
+
+use super::{
+    config::{StorageBackend, StorageConfig},
+    Storage,
+};
+
+/// Config of the storage under test. Memos are ready to be posted one second after they are
+/// created, so that the suite does not wait for a whole proposing interval. A memo missing
+/// signatures waits for them until a second passes without one, for up to three seconds.
+pub fn test_config(backend: StorageBackend) -> StorageConfig {
+    StorageConfig {
+        use_fee: true,
+        use_collateral: false,
+        block_builder_address: Address::default(),
+        fee_beneficiary: U256::default(),
+        tx_timeout: 80,
+        accepting_tx_interval: 40,
+        proposing_block_interval: 0,
+        min_signatures_to_post: NUM_SENDERS_IN_BLOCK,
+        max_proposing_block_interval: 3,
+        deposit_check_interval: Some(20),
+        block_builder_id: "builder1".to_string(),
+        nonce_waiting_time: 5,
+        fee_priority_ordering: false,
+        min_senders_for_immediate_post: 2,
+        fee_token_allowlist: None,
+        dry_run: false,
+        reconcile_unposted_fees: true,
+        backend,
+        redis_url: None,
+        cluster_id: Some("conformance".to_string()),
+        database_url: None,
+        database_max_connections: 10,
+        database_timeout: 10,
+    }
+}
+
+/// Rollup contract answering the on-chain nonce queries of two nonce reservations
+pub fn mock_rollup() -> RollupContract {
+    let asserter = Asserter::new();
+    for _ in 0..2 {
+        asserter.push_success(&Rollup::builderRegistrationNonceCall::abi_encode_returns(
+            &1,
+        ));
+        asserter.push_success(&Rollup::builderNonRegistrationNonceCall::abi_encode_returns(&1));
+    }
+    let provider = ProviderBuilder::default()
+        .with_gas_estimation()
+        .with_simple_nonce_management()
+        .fetch_chain_id()
+        .connect_mocked_client(asserter);
+    RollupContract::new(provider, Default::default())
+}
+
+/// Run every check on a fresh `storage` built with `test_config`, in order. The storage must
+/// be able to reserve two nonces, e.g. with `mock_rollup`.
+pub async fn run_storage_conformance<S: Storage>(storage: S) {
+    let storage: &dyn Storage = &storage;
+    check_empty_queue(storage).await;
+    check_process_requests(storage).await;
+    check_expired_request(storage).await;
+    check_add_signature(storage).await;
+    check_process_signatures(storage).await;
+    check_fee_collection(storage).await;
+    check_empty_block(storage).await;
+    check_fee_reconciliation(storage).await;
+    check_signature_window(storage).await;
+}
+
+async fn check_empty_queue(storage: &dyn Storage) {
+    storage.process_requests(true).await.unwrap();
+    storage.process_requests(false).await.unwrap();
+    assert!(storage.query_proposal("unknown").await.unwrap().is_none());
+    assert!(!storage.is_expired("unknown").await.unwrap());
+    assert!(storage.dequeue_block_post_task().await.unwrap().is_none());
+}
+
+async fn check_process_requests(storage: &dyn Storage) {
+    let tx_request = TxRequest {
+        request_id: "conformance-1".to_string(),
+        ..Default::default()
+    };
+    storage.add_tx(true, tx_request.clone()).await.unwrap();
+    storage.process_requests(true).await.unwrap();
+
+    let proposal = storage
+        .query_proposal("conformance-1")
+        .await
+        .unwrap()
+        .expect("proposal not found");
+    assert!(proposal.block_sign_payload.is_registration_block);
+    assert_eq!(proposal.pubkeys.len(), NUM_SENDERS_IN_BLOCK);
+    proposal.verify(tx_request.tx).unwrap();
+    assert!(!storage.is_expired("conformance-1").await.unwrap());
+}
+
+async fn check_expired_request(storage: &dyn Storage) {
+    let current_time = chrono::Utc::now().timestamp() as u64;
+    let tx_request = TxRequest {
+        request_id: "conformance-expired".to_string(),
+        valid_until: Some(current_time - 10),
+        ..Default::default()
+    };
+    storage.add_tx(false, tx_request).await.unwrap();
+    storage.process_requests(false).await.unwrap();
+
+    assert!(storage.is_expired("conformance-expired").await.unwrap());
+    assert!(storage
+        .query_proposal("conformance-expired")
+        .await
+        .unwrap()
+        .is_none());
+}
+
+async fn check_add_signature(storage: &dyn Storage) {
+    let proposal = storage
+        .query_proposal("conformance-1")
+        .await
+        .unwrap()
+        .expect("proposal not found");
+    let key = KeySet::rand(&mut default_rng());
+    let signature = proposal.sign(key);
+    assert!(storage
+        .add_signature("unknown", signature.clone())
+        .await
+        .is_err());
+    storage
+        .add_signature("conformance-1", signature)
+        .await
+        .unwrap();
+}
+
+async fn check_process_signatures(storage: &dyn Storage) {
+    let proposal = storage
+        .query_proposal("conformance-1")
+        .await
+        .unwrap()
+        .expect("proposal not found");
+    // wait for the proposing interval to pass
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    storage.process_signatures().await.unwrap();
+
+    // the memo is gone once its block is queued
+    assert!(storage
+        .query_proposal("conformance-1")
+        .await
+        .unwrap()
+        .is_none());
+
+    // the nonce of the proposal is the smallest reserved one, so the task is dequeued at once
+    // and its nonce released
+    let block_post_task = storage
+        .dequeue_block_post_task()
+        .await
+        .unwrap()
+        .expect("block post task not enqueued");
+    assert!(!block_post_task.force_post);
+    assert_eq!(block_post_task.block_id, proposal.block_id);
+    assert_eq!(block_post_task.signatures.len(), 1);
+    assert!(storage.dequeue_block_post_task().await.unwrap().is_none());
+}
+
+async fn check_fee_collection(storage: &dyn Storage) {
+    let store_vault = InMemoryStoreVaultClient::new();
+    // the request has no fee proof, so collecting its fee fails, and the task is dropped
+    assert!(storage.process_fee_collection(&store_vault).await.is_err());
+    storage.process_fee_collection(&store_vault).await.unwrap();
+    assert!(storage.dequeue_block_post_task().await.unwrap().is_none());
+}
+
+async fn check_empty_block(storage: &dyn Storage) {
+    // the second one is within the deposit check interval
+    storage.enqueue_empty_block().await.unwrap();
+    storage.enqueue_empty_block().await.unwrap();
+
+    let block_post_task = storage
+        .dequeue_block_post_task()
+        .await
+        .unwrap()
+        .expect("empty block not enqueued");
+    assert!(block_post_task.force_post);
+    assert!(!block_post_task.block_sign_payload.is_registration_block);
+    assert!(storage.dequeue_block_post_task().await.unwrap().is_none());
+}
+
+async fn check_fee_reconciliation(storage: &dyn Storage) {
+    let transfers = vec![IntendedFeeTransfer {
+        sender: U256::from(1),
+        token_index: 0,
+        amount: U256::from(100),
+        is_collateral: false,
+    }];
+    let unposted = |block_id: &str| UnpostedFeeBlock {
+        block_id: block_id.to_string(),
+        transfers: transfers.clone(),
+        reason: "reverted".to_string(),
+    };
+
+    // the fee is collected before the post fails
+    assert!(storage
+        .record_collected_fee("conformance-fee-1", transfers.clone())
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(
+        storage
+            .record_failed_block_post("conformance-fee-1", "reverted")
+            .await
+            .unwrap(),
+        Some(unposted("conformance-fee-1"))
+    );
+    // a block is flagged once
+    assert!(storage
+        .record_failed_block_post("conformance-fee-1", "reverted")
+        .await
+        .unwrap()
+        .is_none());
+    assert!(storage
+        .record_collected_fee("conformance-fee-1", transfers.clone())
+        .await
+        .unwrap()
+        .is_none());
+
+    // the post fails before the fee is collected
+    assert!(storage
+        .record_failed_block_post("conformance-fee-2", "reverted")
+        .await
+        .unwrap()
+        .is_none());
+    assert_eq!(
+        storage
+            .record_collected_fee("conformance-fee-2", transfers.clone())
+            .await
+            .unwrap(),
+        Some(unposted("conformance-fee-2"))
+    );
+
+    // a failed post without a collected fee is not flagged
+    assert!(storage
+        .record_failed_block_post("conformance-fee-3", "reverted")
+        .await
+        .unwrap()
+        .is_none());
+}
+
+async fn check_signature_window(storage: &dyn Storage) {
+    // two senders fill the queue, so that their block is proposed at once
+    for (request_id, pubkey) in [("conformance-window-1", 1), ("conformance-window-2", 2)] {
+        let tx_request = TxRequest {
+            request_id: request_id.to_string(),
+            pubkey: U256::from(pubkey),
+            ..Default::default()
+        };
+        storage.add_tx(true, tx_request).await.unwrap();
+    }
+    storage.process_requests(true).await.unwrap();
+    let proposal = storage
+        .query_proposal("conformance-window-1")
+        .await
+        .unwrap()
+        .expect("proposal not found");
+
+    // one of the two signatures arrives after the proposing interval, so the memo waits for the
+    // other one
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    let signature = proposal.sign(KeySet::rand(&mut default_rng()));
+    storage
+        .add_signature("conformance-window-1", signature)
+        .await
+        .unwrap();
+    storage.process_signatures().await.unwrap();
+    assert!(storage
+        .query_proposal("conformance-window-1")
+        .await
+        .unwrap()
+        .is_some());
+    assert!(storage.dequeue_block_post_task().await.unwrap().is_none());
+
+    // the window closes once no signature arrives for a proposing interval
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    storage.process_signatures().await.unwrap();
+    assert!(storage
+        .query_proposal("conformance-window-1")
+        .await
+        .unwrap()
+        .is_none());
+    let block_post_task = storage
+        .dequeue_block_post_task()
+        .await
+        .unwrap()
+        .expect("block post task not enqueued");
+    assert_eq!(block_post_task.block_id, proposal.block_id);
+    assert_eq!(block_post_task.signatures.len(), 1);
+}
@@ -21,6 +21,9 @@ pub enum StorageError {
     #[error("Redis error: {0}")]
     RedisError(#[from] RedisClientError),
 
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+
     #[error("Serialization/Deserialization error: {0}")]
     SerdeError(#[from] SerdeJsonError),
 }
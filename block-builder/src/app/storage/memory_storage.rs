@@ -1,6 +1,7 @@
 use std::{
-    collections::{HashMap, VecDeque},
+    collections::{HashMap, HashSet, VecDeque},
     sync::Arc,
+    time::Duration,
 };
 
 use intmax2_client_sdk::external_api::utils::time::sleep_for;
@@ -14,19 +15,47 @@ use tokio::sync::RwLock;
 
 use crate::app::{
     block_post::BlockPostTask,
-    fee::{collect_fee, FeeCollection},
+    fee::{collect_fee, FeeCollection, IntendedFeeTransfer, UnpostedFeeBlock},
     storage::nonce_manager::NonceManager,
-    types::{ProposalMemo, TxRequest},
+    types::{select_by_fee_priority, ProposalMemo, TxRequest},
 };
 
 use super::{
     config::StorageConfig, error::StorageError,
-    nonce_manager::memory_nonce_manager::InMemoryNonceManager, Storage,
+    nonce_manager::memory_nonce_manager::InMemoryNonceManager, record_collected_fees,
+    record_queue_depth, Storage,
 };
 
 type AR<T> = Arc<RwLock<T>>;
 type ARQueue<T> = AR<VecDeque<T>>;
 type ARMap<K, V> = AR<HashMap<K, V>>;
+type ARSet<T> = AR<HashSet<T>>;
+
+/// What is known of a block for the reconciliation of its fee
+#[derive(Debug, Default)]
+pub struct FeeReconciliation {
+    pub transfers: Option<Vec<IntendedFeeTransfer>>, // fee transfers saved for the beneficiary
+    pub failure: Option<String>,                     // error of the failed post
+    pub flagged: bool,
+}
+
+impl FeeReconciliation {
+    /// Returns the block the first time both its fee and its failure are known
+    fn flag(&mut self, block_id: &str) -> Option<UnpostedFeeBlock> {
+        if self.flagged {
+            return None;
+        }
+        let (Some(transfers), Some(reason)) = (&self.transfers, &self.failure) else {
+            return None;
+        };
+        self.flagged = true;
+        Some(UnpostedFeeBlock {
+            block_id: block_id.to_string(),
+            transfers: transfers.clone(),
+            reason: reason.clone(),
+        })
+    }
+}
 
 pub struct InMemoryStorage {
     pub config: StorageConfig,
@@ -43,10 +72,14 @@ pub struct InMemoryStorage {
     pub request_id_to_block_id: ARMap<String, String>, // request_id -> block_id
     pub memos: ARMap<String, ProposalMemo>,            // block_id -> memo
     pub signatures: ARMap<String, Vec<UserSignature>>, // block_id -> user signature
+    pub last_signature_at: ARMap<String, u64>, // block_id -> timestamp of the last signature
+    pub expired_request_ids: ARSet<String>,    // requests dropped after their valid_until
 
     pub fee_collection_tasks: ARQueue<FeeCollection>, // fee collection tasks queue
     pub block_post_tasks_hi: ARQueue<BlockPostTask>,  // high priority tasks queue
     pub block_post_tasks_lo: ARQueue<BlockPostTask>,  // low priority tasks queue
+
+    pub fee_reconciliation: ARMap<String, FeeReconciliation>, // block_id -> fee reconciliation
 }
 
 impl InMemoryStorage {
@@ -64,10 +97,14 @@ impl InMemoryStorage {
             request_id_to_block_id: Default::default(),
             memos: Default::default(),
             signatures: Default::default(),
+            last_signature_at: Default::default(),
+            expired_request_ids: Default::default(),
 
             fee_collection_tasks: Default::default(),
             block_post_tasks_hi: Default::default(),
             block_post_tasks_lo: Default::default(),
+
+            fee_reconciliation: Default::default(),
         }
     }
 }
@@ -103,21 +140,54 @@ impl Storage for InMemoryStorage {
         };
 
         // If more than self.config.accepting_tx_interval seconds have passed since last_processed,
-        // or if there are NUM_SENDERS_IN_BLOCK tx_requests, process them.
+        // or if there are min_senders_for_immediate_post tx_requests, process them.
         let last_processed_ = *last_processed.read().await;
         let mut tx_requests = tx_requests.write().await;
         let current_time = chrono::Utc::now().timestamp() as u64;
-        if (tx_requests.len() < NUM_SENDERS_IN_BLOCK
-            && current_time < last_processed_ + self.config.accepting_tx_interval)
-            || tx_requests.is_empty()
+
+        // drop the requests that are past their valid_until instead of building them
+        let mut expired_request_ids = Vec::new();
+        tx_requests.retain(|r| {
+            if r.is_expired(current_time) {
+                expired_request_ids.push(r.request_id.clone());
+                false
+            } else {
+                true
+            }
+        });
+        if !expired_request_ids.is_empty() {
+            log::info!("dropped expired tx requests: {expired_request_ids:?}");
+            self.expired_request_ids
+                .write()
+                .await
+                .extend(expired_request_ids);
+        }
+
+        record_queue_depth(is_registration, tx_requests.len());
+        if !self
+            .config
+            .should_process_requests(tx_requests.len(), last_processed_, current_time)
         {
             return Ok(());
         }
 
         log::info!("process_requests is_registration: {}", is_registration);
 
-        let num_tx_requests = tx_requests.len().min(NUM_SENDERS_IN_BLOCK);
-        let tx_requests: Vec<TxRequest> = tx_requests.drain(..num_tx_requests).collect();
+        let tx_requests: Vec<TxRequest> =
+            if self.config.fee_priority_ordering && tx_requests.len() > NUM_SENDERS_IN_BLOCK {
+                let queue: Vec<TxRequest> = tx_requests.drain(..).collect();
+                let selected = select_by_fee_priority(&queue, NUM_SENDERS_IN_BLOCK);
+                let (selected, remaining): (Vec<_>, Vec<_>) = queue
+                    .into_iter()
+                    .enumerate()
+                    .partition(|(i, _)| selected.binary_search(i).is_ok());
+                // unselected requests keep their queue order
+                tx_requests.extend(remaining.into_iter().map(|(_, r)| r));
+                selected.into_iter().map(|(_, r)| r).collect()
+            } else {
+                let num_tx_requests = tx_requests.len().min(NUM_SENDERS_IN_BLOCK);
+                tx_requests.drain(..num_tx_requests).collect()
+            };
         let nonce = self.nonce_manager.reserve_nonce(is_registration).await?;
         let memo = ProposalMemo::from_tx_requests(
             is_registration,
@@ -178,6 +248,10 @@ impl Storage for InMemoryStorage {
         Ok(proposal)
     }
 
+    async fn is_expired(&self, request_id: &str) -> Result<bool, StorageError> {
+        Ok(self.expired_request_ids.read().await.contains(request_id))
+    }
+
     async fn add_signature(
         &self,
         request_id: &str,
@@ -205,22 +279,29 @@ impl Storage for InMemoryStorage {
             .map_err(|e| {
                 StorageError::AddSignatureError(format!("signature verification failed: {e}"))
             })?;
+        self.config
+            .validate_signer_fee_tokens(memo, signature.pubkey)?;
 
         // add signature
         let mut signatures = self.signatures.write().await;
         let signatures = signatures.entry(block_id.clone()).or_insert_with(Vec::new);
         signatures.push(signature);
+        let current_time = chrono::Utc::now().timestamp() as u64;
+        self.last_signature_at
+            .write()
+            .await
+            .insert(block_id.clone(), current_time);
 
         Ok(())
     }
 
     async fn process_signatures(&self) -> Result<(), StorageError> {
         // get all memos
+        let current_time = chrono::Utc::now().timestamp() as u64;
         let target_memos = {
             let memos = self.memos.read().await;
             let memos = memos.values().cloned().collect::<Vec<_>>();
             // get those that have passed self.config.proposing_block_interval
-            let current_time = chrono::Utc::now().timestamp() as u64;
             memos
                 .into_iter()
                 .filter(|memo| {
@@ -242,8 +323,20 @@ impl Storage for InMemoryStorage {
 
             log::info!("num signatures: {}", signatures.len());
 
-            // if there is no signature, skip
-            if signatures.is_empty() {
+            // skip if no signatures, or wait for more while they are still arriving
+            let last_signature_at = self
+                .last_signature_at
+                .read()
+                .await
+                .get(&memo.block_id)
+                .copied();
+            if !self.config.should_post_signatures(
+                memo.created_at,
+                memo.tx_requests.len(),
+                signatures.len(),
+                last_signature_at,
+                current_time,
+            ) {
                 continue;
             }
 
@@ -272,6 +365,10 @@ impl Storage for InMemoryStorage {
                 let mut signatures = self.signatures.write().await;
                 signatures.remove(&memo.block_id);
             }
+            {
+                let mut last_signature_at = self.last_signature_at.write().await;
+                last_signature_at.remove(&memo.block_id);
+            }
         }
 
         Ok(())
@@ -290,12 +387,17 @@ impl Storage for InMemoryStorage {
             Some(fee_collection) => fee_collection,
             None => return Ok(()),
         };
+        if self.config.dry_run {
+            self.config.log_intended_fee_collection(&fee_collection);
+            return Ok(());
+        }
         let block_post_tasks = collect_fee(
             store_vault_server_client,
             self.config.fee_beneficiary,
             &fee_collection,
         )
         .await?;
+        record_collected_fees(self, &fee_collection, &block_post_tasks).await?;
 
         // add to block_post_tasks_lo
         let mut block_post_tasks_lo = self.block_post_tasks_lo.write().await;
@@ -304,6 +406,55 @@ impl Storage for InMemoryStorage {
         Ok(())
     }
 
+    async fn reclaim_stale_nonces(&self, max_age: Duration) -> Result<(), StorageError> {
+        for is_registration in [true, false] {
+            let reclaimed = self
+                .nonce_manager
+                .reclaim_stale_nonces(max_age, is_registration)
+                .await?;
+            if !reclaimed.is_empty() {
+                log::warn!(
+                    "Reclaimed stale {} nonces: {:?}",
+                    if is_registration {
+                        "registration"
+                    } else {
+                        "non-registration"
+                    },
+                    reclaimed
+                );
+            }
+        }
+        Ok(())
+    }
+
+    async fn record_collected_fee(
+        &self,
+        block_id: &str,
+        transfers: Vec<IntendedFeeTransfer>,
+    ) -> Result<Option<UnpostedFeeBlock>, StorageError> {
+        if !self.config.reconcile_unposted_fees {
+            return Ok(None);
+        }
+        let mut fee_reconciliation = self.fee_reconciliation.write().await;
+        let entry = fee_reconciliation.entry(block_id.to_string()).or_default();
+        entry.transfers = Some(transfers);
+        Ok(entry.flag(block_id))
+    }
+
+    async fn record_failed_block_post(
+        &self,
+        block_id: &str,
+        reason: &str,
+    ) -> Result<Option<UnpostedFeeBlock>, StorageError> {
+        if !self.config.reconcile_unposted_fees {
+            return Ok(None);
+        }
+        let mut fee_reconciliation = self.fee_reconciliation.write().await;
+        let entry = fee_reconciliation.entry(block_id.to_string()).or_default();
+        entry.failure = Some(reason.to_string());
+        Ok(entry.flag(block_id))
+    }
+
     async fn enqueue_empty_block(&self) -> Result<(), StorageError> {
         if self.config.deposit_check_interval.is_none() {
             // if deposit check is disabled, do nothing
@@ -389,7 +540,9 @@ impl Storage for InMemoryStorage {
 
 #[cfg(test)]
 mod tests {
-    use crate::app::storage::nonce_manager::config::NonceManagerConfig;
+    use crate::app::storage::{
+        config::StorageBackend, conformance, nonce_manager::config::NonceManagerConfig,
+    };
 
     use super::*;
     use alloy::providers::{mock::Asserter, ProviderBuilder};
@@ -407,11 +560,22 @@ mod tests {
             tx_timeout: 60,
             accepting_tx_interval: 10,
             proposing_block_interval: 10,
+            min_signatures_to_post: NUM_SENDERS_IN_BLOCK,
+            max_proposing_block_interval: 10,
             deposit_check_interval: Some(5),
             nonce_waiting_time: 5,
+            fee_priority_ordering: false,
+            min_senders_for_immediate_post: NUM_SENDERS_IN_BLOCK,
+            fee_token_allowlist: None,
+            dry_run: false,
+            reconcile_unposted_fees: false,
             block_builder_id: "builder1".to_string(),
+            backend: StorageBackend::Memory,
             redis_url: None,
             cluster_id: None,
+            database_url: None,
+            database_max_connections: 10,
+            database_timeout: 10,
         };
 
         let provider_asserter = Asserter::new();
@@ -438,6 +602,9 @@ mod tests {
             account_id: None,
             tx: Default::default(), // assuming Tx: Default
             fee_proof: None,
+            fee: U256::default(),
+            fee_token_index: 0,
+            valid_until: None,
         }
     }
 
@@ -464,4 +631,35 @@ mod tests {
         assert_eq!(queue.len(), 1);
         assert_eq!(queue.front().unwrap().request_id, tx.request_id);
     }
+
+    #[tokio::test]
+    async fn test_conformance() {
+        let config = conformance::test_config(StorageBackend::Memory);
+        let nonce_config = NonceManagerConfig {
+            block_builder_address: convert_address_to_alloy(config.block_builder_address),
+            redis_url: None,
+            cluster_id: None,
+        };
+        let nonce_manager = InMemoryNonceManager::new(nonce_config, conformance::mock_rollup());
+        let storage = InMemoryStorage::new(&config, nonce_manager);
+        conformance::run_storage_conformance(storage).await;
+    }
+
+    #[tokio::test]
+    async fn test_process_requests_drops_expired() {
+        let storage = create_storage().await;
+        let current_time = chrono::Utc::now().timestamp() as u64;
+        let tx = TxRequest {
+            valid_until: Some(current_time - 10),
+            ..dummy_tx_request("expired-1")
+        };
+
+        storage.add_tx(false, tx).await.unwrap();
+        storage.process_requests(false).await.unwrap();
+
+        assert!(storage.non_registration_tx_requests.read().await.is_empty());
+        assert!(storage.is_expired("expired-1").await.unwrap());
+        assert!(!storage.is_expired("unknown").await.unwrap());
+        assert!(storage.query_proposal("expired-1").await.unwrap().is_none());
+    }
 }
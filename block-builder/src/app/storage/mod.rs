@@ -3,18 +3,28 @@ use intmax2_client_sdk::external_api::contract::{
 };
 use intmax2_interfaces::api::store_vault_server::interface::StoreVaultClientInterface;
 use intmax2_zkp::common::block_builder::{BlockProposal, UserSignature};
+use server_common::db::{DbPool, DbPoolConfig};
+use std::time::Duration;
+
 use nonce_manager::{
     config::NonceManagerConfig, memory_nonce_manager::InMemoryNonceManager,
-    redis_nonce_manager::RedisNonceManager,
+    postgres_nonce_manager::PostgresNonceManager, redis_nonce_manager::RedisNonceManager,
 };
 
-use super::{block_post::BlockPostTask, types::TxRequest};
+use super::{
+    block_post::BlockPostTask,
+    fee::{collected_fee_transfers, FeeCollection, IntendedFeeTransfer, UnpostedFeeBlock},
+    types::TxRequest,
+};
 
 pub mod config;
-use config::StorageConfig;
+use config::{StorageBackend, StorageConfig};
+#[cfg(test)]
+pub(crate) mod conformance;
 pub mod error;
 pub mod memory_storage;
 pub mod nonce_manager;
+pub mod postgres_storage;
 pub mod redis_storage;
 
 #[async_trait::async_trait(?Send)]
@@ -31,6 +41,9 @@ pub trait Storage: Sync + Send {
         request_id: &str,
     ) -> Result<Option<BlockProposal>, error::StorageError>;
 
+    /// Whether the request was dropped from the queue because it passed its `valid_until`
+    async fn is_expired(&self, request_id: &str) -> Result<bool, error::StorageError>;
+
     /// Add a signature for a transaction request
     async fn add_signature(
         &self,
@@ -54,29 +67,104 @@ pub trait Storage: Sync + Send {
     ) -> Result<(), error::StorageError>;
 
     async fn enqueue_empty_block(&self) -> Result<(), error::StorageError>;
+
+    /// Release the nonces that have been reserved for longer than `max_age`
+    async fn reclaim_stale_nonces(&self, max_age: Duration) -> Result<(), error::StorageError>;
+
+    /// Record that the fee `transfers` settled by `block_id` were saved for the beneficiary.
+    /// Returns the block if its post already failed and it was not flagged yet, so that each
+    /// block is flagged once. Does nothing unless `reconcile_unposted_fees` is set.
+    async fn record_collected_fee(
+        &self,
+        block_id: &str,
+        transfers: Vec<IntendedFeeTransfer>,
+    ) -> Result<Option<UnpostedFeeBlock>, error::StorageError>;
+
+    /// Record that posting `block_id` failed with `reason`. Returns the block if its fee was
+    /// already collected and it was not flagged yet, so that each block is flagged once. Does
+    /// nothing unless `reconcile_unposted_fees` is set.
+    async fn record_failed_block_post(
+        &self,
+        block_id: &str,
+        reason: &str,
+    ) -> Result<Option<UnpostedFeeBlock>, error::StorageError>;
+}
+
+/// Record the fees collected from `fee_collection` and report the blocks among them whose post
+/// already failed. This is shared by all storage backends, after `collect_fee` succeeds.
+pub(crate) async fn record_collected_fees(
+    storage: &dyn Storage,
+    fee_collection: &FeeCollection,
+    block_post_tasks: &[BlockPostTask],
+) -> Result<(), error::StorageError> {
+    for (block_id, transfers) in collected_fee_transfers(fee_collection, block_post_tasks) {
+        if let Some(unposted) = storage.record_collected_fee(&block_id, transfers).await? {
+            unposted.report();
+        }
+    }
+    Ok(())
+}
+
+/// Export the number of queued tx requests, observed each time the queue is processed
+pub(crate) fn record_queue_depth(is_registration: bool, queue_len: usize) {
+    let queue = if is_registration {
+        "registration"
+    } else {
+        "non_registration"
+    };
+    server_common::metrics::set_gauge(
+        "block_builder_tx_queue_depth",
+        "Number of tx requests waiting to be included in a block",
+        &[("queue", queue)],
+        queue_len as f64,
+    );
 }
 
 /// Create a storage implementation based on the configuration
 ///
-/// Returns RedisStorage if redis_url is set in the config, otherwise returns InMemoryStorage
+/// Returns the storage of `config.backend`, whose connection settings must be set
 pub async fn create_storage(config: &StorageConfig, rollup: RollupContract) -> Box<dyn Storage> {
-    if config.redis_url.is_some() {
-        log::info!("use redis storage");
-        let nonce_config = NonceManagerConfig {
-            block_builder_address: convert_address_to_alloy(config.block_builder_address),
-            redis_url: config.redis_url.clone(),
-            cluster_id: config.cluster_id.clone(),
-        };
-        let nonce_manager = RedisNonceManager::new(nonce_config, rollup).await;
-        Box::new(redis_storage::RedisStorage::new(config, nonce_manager).await)
-    } else {
-        log::info!("use in-memory storage");
-        let nonce_config = NonceManagerConfig {
-            block_builder_address: convert_address_to_alloy(config.block_builder_address),
-            redis_url: None,
-            cluster_id: None,
-        };
-        let nonce_manager = InMemoryNonceManager::new(nonce_config, rollup);
-        Box::new(memory_storage::InMemoryStorage::new(config, nonce_manager))
+    match config.backend {
+        StorageBackend::Redis => {
+            log::info!("use redis storage");
+            let nonce_config = NonceManagerConfig {
+                block_builder_address: convert_address_to_alloy(config.block_builder_address),
+                redis_url: config.redis_url.clone(),
+                cluster_id: config.cluster_id.clone(),
+            };
+            let nonce_manager = RedisNonceManager::new(nonce_config, rollup).await;
+            Box::new(redis_storage::RedisStorage::new(config, nonce_manager).await)
+        }
+        StorageBackend::Postgres => {
+            log::info!("use postgres storage");
+            let pool = DbPool::from_config(&DbPoolConfig {
+                url: config.database_url.clone().expect("database_url not found"),
+                max_connections: config.database_max_connections,
+                idle_timeout: config.database_timeout,
+            })
+            .await
+            .expect("Failed to connect to the database");
+            let nonce_config = NonceManagerConfig {
+                block_builder_address: convert_address_to_alloy(config.block_builder_address),
+                redis_url: None,
+                cluster_id: config.cluster_id.clone(),
+            };
+            let nonce_manager = PostgresNonceManager::new(nonce_config, pool.clone(), rollup);
+            Box::new(postgres_storage::PostgresStorage::new(
+                config,
+                pool,
+                nonce_manager,
+            ))
+        }
+        StorageBackend::Memory => {
+            log::info!("use in-memory storage");
+            let nonce_config = NonceManagerConfig {
+                block_builder_address: convert_address_to_alloy(config.block_builder_address),
+                redis_url: None,
+                cluster_id: None,
+            };
+            let nonce_manager = InMemoryNonceManager::new(nonce_config, rollup);
+            Box::new(memory_storage::InMemoryStorage::new(config, nonce_manager))
+        }
     }
 }
@@ -5,6 +5,9 @@ pub enum NonceError {
     #[error("Redis error: {0}")]
     RedisError(#[from] redis::RedisError),
 
+    #[error("Database error: {0}")]
+    DatabaseError(#[from] sqlx::Error),
+
     #[error("Serialization/Deserialization error: {0}")]
     SerdeError(#[from] serde_json::Error),
 
@@ -1,4 +1,4 @@
-use std::{collections::BTreeSet, sync::Arc};
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
 
 use intmax2_client_sdk::external_api::contract::rollup_contract::RollupContract;
 use tokio::sync::RwLock;
@@ -16,8 +16,9 @@ pub struct InMemoryNonceManager {
     pub rollup: RollupContract,
     pub next_registration_nonce: AR<u32>,
     pub next_non_registration_nonce: AR<u32>,
-    pub reserved_registration_nonces: AR<BTreeSet<u32>>,
-    pub reserved_non_registration_nonces: AR<BTreeSet<u32>>,
+    // reserved nonce -> unix timestamp of the reservation
+    pub reserved_registration_nonces: AR<BTreeMap<u32, u64>>,
+    pub reserved_non_registration_nonces: AR<BTreeMap<u32, u64>>,
 }
 
 impl InMemoryNonceManager {
@@ -27,8 +28,8 @@ impl InMemoryNonceManager {
             rollup,
             next_registration_nonce: Arc::new(RwLock::new(0)),
             next_non_registration_nonce: Arc::new(RwLock::new(0)),
-            reserved_registration_nonces: Arc::new(RwLock::new(BTreeSet::new())),
-            reserved_non_registration_nonces: Arc::new(RwLock::new(BTreeSet::new())),
+            reserved_registration_nonces: Arc::new(RwLock::new(BTreeMap::new())),
+            reserved_non_registration_nonces: Arc::new(RwLock::new(BTreeMap::new())),
         }
     }
 }
@@ -52,13 +53,13 @@ impl InMemoryNonceManager {
         let mut reserved_registration_nonces_guard =
             self.reserved_registration_nonces.write().await;
         reserved_registration_nonces_guard
-            .retain(|&nonce| nonce >= onchain_next_registration_nonce);
+            .retain(|&nonce, _| nonce >= onchain_next_registration_nonce);
         drop(reserved_registration_nonces_guard);
 
         let mut reserved_non_registration_nonces_guard =
             self.reserved_non_registration_nonces.write().await;
         reserved_non_registration_nonces_guard
-            .retain(|&nonce| nonce >= onchain_next_non_registration_nonce);
+            .retain(|&nonce, _| nonce >= onchain_next_non_registration_nonce);
         drop(reserved_non_registration_nonces_guard);
 
         Ok(())
@@ -86,7 +87,11 @@ impl NonceManager for InMemoryNonceManager {
         } else {
             &self.reserved_non_registration_nonces
         };
-        reserved_nonces_arc.write().await.insert(next_nonce);
+        let reserved_at = chrono::Utc::now().timestamp() as u64;
+        reserved_nonces_arc
+            .write()
+            .await
+            .insert(next_nonce, reserved_at);
 
         tracing::Span::current().record("next_nonce", next_nonce);
         Ok(next_nonce)
@@ -113,8 +118,32 @@ impl NonceManager for InMemoryNonceManager {
         } else {
             self.reserved_non_registration_nonces.read().await
         };
-        // `BTreeSet` iterators yield elements in ascending order.
-        // So, the first element from `iter().next()` is the smallest.
-        Ok(reserved_nonces_guard.iter().next().cloned())
+        // `BTreeMap` iterators yield keys in ascending order.
+        // So, the first element from `keys().next()` is the smallest.
+        Ok(reserved_nonces_guard.keys().next().cloned())
+    }
+
+    #[instrument(skip(self))]
+    async fn reclaim_stale_nonces(
+        &self,
+        max_age: Duration,
+        is_registration: bool,
+    ) -> Result<Vec<u32>, NonceError> {
+        let cutoff = (chrono::Utc::now().timestamp() as u64).saturating_sub(max_age.as_secs());
+        let reserved_nonces_arc = if is_registration {
+            &self.reserved_registration_nonces
+        } else {
+            &self.reserved_non_registration_nonces
+        };
+        let mut reserved_nonces_guard = reserved_nonces_arc.write().await;
+        let stale_nonces: Vec<u32> = reserved_nonces_guard
+            .iter()
+            .filter(|(_, &reserved_at)| reserved_at <= cutoff)
+            .map(|(&nonce, _)| nonce)
+            .collect();
+        for nonce in stale_nonces.iter() {
+            reserved_nonces_guard.remove(nonce);
+        }
+        Ok(stale_nonces)
     }
 }
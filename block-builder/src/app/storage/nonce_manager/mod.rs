@@ -1,9 +1,11 @@
 use error::NonceError;
+use std::time::Duration;
 
 pub mod common;
 pub mod config;
 pub mod error;
 pub mod memory_nonce_manager;
+pub mod postgres_nonce_manager;
 pub mod redis_nonce_manager;
 
 #[async_trait::async_trait(?Send)]
@@ -19,4 +21,12 @@ pub trait NonceManager: Sync + Send {
         &self,
         is_registration: bool,
     ) -> Result<Option<u32>, NonceError>;
+
+    /// Release the nonces that have been reserved for longer than `max_age`, e.g. because the
+    /// process crashed before releasing them. Returns the released nonces.
+    async fn reclaim_stale_nonces(
+        &self,
+        max_age: Duration,
+        is_registration: bool,
+    ) -> Result<Vec<u32>, NonceError>;
 }
@@ -0,0 +1,171 @@
+use std::time::Duration;
+
+use intmax2_client_sdk::external_api::contract::rollup_contract::RollupContract;
+use server_common::db::DbPool;
+use tracing::instrument;
+
+use super::{
+    common::get_onchain_next_nonce, config::NonceManagerConfig, error::NonceError, NonceManager,
+};
+
+pub struct PostgresNonceManager {
+    pub config: NonceManagerConfig,
+    pub pool: DbPool,
+    pub rollup: RollupContract,
+    cluster_id: String,
+}
+
+impl PostgresNonceManager {
+    pub fn new(config: NonceManagerConfig, pool: DbPool, rollup: RollupContract) -> Self {
+        let cluster_id = config
+            .cluster_id
+            .clone()
+            .unwrap_or_else(|| "default".to_string());
+        Self {
+            config,
+            pool,
+            rollup,
+            cluster_id,
+        }
+    }
+
+    async fn sync_onchain(&self) -> Result<(), NonceError> {
+        for is_registration in [true, false] {
+            let onchain_next_nonce = get_onchain_next_nonce(
+                &self.rollup,
+                is_registration,
+                self.config.block_builder_address,
+            )
+            .await?;
+            let mut tx = self.pool.begin().await?;
+            sqlx::query!(
+                r#"
+                INSERT INTO block_builder_next_nonces (cluster_id, is_registration, next_nonce)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (cluster_id, is_registration)
+                DO UPDATE SET next_nonce = GREATEST(block_builder_next_nonces.next_nonce, EXCLUDED.next_nonce)
+                "#,
+                self.cluster_id,
+                is_registration,
+                onchain_next_nonce as i64
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query!(
+                r#"
+                DELETE FROM block_builder_reserved_nonces
+                WHERE cluster_id = $1 AND is_registration = $2 AND nonce < $3
+                "#,
+                self.cluster_id,
+                is_registration,
+                onchain_next_nonce as i64
+            )
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl NonceManager for PostgresNonceManager {
+    async fn reserve_nonce(&self, is_registration: bool) -> Result<u32, NonceError> {
+        self.sync_onchain().await?;
+
+        let mut tx = self.pool.begin().await?;
+        let reserved_nonce = sqlx::query_scalar!(
+            r#"
+            UPDATE block_builder_next_nonces SET next_nonce = next_nonce + 1
+            WHERE cluster_id = $1 AND is_registration = $2
+            RETURNING next_nonce - 1 AS "reserved_nonce!"
+            "#,
+            self.cluster_id,
+            is_registration
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO block_builder_reserved_nonces (cluster_id, is_registration, nonce, reserved_at)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            self.cluster_id,
+            is_registration,
+            reserved_nonce,
+            chrono::Utc::now().timestamp()
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        let reserved_nonce = reserved_nonce as u32;
+        log::info!(
+            "Reserved nonce: {} for {}",
+            reserved_nonce,
+            if is_registration {
+                "registration"
+            } else {
+                "non-registration"
+            }
+        );
+        Ok(reserved_nonce)
+    }
+
+    #[instrument(skip(self))]
+    async fn release_nonce(&self, nonce: u32, is_registration: bool) -> Result<(), NonceError> {
+        sqlx::query!(
+            r#"
+            DELETE FROM block_builder_reserved_nonces
+            WHERE cluster_id = $1 AND is_registration = $2 AND nonce = $3
+            "#,
+            self.cluster_id,
+            is_registration,
+            nonce as i64
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn smallest_reserved_nonce(
+        &self,
+        is_registration: bool,
+    ) -> Result<Option<u32>, NonceError> {
+        let nonce = sqlx::query_scalar!(
+            r#"
+            SELECT MIN(nonce) FROM block_builder_reserved_nonces
+            WHERE cluster_id = $1 AND is_registration = $2
+            "#,
+            self.cluster_id,
+            is_registration
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(nonce.map(|nonce| nonce as u32))
+    }
+
+    #[instrument(skip(self))]
+    async fn reclaim_stale_nonces(
+        &self,
+        max_age: Duration,
+        is_registration: bool,
+    ) -> Result<Vec<u32>, NonceError> {
+        let cutoff = chrono::Utc::now().timestamp() - max_age.as_secs() as i64;
+        let mut reclaimed = sqlx::query_scalar!(
+            r#"
+            DELETE FROM block_builder_reserved_nonces
+            WHERE cluster_id = $1 AND is_registration = $2 AND reserved_at <= $3
+            RETURNING nonce
+            "#,
+            self.cluster_id,
+            is_registration,
+            cutoff
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        reclaimed.sort();
+        Ok(reclaimed.into_iter().map(|nonce| nonce as u32).collect())
+    }
+}
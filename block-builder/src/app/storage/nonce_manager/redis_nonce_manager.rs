@@ -5,7 +5,7 @@ use intmax2_client_sdk::external_api::{
     contract::rollup_contract::RollupContract, utils::retry::with_retry,
 };
 use redis::{aio::ConnectionManager, Client};
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 use tokio::sync::Mutex;
 use tracing::instrument;
 
@@ -18,6 +18,10 @@ pub struct RedisNonceManager {
     pub next_non_registration_nonce_key: String,
     pub reserved_registration_nonces_key: String,
     pub reserved_non_registration_nonces_key: String,
+
+    // sorted sets of reserved nonces scored by the unix timestamp of the reservation
+    pub registration_reserved_at_key: String,
+    pub non_registration_reserved_at_key: String,
 }
 
 impl RedisNonceManager {
@@ -33,6 +37,9 @@ impl RedisNonceManager {
         let reserved_registration_nonces_key = format!("{prefix}:reserved_registration_nonces");
         let reserved_non_registration_nonces_key =
             format!("{prefix}:reserved_non_registration_nonces");
+        let registration_reserved_at_key = format!("{prefix}:registration_nonce_reserved_at");
+        let non_registration_reserved_at_key =
+            format!("{prefix}:non_registration_nonce_reserved_at");
 
         let redis_url = config
             .redis_url
@@ -51,6 +58,16 @@ impl RedisNonceManager {
             next_non_registration_nonce_key,
             reserved_registration_nonces_key,
             reserved_non_registration_nonces_key,
+            registration_reserved_at_key,
+            non_registration_reserved_at_key,
+        }
+    }
+
+    fn reserved_at_key(&self, is_registration: bool) -> &str {
+        if is_registration {
+            &self.registration_reserved_at_key
+        } else {
+            &self.non_registration_reserved_at_key
         }
     }
 
@@ -130,6 +147,8 @@ impl NonceManager for RedisNonceManager {
             &self.reserved_non_registration_nonces_key
         };
 
+        let reserved_at_key = self.reserved_at_key(is_registration);
+
         let reserved_nonce = with_retry(|| async {
             let mut conn = self.get_conn().await?;
             let val_after_incr: i64 = redis::cmd("INCR")
@@ -143,6 +162,12 @@ impl NonceManager for RedisNonceManager {
                 .arg(reserved_nonce)
                 .query_async(&mut conn)
                 .await?;
+            let _: i64 = redis::cmd("ZADD")
+                .arg(reserved_at_key)
+                .arg(chrono::Utc::now().timestamp())
+                .arg(reserved_nonce)
+                .query_async(&mut conn)
+                .await?;
             Result::<_, NonceError>::Ok(reserved_nonce)
         })
         .await?;
@@ -172,6 +197,11 @@ impl NonceManager for RedisNonceManager {
                 .arg(nonce)
                 .query_async(&mut conn)
                 .await?;
+            let () = redis::cmd("ZREM")
+                .arg(self.reserved_at_key(is_registration))
+                .arg(nonce)
+                .query_async(&mut conn)
+                .await?;
 
             Ok(())
         })
@@ -200,6 +230,50 @@ impl NonceManager for RedisNonceManager {
 
         Ok(result.first().cloned())
     }
+
+    #[instrument(skip(self))]
+    async fn reclaim_stale_nonces(
+        &self,
+        max_age: Duration,
+        is_registration: bool,
+    ) -> Result<Vec<u32>, NonceError> {
+        let cutoff = chrono::Utc::now().timestamp() - max_age.as_secs() as i64;
+        let reserved_nonces_key = if is_registration {
+            &self.reserved_registration_nonces_key
+        } else {
+            &self.reserved_non_registration_nonces_key
+        };
+        let reserved_at_key = self.reserved_at_key(is_registration);
+
+        with_retry(|| async {
+            let mut conn = self.get_conn().await?;
+            let stale_nonces: Vec<u32> = redis::cmd("ZRANGEBYSCORE")
+                .arg(reserved_at_key)
+                .arg("-inf")
+                .arg(cutoff)
+                .query_async(&mut conn)
+                .await?;
+            let mut reclaimed = Vec::new();
+            for nonce in stale_nonces {
+                // the nonce may have already been removed by the on-chain sync
+                let removed: i64 = redis::cmd("ZREM")
+                    .arg(reserved_nonces_key)
+                    .arg(nonce)
+                    .query_async(&mut conn)
+                    .await?;
+                let _: i64 = redis::cmd("ZREM")
+                    .arg(reserved_at_key)
+                    .arg(nonce)
+                    .query_async(&mut conn)
+                    .await?;
+                if removed > 0 {
+                    reclaimed.push(nonce);
+                }
+            }
+            Result::<_, NonceError>::Ok(reclaimed)
+        })
+        .await
+    }
 }
 
 #[cfg(test)]
@@ -299,6 +373,60 @@ mod tests {
         assert_eq!(smallest_non_reg_nonce_after_release, Some(21));
     }
 
+    #[tokio::test]
+    async fn test_nonce_manager_reclaim_stale_nonces() {
+        let port = find_free_port();
+        let cont_name = "redis-test_nonce_manager_reclaim_stale_nonces";
+
+        stop_redis_docker(cont_name);
+        let output = run_redis_docker(port, cont_name);
+        assert!(
+            output.status.success(),
+            "Couldn't start {}: {}",
+            cont_name,
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        let (client, asserter) = create_client(port).await;
+
+        set_reg_nonce_asserter(&asserter, 10);
+        set_non_reg_nonce_asserter(&asserter, 20);
+        let nonce1 = client.reserve_nonce(true).await.unwrap();
+        assert_eq!(nonce1, 10);
+
+        set_reg_nonce_asserter(&asserter, 10);
+        set_non_reg_nonce_asserter(&asserter, 20);
+        let nonce2 = client.reserve_nonce(true).await.unwrap();
+        assert_eq!(nonce2, 11);
+        client.release_nonce(nonce2, true).await.unwrap();
+
+        // nothing is stale yet
+        let reclaimed = client
+            .reclaim_stale_nonces(Duration::from_secs(3600), true)
+            .await
+            .unwrap();
+        assert!(reclaimed.is_empty());
+        assert_eq!(
+            client.smallest_reserved_nonce(true).await.unwrap(),
+            Some(10)
+        );
+
+        // the stranded nonce is reclaimed, the released one is not reported again
+        let reclaimed = client
+            .reclaim_stale_nonces(Duration::from_secs(0), true)
+            .await
+            .unwrap();
+        assert_eq!(reclaimed, vec![10]);
+        assert_eq!(client.smallest_reserved_nonce(true).await.unwrap(), None);
+
+        // non-registration nonces are not affected
+        let reclaimed = client
+            .reclaim_stale_nonces(Duration::from_secs(0), false)
+            .await
+            .unwrap();
+        assert!(reclaimed.is_empty());
+    }
+
     #[tokio::test]
     async fn test_nonce_manager_clean_when_sync() {
         let port = find_free_port();
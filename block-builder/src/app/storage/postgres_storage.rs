@@ -0,0 +1,821 @@
+use std::time::Duration;
+
+use intmax2_client_sdk::external_api::utils::time::sleep_for;
+use intmax2_interfaces::api::store_vault_server::interface::StoreVaultClientInterface;
+use intmax2_zkp::{
+    common::block_builder::{BlockProposal, UserSignature},
+    constants::NUM_SENDERS_IN_BLOCK,
+};
+use rand::Rng as _;
+use server_common::db::DbPool;
+
+use crate::app::{
+    block_post::BlockPostTask,
+    fee::{collect_fee, FeeCollection, IntendedFeeTransfer, UnpostedFeeBlock},
+    storage::nonce_manager::NonceManager,
+    types::{select_by_fee_priority, ProposalMemo, TxRequest},
+};
+
+use super::{
+    config::StorageConfig, error::StorageError,
+    nonce_manager::postgres_nonce_manager::PostgresNonceManager, record_collected_fees,
+    record_queue_depth, Storage,
+};
+
+/// Rows older than this are pruned, like the keys of the redis storage expire
+const ROW_TTL_SECONDS: i64 = 1200; // 20min
+
+type Result<T> = std::result::Result<T, StorageError>;
+
+/// Storage backed by the tables of the `migrations` directory, shared by all instances with the
+/// same cluster id.
+///
+/// Where the redis storage takes a lock, this one locks a row with `FOR UPDATE SKIP LOCKED`, so
+/// that an instance finding the row locked skips the work like it would skip a held lock.
+pub struct PostgresStorage {
+    pub config: StorageConfig,
+    pool: DbPool,
+    pub nonce_manager: PostgresNonceManager,
+    cluster_id: String,
+}
+
+impl PostgresStorage {
+    pub fn new(config: &StorageConfig, pool: DbPool, nonce_manager: PostgresNonceManager) -> Self {
+        let cluster_id = config
+            .cluster_id
+            .clone()
+            .unwrap_or_else(|| "default".to_string());
+        log::info!("Postgres storage initialized");
+        Self {
+            config: config.clone(),
+            pool,
+            nonce_manager,
+            cluster_id,
+        }
+    }
+
+    async fn get_block_id(&self, request_id: &str) -> Result<Option<String>> {
+        let block_id = sqlx::query_scalar!(
+            r#"
+            SELECT block_id FROM block_builder_request_blocks
+            WHERE cluster_id = $1 AND request_id = $2
+            "#,
+            self.cluster_id,
+            request_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(block_id)
+    }
+
+    async fn get_memo(&self, block_id: &str) -> Result<Option<ProposalMemo>> {
+        let memo = sqlx::query_scalar!(
+            r#"
+            SELECT memo FROM block_builder_memos WHERE cluster_id = $1 AND block_id = $2
+            "#,
+            self.cluster_id,
+            block_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        memo.map(|memo| serde_json::from_str(&memo))
+            .transpose()
+            .map_err(StorageError::from)
+    }
+
+    /// Remove and return the oldest block post task of the queue, skipping a task that another
+    /// instance is removing.
+    async fn pop_block_post_task(&self, is_high_priority: bool) -> Result<Option<BlockPostTask>> {
+        let task = sqlx::query_scalar!(
+            r#"
+            DELETE FROM block_builder_block_post_tasks
+            WHERE id = (
+                SELECT id FROM block_builder_block_post_tasks
+                WHERE cluster_id = $1 AND is_high_priority = $2
+                ORDER BY id
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING task
+            "#,
+            self.cluster_id,
+            is_high_priority
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        task.map(|task| serde_json::from_str(&task))
+            .transpose()
+            .map_err(StorageError::from)
+    }
+
+    /// Pop a high priority task and release its nonce.
+    async fn pop_high_priority_task(&self) -> Result<Option<BlockPostTask>> {
+        let task = self.pop_block_post_task(true).await?;
+        if let Some(task) = &task {
+            self.nonce_manager
+                .release_nonce(
+                    task.block_sign_payload.block_builder_nonce,
+                    task.block_sign_payload.is_registration_block,
+                )
+                .await?;
+        }
+        Ok(task)
+    }
+
+    /// Set the given `transfers` or `failure` of the fee reconciliation of `block_id`. Returns
+    /// the transfers and the failure the first time both are set.
+    ///
+    /// The upsert locks the row until the commit, so that the fee collection and the block post
+    /// of different instances cannot both flag the block.
+    async fn record_fee_reconciliation(
+        &self,
+        block_id: &str,
+        transfers: Option<&str>,
+        failure: Option<&str>,
+    ) -> Result<Option<(String, String)>> {
+        let current_time = chrono::Utc::now().timestamp();
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO block_builder_fee_reconciliation (cluster_id, block_id, transfers, failure, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (cluster_id, block_id) DO UPDATE SET
+                transfers = COALESCE(EXCLUDED.transfers, block_builder_fee_reconciliation.transfers),
+                failure = COALESCE(EXCLUDED.failure, block_builder_fee_reconciliation.failure)
+            "#,
+            self.cluster_id,
+            block_id,
+            transfers,
+            failure,
+            current_time
+        )
+        .execute(&mut *tx)
+        .await?;
+        let flagged = sqlx::query!(
+            r#"
+            UPDATE block_builder_fee_reconciliation SET flagged = TRUE
+            WHERE cluster_id = $1 AND block_id = $2
+                AND transfers IS NOT NULL AND failure IS NOT NULL AND NOT flagged
+            RETURNING transfers AS "transfers!", failure AS "failure!"
+            "#,
+            self.cluster_id,
+            block_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(flagged.map(|r| (r.transfers, r.failure)))
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl Storage for PostgresStorage {
+    async fn add_tx(&self, is_registration: bool, tx_request: TxRequest) -> Result<()> {
+        let serialized = serde_json::to_string(&tx_request)?;
+        sqlx::query!(
+            r#"
+            INSERT INTO block_builder_tx_requests (cluster_id, is_registration, request, created_at)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            self.cluster_id,
+            is_registration,
+            serialized,
+            chrono::Utc::now().timestamp()
+        )
+        .execute(&self.pool)
+        .await?;
+        log::info!(
+            "Transaction added to {} queue: {}",
+            if is_registration {
+                "registration"
+            } else {
+                "non-registration"
+            },
+            tx_request.request_id
+        );
+        Ok(())
+    }
+
+    async fn query_proposal(&self, request_id: &str) -> Result<Option<BlockProposal>> {
+        let Some(block_id) = self.get_block_id(request_id).await? else {
+            return Ok(None);
+        };
+        let Some(memo) = self.get_memo(&block_id).await? else {
+            return Ok(None);
+        };
+        let proposal = memo
+            .tx_requests
+            .iter()
+            .position(|r| r.request_id == request_id)
+            .map(|position| memo.proposals[position].clone());
+        Ok(proposal)
+    }
+
+    async fn is_expired(&self, request_id: &str) -> Result<bool> {
+        let is_expired = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM block_builder_expired_requests
+                WHERE cluster_id = $1 AND request_id = $2
+            ) AS "is_expired!"
+            "#,
+            self.cluster_id,
+            request_id
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(is_expired)
+    }
+
+    async fn process_requests(&self, is_registration: bool) -> Result<()> {
+        let current_time = chrono::Utc::now().timestamp() as u64;
+        let mut tx = self.pool.begin().await?;
+
+        // The row of the queue is locked until commit, so that only one instance processes it
+        sqlx::query!(
+            r#"
+            INSERT INTO block_builder_last_processed (cluster_id, is_registration, processed_at)
+            VALUES ($1, $2, 0)
+            ON CONFLICT (cluster_id, is_registration) DO NOTHING
+            "#,
+            self.cluster_id,
+            is_registration
+        )
+        .execute(&mut *tx)
+        .await?;
+        let last_processed = sqlx::query_scalar!(
+            r#"
+            SELECT processed_at FROM block_builder_last_processed
+            WHERE cluster_id = $1 AND is_registration = $2
+            FOR UPDATE SKIP LOCKED
+            "#,
+            self.cluster_id,
+            is_registration
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(last_processed) = last_processed else {
+            // Another instance is already processing
+            return Ok(());
+        };
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT id, request FROM block_builder_tx_requests
+            WHERE cluster_id = $1 AND is_registration = $2
+            ORDER BY id
+            "#,
+            self.cluster_id,
+            is_registration
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        let mut queue = Vec::with_capacity(rows.len());
+        for row in rows {
+            queue.push((row.id, serde_json::from_str::<TxRequest>(&row.request)?));
+        }
+
+        // Drop the requests that are past their valid_until instead of building them
+        let (expired, queue): (Vec<_>, Vec<_>) = queue
+            .into_iter()
+            .partition(|(_, r)| r.is_expired(current_time));
+        if !expired.is_empty() {
+            for (_, tx_request) in &expired {
+                sqlx::query!(
+                    r#"
+                    INSERT INTO block_builder_expired_requests (cluster_id, request_id, expired_at)
+                    VALUES ($1, $2, $3)
+                    ON CONFLICT (cluster_id, request_id) DO NOTHING
+                    "#,
+                    self.cluster_id,
+                    tx_request.request_id,
+                    current_time as i64
+                )
+                .execute(&mut *tx)
+                .await?;
+            }
+            let expired_ids: Vec<i64> = expired.iter().map(|(id, _)| *id).collect();
+            sqlx::query!(
+                "DELETE FROM block_builder_tx_requests WHERE id = ANY($1)",
+                &expired_ids
+            )
+            .execute(&mut *tx)
+            .await?;
+            log::info!("dropped {} expired tx requests", expired.len());
+        }
+
+        record_queue_depth(is_registration, queue.len());
+        if !self
+            .config
+            .should_process_requests(queue.len(), last_processed as u64, current_time)
+        {
+            tx.commit().await?;
+            return Ok(());
+        }
+
+        log::info!("process_requests is_registration: {is_registration}");
+
+        let selected: Vec<(i64, TxRequest)> =
+            if self.config.fee_priority_ordering && queue.len() > NUM_SENDERS_IN_BLOCK {
+                let requests: Vec<TxRequest> = queue.iter().map(|(_, r)| r.clone()).collect();
+                select_by_fee_priority(&requests, NUM_SENDERS_IN_BLOCK)
+                    .into_iter()
+                    .map(|i| queue[i].clone())
+                    .collect()
+            } else {
+                queue.into_iter().take(NUM_SENDERS_IN_BLOCK).collect()
+            };
+        let (selected_ids, tx_requests): (Vec<i64>, Vec<TxRequest>) = selected.into_iter().unzip();
+
+        let nonce = self.nonce_manager.reserve_nonce(is_registration).await?;
+        let memo = ProposalMemo::from_tx_requests(
+            is_registration,
+            self.config.block_builder_address,
+            nonce,
+            &tx_requests,
+            self.config.tx_timeout,
+        );
+        log::info!(
+            "constructed proposal block_id: {}, payload: {:?}",
+            memo.block_id,
+            memo.block_sign_payload.clone()
+        );
+
+        sqlx::query!(
+            r#"
+            INSERT INTO block_builder_memos (cluster_id, block_id, memo, created_at)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            self.cluster_id,
+            memo.block_id,
+            serde_json::to_string(&memo)?,
+            memo.created_at as i64
+        )
+        .execute(&mut *tx)
+        .await?;
+        for tx_request in &tx_requests {
+            sqlx::query!(
+                r#"
+                INSERT INTO block_builder_request_blocks (cluster_id, request_id, block_id, created_at)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (cluster_id, request_id) DO UPDATE SET block_id = EXCLUDED.block_id
+                "#,
+                self.cluster_id,
+                tx_request.request_id,
+                memo.block_id,
+                current_time as i64
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        sqlx::query!(
+            "DELETE FROM block_builder_tx_requests WHERE id = ANY($1)",
+            &selected_ids
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            r#"
+            UPDATE block_builder_last_processed SET processed_at = $3
+            WHERE cluster_id = $1 AND is_registration = $2
+            "#,
+            self.cluster_id,
+            is_registration,
+            current_time as i64
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn add_signature(&self, request_id: &str, signature: UserSignature) -> Result<()> {
+        let block_id = self.get_block_id(request_id).await?.ok_or_else(|| {
+            StorageError::AddSignatureError(format!(
+                "block_id not found for request_id: {request_id}"
+            ))
+        })?;
+        let memo = self.get_memo(&block_id).await?.ok_or_else(|| {
+            StorageError::AddSignatureError(format!("memo not found for block_id: {block_id}"))
+        })?;
+
+        signature
+            .verify(&memo.block_sign_payload, memo.pubkey_hash)
+            .map_err(|e| {
+                StorageError::AddSignatureError(format!("signature verification failed: {e}"))
+            })?;
+        self.config
+            .validate_signer_fee_tokens(&memo, signature.pubkey)?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO block_builder_signatures (cluster_id, block_id, signature, created_at)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            self.cluster_id,
+            block_id,
+            serde_json::to_string(&signature)?,
+            chrono::Utc::now().timestamp()
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn process_signatures(&self) -> Result<()> {
+        let current_time = chrono::Utc::now().timestamp();
+        let mut tx = self.pool.begin().await?;
+
+        // Memos locked by another instance are being processed by it
+        let memos = sqlx::query!(
+            r#"
+            SELECT block_id, memo FROM block_builder_memos
+            WHERE cluster_id = $1 AND created_at < $2
+            FOR UPDATE SKIP LOCKED
+            "#,
+            self.cluster_id,
+            current_time - self.config.proposing_block_interval as i64
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        for row in memos {
+            let block_id = row.block_id;
+            let memo = match serde_json::from_str::<ProposalMemo>(&row.memo) {
+                Ok(memo) => memo,
+                Err(e) => {
+                    log::error!("Failed to deserialize memo for block_id {block_id}: {e}");
+                    continue;
+                }
+            };
+            let rows = sqlx::query!(
+                r#"
+                SELECT signature, created_at FROM block_builder_signatures
+                WHERE cluster_id = $1 AND block_id = $2
+                ORDER BY id
+                "#,
+                self.cluster_id,
+                block_id
+            )
+            .fetch_all(&mut *tx)
+            .await?;
+            // skip if no signatures, or wait for more while they are still arriving
+            let last_signature_at = rows.iter().map(|row| row.created_at as u64).max();
+            if !self.config.should_post_signatures(
+                memo.created_at,
+                memo.tx_requests.len(),
+                rows.len(),
+                last_signature_at,
+                current_time as u64,
+            ) {
+                continue;
+            }
+            let serialized_signatures: Vec<String> =
+                rows.into_iter().map(|row| row.signature).collect();
+            log::info!("process_signatures block_id: {block_id}");
+
+            let mut signatures = Vec::with_capacity(serialized_signatures.len());
+            for serialized in serialized_signatures {
+                match serde_json::from_str::<UserSignature>(&serialized) {
+                    Ok(signature) => signatures.push(signature),
+                    Err(e) => log::error!("Failed to deserialize signature: {e}"),
+                }
+            }
+
+            let block_post_task = BlockPostTask::from_memo(&memo, &signatures);
+            sqlx::query!(
+                r#"
+                INSERT INTO block_builder_block_post_tasks (cluster_id, is_high_priority, task, created_at)
+                VALUES ($1, TRUE, $2, $3)
+                "#,
+                self.cluster_id,
+                serde_json::to_string(&block_post_task)?,
+                current_time
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            if self.config.use_fee {
+                let fee_collection = FeeCollection {
+                    use_collateral: self.config.use_collateral,
+                    memo: memo.clone(),
+                    signatures,
+                };
+                sqlx::query!(
+                    r#"
+                    INSERT INTO block_builder_fee_collection_tasks (cluster_id, task, created_at)
+                    VALUES ($1, $2, $3)
+                    "#,
+                    self.cluster_id,
+                    serde_json::to_string(&fee_collection)?,
+                    current_time
+                )
+                .execute(&mut *tx)
+                .await?;
+            }
+
+            sqlx::query!(
+                "DELETE FROM block_builder_memos WHERE cluster_id = $1 AND block_id = $2",
+                self.cluster_id,
+                block_id
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query!(
+                "DELETE FROM block_builder_signatures WHERE cluster_id = $1 AND block_id = $2",
+                self.cluster_id,
+                block_id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        // Prune what the redis storage would have let expire
+        let cutoff = current_time - ROW_TTL_SECONDS;
+        sqlx::query!(
+            "DELETE FROM block_builder_memos WHERE cluster_id = $1 AND created_at < $2",
+            self.cluster_id,
+            cutoff
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM block_builder_signatures WHERE cluster_id = $1 AND created_at < $2",
+            self.cluster_id,
+            cutoff
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM block_builder_request_blocks WHERE cluster_id = $1 AND created_at < $2",
+            self.cluster_id,
+            cutoff
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM block_builder_expired_requests WHERE cluster_id = $1 AND expired_at < $2",
+            self.cluster_id,
+            cutoff
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM block_builder_fee_reconciliation WHERE cluster_id = $1 AND created_at < $2",
+            self.cluster_id,
+            cutoff
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn process_fee_collection(
+        &self,
+        store_vault_server_client: &dyn StoreVaultClientInterface,
+    ) -> Result<()> {
+        // The task is removed before it is processed, like BLPOP does in the redis storage
+        let serialized_fee_collection = sqlx::query_scalar!(
+            r#"
+            DELETE FROM block_builder_fee_collection_tasks
+            WHERE id = (
+                SELECT id FROM block_builder_fee_collection_tasks
+                WHERE cluster_id = $1
+                ORDER BY id
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING task
+            "#,
+            self.cluster_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        let Some(serialized_fee_collection) = serialized_fee_collection else {
+            return Ok(());
+        };
+        let fee_collection: FeeCollection = serde_json::from_str(&serialized_fee_collection)?;
+        if self.config.dry_run {
+            self.config.log_intended_fee_collection(&fee_collection);
+            return Ok(());
+        }
+        let block_post_tasks = collect_fee(
+            store_vault_server_client,
+            self.config.fee_beneficiary,
+            &fee_collection,
+        )
+        .await?;
+        record_collected_fees(self, &fee_collection, &block_post_tasks).await?;
+
+        let current_time = chrono::Utc::now().timestamp();
+        let mut tx = self.pool.begin().await?;
+        for task in block_post_tasks {
+            sqlx::query!(
+                r#"
+                INSERT INTO block_builder_block_post_tasks (cluster_id, is_high_priority, task, created_at)
+                VALUES ($1, FALSE, $2, $3)
+                "#,
+                self.cluster_id,
+                serde_json::to_string(&task)?,
+                current_time
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn enqueue_empty_block(&self) -> Result<()> {
+        let Some(deposit_check_interval) = self.config.deposit_check_interval else {
+            // if deposit check is disabled, do nothing
+            return Ok(());
+        };
+        let current_time = chrono::Utc::now().timestamp();
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO block_builder_empty_block_posts (cluster_id, posted_at) VALUES ($1, 0)
+            ON CONFLICT (cluster_id) DO NOTHING
+            "#,
+            self.cluster_id
+        )
+        .execute(&mut *tx)
+        .await?;
+        let empty_block_posted_at = sqlx::query_scalar!(
+            r#"
+            SELECT posted_at FROM block_builder_empty_block_posts
+            WHERE cluster_id = $1
+            FOR UPDATE SKIP LOCKED
+            "#,
+            self.cluster_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(empty_block_posted_at) = empty_block_posted_at else {
+            // Another instance is already enqueueing
+            return Ok(());
+        };
+
+        let multiplier = rand::thread_rng().gen_range(0.5..=1.5);
+        let deposit_check_interval = (deposit_check_interval as f64 * multiplier) as i64;
+        if empty_block_posted_at > 0
+            && current_time < empty_block_posted_at + deposit_check_interval
+        {
+            // if less than deposit_check_interval seconds have passed since the last empty block post, do nothing
+            return Ok(());
+        }
+
+        sqlx::query!(
+            r#"
+            INSERT INTO block_builder_block_post_tasks (cluster_id, is_high_priority, task, created_at)
+            VALUES ($1, FALSE, $2, $3)
+            "#,
+            self.cluster_id,
+            serde_json::to_string(&BlockPostTask::default())?,
+            current_time
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "UPDATE block_builder_empty_block_posts SET posted_at = $2 WHERE cluster_id = $1",
+            self.cluster_id,
+            current_time
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn dequeue_block_post_task(&self) -> Result<Option<BlockPostTask>> {
+        let head = sqlx::query_scalar!(
+            r#"
+            SELECT task FROM block_builder_block_post_tasks
+            WHERE cluster_id = $1 AND is_high_priority = TRUE
+            ORDER BY id
+            LIMIT 1
+            "#,
+            self.cluster_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        if let Some(head) = head {
+            let peek_task: BlockPostTask = serde_json::from_str(&head)?;
+            let is_registration = peek_task.block_sign_payload.is_registration_block;
+            let block_nonce = peek_task.block_sign_payload.block_builder_nonce;
+            let smallest_reserved_nonce = self
+                .nonce_manager
+                .smallest_reserved_nonce(is_registration)
+                .await?;
+            if smallest_reserved_nonce != Some(block_nonce) {
+                log::info!(
+                    "High-priority head nonce {} ≠ smallest {:?}. Waiting {} then processing anyway.",
+                    block_nonce,
+                    smallest_reserved_nonce,
+                    self.config.nonce_waiting_time,
+                );
+                sleep_for(self.config.nonce_waiting_time).await;
+            }
+            if let Some(task) = self.pop_high_priority_task().await? {
+                log::info!("Dequeued high-priority task: id={}", task.block_id);
+                return Ok(Some(task));
+            }
+        }
+
+        let task = self.pop_block_post_task(false).await?;
+        if let Some(task) = &task {
+            log::info!("Dequeued low-priority task: id={}", task.block_id);
+        }
+        Ok(task)
+    }
+
+    async fn reclaim_stale_nonces(&self, max_age: Duration) -> Result<()> {
+        for is_registration in [true, false] {
+            let reclaimed = self
+                .nonce_manager
+                .reclaim_stale_nonces(max_age, is_registration)
+                .await?;
+            if !reclaimed.is_empty() {
+                log::warn!(
+                    "Reclaimed stale {} nonces: {:?}",
+                    if is_registration {
+                        "registration"
+                    } else {
+                        "non-registration"
+                    },
+                    reclaimed
+                );
+            }
+        }
+        Ok(())
+    }
+
+    async fn record_collected_fee(
+        &self,
+        block_id: &str,
+        transfers: Vec<IntendedFeeTransfer>,
+    ) -> Result<Option<UnpostedFeeBlock>> {
+        if !self.config.reconcile_unposted_fees {
+            return Ok(None);
+        }
+        let flagged = self
+            .record_fee_reconciliation(block_id, Some(&serde_json::to_string(&transfers)?), None)
+            .await?;
+        Ok(flagged.map(|(_, reason)| UnpostedFeeBlock {
+            block_id: block_id.to_string(),
+            transfers,
+            reason,
+        }))
+    }
+
+    async fn record_failed_block_post(
+        &self,
+        block_id: &str,
+        reason: &str,
+    ) -> Result<Option<UnpostedFeeBlock>> {
+        if !self.config.reconcile_unposted_fees {
+            return Ok(None);
+        }
+        let flagged = self
+            .record_fee_reconciliation(block_id, None, Some(reason))
+            .await?;
+        let Some((transfers, _)) = flagged else {
+            return Ok(None);
+        };
+        Ok(Some(UnpostedFeeBlock {
+            block_id: block_id.to_string(),
+            transfers: serde_json::from_str(&transfers)?,
+            reason: reason.to_string(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use crate::app::storage::{
+        config::StorageBackend, conformance, nonce_manager::config::NonceManagerConfig,
+    };
+    use intmax2_client_sdk::external_api::contract::convert::convert_address_to_alloy;
+
+    use super::*;
+
+    #[sqlx::test]
+    async fn test_conformance(pool: PgPool) {
+        let config = conformance::test_config(StorageBackend::Postgres);
+        let nonce_config = NonceManagerConfig {
+            block_builder_address: convert_address_to_alloy(config.block_builder_address),
+            redis_url: None,
+            cluster_id: config.cluster_id.clone(),
+        };
+        let pool = DbPool::new(pool);
+        let nonce_manager =
+            PostgresNonceManager::new(nonce_config, pool.clone(), conformance::mock_rollup());
+        let storage = PostgresStorage::new(&config, pool, nonce_manager);
+        conformance::run_storage_conformance(storage).await;
+    }
+}
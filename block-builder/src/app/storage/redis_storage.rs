@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use intmax2_client_sdk::external_api::utils::{retry::with_retry, time::sleep_for};
 use intmax2_interfaces::api::store_vault_server::interface::StoreVaultClientInterface;
@@ -14,14 +14,15 @@ use tokio::sync::Mutex;
 
 use crate::app::{
     block_post::BlockPostTask,
-    fee::{collect_fee, FeeCollection},
+    fee::{collect_fee, FeeCollection, IntendedFeeTransfer, UnpostedFeeBlock},
     storage::nonce_manager::NonceManager,
-    types::{ProposalMemo, TxRequest},
+    types::{select_by_fee_priority, ProposalMemo, TxRequest},
 };
 
 use super::{
     config::StorageConfig, error::StorageError,
-    nonce_manager::redis_nonce_manager::RedisNonceManager, Storage,
+    nonce_manager::redis_nonce_manager::RedisNonceManager, record_collected_fees,
+    record_queue_depth, Storage,
 };
 
 /// Timeout for distributed locks in seconds
@@ -55,9 +56,13 @@ pub struct RedisStorage {
     request_id_to_block_id_key: String,
     memos_key: String,
     signatures_key: String,
+    expired_request_ids_key: String,
     fee_collection_tasks_key: String,
     block_post_tasks_hi_key: String,
     block_post_tasks_lo_key: String,
+    collected_fees_key: String,
+    failed_block_posts_key: String,
+    flagged_fee_blocks_key: String,
 }
 
 impl RedisStorage {
@@ -96,9 +101,13 @@ impl RedisStorage {
             request_id_to_block_id_key: format!("{prefix}:request_id_to_block_id"),
             memos_key: format!("{prefix}:memos"),
             signatures_key: format!("{prefix}:signatures"),
+            expired_request_ids_key: format!("{prefix}:expired_request_ids"),
             fee_collection_tasks_key: format!("{prefix}:fee_collection_tasks"),
             block_post_tasks_hi_key: format!("{prefix}:block_post_tasks_hi"),
             block_post_tasks_lo_key: format!("{prefix}:block_post_tasks_lo"),
+            collected_fees_key: format!("{prefix}:collected_fees"),
+            failed_block_posts_key: format!("{prefix}:failed_block_posts"),
+            flagged_fee_blocks_key: format!("{prefix}:flagged_fee_blocks"),
         }
     }
 
@@ -169,6 +178,46 @@ impl RedisStorage {
         log::debug!("Lock released: {lock_name}");
         Ok(())
     }
+
+    /// Record one half of the fee reconciliation of `block_id`, `value` in the hash `key`.
+    ///
+    /// Returns the other half, from the hash `other_key`, the first time both are recorded.
+    /// The check and the flag are done in a Lua script, so that the fee collection and the
+    /// block post of different instances cannot both flag the block.
+    async fn record_fee_reconciliation(
+        &self,
+        key: &str,
+        other_key: &str,
+        block_id: &str,
+        value: &str,
+    ) -> Result<Option<String>> {
+        let mut conn = self.get_conn().await?;
+        let script = Script::new(
+            r"
+            redis.call('hset', KEYS[1], ARGV[1], ARGV[2])
+            redis.call('expire', KEYS[1], ARGV[3])
+            local other = redis.call('hget', KEYS[2], ARGV[1])
+            if not other then
+                return false
+            end
+            if redis.call('sadd', KEYS[3], ARGV[1]) == 0 then
+                return false
+            end
+            redis.call('expire', KEYS[3], ARGV[3])
+            return other
+        ",
+        );
+        let other: Option<String> = script
+            .key(key)
+            .key(other_key)
+            .key(&self.flagged_fee_blocks_key)
+            .arg(block_id)
+            .arg(value)
+            .arg(GENERAL_KEY_TTL_SECONDS)
+            .invoke_async(&mut conn)
+            .await?;
+        Ok(other)
+    }
 }
 
 #[async_trait::async_trait(?Send)]
@@ -283,6 +332,25 @@ impl Storage for RedisStorage {
         Ok(block_proposal)
     }
 
+    /// Check if a transaction request expired
+    ///
+    /// # Arguments
+    /// * `request_id` - Transaction request ID
+    ///
+    /// # Returns
+    /// * `true` - The request was dropped because it passed its `valid_until`
+    async fn is_expired(&self, request_id: &str) -> Result<bool> {
+        let is_expired = with_retry(|| async {
+            let mut conn = self.get_conn().await?;
+            let is_expired: bool = conn
+                .hexists(&self.expired_request_ids_key, request_id)
+                .await?;
+            Result::Ok(is_expired)
+        })
+        .await?;
+        Ok(is_expired)
+    }
+
     /// Process transaction requests and create memos
     ///
     /// Processes request batch, creates proposal memo, and stores it with locking.
@@ -305,7 +373,7 @@ impl Storage for RedisStorage {
 
         // Make sure we release the lock when we're done
         // we don't use `with_retry` here because we want to ensure the lock is released quickly
-        let result = {
+        let result = 'process: {
             // Select the appropriate keys based on transaction type
             let requests_key = if is_registration {
                 &self.registration_tx_requests_key
@@ -330,32 +398,92 @@ impl Storage for RedisStorage {
 
             // Get the length of the queue
             let queue_len: usize = conn.llen(requests_key).await?;
+            record_queue_depth(is_registration, queue_len);
 
             // Check if we should process requests:
             // 1. If queue is empty, nothing to process
-            // 2. If queue is not full and we haven't waited long enough, wait for more transactions
+            // 2. If fewer than min_senders_for_immediate_post requests are queued and we haven't
+            //    waited long enough, wait for more transactions
             let current_time = chrono::Utc::now().timestamp() as u64;
-            if (queue_len < NUM_SENDERS_IN_BLOCK
-                && current_time < last_processed + self.config.accepting_tx_interval)
-                || queue_len == 0
+            if !self
+                .config
+                .should_process_requests(queue_len, last_processed, current_time)
             {
-                return Ok(());
+                break 'process Ok(());
             }
 
-            // Get up to NUM_SENDERS_IN_BLOCK requests
+            // Get up to NUM_SENDERS_IN_BLOCK requests. With fee priority ordering, the whole
+            // queue is read so that the highest-fee requests can be picked.
+            let fee_priority =
+                self.config.fee_priority_ordering && queue_len > NUM_SENDERS_IN_BLOCK;
             let num_to_process = std::cmp::min(queue_len, NUM_SENDERS_IN_BLOCK);
+            let num_to_read = if fee_priority {
+                queue_len
+            } else {
+                num_to_process
+            };
             let serialized_requests: Vec<String> = conn
-                .lrange(requests_key, 0, num_to_process as isize - 1)
+                .lrange(requests_key, 0, num_to_read as isize - 1)
                 .await?;
 
             // Deserialize requests
-            let mut tx_requests = Vec::with_capacity(num_to_process);
+            let mut tx_requests = Vec::with_capacity(serialized_requests.len());
             for serialized in &serialized_requests {
                 let request_with_timestamp: TxRequestWithTimestamp =
                     serde_json::from_str(serialized)?;
                 tx_requests.push(request_with_timestamp.request);
             }
 
+            // Use a transaction to ensure atomicity of the following operations
+            let mut pipe = redis::pipe();
+            pipe.atomic();
+
+            // Drop the requests that are past their valid_until instead of building them
+            let (expired_indices, live_indices): (Vec<usize>, Vec<usize>) =
+                (0..tx_requests.len()).partition(|&i| tx_requests[i].is_expired(current_time));
+            if !expired_indices.is_empty() {
+                for &i in &expired_indices {
+                    pipe.hset(
+                        &self.expired_request_ids_key,
+                        &tx_requests[i].request_id,
+                        current_time,
+                    );
+                    if fee_priority {
+                        pipe.lrem(requests_key, 1, &serialized_requests[i]);
+                    }
+                }
+                pipe.expire(
+                    &self.expired_request_ids_key,
+                    GENERAL_KEY_TTL_SECONDS as i64,
+                );
+                log::info!("dropped {} expired tx requests", expired_indices.len());
+            }
+            if live_indices.is_empty() {
+                if !fee_priority {
+                    pipe.ltrim(requests_key, num_to_process as isize, -1);
+                }
+                let _: () = pipe.query_async(&mut conn).await?;
+                break 'process Ok(());
+            }
+
+            // Selection only depends on the queue contents, so every instance picks the same set
+            let live_requests: Vec<TxRequest> = live_indices
+                .iter()
+                .map(|&i| tx_requests[i].clone())
+                .collect();
+            let selected_indices: Vec<usize> = if fee_priority {
+                select_by_fee_priority(&live_requests, NUM_SENDERS_IN_BLOCK)
+                    .into_iter()
+                    .map(|i| live_indices[i])
+                    .collect()
+            } else {
+                live_indices
+            };
+            let tx_requests: Vec<TxRequest> = selected_indices
+                .iter()
+                .map(|&i| tx_requests[i].clone())
+                .collect();
+
             let nonce = self.nonce_manager.reserve_nonce(is_registration).await?;
 
             // Create memo from the transaction requests
@@ -375,10 +503,6 @@ impl Storage for RedisStorage {
             // Serialize the memo for storage
             let serialized_memo = serde_json::to_string(&memo)?;
 
-            // Use a transaction to ensure atomicity of the following operations
-            let mut pipe = redis::pipe();
-            pipe.atomic();
-
             // Store memo by block ID
             pipe.hset(&self.memos_key, &memo.block_id, &serialized_memo);
             // Set TTL for memos hash
@@ -398,8 +522,15 @@ impl Storage for RedisStorage {
                 GENERAL_KEY_TTL_SECONDS as i64,
             );
 
-            // Remove processed requests from the queue
-            pipe.ltrim(requests_key, num_to_process as isize, -1);
+            // Remove processed requests from the queue. Without fee priority, every read request
+            // is either selected or expired.
+            if fee_priority {
+                for &i in &selected_indices {
+                    pipe.lrem(requests_key, 1, &serialized_requests[i]);
+                }
+            } else {
+                pipe.ltrim(requests_key, num_to_process as isize, -1);
+            }
 
             // Update last processed timestamp
             pipe.set(last_processed_key, current_time.to_string());
@@ -468,6 +599,8 @@ impl Storage for RedisStorage {
                 .map_err(|e| {
                     StorageError::AddSignatureError(format!("signature verification failed: {e}"))
                 })?;
+            self.config
+                .validate_signer_fee_tokens(&memo, signature.pubkey)?;
 
             // Serialize signature
             let serialized_signature = serde_json::to_string(&signature)?;
@@ -481,6 +614,16 @@ impl Storage for RedisStorage {
                 .expire(&signatures_key, GENERAL_KEY_TTL_SECONDS as i64)
                 .await?;
 
+            // Record the arrival time of the last signature for the adaptive signature window
+            let last_signature_at_key = format!("{signatures_key}:last_at");
+            let _: () = conn
+                .set_ex(
+                    &last_signature_at_key,
+                    chrono::Utc::now().timestamp() as u64,
+                    GENERAL_KEY_TTL_SECONDS as u64,
+                )
+                .await?;
+
             Ok(())
         })
         .await
@@ -539,8 +682,16 @@ impl Storage for RedisStorage {
                 let serialized_signatures: Vec<String> =
                     conn.lrange(&signatures_key, 0, -1).await?;
 
-                // Skip if no signatures
-                if serialized_signatures.is_empty() {
+                // Skip if no signatures, or wait for more while they are still arriving
+                let last_signature_at_key = format!("{signatures_key}:last_at");
+                let last_signature_at: Option<u64> = conn.get(&last_signature_at_key).await?;
+                if !self.config.should_post_signatures(
+                    memo.created_at,
+                    memo.tx_requests.len(),
+                    serialized_signatures.len(),
+                    last_signature_at,
+                    current_time,
+                ) {
                     continue;
                 }
 
@@ -605,6 +756,7 @@ impl Storage for RedisStorage {
                 // Remove memo and signatures
                 pipe.hdel(&self.memos_key, &block_id);
                 pipe.del(&signatures_key);
+                pipe.del(&last_signature_at_key);
 
                 // Execute the transaction
                 if let Err(e) = pipe.query_async::<()>(&mut conn).await {
@@ -645,7 +797,7 @@ impl Storage for RedisStorage {
 
         // Make sure we release the lock when we're done
         // we don't use `with_retry` here because we want to ensure the lock is released quickly
-        let result = {
+        let result = 'process: {
             let mut conn = self.get_conn().await?;
 
             // Use BLPOP with a short timeout to avoid race conditions between multiple instances
@@ -655,19 +807,33 @@ impl Storage for RedisStorage {
             // Return if there's no task
             let serialized_fee_collection = match serialized_fee_collection {
                 Some((_, value)) => value,
-                None => return Ok(()),
+                None => break 'process Ok(()),
             };
 
             // Deserialize the fee collection task
             let fee_collection: FeeCollection = serde_json::from_str(&serialized_fee_collection)?;
 
-            // Process the fee collection
-            let block_post_tasks = collect_fee(
-                store_vault_server_client,
-                self.config.fee_beneficiary,
-                &fee_collection,
-            )
-            .await?;
+            // Process the fee collection. In dry run mode, nothing is collected.
+            let block_post_tasks = if self.config.dry_run {
+                self.config.log_intended_fee_collection(&fee_collection);
+                Vec::new()
+            } else {
+                match collect_fee(
+                    store_vault_server_client,
+                    self.config.fee_beneficiary,
+                    &fee_collection,
+                )
+                .await
+                {
+                    Ok(block_post_tasks) => block_post_tasks,
+                    // break instead of returning, so that the lock is released
+                    Err(e) => break 'process Err(e.into()),
+                }
+            };
+
+            if let Err(e) = record_collected_fees(self, &fee_collection, &block_post_tasks).await {
+                break 'process Err(e);
+            }
 
             // Use a transaction to add all tasks atomically
             if !block_post_tasks.is_empty() {
@@ -703,6 +869,76 @@ impl Storage for RedisStorage {
         result
     }
 
+    async fn reclaim_stale_nonces(&self, max_age: Duration) -> Result<()> {
+        for is_registration in [true, false] {
+            let reclaimed = self
+                .nonce_manager
+                .reclaim_stale_nonces(max_age, is_registration)
+                .await?;
+            if !reclaimed.is_empty() {
+                log::warn!(
+                    "Reclaimed stale {} nonces: {:?}",
+                    if is_registration {
+                        "registration"
+                    } else {
+                        "non-registration"
+                    },
+                    reclaimed
+                );
+            }
+        }
+        Ok(())
+    }
+
+    async fn record_collected_fee(
+        &self,
+        block_id: &str,
+        transfers: Vec<IntendedFeeTransfer>,
+    ) -> Result<Option<UnpostedFeeBlock>> {
+        if !self.config.reconcile_unposted_fees {
+            return Ok(None);
+        }
+        let reason = self
+            .record_fee_reconciliation(
+                &self.collected_fees_key,
+                &self.failed_block_posts_key,
+                block_id,
+                &serde_json::to_string(&transfers)?,
+            )
+            .await?;
+        Ok(reason.map(|reason| UnpostedFeeBlock {
+            block_id: block_id.to_string(),
+            transfers,
+            reason,
+        }))
+    }
+
+    async fn record_failed_block_post(
+        &self,
+        block_id: &str,
+        reason: &str,
+    ) -> Result<Option<UnpostedFeeBlock>> {
+        if !self.config.reconcile_unposted_fees {
+            return Ok(None);
+        }
+        let transfers = self
+            .record_fee_reconciliation(
+                &self.failed_block_posts_key,
+                &self.collected_fees_key,
+                block_id,
+                reason,
+            )
+            .await?;
+        let Some(transfers) = transfers else {
+            return Ok(None);
+        };
+        Ok(Some(UnpostedFeeBlock {
+            block_id: block_id.to_string(),
+            transfers: serde_json::from_str(&transfers)?,
+            reason: reason.to_string(),
+        }))
+    }
+
     /// Enqueue empty block for deposit checking
     ///
     /// Adds empty block task if enough time passed since last check.
@@ -720,7 +956,7 @@ impl Storage for RedisStorage {
 
         // Make sure we release the lock when we're done
         // we don't use `with_retry` here because we want to ensure the lock is released quickly
-        let result = {
+        let result = 'process: {
             let mut conn = self.get_conn().await?;
 
             // Key for storing the timestamp of the last empty block post
@@ -743,7 +979,7 @@ impl Storage for RedisStorage {
                 && current_time < empty_block_posted_at + deposit_check_interval
             {
                 // Not enough time has passed, do nothing
-                return Ok(());
+                break 'process Ok(());
             }
 
             // Create a default block post task (empty block)
@@ -931,7 +1167,9 @@ pub mod test_redis_helper {
 
 #[cfg(test)]
 mod tests {
-    use crate::app::storage::nonce_manager::config::NonceManagerConfig;
+    use crate::app::storage::{
+        config::StorageBackend, conformance, nonce_manager::config::NonceManagerConfig,
+    };
     use std::panic::AssertUnwindSafe;
 
     use super::*;
@@ -960,10 +1198,21 @@ mod tests {
             tx_timeout: 80,
             accepting_tx_interval: 40,
             proposing_block_interval: 10,
+            min_signatures_to_post: NUM_SENDERS_IN_BLOCK,
+            max_proposing_block_interval: 10,
             deposit_check_interval: Some(20),
             nonce_waiting_time: 5,
+            fee_priority_ordering: false,
+            min_senders_for_immediate_post: NUM_SENDERS_IN_BLOCK,
+            fee_token_allowlist: None,
+            dry_run: false,
+            reconcile_unposted_fees: false,
+            backend: StorageBackend::Redis,
             redis_url: Some(redis_port.to_string()),
             cluster_id: Some(instance_id.to_string()),
+            database_url: None,
+            database_max_connections: 10,
+            database_timeout: 10,
             block_builder_id: Uuid::new_v4().to_string(),
         };
         let nonce_config = NonceManagerConfig {
@@ -1178,4 +1427,43 @@ mod tests {
             String::from_utf8_lossy(&output.stderr)
         );
     }
+
+    #[tokio::test]
+    async fn test_conformance() {
+        let port = find_free_port();
+        let cont_name = "redis-test-conformance";
+
+        // Run docker image
+        stop_redis_docker(cont_name);
+        let output = run_redis_docker(port, cont_name);
+        assert!(
+            output.status.success(),
+            "Couldn't start {}: {}",
+            cont_name,
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        // A failed check leaves the container running until the next run stops it
+        let config = StorageConfig {
+            redis_url: Some(format!("redis://localhost:{port}")),
+            ..conformance::test_config(StorageBackend::Redis)
+        };
+        let nonce_config = NonceManagerConfig {
+            block_builder_address: convert_address_to_alloy(config.block_builder_address),
+            redis_url: config.redis_url.clone(),
+            cluster_id: config.cluster_id.clone(),
+        };
+        let nonce_manager = RedisNonceManager::new(nonce_config, conformance::mock_rollup()).await;
+        let redis_storage = RedisStorage::new(&config, nonce_manager).await;
+        conformance::run_storage_conformance(redis_storage).await;
+
+        // Stop docker image
+        let output = stop_redis_docker(cont_name);
+        assert!(
+            output.status.success(),
+            "Couldn't stop {}: {}",
+            cont_name,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
 }
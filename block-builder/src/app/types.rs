@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use intmax2_interfaces::api::block_builder::interface::FeeProof;
 use intmax2_zkp::{
     common::{
@@ -24,6 +26,22 @@ pub struct TxRequest {
     pub account_id: Option<AccountId>,
     pub tx: Tx,
     pub fee_proof: Option<FeeProof>,
+    /// Fee amount offered by the sender. Zero if the block builder does not require fees.
+    #[serde(default)]
+    pub fee: U256,
+    /// Token of `fee`. Amounts of different tokens are not comparable.
+    #[serde(default)]
+    pub fee_token_index: u32,
+    /// Unix timestamp after which the request is dropped instead of built.
+    #[serde(default)]
+    pub valid_until: Option<u64>,
+}
+
+impl TxRequest {
+    pub fn is_expired(&self, current_time: u64) -> bool {
+        self.valid_until
+            .is_some_and(|valid_until| valid_until < current_time)
+    }
 }
 
 impl Default for TxRequest {
@@ -34,10 +52,41 @@ impl Default for TxRequest {
             account_id: Some(AccountId::dummy()),
             tx: Tx::default(),
             fee_proof: None,
+            fee: U256::default(),
+            fee_token_index: 0,
+            valid_until: None,
         }
     }
 }
 
+/// Select up to `limit` tx requests from a queue, preferring higher offered fees.
+///
+/// Fees are only compared within the same fee token, since raw amounts of different tokens are
+/// not comparable. The requests of each token are ranked by fee, and the requests are taken
+/// rank by rank, so the highest bidders of every token come first. Requests with equal ranks
+/// are taken in queue order, so the selection is fully determined by the queue contents.
+/// Returns the queue positions of the selected requests in ascending order.
+///
+/// # Arguments
+/// * `tx_requests` - Queued transaction requests, oldest first
+/// * `limit` - Maximum number of requests to select
+pub fn select_by_fee_priority(tx_requests: &[TxRequest], limit: usize) -> Vec<usize> {
+    let mut by_token: HashMap<u32, Vec<usize>> = HashMap::new();
+    for (i, r) in tx_requests.iter().enumerate() {
+        by_token.entry(r.fee_token_index).or_default().push(i);
+    }
+    let mut ranked: Vec<(usize, usize)> = Vec::with_capacity(tx_requests.len());
+    for mut indices in by_token.into_values() {
+        // stable sort keeps FIFO order among equal fees
+        indices.sort_by(|&a, &b| tx_requests[b].fee.cmp(&tx_requests[a].fee));
+        ranked.extend(indices.into_iter().enumerate());
+    }
+    ranked.sort_unstable();
+    let mut indices: Vec<usize> = ranked.into_iter().take(limit).map(|(_, i)| i).collect();
+    indices.sort_unstable();
+    indices
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProposalMemo {
     pub created_at: u64,
@@ -174,6 +223,49 @@ mod tests {
 
     use num_bigint::BigUint;
 
+    #[test]
+    fn test_select_by_fee_priority() {
+        let fees = [3u32, 7, 1, 7, 5];
+        let tx_requests: Vec<TxRequest> = fees
+            .iter()
+            .map(|&fee| TxRequest {
+                fee: U256::from(fee),
+                ..Default::default()
+            })
+            .collect();
+
+        // highest fees first, earlier request wins the tie at 7
+        assert_eq!(select_by_fee_priority(&tx_requests, 3), vec![1, 3, 4]);
+        assert_eq!(select_by_fee_priority(&tx_requests, 1), vec![1]);
+        assert_eq!(
+            select_by_fee_priority(&tx_requests, 10),
+            vec![0, 1, 2, 3, 4]
+        );
+
+        // all equal fees falls back to FIFO
+        let tx_requests = vec![TxRequest::default(); 4];
+        assert_eq!(select_by_fee_priority(&tx_requests, 2), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_select_by_fee_priority_across_tokens() {
+        // a huge amount of a low value token does not outbid the other token
+        let fees = [(0u32, 5u32), (1, 1_000_000), (0, 9), (1, 2_000_000), (0, 7)];
+        let tx_requests: Vec<TxRequest> = fees
+            .iter()
+            .map(|&(fee_token_index, fee)| TxRequest {
+                fee: U256::from(fee),
+                fee_token_index,
+                ..Default::default()
+            })
+            .collect();
+
+        // the best request of each token first, then the second best of each token
+        assert_eq!(select_by_fee_priority(&tx_requests, 2), vec![2, 3]);
+        assert_eq!(select_by_fee_priority(&tx_requests, 4), vec![1, 2, 3, 4]);
+        assert_eq!(select_by_fee_priority(&tx_requests, 3), vec![1, 2, 3]);
+    }
+
     #[test]
     fn test_basic_from_tx_requests() {
         let pubkey = U256::from(1);
@@ -1,3 +1,4 @@
+use crate::app::storage::config::StorageBackend;
 use alloy::primitives::{Address, B256};
 use intmax2_zkp::ethereum_types::bytes32::Bytes32;
 use serde::Deserialize;
@@ -9,8 +10,14 @@ pub mod app;
 pub struct EnvVar {
     pub port: u16,
     pub block_builder_url: String,
+    // memory, redis or postgres. Defaults to redis if redis_url is set, otherwise memory
+    pub storage_backend: Option<StorageBackend>,
     pub redis_url: Option<String>,
     pub cluster_id: Option<String>,
+    // Postgres storage configuration
+    pub database_url: Option<String>,
+    pub database_max_connections: Option<u32>,
+    pub database_timeout: Option<u64>,
     pub l2_rpc_url: String,
     pub rollup_contract_address: Address,
     pub block_builder_registry_contract_address: Address,
@@ -30,10 +37,18 @@ pub struct EnvVar {
     pub gas_limit_for_block_post: Option<u64>,
     pub heart_beat_interval: u64,
     pub nonce_waiting_time: Option<u64>,
+    pub nonce_reclaim_max_age: Option<u64>,
+    pub fee_priority_ordering: Option<bool>,
+    pub min_senders_for_immediate_post: Option<usize>,
+    pub min_signatures_to_post: Option<usize>,
+    pub max_proposing_block_interval: Option<u64>,
+    pub fee_collection_dry_run: Option<bool>,
+    pub reconcile_unposted_fees: Option<bool>,
 
     pub beneficiary_pubkey: Option<Bytes32>,
     pub registration_fee: Option<String>,
     pub non_registration_fee: Option<String>,
     pub registration_collateral_fee: Option<String>,
     pub non_registration_collateral_fee: Option<String>,
+    pub fee_token_allowlist: Option<String>,
 }
@@ -1,12 +1,13 @@
-use actix_cors::Cors;
-use actix_web::{web::Data, App, HttpServer};
+use actix_web::{middleware::from_fn, web::Data, App, HttpServer};
 use block_builder::{
     api::{routes::block_builder_scope, state::State},
     EnvVar,
 };
 use server_common::{
+    cors::CorsConfig,
     health_check::{health_check, set_name_and_version},
     logger,
+    metrics::track_requests,
 };
 use std::io;
 use tracing_actix_web::TracingLogger;
@@ -25,11 +26,14 @@ async fn main() -> std::io::Result<()> {
     state.run();
 
     let data = Data::new(state);
+    let cors_config = CorsConfig::from_env().map_err(io::Error::other)?;
+
     HttpServer::new(move || {
-        let cors = Cors::permissive();
+        let cors = cors_config.cors();
         App::new()
             .wrap(cors)
             .wrap(TracingLogger::<logger::CustomRootSpanBuilder>::new())
+            .wrap(from_fn(track_requests))
             .app_data(data.clone())
             .service(health_check)
             .service(block_builder_scope())
@@ -1,17 +1,24 @@
 use alloy::primitives::B256;
 use clap::{Parser, Subcommand};
+use intmax2_client_sdk::external_api::contract::handlers::GasStrategy;
 use intmax2_interfaces::{
     api::store_vault_server::types::CursorOrder, data::deposit_data::TokenType,
 };
 use intmax2_zkp::ethereum_types::{address::Address, bytes32::Bytes32, u256::U256};
 use std::path::PathBuf;
 
+use crate::format::{parse_gas_strategy, AmountUnit};
+
 #[derive(Parser)]
 #[clap(name = "intmax2_cli")]
 #[clap(about = "Intmax2 CLI tool")]
 pub struct Args {
     #[clap(subcommand)]
     pub command: Commands,
+
+    /// Print the output of get/status commands as JSON
+    #[clap(long, global = true, default_value = "false")]
+    pub json: bool,
 }
 
 #[derive(Subcommand)]
@@ -21,12 +28,19 @@ pub enum Commands {
         private_key: Bytes32,
         #[clap(long)]
         to: Bytes32,
+        /// Amount to send. Zero sends only the memo, the fee is still paid
         #[clap(long)]
         amount: U256,
         #[clap(long)]
         token_index: u32,
         #[clap(long)]
         fee_token_index: Option<u32>,
+        /// Payment memo attached to the transfer
+        #[clap(long)]
+        memo: Option<String>,
+        /// Name of the payment memo, used to look it up with `payment-memos`
+        #[clap(long, default_value = "default")]
+        memo_name: String,
         #[clap(long, default_value = "false")]
         wait: bool,
     },
@@ -51,10 +65,16 @@ pub enum Commands {
         private_key: Bytes32,
         #[clap(long)]
         csv_path: String,
+        /// TOML or JSON file mapping names to addresses, referenced as `@name` in the CSV
+        #[clap(long)]
+        address_book: Option<String>,
         #[clap(long)]
         fee_token_index: Option<u32>,
         #[clap(long, default_value = "false")]
         wait: bool,
+        /// Split the transfers into multiple txs if they exceed the per-tx limit
+        #[clap(long, default_value = "false")]
+        auto_split: bool,
     },
     Deposit {
         #[clap(long)]
@@ -63,14 +83,33 @@ pub enum Commands {
         private_key: Bytes32,
         #[clap(long)]
         token_type: TokenType,
-        #[clap(long)]
-        amount: Option<U256>,
+        /// Comma separated list of amounts. Multiple amounts are only supported for ERC1155
+        #[clap(long, value_delimiter = ',')]
+        amount: Vec<String>,
+        /// Unit of native and ERC20 amounts: `base` for the smallest unit of the token, `token`
+        /// for whole tokens, e.g. 1.5
+        #[clap(long, value_enum, default_value = "base")]
+        unit: AmountUnit,
         #[clap(long)]
         token_address: Option<Address>,
-        #[clap(long)]
-        token_id: Option<U256>,
+        /// Comma separated list of token ids, parallel to `amount` for ERC1155
+        #[clap(long, value_delimiter = ',')]
+        token_id: Vec<U256>,
         #[clap(long, default_value = "false")]
         mining: bool,
+        /// `fixed:<max_fee_gwei>:<priority_fee_gwei>`, `multiplier:<multiplier>` (times the base
+        /// fee) or `capped:<max_fee_gwei>`. The fees are estimated by the provider if omitted
+        #[clap(long, value_parser = parse_gas_strategy)]
+        gas_strategy: Option<GasStrategy>,
+        /// Nonce of a stuck deposit to replace, together with a higher `--gas-strategy`
+        #[clap(long)]
+        nonce: Option<u64>,
+        /// Wait until the validity prover observes the deposits
+        #[clap(long, default_value = "false")]
+        wait: bool,
+        /// Seconds to wait with `--wait`, `DEPOSIT_TIMEOUT` if omitted
+        #[clap(long)]
+        wait_timeout: Option<u64>,
     },
     SyncWithdrawals {
         #[clap(long)]
@@ -107,6 +146,12 @@ pub enum Commands {
     WithdrawalStatus {
         #[clap(long)]
         private_key: Bytes32,
+        /// Keep polling and reprint on status changes until every entry is in a terminal state
+        #[clap(long, default_value = "false")]
+        watch: bool,
+        /// Polling interval of `--watch` in seconds
+        #[clap(long, default_value = "10")]
+        interval_secs: u64,
     },
     MiningList {
         #[clap(long)]
@@ -115,12 +160,25 @@ pub enum Commands {
     ClaimStatus {
         #[clap(long)]
         private_key: Bytes32,
+        /// Keep polling and reprint on status changes until every entry is in a terminal state
+        #[clap(long, default_value = "false")]
+        watch: bool,
+        /// Polling interval of `--watch` in seconds
+        #[clap(long, default_value = "10")]
+        interval_secs: u64,
     },
     ClaimWithdrawals {
         #[clap(long)]
         private_key: Bytes32,
         #[clap(long)]
         eth_private_key: Bytes32,
+        /// `fixed:<max_fee_gwei>:<priority_fee_gwei>`, `multiplier:<multiplier>` (times the base
+        /// fee) or `capped:<max_fee_gwei>`. The fees are estimated by the provider if omitted
+        #[clap(long, value_parser = parse_gas_strategy)]
+        gas_strategy: Option<GasStrategy>,
+        /// Nonce of a stuck claim to replace, together with a higher `--gas-strategy`
+        #[clap(long)]
+        nonce: Option<u64>,
     },
     PaymentMemos {
         #[clap(long)]
@@ -128,6 +186,10 @@ pub enum Commands {
         #[clap(long)]
         name: String,
     },
+    ListTopics {
+        #[clap(long)]
+        private_key: Bytes32,
+    },
     ClaimBuilderReward {
         #[clap(long)]
         eth_private_key: Bytes32,
@@ -137,6 +199,12 @@ pub enum Commands {
         private_key: Bytes32,
         #[clap(long, default_value = "false")]
         deep: bool,
+        /// Continue an interrupted deep resync instead of starting a new one. Implies --deep
+        #[clap(long, default_value = "false", conflicts_with = "token_index")]
+        resume: bool,
+        /// Only resync the receives of this token index
+        #[clap(long)]
+        token_index: Option<u32>,
     },
     MakeBackup {
         #[clap(long)]
@@ -145,12 +213,61 @@ pub enum Commands {
         dir: Option<PathBuf>,
         #[clap(long)]
         from: Option<u64>,
+        /// Resume from the checkpoint left in `dir` by a previous run
+        #[clap(long, default_value = "false")]
+        resume: bool,
+        /// Encrypt the backup to the RSA public key in this PEM file
+        #[clap(long)]
+        rsa_public_key_path: Option<PathBuf>,
     },
     IncorporateBackup {
         #[clap(long)]
         path: PathBuf,
+        /// Reject the whole backup if any entry is corrupt
+        #[clap(long, default_value = "false")]
+        strict: bool,
+        /// Hex-encoded AES key unwrapped from an RSA-encrypted backup
+        #[clap(long)]
+        aes_key: Option<String>,
+    },
+    /// Merge plain CSV backups made on different devices into one deduplicated backup
+    MergeBackups {
+        #[clap(long)]
+        private_key: Bytes32,
+        /// Comma separated list of directories holding the backups to merge
+        #[clap(long, value_delimiter = ',')]
+        input_dirs: Vec<PathBuf>,
+        /// Output directory of the merged backup
+        #[clap(long)]
+        dir: PathBuf,
+    },
+    /// Write the user data and balance proof, encrypted to a support RSA key, for debugging
+    DumpState {
+        #[clap(long)]
+        private_key: Bytes32,
+        /// The support RSA public key in PEM format
+        #[clap(long)]
+        rsa_public_key_path: PathBuf,
+        /// Output file, `state_dump_<timestamp>.json` if omitted
+        #[clap(long)]
+        path: Option<PathBuf>,
     },
     CheckValidityProver,
+    /// Check the local state against the validity prover before reporting a balance issue
+    AuditConsistency {
+        #[clap(long)]
+        private_key: Bytes32,
+    },
+    CompareRoots {
+        #[clap(long)]
+        prover_url_a: String,
+        #[clap(long)]
+        prover_url_b: String,
+        #[clap(long)]
+        from: u32,
+        #[clap(long)]
+        to: u32,
+    },
     GenerateKey,
     PublicKey {
         #[clap(long)]
@@ -163,6 +280,11 @@ pub enum Commands {
         redeposit_index: Option<u32>,
         #[clap(long)]
         wallet_index: Option<u32>,
+        /// Custom derivation context folded into the key derivation. Any value, even an empty
+        /// one, derives a completely different account, which can only be recovered with the
+        /// exact same context.
+        #[clap(long)]
+        derivation_context: Option<String>,
     },
     KeyFromBackupKey {
         #[clap(long)]
@@ -1,7 +1,11 @@
 use crate::env_var::EnvVar;
-use intmax2_client_sdk::external_api::local_backup_store_vault::local_store_vault::LocalStoreVaultClient;
-use intmax2_zkp::common::signature_content::key_set::KeySet;
-use std::path::Path;
+use intmax2_client_sdk::{
+    client::backup::{merge_history_backups, parse_rsa_public_key_pem, EncryptedBackupChunk},
+    external_api::local_backup_store_vault::local_store_vault::LocalStoreVaultClient,
+};
+use intmax2_interfaces::data::meta_data::MetaData;
+use intmax2_zkp::{common::signature_content::key_set::KeySet, ethereum_types::bytes32::Bytes32};
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
 use super::{
@@ -10,25 +14,158 @@ use super::{
 };
 
 const BACKUP_CHUNK_SIZE: usize = 1000;
+const BACKUP_CHECKPOINT_FILE: &str = "backup_checkpoint.json";
 
-pub fn incorporate_backup(file_path: &Path) -> Result<(), CliError> {
+/// Incorporates the backup file into the local store. In strict mode, the whole file is
+/// rejected if any record is corrupt. Otherwise, corrupt records are skipped.
+/// RSA-encrypted backups are decrypted with `aes_key`, the AES key unwrapped by the holder of
+/// the RSA private key.
+pub fn incorporate_backup(
+    file_path: &Path,
+    strict: bool,
+    aes_key: Option<&[u8]>,
+) -> Result<(), CliError> {
     let env = envy::from_env::<EnvVar>()?;
     let root_path = get_backup_root_path(&env)?;
-    let local_store_vault = LocalStoreVaultClient::new(root_path);
-    local_store_vault.incorporate_diff(file_path)?;
+    let local_store_vault = match &env.local_backup_passphrase {
+        Some(passphrase) => LocalStoreVaultClient::new_encrypted(root_path, passphrase),
+        None => LocalStoreVaultClient::new(root_path),
+    };
+    let content = std::fs::read_to_string(file_path)
+        .map_err(|e| CliError::BackupError(format!("Failed to read file: {e}")))?;
+    let csv_content = match EncryptedBackupChunk::parse(&content) {
+        Some(encrypted) => {
+            let aes_key = aes_key.ok_or(CliError::BackupError(
+                "The backup is RSA-encrypted. Specify the unwrapped AES key".to_string(),
+            ))?;
+            encrypted
+                .decrypt_with_aes_key(aes_key)
+                .map_err(|e| CliError::BackupError(e.to_string()))?
+        }
+        None => content,
+    };
+    if strict {
+        local_store_vault.incorporate_diff_content(&csv_content)?;
+    } else {
+        let report = local_store_vault.incorporate_diff_content_incremental(&csv_content)?;
+        println!(
+            "Imported {} entries, skipped {} entries",
+            report.imported, report.skipped
+        );
+    }
     Ok(())
 }
 
-pub async fn make_history_backup(key: KeySet, dir: &Path, from: u64) -> Result<(), CliError> {
+/// Writes the history backup to `dir` chunk by chunk. After each chunk is written, its cursor
+/// is saved to a checkpoint file in `dir`, so that the backup can be resumed with `resume`
+/// if it is interrupted.
+pub async fn make_history_backup(
+    key: KeySet,
+    dir: &Path,
+    from: u64,
+    resume: bool,
+    rsa_public_key_path: Option<&Path>,
+) -> Result<(), CliError> {
     let client = get_client()?;
-    let csvs = client
-        .make_history_backup(key, from, BACKUP_CHUNK_SIZE)
+    let rsa_pubkey_pem = rsa_public_key_path
+        .map(std::fs::read_to_string)
+        .transpose()
+        .map_err(|e| CliError::BackupError(format!("Failed to read RSA public key: {e}")))?;
+    let checkpoint_path = dir.join(BACKUP_CHECKPOINT_FILE);
+    let cursor = match read_checkpoint(&checkpoint_path)? {
+        Some(cursor) if resume => {
+            log::info!("Resuming backup from timestamp {}", cursor.timestamp);
+            cursor
+        }
+        _ => MetaData {
+            timestamp: from,
+            digest: Bytes32::default(),
+        },
+    };
+    let chunks = client
+        .make_history_backup_from_cursor(key, cursor, BACKUP_CHUNK_SIZE, rsa_pubkey_pem.as_deref())
         .await?;
-    for csv_str in csvs.iter() {
+    let extension = if rsa_pubkey_pem.is_some() {
+        "json"
+    } else {
+        "csv"
+    };
+    for chunk in chunks.iter() {
         let id = Uuid::new_v4().to_string()[..8].to_string();
-        let file_path = dir.join(format!("backup_{id}.csv"));
-        std::fs::write(file_path, csv_str)
+        let file_path = dir.join(format!("backup_{id}.{extension}"));
+        std::fs::write(file_path, &chunk.content)
+            .map_err(|e| CliError::BackupError(format!("Failed to write file: {e}")))?;
+        let checkpoint = serde_json::to_string(&chunk.cursor)?;
+        std::fs::write(&checkpoint_path, checkpoint)
+            .map_err(|e| CliError::BackupError(format!("Failed to write checkpoint: {e}")))?;
+    }
+    Ok(())
+}
+
+/// Merges the plain CSV backups in `input_dirs`, e.g. made on different devices, into a single
+/// deduplicated backup written to `dir`.
+pub fn merge_backups(key: KeySet, input_dirs: &[PathBuf], dir: &Path) -> Result<(), CliError> {
+    let mut inputs = Vec::new();
+    for input_dir in input_dirs {
+        let mut paths = std::fs::read_dir(input_dir)
+            .map_err(|e| CliError::BackupError(format!("Failed to read directory: {e}")))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "csv"))
+            .collect::<Vec<_>>();
+        paths.sort();
+        let chunks = paths
+            .iter()
+            .map(std::fs::read_to_string)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| CliError::BackupError(format!("Failed to read file: {e}")))?;
+        inputs.push(chunks);
+    }
+    let chunks = merge_history_backups(key, inputs, BACKUP_CHUNK_SIZE)
+        .map_err(|e| CliError::BackupError(e.to_string()))?;
+    std::fs::create_dir_all(dir)
+        .map_err(|e| CliError::BackupError(format!("Failed to create directory: {e}")))?;
+    for (i, chunk) in chunks.iter().enumerate() {
+        let file_path = dir.join(format!("backup_merged_{i:04}.csv"));
+        std::fs::write(file_path, chunk)
             .map_err(|e| CliError::BackupError(format!("Failed to write file: {e}")))?;
     }
+    println!("Wrote {} merged chunks to {}", chunks.len(), dir.display());
     Ok(())
 }
+
+/// Writes the user data and head balance proof of `key`, encrypted to the support RSA public
+/// key, to `path` for support debugging. The private key is not included.
+pub async fn dump_state(
+    key: KeySet,
+    rsa_public_key_path: &Path,
+    path: Option<&Path>,
+) -> Result<(), CliError> {
+    let client = get_client()?;
+    let rsa_pubkey_pem = std::fs::read_to_string(rsa_public_key_path)
+        .map_err(|e| CliError::BackupError(format!("Failed to read RSA public key: {e}")))?;
+    let rsa_pubkey = parse_rsa_public_key_pem(&rsa_pubkey_pem)
+        .map_err(|e| CliError::BackupError(e.to_string()))?;
+    let dump = client.make_state_dump(key, &rsa_pubkey).await?;
+    let default_path = format!("state_dump_{}.json", dump.created_at);
+    let path = path.unwrap_or(Path::new(&default_path));
+    std::fs::write(path, serde_json::to_string_pretty(&dump)?)
+        .map_err(|e| CliError::BackupError(format!("Failed to write file: {e}")))?;
+    println!(
+        "Wrote {} (validity prover block: {}, balance proof block: {})",
+        path.display(),
+        dump.validity_prover_block_number,
+        dump.balance_proof_block_number
+            .map_or("N/A".to_string(), |b| b.to_string())
+    );
+    Ok(())
+}
+
+fn read_checkpoint(path: &Path) -> Result<Option<MetaData>, CliError> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let checkpoint = std::fs::read_to_string(path)
+        .map_err(|e| CliError::BackupError(format!("Failed to read checkpoint: {e}")))?;
+    let cursor = serde_json::from_str(&checkpoint)?;
+    Ok(Some(cursor))
+}
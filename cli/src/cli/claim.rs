@@ -2,6 +2,7 @@ use alloy::primitives::U256;
 use intmax2_client_sdk::external_api::contract::{
     block_builder_reward::BlockBuilderRewardContract,
     convert::{convert_address_to_alloy, convert_bytes32_to_b256},
+    handlers::GasOptions,
     utils::get_address_from_private_key,
 };
 use intmax2_interfaces::api::withdrawal_server::interface::WithdrawalStatus;
@@ -11,7 +12,11 @@ use crate::{cli::client::get_client, env_var::EnvVar};
 
 use super::error::CliError;
 
-pub async fn claim_withdrawals(key: KeySet, eth_private_key: Bytes32) -> Result<(), CliError> {
+pub async fn claim_withdrawals(
+    key: KeySet,
+    eth_private_key: Bytes32,
+    gas_options: GasOptions,
+) -> Result<(), CliError> {
     let signer_private_key = convert_bytes32_to_b256(eth_private_key);
     let client = get_client()?;
     let withdrawal_info = client.get_withdrawal_info(key).await?;
@@ -43,7 +48,7 @@ pub async fn claim_withdrawals(key: KeySet, eth_private_key: Bytes32) -> Result<
     }
     let liquidity_contract = client.liquidity_contract.clone();
     liquidity_contract
-        .claim_withdrawals(signer_private_key, None, &claim_withdrawals)
+        .claim_withdrawals(signer_private_key, None, gas_options, &claim_withdrawals)
         .await?;
     Ok(())
 }
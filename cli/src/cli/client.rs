@@ -1,7 +1,14 @@
 use std::{path::PathBuf, sync::Arc};
 
 use intmax2_client_sdk::{
-    client::{client::Client, config::ClientConfig},
+    client::{
+        client::Client,
+        config::{
+            ClientConfig, DEFAULT_BALANCE_PROVER_WARMUP_RETRIES, DEFAULT_PROPOSAL_EXPIRY_MARGIN,
+            DEFAULT_RECEIVE_CONCURRENCY,
+        },
+        token_info::TokenInfoCache,
+    },
     external_api::{
         balance_prover::BalanceProverClient,
         block_builder::BlockBuilderClient,
@@ -16,6 +23,7 @@ use intmax2_client_sdk::{
         private_zkp_server::{PrivateZKPServerClient, PrivateZKPServerConfig},
         s3_store_vault::S3StoreVaultClient,
         store_vault_server::StoreVaultServerClient,
+        utils::retry::RetryConfig,
         validity_prover::ValidityProverClient,
         withdrawal_server::WithdrawalServerClient,
     },
@@ -31,7 +39,12 @@ use super::error::CliError;
 
 pub fn get_client() -> Result<Client, CliError> {
     let env = envy::from_env::<EnvVar>()?;
-    let block_builder = Box::new(BlockBuilderClient::new());
+    let retry_config = RetryConfig::from_options(
+        env.retry_max_retries,
+        env.retry_initial_delay_ms,
+        env.retry_jitter_ms,
+    );
+    let block_builder = Box::new(BlockBuilderClient::new().with_retry_config(retry_config));
 
     let root_path = get_backup_root_path(&env)?;
     if env.store_vault_type != StoreVaultType::Local && env.store_vault_server_base_url.is_none() {
@@ -40,47 +53,63 @@ pub fn get_client() -> Result<Client, CliError> {
         ));
     }
     let store_vault_server: Box<dyn StoreVaultClientInterface> = match env.store_vault_type {
-        StoreVaultType::Local => Box::new(LocalStoreVaultClient::new(root_path)),
-        StoreVaultType::LegacyRemote => Box::new(StoreVaultServerClient::new(
-            &env.store_vault_server_base_url.unwrap(),
-        )),
-        StoreVaultType::Remote => Box::new(S3StoreVaultClient::new(
-            &env.store_vault_server_base_url.unwrap(),
-        )),
+        StoreVaultType::Local => Box::new(match &env.local_backup_passphrase {
+            Some(passphrase) => LocalStoreVaultClient::new_encrypted(root_path, passphrase),
+            None => LocalStoreVaultClient::new(root_path),
+        }),
+        StoreVaultType::LegacyRemote => Box::new(
+            StoreVaultServerClient::new(&env.store_vault_server_base_url.unwrap())
+                .with_retry_config(retry_config),
+        ),
+        StoreVaultType::Remote => Box::new(
+            S3StoreVaultClient::new(&env.store_vault_server_base_url.unwrap())
+                .with_retry_config(retry_config),
+        ),
         StoreVaultType::RemoteWithBackup => {
             let inner_store_vault_server: Box<dyn StoreVaultClientInterface> = Box::new(
-                S3StoreVaultClient::new(&env.store_vault_server_base_url.unwrap()),
+                S3StoreVaultClient::new(&env.store_vault_server_base_url.unwrap())
+                    .with_retry_config(retry_config),
             );
-            Box::new(LocalBackupStoreVaultClient::new(
-                Arc::new(inner_store_vault_server),
+            Box::new(new_local_backup_store_vault(
+                inner_store_vault_server,
                 root_path,
+                env.local_backup_passphrase.as_deref(),
             ))
         }
         StoreVaultType::LegacyRemoteWithBackup => {
             let inner_store_vault_server: Box<dyn StoreVaultClientInterface> = Box::new(
-                StoreVaultServerClient::new(&env.store_vault_server_base_url.unwrap()),
+                StoreVaultServerClient::new(&env.store_vault_server_base_url.unwrap())
+                    .with_retry_config(retry_config),
             );
-            Box::new(LocalBackupStoreVaultClient::new(
-                Arc::new(inner_store_vault_server),
+            Box::new(new_local_backup_store_vault(
+                inner_store_vault_server,
                 root_path,
+                env.local_backup_passphrase.as_deref(),
             ))
         }
     };
-    let validity_prover = Box::new(ValidityProverClient::new(&env.validity_prover_base_url));
-    let balance_prover: Box<dyn BalanceProverClientInterface> =
-        if env.use_private_zkp_server.unwrap_or(true) {
-            let private_zkp_server_config = PrivateZKPServerConfig {
-                max_retries: env.private_zkp_server_max_retires.unwrap_or(30),
-                retry_interval: env.private_zkp_server_retry_interval.unwrap_or(5),
-            };
-            Box::new(PrivateZKPServerClient::new(
-                &env.balance_prover_base_url,
-                &private_zkp_server_config,
-            ))
-        } else {
-            Box::new(BalanceProverClient::new(&env.balance_prover_base_url))
+    let validity_prover = Box::new(
+        ValidityProverClient::new(&env.validity_prover_base_url).with_retry_config(retry_config),
+    );
+    let use_private_zkp_server = env.use_private_zkp_server.unwrap_or(true);
+    let balance_prover: Box<dyn BalanceProverClientInterface> = if use_private_zkp_server {
+        let private_zkp_server_config = PrivateZKPServerConfig {
+            max_retries: env.private_zkp_server_max_retires.unwrap_or(30),
+            retry_interval: env.private_zkp_server_retry_interval.unwrap_or(5),
         };
-    let withdrawal_server = Box::new(WithdrawalServerClient::new(&env.withdrawal_server_base_url));
+        Box::new(
+            PrivateZKPServerClient::new(&env.balance_prover_base_url, &private_zkp_server_config)
+                .with_retry_config(retry_config),
+        )
+    } else {
+        Box::new(
+            BalanceProverClient::new(&env.balance_prover_base_url).with_retry_config(retry_config),
+        )
+    };
+    let withdrawal_server = Box::new(
+        WithdrawalServerClient::new(&env.withdrawal_server_base_url)
+            .with_retry_config(retry_config),
+    );
 
     let l1_provider = get_provider_with_fallback(std::slice::from_ref(&env.l1_rpc_url))?;
     let l2_provider = get_provider_with_fallback(std::slice::from_ref(&env.l2_rpc_url))?;
@@ -105,6 +134,25 @@ pub fn get_client() -> Result<Client, CliError> {
         block_builder_query_interval: env.block_builder_query_interval,
         block_builder_query_limit: env.block_builder_query_limit,
         is_faster_mining: env.is_faster_mining,
+        receive_concurrency: env
+            .receive_concurrency
+            .unwrap_or(DEFAULT_RECEIVE_CONCURRENCY),
+        balance_prover_warmup_retries: env
+            .balance_prover_warmup_retries
+            .unwrap_or(DEFAULT_BALANCE_PROVER_WARMUP_RETRIES),
+        deposit_confirmations: env.deposit_confirmations.unwrap_or(0),
+        compress_payloads: env.compress_payloads.unwrap_or(false),
+        proposal_expiry_margin: env
+            .proposal_expiry_margin
+            .unwrap_or(DEFAULT_PROPOSAL_EXPIRY_MARGIN),
+        max_transfer_amount: env
+            .max_transfer_amount
+            .as_deref()
+            .map(|caps| {
+                caps.parse()
+                    .map_err(|e| CliError::EnvError(format!("invalid MAX_TRANSFER_AMOUNT: {e}")))
+            })
+            .transpose()?,
     };
 
     let client = Client {
@@ -116,12 +164,26 @@ pub fn get_client() -> Result<Client, CliError> {
         liquidity_contract,
         rollup_contract,
         withdrawal_contract,
+        token_info_cache: TokenInfoCache::default(),
         config,
     };
 
     Ok(client)
 }
 
+fn new_local_backup_store_vault(
+    store_vault: Box<dyn StoreVaultClientInterface>,
+    root_path: PathBuf,
+    passphrase: Option<&str>,
+) -> LocalBackupStoreVaultClient {
+    match passphrase {
+        Some(passphrase) => {
+            LocalBackupStoreVaultClient::new_encrypted(Arc::new(store_vault), root_path, passphrase)
+        }
+        None => LocalBackupStoreVaultClient::new(Arc::new(store_vault), root_path),
+    }
+}
+
 pub fn get_backup_root_path(env: &EnvVar) -> Result<PathBuf, CliError> {
     let root_path = env.local_backup_path.clone().map_or_else(
         || {
@@ -1,15 +1,19 @@
 use alloy::providers::Provider;
 use intmax2_client_sdk::{
-    client::client::Client,
+    client::{
+        client::{Client, DepositResult},
+        error::ClientError,
+    },
     external_api::{
         contract::{
             convert::{
                 convert_address_to_alloy, convert_address_to_intmax, convert_bytes32_to_b256,
-                convert_u256_to_alloy,
+                convert_u256_to_alloy, convert_u256_to_intmax,
             },
             erc1155_contract::ERC1155Contract,
             erc20_contract::ERC20Contract,
             erc721_contract::ERC721Contract,
+            handlers::GasOptions,
             liquidity_contract::LiquidityContract,
             utils::get_address_from_private_key,
         },
@@ -26,29 +30,119 @@ use crate::env_var::EnvVar;
 
 use super::{client::get_client, error::CliError, utils::is_local};
 
+/// Deposit each `(amount, token_id)` pair of `tokens` in a separate deposit.
+///
+/// Balances are checked and approvals are made for all the pairs before any deposit is sent.
+/// Multiple pairs are only meaningful for ERC1155, see `format_token_info`.
+///
+/// A stuck deposit is replaced by depositing again with the nonce of the stuck transaction and
+/// a higher gas price in `gas_options`. The replaced deposit never settles.
+///
+/// With `wait`, waits until the validity prover observes each deposit, for `wait_timeout`
+/// seconds or `DEPOSIT_TIMEOUT` if `None`. Ctrl-C aborts the wait, not the sent deposits.
+#[allow(clippy::too_many_arguments)]
 pub async fn deposit(
     key: KeySet,
     eth_private_key: Bytes32,
     token_type: TokenType,
-    amount: U256,
     token_address: Address,
-    token_id: U256,
+    tokens: &[(U256, U256)],
     is_mining: bool,
-) -> Result<(), CliError> {
+    gas_options: GasOptions,
+    wait: bool,
+    wait_timeout: Option<u64>,
+) -> Result<Vec<DepositResult>, CliError> {
+    if gas_options.nonce.is_some() && tokens.len() > 1 {
+        return Err(CliError::UnexpectedError(
+            "A nonce can only be given for a single deposit".to_string(),
+        ));
+    }
     let client = get_client()?;
     let liquidity_contract = client.liquidity_contract.clone();
-    balance_check_and_approve(
-        &liquidity_contract,
-        eth_private_key,
-        amount,
-        token_type,
-        token_address,
-        token_id,
-    )
-    .await?;
+
+    // aggregate amounts of the same token id so that the balance check covers all deposits
+    let mut total_amounts: Vec<(U256, alloy::primitives::U256)> = Vec::new();
+    for &(amount, token_id) in tokens {
+        let amount = convert_u256_to_alloy(amount);
+        match total_amounts.iter_mut().find(|(id, _)| *id == token_id) {
+            Some((_, total)) => {
+                *total = total
+                    .checked_add(amount)
+                    .ok_or(CliError::UnexpectedError("amount overflow".to_string()))?;
+            }
+            None => total_amounts.push((token_id, amount)),
+        }
+    }
+    for &(token_id, amount) in total_amounts.iter() {
+        balance_check_and_approve(
+            &liquidity_contract,
+            eth_private_key,
+            convert_u256_to_intmax(amount),
+            token_type,
+            token_address,
+            token_id,
+        )
+        .await?;
+    }
 
     log::info!("Balance check done");
 
+    let mut deposit_results = Vec::with_capacity(tokens.len());
+    let mut tx_hashes = Vec::with_capacity(tokens.len());
+    for &(amount, token_id) in tokens {
+        let (deposit_result, tx_hash) = deposit_single(
+            &client,
+            key,
+            eth_private_key,
+            token_type,
+            amount,
+            token_address,
+            token_id,
+            is_mining,
+            gas_options,
+        )
+        .await?;
+        deposit_results.push(deposit_result);
+        tx_hashes.push(tx_hash);
+    }
+
+    if wait {
+        // the wait is aborted by dropping it on Ctrl-C instead
+        let never_abort = || false;
+        for (deposit_result, tx_hash) in deposit_results.iter().zip(tx_hashes) {
+            let pubkey_salt_hash = deposit_result.deposit_data.pubkey_salt_hash;
+            log::info!("Waiting for deposit tx {tx_hash} to be observed by the validity prover");
+            let waiting =
+                client.wait_for_deposit(pubkey_salt_hash, tx_hash, wait_timeout, &never_abort);
+            let deposit_info = tokio::select! {
+                result = waiting => result?,
+                _ = tokio::signal::ctrl_c() => {
+                    return Err(ClientError::DepositWaitAborted.into());
+                }
+            };
+            log::info!(
+                "Deposit {} observed with deposit id {}",
+                deposit_result.deposit_digest,
+                deposit_info.deposit_id
+            );
+        }
+    }
+    Ok(deposit_results)
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn deposit_single(
+    client: &Client,
+    key: KeySet,
+    eth_private_key: Bytes32,
+    token_type: TokenType,
+    amount: U256,
+    token_address: Address,
+    token_id: U256,
+    is_mining: bool,
+    gas_options: GasOptions,
+) -> Result<(DepositResult, Bytes32), CliError> {
+    let liquidity_contract = &client.liquidity_contract;
     let signer_private_key = convert_bytes32_to_b256(eth_private_key);
     let depositor = get_address_from_private_key(signer_private_key);
     let depositor = convert_address_to_intmax(depositor);
@@ -64,10 +158,10 @@ pub async fn deposit(
             is_mining,
         )
         .await?;
-    let deposit_data = deposit_result.deposit_data;
+    let deposit_data = deposit_result.deposit_data.clone();
 
     let aml_permission = fetch_predicate_permission(
-        &client,
+        client,
         depositor,
         deposit_data.pubkey_salt_hash,
         token_type,
@@ -78,50 +172,54 @@ pub async fn deposit(
     .await?;
     let eligibility_permission = vec![];
 
-    match token_type {
+    let tx_hash = match token_type {
         TokenType::NATIVE => {
             liquidity_contract
                 .deposit_native(
                     signer_private_key,
                     None,
+                    gas_options,
                     deposit_data.pubkey_salt_hash,
                     deposit_data.amount,
                     &aml_permission,
                     &eligibility_permission,
                 )
-                .await?;
+                .await?
         }
         TokenType::ERC20 => {
             liquidity_contract
                 .deposit_erc20(
                     signer_private_key,
                     None,
+                    gas_options,
                     deposit_data.pubkey_salt_hash,
                     deposit_data.amount,
                     deposit_data.token_address,
                     &aml_permission,
                     &eligibility_permission,
                 )
-                .await?;
+                .await?
         }
         TokenType::ERC721 => {
             liquidity_contract
                 .deposit_erc721(
                     signer_private_key,
                     None,
+                    gas_options,
                     deposit_data.pubkey_salt_hash,
                     deposit_data.token_address,
                     deposit_data.token_id,
                     &aml_permission,
                     &eligibility_permission,
                 )
-                .await?;
+                .await?
         }
         TokenType::ERC1155 => {
             liquidity_contract
                 .deposit_erc1155(
                     signer_private_key,
                     None,
+                    gas_options,
                     deposit_data.pubkey_salt_hash,
                     deposit_data.token_address,
                     deposit_data.token_id,
@@ -129,9 +227,9 @@ pub async fn deposit(
                     &aml_permission,
                     &eligibility_permission,
                 )
-                .await?;
+                .await?
         }
-    }
+    };
 
     // relay deposits by self if local
     if is_local()? {
@@ -156,7 +254,17 @@ pub async fn deposit(
             .await?;
     }
 
-    Ok(())
+    Ok((deposit_result, tx_hash))
+}
+
+/// Decimals of the ERC20 token, or `None` if the contract does not implement `decimals()`.
+pub async fn get_erc20_decimals(token_address: Address) -> Result<Option<u8>, CliError> {
+    let client = get_client()?;
+    let contract = ERC20Contract::new(
+        client.liquidity_contract.provider.clone(),
+        convert_address_to_alloy(token_address),
+    );
+    Ok(contract.decimals().await.ok())
 }
 
 async fn balance_check_and_approve(
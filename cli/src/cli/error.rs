@@ -1,7 +1,7 @@
 use alloy::transports::{RpcError, TransportErrorKind};
 use envy::Error as EnvyError;
 use intmax2_client_sdk::{
-    client::{error::ClientError, sync::error::SyncError},
+    client::{error::ClientError, strategy::error::PendingTxInfo, sync::error::SyncError},
     external_api::{
         contract::error::BlockchainError, local_backup_store_vault::error::LocalStoreVaultError,
     },
@@ -63,9 +63,20 @@ pub enum CliError {
     #[error("Parse error: {0}")]
     ParseError(String),
 
-    #[error("Pending tx error")]
-    PendingTxError,
-
     #[error("Tx failed: {0}")]
     TxFailed(String),
+
+    #[error("JSON error: {0}")]
+    JsonError(#[from] serde_json::Error),
+}
+
+impl CliError {
+    /// Details of the pending tx blocking the sync, if that is the cause of the error.
+    pub fn pending_tx_info(&self) -> Option<&PendingTxInfo> {
+        match self {
+            CliError::SyncError(e) => e.pending_tx_info(),
+            CliError::ClientError(e) => e.pending_tx_info(),
+            _ => None,
+        }
+    }
 }
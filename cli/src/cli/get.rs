@@ -1,19 +1,67 @@
 use colored::Colorize;
-use intmax2_client_sdk::client::misc::payment_memo::get_all_payment_memos;
-use intmax2_interfaces::data::deposit_data::TokenType;
+use intmax2_client_sdk::client::misc::{
+    compare_roots::compare_roots as inner_compare_roots, payment_memo::get_all_payment_memos,
+};
+use intmax2_interfaces::{
+    api::{
+        store_vault_server::interface::StoreVaultClientInterface as _,
+        withdrawal_server::interface::{ClaimInfo, ClaimStatus, WithdrawalInfo, WithdrawalStatus},
+    },
+    data::deposit_data::TokenType,
+};
 use intmax2_zkp::{
     common::{signature_content::key_set::KeySet, trees::asset_tree::AssetLeaf},
-    ethereum_types::u32limb_trait::U32LimbTrait,
+    ethereum_types::{address::Address, bytes32::Bytes32, u256::U256, u32limb_trait::U32LimbTrait},
 };
+use serde::Serialize;
+use std::{collections::BTreeMap, fmt::Display, future::Future, time::Duration};
 
 use crate::cli::{client::get_client, history::format_timestamp};
 
 use super::error::CliError;
 
-pub async fn balance(key: KeySet, sync: bool) -> Result<(), CliError> {
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BalanceEntry {
+    token_index: u32,
+    amount: U256,
+    is_insufficient: bool,
+    token_type: TokenType,
+    token_address: Address,
+    token_id: U256,
+    decimals: Option<u8>,
+    symbol: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MiningEntry {
+    deposit_digest: Bytes32,
+    deposit_amount: U256,
+    block_number: Option<u32>,
+    maturity: Option<u64>,
+    status: String,
+    claimable_at: Option<u64>,
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<(), CliError> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+pub async fn balance(key: KeySet, sync: bool, json: bool) -> Result<(), CliError> {
     let client = get_client()?;
     let balances = if sync {
-        client.sync(key).await?;
+        client
+            .sync_with_progress(key, &|event| {
+                if !json {
+                    println!(
+                        "Synced {:?} at block {} ({}/{})",
+                        event.action_kind, event.block_number, event.current, event.total
+                    );
+                }
+            })
+            .await?;
         let user_data = client.get_user_data(key).await?;
         user_data.balances()
     } else {
@@ -22,11 +70,41 @@ pub async fn balance(key: KeySet, sync: bool) -> Result<(), CliError> {
     let mut balances: Vec<(u32, AssetLeaf)> = balances.0.into_iter().collect();
     balances.sort_by_key(|(i, _leaf)| *i);
 
+    if json {
+        let mut entries = Vec::with_capacity(balances.len());
+        for (i, leaf) in balances.iter() {
+            let token_info = client.get_token_info(*i).await?;
+            entries.push(BalanceEntry {
+                token_index: *i,
+                amount: leaf.amount,
+                is_insufficient: leaf.is_insufficient,
+                token_type: token_info.token_type,
+                token_address: token_info.address,
+                token_id: token_info.token_id,
+                decimals: token_info.decimals,
+                symbol: token_info.symbol,
+            });
+        }
+        return print_json(&entries);
+    }
+
     println!("Balances:");
     for (i, leaf) in balances.iter() {
-        let (token_type, address, token_id) = client.liquidity_contract.get_token_info(*i).await?;
+        let token_info = client.get_token_info(*i).await?;
+        let (token_type, address, token_id) = (
+            token_info.token_type,
+            token_info.address,
+            token_info.token_id,
+        );
         println!("\t Token #{i}:");
-        println!("\t\t Amount: {}", leaf.amount);
+        match &token_info.symbol {
+            Some(symbol) => println!(
+                "\t\t Amount: {} {symbol} (raw {})",
+                token_info.format_amount(leaf.amount),
+                leaf.amount
+            ),
+            None => println!("\t\t Amount: {}", leaf.amount),
+        }
         println!("\t\t Type: {token_type}");
 
         match token_type {
@@ -47,9 +125,38 @@ pub async fn balance(key: KeySet, sync: bool) -> Result<(), CliError> {
     Ok(())
 }
 
-pub async fn withdrawal_status(key: KeySet) -> Result<(), CliError> {
+/// Print the withdrawal status. If `watch_interval` is given, keep polling until every
+/// withdrawal reaches a terminal state.
+pub async fn withdrawal_status(
+    key: KeySet,
+    json: bool,
+    watch_interval: Option<Duration>,
+) -> Result<(), CliError> {
     let client = get_client()?;
-    let withdrawal_info = client.get_withdrawal_info(key).await?;
+    let Some(interval) = watch_interval else {
+        let withdrawal_info = client.get_withdrawal_info(key).await?;
+        return print_withdrawal_status(&withdrawal_info, json);
+    };
+    let client = &client;
+    watch_status(
+        interval,
+        move || async move { Ok(client.get_withdrawal_info(key).await?) },
+        |info: &WithdrawalInfo| info.status.clone(),
+        |status| {
+            matches!(
+                status,
+                WithdrawalStatus::Success | WithdrawalStatus::NeedClaim | WithdrawalStatus::Failed
+            )
+        },
+        |withdrawal_info| print_withdrawal_status(withdrawal_info, json),
+    )
+    .await
+}
+
+fn print_withdrawal_status(withdrawal_info: &[WithdrawalInfo], json: bool) -> Result<(), CliError> {
+    if json {
+        return print_json(&withdrawal_info);
+    }
     println!("Withdrawal status:");
     for (i, withdrawal_info) in withdrawal_info.iter().enumerate() {
         let withdrawal = withdrawal_info.contract_withdrawal.clone();
@@ -69,9 +176,23 @@ pub async fn withdrawal_status(key: KeySet) -> Result<(), CliError> {
     Ok(())
 }
 
-pub async fn mining_list(key: KeySet) -> Result<(), CliError> {
+pub async fn mining_list(key: KeySet, json: bool) -> Result<(), CliError> {
     let client = get_client()?;
     let minings = client.get_mining_list(key).await?;
+    if json {
+        let entries: Vec<MiningEntry> = minings
+            .iter()
+            .map(|mining| MiningEntry {
+                deposit_digest: mining.meta.digest,
+                deposit_amount: mining.deposit_data.amount,
+                block_number: mining.block.as_ref().map(|b| b.block_number),
+                maturity: mining.maturity,
+                status: mining.status.to_string(),
+                claimable_at: mining.prediction.as_ref().map(|p| p.claimable_at),
+            })
+            .collect();
+        return print_json(&entries);
+    }
     println!("Mining list:");
     for (i, mining) in minings.iter().enumerate() {
         let block_number = mining
@@ -79,17 +200,48 @@ pub async fn mining_list(key: KeySet) -> Result<(), CliError> {
             .as_ref()
             .map_or("N/A".to_string(), |b| b.block_number.to_string());
         let maturity = mining.maturity.map_or("N/A".to_string(), format_timestamp);
+        let claimable_at = mining.prediction.as_ref().map_or("N/A".to_string(), |p| {
+            format!(
+                "{} (block {} or later)",
+                format_timestamp(p.claimable_at),
+                p.earliest_block_number
+            )
+        });
         println!(
-            "#{}: deposit included block :{}, deposit amount: {}, maturity: {}, status: {}",
-            i, block_number, mining.deposit_data.amount, maturity, mining.status
+            "#{}: deposit included block :{}, deposit amount: {}, maturity: {}, status: {}, claimable at: {}",
+            i, block_number, mining.deposit_data.amount, maturity, mining.status, claimable_at
         );
     }
     Ok(())
 }
 
-pub async fn claim_status(key: KeySet) -> Result<(), CliError> {
+/// Print the claim status. If `watch_interval` is given, keep polling until every claim reaches
+/// a terminal state.
+pub async fn claim_status(
+    key: KeySet,
+    json: bool,
+    watch_interval: Option<Duration>,
+) -> Result<(), CliError> {
     let client = get_client()?;
-    let claim_info = client.get_claim_info(key).await?;
+    let Some(interval) = watch_interval else {
+        let claim_info = client.get_claim_info(key).await?;
+        return print_claim_status(&claim_info, json);
+    };
+    let client = &client;
+    watch_status(
+        interval,
+        move || async move { Ok(client.get_claim_info(key).await?) },
+        |info: &ClaimInfo| info.status.clone(),
+        |status| matches!(status, ClaimStatus::Success | ClaimStatus::Failed),
+        |claim_info| print_claim_status(claim_info, json),
+    )
+    .await
+}
+
+fn print_claim_status(claim_info: &[ClaimInfo], json: bool) -> Result<(), CliError> {
+    if json {
+        return print_json(&claim_info);
+    }
     println!("Claim status:");
     for (i, claim_info) in claim_info.iter().enumerate() {
         let claim = claim_info.claim.clone();
@@ -107,12 +259,167 @@ pub async fn claim_status(key: KeySet) -> Result<(), CliError> {
     Ok(())
 }
 
+/// Poll `fetch` every `interval` and reprint the entries whenever a status changes, until every
+/// status is terminal or Ctrl-C is pressed. A summary of the last statuses is printed at the end.
+async fn watch_status<T, S, F, Fut>(
+    interval: Duration,
+    fetch: F,
+    status_of: impl Fn(&T) -> S,
+    is_terminal: impl Fn(&S) -> bool,
+    print: impl Fn(&[T]) -> Result<(), CliError>,
+) -> Result<(), CliError>
+where
+    S: PartialEq + Display,
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<Vec<T>, CliError>>,
+{
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    let mut last_statuses: Option<Vec<S>> = None;
+    let mut interrupted = false;
+    loop {
+        let entries = tokio::select! {
+            entries = fetch() => entries?,
+            _ = &mut ctrl_c => {
+                interrupted = true;
+                break;
+            }
+        };
+        let statuses = entries.iter().map(&status_of).collect::<Vec<_>>();
+        if last_statuses.as_ref() != Some(&statuses) {
+            print(&entries)?;
+        }
+        let is_done = statuses.iter().all(&is_terminal);
+        last_statuses = Some(statuses);
+        if is_done {
+            break;
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {}
+            _ = &mut ctrl_c => {
+                interrupted = true;
+                break;
+            }
+        }
+    }
+
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for status in last_statuses.iter().flatten() {
+        *counts.entry(status.to_string()).or_default() += 1;
+    }
+    let counts = counts
+        .iter()
+        .map(|(status, count)| format!("{status}: {count}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    if interrupted {
+        println!(
+            "{}",
+            format!("Interrupted. Last statuses: {counts}").yellow()
+        );
+    } else {
+        println!(
+            "{}",
+            format!("All entries are in a terminal state. {counts}").green()
+        );
+    }
+    Ok(())
+}
+
 pub async fn check_validity_prover() -> Result<(), CliError> {
     let client = get_client()?;
+    let sync_status = client.validity_prover.get_sync_status().await?;
+    if !sync_status.is_synced_to_tip {
+        println!(
+            "{}",
+            format!(
+                "Validity prover is still catching up ({} blocks behind chain head {}), balances may be stale",
+                sync_status.block_gap, sync_status.chain_head_block_number
+            )
+            .yellow()
+        );
+    }
     client.check_validity_prover().await?;
     Ok(())
 }
 
+pub async fn audit_consistency(key: KeySet, json: bool) -> Result<(), CliError> {
+    let client = get_client()?;
+    let report = client.audit_consistency(key).await?;
+    if json {
+        return print_json(&report);
+    }
+    println!(
+        "Validity prover block: {}, balance proof block: {}",
+        report.validity_prover_block_number,
+        report
+            .balance_proof_block_number
+            .map_or("N/A".to_string(), |b| b.to_string())
+    );
+    for entry in report
+        .unknown_block_entries
+        .iter()
+        .chain(&report.missing_entries)
+    {
+        println!(
+            "{}",
+            format!("{} {}: {}", entry.data_type, entry.digest, entry.reason).red()
+        );
+    }
+    for chain_break in report.balance_proof_chain_breaks.iter() {
+        let target = chain_break
+            .tx_digest
+            .map_or("latest balance proof".to_string(), |d| format!("tx {d}"));
+        println!(
+            "{}",
+            format!(
+                "Balance proof chain break at {target} (block {}): previous balance proof block {:?}, previous tx block {:?}",
+                chain_break.block_number,
+                chain_break.prev_balance_proof_block_number,
+                chain_break.prev_tx_block_number
+            )
+            .red()
+        );
+    }
+    if report.is_consistent() {
+        println!("{}", "The local state is consistent".green());
+    }
+    Ok(())
+}
+
+pub async fn compare_roots(
+    prover_url_a: &str,
+    prover_url_b: &str,
+    from: u32,
+    to: u32,
+) -> Result<(), CliError> {
+    let divergence = inner_compare_roots(prover_url_a, prover_url_b, from..=to).await?;
+    match divergence {
+        Some(divergence) => {
+            println!(
+                "{}",
+                format!(
+                    "{} root diverged at block {}",
+                    divergence.root_kind, divergence.block_number
+                )
+                .red()
+            );
+            let label_a = format!("A ({prover_url_a}):");
+            let label_b = format!("B ({prover_url_b}):");
+            let width = label_a.len().max(label_b.len());
+            println!("  {label_a:<width$} {}", divergence.root_a);
+            println!("  {label_b:<width$} {}", divergence.root_b);
+        }
+        None => {
+            println!(
+                "{}",
+                format!("Roots match for blocks {from} to {to}").green()
+            );
+        }
+    }
+    Ok(())
+}
+
 pub async fn get_payment_memos(key: KeySet, name: &str) -> Result<(), CliError> {
     let client = get_client()?;
     let payment_memos =
@@ -130,9 +437,22 @@ pub async fn get_payment_memos(key: KeySet, name: &str) -> Result<(), CliError>
     Ok(())
 }
 
-pub async fn get_user_data(key: KeySet) -> Result<(), CliError> {
+pub async fn list_topics(key: KeySet) -> Result<(), CliError> {
+    let client = get_client()?;
+    let topics = client.store_vault_server.list_topics(key).await?;
+    println!("Topics:");
+    for entry in topics.iter() {
+        println!("{}: {} entries", entry.topic, entry.count);
+    }
+    Ok(())
+}
+
+pub async fn get_user_data(key: KeySet, json: bool) -> Result<(), CliError> {
     let client = get_client()?;
     let user_data = client.get_user_data(key).await?;
+    if json {
+        return print_json(&user_data);
+    }
     println!(
         "{}: {:?}\n",
         "Nullifiers".bright_magenta(),
@@ -159,5 +479,18 @@ pub async fn get_user_data(key: KeySet) -> Result<(), CliError> {
         "Claim Status".bright_red(),
         user_data.claim_status
     );
+    if !user_data.stuck_receives.is_empty() {
+        println!("{}:", "Stuck Receives".bright_red());
+        for receive in &user_data.stuck_receives {
+            println!(
+                "{} {} at block {} after {} attempts: {}",
+                receive.data_type,
+                receive.digest,
+                receive.block_number,
+                receive.attempts,
+                receive.last_error
+            );
+        }
+    }
     Ok(())
 }
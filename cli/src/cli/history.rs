@@ -1,13 +1,8 @@
 use chrono::DateTime;
 use colored::{ColoredString, Colorize as _};
-use intmax2_client_sdk::client::history::EntryStatus;
-use intmax2_interfaces::{
-    api::store_vault_server::types::{CursorOrder, MetaDataCursor},
-    data::{
-        deposit_data::DepositData, meta_data::MetaData, transfer_data::TransferData,
-        tx_data::TxData,
-    },
-};
+use futures::{pin_mut, TryStreamExt as _};
+use intmax2_client_sdk::client::history::{EntryStatus, HistoryEntry, HistoryItem};
+use intmax2_interfaces::api::store_vault_server::types::CursorOrder;
 use intmax2_zkp::{
     common::{signature_content::key_set::KeySet, transfer::Transfer},
     ethereum_types::{bytes32::Bytes32, u32limb_trait::U32LimbTrait as _},
@@ -22,55 +17,13 @@ pub async fn history(
     order: CursorOrder,
     from_timestamp: Option<u64>,
 ) -> Result<(), CliError> {
-    let cursor = MetaDataCursor {
-        cursor: from_timestamp.map(|timestamp| MetaData {
-            timestamp,
-            digest: Bytes32::default(),
-        }),
-        order: order.clone(),
-        limit: None,
-    };
-
     let client = get_client()?;
-    let (deposit_history, _) = client.fetch_deposit_history(key, &cursor).await?;
-    let (transfer_history, _) = client.fetch_transfer_history(key, &cursor).await?;
-    let (tx_history, _) = client.fetch_tx_history(key, &cursor).await?;
-
-    let mut history: Vec<HistoryEum> = Vec::new();
-    for entry in deposit_history {
-        history.push(HistoryEum::Deposit {
-            deposit: entry.data,
-            status: entry.status,
-            meta: entry.meta,
-        });
-    }
-    for entry in transfer_history {
-        history.push(HistoryEum::Receive {
-            transfer: entry.data,
-            status: entry.status,
-            meta: entry.meta,
-        });
-    }
-    for entry in tx_history {
-        history.push(HistoryEum::Send {
-            tx: entry.data,
-            status: entry.status,
-            meta: entry.meta,
-        });
-    }
-
-    history.sort_by_key(|entry| match entry {
-        HistoryEum::Deposit { meta, .. } => (meta.timestamp, meta.digest.to_hex()),
-        HistoryEum::Receive { meta, .. } => (meta.timestamp, meta.digest.to_hex()),
-        HistoryEum::Send { meta, .. } => (meta.timestamp, meta.digest.to_hex()),
-    });
-    if order == CursorOrder::Desc {
-        history.reverse();
-    }
+    let history = client.history_stream(key, order, from_timestamp);
+    pin_mut!(history);
 
     println!("History:");
-    for entry in history {
-        print_history_entry(&entry)?;
+    while let Some(item) = history.try_next().await? {
+        print_history_entry(&item)?;
         println!();
     }
     Ok(())
@@ -109,31 +62,13 @@ fn format_transfer(transfer: &Transfer, transfer_type: &str, transfer_digest: By
     )
 }
 
-enum HistoryEum {
-    Deposit {
-        deposit: DepositData,
-        status: EntryStatus,
-        meta: MetaData,
-    },
-    Receive {
-        transfer: TransferData,
-        status: EntryStatus,
-        meta: MetaData,
-    },
-    Send {
-        tx: TxData,
-        status: EntryStatus,
-        meta: MetaData,
-    },
-}
-
-fn print_history_entry(entry: &HistoryEum) -> Result<(), CliError> {
+fn print_history_entry(entry: &HistoryItem) -> Result<(), CliError> {
     match entry {
-        HistoryEum::Deposit {
-            deposit,
+        HistoryItem::Deposit(HistoryEntry {
+            data: deposit,
             status,
             meta,
-        } => {
+        }) => {
             let time = format_timestamp(meta.timestamp);
             println!(
                 "{} [{}]",
@@ -163,11 +98,11 @@ fn print_history_entry(entry: &HistoryEum) -> Result<(), CliError> {
                     .map_or("N/A".to_string(), |h| h.to_string())
             );
         }
-        HistoryEum::Receive {
-            transfer,
+        HistoryItem::Receive(HistoryEntry {
+            data: transfer,
             status,
             meta,
-        } => {
+        }) => {
             let time = format_timestamp(meta.timestamp);
 
             println!(
@@ -187,7 +122,11 @@ fn print_history_entry(entry: &HistoryEum) -> Result<(), CliError> {
                 transfer.transfer.amount.to_string().bright_green()
             );
         }
-        HistoryEum::Send { tx, status, meta } => {
+        HistoryItem::Send(HistoryEntry {
+            data: tx,
+            status,
+            meta,
+        }) => {
             let time = format_timestamp(meta.timestamp);
             println!("{} [{}]", "SEND".bright_red().bold(), time.bright_blue(),);
             println!("  Digest: {}", meta.digest);
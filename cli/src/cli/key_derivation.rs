@@ -1,6 +1,6 @@
 use alloy::primitives::B256;
 use intmax2_client_sdk::external_api::wallet_key_vault::{
-    mnemonic_to_keyset, WalletKeyVaultClient,
+    mnemonic_to_keyset_with_context, WalletKeyVaultClient,
 };
 use intmax2_interfaces::api::wallet_key_vault::interface::WalletKeyVaultClientInterface;
 use intmax2_zkp::common::signature_content::key_set::KeySet;
@@ -9,10 +9,13 @@ use crate::env_var::EnvVar;
 
 use super::error::CliError;
 
+/// Derives the intmax key of `eth_private_key` from its wallet key vault mnemonic. A
+/// `derivation_context` derives a different account than the default one.
 pub async fn derive_key_from_eth(
     eth_private_key: B256,
     redeposit_index: u32,
     wallet_index: u32,
+    derivation_context: Option<&str>,
 ) -> Result<KeySet, CliError> {
     let env = envy::from_env::<EnvVar>()?;
     if env.wallet_key_vault_base_url.is_none() {
@@ -22,6 +25,11 @@ pub async fn derive_key_from_eth(
     }
     let client = WalletKeyVaultClient::new(env.wallet_key_vault_base_url.unwrap());
     let mnemonic = client.derive_mnemonic(eth_private_key).await?;
-    let key = mnemonic_to_keyset(&mnemonic, redeposit_index, wallet_index);
+    let key = mnemonic_to_keyset_with_context(
+        &mnemonic,
+        redeposit_index,
+        wallet_index,
+        derivation_context,
+    );
     Ok(key)
 }
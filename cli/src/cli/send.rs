@@ -1,12 +1,15 @@
 use intmax2_client_sdk::{
     client::{client::PaymentMemoEntry, strategy::tx_status::TxStatus},
-    external_api::{indexer::IndexerClient, utils::time::sleep_for},
+    external_api::{
+        indexer::IndexerClient,
+        utils::{rate_limit::RateLimitConfig, retry::RetryConfig, time::sleep_for},
+    },
 };
 use intmax2_interfaces::api::indexer::interface::IndexerClientInterface;
 use intmax2_zkp::{
     common::{signature_content::key_set::KeySet, transfer::Transfer},
     constants::NUM_TRANSFERS_IN_TX,
-    ethereum_types::u32limb_trait::U32LimbTrait,
+    ethereum_types::{bytes32::Bytes32, u32limb_trait::U32LimbTrait},
 };
 
 use crate::{cli::client::get_client, env_var::EnvVar};
@@ -23,24 +26,105 @@ pub async fn send_transfers(
     fee_token_index: u32,
     wait: bool,
 ) -> Result<(), CliError> {
+    send_transfers_inner(key, transfers, payment_memos, fee_token_index, wait).await?;
+    Ok(())
+}
+
+/// Splits `transfers` into chunks of `chunk_size` and sends them as sequential txs, waiting for
+/// each tx to be finalized before sending the next one. `recipients` are the recipients as
+/// written in the input, and are used to report the transfers that were not sent on failure.
+pub async fn send_transfers_in_chunks(
+    key: KeySet,
+    transfers: &[Transfer],
+    recipients: &[String],
+    fee_token_index: u32,
+    chunk_size: usize,
+) -> Result<(), CliError> {
+    for (chunk_index, chunk) in transfers.chunks(chunk_size).enumerate() {
+        let result = send_transfers_inner(key, chunk, vec![], fee_token_index, true)
+            .await
+            .and_then(|(tx_tree_root, status)| match status {
+                Some(TxStatus::Success) => Ok(tx_tree_root),
+                Some(TxStatus::Failed(reason)) => Err(CliError::TxFailed(reason)),
+                _ => Err(CliError::TxFailed(
+                    "tx was not finalized before expiry".to_string(),
+                )),
+            });
+        match result {
+            Ok(tx_tree_root) => {
+                println!(
+                    "chunk #{}: {} transfers, tx tree root: {}",
+                    chunk_index,
+                    chunk.len(),
+                    tx_tree_root.to_hex()
+                );
+            }
+            Err(e) => {
+                let first_unsent = chunk_index * chunk_size;
+                println!(
+                    "chunk #{} failed: {}. The following {} recipients were not sent:",
+                    chunk_index,
+                    e,
+                    transfers.len() - first_unsent
+                );
+                for (i, recipient) in recipients.iter().enumerate().skip(first_unsent) {
+                    // row numbers exclude the header row
+                    println!("row {}: {}", i + 1, recipient);
+                }
+                return Err(e);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Sends the transfers and returns the tx tree root, along with the final tx status if `wait`
+/// is set and the tx was finalized before expiry.
+async fn send_transfers_inner(
+    key: KeySet,
+    transfers: &[Transfer],
+    payment_memos: Vec<PaymentMemoEntry>,
+    fee_token_index: u32,
+    wait: bool,
+) -> Result<(Bytes32, Option<TxStatus>), CliError> {
     if transfers.len() > NUM_TRANSFERS_IN_TX - 1 {
         return Err(CliError::TooManyTransfer(transfers.len()));
     }
     let env = envy::from_env::<EnvVar>()?;
     let client = get_client()?;
-    // override block builder base url if it is set in the env
-    let block_builder_url = if let Some(block_builder_base_url) = env.block_builder_base_url {
-        block_builder_base_url.to_string()
+    // override block builder base url if it is set in the env. A comma separated list is tried
+    // in order, failing over to the next block builder if one does not accept the tx request.
+    let block_builder_urls = if let Some(block_builder_base_url) = env.block_builder_base_url {
+        block_builder_base_url
+            .split(',')
+            .map(|url| url.trim().to_string())
+            .filter(|url| !url.is_empty())
+            .collect::<Vec<_>>()
     } else {
         // get block builder info
-        let indexer = IndexerClient::new(&env.indexer_base_url.to_string());
+        let retry_config = RetryConfig::from_options(
+            env.retry_max_retries,
+            env.retry_initial_delay_ms,
+            env.retry_jitter_ms,
+        );
+        let mut indexer =
+            IndexerClient::new(&env.indexer_base_url.to_string()).with_retry_config(retry_config);
+        if let Some(rate_limit_config) = RateLimitConfig::from_options(
+            env.indexer_rate_limit_per_second,
+            env.indexer_rate_limit_burst,
+        ) {
+            indexer = indexer.with_rate_limit(rate_limit_config);
+        }
         let block_builder_info = indexer.get_block_builder_info().await?;
-        block_builder_info.url.clone()
+        vec![block_builder_info.url.clone()]
     };
-    log::info!("Block Builder URL: {block_builder_url}",);
+    log::info!("Block Builder URLs: {}", block_builder_urls.join(", "));
+    let block_builder_url = block_builder_urls.first().ok_or(CliError::UnexpectedError(
+        "BLOCK_BUILDER_BASE_URL is empty".to_string(),
+    ))?;
 
     let fee_quote = client
-        .quote_transfer_fee(&block_builder_url, key.pubkey, fee_token_index)
+        .quote_transfer_fee(block_builder_url, key.pubkey, fee_token_index)
         .await?;
     if let Some(fee) = &fee_quote.fee {
         log::info!("beneficiary: {}", fee_quote.beneficiary.unwrap().to_hex());
@@ -55,11 +139,12 @@ pub async fn send_transfers(
     }
     let memo = client
         .send_tx_request(
-            &block_builder_url,
+            &block_builder_urls,
             key,
             transfers,
             &payment_memos,
             &fee_quote,
+            None,
         )
         .await?;
 
@@ -70,13 +155,14 @@ pub async fn send_transfers(
     .await;
 
     let proposal = client
-        .query_proposal(&block_builder_url, &memo.request_id)
+        .query_proposal(&memo.block_builder_url, &memo.request_id)
         .await?;
 
     log::info!("Finalizing tx");
     let result = client
-        .finalize_tx(&block_builder_url, key, &memo, &proposal)
+        .finalize_tx(&memo.block_builder_url, key, &memo, &proposal)
         .await?;
+    log::info!("Tx handled by block builder {}", memo.block_builder_url);
 
     let expiry: u64 = proposal.block_sign_payload.expiry.into();
     let expiry_with_margin = if expiry > 0 {
@@ -85,6 +171,7 @@ pub async fn send_transfers(
         chrono::Utc::now().timestamp() as u64 + BLOCK_SYNC_MARGIN
     };
 
+    let mut final_status = None;
     if wait {
         log::info!("Waiting for the block to be finalized");
 
@@ -96,16 +183,23 @@ pub async fn send_transfers(
             let status = client
                 .get_tx_status(key.pubkey, result.tx_tree_root)
                 .await?;
-            match status {
+            match &status {
                 TxStatus::Pending => {
                     log::info!("tx pending");
                 }
                 TxStatus::Success => {
                     log::info!("tx success");
+                    final_status = Some(status);
                     break;
                 }
                 TxStatus::Failed(reason) => {
                     log::error!("tx failed: {reason}");
+                    final_status = Some(status);
+                    break;
+                }
+                TxStatus::Expired => {
+                    log::error!("tx request expired");
+                    final_status = Some(status);
                     break;
                 }
             }
@@ -113,5 +207,5 @@ pub async fn send_transfers(
         }
     }
 
-    Ok(())
+    Ok((result.tx_tree_root, final_status))
 }
@@ -26,8 +26,17 @@ pub async fn sync_claims(
     Ok(())
 }
 
-pub async fn resync(key: KeySet, is_deep: bool) -> Result<(), CliError> {
+pub async fn resync(
+    key: KeySet,
+    is_deep: bool,
+    resume: bool,
+    token_index: Option<u32>,
+) -> Result<(), CliError> {
     let client = get_client()?;
-    client.resync(key, is_deep).await?;
+    match token_index {
+        Some(token_index) => client.resync_token(key, token_index, is_deep).await?,
+        None if resume => client.resume_deep_resync(key).await?,
+        None => client.resync(key, is_deep).await?,
+    }
     Ok(())
 }
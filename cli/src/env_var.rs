@@ -12,6 +12,7 @@ pub struct EnvVar {
     pub indexer_base_url: String,
     pub store_vault_type: StoreVaultType,
     pub local_backup_path: Option<String>,
+    pub local_backup_passphrase: Option<String>,
     pub store_vault_server_base_url: Option<String>,
     pub validity_prover_base_url: String,
     pub balance_prover_base_url: String,
@@ -37,6 +38,23 @@ pub struct EnvVar {
     // mining settings
     pub is_faster_mining: bool,
 
+    // optional sync settings
+    pub receive_concurrency: Option<usize>,
+    pub balance_prover_warmup_retries: Option<u32>,
+    pub deposit_confirmations: Option<u64>,
+    pub compress_payloads: Option<bool>,
+    pub proposal_expiry_margin: Option<u64>,
+    pub max_transfer_amount: Option<String>,
+
+    // optional retry policy for external API calls
+    pub retry_max_retries: Option<u32>,
+    pub retry_initial_delay_ms: Option<u64>,
+    pub retry_jitter_ms: Option<u64>,
+
+    // optional client-side rate limit of the indexer
+    pub indexer_rate_limit_per_second: Option<f64>,
+    pub indexer_rate_limit_burst: Option<u32>,
+
     // optional block builder base url
     pub block_builder_base_url: Option<String>,
 
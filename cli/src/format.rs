@@ -1,4 +1,7 @@
-use anyhow::{bail, ensure};
+use std::{collections::HashMap, path::Path, str::FromStr as _};
+
+use anyhow::{bail, ensure, Context as _};
+use intmax2_client_sdk::external_api::contract::handlers::GasStrategy;
 use intmax2_interfaces::data::deposit_data::TokenType;
 use intmax2_zkp::{
     common::{generic_address::GenericAddress, signature_content::key_set::KeySet},
@@ -15,47 +18,195 @@ pub enum FormatTokenInfoError {
     MissingTokenAddress,
     #[error("Missing token id")]
     MissingTokenId,
-    #[error("Amount should not be specified")]
-    AmountShouldNotBeSpecified,
+    #[error("ERC721 amount must be 1, got {0}")]
+    ERC721AmountMustBeOne(U256),
+    #[error("Invalid amount {0}")]
+    InvalidAmount(String),
+    #[error("Amount {0} must be an integer in the smallest unit of the token")]
+    FractionalAmountNotSupported(String),
+    #[error("Decimals of the token are unknown, give the amount in its smallest unit")]
+    MissingDecimals,
+    #[error("Amount {amount} has more than {decimals} decimals")]
+    TooManyDecimals { amount: String, decimals: u8 },
+    #[error("Multiple amounts or token ids are only supported for ERC1155")]
+    MultipleValuesNotSupported,
+    #[error("Number of token ids ({token_ids}) does not match number of amounts ({amounts})")]
+    LengthMismatch { token_ids: usize, amounts: usize },
+}
+
+/// Decimals of the native token
+pub const NATIVE_DECIMALS: u8 = 18;
+
+/// Unit of the native and ERC20 amounts given on the command line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum AmountUnit {
+    /// Smallest unit of the token, e.g. wei
+    #[default]
+    Base,
+    /// Whole tokens, scaled by the decimals of the token
+    Token,
 }
 
+/// Returns the token address and the `(amount, token_id)` pairs to deposit.
+///
+/// ERC1155 accepts parallel lists of amounts and token ids, one deposit per pair. The other
+/// token types accept at most one amount and token id. The ERC721 amount may be omitted and
+/// must be 1 otherwise.
+///
+/// With `AmountUnit::Base`, amounts are base 10 integers in the smallest unit. With
+/// `AmountUnit::Token`, native and ERC20 amounts are in whole tokens, e.g. `1` or `1.5`, and are
+/// scaled by the decimals of the token. `erc20_decimals` is the decimals read from the ERC20
+/// contract, `None` if the contract does not implement `decimals()`. ERC721 and ERC1155 amounts
+/// are counts of tokens and are always integers.
 pub fn format_token_info(
     token_type: TokenType,
-    amount: Option<U256>,
+    amounts: Vec<String>,
+    unit: AmountUnit,
     token_address: Option<Address>,
-    token_id: Option<U256>,
-) -> Result<(U256, Address, U256), FormatTokenInfoError> {
+    token_ids: Vec<U256>,
+    erc20_decimals: Option<u8>,
+) -> Result<(Address, Vec<(U256, U256)>), FormatTokenInfoError> {
     match token_type {
-        TokenType::NATIVE => Ok({
-            let amount = amount.ok_or(FormatTokenInfoError::MissingAmount)?;
-            (amount, Address::zero(), U256::zero())
-        }),
+        TokenType::NATIVE => {
+            let amount = single_value(&amounts)?.ok_or(FormatTokenInfoError::MissingAmount)?;
+            let decimals = match unit {
+                AmountUnit::Base => None,
+                AmountUnit::Token => Some(NATIVE_DECIMALS),
+            };
+            let amount = parse_amount(&amount, decimals)?;
+            single_value(&token_ids)?;
+            Ok((Address::zero(), vec![(amount, U256::zero())]))
+        }
         TokenType::ERC20 => {
-            let amount = amount.ok_or(FormatTokenInfoError::MissingAmount)?;
+            let amount = single_value(&amounts)?.ok_or(FormatTokenInfoError::MissingAmount)?;
+            let decimals = match unit {
+                AmountUnit::Base => None,
+                AmountUnit::Token => {
+                    Some(erc20_decimals.ok_or(FormatTokenInfoError::MissingDecimals)?)
+                }
+            };
+            let amount = parse_amount(&amount, decimals)?;
+            single_value(&token_ids)?;
             let token_address = token_address.ok_or(FormatTokenInfoError::MissingTokenAddress)?;
-            Ok((amount, token_address, U256::zero()))
+            Ok((token_address, vec![(amount, U256::zero())]))
         }
         TokenType::ERC721 => {
-            if amount.is_some() {
-                return Err(FormatTokenInfoError::AmountShouldNotBeSpecified);
+            if let Some(amount) = single_value(&amounts)? {
+                let amount = parse_amount(&amount, None)?;
+                if amount != U256::one() {
+                    return Err(FormatTokenInfoError::ERC721AmountMustBeOne(amount));
+                }
             }
             let token_address = token_address.ok_or(FormatTokenInfoError::MissingTokenAddress)?;
-            let token_id = token_id.ok_or(FormatTokenInfoError::MissingTokenId)?;
-            Ok((U256::one(), token_address, token_id))
+            let token_id = single_value(&token_ids)?.ok_or(FormatTokenInfoError::MissingTokenId)?;
+            Ok((token_address, vec![(U256::one(), token_id)]))
         }
         TokenType::ERC1155 => {
-            let amount = amount.ok_or(FormatTokenInfoError::MissingAmount)?;
+            if amounts.is_empty() {
+                return Err(FormatTokenInfoError::MissingAmount);
+            }
+            let amounts = amounts
+                .iter()
+                .map(|amount| parse_amount(amount, None))
+                .collect::<Result<Vec<_>, _>>()?;
             let token_address = token_address.ok_or(FormatTokenInfoError::MissingTokenAddress)?;
-            let token_id = token_id.ok_or(FormatTokenInfoError::MissingTokenId)?;
-            Ok((amount, token_address, token_id))
+            if token_ids.is_empty() {
+                return Err(FormatTokenInfoError::MissingTokenId);
+            }
+            if amounts.len() != token_ids.len() {
+                return Err(FormatTokenInfoError::LengthMismatch {
+                    token_ids: token_ids.len(),
+                    amounts: amounts.len(),
+                });
+            }
+            Ok((token_address, amounts.into_iter().zip(token_ids).collect()))
+        }
+    }
+}
+
+fn single_value<T: Clone>(values: &[T]) -> Result<Option<T>, FormatTokenInfoError> {
+    match values {
+        [] => Ok(None),
+        [value] => Ok(Some(value.clone())),
+        _ => Err(FormatTokenInfoError::MultipleValuesNotSupported),
+    }
+}
+
+/// Parses an amount in whole tokens scaled by `decimals`, or a base 10 integer amount in the
+/// smallest unit if `decimals` is `None`.
+fn parse_amount(amount: &str, decimals: Option<u8>) -> Result<U256, FormatTokenInfoError> {
+    let invalid = || FormatTokenInfoError::InvalidAmount(amount.to_string());
+    let base_units = match decimals {
+        None if amount.contains('.') => {
+            return Err(FormatTokenInfoError::FractionalAmountNotSupported(
+                amount.to_string(),
+            ));
+        }
+        None => amount.to_string(),
+        Some(decimals) => {
+            let (integer, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+            if fraction.len() > decimals as usize {
+                return Err(FormatTokenInfoError::TooManyDecimals {
+                    amount: amount.to_string(),
+                    decimals,
+                });
+            }
+            if integer.is_empty() && fraction.is_empty() {
+                return Err(invalid());
+            }
+            format!("{integer}{fraction:0<width$}", width = decimals as usize)
         }
+    };
+    if base_units.is_empty() || !base_units.chars().all(|c| c.is_ascii_digit()) {
+        return Err(invalid());
     }
+    U256::from_str(&base_units).map_err(|_| invalid())
 }
 
 pub fn privkey_to_keyset(privkey: Bytes32) -> KeySet {
     KeySet::new(privkey.into())
 }
 
+/// Names mapped to intmax addresses, loaded from a TOML or JSON file of `name = "0x..."` entries.
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(transparent)]
+pub struct AddressBook(HashMap<String, String>);
+
+impl AddressBook {
+    /// Load the address book. Files with the `.toml` extension are parsed as TOML, others as
+    /// JSON.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read address book {}", path.display()))?;
+        let book = if path.extension().is_some_and(|ext| ext == "toml") {
+            toml::from_str(&content)
+                .with_context(|| format!("Failed to parse address book {}", path.display()))?
+        } else {
+            serde_json::from_str(&content)
+                .with_context(|| format!("Failed to parse address book {}", path.display()))?
+        };
+        Ok(book)
+    }
+}
+
+/// Parse a recipient that is either a raw address or a `@name` reference to the address book.
+pub fn resolve_generic_address(
+    recipient: &str,
+    address_book: Option<&AddressBook>,
+) -> anyhow::Result<GenericAddress> {
+    let Some(name) = recipient.strip_prefix('@') else {
+        return parse_generic_address(recipient);
+    };
+    let address_book =
+        address_book.with_context(|| format!("@{name} is used without an address book"))?;
+    let address = address_book
+        .0
+        .get(name)
+        .with_context(|| format!("Unknown address book entry @{name}"))?;
+    parse_generic_address(address)
+        .with_context(|| format!("Invalid address of address book entry @{name}"))
+}
+
 pub fn parse_generic_address(address: &str) -> anyhow::Result<GenericAddress> {
     ensure!(address.starts_with("0x"), "Invalid prefix");
     let bytes = hex::decode(&address[2..])?;
@@ -69,3 +220,238 @@ pub fn parse_generic_address(address: &str) -> anyhow::Result<GenericAddress> {
         bail!("Invalid length");
     }
 }
+
+/// Parses a gas strategy given as `fixed:<max_fee_gwei>:<priority_fee_gwei>`,
+/// `multiplier:<multiplier>` or `capped:<max_fee_gwei>`.
+pub fn parse_gas_strategy(value: &str) -> anyhow::Result<GasStrategy> {
+    let parts = value.split(':').collect::<Vec<_>>();
+    let strategy = match parts.as_slice() {
+        ["fixed", max_fee, priority_fee] => GasStrategy::Fixed {
+            max_fee_per_gas: parse_gwei(max_fee)?,
+            max_priority_fee_per_gas: parse_gwei(priority_fee)?,
+        },
+        ["multiplier", multiplier] => {
+            let multiplier: f64 = multiplier.parse().context("Invalid multiplier")?;
+            ensure!(
+                multiplier.is_finite() && multiplier > 0.0,
+                "Multiplier must be positive"
+            );
+            GasStrategy::Multiplier(multiplier)
+        }
+        ["capped", max_fee] => GasStrategy::Capped {
+            max_fee_per_gas: parse_gwei(max_fee)?,
+        },
+        _ => bail!(
+            "Invalid gas strategy {value}, expected fixed:<max_fee_gwei>:<priority_fee_gwei>, multiplier:<multiplier> or capped:<max_fee_gwei>"
+        ),
+    };
+    Ok(strategy)
+}
+
+fn parse_gwei(value: &str) -> anyhow::Result<u128> {
+    let gwei: f64 = value
+        .parse()
+        .with_context(|| format!("Invalid gwei amount {value}"))?;
+    ensure!(
+        gwei.is_finite() && gwei >= 0.0,
+        "Invalid gwei amount {value}"
+    );
+    Ok((gwei * 1e9).round() as u128)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr as _;
+
+    use super::*;
+
+    fn amounts(amounts: &[&str]) -> Vec<String> {
+        amounts.iter().map(|amount| amount.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_amount_in_base_units() {
+        assert_eq!(parse_amount("1", None).unwrap(), U256::from(1));
+        assert_eq!(parse_amount("0", None).unwrap(), U256::from(0));
+        assert!(matches!(
+            parse_amount("1.0", None),
+            Err(FormatTokenInfoError::FractionalAmountNotSupported(_))
+        ));
+        for invalid in ["", "-1", "1e18", "0x10", " 1"] {
+            assert!(
+                matches!(
+                    parse_amount(invalid, None),
+                    Err(FormatTokenInfoError::InvalidAmount(_))
+                ),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_amount_in_whole_tokens() {
+        // an integer and a decimal amount of the same value are scaled alike
+        assert_eq!(parse_amount("1", Some(6)).unwrap(), U256::from(1_000_000));
+        assert_eq!(parse_amount("1.0", Some(6)).unwrap(), U256::from(1_000_000));
+        assert_eq!(parse_amount("1.5", Some(6)).unwrap(), U256::from(1_500_000));
+        assert_eq!(parse_amount(".5", Some(6)).unwrap(), U256::from(500_000));
+        assert_eq!(parse_amount("0.000001", Some(6)).unwrap(), U256::from(1));
+        assert_eq!(parse_amount("7", Some(0)).unwrap(), U256::from(7));
+        assert!(matches!(
+            parse_amount("0.0000001", Some(6)),
+            Err(FormatTokenInfoError::TooManyDecimals { decimals: 6, .. })
+        ));
+        for invalid in ["", ".", "1.2.3", "-1", "1,5"] {
+            assert!(
+                matches!(
+                    parse_amount(invalid, Some(6)),
+                    Err(FormatTokenInfoError::InvalidAmount(_))
+                ),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_format_native_and_erc20() {
+        let token_address = Address::from_bytes_be(&[1; 20]).unwrap();
+        let (address, tokens) = format_token_info(
+            TokenType::NATIVE,
+            amounts(&["1000"]),
+            AmountUnit::Base,
+            None,
+            vec![],
+            None,
+        )
+        .unwrap();
+        assert_eq!(address, Address::zero());
+        assert_eq!(tokens, vec![(U256::from(1000), U256::zero())]);
+
+        let (_, tokens) = format_token_info(
+            TokenType::NATIVE,
+            amounts(&["1"]),
+            AmountUnit::Token,
+            None,
+            vec![],
+            None,
+        )
+        .unwrap();
+        let one_ether = U256::from_str("1000000000000000000").unwrap();
+        assert_eq!(tokens, vec![(one_ether, U256::zero())]);
+
+        let (address, tokens) = format_token_info(
+            TokenType::ERC20,
+            amounts(&["2.5"]),
+            AmountUnit::Token,
+            Some(token_address),
+            vec![],
+            Some(6),
+        )
+        .unwrap();
+        assert_eq!(address, token_address);
+        assert_eq!(tokens, vec![(U256::from(2_500_000), U256::zero())]);
+
+        assert!(matches!(
+            format_token_info(
+                TokenType::ERC20,
+                amounts(&["2.5"]),
+                AmountUnit::Token,
+                Some(token_address),
+                vec![],
+                None,
+            ),
+            Err(FormatTokenInfoError::MissingDecimals)
+        ));
+        assert!(matches!(
+            format_token_info(
+                TokenType::ERC20,
+                amounts(&["2"]),
+                AmountUnit::Base,
+                None,
+                vec![],
+                None,
+            ),
+            Err(FormatTokenInfoError::MissingTokenAddress)
+        ));
+        assert!(matches!(
+            format_token_info(
+                TokenType::NATIVE,
+                amounts(&["1", "2"]),
+                AmountUnit::Base,
+                None,
+                vec![],
+                None,
+            ),
+            Err(FormatTokenInfoError::MultipleValuesNotSupported)
+        ));
+    }
+
+    #[test]
+    fn test_format_nft() {
+        let token_address = Address::from_bytes_be(&[1; 20]).unwrap();
+        let (_, tokens) = format_token_info(
+            TokenType::ERC721,
+            vec![],
+            AmountUnit::Base,
+            Some(token_address),
+            vec![U256::from(7)],
+            None,
+        )
+        .unwrap();
+        assert_eq!(tokens, vec![(U256::one(), U256::from(7))]);
+        assert!(matches!(
+            format_token_info(
+                TokenType::ERC721,
+                amounts(&["2"]),
+                AmountUnit::Base,
+                Some(token_address),
+                vec![U256::from(7)],
+                None,
+            ),
+            Err(FormatTokenInfoError::ERC721AmountMustBeOne(_))
+        ));
+        // NFT amounts are counts, even in whole token units
+        assert!(matches!(
+            format_token_info(
+                TokenType::ERC1155,
+                amounts(&["1.5"]),
+                AmountUnit::Token,
+                Some(token_address),
+                vec![U256::from(7)],
+                None,
+            ),
+            Err(FormatTokenInfoError::FractionalAmountNotSupported(_))
+        ));
+
+        let (_, tokens) = format_token_info(
+            TokenType::ERC1155,
+            amounts(&["3", "4"]),
+            AmountUnit::Base,
+            Some(token_address),
+            vec![U256::from(7), U256::from(8)],
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                (U256::from(3), U256::from(7)),
+                (U256::from(4), U256::from(8))
+            ]
+        );
+        assert!(matches!(
+            format_token_info(
+                TokenType::ERC1155,
+                amounts(&["3", "4"]),
+                AmountUnit::Base,
+                Some(token_address),
+                vec![U256::from(7)],
+                None,
+            ),
+            Err(FormatTokenInfoError::LengthMismatch {
+                token_ids: 1,
+                amounts: 2
+            })
+        ));
+    }
+}
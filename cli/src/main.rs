@@ -6,31 +6,42 @@ use colored::{control, Colorize as _};
 use intmax2_cli::{
     args::{Args, Commands},
     cli::{
-        backup::{incorporate_backup, make_history_backup},
+        backup::{dump_state, incorporate_backup, make_history_backup, merge_backups},
         claim::{claim_builder_reward, claim_withdrawals},
-        deposit::deposit,
+        deposit::{deposit, get_erc20_decimals},
         error::CliError,
         get::{
-            balance, check_validity_prover, claim_status, get_payment_memos, get_user_data,
-            mining_list, withdrawal_status,
+            audit_consistency, balance, check_validity_prover, claim_status, compare_roots,
+            get_payment_memos, get_user_data, list_topics, mining_list, withdrawal_status,
         },
         history::history,
         key_derivation::derive_key_from_eth,
-        send::send_transfers,
+        send::{send_transfers, send_transfers_in_chunks},
         sync::{resync, sync_claims, sync_withdrawals},
         withdrawal::send_withdrawal,
     },
-    format::{format_token_info, parse_generic_address, privkey_to_keyset},
+    format::{
+        format_token_info, privkey_to_keyset, resolve_generic_address, AddressBook, AmountUnit,
+    },
 };
-use intmax2_client_sdk::client::{
-    key_from_eth::generate_intmax_account_from_eth_key, sync::utils::generate_salt,
+use intmax2_client_sdk::{
+    client::{
+        client::PaymentMemoEntry,
+        key_from_eth::generate_intmax_account_from_eth_key,
+        misc::payment_memo::payment_memo_topic,
+        operation::current_operation_id,
+        strategy::error::{PendingTxInfo, PendingTxKind},
+        sync::utils::generate_salt,
+    },
+    external_api::contract::handlers::GasOptions,
 };
-use intmax2_interfaces::utils::random::default_rng;
+use intmax2_interfaces::{data::deposit_data::TokenType, utils::random::default_rng};
 use intmax2_zkp::{
     common::{signature_content::key_set::KeySet, transfer::Transfer},
     ethereum_types::{u256::U256, u32limb_trait::U32LimbTrait},
 };
 use serde::Deserialize;
+use std::{io::Write as _, path::Path, time::Duration};
 
 const MAX_BATCH_TRANSFER: usize = 63;
 
@@ -43,19 +54,43 @@ async fn main() -> anyhow::Result<()> {
 
     env_logger::builder()
         .filter_level(log::LevelFilter::Info)
+        .format(|buf, record| {
+            // tag the lines logged during a client operation with its id
+            let operation_id = current_operation_id()
+                .map(|id| format!(" op={id}"))
+                .unwrap_or_default();
+            writeln!(
+                buf,
+                "[{} {} {}{operation_id}] {}",
+                buf.timestamp(),
+                record.level(),
+                record.target(),
+                record.args()
+            )
+        })
         .init();
     let args = Args::parse();
 
     dotenvy::dotenv().ok();
 
-    match main_process(args.command).await {
+    if args.json {
+        // keep stdout machine-parseable
+        colored::control::set_override(false);
+    }
+
+    match main_process(args.command, args.json).await {
         Ok(_) => {}
+        Err(e) if args.json => {
+            let output = match e.pending_tx_info() {
+                Some(info) => serde_json::json!({ "error": e.to_string(), "pendingTx": info }),
+                None => serde_json::json!({ "error": e.to_string() }),
+            };
+            println!("{output}");
+            std::process::exit(1);
+        }
         Err(e) => {
-            if matches!(e, CliError::PendingTxError) {
-                println!(
-                    "{}",
-                    "There are pending sent tx. Please try again later.".red()
-                );
+            if let Some(info) = e.pending_tx_info() {
+                println!("{}", format_pending_tx(info).red());
                 std::process::exit(1);
             }
             println!("{}", e.to_string().red());
@@ -65,7 +100,22 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
-async fn main_process(command: Commands) -> Result<(), CliError> {
+fn format_pending_tx(info: &PendingTxInfo) -> String {
+    let reason = match info.kind {
+        PendingTxKind::Send => format!("There is a pending sent tx {}.", info.tx_digest),
+        PendingTxKind::Receive => format!(
+            "Tx {} is waiting for pending deposits or transfers.",
+            info.tx_digest
+        ),
+    };
+    let now = chrono::Utc::now().timestamp() as u64;
+    match info.retry_after_secs(now) {
+        0 => format!("{reason} Please try again shortly."),
+        secs => format!("{reason} Please retry in ~{secs}s."),
+    }
+}
+
+async fn main_process(command: Commands, json: bool) -> Result<(), CliError> {
     match command {
         Commands::Transfer {
             private_key,
@@ -73,6 +123,8 @@ async fn main_process(command: Commands) -> Result<(), CliError> {
             amount,
             token_index,
             fee_token_index,
+            memo,
+            memo_name,
             wait,
         } => {
             let key = privkey_to_keyset(private_key);
@@ -82,10 +134,18 @@ async fn main_process(command: Commands) -> Result<(), CliError> {
                 token_index,
                 salt: generate_salt(),
             };
+            let payment_memos = memo
+                .map(|memo| PaymentMemoEntry {
+                    transfer_index: 0,
+                    topic: payment_memo_topic(&memo_name),
+                    memo,
+                })
+                .into_iter()
+                .collect();
             send_transfers(
                 key,
                 &[transfer],
-                vec![],
+                payment_memos,
                 fee_token_index.unwrap_or_default(),
                 wait,
             )
@@ -116,56 +176,112 @@ async fn main_process(command: Commands) -> Result<(), CliError> {
         Commands::BatchTransfer {
             private_key,
             csv_path,
+            address_book,
             fee_token_index,
             wait,
+            auto_split,
         } => {
             let key = privkey_to_keyset(private_key);
+            let address_book = address_book
+                .map(|path| AddressBook::load(Path::new(&path)))
+                .transpose()
+                .map_err(|e| CliError::ParseError(format!("{e:#}")))?;
             let mut reader = csv::Reader::from_path(csv_path)?;
             let mut transfers = vec![];
-            for result in reader.deserialize() {
+            let mut recipients = vec![];
+            for (row, result) in reader.deserialize().enumerate() {
                 let transfer_input: TransferInput = result?;
                 transfers.push(Transfer {
-                    recipient: parse_generic_address(&transfer_input.recipient)
-                        .map_err(|e| CliError::ParseError(e.to_string()))?,
+                    recipient: resolve_generic_address(
+                        &transfer_input.recipient,
+                        address_book.as_ref(),
+                    )
+                    .map_err(|e| CliError::ParseError(format!("CSV row {}: {e:#}", row + 1)))?,
                     amount: transfer_input.amount,
                     token_index: transfer_input.token_index,
                     salt: generate_salt(),
                 });
+                recipients.push(transfer_input.recipient);
             }
-            if transfers.len() > MAX_BATCH_TRANSFER {
-                return Err(CliError::TooManyTransfer(transfers.len()));
+            if auto_split {
+                send_transfers_in_chunks(
+                    key,
+                    &transfers,
+                    &recipients,
+                    fee_token_index.unwrap_or_default(),
+                    MAX_BATCH_TRANSFER,
+                )
+                .await?;
+            } else {
+                if transfers.len() > MAX_BATCH_TRANSFER {
+                    return Err(CliError::TooManyTransfer(transfers.len()));
+                }
+                send_transfers(
+                    key,
+                    &transfers,
+                    vec![],
+                    fee_token_index.unwrap_or_default(),
+                    wait,
+                )
+                .await?;
             }
-            send_transfers(
-                key,
-                &transfers,
-                vec![],
-                fee_token_index.unwrap_or_default(),
-                wait,
-            )
-            .await?;
         }
         Commands::Deposit {
             eth_private_key,
             private_key,
             amount,
+            unit,
             token_type,
             token_address,
             token_id,
             mining,
+            gas_strategy,
+            nonce,
+            wait,
+            wait_timeout,
         } => {
             let key = privkey_to_keyset(private_key);
-            let (amount, token_address, token_id) =
-                format_token_info(token_type, amount, token_address, token_id)?;
-            deposit(
-                key,
-                eth_private_key,
+            // decimals are only needed to scale amounts given in whole tokens
+            let erc20_decimals = match token_address {
+                Some(token_address)
+                    if token_type == TokenType::ERC20 && unit == AmountUnit::Token =>
+                {
+                    get_erc20_decimals(token_address).await?
+                }
+                _ => None,
+            };
+            let (token_address, tokens) = format_token_info(
                 token_type,
                 amount,
+                unit,
                 token_address,
                 token_id,
+                erc20_decimals,
+            )?;
+            let gas_options = GasOptions {
+                strategy: gas_strategy,
+                nonce,
+            };
+            let deposit_results = deposit(
+                key,
+                eth_private_key,
+                token_type,
+                token_address,
+                &tokens,
                 mining,
+                gas_options,
+                wait,
+                wait_timeout,
             )
             .await?;
+            for deposit_result in deposit_results {
+                println!(
+                    "Deposit digest: {}, token id: {}, amount: {}",
+                    deposit_result.deposit_digest,
+                    deposit_result.deposit_data.token_id,
+                    deposit_result.deposit_data.amount
+                );
+            }
         }
         Commands::SyncWithdrawals {
             private_key,
@@ -190,11 +306,11 @@ async fn main_process(command: Commands) -> Result<(), CliError> {
             without_sync,
         } => {
             let key = privkey_to_keyset(private_key);
-            balance(key, !without_sync).await?;
+            balance(key, !without_sync, json).await?;
         }
         Commands::UserData { private_key } => {
             let key = privkey_to_keyset(private_key);
-            get_user_data(key).await?;
+            get_user_data(key, json).await?;
         }
         Commands::History {
             private_key,
@@ -205,49 +321,112 @@ async fn main_process(command: Commands) -> Result<(), CliError> {
             let order = order.unwrap_or_default();
             history(key, order, from).await?;
         }
-        Commands::WithdrawalStatus { private_key } => {
+        Commands::WithdrawalStatus {
+            private_key,
+            watch,
+            interval_secs,
+        } => {
             let key = privkey_to_keyset(private_key);
-            withdrawal_status(key).await?;
+            let watch_interval = watch.then(|| Duration::from_secs(interval_secs));
+            withdrawal_status(key, json, watch_interval).await?;
         }
         Commands::MiningList { private_key } => {
             let key = privkey_to_keyset(private_key);
-            mining_list(key).await?;
+            mining_list(key, json).await?;
         }
-        Commands::ClaimStatus { private_key } => {
+        Commands::ClaimStatus {
+            private_key,
+            watch,
+            interval_secs,
+        } => {
             let key = privkey_to_keyset(private_key);
-            claim_status(key).await?;
+            let watch_interval = watch.then(|| Duration::from_secs(interval_secs));
+            claim_status(key, json, watch_interval).await?;
         }
         Commands::PaymentMemos { private_key, name } => {
             let key = privkey_to_keyset(private_key);
             get_payment_memos(key, &name).await?;
         }
+        Commands::ListTopics { private_key } => {
+            let key = privkey_to_keyset(private_key);
+            list_topics(key).await?;
+        }
         Commands::ClaimWithdrawals {
             private_key,
             eth_private_key,
+            gas_strategy,
+            nonce,
         } => {
             let key = privkey_to_keyset(private_key);
-            claim_withdrawals(key, eth_private_key).await?;
+            let gas_options = GasOptions {
+                strategy: gas_strategy,
+                nonce,
+            };
+            claim_withdrawals(key, eth_private_key, gas_options).await?;
         }
-        Commands::Resync { private_key, deep } => {
+        Commands::Resync {
+            private_key,
+            deep,
+            resume,
+            token_index,
+        } => {
             let key = privkey_to_keyset(private_key);
-            resync(key, deep).await?;
+            resync(key, deep, resume, token_index).await?;
         }
         Commands::MakeBackup {
             private_key,
             dir,
             from,
+            resume,
+            rsa_public_key_path,
         } => {
             let key = privkey_to_keyset(private_key);
             let from = from.unwrap_or_default();
             let dir = dir.unwrap_or_default();
-            make_history_backup(key, &dir, from).await?;
+            make_history_backup(key, &dir, from, resume, rsa_public_key_path.as_deref()).await?;
         }
-        Commands::IncorporateBackup { path } => {
-            incorporate_backup(&path)?;
+        Commands::IncorporateBackup {
+            path,
+            strict,
+            aes_key,
+        } => {
+            let aes_key = aes_key
+                .map(|key| hex::decode(key.trim_start_matches("0x")))
+                .transpose()
+                .map_err(|e| CliError::ParseError(format!("Failed to parse AES key: {e}")))?;
+            incorporate_backup(&path, strict, aes_key.as_deref())?;
+        }
+        Commands::MergeBackups {
+            private_key,
+            input_dirs,
+            dir,
+        } => {
+            let key = privkey_to_keyset(private_key);
+            merge_backups(key, &input_dirs, &dir)?;
+        }
+        Commands::DumpState {
+            private_key,
+            rsa_public_key_path,
+            path,
+        } => {
+            let key = privkey_to_keyset(private_key);
+            dump_state(key, &rsa_public_key_path, path.as_deref()).await?;
         }
         Commands::CheckValidityProver => {
             check_validity_prover().await?;
         }
+        Commands::AuditConsistency { private_key } => {
+            let key = privkey_to_keyset(private_key);
+            audit_consistency(key, json).await?;
+        }
+        Commands::CompareRoots {
+            prover_url_a,
+            prover_url_b,
+            from,
+            to,
+        } => {
+            compare_roots(&prover_url_a, &prover_url_b, from, to).await?;
+        }
         Commands::GenerateKey => {
             let mut rng = default_rng();
             let key = KeySet::rand(&mut rng);
@@ -267,11 +446,13 @@ async fn main_process(command: Commands) -> Result<(), CliError> {
             eth_private_key,
             redeposit_index,
             wallet_index,
+            derivation_context,
         } => {
             let key = derive_key_from_eth(
                 eth_private_key,
                 redeposit_index.unwrap_or_default(),
                 wallet_index.unwrap_or_default(),
+                derivation_context.as_deref(),
             )
             .await?;
             println!("Private key: {}", key.privkey.to_hex());
@@ -0,0 +1,277 @@
+use intmax2_interfaces::data::{
+    data_type::DataType, meta_data::MetaData, user_data::ProcessStatus,
+};
+use intmax2_zkp::{
+    circuits::balance::balance_pis::BalancePublicInputs,
+    common::signature_content::key_set::KeySet, ethereum_types::bytes32::Bytes32,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    balance_at_block::fetch_all,
+    client::Client,
+    error::ClientError,
+    history::{EntryStatus, HistoryEntry},
+    strategy::common::fetch_sender_proof_set,
+};
+
+/// Result of `audit_consistency`. The local state is consistent if every list is empty.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsistencyReport {
+    /// Latest block number of the validity prover at the time of the audit
+    pub validity_prover_block_number: u32,
+    /// Block number of the balance proof in the user data
+    pub balance_proof_block_number: Option<u32>,
+    /// Processed entries whose block the validity prover does not know about
+    pub unknown_block_entries: Vec<AuditEntry>,
+    /// Processed entries that could not be fetched or decrypted from the store vault
+    pub missing_entries: Vec<AuditEntry>,
+    /// Breaks in the balance proof chain formed by the sent txs
+    pub balance_proof_chain_breaks: Vec<BalanceProofChainBreak>,
+}
+
+impl ConsistencyReport {
+    pub fn is_consistent(&self) -> bool {
+        self.unknown_block_entries.is_empty()
+            && self.missing_entries.is_empty()
+            && self.balance_proof_chain_breaks.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    pub data_type: DataType,
+    pub digest: Bytes32,
+    /// Block number reported for the entry, if any
+    pub block_number: Option<u32>,
+    pub reason: String,
+}
+
+/// A sent tx whose previous balance proof does not continue the chain. Each tx must be proven
+/// on top of a balance proof that is older than the tx block, and at least as new as the block
+/// of the preceding tx.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BalanceProofChainBreak {
+    /// Digest of the tx, or `None` if the latest balance proof in the user data is older than
+    /// the last tx
+    pub tx_digest: Option<Bytes32>,
+    /// Block number of the tx, or of the last tx
+    pub block_number: u32,
+    /// Block number of the balance proof the tx was proven on, or of the latest balance proof
+    pub prev_balance_proof_block_number: Option<u32>,
+    /// Block number of the preceding tx
+    pub prev_tx_block_number: Option<u32>,
+}
+
+/// Checks every processed deposit, transfer and tx in the user data of `key` against the
+/// validity prover, and checks that the balance proof chain of the sent txs is monotonic and
+/// gap-free.
+pub async fn audit_consistency(
+    client: &Client,
+    key: KeySet,
+) -> Result<ConsistencyReport, ClientError> {
+    let user_data = client.get_user_data(key).await?;
+    let validity_prover_block_number = client.validity_prover.get_block_number().await?;
+    let balance_proof_block_number = if user_data.balance_proof.is_some() {
+        Some(
+            user_data
+                .block_number()
+                .map_err(|e| ClientError::UnexpectedError(e.to_string()))?,
+        )
+    } else {
+        None
+    };
+
+    let deposits =
+        fetch_all(|cursor| async move { client.fetch_deposit_history(key, &cursor).await }).await?;
+    let transfers =
+        fetch_all(|cursor| async move { client.fetch_transfer_history(key, &cursor).await })
+            .await?;
+    let txs =
+        fetch_all(|cursor| async move { client.fetch_tx_history(key, &cursor).await }).await?;
+
+    let mut unknown_block_entries = Vec::new();
+    let mut missing_entries = Vec::new();
+    for (data_type, status, history) in [
+        (
+            DataType::Deposit,
+            &user_data.deposit_status,
+            entry_statuses(&deposits),
+        ),
+        (
+            DataType::Transfer,
+            &user_data.transfer_status,
+            entry_statuses(&transfers),
+        ),
+        (DataType::Tx, &user_data.tx_status, entry_statuses(&txs)),
+    ] {
+        let (unknown, missing) =
+            audit_processed(data_type, status, &history, validity_prover_block_number);
+        unknown_block_entries.extend(unknown);
+        missing_entries.extend(missing);
+    }
+
+    // (tx digest, tx block number, previous balance proof block number)
+    let mut sent_txs = Vec::new();
+    for entry in txs.iter() {
+        let EntryStatus::Processed(block_number) = entry.status else {
+            continue;
+        };
+        let prev_block_number = match fetch_sender_proof_set(
+            client.store_vault_server.as_ref(),
+            entry.data.sender_proof_set_ephemeral_key,
+        )
+        .await
+        {
+            Ok(sender_proof_set) => {
+                let prev_balance_proof = sender_proof_set.prev_balance_proof.decompress()?;
+                let prev_balance_pis =
+                    BalancePublicInputs::from_pis(&prev_balance_proof.public_inputs)
+                        .map_err(|e| ClientError::UnexpectedError(e.to_string()))?;
+                Some(prev_balance_pis.public_state.block_number)
+            }
+            Err(e) => {
+                log::warn!(
+                    "failed to fetch sender proof set of tx {}: {e}",
+                    entry.meta.digest
+                );
+                None
+            }
+        };
+        sent_txs.push((entry.meta.digest, block_number, prev_block_number));
+    }
+    let balance_proof_chain_breaks =
+        check_balance_proof_chain(sent_txs, balance_proof_block_number);
+
+    Ok(ConsistencyReport {
+        validity_prover_block_number,
+        balance_proof_block_number,
+        unknown_block_entries,
+        missing_entries,
+        balance_proof_chain_breaks,
+    })
+}
+
+fn entry_statuses<T>(entries: &[HistoryEntry<T>]) -> Vec<(MetaData, EntryStatus)> {
+    entries
+        .iter()
+        .map(|entry| (entry.meta.clone(), entry.status.clone()))
+        .collect()
+}
+
+/// Returns the processed entries whose block is unknown to the validity prover, and the
+/// processed entries that are missing from the history.
+fn audit_processed(
+    data_type: DataType,
+    status: &ProcessStatus,
+    history: &[(MetaData, EntryStatus)],
+    validity_prover_block_number: u32,
+) -> (Vec<AuditEntry>, Vec<AuditEntry>) {
+    let mut unknown = Vec::new();
+    let mut missing = Vec::new();
+    for digest in status.processed_digests.iter() {
+        let entry = |block_number, reason: &str| AuditEntry {
+            data_type,
+            digest: *digest,
+            block_number,
+            reason: reason.to_string(),
+        };
+        match history.iter().find(|(meta, _)| meta.digest == *digest) {
+            None => missing.push(entry(None, "not found in the store vault")),
+            Some((_, EntryStatus::Pending | EntryStatus::Timeout)) => unknown.push(entry(
+                None,
+                "the validity prover does not know the block of this entry",
+            )),
+            Some((
+                _,
+                EntryStatus::Settled(block_number) | EntryStatus::Processed(block_number),
+            )) => {
+                if *block_number > validity_prover_block_number {
+                    unknown.push(entry(
+                        Some(*block_number),
+                        "the block is ahead of the validity prover",
+                    ));
+                }
+            }
+        }
+    }
+    (unknown, missing)
+}
+
+/// Checks that each tx, ordered by block number, was proven on a balance proof older than the
+/// tx and not older than the preceding tx, and that the latest balance proof is not older than
+/// the last tx.
+fn check_balance_proof_chain(
+    mut sent_txs: Vec<(Bytes32, u32, Option<u32>)>,
+    latest_block_number: Option<u32>,
+) -> Vec<BalanceProofChainBreak> {
+    sent_txs.sort_by_key(|(_, block_number, _)| *block_number);
+    let mut breaks = Vec::new();
+    let mut prev_tx_block_number = None;
+    for (tx_digest, block_number, prev_balance_proof_block_number) in sent_txs {
+        let is_continuous = match prev_balance_proof_block_number {
+            Some(prev) => prev < block_number && prev_tx_block_number.is_none_or(|b| prev >= b),
+            None => false,
+        };
+        if !is_continuous {
+            breaks.push(BalanceProofChainBreak {
+                tx_digest: Some(tx_digest),
+                block_number,
+                prev_balance_proof_block_number,
+                prev_tx_block_number,
+            });
+        }
+        prev_tx_block_number = Some(block_number);
+    }
+    if let Some(last_tx_block_number) = prev_tx_block_number {
+        if latest_block_number.is_none_or(|latest| latest < last_tx_block_number) {
+            breaks.push(BalanceProofChainBreak {
+                tx_digest: None,
+                block_number: last_tx_block_number,
+                prev_balance_proof_block_number: latest_block_number,
+                prev_tx_block_number: None,
+            });
+        }
+    }
+    breaks
+}
+
+#[cfg(test)]
+mod tests {
+    use intmax2_zkp::ethereum_types::{bytes32::Bytes32, u32limb_trait::U32LimbTrait as _};
+
+    use super::check_balance_proof_chain;
+
+    fn digest(i: u32) -> Bytes32 {
+        Bytes32::from_u32_slice(&[0, 0, 0, 0, 0, 0, 0, i]).unwrap()
+    }
+
+    #[test]
+    fn test_continuous_chain() {
+        let txs = vec![
+            (digest(2), 20, Some(15)),
+            (digest(1), 10, Some(0)),
+            (digest(3), 30, Some(20)),
+        ];
+        assert!(check_balance_proof_chain(txs, Some(35)).is_empty());
+    }
+
+    #[test]
+    fn test_chain_breaks() {
+        // tx 2 was proven on a balance proof older than tx 1
+        let txs = vec![(digest(1), 10, Some(0)), (digest(2), 20, Some(5))];
+        let breaks = check_balance_proof_chain(txs, Some(20));
+        assert_eq!(breaks.len(), 1);
+        assert_eq!(breaks[0].tx_digest, Some(digest(2)));
+        assert_eq!(breaks[0].prev_tx_block_number, Some(10));
+
+        // the latest balance proof does not include the last tx
+        let txs = vec![(digest(1), 10, Some(0))];
+        let breaks = check_balance_proof_chain(txs, Some(5));
+        assert_eq!(breaks.len(), 1);
+        assert_eq!(breaks[0].tx_digest, None);
+    }
+}
@@ -1,34 +1,335 @@
+use std::collections::BTreeMap;
+
 use intmax2_interfaces::{
     api::store_vault_server::types::{CursorOrder, MetaDataCursor},
     data::{
-        data_type::DataType, encryption::BlsEncryption, meta_data::MetaData,
+        data_type::DataType,
+        deposit_data::DepositData,
+        encryption::{rsa::RsaEncryptedMessage, BlsEncryption, RsaEncryption},
+        meta_data::MetaData,
+        rw_rights::WriteRights,
         transfer_data::TransferData,
+        tx_data::TxData,
+        validation::Validation,
     },
     utils::digest::get_digest,
 };
-use intmax2_zkp::{common::signature_content::key_set::KeySet, ethereum_types::bytes32::Bytes32};
+use intmax2_zkp::{
+    common::signature_content::key_set::KeySet,
+    ethereum_types::{bytes32::Bytes32, u32limb_trait::U32LimbTrait as _},
+};
+use rsa::{pkcs1::DecodeRsaPublicKey as _, pkcs8::DecodePublicKey as _, RsaPublicKey};
+use serde::{Deserialize, Serialize};
 
 use crate::external_api::local_backup_store_vault::diff_data_client::{
-    make_backup_csv_from_records, DiffRecord,
+    make_backup_csv_from_records, DiffDataClient, DiffRecord,
 };
 
 use super::{client::Client, strategy::error::StrategyError};
 
+/// A chunk of a history backup.
+#[derive(Debug, Clone)]
+pub struct HistoryBackupChunk {
+    /// The CSV of the chunk, or the serialized `EncryptedBackupChunk` if the backup is
+    /// encrypted with an RSA public key.
+    pub content: String,
+    /// All history entries up to and including this cursor are contained in this chunk or in
+    /// the preceding ones. Passing it to `make_history_backup_from_cursor` resumes the backup
+    /// right after this chunk without dropping or duplicating entries.
+    pub cursor: MetaData,
+}
+
+/// An encrypted backup chunk. Plain chunks are raw CSV, so the importer can tell the two
+/// apart by whether the content parses as this type.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "scheme", rename_all = "camelCase")]
+pub enum EncryptedBackupChunk {
+    /// The CSV is encrypted with a random AES-256-GCM key, and the AES key is encrypted with
+    /// RSA-OAEP (SHA-256) so that it can be unwrapped by a KMS holding the private key.
+    RsaAes256Gcm { message: RsaEncryptedMessage },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BackupChunkPayload {
+    csv: String,
+}
+
+impl RsaEncryption for BackupChunkPayload {}
+
+impl EncryptedBackupChunk {
+    pub fn encrypt(csv: &str, pubkey: &RsaPublicKey) -> Self {
+        let payload = BackupChunkPayload {
+            csv: csv.to_string(),
+        };
+        EncryptedBackupChunk::RsaAes256Gcm {
+            message: payload.encrypt_with_rsa(pubkey),
+        }
+    }
+
+    /// Parses the content of a backup chunk. Returns `None` if the chunk is plain CSV.
+    pub fn parse(content: &str) -> Option<Self> {
+        serde_json::from_str(content).ok()
+    }
+
+    /// The RSA-encrypted AES key, which should be unwrapped by the holder of the private key
+    /// (e.g. with `decrypt_aes_key`).
+    pub fn encrypted_key(&self) -> &[u8] {
+        match self {
+            EncryptedBackupChunk::RsaAes256Gcm { message } => &message.encrypted_key,
+        }
+    }
+
+    /// Decrypts the chunk with the unwrapped AES key and returns the CSV.
+    pub fn decrypt_with_aes_key(&self, aes_key: &[u8]) -> Result<String, StrategyError> {
+        match self {
+            EncryptedBackupChunk::RsaAes256Gcm { message } => {
+                let payload = BackupChunkPayload::decrypt_with_aes_key(aes_key, message)
+                    .map_err(|e| StrategyError::BackupEncryptionError(e.to_string()))?;
+                Ok(payload.csv)
+            }
+        }
+    }
+
+    pub fn to_content(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+/// A history entry decoded from a backup chunk. The status is not included because it can only
+/// be determined online.
+#[derive(Debug, Clone)]
+pub enum BackupHistoryEntry {
+    Deposit(MetaData, DepositData),
+    Transfer(MetaData, TransferData),
+    Withdrawal(MetaData, TransferData),
+    Tx(MetaData, TxData),
+}
+
+/// Decodes the history entries of a plain CSV backup chunk with `key`, without incorporating
+/// them into any store. Snapshots (user data and sender proof sets) are skipped, as are entries
+/// that fail to decrypt or validate.
+pub fn decode_history_backup_chunk(
+    key: KeySet,
+    csv: &str,
+) -> Result<Vec<BackupHistoryEntry>, StrategyError> {
+    let records = DiffDataClient
+        .parse(csv)
+        .map_err(|e| StrategyError::UnexpectedError(format!("failed to parse backup csv: {e}")))?;
+    let mut entries = Vec::new();
+    for record in records {
+        let meta = MetaData {
+            timestamp: record.timestamp,
+            digest: record.digest,
+        };
+        let entry = if record.topic == DataType::Deposit.to_topic() {
+            decrypt_record(key, DataType::Deposit, &record)
+                .map(|data| BackupHistoryEntry::Deposit(meta, data))
+        } else if record.topic == DataType::Transfer.to_topic() {
+            decrypt_record(key, DataType::Transfer, &record)
+                .map(|data| BackupHistoryEntry::Transfer(meta, data))
+        } else if record.topic == DataType::Withdrawal.to_topic() {
+            decrypt_record(key, DataType::Withdrawal, &record)
+                .map(|data| BackupHistoryEntry::Withdrawal(meta, data))
+        } else if record.topic == DataType::Tx.to_topic() {
+            decrypt_record(key, DataType::Tx, &record)
+                .map(|data| BackupHistoryEntry::Tx(meta, data))
+        } else {
+            None
+        };
+        entries.extend(entry);
+    }
+    Ok(entries)
+}
+
+fn decrypt_record<T: BlsEncryption + Validation>(
+    key: KeySet,
+    data_type: DataType,
+    record: &DiffRecord,
+) -> Option<T> {
+    let enc_sender = match data_type.rw_rights().write_rights {
+        WriteRights::SingleAuthWrite | WriteRights::AuthWrite => Some(key.pubkey),
+        WriteRights::SingleOpenWrite | WriteRights::OpenWrite => None,
+    };
+    match T::decrypt(key, enc_sender, &record.data) {
+        Ok(data) => match data.validate(key.pubkey) {
+            Ok(_) => Some(data),
+            Err(e) => {
+                log::warn!("failed to validate {data_type} {}: {e}", record.digest);
+                None
+            }
+        },
+        Err(e) => {
+            log::warn!("failed to decrypt {data_type} {}: {e}", record.digest);
+            None
+        }
+    }
+}
+
+/// Merges plain CSV history backups, e.g. made on different devices, into one backup of
+/// `chunk_size` records per chunk. History entries are deduplicated by digest and ordered by
+/// (timestamp, digest), each transfer followed by its sender proof set, and the newest user data
+/// snapshot is placed in the last chunk. Fails if two entries share a digest but differ, or if an
+/// entry does not belong to `key`.
+pub fn merge_history_backups(
+    key: KeySet,
+    inputs: Vec<Vec<String>>,
+    chunk_size: usize,
+) -> Result<Vec<String>, StrategyError> {
+    if chunk_size == 0 {
+        return Err(StrategyError::UnexpectedError(
+            "chunk size must be greater than 0".to_string(),
+        ));
+    }
+    let pubkey: Bytes32 = key.pubkey.into();
+    // history entries by hex digest, each with the sender proof sets that follow it
+    let mut entries: BTreeMap<String, (DiffRecord, Vec<DiffRecord>)> = BTreeMap::new();
+    let mut user_data: Option<DiffRecord> = None;
+    for content in inputs.iter().flatten() {
+        if EncryptedBackupChunk::parse(content).is_some() {
+            return Err(StrategyError::BackupEncryptionError(
+                "encrypted backup chunks must be decrypted before merging".to_string(),
+            ));
+        }
+        let records = DiffDataClient.parse(content).map_err(|e| {
+            StrategyError::UnexpectedError(format!("failed to parse backup csv: {e}"))
+        })?;
+        let mut last_digest = None;
+        for record in records {
+            if record.topic == DataType::SenderProofSet.to_topic() {
+                let Some((_, proof_sets)) = last_digest.as_ref().and_then(|d| entries.get_mut(d))
+                else {
+                    return Err(StrategyError::ValidationError(format!(
+                        "sender proof set {} does not follow a transfer",
+                        record.digest
+                    )));
+                };
+                record
+                    .verify()
+                    .map_err(|e| StrategyError::ValidationError(e.to_string()))?;
+                if !proof_sets.iter().any(|r| r.digest == record.digest) {
+                    proof_sets.push(record);
+                }
+                continue;
+            }
+            if record.pubkey != pubkey {
+                return Err(StrategyError::ValidationError(format!(
+                    "entry {} belongs to another pubkey",
+                    record.digest
+                )));
+            }
+            if record.topic == DataType::UserData.to_topic() {
+                record
+                    .verify()
+                    .map_err(|e| StrategyError::ValidationError(e.to_string()))?;
+                if user_data
+                    .as_ref()
+                    .is_none_or(|u| u.timestamp < record.timestamp)
+                {
+                    user_data = Some(record);
+                }
+                continue;
+            }
+            let digest = record.digest.to_hex();
+            last_digest = Some(digest.clone());
+            match entries.get(&digest) {
+                Some((existing, _)) => {
+                    if !is_same_record(existing, &record) {
+                        return Err(StrategyError::BackupConflict(record.digest));
+                    }
+                }
+                None => {
+                    record
+                        .verify()
+                        .map_err(|e| StrategyError::ValidationError(e.to_string()))?;
+                    entries.insert(digest, (record, Vec::new()));
+                }
+            }
+        }
+    }
+
+    let mut groups = entries
+        .into_values()
+        .map(|(record, proof_sets)| {
+            let meta = MetaData {
+                timestamp: record.timestamp,
+                digest: record.digest,
+            };
+            let mut group = vec![record];
+            group.extend(proof_sets);
+            (meta, group)
+        })
+        .collect::<Vec<_>>();
+    groups.sort_by(|(a, _), (b, _)| a.cmp(b));
+    if let Some(user_data) = user_data {
+        let last_cursor = groups
+            .last()
+            .map_or(MetaData::default(), |(meta, _)| meta.clone());
+        groups.push((last_cursor, vec![user_data]));
+    }
+
+    pack_into_chunks(MetaData::default(), groups, chunk_size)
+        .into_iter()
+        .map(|(_, records)| {
+            make_backup_csv_from_records(&records).map_err(|e| {
+                StrategyError::UnexpectedError(format!("failed to make backup csv: {e}"))
+            })
+        })
+        .collect()
+}
+
+fn is_same_record(a: &DiffRecord, b: &DiffRecord) -> bool {
+    a.topic == b.topic && a.pubkey == b.pubkey && a.timestamp == b.timestamp && a.data == b.data
+}
+
+/// Parses an RSA public key in either SPKI ("BEGIN PUBLIC KEY") or PKCS#1 ("BEGIN RSA PUBLIC
+/// KEY") PEM format.
+pub fn parse_rsa_public_key_pem(pem: &str) -> Result<RsaPublicKey, StrategyError> {
+    RsaPublicKey::from_public_key_pem(pem)
+        .or_else(|_| RsaPublicKey::from_pkcs1_pem(pem))
+        .map_err(|e| {
+            StrategyError::BackupEncryptionError(format!("failed to parse RSA public key: {e}"))
+        })
+}
+
 pub async fn make_history_backup(
     client: &Client,
     key: KeySet,
     from: u64,
     chunk_size: usize,
+    rsa_pubkey: Option<&RsaPublicKey>,
 ) -> Result<Vec<String>, StrategyError> {
+    let cursor = MetaData {
+        timestamp: from,
+        digest: Bytes32::default(),
+    };
+    let chunks =
+        make_history_backup_from_cursor(client, key, cursor, chunk_size, rsa_pubkey).await?;
+    Ok(chunks.into_iter().map(|chunk| chunk.content).collect())
+}
+
+/// Makes a history backup of the entries strictly after `cursor`, ordered by (timestamp,
+/// digest). Each transfer is kept in the same chunk as its sender proof set, and the user data
+/// snapshot is placed in the last chunk. If `rsa_pubkey` is given, each chunk is encrypted
+/// as an `EncryptedBackupChunk`.
+pub async fn make_history_backup_from_cursor(
+    client: &Client,
+    key: KeySet,
+    cursor: MetaData,
+    chunk_size: usize,
+    rsa_pubkey: Option<&RsaPublicKey>,
+) -> Result<Vec<HistoryBackupChunk>, StrategyError> {
+    if chunk_size == 0 {
+        return Err(StrategyError::UnexpectedError(
+            "chunk size must be greater than 0".to_string(),
+        ));
+    }
+    let start_cursor = cursor.clone();
     let cursor = MetaDataCursor {
-        cursor: Some(MetaData {
-            timestamp: from,
-            digest: Bytes32::default(),
-        }),
+        cursor: Some(cursor),
         order: CursorOrder::Asc,
         limit: None,
     };
-    let mut all_records = Vec::new();
+    let mut history_records = Vec::new();
 
     for data_type in [
         DataType::Deposit,
@@ -37,68 +338,115 @@ pub async fn make_history_backup(
         DataType::Withdrawal,
     ] {
         let records = fetch_records(client, key, &data_type.to_topic(), &cursor).await?;
-        all_records.extend(records);
+        history_records.extend(records);
     }
+    // order the entries globally so that a cursor is meaningful across topics
+    history_records.sort_by_key(|record| MetaData {
+        timestamp: record.timestamp,
+        digest: record.digest,
+    });
 
-    // decrypt transfer data to fetch sender proof set
-    let mut transfer_data = Vec::new();
-    for record in all_records.iter() {
+    // group each history record with the records it depends on
+    let mut groups: Vec<(MetaData, Vec<DiffRecord>)> = Vec::new();
+    for record in history_records {
+        let meta = MetaData {
+            timestamp: record.timestamp,
+            digest: record.digest,
+        };
+        let mut group = Vec::new();
         if record.topic == DataType::Transfer.to_topic() {
-            let transfer_data_entry = match TransferData::decrypt(key, None, &record.data) {
-                Ok(transfer_data_entry) => transfer_data_entry,
+            // decrypt transfer data to fetch sender proof set
+            match TransferData::decrypt(key, None, &record.data) {
+                Ok(transfer_data) => {
+                    let sender_proof_set_key =
+                        KeySet::new(transfer_data.sender_proof_set_ephemeral_key);
+                    let sender_proof_set_data = client
+                        .store_vault_server
+                        .get_snapshot(sender_proof_set_key, &DataType::SenderProofSet.to_topic())
+                        .await?
+                        .ok_or(StrategyError::SenderProofSetNotFound)?;
+                    group.push(record);
+                    group.push(DiffRecord {
+                        topic: DataType::SenderProofSet.to_topic(),
+                        pubkey: sender_proof_set_key.pubkey.into(),
+                        digest: get_digest(&sender_proof_set_data),
+                        timestamp: chrono::Utc::now().timestamp() as u64, // use current time because we don't have to care about the timestamp for snapshot
+                        data: sender_proof_set_data,
+                    });
+                }
                 Err(e) => {
                     log::warn!(
                         "failed to decrypt transfer data with digest {}: {}",
                         record.digest,
                         e
                     );
-                    continue;
+                    group.push(record);
                 }
-            };
-            transfer_data.push(transfer_data_entry);
+            }
+        } else {
+            group.push(record);
         }
+        groups.push((meta, group));
     }
 
-    // fetch sender proof set
-    for transfer_data in transfer_data.iter() {
-        let sender_proof_set_key = KeySet::new(transfer_data.sender_proof_set_ephemeral_key);
-        let sender_proof_set_data = client
-            .store_vault_server
-            .get_snapshot(sender_proof_set_key, &DataType::SenderProofSet.to_topic())
-            .await?
-            .ok_or(StrategyError::SenderProofSetNotFound)?;
-        all_records.push(DiffRecord {
-            topic: DataType::SenderProofSet.to_topic(),
-            pubkey: sender_proof_set_key.pubkey.into(),
-            digest: get_digest(&sender_proof_set_data),
-            timestamp: chrono::Utc::now().timestamp() as u64, // use current time because we don't have to care about the timestamp for snapshot
-            data: sender_proof_set_data,
-        });
-    }
-
-    // fetch user data
+    // fetch user data, which does not advance the cursor
     let user_data = client
         .store_vault_server
         .get_snapshot(key, &DataType::UserData.to_topic())
         .await?;
     if let Some(user_data) = user_data {
-        all_records.push(DiffRecord {
-            topic: DataType::UserData.to_topic(),
-            pubkey: key.pubkey.into(),
-            digest: get_digest(&user_data),
-            timestamp: chrono::Utc::now().timestamp() as u64, // use current time because we don't have to care about the timestamp for snapshot
-            data: user_data,
-        });
+        let last_cursor = groups
+            .last()
+            .map_or(start_cursor.clone(), |(meta, _)| meta.clone());
+        groups.push((
+            last_cursor,
+            vec![DiffRecord {
+                topic: DataType::UserData.to_topic(),
+                pubkey: key.pubkey.into(),
+                digest: get_digest(&user_data),
+                timestamp: chrono::Utc::now().timestamp() as u64, // use current time because we don't have to care about the timestamp for snapshot
+                data: user_data,
+            }],
+        ));
     }
 
-    let mut backup_csvs = Vec::new();
-    for chunks in all_records.chunks(chunk_size) {
-        let csv = make_backup_csv_from_records(chunks).map_err(|e| {
+    let chunks = pack_into_chunks(start_cursor, groups, chunk_size);
+    let mut backup_chunks = Vec::new();
+    for (cursor, records) in chunks {
+        let csv = make_backup_csv_from_records(&records).map_err(|e| {
             StrategyError::UnexpectedError(format!("failed to make backup csv: {e}"))
         })?;
-        backup_csvs.push(csv);
+        let content = match rsa_pubkey {
+            Some(pubkey) => EncryptedBackupChunk::encrypt(&csv, pubkey).to_content(),
+            None => csv,
+        };
+        backup_chunks.push(HistoryBackupChunk { content, cursor });
     }
-    Ok(backup_csvs)
+    Ok(backup_chunks)
+}
+
+/// Packs groups of records into chunks of at most `chunk_size` records without splitting a
+/// group. A group larger than `chunk_size` gets a chunk of its own. Each chunk is paired with
+/// the cursor of its last group.
+fn pack_into_chunks(
+    start_cursor: MetaData,
+    groups: Vec<(MetaData, Vec<DiffRecord>)>,
+    chunk_size: usize,
+) -> Vec<(MetaData, Vec<DiffRecord>)> {
+    let mut chunks = Vec::new();
+    let mut current_cursor = start_cursor;
+    let mut current_records = Vec::new();
+    for (meta, group) in groups {
+        if !current_records.is_empty() && current_records.len() + group.len() > chunk_size {
+            chunks.push((current_cursor.clone(), std::mem::take(&mut current_records)));
+        }
+        current_records.extend(group);
+        current_cursor = meta;
+    }
+    if !current_records.is_empty() {
+        chunks.push((current_cursor, current_records));
+    }
+    chunks
 }
 
 async fn fetch_records(
@@ -128,3 +476,171 @@ async fn fetch_records(
     }
     Ok(records)
 }
+
+#[cfg(test)]
+mod tests {
+    use intmax2_interfaces::{
+        data::{data_type::DataType, encryption::rsa::decrypt_aes_key, meta_data::MetaData},
+        utils::digest::get_digest,
+    };
+    use intmax2_zkp::{
+        common::signature_content::key_set::KeySet,
+        ethereum_types::{bytes32::Bytes32, u32limb_trait::U32LimbTrait as _},
+    };
+    use rand::rngs::OsRng;
+    use rsa::RsaPrivateKey;
+
+    use crate::{
+        client::strategy::error::StrategyError,
+        external_api::local_backup_store_vault::diff_data_client::{
+            make_backup_csv_from_records, DiffDataClient, DiffRecord,
+        },
+    };
+
+    use super::{
+        decode_history_backup_chunk, merge_history_backups, pack_into_chunks, EncryptedBackupChunk,
+    };
+
+    fn record(topic: &str, timestamp: u64, digest: u32) -> DiffRecord {
+        DiffRecord {
+            topic: topic.to_string(),
+            pubkey: Bytes32::default(),
+            digest: Bytes32::from_u32_slice(&[0, 0, 0, 0, 0, 0, 0, digest]).unwrap(),
+            timestamp,
+            data: vec![],
+        }
+    }
+
+    fn meta(record: &DiffRecord) -> MetaData {
+        MetaData {
+            timestamp: record.timestamp,
+            digest: record.digest,
+        }
+    }
+
+    #[test]
+    fn test_pack_into_chunks_keeps_groups_together() {
+        // entries 1 and 2 share a timestamp, entry 2 is a transfer with its sender proof set
+        let entry1 = record("deposit", 10, 1);
+        let entry2 = record("transfer", 10, 2);
+        let proof_set = record("sender_proof_set", 99, 3);
+        let entry3 = record("tx", 11, 4);
+        let groups = vec![
+            (meta(&entry1), vec![entry1]),
+            (meta(&entry2), vec![entry2, proof_set]),
+            (meta(&entry3), vec![entry3]),
+        ];
+        let start = MetaData::default();
+        let chunks = pack_into_chunks(start, groups, 2);
+
+        assert_eq!(chunks.len(), 3);
+        let digests =
+            |records: &[DiffRecord]| -> Vec<Bytes32> { records.iter().map(|r| r.digest).collect() };
+        // the transfer is not split from its sender proof set
+        assert_eq!(chunks[0].1.len(), 1);
+        assert_eq!(chunks[1].1.len(), 2);
+        assert_eq!(chunks[2].1.len(), 1);
+        // each cursor points to the last history entry of its chunk, so resuming from it
+        // neither drops nor duplicates entries that share a timestamp
+        assert_eq!(chunks[0].0.digest, digests(&chunks[0].1)[0]);
+        assert_eq!(chunks[1].0.timestamp, 10);
+        assert_eq!(chunks[1].0.digest, digests(&chunks[1].1)[0]);
+        assert_eq!(chunks[2].0.timestamp, 11);
+    }
+
+    #[test]
+    fn test_encrypted_backup_chunk() {
+        let private_key = RsaPrivateKey::new(&mut OsRng, 2048).unwrap();
+        let public_key = private_key.to_public_key();
+        let csv = "topic,pubkey,digest,timestamp,data\n";
+
+        let content = EncryptedBackupChunk::encrypt(csv, &public_key).to_content();
+        // plain csv is not mistaken for an encrypted chunk
+        assert!(EncryptedBackupChunk::parse(csv).is_none());
+        let encrypted = EncryptedBackupChunk::parse(&content).unwrap();
+
+        // the AES key is unwrapped separately, e.g. by a KMS
+        let aes_key = decrypt_aes_key(&private_key, encrypted.encrypted_key()).unwrap();
+        let decrypted = encrypted.decrypt_with_aes_key(&aes_key).unwrap();
+        assert_eq!(decrypted, csv);
+    }
+
+    #[test]
+    fn test_decode_history_backup_chunk_skips_snapshots_and_undecryptable_entries() {
+        let key = KeySet::rand(&mut rand::thread_rng());
+        let mut deposit = record(&DataType::Deposit.to_topic(), 10, 1);
+        deposit.data = vec![1, 2, 3];
+        let user_data = record(&DataType::UserData.to_topic(), 11, 2);
+        let csv = make_backup_csv_from_records(&[deposit, user_data]).unwrap();
+
+        let entries = decode_history_backup_chunk(key, &csv).unwrap();
+        assert!(entries.is_empty());
+        assert!(decode_history_backup_chunk(key, "not,a\ncsv").is_err());
+    }
+
+    fn entry(key: KeySet, data_type: DataType, timestamp: u64, data: u8) -> DiffRecord {
+        DiffRecord {
+            topic: data_type.to_topic(),
+            pubkey: key.pubkey.into(),
+            digest: get_digest(&[data]),
+            timestamp,
+            data: vec![data],
+        }
+    }
+
+    #[test]
+    fn test_merge_history_backups() {
+        let key = KeySet::rand(&mut rand::thread_rng());
+        let deposit = || entry(key, DataType::Deposit, 10, 1);
+        let device1 = make_backup_csv_from_records(&[
+            deposit(),
+            entry(key, DataType::Transfer, 12, 2),
+            entry(key, DataType::SenderProofSet, 99, 9),
+            entry(key, DataType::UserData, 100, 5),
+        ])
+        .unwrap();
+        let device2 = make_backup_csv_from_records(&[
+            deposit(),
+            entry(key, DataType::Tx, 11, 3),
+            entry(key, DataType::UserData, 200, 6),
+        ])
+        .unwrap();
+
+        let merged =
+            merge_history_backups(key, vec![vec![device1.clone()], vec![device2.clone()]], 10)
+                .unwrap();
+        assert_eq!(merged.len(), 1);
+        let records = DiffDataClient.parse(&merged[0]).unwrap();
+        let data = records.iter().map(|r| r.data[0]).collect::<Vec<_>>();
+        // deduplicated, ordered by timestamp, the transfer followed by its sender proof set,
+        // and only the newest user data
+        assert_eq!(data, vec![1, 3, 2, 9, 6]);
+
+        // the transfer is not split from its sender proof set
+        let merged = merge_history_backups(key, vec![vec![device1.clone(), device2]], 2).unwrap();
+        let sizes = merged
+            .iter()
+            .map(|chunk| DiffDataClient.parse(chunk).unwrap().len())
+            .collect::<Vec<_>>();
+        assert_eq!(sizes, vec![2, 2, 1]);
+
+        let mut conflicting = deposit();
+        conflicting.timestamp = 20;
+        let device3 = make_backup_csv_from_records(&[conflicting]).unwrap();
+        let err =
+            merge_history_backups(key, vec![vec![device1.clone()], vec![device3]], 10).unwrap_err();
+        assert!(matches!(err, StrategyError::BackupConflict(d) if d == deposit().digest));
+
+        let other_key = KeySet::rand(&mut rand::thread_rng());
+        assert!(merge_history_backups(other_key, vec![vec![device1]], 10).is_err());
+    }
+
+    #[test]
+    fn test_pack_into_chunks_empty() {
+        let start = MetaData {
+            timestamp: 5,
+            digest: Bytes32::default(),
+        };
+        assert!(pack_into_chunks(start, vec![], 10).is_empty());
+    }
+}
@@ -0,0 +1,129 @@
+use hashbrown::HashMap;
+use intmax2_interfaces::{
+    api::store_vault_server::types::{MetaDataCursor, MetaDataCursorResponse},
+    data::user_data::Balances,
+};
+use intmax2_zkp::{
+    circuits::balance::balance_pis::BalancePublicInputs, common::signature_content::key_set::KeySet,
+};
+
+use super::{
+    client::Client,
+    error::ClientError,
+    history::{EntryStatus, HistoryEntry},
+    strategy::common::fetch_sender_proof_set,
+};
+
+/// Balances of a user reconstructed as of a historical block.
+#[derive(Debug, Clone)]
+pub struct BalanceAtBlock {
+    /// Block number of the balance proof the balances are anchored to.
+    /// This is the greatest balance proof block number that is <= the requested block.
+    pub proof_block_number: u32,
+    /// Proven balances if the anchor is the latest balance proof, otherwise the settled-balance
+    /// estimate described in `get_balance_at_block`.
+    pub balances: Balances,
+}
+
+/// Reconstruct the balances of `key` as of `block_number`.
+///
+/// The balance proof with the greatest block number <= `block_number` is located among the
+/// latest balance proof in the user data and the previous balance proofs of the sent txs.
+/// If it is the latest one, its private state is decoded directly, and the balances are the
+/// proven ones.
+///
+/// Otherwise the balances are only a settled-balance estimate, replayed from the processed
+/// history entries settled at or before the anchor block. The history does not record the block
+/// at which a deposit or transfer was incorporated into the balance proof chain, so a receive
+/// settled before the anchor but incorporated after it is counted although the proof at the
+/// anchor does not include it yet.
+pub async fn get_balance_at_block(
+    client: &Client,
+    key: KeySet,
+    block_number: u32,
+) -> Result<BalanceAtBlock, ClientError> {
+    let user_data = client.get_user_data(key).await?;
+    let latest_proof_block_number = if user_data.balance_proof.is_some() {
+        Some(
+            user_data
+                .block_number()
+                .map_err(|e| ClientError::UnexpectedError(e.to_string()))?,
+        )
+    } else {
+        None
+    };
+    if let Some(latest) = latest_proof_block_number {
+        if latest <= block_number {
+            return Ok(BalanceAtBlock {
+                proof_block_number: latest,
+                balances: user_data.balances(),
+            });
+        }
+    }
+
+    let txs =
+        fetch_all(|cursor| async move { client.fetch_tx_history(key, &cursor).await }).await?;
+    let mut proof_block_number = None;
+    for entry in txs.iter() {
+        if !matches!(entry.status, EntryStatus::Processed(_)) {
+            continue;
+        }
+        let sender_proof_set = fetch_sender_proof_set(
+            client.store_vault_server.as_ref(),
+            entry.data.sender_proof_set_ephemeral_key,
+        )
+        .await?;
+        let prev_balance_proof = sender_proof_set.prev_balance_proof.decompress()?;
+        let prev_balance_pis = BalancePublicInputs::from_pis(&prev_balance_proof.public_inputs)
+            .map_err(|e| ClientError::UnexpectedError(e.to_string()))?;
+        let candidate = prev_balance_pis.public_state.block_number;
+        if candidate <= block_number && proof_block_number.is_none_or(|b| b < candidate) {
+            proof_block_number = Some(candidate);
+        }
+    }
+    let proof_block_number =
+        proof_block_number.ok_or(ClientError::BalanceProofNotFound(block_number))?;
+
+    let deposits =
+        fetch_all(|cursor| async move { client.fetch_deposit_history(key, &cursor).await }).await?;
+    let transfers =
+        fetch_all(|cursor| async move { client.fetch_transfer_history(key, &cursor).await })
+            .await?;
+
+    let is_incorporated = |status: &EntryStatus| matches!(status, EntryStatus::Processed(settled) if *settled <= proof_block_number);
+    let mut balances = Balances(HashMap::new());
+    for entry in deposits.iter().filter(|e| is_incorporated(&e.status)) {
+        balances.add_deposit(&entry.data);
+    }
+    for entry in transfers.iter().filter(|e| is_incorporated(&e.status)) {
+        balances.add_transfer(&entry.data);
+    }
+    for entry in txs.iter().filter(|e| is_incorporated(&e.status)) {
+        balances.sub_tx(&entry.data);
+    }
+    Ok(BalanceAtBlock {
+        proof_block_number,
+        balances,
+    })
+}
+
+/// Fetch every page of a history query.
+pub(crate) async fn fetch_all<T, F, Fut>(fetch_page: F) -> Result<Vec<HistoryEntry<T>>, ClientError>
+where
+    F: Fn(MetaDataCursor) -> Fut,
+    Fut: std::future::Future<
+        Output = Result<(Vec<HistoryEntry<T>>, MetaDataCursorResponse), ClientError>,
+    >,
+{
+    let mut cursor = MetaDataCursor::default();
+    let mut entries = Vec::new();
+    loop {
+        let (page, cursor_response) = fetch_page(cursor.clone()).await?;
+        entries.extend(page);
+        if !cursor_response.has_more {
+            break;
+        }
+        cursor.cursor = cursor_response.next_cursor;
+    }
+    Ok(entries)
+}
@@ -0,0 +1,346 @@
+use intmax2_interfaces::{
+    data::proof_compression::CompressedBalanceProof, utils::circuit_verifiers::CircuitVerifiers,
+};
+use intmax2_zkp::{
+    circuits::balance::balance_pis::BalancePublicInputs,
+    common::{
+        private_state::FullPrivateState,
+        signature_content::{
+            flatten::FlatG2,
+            key_set::KeySet,
+            sign_tools::{sign_message, verify_signature},
+        },
+    },
+    ethereum_types::{bytes32::Bytes32, u256::U256},
+    utils::poseidon_hash_out::PoseidonHashOut,
+};
+use serde::{Deserialize, Serialize};
+use sha2::Digest as _;
+
+use super::{
+    client::Client,
+    error::ClientError,
+    min_balance_circuit::{MinBalanceCircuit, MinBalanceProof, MinBalancePublicInputs},
+};
+
+/// Version of the `MinBalanceAttestation` format.
+pub const MIN_BALANCE_ATTESTATION_VERSION: u32 = 2;
+
+/// Attestation that `pubkey` controls an account holding at least `threshold` of
+/// `token_index` as of `block_number`, bound to a `challenge` chosen by the verifier.
+///
+/// The balance proof binds the private commitment of the account at the block, and the min
+/// balance proof shows in zero knowledge that the private state behind that commitment holds at
+/// least `threshold` of the token. Neither the amount nor the private state is disclosed.
+/// The private commitment itself is public in the balance proof.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MinBalanceAttestation {
+    pub version: u32,
+    pub pubkey: U256,
+    pub token_index: u32,
+    pub threshold: U256,
+    pub challenge: Bytes32,
+    pub block_number: u32,
+    pub balance_proof: CompressedBalanceProof,
+    /// Proof of the `MinBalanceCircuit` for the private commitment of `balance_proof`
+    pub min_balance_proof: MinBalanceProof,
+    /// BLS signature of the attested statement by `pubkey`
+    pub signature: FlatG2,
+}
+
+#[derive(Serialize)]
+struct AttestationSignContent {
+    pubkey: U256,
+    token_index: u32,
+    threshold: U256,
+    challenge: Bytes32,
+    block_number: u32,
+    private_commitment: PoseidonHashOut,
+}
+
+impl AttestationSignContent {
+    fn digest(&self) -> Vec<u8> {
+        let serialized = bincode::serialize(self).unwrap();
+        sha2::Sha256::digest(&serialized).to_vec()
+    }
+}
+
+fn invalid(reason: impl Into<String>) -> ClientError {
+    ClientError::InvalidBalanceAttestation(reason.into())
+}
+
+/// Attest that `key` holds at least `threshold` of `token_index` as of its latest balance
+/// proof. The user data must be synced so that the private state matches the balance proof.
+pub async fn attest_min_balance(
+    client: &Client,
+    key: KeySet,
+    token_index: u32,
+    threshold: U256,
+    challenge: Bytes32,
+) -> Result<MinBalanceAttestation, ClientError> {
+    let user_data = client.get_user_data(key).await?;
+    let balance_proof = user_data
+        .balance_proof
+        .clone()
+        .ok_or_else(|| invalid("no balance proof, sync the account first"))?;
+    build_min_balance_attestation(
+        key,
+        balance_proof,
+        &user_data.full_private_state,
+        token_index,
+        threshold,
+        challenge,
+    )
+}
+
+fn build_min_balance_attestation(
+    key: KeySet,
+    balance_proof: CompressedBalanceProof,
+    full_private_state: &FullPrivateState,
+    token_index: u32,
+    threshold: U256,
+    challenge: Bytes32,
+) -> Result<MinBalanceAttestation, ClientError> {
+    let balance_pis = BalancePublicInputs::from_pis(&balance_proof.decompress()?.public_inputs)
+        .map_err(|e| ClientError::UnexpectedError(e.to_string()))?;
+    let private_state = full_private_state.to_private_state();
+    if private_state.commitment() != balance_pis.private_commitment {
+        return Err(invalid(
+            "private state does not match the balance proof, sync the account first",
+        ));
+    }
+
+    let asset_tree = &full_private_state.asset_tree;
+    let asset_leaf = asset_tree.get_leaf(token_index as u64);
+    if asset_leaf.is_insufficient || asset_leaf.amount < threshold {
+        return Err(ClientError::BalanceError(format!(
+            "balance of token {token_index} is below the threshold {threshold}"
+        )));
+    }
+    let asset_merkle_proof = asset_tree.prove(token_index as u64);
+    let proof = MinBalanceCircuit::new()
+        .prove(
+            &private_state,
+            asset_leaf,
+            &asset_merkle_proof,
+            token_index,
+            threshold,
+        )
+        .map_err(|e| ClientError::UnexpectedError(format!("failed to prove min balance: {e}")))?;
+    let min_balance_proof =
+        MinBalanceProof::new(&proof).map_err(|e| ClientError::UnexpectedError(e.to_string()))?;
+
+    let block_number = balance_pis.public_state.block_number;
+    let digest = AttestationSignContent {
+        pubkey: key.pubkey,
+        token_index,
+        threshold,
+        challenge,
+        block_number,
+        private_commitment: balance_pis.private_commitment,
+    }
+    .digest();
+    let signature = sign_message(key.privkey, &digest).into();
+    Ok(MinBalanceAttestation {
+        version: MIN_BALANCE_ATTESTATION_VERSION,
+        pubkey: key.pubkey,
+        token_index,
+        threshold,
+        challenge,
+        block_number,
+        balance_proof,
+        min_balance_proof,
+        signature,
+    })
+}
+
+/// Verify `attestation` without a client and return the public inputs of its balance proof.
+/// `challenge` is the nonce the verifier handed to the user, which rejects attestations made
+/// for someone else or replayed.
+///
+/// The verifier should also check that the block of the attestation is recent enough, and that
+/// the block hash in the returned public state is the one on the rollup contract.
+pub fn verify_min_balance_attestation(
+    attestation: &MinBalanceAttestation,
+    challenge: Bytes32,
+) -> Result<BalancePublicInputs, ClientError> {
+    if attestation.version != MIN_BALANCE_ATTESTATION_VERSION {
+        return Err(invalid(format!(
+            "unsupported version {}",
+            attestation.version
+        )));
+    }
+    if attestation.challenge != challenge {
+        return Err(invalid("challenge mismatch"));
+    }
+
+    let balance_proof = attestation.balance_proof.decompress()?;
+    let balance_pis = BalancePublicInputs::from_pis(&balance_proof.public_inputs)
+        .map_err(|e| invalid(format!("failed to parse balance proof pis: {e}")))?;
+    if balance_pis.pubkey != attestation.pubkey {
+        return Err(invalid("balance proof is for another pubkey"));
+    }
+    if balance_pis.public_state.block_number != attestation.block_number {
+        return Err(invalid(format!(
+            "balance proof is at block {} instead of {}",
+            balance_pis.public_state.block_number, attestation.block_number
+        )));
+    }
+
+    let min_balance_proof = attestation
+        .min_balance_proof
+        .decompress()
+        .map_err(|e| invalid(format!("failed to deserialize min balance proof: {e}")))?;
+    MinBalanceCircuit::new()
+        .verify(
+            &min_balance_proof,
+            &MinBalancePublicInputs {
+                private_commitment: balance_pis.private_commitment,
+                token_index: attestation.token_index,
+                threshold: attestation.threshold,
+            },
+        )
+        .map_err(|e| invalid(format!("invalid min balance proof: {e}")))?;
+    CircuitVerifiers::load()
+        .get_balance_vd()
+        .verify(balance_proof)
+        .map_err(|e| invalid(format!("invalid balance proof: {e}")))?;
+
+    let digest = AttestationSignContent {
+        pubkey: attestation.pubkey,
+        token_index: attestation.token_index,
+        threshold: attestation.threshold,
+        challenge: attestation.challenge,
+        block_number: attestation.block_number,
+        private_commitment: balance_pis.private_commitment,
+    }
+    .digest();
+    verify_signature(
+        attestation.signature.clone().into(),
+        attestation.pubkey,
+        &digest,
+    )
+    .map_err(|e| invalid(format!("invalid signature: {e}")))?;
+    Ok(balance_pis)
+}
+
+#[cfg(test)]
+mod tests {
+    use intmax2_zkp::{
+        circuits::balance::balance_processor::get_prev_balance_pis,
+        common::trees::asset_tree::AssetLeaf, ethereum_types::u32limb_trait::U32LimbTrait as _,
+    };
+    use plonky2::{
+        field::{goldilocks_field::GoldilocksField, types::Field as _},
+        recursion::dummy_circuit::cyclic_base_proof,
+    };
+
+    use super::*;
+
+    const TOKEN_INDEX: u32 = 1;
+
+    fn full_private_state(amount: u32) -> FullPrivateState {
+        let mut full_private_state = FullPrivateState::new();
+        full_private_state.asset_tree.update(
+            TOKEN_INDEX as u64,
+            AssetLeaf {
+                is_insufficient: false,
+                amount: U256::from(amount),
+            },
+        );
+        full_private_state
+    }
+
+    // Stand-in for a balance proof that carries the pubkey and private commitment. It does not
+    // pass the balance circuit verifier, which is checked after the min balance proof.
+    fn balance_proof(key: KeySet, full_private_state: &FullPrivateState) -> CompressedBalanceProof {
+        let mut balance_pis = get_prev_balance_pis(key.pubkey, &None).unwrap();
+        balance_pis.private_commitment = full_private_state.to_private_state().commitment();
+        let balance_vd = CircuitVerifiers::load().get_balance_vd();
+        let proof = cyclic_base_proof(
+            &balance_vd.common,
+            &balance_vd.verifier_only,
+            balance_pis
+                .to_u64_vec()
+                .into_iter()
+                .enumerate()
+                .map(|(i, x)| (i, GoldilocksField::from_canonical_u64(x)))
+                .collect(),
+        );
+        CompressedBalanceProof::new(&proof).unwrap()
+    }
+
+    fn attest(amount: u32, threshold: u32) -> Result<MinBalanceAttestation, ClientError> {
+        let key = KeySet::rand(&mut rand::thread_rng());
+        let full_private_state = full_private_state(amount);
+        build_min_balance_attestation(
+            key,
+            balance_proof(key, &full_private_state),
+            &full_private_state,
+            TOKEN_INDEX,
+            U256::from(threshold),
+            Bytes32::default(),
+        )
+    }
+
+    fn rejection_reason(attestation: &MinBalanceAttestation, challenge: Bytes32) -> String {
+        match verify_min_balance_attestation(attestation, challenge) {
+            Err(ClientError::InvalidBalanceAttestation(reason)) => reason,
+            other => panic!("expected an invalid attestation, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_honest_attestation_passes_min_balance_proof() {
+        let attestation = attest(100, 50).unwrap();
+        // Every check before the balance circuit verifier passes
+        let reason = rejection_reason(&attestation, Bytes32::default());
+        assert!(reason.starts_with("invalid balance proof"), "{reason}");
+    }
+
+    #[test]
+    fn test_rejects_wrong_challenge() {
+        let attestation = attest(100, 50).unwrap();
+        let other_challenge = Bytes32::rand(&mut rand::thread_rng());
+        let reason = rejection_reason(&attestation, other_challenge);
+        assert_eq!(reason, "challenge mismatch");
+    }
+
+    #[test]
+    fn test_rejects_tampered_leaf() {
+        let mut attestation = attest(100, 50).unwrap();
+
+        // Prove 500 from a leaf inflated to 1000, which opens against another asset tree root
+        let tampered = full_private_state(1000);
+        let asset_tree = &tampered.asset_tree;
+        let proof = MinBalanceCircuit::new()
+            .prove(
+                &tampered.to_private_state(),
+                asset_tree.get_leaf(TOKEN_INDEX as u64),
+                &asset_tree.prove(TOKEN_INDEX as u64),
+                TOKEN_INDEX,
+                U256::from(500),
+            )
+            .unwrap();
+        attestation.threshold = U256::from(500);
+        attestation.min_balance_proof = MinBalanceProof::new(&proof).unwrap();
+
+        let reason = rejection_reason(&attestation, Bytes32::default());
+        assert!(reason.starts_with("invalid min balance proof"), "{reason}");
+    }
+
+    #[test]
+    fn test_rejects_balance_below_threshold() {
+        assert!(matches!(
+            attest(100, 200),
+            Err(ClientError::BalanceError(_))
+        ));
+
+        // Raising the threshold of an attestation does not carry over its proof
+        let mut attestation = attest(100, 50).unwrap();
+        attestation.threshold = U256::from(200);
+        let reason = rejection_reason(&attestation, Bytes32::default());
+        assert!(reason.starts_with("invalid min balance proof"), "{reason}");
+    }
+}
@@ -1,14 +1,19 @@
+use std::collections::BTreeMap;
+
+use futures::Stream;
 use intmax2_interfaces::{
     api::{
         balance_prover::interface::BalanceProverClientInterface,
-        block_builder::interface::{BlockBuilderClientInterface, Fee},
+        block_builder::interface::{
+            BlockBuilderClientInterface, BlockBuilderFeeInfo, Fee, FeeProof,
+        },
         store_vault_server::{
             interface::{SaveDataEntry, StoreVaultClientInterface},
-            types::{MetaDataCursor, MetaDataCursorResponse},
+            types::{CursorOrder, MetaDataCursor, MetaDataCursorResponse},
         },
-        validity_prover::interface::ValidityProverClientInterface,
+        validity_prover::interface::{DepositInfo, ValidityProverClientInterface},
         withdrawal_server::interface::{
-            ClaimInfo, WithdrawalInfo, WithdrawalServerClientInterface,
+            ClaimInfo, WithdrawalInfo, WithdrawalServerClientInterface, WithdrawalSummary,
         },
     },
     data::{
@@ -30,17 +35,23 @@ use intmax2_zkp::{
     common::{
         block_builder::BlockProposal, deposit::get_pubkey_salt_hash,
         signature_content::key_set::KeySet, transfer::Transfer, trees::transfer_tree::TransferTree,
-        tx::Tx, witness::spent_witness::SpentWitness,
+        tx::Tx, withdrawal::Withdrawal, witness::spent_witness::SpentWitness,
     },
     constants::{NUM_TRANSFERS_IN_TX, TRANSFER_TREE_HEIGHT},
     ethereum_types::{address::Address, bytes32::Bytes32, u256::U256, u32limb_trait::U32LimbTrait},
+    utils::conversion::ToU64,
+};
+use num_bigint::BigUint;
+use plonky2::{
+    field::goldilocks_field::GoldilocksField,
+    plonk::{config::PoseidonGoldilocksConfig, proof::ProofWithPublicInputs},
 };
-
 use serde::{Deserialize, Serialize};
 
 use crate::{
     client::{
         fee_payment::generate_withdrawal_transfers,
+        operation::{current_operation_id, run_operation},
         receipt::generate_transfer_receipt,
         strategy::{
             mining::validate_mining_deposit_criteria, utils::wait_till_validity_prover_synced,
@@ -58,30 +69,50 @@ use crate::{
 };
 
 use super::{
-    backup::make_history_backup,
-    config::ClientConfig,
+    audit::{audit_consistency, ConsistencyReport},
+    backup::{
+        make_history_backup, make_history_backup_from_cursor, parse_rsa_public_key_pem,
+        HistoryBackupChunk,
+    },
+    balance_at_block::{get_balance_at_block, BalanceAtBlock},
+    balance_attestation::{attest_min_balance, MinBalanceAttestation},
+    config::{ClientConfig, MaxTransferAmount},
+    deposit_wait::wait_for_deposit,
     error::ClientError,
     fee_payment::{
-        quote_claim_fee, quote_withdrawal_fee, WithdrawalTransfers, CLAIM_FEE_MEMO,
-        WITHDRAWAL_FEE_MEMO,
+        generate_fee_contribution_transfers, quote_withdrawal_fee, validate_fee_contributions,
+        WithdrawalTransfers, CLAIM_FEE_MEMO, WITHDRAWAL_FEE_MEMO,
+    },
+    fee_proof::{generate_collateral_transfer, generate_fee_proof, quote_transfer_fee},
+    history::{
+        fetch_deposit_history, fetch_history, fetch_transfer_history, fetch_tx_history,
+        history_stream, HistoryEntry, HistoryItem,
     },
-    fee_proof::{generate_fee_proof, quote_transfer_fee},
-    history::{fetch_deposit_history, fetch_transfer_history, fetch_tx_history, HistoryEntry},
     misc::payment_memo::{payment_memo_topic, PaymentMemo},
-    receipt::validate_transfer_receipt,
+    offline_signing::{sign_proposal, SignedProposal},
+    rebuild::{rebuild_user_data_from_store, RebuildReport},
+    receipt::{validate_transfer_receipt, ValidatedTransferReceipt},
     strategy::{
         mining::{fetch_mining_info, Mining},
-        strategy::determine_sequence,
+        strategy::{determine_balance_breakdown, determine_sequence, TokenBalanceBreakdown},
         tx::fetch_all_unprocessed_tx_info,
-        tx_status::{get_tx_status, TxStatus},
+        tx_status::{get_tx_status, get_tx_status_batch, TxStatus},
+    },
+    sync::{
+        error::SyncError,
+        utils::{generate_spent_witness, get_balance_proof, quote_withdrawal_claim_fee},
     },
-    sync::utils::{generate_spent_witness, get_balance_proof},
+    token_info::{get_token_info, get_token_infos, TokenInfo, TokenInfoCache},
 };
 
 // Buffer time for the expiry of the block proposal
 // This is to prevent "expiry too far" error when the client time is not synced with the server time
 const EXPIRY_BUFFER: u64 = 60;
 
+type F = GoldilocksField;
+type C = PoseidonGoldilocksConfig;
+const D: usize = 2;
+
 pub struct Client {
     pub config: ClientConfig,
 
@@ -94,8 +125,12 @@ pub struct Client {
     pub liquidity_contract: LiquidityContract,
     pub rollup_contract: RollupContract,
     pub withdrawal_contract: WithdrawalContract,
+
+    pub token_info_cache: TokenInfoCache,
 }
 
+/// Memo attached to the transfer at `transfer_index`. The transfer may have a zero amount, in
+/// which case the tx only carries the memo, while the fee is still paid.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PaymentMemoEntry {
     pub transfer_index: u32,
@@ -114,6 +149,19 @@ pub struct TxRequestMemo {
     pub sender_proof_set_ephemeral_key: U256,
     pub payment_memos: Vec<PaymentMemoEntry>,
     pub fee_index: Option<u32>,
+    /// Indices of the additional fee transfers when the fee is split across multiple tokens.
+    #[serde(default)]
+    pub additional_fee_indices: Vec<u32>,
+    /// URL of the block builder that accepted the request. The proposal must be queried from
+    /// and signed for this block builder only.
+    #[serde(default)]
+    pub block_builder_url: String,
+    /// Unix timestamp after which the block builder drops the request instead of building it.
+    #[serde(default)]
+    pub valid_until: Option<u64>,
+    /// Id of the operation that sent the request, logged by the client and the servers
+    #[serde(default)]
+    pub operation_id: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -122,6 +170,9 @@ pub struct DepositResult {
     pub deposit_data: DepositData,
     pub deposit_digest: Bytes32,
     pub backup_csv: String,
+    /// Id of the operation that prepared the deposit, logged by the client and the servers
+    #[serde(default)]
+    pub operation_id: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -131,6 +182,29 @@ pub struct TransferFeeQuote {
     pub fee: Option<Fee>,
     pub collateral_fee: Option<Fee>,
     pub block_builder_address: Address,
+    /// If set, the fee is paid by these (token_index, amount) contributions instead of `fee`.
+    /// Their total value, converted at the rates of the block builder's fee list, must cover
+    /// the fee quoted in the first contribution's token.
+    #[serde(default)]
+    pub fee_contributions: Option<Vec<Fee>>,
+}
+
+/// Transfer fee quote for one of the fee tokens asked in `quote_transfer_fee_multi`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenFeeQuote {
+    pub token_index: u32,
+    /// `None` if the block builder does not accept the token as fee
+    pub fee_quote: Option<TransferFeeQuote>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxSimulationResult {
+    pub fee_quote: TransferFeeQuote,
+    pub is_sufficient_balance: bool,
+    /// Reason of the insufficiency, if any
+    pub balance_error: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -139,6 +213,10 @@ pub struct FeeQuote {
     pub beneficiary: Option<U256>,
     pub fee: Option<Fee>,
     pub collateral_fee: Option<Fee>,
+    /// Mining mode the claim fee applies to. Only set for claim fee quotes.
+    pub is_faster_mining: Option<bool>,
+    /// Claim fee for the other mining mode, if the withdrawal server accepts both modes.
+    pub alternative_mode_fee: Option<Fee>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -149,6 +227,11 @@ pub struct TxResult {
     pub tx_data: TxData,
     pub transfer_data_vec: Vec<TransferData>,
     pub backup_csv: String,
+    /// URL of the block builder the signature was sent to
+    pub block_builder_url: String,
+    /// Id of the operation that sent the signature, logged by the client and the servers
+    #[serde(default)]
+    pub operation_id: String,
 }
 
 impl Client {
@@ -163,6 +246,44 @@ impl Client {
         token_address: Address,
         token_id: U256,
         is_mining: bool,
+    ) -> Result<DepositResult, ClientError> {
+        run_operation(
+            "prepare_deposit",
+            self.prepare_deposit_inner(
+                depositor,
+                pubkey,
+                amount,
+                token_type,
+                token_address,
+                token_id,
+                is_mining,
+            ),
+        )
+        .await
+    }
+
+    /// Wait until the validity prover observes the deposit sent in the L1 tx `tx_hash`. See
+    /// `wait_for_deposit` for the timeout and abort.
+    pub async fn wait_for_deposit(
+        &self,
+        pubkey_salt_hash: Bytes32,
+        tx_hash: Bytes32,
+        timeout: Option<u64>,
+        should_abort: &dyn Fn() -> bool,
+    ) -> Result<DepositInfo, ClientError> {
+        wait_for_deposit(self, pubkey_salt_hash, tx_hash, timeout, should_abort).await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn prepare_deposit_inner(
+        &self,
+        depositor: Address,
+        pubkey: U256,
+        amount: U256,
+        token_type: TokenType,
+        token_address: Address,
+        token_id: U256,
+        is_mining: bool,
     ) -> Result<DepositResult, ClientError> {
         log::info!(
             "prepare_deposit: pubkey {pubkey}, amount {amount}, token_type {token_type:?}, token_address {token_address}, token_id {token_id}"
@@ -206,6 +327,7 @@ impl Client {
             deposit_data,
             deposit_digest,
             backup_csv,
+            operation_id: current_operation_id().unwrap_or_default(),
         };
         Ok(result)
     }
@@ -217,35 +339,9 @@ impl Client {
         transfers: &[Transfer],
         fee_quote: &TransferFeeQuote,
     ) -> Result<UserData, ClientError> {
-        // input validation
-        if transfers.is_empty() {
-            return Err(ClientError::TransferLenError(
-                "transfers is empty".to_string(),
-            ));
-        }
-        if transfers.len() > NUM_TRANSFERS_IN_TX - 1 {
-            return Err(ClientError::TransferLenError(
-                "transfers is too many".to_string(),
-            ));
-        }
-        if fee_quote.fee.is_some() && fee_quote.beneficiary.is_none() {
-            return Err(ClientError::BlockBuilderFeeError(
-                "fee_beneficiary is required".to_string(),
-            ));
-        }
+        validate_tx_inputs(transfers, fee_quote)?;
         // balance check
-        let mut transfer_amounts = transfers
-            .iter()
-            .map(|t| (t.token_index, t.amount))
-            .collect::<Vec<_>>();
-        if let Some(fee) = &fee_quote.fee {
-            transfer_amounts.push((fee.token_index, fee.amount));
-        }
-        let collateral_amounts = if let Some(collateral_fee) = &fee_quote.collateral_fee {
-            vec![(collateral_fee.token_index, collateral_fee.amount)]
-        } else {
-            vec![]
-        };
+        let (transfer_amounts, collateral_amounts) = required_amounts(transfers, fee_quote);
         let mut user_data = self.get_user_data(key).await?;
         let mut already_synced = false;
 
@@ -308,14 +404,46 @@ impl Client {
         Ok(user_data)
     }
 
-    /// Send a transaction request to the block builder
+    /// Send a transaction request to the block builders in `block_builder_urls`, in order, until
+    /// one of them accepts it. The fee quote must be valid for each of them, e.g. replicas of the
+    /// same block builder. The accepted block builder is recorded in the returned memo.
+    ///
+    /// Transfers of zero amount are allowed, e.g. to send a payment memo only. The fee of the
+    /// quote is owed regardless of the transfer amounts.
+    ///
+    /// If `valid_until` (unix timestamp) is set, the block builder drops the request instead of
+    /// building it if it is still queued after that time.
     pub async fn send_tx_request(
         &self,
-        block_builder_url: &str,
+        block_builder_urls: &[String],
+        key: KeySet,
+        transfers: &[Transfer],
+        payment_memos: &[PaymentMemoEntry],
+        fee_quote: &TransferFeeQuote,
+        valid_until: Option<u64>,
+    ) -> Result<TxRequestMemo, ClientError> {
+        run_operation(
+            "send_tx_request",
+            self.send_tx_request_inner(
+                block_builder_urls,
+                key,
+                transfers,
+                payment_memos,
+                fee_quote,
+                valid_until,
+            ),
+        )
+        .await
+    }
+
+    async fn send_tx_request_inner(
+        &self,
+        block_builder_urls: &[String],
         key: KeySet,
         transfers: &[Transfer],
         payment_memos: &[PaymentMemoEntry],
         fee_quote: &TransferFeeQuote,
+        valid_until: Option<u64>,
     ) -> Result<TxRequestMemo, ClientError> {
         log::info!(
             "send_tx_request: pubkey {}, transfers {}, fee_beneficiary {}, fee {:?}, collateral_fee {:?}",
@@ -325,47 +453,61 @@ impl Client {
             fee_quote.fee,
             fee_quote.collateral_fee
         );
-        for e in payment_memos {
-            if e.transfer_index as usize >= transfers.len() {
-                return Err(ClientError::PaymentMemoError(
-                    "memo.transfer_index is out of range".to_string(),
-                ));
+        // the transfers and fee are validated by `await_tx_sendable`
+        validate_payment_memos(transfers, payment_memos)?;
+        check_transfer_amounts(transfers, self.config.max_transfer_amount.as_ref())?;
+        if let Some(valid_until) = valid_until {
+            let current_time = chrono::Utc::now().timestamp() as u64;
+            if valid_until <= current_time {
+                return Err(ClientError::TxRequestExpired(format!(
+                    "valid_until {valid_until} is not after the current time {current_time}"
+                )));
             }
         }
+        let primary_block_builder_url =
+            block_builder_urls
+                .first()
+                .ok_or(ClientError::SendTxRequestError(
+                    "no block builder url is given".to_string(),
+                ))?;
         let user_data = self.await_tx_sendable(key, transfers, fee_quote).await?;
 
         // fetch if this is first time tx
         let account_info = self.validity_prover.get_account_info(key.pubkey).await?;
         let is_registration_block = account_info.account_id.is_none();
 
-        let fee_transfer = fee_quote.fee.clone().map(|fee| Transfer {
-            recipient: fee_quote.beneficiary.unwrap().into(),
-            amount: fee.amount,
-            token_index: fee.token_index,
-            salt: generate_salt(),
-        });
-        let collateral_transfer = fee_quote.collateral_fee.clone().map(|fee| Transfer {
-            recipient: fee_quote.beneficiary.unwrap().into(),
-            amount: fee.amount,
-            token_index: fee.token_index,
-            salt: generate_salt(),
-        });
-
-        // add fee transfer to the end
-        let transfers: Vec<Transfer> = if let Some(fee_transfer) = fee_transfer {
-            transfers
-                .iter()
-                .cloned()
-                .chain(std::iter::once(fee_transfer))
-                .collect()
-        } else {
-            transfers.to_vec()
-        };
-        let fee_index = if fee_transfer.is_some() {
-            Some(transfers.len() as u32 - 1)
-        } else {
-            None
+        let fee_transfers = match (&fee_quote.fee, &fee_quote.fee_contributions) {
+            (Some(_), Some(contributions)) => {
+                let fee_info = self
+                    .block_builder
+                    .get_fee_info(primary_block_builder_url)
+                    .await?;
+                let fee_list = if is_registration_block {
+                    fee_info.registration_fee
+                } else {
+                    fee_info.non_registration_fee
+                }
+                .ok_or(ClientError::BlockBuilderFeeError(
+                    "block builder does not require fee".to_string(),
+                ))?;
+                validate_fee_contributions(contributions, &fee_list)?;
+                generate_fee_contribution_transfers(fee_quote.beneficiary.unwrap(), contributions)
+            }
+            (Some(fee), None) => generate_fee_contribution_transfers(
+                fee_quote.beneficiary.unwrap(),
+                std::slice::from_ref(fee),
+            ),
+            (None, _) => vec![],
         };
+        let collateral_transfer =
+            generate_collateral_transfer(fee_quote.beneficiary, fee_quote.collateral_fee.as_ref());
+
+        // add fee transfers to the end
+        let fee_indices = (transfers.len() as u32..(transfers.len() + fee_transfers.len()) as u32)
+            .collect::<Vec<_>>();
+        let transfers: Vec<Transfer> = transfers.iter().cloned().chain(fee_transfers).collect();
+        let fee_index = fee_indices.first().copied();
+        let additional_fee_indices = fee_indices.iter().skip(1).copied().collect::<Vec<_>>();
 
         let balance_proof =
             get_balance_proof(&user_data)?.ok_or(ClientError::CannotSendTxByZeroBalanceAccount)?;
@@ -404,6 +546,7 @@ impl Client {
                 sender_proof_set_ephemeral_key,
                 tx_nonce,
                 fee_index,
+                &additional_fee_indices,
                 &transfers,
                 collateral_transfer,
                 is_registration_block,
@@ -415,14 +558,14 @@ impl Client {
             None
         };
         // send tx request
-        let request_id = self
-            .block_builder
-            .send_tx_request(
-                block_builder_url,
+        let (block_builder_url, request_id) = self
+            .send_tx_request_with_failover(
+                block_builder_urls,
                 is_registration_block,
                 key.pubkey,
                 tx,
-                fee_proof.clone(),
+                fee_proof,
+                valid_until,
             )
             .await?;
         let memo = TxRequestMemo {
@@ -433,11 +576,86 @@ impl Client {
             spent_witness,
             sender_proof_set_ephemeral_key,
             fee_index,
+            additional_fee_indices,
             payment_memos: payment_memos.to_vec(),
+            block_builder_url,
+            valid_until,
+            operation_id: current_operation_id().unwrap_or_default(),
         };
         Ok(memo)
     }
 
+    /// Sends the tx request to the block builders in order until one accepts it, and returns the
+    /// URL of that block builder with the request id. Sending the same request to several block
+    /// builders cannot get the tx included twice: it only becomes valid once the user signs the
+    /// proposal, which `finalize_tx` does for the accepting block builder only.
+    async fn send_tx_request_with_failover(
+        &self,
+        block_builder_urls: &[String],
+        is_registration_block: bool,
+        pubkey: U256,
+        tx: Tx,
+        fee_proof: Option<FeeProof>,
+        valid_until: Option<u64>,
+    ) -> Result<(String, String), ClientError> {
+        let mut last_error = None;
+        for block_builder_url in block_builder_urls {
+            match self
+                .block_builder
+                .send_tx_request(
+                    block_builder_url,
+                    is_registration_block,
+                    pubkey,
+                    tx,
+                    fee_proof.clone(),
+                    valid_until,
+                )
+                .await
+            {
+                Ok(request_id) => return Ok((block_builder_url.clone(), request_id)),
+                Err(e) => {
+                    log::warn!(
+                        "Block builder {block_builder_url} did not accept the tx request: {e}"
+                    );
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(match last_error {
+            Some(e) => e.into(),
+            None => ClientError::SendTxRequestError("no block builder url is given".to_string()),
+        })
+    }
+
+    /// Validate a tx request and quote its fee without submitting it to the block builder.
+    /// This does not sync, reserve a nonce, or save any data to the store vault.
+    pub async fn simulate_tx_request(
+        &self,
+        block_builder_url: &str,
+        key: KeySet,
+        transfers: &[Transfer],
+        payment_memos: &[PaymentMemoEntry],
+        fee_token_index: u32,
+    ) -> Result<TxSimulationResult, ClientError> {
+        let fee_quote = self
+            .quote_transfer_fee(block_builder_url, key.pubkey, fee_token_index)
+            .await?;
+        validate_tx_inputs(transfers, &fee_quote)?;
+        validate_payment_memos(transfers, payment_memos)?;
+
+        let balances = self.get_balances_without_sync(key).await?;
+        let (transfer_amounts, collateral_amounts) = required_amounts(transfers, &fee_quote);
+        let balance_error = balance_check(&balances, &transfer_amounts)
+            .and_then(|_| balance_check(&balances, &collateral_amounts))
+            .err()
+            .map(|e| e.to_string());
+        Ok(TxSimulationResult {
+            fee_quote,
+            is_sufficient_balance: balance_error.is_none(),
+            balance_error,
+        })
+    }
+
     pub async fn query_proposal(
         &self,
         block_builder_url: &str,
@@ -445,13 +663,18 @@ impl Client {
     ) -> Result<BlockProposal, ClientError> {
         let mut tries = 0;
         let proposal = loop {
-            let proposal = self
+            let response = self
                 .block_builder
                 .query_proposal(block_builder_url, request_id)
                 .await?;
-            if let Some(proposal) = proposal {
+            if let Some(proposal) = response.block_proposal {
                 break proposal;
             }
+            if response.is_expired {
+                return Err(ClientError::TxRequestExpired(format!(
+                    "request {request_id} was dropped by the block builder"
+                )));
+            }
             if tries > self.config.block_builder_query_limit {
                 return Err(ClientError::FailedToGetProposal(
                     "block builder query limit exceeded".to_string(),
@@ -464,6 +687,12 @@ impl Client {
             );
             sleep_for(self.config.block_builder_query_interval).await;
         };
+        // fail before the user signs a proposal that the block builder would reject anyway
+        check_proposal_expiry(
+            proposal.block_sign_payload.expiry.into(),
+            chrono::Utc::now().timestamp() as u64,
+            self.config.proposal_expiry_margin,
+        )?;
         Ok(proposal)
     }
 
@@ -475,6 +704,44 @@ impl Client {
         memo: &TxRequestMemo,
         proposal: &BlockProposal,
     ) -> Result<TxResult, ClientError> {
+        let signed_proposal = sign_proposal(key, proposal);
+        self.submit_signed_proposal(block_builder_url, key, memo, proposal, &signed_proposal)
+            .await
+    }
+
+    /// Same as `finalize_tx`, with a signature made by `sign_proposal` elsewhere, e.g. on an
+    /// air-gapped machine. The key is still needed to back up the tx data to the store vault.
+    pub async fn submit_signed_proposal(
+        &self,
+        block_builder_url: &str,
+        key: KeySet,
+        memo: &TxRequestMemo,
+        proposal: &BlockProposal,
+        signed_proposal: &SignedProposal,
+    ) -> Result<TxResult, ClientError> {
+        run_operation(
+            "submit_signed_proposal",
+            self.submit_signed_proposal_inner(
+                block_builder_url,
+                key,
+                memo,
+                proposal,
+                signed_proposal,
+            ),
+        )
+        .await
+    }
+
+    async fn submit_signed_proposal_inner(
+        &self,
+        block_builder_url: &str,
+        key: KeySet,
+        memo: &TxRequestMemo,
+        proposal: &BlockProposal,
+        signed_proposal: &SignedProposal,
+    ) -> Result<TxResult, ClientError> {
+        signed_proposal.verify(key.pubkey, proposal)?;
+
         // verify proposal
         proposal
             .verify(memo.tx)
@@ -487,11 +754,9 @@ impl Client {
             return Err(ClientError::InvalidBlockProposal(
                 "expiry 0 is not allowed".to_string(),
             ));
-        } else if expiry < current_time {
-            return Err(ClientError::InvalidBlockProposal(
-                "proposal expired".to_string(),
-            ));
-        } else if expiry > current_time + self.config.tx_timeout + EXPIRY_BUFFER {
+        }
+        check_proposal_expiry(expiry, current_time, self.config.proposal_expiry_margin)?;
+        if expiry > current_time + self.config.tx_timeout + EXPIRY_BUFFER {
             return Err(ClientError::InvalidBlockProposal(format!(
                 "proposal expiry {} is too far: current time {}, timeout {}, buffer {}",
                 expiry, current_time, self.config.tx_timeout, EXPIRY_BUFFER
@@ -566,8 +831,8 @@ impl Client {
                 }
             })
             .collect::<Vec<_>>();
-        if let Some(fee_index) = memo.fee_index {
-            transfer_types[fee_index as usize] = TransferType::TransferFee;
+        for fee_index in memo.fee_index.iter().chain(&memo.additional_fee_indices) {
+            transfer_types[*fee_index as usize] = TransferType::TransferFee;
         }
         for payment_memo in &memo.payment_memos {
             if payment_memo.topic == payment_memo_topic(WITHDRAWAL_FEE_MEMO) {
@@ -586,7 +851,11 @@ impl Client {
         for (data_type, receiver, transfer_data, encrypted_data, _) in
             &transfer_data_and_encrypted_data
         {
-            if Some(transfer_data.transfer_index) == memo.fee_index {
+            if Some(transfer_data.transfer_index) == memo.fee_index
+                || memo
+                    .additional_fee_indices
+                    .contains(&transfer_data.transfer_index)
+            {
                 // ignore fee transfer because it will be saved on block builder side
                 continue;
             }
@@ -606,7 +875,13 @@ impl Client {
             transfer_types,
             sender_proof_set_ephemeral_key: memo.sender_proof_set_ephemeral_key,
         };
-        let tx_data_encrypted = tx_data.encrypt(key.pubkey, Some(key))?;
+        // tx data is only read by the sender, so it may be compressed. Transfer data is read by
+        // the recipients' clients, which may predate compression.
+        let tx_data_encrypted = tx_data.encrypt_with_compression(
+            key.pubkey,
+            Some(key),
+            self.config.compress_payloads,
+        )?;
         let tx_digest = get_digest(&tx_data_encrypted);
         entries.push(SaveDataEntry {
             topic: DataType::Tx.to_topic(),
@@ -618,14 +893,13 @@ impl Client {
             .save_data_batch(key, &entries)
             .await?;
 
-        // sign and post signature
-        let signature = proposal.sign(key);
+        // post signature
         self.block_builder
             .post_signature(
                 block_builder_url,
                 &memo.request_id,
                 key.pubkey,
-                signature.signature,
+                signed_proposal.signature.clone(),
             )
             .await?;
 
@@ -677,6 +951,8 @@ impl Client {
             tx_data,
             transfer_data_vec,
             backup_csv,
+            block_builder_url: block_builder_url.to_string(),
+            operation_id: current_operation_id().unwrap_or_default(),
         };
 
         Ok(result)
@@ -691,6 +967,39 @@ impl Client {
         Ok(status)
     }
 
+    /// Status of a sent tx request. `Expired` if the block builder dropped the request after its
+    /// `valid_until`, `Pending` until the request is built into a block, then the status of
+    /// the tx in that block.
+    pub async fn get_tx_request_status(
+        &self,
+        sender: U256,
+        memo: &TxRequestMemo,
+    ) -> Result<TxStatus, ClientError> {
+        let response = self
+            .block_builder
+            .query_proposal(&memo.block_builder_url, &memo.request_id)
+            .await?;
+        if let Some(proposal) = response.block_proposal {
+            return self
+                .get_tx_status(sender, proposal.block_sign_payload.tx_tree_root)
+                .await;
+        }
+        if response.is_expired {
+            return Ok(TxStatus::Expired);
+        }
+        Ok(TxStatus::Pending)
+    }
+
+    pub async fn get_tx_status_batch(
+        &self,
+        sender: U256,
+        tx_tree_roots: &[Bytes32],
+    ) -> Result<Vec<TxStatus>, ClientError> {
+        let statuses =
+            get_tx_status_batch(self.validity_prover.as_ref(), sender, tx_tree_roots).await?;
+        Ok(statuses)
+    }
+
     pub async fn get_withdrawal_info(
         &self,
         key: KeySet,
@@ -699,6 +1008,18 @@ impl Client {
         Ok(withdrawal_info)
     }
 
+    pub async fn get_withdrawal_info_paged(
+        &self,
+        key: KeySet,
+        cursor: &MetaDataCursor,
+    ) -> Result<(Vec<WithdrawalInfo>, MetaDataCursorResponse), ClientError> {
+        let page = self
+            .withdrawal_server
+            .get_withdrawal_info_paged(key, cursor)
+            .await?;
+        Ok(page)
+    }
+
     pub async fn get_withdrawal_info_by_recipient(
         &self,
         recipient: Address,
@@ -710,6 +1031,17 @@ impl Client {
         Ok(withdrawal_info)
     }
 
+    pub async fn get_withdrawal_summary_by_recipient(
+        &self,
+        recipient: Address,
+    ) -> Result<Vec<WithdrawalSummary>, ClientError> {
+        let summary = self
+            .withdrawal_server
+            .get_withdrawal_summary_by_recipient(recipient)
+            .await?;
+        Ok(summary)
+    }
+
     pub async fn get_mining_list(&self, key: KeySet) -> Result<Vec<Mining>, ClientError> {
         let current_time = chrono::Utc::now().timestamp() as u64;
         let minings = fetch_mining_info(
@@ -732,6 +1064,18 @@ impl Client {
         Ok(claim_info)
     }
 
+    pub async fn get_claim_info_paged(
+        &self,
+        key: KeySet,
+        cursor: &MetaDataCursor,
+    ) -> Result<(Vec<ClaimInfo>, MetaDataCursorResponse), ClientError> {
+        let page = self
+            .withdrawal_server
+            .get_claim_info_paged(key, cursor)
+            .await?;
+        Ok(page)
+    }
+
     pub async fn fetch_deposit_history(
         &self,
         key: KeySet,
@@ -756,6 +1100,28 @@ impl Client {
         fetch_tx_history(self, key, cursor).await
     }
 
+    /// Fetch the whole deposit, transfer and tx history from `from_timestamp`, merged and sorted
+    /// by timestamp in `order`.
+    pub async fn fetch_history(
+        &self,
+        key: KeySet,
+        order: CursorOrder,
+        from_timestamp: Option<u64>,
+    ) -> Result<Vec<HistoryItem>, ClientError> {
+        fetch_history(self, key, order, from_timestamp).await
+    }
+
+    /// Stream the same entries as `fetch_history`, in the same order, as their pages are
+    /// fetched and decrypted.
+    pub fn history_stream(
+        &self,
+        key: KeySet,
+        order: CursorOrder,
+        from_timestamp: Option<u64>,
+    ) -> impl Stream<Item = Result<HistoryItem, ClientError>> + '_ {
+        history_stream(self, key, order, from_timestamp)
+    }
+
     pub async fn quote_transfer_fee(
         &self,
         block_builder_url: &str,
@@ -767,22 +1133,38 @@ impl Client {
         let fee_info = self.block_builder.get_fee_info(block_builder_url).await?;
         let (fee, collateral_fee) =
             quote_transfer_fee(is_registration_block, fee_token_index, &fee_info)?;
-        if fee_info.beneficiary.is_none() && fee.is_some() {
-            return Err(ClientError::BlockBuilderFeeError(
-                "beneficiary is required".to_string(),
-            ));
-        }
-        if fee.is_none() && collateral_fee.is_some() {
-            return Err(ClientError::BlockBuilderFeeError(
-                "collateral fee is required but fee is not found".to_string(),
-            ));
-        }
-        Ok(TransferFeeQuote {
-            beneficiary: fee_info.beneficiary,
-            fee,
-            collateral_fee,
-            block_builder_address: fee_info.block_builder_address,
-        })
+        to_transfer_fee_quote(&fee_info, fee, collateral_fee)
+    }
+
+    /// Quote the transfer fee in each of `fee_token_indices`, fetching the fee info of the block
+    /// builder once. The quotes are aligned by index with `fee_token_indices`, and have no
+    /// `fee_quote` for the tokens the block builder does not accept.
+    pub async fn quote_transfer_fee_multi(
+        &self,
+        block_builder_url: &str,
+        pubkey: U256,
+        fee_token_indices: &[u32],
+    ) -> Result<Vec<TokenFeeQuote>, ClientError> {
+        let account_info = self.validity_prover.get_account_info(pubkey).await?;
+        let is_registration_block = account_info.account_id.is_none();
+        let fee_info = self.block_builder.get_fee_info(block_builder_url).await?;
+        fee_token_indices
+            .iter()
+            .map(|&token_index| {
+                // the quote only fails if the token is missing from the fee lists
+                let fee_quote =
+                    match quote_transfer_fee(is_registration_block, token_index, &fee_info) {
+                        Ok((fee, collateral_fee)) => {
+                            Some(to_transfer_fee_quote(&fee_info, fee, collateral_fee)?)
+                        }
+                        Err(_) => None,
+                    };
+                Ok(TokenFeeQuote {
+                    token_index,
+                    fee_quote,
+                })
+            })
+            .collect()
     }
 
     pub async fn quote_withdrawal_fee(
@@ -801,16 +1183,34 @@ impl Client {
             beneficiary,
             fee,
             collateral_fee: None,
+            is_faster_mining: None,
+            alternative_mode_fee: None,
         })
     }
 
     pub async fn quote_claim_fee(&self, fee_token_index: u32) -> Result<FeeQuote, ClientError> {
-        let (beneficiary, fee) =
-            quote_claim_fee(self.withdrawal_server.as_ref(), fee_token_index).await?;
+        let fee_info = self.withdrawal_server.get_claim_fee().await?;
+        let fee = quote_withdrawal_claim_fee(Some(fee_token_index), fee_info.fee)?;
+        // the alternative mode may not accept the fee token
+        let alternative_mode_fee =
+            quote_withdrawal_claim_fee(Some(fee_token_index), fee_info.alternative_mode_fee)
+                .ok()
+                .flatten();
+        if fee_info
+            .is_faster_mining
+            .is_some_and(|mode| mode != self.config.is_faster_mining)
+        {
+            log::warn!(
+                "withdrawal server claim mode (is_faster_mining: {:?}) differs from the client config",
+                fee_info.is_faster_mining
+            );
+        }
         Ok(FeeQuote {
-            beneficiary,
+            beneficiary: fee_info.beneficiary,
             fee,
             collateral_fee: None,
+            is_faster_mining: fee_info.is_faster_mining,
+            alternative_mode_fee,
         })
     }
 
@@ -820,6 +1220,10 @@ impl Client {
         fee_token_index: u32,
         with_claim_fee: bool,
     ) -> Result<WithdrawalTransfers, ClientError> {
+        check_transfer_amounts(
+            std::slice::from_ref(withdrawal_transfer),
+            self.config.max_transfer_amount.as_ref(),
+        )?;
         let withdrawal_transfers = generate_withdrawal_transfers(
             self.withdrawal_server.as_ref(),
             &self.withdrawal_contract,
@@ -831,16 +1235,36 @@ impl Client {
         Ok(withdrawal_transfers)
     }
 
+    /// Makes a history backup of the entries from `from`. If `rsa_pubkey_pem` is given, each
+    /// chunk is encrypted to the RSA public key instead of being plain CSV.
     pub async fn make_history_backup(
         &self,
         key: KeySet,
         from: u64,
         chunk_size: usize,
+        rsa_pubkey_pem: Option<&str>,
     ) -> Result<Vec<String>, ClientError> {
-        let csvs = make_history_backup(self, key, from, chunk_size).await?;
+        let rsa_pubkey = rsa_pubkey_pem.map(parse_rsa_public_key_pem).transpose()?;
+        let csvs = make_history_backup(self, key, from, chunk_size, rsa_pubkey.as_ref()).await?;
         Ok(csvs)
     }
 
+    /// Makes a history backup of the entries after `cursor`. The cursor of each returned chunk
+    /// can be used to resume the backup if it is interrupted.
+    pub async fn make_history_backup_from_cursor(
+        &self,
+        key: KeySet,
+        cursor: MetaData,
+        chunk_size: usize,
+        rsa_pubkey_pem: Option<&str>,
+    ) -> Result<Vec<HistoryBackupChunk>, ClientError> {
+        let rsa_pubkey = rsa_pubkey_pem.map(parse_rsa_public_key_pem).transpose()?;
+        let chunks =
+            make_history_backup_from_cursor(self, key, cursor, chunk_size, rsa_pubkey.as_ref())
+                .await?;
+        Ok(chunks)
+    }
+
     pub async fn generate_transfer_receipt(
         &self,
         key: KeySet,
@@ -854,7 +1278,7 @@ impl Client {
         &self,
         key: KeySet,
         transfer_receipt: &str,
-    ) -> Result<TransferData, ClientError> {
+    ) -> Result<ValidatedTransferReceipt, ClientError> {
         validate_transfer_receipt(self, key, transfer_receipt).await
     }
 
@@ -866,12 +1290,98 @@ impl Client {
             &self.liquidity_contract,
             key,
             self.config.deposit_timeout,
+            self.config.deposit_confirmations,
             self.config.tx_timeout,
         )
         .await?;
         Ok(balances)
     }
 
+    /// Get the balances split into spendable, incoming pending and outgoing pending amounts per
+    /// token. Works without syncing and while a tx is pending.
+    pub async fn get_balance_breakdown(
+        &self,
+        key: KeySet,
+    ) -> Result<Vec<TokenBalanceBreakdown>, ClientError> {
+        let breakdown = determine_balance_breakdown(
+            self.store_vault_server.as_ref(),
+            self.validity_prover.as_ref(),
+            &self.liquidity_contract,
+            key,
+            self.config.deposit_timeout,
+            self.config.deposit_confirmations,
+            self.config.tx_timeout,
+        )
+        .await?;
+        Ok(breakdown)
+    }
+
+    /// Get the balances as of `block_number`, anchored to the latest balance proof at or
+    /// before that block. Returns an error if no such balance proof exists. Before the latest
+    /// balance proof, the balances are a settled-balance estimate rather than the proven ones.
+    pub async fn get_balance_at_block(
+        &self,
+        key: KeySet,
+        block_number: u32,
+    ) -> Result<BalanceAtBlock, ClientError> {
+        get_balance_at_block(self, key, block_number).await
+    }
+
+    /// Attest to a third party that `key` holds at least `threshold` of `token_index` as of the
+    /// latest balance proof, bound to the `challenge` nonce given by the third party. Check it
+    /// with `verify_min_balance_attestation`. The attestation proves the threshold in zero
+    /// knowledge and discloses neither the balance nor the private state.
+    pub async fn attest_min_balance(
+        &self,
+        key: KeySet,
+        token_index: u32,
+        threshold: U256,
+        challenge: Bytes32,
+    ) -> Result<MinBalanceAttestation, ClientError> {
+        attest_min_balance(self, key, token_index, threshold, challenge).await
+    }
+
+    /// Get the token registered under `token_index` with its decimals and symbol.
+    /// Results are cached for the lifetime of the client.
+    pub async fn get_token_info(&self, token_index: u32) -> Result<TokenInfo, ClientError> {
+        let token_info = get_token_info(
+            &self.liquidity_contract,
+            &self.token_info_cache,
+            token_index,
+        )
+        .await?;
+        Ok(token_info)
+    }
+
+    /// Batch form of `get_token_info`. The result is aligned by index with `token_indices`.
+    pub async fn get_token_infos(
+        &self,
+        token_indices: &[u32],
+    ) -> Result<Vec<TokenInfo>, ClientError> {
+        let token_infos = get_token_infos(
+            &self.liquidity_contract,
+            &self.token_info_cache,
+            token_indices,
+        )
+        .await?;
+        Ok(token_infos)
+    }
+
+    /// Check the processed entries and the balance proof chain of the user data against the
+    /// validity prover. See `ConsistencyReport` for the details.
+    pub async fn audit_consistency(&self, key: KeySet) -> Result<ConsistencyReport, ClientError> {
+        audit_consistency(self, key).await
+    }
+
+    /// Rebuild the deposit, transfer and tx statuses of the user data from the store vault,
+    /// checked against the balance proof without proving. See `rebuild_user_data_from_store`.
+    pub async fn rebuild_user_data_from_store(
+        &self,
+        key: KeySet,
+    ) -> Result<RebuildReport, ClientError> {
+        rebuild_user_data_from_store(self, key).await
+    }
+
     pub async fn check_validity_prover(&self) -> Result<(), ClientError> {
         let onchain_block_number = self.rollup_contract.get_latest_block_number().await?;
         wait_till_validity_prover_synced(self.validity_prover.as_ref(), true, onchain_block_number)
@@ -902,18 +1412,289 @@ impl Client {
         log::info!("validity proof is valid");
         Ok(())
     }
+
+    /// Verify a single withdrawal proof against the single withdrawal circuit verifier and
+    /// return the withdrawal encoded in its public inputs. This is the same check the
+    /// withdrawal server performs on `request_withdrawal`.
+    pub fn verify_single_withdrawal_proof(
+        &self,
+        single_withdrawal_proof: &ProofWithPublicInputs<F, C, D>,
+    ) -> Result<Withdrawal, ClientError> {
+        let verifier = CircuitVerifiers::load().get_single_withdrawal_vd();
+        verifier
+            .verify(single_withdrawal_proof.clone())
+            .map_err(|e| {
+                ClientError::InvalidWithdrawalProof(format!("Failed to verify proof: {e}"))
+            })?;
+        Withdrawal::from_u64_slice(&single_withdrawal_proof.public_inputs.to_u64_vec()).map_err(
+            |e| ClientError::InvalidWithdrawalProof(format!("Failed to parse public inputs: {e}")),
+        )
+    }
+}
+
+fn validate_tx_inputs(
+    transfers: &[Transfer],
+    fee_quote: &TransferFeeQuote,
+) -> Result<(), ClientError> {
+    if transfers.is_empty() {
+        return Err(ClientError::TransferLenError(
+            "transfers is empty".to_string(),
+        ));
+    }
+    let num_fee_transfers = fee_quote
+        .fee_contributions
+        .as_ref()
+        .map_or(1, |contributions| contributions.len());
+    if transfers.len() + num_fee_transfers > NUM_TRANSFERS_IN_TX {
+        return Err(ClientError::TransferLenError(
+            "transfers is too many".to_string(),
+        ));
+    }
+    if fee_quote.fee.is_some() && fee_quote.beneficiary.is_none() {
+        return Err(ClientError::BlockBuilderFeeError(
+            "fee_beneficiary is required".to_string(),
+        ));
+    }
+    if let Some(contributions) = &fee_quote.fee_contributions {
+        if fee_quote.fee.is_none() {
+            return Err(ClientError::BlockBuilderFeeError(
+                "fee contributions are given but fee is not required".to_string(),
+            ));
+        }
+        if contributions.is_empty() {
+            return Err(ClientError::BlockBuilderFeeError(
+                "fee contributions are empty".to_string(),
+            ));
+        }
+    }
+    Ok(())
 }
 
+fn validate_payment_memos(
+    transfers: &[Transfer],
+    payment_memos: &[PaymentMemoEntry],
+) -> Result<(), ClientError> {
+    for e in payment_memos {
+        if e.transfer_index as usize >= transfers.len() {
+            return Err(ClientError::PaymentMemoError(
+                "memo.transfer_index is out of range".to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that no transfer exceeds the cap of its token. The block builder fee is not capped.
+fn check_transfer_amounts(
+    transfers: &[Transfer],
+    caps: Option<&MaxTransferAmount>,
+) -> Result<(), ClientError> {
+    let Some(caps) = caps else {
+        return Ok(());
+    };
+    for transfer in transfers {
+        if let Some(limit) = caps.limit(transfer.token_index) {
+            if transfer.amount > limit {
+                return Err(ClientError::TransferAmountExceedsLimit {
+                    token_index: transfer.token_index,
+                    amount: transfer.amount,
+                    limit,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the amounts required for the transfers including the fee, and for the collateral.
+fn required_amounts(
+    transfers: &[Transfer],
+    fee_quote: &TransferFeeQuote,
+) -> (Vec<(u32, U256)>, Vec<(u32, U256)>) {
+    let mut transfer_amounts = transfers
+        .iter()
+        .map(|t| (t.token_index, t.amount))
+        .collect::<Vec<_>>();
+    if let Some(contributions) = &fee_quote.fee_contributions {
+        transfer_amounts.extend(
+            contributions
+                .iter()
+                .map(|fee| (fee.token_index, fee.amount)),
+        );
+    } else if let Some(fee) = &fee_quote.fee {
+        transfer_amounts.push((fee.token_index, fee.amount));
+    }
+    let collateral_amounts = if let Some(collateral_fee) = &fee_quote.collateral_fee {
+        vec![(collateral_fee.token_index, collateral_fee.amount)]
+    } else {
+        vec![]
+    };
+    (transfer_amounts, collateral_amounts)
+}
+
+/// Checks that the balances cover the total amount required per token, so that an
+/// insufficient balance is reported before any proof generation.
 fn balance_check(balances: &Balances, amounts: &[(u32, U256)]) -> Result<(), ClientError> {
-    let mut balances = balances.clone();
+    let mut required_amounts = BTreeMap::<u32, BigUint>::new();
     for (token_index, amount) in amounts {
-        let prev_balance = balances.get(*token_index);
-        let is_insufficient = balances.sub_token(*token_index, *amount);
-        if is_insufficient {
-            return Err(ClientError::BalanceError(format!(
-                "Insufficient balance: {prev_balance} < {amount} for token #{token_index}"
-            )));
+        *required_amounts.entry(*token_index).or_default() += BigUint::from(*amount);
+    }
+    for (token_index, required) in required_amounts {
+        let available = balances.get(token_index);
+        if required > BigUint::from(available) {
+            // the total can exceed U256 only if the balance is insufficient anyway
+            let required = U256::try_from(required)
+                .unwrap_or_else(|_| U256::from_u32_slice(&[u32::MAX; 8]).unwrap());
+            return Err(SyncError::InsufficientBalance {
+                token_index,
+                required,
+                available,
+            }
+            .into());
         }
     }
     Ok(())
 }
+
+fn to_transfer_fee_quote(
+    fee_info: &BlockBuilderFeeInfo,
+    fee: Option<Fee>,
+    collateral_fee: Option<Fee>,
+) -> Result<TransferFeeQuote, ClientError> {
+    if fee_info.beneficiary.is_none() && fee.is_some() {
+        return Err(ClientError::BlockBuilderFeeError(
+            "beneficiary is required".to_string(),
+        ));
+    }
+    if fee.is_none() && collateral_fee.is_some() {
+        return Err(ClientError::BlockBuilderFeeError(
+            "collateral fee is required but fee is not found".to_string(),
+        ));
+    }
+    Ok(TransferFeeQuote {
+        beneficiary: fee_info.beneficiary,
+        fee,
+        collateral_fee,
+        block_builder_address: fee_info.block_builder_address,
+        fee_contributions: None,
+    })
+}
+
+/// Returns `ProposalExpired` if a proposal with `expiry` cannot be signed and posted within
+/// `margin` seconds of `current_time`. An expiry of 0 is left to the proposal verification.
+fn check_proposal_expiry(expiry: u64, current_time: u64, margin: u64) -> Result<(), ClientError> {
+    if expiry != 0 && expiry < current_time + margin {
+        return Err(ClientError::ProposalExpired {
+            expiry,
+            current_time,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use intmax2_zkp::common::trees::asset_tree::AssetLeaf;
+
+    use super::*;
+    use crate::client::sync::utils::generate_salt;
+
+    fn fee(token_index: u32, amount: u32) -> Fee {
+        Fee {
+            token_index,
+            amount: U256::from(amount),
+        }
+    }
+
+    fn memo_only_transfer(token_index: u32) -> Transfer {
+        Transfer {
+            recipient: U256::from(1).into(),
+            token_index,
+            amount: U256::zero(),
+            salt: generate_salt(),
+        }
+    }
+
+    fn fee_quote(fee: Option<Fee>) -> TransferFeeQuote {
+        TransferFeeQuote {
+            beneficiary: fee.as_ref().map(|_| U256::from(2)),
+            fee,
+            collateral_fee: None,
+            block_builder_address: Address::default(),
+            fee_contributions: None,
+        }
+    }
+
+    fn balances(token_index: u32, amount: u32) -> Balances {
+        let mut balances = Balances(Default::default());
+        balances.0.insert(
+            token_index,
+            AssetLeaf {
+                is_insufficient: false,
+                amount: U256::from(amount),
+            },
+        );
+        balances
+    }
+
+    #[test]
+    fn test_memo_only_transfer_inputs() {
+        // a zero amount transfer of a token the sender does not hold
+        let transfers = vec![memo_only_transfer(1)];
+        let memos = vec![PaymentMemoEntry {
+            transfer_index: 0,
+            topic: "payment_memo/invoice".to_string(),
+            memo: "invoice #1".to_string(),
+        }];
+        let quote = fee_quote(Some(fee(0, 100)));
+        assert!(validate_tx_inputs(&transfers, &quote).is_ok());
+        assert!(validate_payment_memos(&transfers, &memos).is_ok());
+
+        // the fee is still owed
+        let (transfer_amounts, _) = required_amounts(&transfers, &quote);
+        assert_eq!(
+            transfer_amounts,
+            vec![(1, U256::zero()), (0, U256::from(100))]
+        );
+        assert!(balance_check(&balances(0, 100), &transfer_amounts).is_ok());
+        assert!(balance_check(&balances(0, 99), &transfer_amounts).is_err());
+
+        // without a fee, an empty balance is enough
+        let (transfer_amounts, _) = required_amounts(&transfers, &fee_quote(None));
+        assert!(balance_check(&Balances(Default::default()), &transfer_amounts).is_ok());
+    }
+
+    #[test]
+    fn test_check_transfer_amounts() {
+        let transfer = |token_index, amount| Transfer {
+            amount: U256::from(amount),
+            ..memo_only_transfer(token_index)
+        };
+        let transfers = vec![transfer(0, 100), transfer(1, 50)];
+        assert!(check_transfer_amounts(&transfers, None).is_ok());
+
+        let caps: MaxTransferAmount = "0:100".parse().unwrap();
+        assert!(check_transfer_amounts(&transfers, Some(&caps)).is_ok());
+
+        let caps: MaxTransferAmount = "0:100,49".parse().unwrap();
+        assert!(matches!(
+            check_transfer_amounts(&transfers, Some(&caps)),
+            Err(ClientError::TransferAmountExceedsLimit { token_index: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_check_proposal_expiry() {
+        assert!(check_proposal_expiry(110, 100, 5).is_ok());
+        assert!(check_proposal_expiry(105, 100, 5).is_ok());
+        assert!(matches!(
+            check_proposal_expiry(104, 100, 5),
+            Err(ClientError::ProposalExpired {
+                expiry: 104,
+                current_time: 100
+            })
+        ));
+        assert!(check_proposal_expiry(90, 100, 0).is_err());
+        assert!(check_proposal_expiry(0, 100, 5).is_ok());
+    }
+}
@@ -1,5 +1,16 @@
+use std::{collections::BTreeMap, str::FromStr};
+
+use intmax2_zkp::ethereum_types::u256::U256;
 use serde::{Deserialize, Serialize};
 
+pub const DEFAULT_RECEIVE_CONCURRENCY: usize = 4;
+pub const DEFAULT_BALANCE_PROVER_WARMUP_RETRIES: u32 = 3;
+pub const DEFAULT_PROPOSAL_EXPIRY_MARGIN: u64 = 5;
+
+fn default_proposal_expiry_margin() -> u64 {
+    DEFAULT_PROPOSAL_EXPIRY_MARGIN
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ClientConfig {
@@ -9,6 +20,88 @@ pub struct ClientConfig {
     pub block_builder_query_interval: u64,
     pub block_builder_query_limit: u64,
     pub is_faster_mining: bool,
+
+    /// Maximum number of balance proofs generated concurrently during sync, which also bounds
+    /// the number of withdrawals proven concurrently
+    pub receive_concurrency: usize,
+
+    /// Number of health check retries, with exponential backoff, before a sync gives up on an
+    /// unavailable balance prover
+    pub balance_prover_warmup_retries: u32,
+
+    /// Number of L1 blocks that must have passed since the deposit transaction before a deposit
+    /// is treated as settled. 0 trusts the deposit as soon as the validity prover reports it.
+    pub deposit_confirmations: u64,
+
+    /// Whether to zstd-compress the user data snapshot and sent tx data before encryption.
+    /// Entries written either way remain readable, but clients that predate compression cannot
+    /// read compressed entries. Data read by other users is never compressed.
+    #[serde(default)]
+    pub compress_payloads: bool,
+
+    /// Seconds before its expiry from which a block proposal is treated as expired, to leave
+    /// time for the signature to reach the block builder
+    #[serde(default = "default_proposal_expiry_margin")]
+    pub proposal_expiry_margin: u64,
+
+    /// Cap on the amount of a single transfer or withdrawal, checked before the tx is sent.
+    /// No amount is capped if `None`.
+    #[serde(default)]
+    pub max_transfer_amount: Option<MaxTransferAmount>,
+}
+
+/// Caps on the amount of a single transfer, per token index or for every token
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MaxTransferAmount {
+    /// Cap of the tokens without a cap of their own
+    pub global: Option<U256>,
+    /// Caps by token index, taking precedence over `global`
+    #[serde(default)]
+    pub per_token: BTreeMap<u32, U256>,
+}
+
+impl MaxTransferAmount {
+    /// The cap of `token_index`, if any.
+    pub fn limit(&self, token_index: u32) -> Option<U256> {
+        self.per_token.get(&token_index).copied().or(self.global)
+    }
+}
+
+/// Parses comma separated caps, each either `<amount>` for the global cap or
+/// `<token_index>:<amount>`, e.g. `0:1000000000000000000,1000000`.
+impl FromStr for MaxTransferAmount {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_amount = |amount: &str| {
+            U256::from_str(amount.trim()).map_err(|e| format!("invalid amount {amount}: {e}"))
+        };
+        let mut caps = Self::default();
+        for entry in s.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            match entry.split_once(':') {
+                Some((token_index, amount)) => {
+                    let token_index = token_index
+                        .trim()
+                        .parse::<u32>()
+                        .map_err(|e| format!("invalid token index {token_index}: {e}"))?;
+                    if caps
+                        .per_token
+                        .insert(token_index, parse_amount(amount)?)
+                        .is_some()
+                    {
+                        return Err(format!("token index {token_index} is capped twice"));
+                    }
+                }
+                None => {
+                    if caps.global.replace(parse_amount(entry)?).is_some() {
+                        return Err("the global cap is given twice".to_string());
+                    }
+                }
+            }
+        }
+        Ok(caps)
+    }
 }
 
 impl Default for ClientConfig {
@@ -20,6 +113,35 @@ impl Default for ClientConfig {
             block_builder_query_interval: 5,
             block_builder_query_limit: 20,
             is_faster_mining: false,
+            receive_concurrency: DEFAULT_RECEIVE_CONCURRENCY,
+            balance_prover_warmup_retries: DEFAULT_BALANCE_PROVER_WARMUP_RETRIES,
+            deposit_confirmations: 0,
+            compress_payloads: false,
+            proposal_expiry_margin: DEFAULT_PROPOSAL_EXPIRY_MARGIN,
+            max_transfer_amount: None,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use intmax2_zkp::ethereum_types::u256::U256;
+
+    use super::MaxTransferAmount;
+
+    #[test]
+    fn test_parse_max_transfer_amount() {
+        let caps: MaxTransferAmount = "0:1000, 500 ,2:7".parse().unwrap();
+        assert_eq!(caps.limit(0), Some(U256::from(1000)));
+        assert_eq!(caps.limit(1), Some(U256::from(500)));
+        assert_eq!(caps.limit(2), Some(U256::from(7)));
+
+        let caps: MaxTransferAmount = "0:1000".parse().unwrap();
+        assert_eq!(caps.limit(1), None);
+
+        assert!("0:1,0:2".parse::<MaxTransferAmount>().is_err());
+        assert!("1,2".parse::<MaxTransferAmount>().is_err());
+        assert!("x:1".parse::<MaxTransferAmount>().is_err());
+        assert!("0:abc".parse::<MaxTransferAmount>().is_err());
+    }
+}
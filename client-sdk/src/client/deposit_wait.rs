@@ -0,0 +1,63 @@
+use intmax2_interfaces::{
+    api::validity_prover::interface::DepositInfo, utils::signature::current_time,
+};
+use intmax2_zkp::ethereum_types::bytes32::Bytes32;
+
+use crate::external_api::utils::time::sleep_for;
+
+use super::{client::Client, error::ClientError};
+
+/// Interval in seconds between two polls of the validity prover
+const DEPOSIT_WAIT_INTERVAL: u64 = 5;
+
+/// Waits until the validity prover observes the deposit to `pubkey_salt_hash` sent in the L1 tx
+/// `tx_hash`, for `timeout` seconds or `deposit_timeout` of the config if `None`.
+///
+/// `should_abort` is checked before each poll, and the wait fails with `DepositWaitAborted`
+/// once it returns true. On timeout, the error tells whether the L1 tx is not mined
+/// (`DepositTxNotMined`), or is mined but the validity prover has not caught up with it
+/// (`DepositNotObserved`), in which case the deposit is safe.
+pub async fn wait_for_deposit(
+    client: &Client,
+    pubkey_salt_hash: Bytes32,
+    tx_hash: Bytes32,
+    timeout: Option<u64>,
+    should_abort: &dyn Fn() -> bool,
+) -> Result<DepositInfo, ClientError> {
+    let timeout = timeout.unwrap_or(client.config.deposit_timeout);
+    let deadline = current_time().saturating_add(timeout);
+    loop {
+        if should_abort() {
+            return Err(ClientError::DepositWaitAborted);
+        }
+        if let Some(deposit_info) = client
+            .validity_prover
+            .get_deposit_info(pubkey_salt_hash)
+            .await?
+        {
+            return Ok(deposit_info);
+        }
+        if current_time() >= deadline {
+            break;
+        }
+        log::info!("deposit {pubkey_salt_hash} is not observed by the validity prover yet");
+        sleep_for(
+            DEPOSIT_WAIT_INTERVAL
+                .min(deadline.saturating_sub(current_time()))
+                .max(1),
+        )
+        .await;
+    }
+    match client
+        .liquidity_contract
+        .get_tx_block_number(tx_hash)
+        .await?
+    {
+        Some(l1_block_number) => Err(ClientError::DepositNotObserved {
+            tx_hash,
+            l1_block_number,
+            timeout,
+        }),
+        None => Err(ClientError::DepositTxNotMined { tx_hash, timeout }),
+    }
+}
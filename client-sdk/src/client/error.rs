@@ -2,12 +2,16 @@ use intmax2_interfaces::{
     api::error::ServerError,
     data::{encryption::errors::BlsEncryptionError, proof_compression::ProofCompressionError},
 };
-use intmax2_zkp::common::error::CommonError;
+use intmax2_zkp::{
+    common::error::CommonError,
+    ethereum_types::{bytes32::Bytes32, u256::U256},
+};
 
 use crate::external_api::contract::error::BlockchainError;
 
 use super::{
-    receive_validation::ReceiveValidationError, strategy::error::StrategyError,
+    receive_validation::ReceiveValidationError,
+    strategy::error::{PendingTxInfo, StrategyError},
     sync::error::SyncError,
 };
 
@@ -49,21 +53,54 @@ pub enum ClientError {
     #[error("Failed to get proposal: {0}")]
     FailedToGetProposal(String),
 
+    #[error("Tx request expired: {0}")]
+    TxRequestExpired(String),
+
     #[error("Balance error: {0}")]
     BalanceError(String),
 
+    #[error("No balance proof found at or before block {0}")]
+    BalanceProofNotFound(u32),
+
     #[error("Invalid transfer len: {0}")]
     TransferLenError(String),
 
+    #[error(
+        "Transfer amount {amount} of token {token_index} exceeds the configured limit {limit}"
+    )]
+    TransferAmountExceedsLimit {
+        token_index: u32,
+        amount: U256,
+        limit: U256,
+    },
+
     #[error("Cannot send tx by zero balance account")]
     CannotSendTxByZeroBalanceAccount,
 
     #[error("Invalid block proposal: {0}")]
     InvalidBlockProposal(String),
 
+    #[error("Block proposal expired: expiry {expiry}, current time {current_time}")]
+    ProposalExpired { expiry: u64, current_time: u64 },
+
     #[error("Invalid mining deposit criteria")]
     InvalidMiningDepositCriteria,
 
+    #[error("Deposit tx {tx_hash} was not mined within {timeout} seconds")]
+    DepositTxNotMined { tx_hash: Bytes32, timeout: u64 },
+
+    #[error(
+        "Deposit tx {tx_hash} was mined in L1 block {l1_block_number}, but the validity prover did not observe it within {timeout} seconds. The deposit is safe and will appear after the validity prover syncs"
+    )]
+    DepositNotObserved {
+        tx_hash: Bytes32,
+        l1_block_number: u64,
+        timeout: u64,
+    },
+
+    #[error("Waiting for the deposit was aborted")]
+    DepositWaitAborted,
+
     #[error("Block builder fee error: {0}")]
     BlockBuilderFeeError(String),
 
@@ -73,9 +110,29 @@ pub enum ClientError {
     #[error("Validity prover error: {0}")]
     ValidityProverError(String),
 
+    #[error("Invalid withdrawal proof: {0}")]
+    InvalidWithdrawalProof(String),
+
+    #[error("Invalid balance attestation: {0}")]
+    InvalidBalanceAttestation(String),
+
+    #[error("Invalid tx inclusion proof: {0}")]
+    InvalidTxInclusionProof(String),
+
     #[error("Backup error: {0}")]
     BackupError(String),
 
     #[error("Deserialization error: {0}")]
     DeserializeError(String),
 }
+
+impl ClientError {
+    /// Details of the pending tx blocking the sync, if that is the cause of the error.
+    pub fn pending_tx_info(&self) -> Option<&PendingTxInfo> {
+        match self {
+            ClientError::StrategyError(e) => e.pending_tx_info(),
+            ClientError::SyncError(e) => e.pending_tx_info(),
+            _ => None,
+        }
+    }
+}
@@ -9,8 +9,10 @@ use intmax2_interfaces::{
 };
 use intmax2_zkp::{
     common::{signature_content::key_set::KeySet, transfer::Transfer},
-    ethereum_types::{u256::U256, u32limb_trait::U32LimbTrait as _},
+    ethereum_types::{bytes32::Bytes32, u256::U256, u32limb_trait::U32LimbTrait as _},
 };
+use num_bigint::BigUint;
+use num_traits::identities::Zero;
 use serde::{Deserialize, Serialize};
 
 use crate::{
@@ -94,6 +96,62 @@ pub(crate) async fn quote_claim_fee(
     Ok((fee_info.beneficiary, fee))
 }
 
+/// Validate that fee contributions split across multiple tokens cover the quoted fee.
+///
+/// The first contribution's token is the reference token. Each contribution is converted into
+/// the reference token at the rate implied by `fee_list`, i.e.
+/// `amount * reference_fee / token_fee`, and the total must be at least the reference fee.
+pub fn validate_fee_contributions(
+    contributions: &[Fee],
+    fee_list: &[Fee],
+) -> Result<(), SyncError> {
+    let reference = contributions.first().ok_or(SyncError::FeeError(
+        "fee contributions are empty".to_string(),
+    ))?;
+    let quoted_fee = |token_index: u32| {
+        fee_list
+            .iter()
+            .find(|fee| fee.token_index == token_index)
+            .map(|fee| fee.amount)
+            .ok_or(SyncError::FeeError(format!(
+                "fee token index {token_index} is not accepted"
+            )))
+    };
+    let reference_fee: BigUint = quoted_fee(reference.token_index)?.into();
+    let mut total = BigUint::zero();
+    for contribution in contributions {
+        let token_fee: BigUint = quoted_fee(contribution.token_index)?.into();
+        if token_fee.is_zero() {
+            // the fee is free in this token
+            return Ok(());
+        }
+        let amount: BigUint = contribution.amount.into();
+        total += amount * &reference_fee / token_fee;
+    }
+    if total < reference_fee {
+        return Err(SyncError::FeeError(format!(
+            "fee contributions are not enough: total: {total}, required: {reference_fee}"
+        )));
+    }
+    Ok(())
+}
+
+/// generate fee transfers to the block builder's beneficiary from fee contributions
+pub fn generate_fee_contribution_transfers(
+    beneficiary: U256,
+    contributions: &[Fee],
+) -> Vec<Transfer> {
+    contributions
+        .iter()
+        .map(|fee| Transfer {
+            recipient: beneficiary.into(),
+            amount: fee.amount,
+            token_index: fee.token_index,
+            salt: generate_salt(),
+        })
+        .collect()
+}
+
 /// generate fee payment memos for withdrawal and claim fee
 pub fn generate_fee_payment_memo(
     transfers: &[Transfer],
@@ -265,6 +323,32 @@ pub async fn select_unused_fees(
     fee: Fee,
     fee_type: FeeType,
     tx_timeout: u64,
+) -> Result<Vec<PaymentMemo>, SyncError> {
+    select_unused_fees_excluding(
+        store_vault_server,
+        validity_prover,
+        key,
+        fee_beneficiary,
+        fee,
+        fee_type,
+        tx_timeout,
+        &[],
+    )
+    .await
+}
+
+/// Same as `select_unused_fees`, skipping the fee transfers in `excluded_digests`, e.g. the ones
+/// already selected for another request of the same batch.
+#[allow(clippy::too_many_arguments)]
+pub async fn select_unused_fees_excluding(
+    store_vault_server: &dyn StoreVaultClientInterface,
+    validity_prover: &dyn ValidityProverClientInterface,
+    key: KeySet,
+    fee_beneficiary: U256,
+    fee: Fee,
+    fee_type: FeeType,
+    tx_timeout: u64,
+    excluded_digests: &[Bytes32],
 ) -> Result<Vec<PaymentMemo>, SyncError> {
     let unused_fees = get_unused_payments(store_vault_server, key, fee_type).await?;
     // Extract only those whose fee.token_index and recipient matches and sort by fee.amount
@@ -273,6 +357,7 @@ pub async fn select_unused_fees(
         .filter(|memo| {
             memo.transfer_data.transfer.token_index == fee.token_index
                 && memo.transfer_data.transfer.recipient == fee_beneficiary.into()
+                && !excluded_digests.contains(&memo.meta.digest)
         })
         .collect::<Vec<_>>();
     sorted_fee_memo.sort_by_key(|memo| memo.transfer_data.transfer.amount);
@@ -318,3 +403,37 @@ pub async fn select_unused_fees(
     }
     Ok(fee_transfers)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fee(token_index: u32, amount: u32) -> Fee {
+        Fee {
+            token_index,
+            amount: U256::from(amount),
+        }
+    }
+
+    #[test]
+    fn test_validate_fee_contributions() {
+        // 100 units of token 0 are worth 50 units of token 1
+        let fee_list = vec![fee(0, 100), fee(1, 50)];
+
+        // single token
+        assert!(validate_fee_contributions(&[fee(0, 100)], &fee_list).is_ok());
+        assert!(validate_fee_contributions(&[fee(0, 99)], &fee_list).is_err());
+
+        // split across tokens: 60 of token 0 + 20 of token 1 (= 40 of token 0)
+        assert!(validate_fee_contributions(&[fee(0, 60), fee(1, 20)], &fee_list).is_ok());
+        assert!(validate_fee_contributions(&[fee(1, 20), fee(0, 60)], &fee_list).is_ok());
+        assert!(validate_fee_contributions(&[fee(0, 60), fee(1, 19)], &fee_list).is_err());
+    }
+
+    #[test]
+    fn test_validate_fee_contributions_invalid_token() {
+        let fee_list = vec![fee(0, 100)];
+        assert!(validate_fee_contributions(&[fee(0, 50), fee(2, 50)], &fee_list).is_err());
+        assert!(validate_fee_contributions(&[], &fee_list).is_err());
+    }
+}
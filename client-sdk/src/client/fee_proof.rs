@@ -25,7 +25,10 @@ use intmax2_zkp::{
     ethereum_types::{address::Address, bytes32::Bytes32, u256::U256},
 };
 
-use super::{error::ClientError, sync::utils::generate_spent_witness};
+use super::{
+    error::ClientError,
+    sync::utils::{generate_salt, generate_spent_witness},
+};
 
 #[allow(clippy::too_many_arguments)]
 pub async fn generate_fee_proof(
@@ -37,6 +40,7 @@ pub async fn generate_fee_proof(
     sender_proof_set_ephemeral_key: U256,
     tx_nonce: u32,
     fee_index: u32,
+    additional_fee_indices: &[u32],
     transfers: &[Transfer],
     collateral_transfer: Option<Transfer>,
     is_registration_block: bool,
@@ -50,110 +54,163 @@ pub async fn generate_fee_proof(
         transfer_tree_root: transfer_tree.get_root(),
         nonce: tx_nonce,
     };
-    let fee_transfer_witness = TransferWitness {
+    let transfer_witness = |index: u32| TransferWitness {
         tx,
-        transfer: transfers[fee_index as usize],
-        transfer_index: fee_index,
-        transfer_merkle_proof: transfer_tree.prove(fee_index as u64),
+        transfer: transfers[index as usize],
+        transfer_index: index,
+        transfer_merkle_proof: transfer_tree.prove(index as u64),
     };
-    let collateral_block = if let Some(collateral_transfer) = collateral_transfer {
-        // spent proof
-        let transfers = vec![collateral_transfer];
-        let collateral_spent_witness =
-            generate_spent_witness(&user_data.full_private_state, tx_nonce, &transfers)?;
-        let tx = collateral_spent_witness.tx;
-        let spent_proof = balance_prover
-            .prove_spent(key, &collateral_spent_witness)
-            .await?;
-        let compressed_spent_proof = CompressedSpentProof::new(&spent_proof)?;
-        let sender_proof_set = SenderProofSet {
-            spent_proof: compressed_spent_proof,
-            prev_balance_proof: user_data.balance_proof.clone().unwrap(), // unwrap is safe
-        };
-        let ephemeral_key = KeySet::rand(&mut default_rng());
-        store_vault_server
-            .save_snapshot(
-                ephemeral_key,
-                &DataType::SenderProofSet.to_topic(),
-                None,
-                &sender_proof_set.encrypt(ephemeral_key.pubkey, Some(ephemeral_key))?,
+    let fee_transfer_witness = transfer_witness(fee_index);
+    let additional_fee_transfer_witnesses = additional_fee_indices
+        .iter()
+        .map(|&index| transfer_witness(index))
+        .collect();
+    // Fast path: most block builders do not require collateral, in which case no collateral
+    // transfer is spent, proven or saved.
+    let collateral_block = match collateral_transfer {
+        Some(collateral_transfer) => Some(
+            generate_collateral_block(
+                store_vault_server,
+                balance_prover,
+                tx_timeout,
+                key,
+                user_data,
+                tx_nonce,
+                collateral_transfer,
+                is_registration_block,
+                block_builder_address,
             )
-            .await?;
-        let sender_proof_set_ephemeral_key = ephemeral_key.privkey;
-
-        let mut transfer_tree = TransferTree::new(TRANSFER_TREE_HEIGHT);
-        transfer_tree.push(collateral_transfer);
-        let transfer_index = 0u32;
-        let transfer_merkle_proof = transfer_tree.prove(transfer_index as u64);
-        let mut tx_tree = TxTree::new(TX_TREE_HEIGHT);
-        tx_tree.push(tx);
-        let tx_index = 0u32;
-        let tx_merkle_proof = tx_tree.prove(tx_index as u64);
-        let tx_tree_root: Bytes32 = tx_tree.get_root().into();
-        let mut pubkeys = vec![key.pubkey];
-        pubkeys.resize(NUM_SENDERS_IN_BLOCK, U256::dummy_pubkey());
-        let pubkey_hash = get_pubkey_hash(&pubkeys);
-
-        let fee_transfer_data = TransferData {
-            sender_proof_set_ephemeral_key,
-            sender_proof_set: None,
-            sender: key.pubkey,
-            tx,
-            tx_index,
-            tx_merkle_proof,
-            tx_tree_root,
-            transfer: collateral_transfer,
-            transfer_index,
-            transfer_merkle_proof,
-        };
-        let encrypted_fee_transfer_data = fee_transfer_data.encrypt(key.pubkey, Some(key))?;
-        let encrypted_fee_transfer_digest = get_digest(&encrypted_fee_transfer_data);
-
-        let expiry = tx_timeout + chrono::Utc::now().timestamp() as u64;
-        let block_sign_payload = BlockSignPayload {
-            is_registration_block,
-            tx_tree_root,
-            expiry: expiry.into(),
-            block_builder_address,
-            block_builder_nonce: 0, // contract will ignore nonce checking
-        };
-        let signature = block_sign_payload.sign(key.privkey, pubkey_hash);
-        let collateral_block = CollateralBlock {
-            sender_proof_set_ephemeral_key,
-            fee_transfer_data,
-            is_registration_block,
-            expiry,
-            block_builder_address,
-            signature,
-        };
-
-        // save tx data for collateral block
-        let transfer_data = &collateral_block.fee_transfer_data;
-        let tx_data = TxData {
-            tx_index: transfer_data.tx_index,
-            tx_merkle_proof: transfer_data.tx_merkle_proof.clone(),
-            tx_tree_root: transfer_data.tx_tree_root,
-            spent_witness: collateral_spent_witness.clone(),
-            transfer_digests: vec![encrypted_fee_transfer_digest],
-            transfer_types: vec![TransferType::TransferCollateralFee.to_string()],
-            sender_proof_set_ephemeral_key: collateral_block.sender_proof_set_ephemeral_key,
-        };
-        let entry = SaveDataEntry {
-            topic: DataType::Tx.to_topic(),
-            pubkey: key.pubkey,
-            data: tx_data.encrypt(key.pubkey, Some(key))?,
-        };
-        store_vault_server.save_data_batch(key, &[entry]).await?;
-
-        Some(collateral_block)
-    } else {
-        None
+            .await?,
+        ),
+        None => None,
     };
 
     Ok(FeeProof {
         fee_transfer_witness,
         collateral_block,
         sender_proof_set_ephemeral_key,
+        additional_fee_transfer_witnesses,
+    })
+}
+
+/// Proves the collateral block, a block that only contains the collateral transfer to the block
+/// builder, and saves its tx data.
+#[allow(clippy::too_many_arguments)]
+async fn generate_collateral_block(
+    store_vault_server: &dyn StoreVaultClientInterface,
+    balance_prover: &dyn BalanceProverClientInterface,
+    tx_timeout: u64,
+    key: KeySet,
+    user_data: &UserData,
+    tx_nonce: u32,
+    collateral_transfer: Transfer,
+    is_registration_block: bool,
+    block_builder_address: Address,
+) -> Result<CollateralBlock, ClientError> {
+    // spent proof
+    let transfers = vec![collateral_transfer];
+    let collateral_spent_witness =
+        generate_spent_witness(&user_data.full_private_state, tx_nonce, &transfers)?;
+    let tx = collateral_spent_witness.tx;
+    let spent_proof = balance_prover
+        .prove_spent(key, &collateral_spent_witness)
+        .await?;
+    let compressed_spent_proof = CompressedSpentProof::new(&spent_proof)?;
+    let sender_proof_set = SenderProofSet {
+        spent_proof: compressed_spent_proof,
+        prev_balance_proof: user_data.balance_proof.clone().unwrap(), // unwrap is safe
+    };
+    let ephemeral_key = KeySet::rand(&mut default_rng());
+    store_vault_server
+        .save_snapshot(
+            ephemeral_key,
+            &DataType::SenderProofSet.to_topic(),
+            None,
+            &sender_proof_set.encrypt(ephemeral_key.pubkey, Some(ephemeral_key))?,
+        )
+        .await?;
+    let sender_proof_set_ephemeral_key = ephemeral_key.privkey;
+
+    let mut transfer_tree = TransferTree::new(TRANSFER_TREE_HEIGHT);
+    transfer_tree.push(collateral_transfer);
+    let transfer_index = 0u32;
+    let transfer_merkle_proof = transfer_tree.prove(transfer_index as u64);
+    let mut tx_tree = TxTree::new(TX_TREE_HEIGHT);
+    tx_tree.push(tx);
+    let tx_index = 0u32;
+    let tx_merkle_proof = tx_tree.prove(tx_index as u64);
+    let tx_tree_root: Bytes32 = tx_tree.get_root().into();
+    let mut pubkeys = vec![key.pubkey];
+    pubkeys.resize(NUM_SENDERS_IN_BLOCK, U256::dummy_pubkey());
+    let pubkey_hash = get_pubkey_hash(&pubkeys);
+
+    let fee_transfer_data = TransferData {
+        sender_proof_set_ephemeral_key,
+        sender_proof_set: None,
+        sender: key.pubkey,
+        tx,
+        tx_index,
+        tx_merkle_proof,
+        tx_tree_root,
+        transfer: collateral_transfer,
+        transfer_index,
+        transfer_merkle_proof,
+    };
+    let encrypted_fee_transfer_data = fee_transfer_data.encrypt(key.pubkey, Some(key))?;
+    let encrypted_fee_transfer_digest = get_digest(&encrypted_fee_transfer_data);
+
+    let expiry = tx_timeout + chrono::Utc::now().timestamp() as u64;
+    let block_sign_payload = BlockSignPayload {
+        is_registration_block,
+        tx_tree_root,
+        expiry: expiry.into(),
+        block_builder_address,
+        block_builder_nonce: 0, // contract will ignore nonce checking
+    };
+    let signature = block_sign_payload.sign(key.privkey, pubkey_hash);
+    let collateral_block = CollateralBlock {
+        sender_proof_set_ephemeral_key,
+        fee_transfer_data,
+        is_registration_block,
+        expiry,
+        block_builder_address,
+        signature,
+    };
+
+    // save tx data for collateral block
+    let transfer_data = &collateral_block.fee_transfer_data;
+    let tx_data = TxData {
+        tx_index: transfer_data.tx_index,
+        tx_merkle_proof: transfer_data.tx_merkle_proof.clone(),
+        tx_tree_root: transfer_data.tx_tree_root,
+        spent_witness: collateral_spent_witness.clone(),
+        transfer_digests: vec![encrypted_fee_transfer_digest],
+        transfer_types: vec![TransferType::TransferCollateralFee.to_string()],
+        sender_proof_set_ephemeral_key: collateral_block.sender_proof_set_ephemeral_key,
+    };
+    let entry = SaveDataEntry {
+        topic: DataType::Tx.to_topic(),
+        pubkey: key.pubkey,
+        data: tx_data.encrypt(key.pubkey, Some(key))?,
+    };
+    store_vault_server.save_data_batch(key, &[entry]).await?;
+
+    Ok(collateral_block)
+}
+
+/// The collateral transfer to the block builder, or `None` if the block builder does not require
+/// collateral. A zero collateral fee is treated as not required, so that no collateral block has
+/// to be proven for it.
+pub(crate) fn generate_collateral_transfer(
+    beneficiary: Option<U256>,
+    collateral_fee: Option<&Fee>,
+) -> Option<Transfer> {
+    let collateral_fee = collateral_fee.filter(|fee| fee.amount != U256::default())?;
+    Some(Transfer {
+        recipient: beneficiary?.into(),
+        amount: collateral_fee.amount,
+        token_index: collateral_fee.token_index,
+        salt: generate_salt(),
     })
 }
 
@@ -180,7 +237,8 @@ pub(crate) fn quote_transfer_fee(
     let collateral_fee = collateral_fee_list
         .as_ref()
         .map(|collateral_fee_list| get_fee(fee_token_index, collateral_fee_list))
-        .transpose()?;
+        .transpose()?
+        .filter(|collateral_fee| collateral_fee.amount != U256::default()); // zero means not required
     Ok((fee, collateral_fee))
 }
 
@@ -193,3 +251,46 @@ fn get_fee(fee_token_index: u32, fee_list: &[Fee]) -> Result<Fee, ClientError> {
         ))?;
     Ok(fee.clone())
 }
+
+#[cfg(test)]
+mod tests {
+    use intmax2_interfaces::api::block_builder::interface::{BlockBuilderFeeInfo, Fee};
+    use intmax2_zkp::ethereum_types::{address::Address, u256::U256};
+
+    use super::{generate_collateral_transfer, quote_transfer_fee};
+
+    fn fee(token_index: u32, amount: u32) -> Fee {
+        Fee {
+            token_index,
+            amount: U256::from(amount),
+        }
+    }
+
+    #[test]
+    fn test_no_collateral_transfer_when_not_required() {
+        let beneficiary = Some(U256::from(1));
+        assert!(generate_collateral_transfer(beneficiary, None).is_none());
+        assert!(generate_collateral_transfer(beneficiary, Some(&fee(0, 0))).is_none());
+
+        let transfer = generate_collateral_transfer(beneficiary, Some(&fee(0, 100))).unwrap();
+        assert_eq!(transfer.amount, U256::from(100));
+    }
+
+    #[test]
+    fn test_zero_collateral_fee_is_not_quoted() {
+        let fee_info = BlockBuilderFeeInfo {
+            block_builder_address: Address::default(),
+            beneficiary: Some(U256::from(1)),
+            registration_fee: Some(vec![fee(0, 100)]),
+            non_registration_fee: Some(vec![fee(0, 50)]),
+            registration_collateral_fee: Some(vec![fee(0, 200)]),
+            non_registration_collateral_fee: Some(vec![fee(0, 0)]),
+        };
+        let (fee, collateral_fee) = quote_transfer_fee(false, 0, &fee_info).unwrap();
+        assert_eq!(fee.unwrap().amount, U256::from(50));
+        assert!(collateral_fee.is_none());
+
+        let (_, collateral_fee) = quote_transfer_fee(true, 0, &fee_info).unwrap();
+        assert_eq!(collateral_fee.unwrap().amount, U256::from(200));
+    }
+}
@@ -1,6 +1,10 @@
+use std::{collections::VecDeque, future::Future};
+
+use futures::{stream, Stream};
 use intmax2_interfaces::{
-    api::store_vault_server::types::{MetaDataCursor, MetaDataCursorResponse},
+    api::store_vault_server::types::{CursorOrder, MetaDataCursor, MetaDataCursorResponse},
     data::{
+        data_type::DataType,
         deposit_data::DepositData,
         meta_data::{MetaData, MetaDataWithBlockNumber},
         transfer_data::TransferData,
@@ -36,6 +40,31 @@ pub enum EntryStatus {
     Timeout,        // Timed out
 }
 
+/// An entry of the deposit, transfer or tx history, as merged by `fetch_history` and
+/// `history_stream`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HistoryItem {
+    Deposit(HistoryEntry<DepositData>),
+    Receive(HistoryEntry<TransferData>),
+    Send(HistoryEntry<TxData>),
+}
+
+impl HistoryItem {
+    pub fn meta(&self) -> &MetaData {
+        match self {
+            HistoryItem::Deposit(entry) => &entry.meta,
+            HistoryItem::Receive(entry) => &entry.meta,
+            HistoryItem::Send(entry) => &entry.meta,
+        }
+    }
+
+    fn sort_key(&self) -> (u64, String) {
+        let meta = self.meta();
+        (meta.timestamp, meta.digest.to_hex())
+    }
+}
+
 impl EntryStatus {
     pub fn from_settled(processed_digests: &[Bytes32], meta: MetaDataWithBlockNumber) -> Self {
         if processed_digests.contains(&meta.meta.digest) {
@@ -67,6 +96,7 @@ pub async fn fetch_deposit_history(
         &[],
         cursor,
         client.config.deposit_timeout,
+        client.config.deposit_confirmations,
     )
     .await?;
     for (meta, settled) in all_deposit_info.settled {
@@ -204,3 +234,332 @@ pub async fn fetch_tx_history(
 
     Ok((history, cursor_response))
 }
+
+/// Number of entries of each history fetched per request by `history_stream`
+const HISTORY_STREAM_PAGE_SIZE: u32 = 64;
+
+// the histories are merged in this order, which breaks ties between entries of equal timestamp
+// and digest
+const HISTORY_DATA_TYPES: [DataType; 3] = [DataType::Deposit, DataType::Transfer, DataType::Tx];
+
+fn history_cursor(
+    order: CursorOrder,
+    from_timestamp: Option<u64>,
+    limit: Option<u32>,
+) -> MetaDataCursor {
+    MetaDataCursor {
+        cursor: from_timestamp.map(|timestamp| MetaData {
+            timestamp,
+            digest: Bytes32::default(),
+        }),
+        order,
+        limit,
+    }
+}
+
+async fn fetch_history_page(
+    client: &Client,
+    key: KeySet,
+    data_type: DataType,
+    cursor: MetaDataCursor,
+) -> Result<(Vec<HistoryItem>, MetaDataCursorResponse), ClientError> {
+    match data_type {
+        DataType::Deposit => {
+            let (entries, cursor_response) = fetch_deposit_history(client, key, &cursor).await?;
+            let items = entries.into_iter().map(HistoryItem::Deposit).collect();
+            Ok((items, cursor_response))
+        }
+        DataType::Transfer => {
+            let (entries, cursor_response) = fetch_transfer_history(client, key, &cursor).await?;
+            let items = entries.into_iter().map(HistoryItem::Receive).collect();
+            Ok((items, cursor_response))
+        }
+        DataType::Tx => {
+            let (entries, cursor_response) = fetch_tx_history(client, key, &cursor).await?;
+            let items = entries.into_iter().map(HistoryItem::Send).collect();
+            Ok((items, cursor_response))
+        }
+        _ => Err(ClientError::UnexpectedError(format!(
+            "no history for data type {data_type}"
+        ))),
+    }
+}
+
+/// Fetches the whole deposit, transfer and tx history after (or before, in descending order)
+/// `from_timestamp`, sorted by timestamp and digest in `order`.
+pub async fn fetch_history(
+    client: &Client,
+    key: KeySet,
+    order: CursorOrder,
+    from_timestamp: Option<u64>,
+) -> Result<Vec<HistoryItem>, ClientError> {
+    collect_history(
+        |data_type, cursor| fetch_history_page(client, key, data_type, cursor),
+        order,
+        from_timestamp,
+    )
+    .await
+}
+
+/// Same as `fetch_history`, but yields the entries as their pages are fetched instead of
+/// waiting for the whole history. The stream ends after the first error.
+pub fn history_stream(
+    client: &Client,
+    key: KeySet,
+    order: CursorOrder,
+    from_timestamp: Option<u64>,
+) -> impl Stream<Item = Result<HistoryItem, ClientError>> + '_ {
+    merge_history_pages(
+        move |data_type, cursor| fetch_history_page(client, key, data_type, cursor),
+        order,
+        from_timestamp,
+        HISTORY_STREAM_PAGE_SIZE,
+    )
+}
+
+async fn collect_history<F, Fut>(
+    fetch_page: F,
+    order: CursorOrder,
+    from_timestamp: Option<u64>,
+) -> Result<Vec<HistoryItem>, ClientError>
+where
+    F: Fn(DataType, MetaDataCursor) -> Fut,
+    Fut: Future<Output = Result<(Vec<HistoryItem>, MetaDataCursorResponse), ClientError>>,
+{
+    let mut history = Vec::new();
+    for data_type in HISTORY_DATA_TYPES {
+        let mut cursor = history_cursor(order.clone(), from_timestamp, None);
+        loop {
+            let (page, cursor_response) = fetch_page(data_type, cursor.clone()).await?;
+            history.extend(page);
+            if !cursor_response.has_more || cursor_response.next_cursor.is_none() {
+                break;
+            }
+            cursor.cursor = cursor_response.next_cursor;
+        }
+    }
+    history.sort_by_key(|item| item.sort_key());
+    if order == CursorOrder::Desc {
+        history.reverse();
+    }
+    Ok(history)
+}
+
+/// Pages through one history, buffering the entries of the current page in `order`.
+struct HistorySource {
+    data_type: DataType,
+    cursor: MetaDataCursor,
+    has_more: bool,
+    buffer: VecDeque<HistoryItem>,
+}
+
+struct HistoryMerge<F> {
+    fetch_page: F,
+    order: CursorOrder,
+    sources: Vec<HistorySource>,
+}
+
+impl<F, Fut> HistoryMerge<F>
+where
+    F: Fn(DataType, MetaDataCursor) -> Fut,
+    Fut: Future<Output = Result<(Vec<HistoryItem>, MetaDataCursorResponse), ClientError>>,
+{
+    async fn next(&mut self) -> Result<Option<HistoryItem>, ClientError> {
+        let fetch_page = &self.fetch_page;
+        for source in self.sources.iter_mut() {
+            // a page may be empty if its entries failed to decrypt, so keep going
+            while source.buffer.is_empty() && source.has_more {
+                let (mut page, cursor_response) =
+                    fetch_page(source.data_type, source.cursor.clone()).await?;
+                // the page is sorted in ascending order, like the materialized history
+                page.sort_by_key(|item| item.sort_key());
+                if self.order == CursorOrder::Desc {
+                    page.reverse();
+                }
+                source.buffer.extend(page);
+                source.has_more = cursor_response.has_more && cursor_response.next_cursor.is_some();
+                source.cursor.cursor = cursor_response.next_cursor;
+            }
+        }
+        // ties go to the first source in ascending order, and to the last one in descending
+        // order, matching the reversed stable sort of `collect_history`
+        let heads = self
+            .sources
+            .iter()
+            .enumerate()
+            .filter_map(|(i, source)| source.buffer.front().map(|item| (item.sort_key(), i)));
+        let next = match self.order {
+            CursorOrder::Asc => heads.min(),
+            CursorOrder::Desc => heads.max(),
+        };
+        Ok(next.and_then(|(_, i)| self.sources[i].buffer.pop_front()))
+    }
+}
+
+fn merge_history_pages<F, Fut>(
+    fetch_page: F,
+    order: CursorOrder,
+    from_timestamp: Option<u64>,
+    page_size: u32,
+) -> impl Stream<Item = Result<HistoryItem, ClientError>>
+where
+    F: Fn(DataType, MetaDataCursor) -> Fut,
+    Fut: Future<Output = Result<(Vec<HistoryItem>, MetaDataCursorResponse), ClientError>>,
+{
+    let sources = HISTORY_DATA_TYPES
+        .into_iter()
+        .map(|data_type| HistorySource {
+            data_type,
+            cursor: history_cursor(order.clone(), from_timestamp, Some(page_size)),
+            has_more: true,
+            buffer: VecDeque::new(),
+        })
+        .collect();
+    let merge = HistoryMerge {
+        fetch_page,
+        order,
+        sources,
+    };
+    stream::try_unfold(merge, |mut merge| async move {
+        let item = merge.next().await?;
+        Ok(item.map(|item| (item, merge)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::TryStreamExt as _;
+    use intmax2_interfaces::{
+        api::store_vault_server::types::{CursorOrder, MetaDataCursor, MetaDataCursorResponse},
+        data::{
+            data_type::DataType,
+            deposit_data::{DepositData, TokenType},
+            meta_data::MetaData,
+        },
+    };
+    use intmax2_zkp::ethereum_types::{
+        address::Address, bytes32::Bytes32, u256::U256, u32limb_trait::U32LimbTrait as _,
+    };
+
+    use super::{
+        collect_history, merge_history_pages, EntryStatus, HistoryEntry, HistoryItem,
+        HISTORY_DATA_TYPES,
+    };
+    use crate::client::{error::ClientError, sync::utils::generate_salt};
+
+    fn item(source: u32, timestamp: u64, digest: u32) -> HistoryItem {
+        HistoryItem::Deposit(HistoryEntry {
+            data: DepositData {
+                deposit_salt: generate_salt(),
+                depositor: Address::default(),
+                pubkey_salt_hash: Bytes32::default(),
+                amount: U256::default(),
+                is_eligible: true,
+                token_type: TokenType::NATIVE,
+                token_address: Address::default(),
+                token_id: U256::default(),
+                is_mining: false,
+                token_index: Some(source),
+            },
+            status: EntryStatus::Pending,
+            meta: MetaData {
+                timestamp,
+                digest: Bytes32::from_u32_slice(&[0, 0, 0, 0, 0, 0, 0, digest]).unwrap(),
+            },
+        })
+    }
+
+    // (source, timestamp, digest) of each item
+    fn summary(items: &[HistoryItem]) -> Vec<(u32, u64, Bytes32)> {
+        items
+            .iter()
+            .map(|item| match item {
+                HistoryItem::Deposit(entry) => (
+                    entry.data.token_index.unwrap(),
+                    entry.meta.timestamp,
+                    entry.meta.digest,
+                ),
+                _ => unreachable!(),
+            })
+            .collect()
+    }
+
+    /// Serves the histories like the store vault server, paging by (timestamp, digest) from
+    /// the cursor, with each page sorted in ascending order like `fetch_*_history`.
+    fn fake_page(
+        histories: &[Vec<HistoryItem>],
+        data_type: DataType,
+        cursor: &MetaDataCursor,
+    ) -> (Vec<HistoryItem>, MetaDataCursorResponse) {
+        let source = HISTORY_DATA_TYPES
+            .iter()
+            .position(|t| *t == data_type)
+            .unwrap();
+        let mut items = histories[source]
+            .iter()
+            .filter(|item| {
+                let key = item.sort_key();
+                match (&cursor.order, &cursor.cursor) {
+                    (_, None) => true,
+                    (CursorOrder::Asc, Some(c)) => key > (c.timestamp, c.digest.to_hex()),
+                    (CursorOrder::Desc, Some(c)) => key < (c.timestamp, c.digest.to_hex()),
+                }
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        items.sort_by_key(|item| item.sort_key());
+        if cursor.order == CursorOrder::Desc {
+            items.reverse();
+        }
+        let limit = cursor.limit.unwrap_or(256) as usize;
+        let has_more = items.len() > limit;
+        items.truncate(limit);
+        let next_cursor = items.last().map(|item| item.meta().clone());
+        items.sort_by_key(|item| item.sort_key());
+        let cursor_response = MetaDataCursorResponse {
+            next_cursor,
+            has_more,
+            total_count: items.len() as u32,
+        };
+        (items, cursor_response)
+    }
+
+    #[tokio::test]
+    async fn test_history_stream_matches_materialized_history() {
+        let histories = vec![
+            vec![
+                item(0, 10, 1),
+                item(0, 30, 2),
+                item(0, 30, 5),
+                item(0, 50, 3),
+            ],
+            vec![item(1, 20, 4), item(1, 30, 3), item(1, 30, 6)],
+            vec![
+                item(2, 5, 7),
+                item(2, 30, 5),
+                item(2, 60, 8),
+                item(2, 70, 9),
+            ],
+        ];
+        let fetch_page = |data_type: DataType, cursor: MetaDataCursor| {
+            let page = fake_page(&histories, data_type, &cursor);
+            async move { Ok::<_, ClientError>(page) }
+        };
+
+        for order in [CursorOrder::Asc, CursorOrder::Desc] {
+            for from_timestamp in [None, Some(30)] {
+                let materialized = collect_history(fetch_page, order.clone(), from_timestamp)
+                    .await
+                    .unwrap();
+                for page_size in [1, 2, 100] {
+                    let streamed =
+                        merge_history_pages(fetch_page, order.clone(), from_timestamp, page_size)
+                            .try_collect::<Vec<_>>()
+                            .await
+                            .unwrap();
+                    assert_eq!(summary(&streamed), summary(&materialized));
+                }
+            }
+        }
+    }
+}
@@ -6,9 +6,28 @@ use num_traits::identities::Zero;
 use sha2::{Digest, Sha512};
 
 pub fn generate_intmax_account_from_eth_key(eth_private_key: B256) -> KeySet {
+    generate_intmax_account_from_eth_key_with_context(eth_private_key, None)
+}
+
+/// Derives the intmax account of `eth_private_key`, folding `derivation_context` into the key
+/// derivation. `None` gives the default account, the same as
+/// `generate_intmax_account_from_eth_key`.
+///
+/// Any context, including an empty string, derives a completely different account from the
+/// same Ethereum key. Funds sent to that account can only be recovered with the exact same
+/// context, so it must be backed up together with the key.
+pub fn generate_intmax_account_from_eth_key_with_context(
+    eth_private_key: B256,
+    derivation_context: Option<&str>,
+) -> KeySet {
     let mut hasher = Sha512::new();
     loop {
         hasher.update(b"INTMAX");
+        if let Some(context) = derivation_context {
+            // length-prefixed so that the context cannot run into the key bytes
+            hasher.update((context.len() as u64).to_be_bytes());
+            hasher.update(context.as_bytes());
+        }
         hasher.update(eth_private_key.0);
         let digest = hasher.clone().finalize();
         let provisional_private_key: Fr = BigUint::from_bytes_be(&digest).into();
@@ -26,7 +45,9 @@ mod test {
     use alloy::primitives::B256;
     use intmax2_zkp::ethereum_types::u32limb_trait::U32LimbTrait;
 
-    use crate::client::key_from_eth::generate_intmax_account_from_eth_key;
+    use crate::client::key_from_eth::{
+        generate_intmax_account_from_eth_key, generate_intmax_account_from_eth_key_with_context,
+    };
 
     struct TestCase {
         private_key: B256,
@@ -72,4 +93,27 @@ mod test {
             assert_eq!(account.pubkey.to_hex(), test_case.public_key);
         }
     }
+
+    #[test]
+    fn test_account_with_context() {
+        let private_key: B256 = "f68ff926147a67518161e65cd54a3a44c2379e4b63c74b52cfc74274d2586299"
+            .parse()
+            .unwrap();
+        let default = generate_intmax_account_from_eth_key(private_key).pubkey;
+        assert_eq!(
+            generate_intmax_account_from_eth_key_with_context(private_key, None).pubkey,
+            default
+        );
+
+        let a = generate_intmax_account_from_eth_key_with_context(private_key, Some("a")).pubkey;
+        let b = generate_intmax_account_from_eth_key_with_context(private_key, Some("b")).pubkey;
+        let empty = generate_intmax_account_from_eth_key_with_context(private_key, Some("")).pubkey;
+        assert_ne!(a, default);
+        assert_ne!(a, b);
+        assert_ne!(empty, default);
+        assert_eq!(
+            generate_intmax_account_from_eth_key_with_context(private_key, Some("a")).pubkey,
+            a
+        );
+    }
 }
@@ -0,0 +1,157 @@
+use intmax2_zkp::{
+    common::{
+        private_state::{PrivateState, PrivateStateTarget},
+        trees::asset_tree::{AssetLeaf, AssetLeafTarget, AssetMerkleProof, AssetMerkleProofTarget},
+    },
+    constants::ASSET_TREE_HEIGHT,
+    ethereum_types::{
+        u256::{U256Target, U256},
+        u32limb_trait::{U32LimbTargetTrait as _, U32LimbTrait as _},
+    },
+    utils::{conversion::ToU64 as _, poseidon_hash_out::PoseidonHashOut},
+};
+use plonky2::{
+    field::{goldilocks_field::GoldilocksField, types::Field as _},
+    iop::{
+        target::Target,
+        witness::{PartialWitness, WitnessWrite as _},
+    },
+    plonk::{
+        circuit_builder::CircuitBuilder,
+        circuit_data::{CircuitConfig, CircuitData},
+        config::PoseidonGoldilocksConfig,
+        proof::ProofWithPublicInputs,
+    },
+};
+use serde::{Deserialize, Serialize};
+use serde_with::{base64::Base64, serde_as};
+
+type F = GoldilocksField;
+type C = PoseidonGoldilocksConfig;
+const D: usize = 2;
+
+/// Statement of a min balance proof: the private state committed to by `private_commitment`
+/// holds at least `threshold` of `token_index`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MinBalancePublicInputs {
+    pub private_commitment: PoseidonHashOut,
+    pub token_index: u32,
+    pub threshold: U256,
+}
+
+impl MinBalancePublicInputs {
+    pub fn to_u64_vec(&self) -> Vec<u64> {
+        let mut vec = self.private_commitment.to_u64_vec();
+        vec.push(self.token_index as u64);
+        vec.extend(
+            self.threshold
+                .to_u32_vec()
+                .into_iter()
+                .map(|limb| limb as u64),
+        );
+        vec
+    }
+}
+
+/// Proof of the min balance circuit, serialized with bincode.
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MinBalanceProof(#[serde_as(as = "Base64")] pub Vec<u8>);
+
+impl MinBalanceProof {
+    pub fn new(proof: &ProofWithPublicInputs<F, C, D>) -> anyhow::Result<Self> {
+        Ok(Self(bincode::serialize(proof)?))
+    }
+
+    pub fn decompress(&self) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+        Ok(bincode::deserialize(&self.0)?)
+    }
+}
+
+/// Circuit proving that the private state behind a private commitment holds at least a threshold
+/// of a token, without disclosing the private state or the amount.
+///
+/// The private state, the asset leaf and its merkle proof are witnesses. The circuit recomputes
+/// the commitment, opens the leaf of the token against the asset tree root and checks the amount
+/// against the threshold. It uses the zero-knowledge config, so the proof hides the witnesses.
+pub struct MinBalanceCircuit {
+    pub data: CircuitData<F, C, D>,
+    private_state: PrivateStateTarget,
+    asset_leaf: AssetLeafTarget,
+    asset_merkle_proof: AssetMerkleProofTarget,
+    token_index: Target,
+    threshold: U256Target,
+}
+
+impl Default for MinBalanceCircuit {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MinBalanceCircuit {
+    pub fn new() -> Self {
+        let mut builder =
+            CircuitBuilder::<F, D>::new(CircuitConfig::standard_recursion_zk_config());
+        let private_state = PrivateStateTarget::new(&mut builder);
+        let asset_leaf = AssetLeafTarget::new(&mut builder, true);
+        let asset_merkle_proof = AssetMerkleProofTarget::new(&mut builder, ASSET_TREE_HEIGHT);
+        let token_index = builder.add_virtual_target();
+        let threshold = U256Target::new(&mut builder, true);
+
+        let private_commitment = private_state.commitment(&mut builder);
+        asset_merkle_proof.verify::<F, C, D>(
+            &mut builder,
+            &asset_leaf,
+            token_index,
+            private_state.asset_tree_root,
+        );
+        builder.assert_zero(asset_leaf.is_insufficient.target);
+        let reaches_threshold = threshold.is_le(&mut builder, &asset_leaf.amount);
+        builder.assert_one(reaches_threshold.target);
+
+        builder.register_public_inputs(&private_commitment.to_vec());
+        builder.register_public_input(token_index);
+        builder.register_public_inputs(&threshold.to_vec());
+        let data = builder.build::<C>();
+        Self {
+            data,
+            private_state,
+            asset_leaf,
+            asset_merkle_proof,
+            token_index,
+            threshold,
+        }
+    }
+
+    pub fn prove(
+        &self,
+        private_state: &PrivateState,
+        asset_leaf: AssetLeaf,
+        asset_merkle_proof: &AssetMerkleProof,
+        token_index: u32,
+        threshold: U256,
+    ) -> anyhow::Result<ProofWithPublicInputs<F, C, D>> {
+        let mut pw = PartialWitness::<F>::new();
+        self.private_state.set_witness(&mut pw, private_state);
+        self.asset_leaf.set_witness(&mut pw, asset_leaf);
+        self.asset_merkle_proof
+            .set_witness(&mut pw, asset_merkle_proof);
+        pw.set_target(self.token_index, F::from_canonical_u32(token_index));
+        self.threshold.set_witness(&mut pw, threshold);
+        self.data.prove(pw)
+    }
+
+    /// Verify `proof` and check that it proves `public_inputs`.
+    pub fn verify(
+        &self,
+        proof: &ProofWithPublicInputs<F, C, D>,
+        public_inputs: &MinBalancePublicInputs,
+    ) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            proof.public_inputs.to_u64_vec() == public_inputs.to_u64_vec(),
+            "public inputs do not match the attested statement"
+        );
+        self.data.verify(proof.clone())
+    }
+}
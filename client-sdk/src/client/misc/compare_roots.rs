@@ -0,0 +1,98 @@
+use std::{fmt, ops::RangeInclusive};
+
+use intmax2_interfaces::api::validity_prover::interface::ValidityProverClientInterface;
+use intmax2_zkp::circuits::validity::validity_pis::ValidityPublicInputs;
+use serde::{Deserialize, Serialize};
+
+use crate::{client::error::ClientError, external_api::validity_prover::ValidityProverClient};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RootKind {
+    Block,
+    Account,
+    Deposit,
+}
+
+impl fmt::Display for RootKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RootKind::Block => write!(f, "block"),
+            RootKind::Account => write!(f, "account"),
+            RootKind::Deposit => write!(f, "deposit"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RootDivergence {
+    pub block_number: u32,
+    pub root_kind: RootKind,
+    pub root_a: String,
+    pub root_b: String,
+}
+
+/// Compare the block/account/deposit roots of two validity provers over `block_range`,
+/// and return the first block where they differ.
+pub async fn compare_roots(
+    prover_url_a: &str,
+    prover_url_b: &str,
+    block_range: RangeInclusive<u32>,
+) -> Result<Option<RootDivergence>, ClientError> {
+    let prover_a = ValidityProverClient::new(prover_url_a);
+    let prover_b = ValidityProverClient::new(prover_url_b);
+    compare_roots_with_clients(&prover_a, &prover_b, block_range).await
+}
+
+pub async fn compare_roots_with_clients(
+    prover_a: &dyn ValidityProverClientInterface,
+    prover_b: &dyn ValidityProverClientInterface,
+    block_range: RangeInclusive<u32>,
+) -> Result<Option<RootDivergence>, ClientError> {
+    for block_number in block_range {
+        let pis_a = fetch_validity_pis(prover_a, block_number).await?;
+        let pis_b = fetch_validity_pis(prover_b, block_number).await?;
+        let state_a = &pis_a.public_state;
+        let state_b = &pis_b.public_state;
+        let roots = [
+            (
+                RootKind::Block,
+                state_a.block_tree_root.to_string(),
+                state_b.block_tree_root.to_string(),
+            ),
+            (
+                RootKind::Account,
+                state_a.account_tree_root.to_string(),
+                state_b.account_tree_root.to_string(),
+            ),
+            (
+                RootKind::Deposit,
+                state_a.deposit_tree_root.to_string(),
+                state_b.deposit_tree_root.to_string(),
+            ),
+        ];
+        if let Some((root_kind, root_a, root_b)) = roots.into_iter().find(|(_, a, b)| a != b) {
+            log::warn!("{root_kind} root diverged at block {block_number}: {root_a} != {root_b}");
+            return Ok(Some(RootDivergence {
+                block_number,
+                root_kind,
+                root_a,
+                root_b,
+            }));
+        }
+    }
+    Ok(None)
+}
+
+async fn fetch_validity_pis(
+    validity_prover: &dyn ValidityProverClientInterface,
+    block_number: u32,
+) -> Result<ValidityPublicInputs, ClientError> {
+    let validity_witness = validity_prover.get_validity_witness(block_number).await?;
+    validity_witness.to_validity_pis().map_err(|e| {
+        ClientError::ValidityProverError(format!(
+            "failed to convert to validity pis at block {block_number}: {e}"
+        ))
+    })
+}
@@ -1 +1,2 @@
+pub mod compare_roots;
 pub mod payment_memo;
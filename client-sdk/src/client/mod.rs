@@ -1,15 +1,26 @@
+pub mod audit;
 pub mod backup;
+pub mod balance_at_block;
+pub mod balance_attestation;
 #[allow(clippy::module_inception)]
 pub mod client;
 pub mod config;
+pub mod deposit_wait;
 pub mod error;
 pub mod fee_payment;
 pub mod fee_proof;
 pub mod history;
 pub mod key_from_eth;
+pub mod min_balance_circuit;
 pub mod misc;
 pub mod multisig;
+pub mod offline_signing;
+pub mod operation;
+pub mod rebuild;
 pub mod receipt;
 pub mod receive_validation;
+pub mod state_dump;
 pub mod strategy;
 pub mod sync;
+pub mod token_info;
+pub mod tx_inclusion;
@@ -0,0 +1,78 @@
+use intmax2_zkp::{
+    common::{
+        block_builder::{BlockProposal, UserSignature},
+        signature_content::{flatten::FlatG2, key_set::KeySet},
+    },
+    ethereum_types::{bytes32::Bytes32, u256::U256, u32limb_trait::U32LimbTrait as _},
+};
+use serde::{Deserialize, Serialize};
+
+use super::error::ClientError;
+
+/// Version of the `SignedProposal` format. Bumped on any change, so that an offline signer and
+/// an online submitter of different versions fail loudly instead of misreading each other.
+pub const SIGNED_PROPOSAL_VERSION: u32 = 1;
+
+/// Signature of a block proposal, made by `sign_proposal` on the machine holding the key and
+/// handed back to the machine that submits it to the block builder.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedProposal {
+    pub version: u32,
+    pub pubkey: U256,
+    pub tx_tree_root: Bytes32,
+    pub signature: FlatG2,
+}
+
+/// Sign `proposal` with `key`. This needs neither the network nor the clock, so it can run on
+/// an air-gapped machine. The proposal is verified against the tx by the submitter.
+pub fn sign_proposal(key: KeySet, proposal: &BlockProposal) -> SignedProposal {
+    let signature = proposal.sign(key);
+    SignedProposal {
+        version: SIGNED_PROPOSAL_VERSION,
+        pubkey: signature.pubkey,
+        tx_tree_root: proposal.block_sign_payload.tx_tree_root,
+        signature: signature.signature,
+    }
+}
+
+impl SignedProposal {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, ClientError> {
+        let signed: Self = serde_json::from_str(json).map_err(|e| {
+            ClientError::InvalidBlockProposal(format!("failed to parse signed proposal: {e}"))
+        })?;
+        if signed.version != SIGNED_PROPOSAL_VERSION {
+            return Err(ClientError::InvalidBlockProposal(format!(
+                "unsupported signed proposal version {}",
+                signed.version
+            )));
+        }
+        Ok(signed)
+    }
+
+    /// Check that this is a valid signature of `proposal` by `pubkey`.
+    pub fn verify(&self, pubkey: U256, proposal: &BlockProposal) -> Result<(), ClientError> {
+        if self.pubkey != pubkey {
+            return Err(ClientError::InvalidBlockProposal(format!(
+                "signed by {} instead of {}",
+                self.pubkey.to_hex(),
+                pubkey.to_hex()
+            )));
+        }
+        if self.tx_tree_root != proposal.block_sign_payload.tx_tree_root {
+            return Err(ClientError::InvalidBlockProposal(
+                "signature is for another proposal".to_string(),
+            ));
+        }
+        UserSignature {
+            pubkey: self.pubkey,
+            signature: self.signature.clone(),
+        }
+        .verify(&proposal.block_sign_payload, proposal.pubkeys_hash)
+        .map_err(|e| ClientError::InvalidBlockProposal(format!("invalid signature: {e}")))
+    }
+}
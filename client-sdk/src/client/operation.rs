@@ -0,0 +1,73 @@
+use std::{fmt::Display, future::Future};
+
+use intmax2_interfaces::utils::random::default_rng;
+use rand::Rng as _;
+
+pub use common::operation::OPERATION_ID_HEADER;
+
+tokio::task_local! {
+    static OPERATION_ID: String;
+}
+
+/// Generate a random operation id, 16 hex characters.
+pub fn generate_operation_id() -> String {
+    let bytes: [u8; 8] = default_rng().gen();
+    hex::encode(bytes)
+}
+
+/// The id of the operation running in the current task, if any. Requests the operation sends to
+/// the servers of this repository carry it in the `OPERATION_ID_HEADER` header.
+pub fn current_operation_id() -> Option<String> {
+    OPERATION_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Run `f` as the operation `operation_id`, e.g. an id the caller has already shown to the user.
+/// The operations started by `f` share this id.
+pub async fn with_operation_id<F: Future>(operation_id: String, f: F) -> F::Output {
+    OPERATION_ID.scope(operation_id, f).await
+}
+
+/// Run `f` as the top-level client operation `name` with a new operation id, logging its start
+/// and its result with the id. Inside another operation, `f` shares the id of that operation
+/// instead.
+pub async fn run_operation<T, E, F>(name: &str, f: F) -> Result<T, E>
+where
+    E: Display,
+    F: Future<Output = Result<T, E>>,
+{
+    if current_operation_id().is_some() {
+        return f.await;
+    }
+    let operation_id = generate_operation_id();
+    log::info!("{name} started: operation id {operation_id}");
+    let result = with_operation_id(operation_id.clone(), f).await;
+    match &result {
+        Ok(_) => log::info!("{name} finished: operation id {operation_id}"),
+        Err(e) => log::warn!("{name} failed: operation id {operation_id}: {e}"),
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_nested_operations_share_the_id() {
+        assert_eq!(current_operation_id(), None);
+        let (outer, inner) = run_operation("outer", async {
+            let outer = current_operation_id();
+            let inner =
+                run_operation("inner", async { Ok::<_, String>(current_operation_id()) }).await?;
+            Ok::<_, String>((outer, inner))
+        })
+        .await
+        .unwrap();
+        assert_eq!(outer.as_ref().map(String::len), Some(16));
+        assert_eq!(outer, inner);
+        assert_eq!(current_operation_id(), None);
+
+        let id = with_operation_id("given".to_string(), async { current_operation_id() }).await;
+        assert_eq!(id.as_deref(), Some("given"));
+    }
+}
@@ -0,0 +1,285 @@
+use intmax2_interfaces::data::{
+    data_type::DataType, meta_data::MetaData, user_data::ProcessStatus,
+};
+use intmax2_zkp::{common::signature_content::key_set::KeySet, ethereum_types::bytes32::Bytes32};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    balance_at_block::fetch_all,
+    client::Client,
+    error::ClientError,
+    history::{EntryStatus, HistoryEntry},
+};
+
+/// Result of `rebuild_user_data_from_store`. The user data was left as is if `changes` is empty.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RebuildReport {
+    /// Block number of the balance proof the entries were checked against
+    pub balance_proof_block_number: u32,
+    /// Changes of the deposit, transfer and tx statuses, one per changed status
+    pub changes: Vec<ProcessStatusChange>,
+}
+
+impl RebuildReport {
+    pub fn is_unchanged(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessStatusChange {
+    pub data_type: DataType,
+    /// Entries reflected in the balance proof that were not marked as processed
+    pub marked_processed: Vec<Bytes32>,
+    /// Entries marked as processed that are not reflected in the balance proof, or that are not
+    /// found in the store vault
+    pub unmarked: Vec<Bytes32>,
+    pub prev_last_processed_meta_data: Option<MetaData>,
+    pub last_processed_meta_data: Option<MetaData>,
+}
+
+/// Rebuilds the deposit, transfer and tx statuses of the user data of `key` from the entries in
+/// the store vault, and saves the user data if they changed.
+///
+/// A deposit or transfer is processed iff its nullifier is in the nullifier tree, and a tx is
+/// processed iff it is settled at or before the block of the balance proof. No proof is
+/// generated, and the balance proof and private state are left as is. The withdrawal and claim
+/// statuses are not rebuilt.
+pub async fn rebuild_user_data_from_store(
+    client: &Client,
+    key: KeySet,
+) -> Result<RebuildReport, ClientError> {
+    let (mut user_data, prev_digest) = client.get_user_data_and_digest(key).await?;
+    let balance_proof_block_number = user_data
+        .block_number()
+        .map_err(|e| ClientError::UnexpectedError(e.to_string()))?;
+    let nullifiers = user_data.full_private_state.nullifier_tree.nullifiers();
+
+    let deposits =
+        fetch_all(|cursor| async move { client.fetch_deposit_history(key, &cursor).await }).await?;
+    let transfers =
+        fetch_all(|cursor| async move { client.fetch_transfer_history(key, &cursor).await })
+            .await?;
+    let txs =
+        fetch_all(|cursor| async move { client.fetch_tx_history(key, &cursor).await }).await?;
+
+    let deposit_entries = entry_marks(&deposits, |data| {
+        data.deposit()
+            .map(|deposit| nullifiers.contains(&deposit.poseidon_hash().into()))
+    });
+    let transfer_entries = entry_marks(&transfers, |data| {
+        Some(nullifiers.contains(&data.transfer.nullifier()))
+    });
+    let tx_entries = txs
+        .iter()
+        .map(|entry| {
+            let processed = match entry.status {
+                EntryStatus::Settled(block_number) | EntryStatus::Processed(block_number) => {
+                    Some(block_number <= balance_proof_block_number)
+                }
+                EntryStatus::Pending => Some(false),
+                EntryStatus::Timeout => None,
+            };
+            (entry.meta.clone(), processed)
+        })
+        .collect::<Vec<_>>();
+
+    let mut changes = Vec::new();
+    for (data_type, status, entries) in [
+        (
+            DataType::Deposit,
+            &mut user_data.deposit_status,
+            deposit_entries,
+        ),
+        (
+            DataType::Transfer,
+            &mut user_data.transfer_status,
+            transfer_entries,
+        ),
+        (DataType::Tx, &mut user_data.tx_status, tx_entries),
+    ] {
+        let rebuilt = rebuild_status(status, entries);
+        if let Some(change) = diff_status(data_type, status, &rebuilt) {
+            log::info!(
+                "rebuild {data_type}: {} entries marked as processed, {} unmarked",
+                change.marked_processed.len(),
+                change.unmarked.len()
+            );
+            changes.push(change);
+            *status = rebuilt;
+        }
+    }
+    if !changes.is_empty() {
+        client.save_user_data(key, prev_digest, &user_data).await?;
+    }
+    Ok(RebuildReport {
+        balance_proof_block_number,
+        changes,
+    })
+}
+
+/// Pairs each entry with whether it is processed. Pending entries are not, and `None` keeps the
+/// current mark of timed out entries and of settled entries that `is_applied` cannot decide.
+fn entry_marks<T>(
+    entries: &[HistoryEntry<T>],
+    is_applied: impl Fn(&T) -> Option<bool>,
+) -> Vec<(MetaData, Option<bool>)> {
+    entries
+        .iter()
+        .map(|entry| {
+            let processed = match entry.status {
+                EntryStatus::Settled(_) | EntryStatus::Processed(_) => is_applied(&entry.data),
+                EntryStatus::Pending => Some(false),
+                EntryStatus::Timeout => None,
+            };
+            (entry.meta.clone(), processed)
+        })
+        .collect()
+}
+
+/// Rebuilds `status` from every entry in the store vault. The cursor is moved to the last
+/// processed entry, and the unprocessed entries before it are kept as pending so that they are
+/// fetched again.
+fn rebuild_status(
+    status: &ProcessStatus,
+    mut entries: Vec<(MetaData, Option<bool>)>,
+) -> ProcessStatus {
+    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let entries = entries
+        .into_iter()
+        .map(|(meta, processed)| {
+            let processed =
+                processed.unwrap_or_else(|| status.processed_digests.contains(&meta.digest));
+            (meta, processed)
+        })
+        .collect::<Vec<_>>();
+    let last_processed_meta_data = entries
+        .iter()
+        .rev()
+        .find(|(_, processed)| *processed)
+        .map(|(meta, _)| meta.clone());
+    let processed_digests = entries
+        .iter()
+        .filter(|(_, processed)| *processed)
+        .map(|(meta, _)| meta.digest)
+        .collect();
+    let pending_digests = entries
+        .iter()
+        .filter(|(meta, processed)| {
+            !processed
+                && last_processed_meta_data
+                    .as_ref()
+                    .is_some_and(|last| meta < last)
+        })
+        .map(|(meta, _)| meta.digest)
+        .collect();
+    ProcessStatus {
+        last_processed_meta_data,
+        processed_digests,
+        pending_digests,
+    }
+}
+
+fn diff_status(
+    data_type: DataType,
+    prev: &ProcessStatus,
+    rebuilt: &ProcessStatus,
+) -> Option<ProcessStatusChange> {
+    let marked_processed = rebuilt
+        .processed_digests
+        .iter()
+        .filter(|digest| !prev.processed_digests.contains(digest))
+        .copied()
+        .collect::<Vec<_>>();
+    let unmarked = prev
+        .processed_digests
+        .iter()
+        .filter(|digest| !rebuilt.processed_digests.contains(digest))
+        .copied()
+        .collect::<Vec<_>>();
+    let is_unchanged = marked_processed.is_empty()
+        && unmarked.is_empty()
+        && prev.last_processed_meta_data == rebuilt.last_processed_meta_data
+        && prev.pending_digests.len() == rebuilt.pending_digests.len()
+        && rebuilt
+            .pending_digests
+            .iter()
+            .all(|digest| prev.pending_digests.contains(digest));
+    if is_unchanged {
+        return None;
+    }
+    Some(ProcessStatusChange {
+        data_type,
+        marked_processed,
+        unmarked,
+        prev_last_processed_meta_data: prev.last_processed_meta_data.clone(),
+        last_processed_meta_data: rebuilt.last_processed_meta_data.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use intmax2_interfaces::data::{
+        data_type::DataType, meta_data::MetaData, user_data::ProcessStatus,
+    };
+    use intmax2_zkp::ethereum_types::{bytes32::Bytes32, u32limb_trait::U32LimbTrait as _};
+
+    use super::{diff_status, rebuild_status};
+
+    fn meta(i: u32) -> MetaData {
+        MetaData {
+            timestamp: i as u64,
+            digest: Bytes32::from_u32_slice(&[0, 0, 0, 0, 0, 0, 0, i]).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_rebuild_status() {
+        // 1 is processed but missing from the store vault, 3 is processed without being marked,
+        // 4 is marked but not processed
+        let status = ProcessStatus {
+            last_processed_meta_data: Some(meta(4)),
+            processed_digests: vec![meta(1).digest, meta(2).digest, meta(4).digest],
+            pending_digests: vec![],
+        };
+        let entries = vec![
+            (meta(5), Some(true)),
+            (meta(2), Some(true)),
+            (meta(3), Some(true)),
+            (meta(4), Some(false)),
+            (meta(6), Some(false)),
+        ];
+        let rebuilt = rebuild_status(&status, entries);
+        assert_eq!(rebuilt.last_processed_meta_data, Some(meta(5)));
+        assert_eq!(
+            rebuilt.processed_digests,
+            vec![meta(2).digest, meta(3).digest, meta(5).digest]
+        );
+        // 6 is after the cursor, so it is fetched without being pending
+        assert_eq!(rebuilt.pending_digests, vec![meta(4).digest]);
+
+        let change = diff_status(DataType::Deposit, &status, &rebuilt).unwrap();
+        assert_eq!(
+            change.marked_processed,
+            vec![meta(3).digest, meta(5).digest]
+        );
+        assert_eq!(change.unmarked, vec![meta(1).digest, meta(4).digest]);
+        assert_eq!(change.prev_last_processed_meta_data, Some(meta(4)));
+        assert!(diff_status(DataType::Deposit, &rebuilt, &rebuilt).is_none());
+    }
+
+    #[test]
+    fn test_undecided_entries_keep_their_mark() {
+        let status = ProcessStatus {
+            last_processed_meta_data: Some(meta(1)),
+            processed_digests: vec![meta(1).digest],
+            pending_digests: vec![],
+        };
+        let rebuilt = rebuild_status(&status, vec![(meta(1), None), (meta(2), None)]);
+        assert_eq!(rebuilt.processed_digests, vec![meta(1).digest]);
+        assert_eq!(rebuilt.last_processed_meta_data, Some(meta(1)));
+        assert!(diff_status(DataType::Tx, &status, &rebuilt).is_none());
+    }
+}
@@ -2,10 +2,13 @@ use base64::{prelude::BASE64_STANDARD, Engine};
 use intmax2_interfaces::data::{
     data_type::DataType, encryption::BlsEncryption, transfer_data::TransferData, tx_data::TxData,
 };
-use intmax2_zkp::{common::signature_content::key_set::KeySet, ethereum_types::bytes32::Bytes32};
+use intmax2_zkp::{
+    common::signature_content::key_set::KeySet,
+    ethereum_types::{bytes32::Bytes32, u256::U256},
+};
 use serde::{Deserialize, Serialize};
 
-use crate::client::receive_validation::validate_receive;
+use crate::client::receive_validation::validate_receive_with_sender;
 
 use super::{client::Client, error::ClientError, strategy::common::fetch_single_data};
 
@@ -47,16 +50,27 @@ pub async fn generate_transfer_receipt(
     Ok(encrypted_data_base64)
 }
 
+/// Transfer data of a validated receipt.
+#[derive(Clone, Debug)]
+pub struct ValidatedTransferReceipt {
+    pub data: TransferData,
+    /// Block number in which the transfer was included.
+    pub block_number: u32,
+    /// Sender pubkey bound by the sender's balance proof. `None` if the sender in the receipt
+    /// does not match the proof.
+    pub verified_sender: Option<U256>,
+}
+
 pub async fn validate_transfer_receipt(
     client: &Client,
     key: KeySet,
     transfer_receipt: &str,
-) -> Result<TransferData, ClientError> {
+) -> Result<ValidatedTransferReceipt, ClientError> {
     let encrypted_data = BASE64_STANDARD.decode(transfer_receipt).map_err(|e| {
         ClientError::DeserializeError(format!("Failed to decode transfer receipt as base64: {e}"))
     })?;
     let transfer_receipt: TransferReceipt = TransferReceipt::decrypt(key, None, &encrypted_data)?;
-    validate_receive(
+    let validated = validate_receive_with_sender(
         client.store_vault_server.as_ref(),
         client.validity_prover.as_ref(),
         key.pubkey,
@@ -64,5 +78,9 @@ pub async fn validate_transfer_receipt(
         &transfer_receipt.data,
     )
     .await?;
-    Ok(transfer_receipt.data.clone())
+    Ok(ValidatedTransferReceipt {
+        data: transfer_receipt.data,
+        block_number: validated.block_number,
+        verified_sender: validated.verified_sender,
+    })
 }
@@ -9,9 +9,11 @@ use intmax2_interfaces::{
     },
 };
 use intmax2_zkp::{
-    circuits::balance::send::spent_circuit::SpentPublicInputs, common::transfer::Transfer,
+    circuits::balance::{balance_pis::BalancePublicInputs, send::spent_circuit::SpentPublicInputs},
+    common::transfer::Transfer,
     ethereum_types::u256::U256,
 };
+use plonky2::field::goldilocks_field::GoldilocksField;
 use thiserror::Error;
 
 use super::strategy::{common::fetch_sender_proof_set, error::StrategyError};
@@ -40,6 +42,17 @@ pub enum ReceiveValidationError {
     GeneralError(String),
 }
 
+/// Result of a successful receive validation.
+#[derive(Debug, Clone)]
+pub struct ValidatedReceive {
+    pub transfer: Transfer,
+    /// Block number in which the tx of the transfer was included.
+    pub block_number: u32,
+    /// Sender pubkey bound by the sender's balance proof. This is `None` if the sender claimed
+    /// in the transfer data is not the one the proof was generated for.
+    pub verified_sender: Option<U256>,
+}
+
 /// Validate the Transfer corresponding to the given transfer_uuid.
 pub async fn validate_receive(
     store_vault_server: &dyn StoreVaultClientInterface,
@@ -48,6 +61,26 @@ pub async fn validate_receive(
     transfer_timestamp: u64,
     transfer_data: &TransferData,
 ) -> Result<Transfer, ReceiveValidationError> {
+    let validated = validate_receive_with_sender(
+        store_vault_server,
+        validity_prover,
+        recipient_pubkey,
+        transfer_timestamp,
+        transfer_data,
+    )
+    .await?;
+    Ok(validated.transfer)
+}
+
+/// Same as `validate_receive`, but also returns the block number of the transfer and the sender
+/// pubkey verified against the sender's balance proof.
+pub async fn validate_receive_with_sender(
+    store_vault_server: &dyn StoreVaultClientInterface,
+    validity_prover: &dyn ValidityProverClientInterface,
+    recipient_pubkey: U256,
+    transfer_timestamp: u64,
+    transfer_data: &TransferData,
+) -> Result<ValidatedReceive, ReceiveValidationError> {
     transfer_data
         .validate(recipient_pubkey)
         .map_err(|e| StrategyError::ValidationError(e.to_string()))?;
@@ -61,10 +94,8 @@ pub async fn validate_receive(
     // check if tx_tree_root included on the block
     let block_number = validity_prover
         .get_block_number_by_tx_tree_root(transfer_data.tx_tree_root)
-        .await?;
-    if block_number.is_none() {
-        return Err(ReceiveValidationError::TxIsNotSettled(transfer_timestamp));
-    }
+        .await?
+        .ok_or(ReceiveValidationError::TxIsNotSettled(transfer_timestamp))?;
     let sender_proof_set = fetch_sender_proof_set(
         store_vault_server,
         transfer_data.sender_proof_set_ephemeral_key,
@@ -96,5 +127,59 @@ pub async fn validate_receive(
             "Insufficient flag is on in spent proof".to_string(),
         ));
     }
-    Ok(transfer_data.transfer)
+
+    let prev_balance_proof = sender_proof_set.prev_balance_proof.decompress()?;
+    let verified_sender = verify_sender(&prev_balance_proof.public_inputs, transfer_data.sender)?;
+    Ok(ValidatedReceive {
+        transfer: transfer_data.transfer,
+        block_number,
+        verified_sender,
+    })
+}
+
+/// The spent proof is chained to the previous balance proof by the private commitment (checked
+/// in the sender proof set validation), so the balance proof's pubkey is the one that spent the
+/// tx. Returns it only if it is the `claimed_sender`.
+fn verify_sender(
+    prev_balance_proof_pis: &[GoldilocksField],
+    claimed_sender: U256,
+) -> Result<Option<U256>, ReceiveValidationError> {
+    let prev_balance_pis = BalancePublicInputs::from_pis(prev_balance_proof_pis).map_err(|e| {
+        ReceiveValidationError::ValidationError(format!(
+            "Failed to parse prev balance proof public inputs: {e}"
+        ))
+    })?;
+    Ok((prev_balance_pis.pubkey == claimed_sender).then_some(prev_balance_pis.pubkey))
+}
+
+#[cfg(test)]
+mod tests {
+    use intmax2_zkp::circuits::balance::balance_processor::get_prev_balance_pis;
+    use plonky2::field::types::Field as _;
+
+    use super::*;
+
+    fn prev_balance_proof_pis(sender: U256) -> Vec<GoldilocksField> {
+        get_prev_balance_pis(sender, &None)
+            .unwrap()
+            .to_u64_vec()
+            .into_iter()
+            .map(GoldilocksField::from_canonical_u64)
+            .collect()
+    }
+
+    #[test]
+    fn test_verify_sender_matching_sender() {
+        let sender = U256::from(2);
+        let verified_sender = verify_sender(&prev_balance_proof_pis(sender), sender).unwrap();
+        assert_eq!(verified_sender, Some(sender));
+    }
+
+    #[test]
+    fn test_verify_sender_other_sender() {
+        // The sender proof set was generated by another pubkey than the claimed sender
+        let verified_sender =
+            verify_sender(&prev_balance_proof_pis(U256::from(2)), U256::from(3)).unwrap();
+        assert_eq!(verified_sender, None);
+    }
 }
@@ -0,0 +1,123 @@
+use intmax2_interfaces::data::{
+    encryption::{errors::RsaEncryptionError, rsa::RsaEncryptedMessage, RsaEncryption},
+    receive_retry::DeferredReceive,
+    user_data::UserData,
+};
+use intmax2_zkp::{common::signature_content::key_set::KeySet, ethereum_types::u256::U256};
+use rsa::RsaPublicKey;
+use serde::{Deserialize, Serialize};
+
+use super::{client::Client, error::ClientError, sync::error::SyncError};
+
+/// State of an account for support debugging. It holds the user data, including the private
+/// state and the head balance proof, but never the private key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateDumpPayload {
+    pub user_data: UserData,
+    /// Not saved with the user data, so it is dumped separately
+    pub stuck_receives: Vec<DeferredReceive>,
+}
+
+impl RsaEncryption for StateDumpPayload {}
+
+/// A state dump encrypted to a support RSA key. The block numbers are left in plain text so
+/// that sync lag can be triaged without decrypting the dump.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateDump {
+    pub pubkey: U256,
+    /// Unix timestamp at which the dump was made
+    pub created_at: u64,
+    /// Block number of the head balance proof, or `None` if the account has none
+    pub balance_proof_block_number: Option<u32>,
+    /// Tip block number reported by the validity prover
+    pub validity_prover_block_number: u32,
+    /// `StateDumpPayload` encrypted with a random AES-256-GCM key, which is wrapped with RSA-OAEP
+    pub message: RsaEncryptedMessage,
+}
+
+impl StateDump {
+    /// Decrypts the payload with the AES key unwrapped by the holder of the RSA private key.
+    pub fn decrypt_with_aes_key(
+        &self,
+        aes_key: &[u8],
+    ) -> Result<StateDumpPayload, RsaEncryptionError> {
+        StateDumpPayload::decrypt_with_aes_key(aes_key, &self.message)
+    }
+}
+
+pub async fn make_state_dump(
+    client: &Client,
+    key: KeySet,
+    rsa_pubkey: &RsaPublicKey,
+) -> Result<StateDump, ClientError> {
+    let validity_prover_block_number = client.validity_prover.get_block_number().await?;
+    let user_data = client.get_user_data(key).await?;
+    let balance_proof_block_number = match &user_data.balance_proof {
+        Some(_) => Some(user_data.block_number().map_err(SyncError::from)?),
+        None => None,
+    };
+    let payload = StateDumpPayload {
+        stuck_receives: user_data.stuck_receives.clone(),
+        user_data,
+    };
+    Ok(StateDump {
+        pubkey: key.pubkey,
+        created_at: chrono::Utc::now().timestamp() as u64,
+        balance_proof_block_number,
+        validity_prover_block_number,
+        message: payload.encrypt_with_rsa(rsa_pubkey),
+    })
+}
+
+impl Client {
+    /// Dumps the user data and head balance proof of `key`, encrypted to `rsa_pubkey`, together
+    /// with the validity prover's tip block.
+    pub async fn make_state_dump(
+        &self,
+        key: KeySet,
+        rsa_pubkey: &RsaPublicKey,
+    ) -> Result<StateDump, ClientError> {
+        make_state_dump(self, key, rsa_pubkey).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use intmax2_interfaces::data::{
+        encryption::{rsa::decrypt_aes_key, RsaEncryption as _},
+        user_data::UserData,
+    };
+    use intmax2_zkp::common::signature_content::key_set::KeySet;
+    use rand::rngs::OsRng;
+    use rsa::RsaPrivateKey;
+
+    use super::{StateDump, StateDumpPayload};
+
+    #[test]
+    fn test_state_dump_roundtrip() {
+        let private_key = RsaPrivateKey::new(&mut OsRng, 2048).unwrap();
+        let key = KeySet::rand(&mut rand::thread_rng());
+        let payload = StateDumpPayload {
+            user_data: UserData::new(key.pubkey),
+            stuck_receives: vec![],
+        };
+        let dump = StateDump {
+            pubkey: key.pubkey,
+            created_at: 0,
+            balance_proof_block_number: None,
+            validity_prover_block_number: 10,
+            message: payload.encrypt_with_rsa(&private_key.to_public_key()),
+        };
+        let content = serde_json::to_string(&dump).unwrap();
+
+        let dump: StateDump = serde_json::from_str(&content).unwrap();
+        let aes_key = decrypt_aes_key(&private_key, &dump.message.encrypted_key).unwrap();
+        let decrypted = dump.decrypt_with_aes_key(&aes_key).unwrap();
+        assert_eq!(decrypted.user_data.pubkey, key.pubkey);
+        assert_eq!(
+            decrypted.user_data.private_commitment(),
+            payload.user_data.private_commitment()
+        );
+    }
+}
@@ -149,3 +149,40 @@ pub async fn fetch_single_data<T: BlsEncryption + Validation>(
     };
     Ok((meta, data))
 }
+
+/// Fetch the data of `digests`, skipping the entries that fail to decrypt or validate.
+pub async fn fetch_decrypt_validate_batch<T: BlsEncryption + Validation>(
+    store_vault_server: &dyn StoreVaultClientInterface,
+    key: KeySet,
+    data_type: DataType,
+    digests: &[Bytes32],
+) -> Result<Vec<(MetaData, T)>, StrategyError> {
+    let encrypted_data_with_meta = store_vault_server
+        .get_data_batch(key, &data_type.to_topic(), digests)
+        .await?;
+    let enc_sender = match data_type.rw_rights().write_rights {
+        WriteRights::SingleAuthWrite => Some(key.pubkey),
+        WriteRights::AuthWrite => Some(key.pubkey),
+        WriteRights::SingleOpenWrite => None,
+        WriteRights::OpenWrite => None,
+    };
+    let data_with_meta = encrypted_data_with_meta
+        .into_iter()
+        .filter_map(
+            |DataWithMetaData { meta, data }| match T::decrypt(key, enc_sender, &data) {
+                Ok(data) => match data.validate(key.pubkey) {
+                    Ok(_) => Some((meta, data)),
+                    Err(e) => {
+                        log::warn!("failed to validate {data_type}: {e}");
+                        None
+                    }
+                },
+                Err(e) => {
+                    log::warn!("failed to decrypt {data_type}: {e}");
+                    None
+                }
+            },
+        )
+        .collect();
+    Ok(data_with_meta)
+}
@@ -22,6 +22,10 @@ use crate::external_api::contract::liquidity_contract::LiquidityContract;
 
 use super::{common::fetch_decrypt_validate, error::StrategyError};
 
+/// Margin subtracted from the earliest candidate timestamp when requesting the deposit bloom
+/// filter, to absorb the gap between the client clock and the L1 deposit timestamp.
+const BLOOM_FILTER_TIMESTAMP_MARGIN: u64 = 24 * 60 * 60;
+
 #[derive(Debug, Clone)]
 pub struct DepositInfo {
     pub settled: Vec<(MetaDataWithBlockNumber, DepositData)>,
@@ -40,6 +44,7 @@ pub async fn fetch_deposit_info(
     excluded_digests: &[Bytes32],
     cursor: &MetaDataCursor,
     deposit_timeout: u64,
+    deposit_confirmations: u64,
 ) -> Result<(DepositInfo, MetaDataCursorResponse), StrategyError> {
     let mut settled = Vec::new();
     let mut pending = Vec::new();
@@ -54,22 +59,62 @@ pub async fn fetch_deposit_info(
     )
     .await?;
 
-    // Batch fetch deposit info for all valid deposits
+    // Batch fetch deposit info for the deposits that may have been observed
+    let maybe_observed = filter_maybe_observed_deposits(
+        validity_prover,
+        &data_with_meta,
+        current_time,
+        deposit_timeout,
+    )
+    .await?;
     let pubkey_salt_hashes: Vec<_> = data_with_meta
         .iter()
-        .map(|(_, deposit_data)| deposit_data.pubkey_salt_hash)
+        .zip(&maybe_observed)
+        .filter(|(_, &maybe_observed)| maybe_observed)
+        .map(|((_, deposit_data), _)| deposit_data.pubkey_salt_hash)
         .collect();
-    let deposit_infos = validity_prover
+    let mut fetched_deposit_infos = validity_prover
         .get_deposit_info_batch(&pubkey_salt_hashes)
-        .await?;
+        .await?
+        .into_iter();
+    let deposit_infos = maybe_observed
+        .iter()
+        .map(|&maybe_observed| {
+            if maybe_observed {
+                fetched_deposit_infos.next().flatten()
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
 
     // Process results and categorize deposits
+    let mut l1_block_number = None; // fetched on the first settled deposit
     for ((meta, mut deposit_data), deposit_info) in data_with_meta.into_iter().zip(deposit_infos) {
         match deposit_info {
             Some(info) => {
                 deposit_data.set_token_index(info.token_index);
 
                 if let Some(block_number) = info.block_number {
+                    if deposit_confirmations > 0 {
+                        let current_block_number = match l1_block_number {
+                            Some(block_number) => block_number,
+                            None => *l1_block_number
+                                .insert(liquidity_contract.get_block_number().await?),
+                        };
+                        let deposit_block_number = liquidity_contract
+                            .get_tx_block_number(info.l1_deposit_tx_hash)
+                            .await?;
+                        if !is_confirmed(
+                            deposit_block_number,
+                            current_block_number,
+                            deposit_confirmations,
+                        ) {
+                            log::info!("Deposit {} is pending confirmation", meta.digest);
+                            pending.push((meta, deposit_data));
+                            continue;
+                        }
+                    }
                     // deposit is settled
                     let meta = MetaDataWithBlockNumber { meta, block_number };
                     settled.push((meta, deposit_data));
@@ -126,6 +171,56 @@ pub async fn fetch_deposit_info(
     ))
 }
 
+/// Whether the deposit transaction mined in `deposit_block_number` has at least
+/// `required_confirmations` confirmations at `current_block_number`. The block including the
+/// transaction counts as the first confirmation.
+fn is_confirmed(
+    deposit_block_number: Option<u64>,
+    current_block_number: u64,
+    required_confirmations: u64,
+) -> bool {
+    match deposit_block_number {
+        Some(deposit_block_number) => {
+            current_block_number.saturating_sub(deposit_block_number) + 1 >= required_confirmations
+        }
+        // the receipt is gone, e.g. the deposit transaction was reorged out
+        None => false,
+    }
+}
+
+/// Returns for each deposit whether it may have been observed by the validity prover.
+///
+/// Deposits that can still be pending regardless of the result are checked against the deposit
+/// bloom filter first, so that the full deposit info is fetched only for the filter hits. The
+/// filter has no false negatives, and deposits that could time out are always fetched, so a
+/// skipped deposit is at worst reported as pending until the next sync.
+async fn filter_maybe_observed_deposits(
+    validity_prover: &dyn ValidityProverClientInterface,
+    data_with_meta: &[(MetaData, DepositData)],
+    current_time: u64,
+    deposit_timeout: u64,
+) -> Result<Vec<bool>, StrategyError> {
+    let can_be_pending =
+        |meta: &MetaData| meta.timestamp.saturating_add(deposit_timeout) >= current_time;
+    let from_timestamp = data_with_meta
+        .iter()
+        .filter(|(meta, _)| can_be_pending(meta))
+        .map(|(meta, _)| meta.timestamp)
+        .min();
+    let Some(from_timestamp) = from_timestamp else {
+        return Ok(vec![true; data_with_meta.len()]);
+    };
+    let bloom_filter = validity_prover
+        .get_deposit_bloom_filter(from_timestamp.saturating_sub(BLOOM_FILTER_TIMESTAMP_MARGIN))
+        .await?;
+    Ok(data_with_meta
+        .iter()
+        .map(|(meta, deposit_data)| {
+            !can_be_pending(meta) || bloom_filter.contains(&deposit_data.pubkey_salt_hash)
+        })
+        .collect())
+}
+
 pub async fn fetch_all_unprocessed_deposit_info(
     store_vault_server: &dyn StoreVaultClientInterface,
     validity_prover: &dyn ValidityProverClientInterface,
@@ -134,6 +229,7 @@ pub async fn fetch_all_unprocessed_deposit_info(
     current_time: u64,
     process_status: &ProcessStatus,
     deposit_timeout: u64,
+    deposit_confirmations: u64,
 ) -> Result<DepositInfo, StrategyError> {
     let mut cursor = MetaDataCursor {
         cursor: process_status.last_processed_meta_data.clone(),
@@ -163,6 +259,7 @@ pub async fn fetch_all_unprocessed_deposit_info(
             &process_status.processed_digests,
             &cursor,
             deposit_timeout,
+            deposit_confirmations,
         )
         .await?;
         if !included_digests.is_empty() {
@@ -184,3 +281,17 @@ pub async fn fetch_all_unprocessed_deposit_info(
         timeout,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::is_confirmed;
+
+    #[test]
+    fn test_is_confirmed() {
+        assert!(is_confirmed(Some(100), 100, 1));
+        assert!(!is_confirmed(Some(100), 100, 2));
+        assert!(is_confirmed(Some(100), 111, 12));
+        assert!(!is_confirmed(Some(100), 110, 12));
+        assert!(!is_confirmed(None, 200, 1));
+    }
+}
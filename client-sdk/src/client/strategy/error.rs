@@ -2,10 +2,60 @@ use intmax2_interfaces::{
     api::error::ServerError,
     data::{encryption::errors::BlsEncryptionError, proof_compression::ProofCompressionError},
 };
+use intmax2_zkp::ethereum_types::bytes32::Bytes32;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use crate::external_api::contract::error::BlockchainError;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PendingTxKind {
+    /// A sent tx is not settled yet
+    Send,
+    /// A settled tx needs pending deposits or transfers to cover its balance
+    Receive,
+}
+
+/// What a sync is blocked on, and when the block is expected to resolve.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingTxInfo {
+    pub kind: PendingTxKind,
+    /// Digest of the blocked tx in the store vault
+    pub tx_digest: Bytes32,
+    /// Block number of the blocked tx, `None` while a sent tx is not settled yet
+    pub block_number: Option<u32>,
+    /// Unix timestamp by which the pending entries are settled or timed out, based on
+    /// `tx_timeout` (and `deposit_timeout` for pending deposits)
+    pub expected_resolution_time: u64,
+}
+
+impl PendingTxInfo {
+    /// Seconds to wait before retrying the sync, 0 if the resolution time has passed.
+    pub fn retry_after_secs(&self, now: u64) -> u64 {
+        self.expected_resolution_time.saturating_sub(now)
+    }
+}
+
+impl std::fmt::Display for PendingTxInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self.kind {
+            PendingTxKind::Send => "pending tx",
+            PendingTxKind::Receive => "pending receives to proceed tx",
+        };
+        write!(f, "{kind}: {}", self.tx_digest)?;
+        if let Some(block_number) = self.block_number {
+            write!(f, " in block {block_number}")?;
+        }
+        write!(
+            f,
+            ", expected to resolve by {}",
+            self.expected_resolution_time
+        )
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum StrategyError {
     #[error("Server client error: {0}")]
@@ -36,14 +86,32 @@ pub enum StrategyError {
     UserDataDecryptionError(String),
 
     #[error("Pending receives error: {0}")]
-    PendingReceivesError(String),
+    PendingReceivesError(PendingTxInfo),
 
     #[error("Pending tx error: {0}")]
-    PendingTxError(String),
+    PendingTxError(PendingTxInfo),
 
     #[error("Sender proof set not found")]
     SenderProofSetNotFound,
 
+    #[error("Backup encryption error: {0}")]
+    BackupEncryptionError(String),
+
+    #[error("Conflicting backup entries with digest {0}")]
+    BackupConflict(Bytes32),
+
     #[error("Unexpected error: {0}")]
     UnexpectedError(String),
 }
+
+impl StrategyError {
+    /// Details of the pending tx blocking the sync, if that is the cause of the error.
+    pub fn pending_tx_info(&self) -> Option<&PendingTxInfo> {
+        match self {
+            StrategyError::PendingTxError(info) | StrategyError::PendingReceivesError(info) => {
+                Some(info)
+            }
+            _ => None,
+        }
+    }
+}
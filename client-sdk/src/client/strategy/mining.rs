@@ -31,6 +31,23 @@ pub struct Mining {
     pub block: Option<Block>,  // the first block that contains the deposit
     pub maturity: Option<u64>, // maturity unix timestamp
     pub status: MiningStatus,
+    /// When the mining becomes claimable. `None` unless the mining is locking.
+    pub prediction: Option<MiningPrediction>,
+}
+
+/// Prediction of the claim of a locking mining.
+///
+/// It is derived from the same maturity that decides `MiningStatus`, so the mining becomes
+/// `Claimable` exactly when the validity prover reaches a block whose timestamp is at least
+/// `claimable_at`, unless a send tx is made before that.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MiningPrediction {
+    /// Unix timestamp from which the mining is claimable.
+    pub claimable_at: u64,
+    /// Lower bound of the block number at which the claim can be made.
+    pub earliest_block_number: u32,
+    /// Amount recorded in the claim, which is the deposit amount.
+    pub amount: U256,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -81,6 +98,7 @@ pub async fn fetch_mining_info(
         current_time,
         &ProcessStatus::default(),
         deposit_timeout,
+        0, // the mining lock time is far longer than any confirmation depth
     )
     .await?;
 
@@ -107,6 +125,7 @@ pub async fn fetch_mining_info(
             block: None,
             maturity: None,
             status: MiningStatus::Pending,
+            prediction: None,
         })
         .collect::<Vec<_>>();
 
@@ -190,12 +209,18 @@ pub async fn fetch_mining_info(
                 MiningStatus::Claimable(current_block_number)
             }
         };
+        let prediction = (status == MiningStatus::Locking).then(|| MiningPrediction {
+            claimable_at: maturity,
+            earliest_block_number: current_block_number + 1,
+            amount: deposit_data.amount,
+        });
         settled_minings.push(Mining {
             meta: meta.meta,
             deposit_data,
             block: Some(block),
             maturity: Some(maturity),
             status,
+            prediction,
         });
     }
 
@@ -1,3 +1,4 @@
+use hashbrown::HashMap;
 use intmax2_interfaces::{
     api::{
         store_vault_server::interface::StoreVaultClientInterface,
@@ -14,10 +15,13 @@ use itertools::Itertools;
 use intmax2_zkp::{
     circuits::claim::utils::get_mining_deposit_nullifier,
     common::{signature_content::key_set::KeySet, withdrawal::get_withdrawal_nullifier},
-    ethereum_types::{bytes32::Bytes32, u32limb_trait::U32LimbTrait},
+    ethereum_types::{bytes32::Bytes32, u256::U256, u32limb_trait::U32LimbTrait},
 };
 
-use super::{error::StrategyError, mining::Mining};
+use super::{
+    error::{PendingTxInfo, PendingTxKind, StrategyError},
+    mining::Mining,
+};
 use crate::{
     client::strategy::{
         common::fetch_user_data,
@@ -81,6 +85,7 @@ pub async fn determine_sequence(
     liquidity_contract: &LiquidityContract,
     key: KeySet,
     deposit_timeout: u64,
+    deposit_confirmations: u64,
     tx_timeout: u64,
 ) -> Result<(Vec<Action>, Balances, PendingInfo), StrategyError> {
     log::info!("determine_sequence");
@@ -109,10 +114,12 @@ pub async fn determine_sequence(
 
     //  First, if there is a pending tx, return a pending error
     if let Some((meta, _tx_data)) = tx_info.pending.first() {
-        return Err(StrategyError::PendingTxError(format!(
-            "pending tx: {:?}",
-            meta.digest
-        )));
+        return Err(StrategyError::PendingTxError(PendingTxInfo {
+            kind: PendingTxKind::Send,
+            tx_digest: meta.digest,
+            block_number: None,
+            expected_resolution_time: meta.timestamp + tx_timeout,
+        }));
     }
 
     // Then, collect deposit and transfer data
@@ -124,6 +131,7 @@ pub async fn determine_sequence(
         current_time,
         &user_data.deposit_status,
         deposit_timeout,
+        deposit_confirmations,
     )
     .await?;
     let transfer_info = fetch_all_unprocessed_transfer_info(
@@ -196,10 +204,24 @@ pub async fn determine_sequence(
             } else {
                 // To incorporate the tx, you need to incorporate the pending deposit/transfer to solve the balance shortage.
                 // TODO: Processing when the balance shortage is not resolved even if the pending deposit/transfer is incorporated
-                return Err(StrategyError::PendingReceivesError(format!(
-                    "pending receives to proceed tx: {:?}",
-                    tx_meta.meta.digest
-                )));
+                let deposit_resolution_times = deposit_info
+                    .pending
+                    .iter()
+                    .map(|(meta, _)| meta.timestamp + deposit_timeout);
+                let transfer_resolution_times = transfer_info
+                    .pending
+                    .iter()
+                    .map(|(meta, _)| meta.timestamp + tx_timeout);
+                let expected_resolution_time = deposit_resolution_times
+                    .chain(transfer_resolution_times)
+                    .max()
+                    .unwrap_or(current_time);
+                return Err(StrategyError::PendingReceivesError(PendingTxInfo {
+                    kind: PendingTxKind::Receive,
+                    tx_digest: tx_meta.meta.digest,
+                    block_number: Some(tx_meta.block_number),
+                    expected_resolution_time,
+                }));
             }
         }
 
@@ -294,6 +316,148 @@ async fn collect_receives(
     Ok(receives)
 }
 
+/// Balance of a token split by whether it can be spent now.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenBalanceBreakdown {
+    pub token_index: u32,
+    /// Balance in the balance proof, minus the outgoing pending amount
+    pub spendable: U256,
+    /// Deposits and transfers not yet incorporated into the balance proof
+    pub incoming_pending: U256,
+    /// Transfers of sent txs not yet incorporated into the balance proof, including fees
+    pub outgoing_pending: U256,
+    /// The balance proof marks the balance as insufficient
+    pub is_insufficient: bool,
+}
+
+/// Split the balances of the user into spendable, incoming pending and outgoing pending
+/// amounts per token, ordered by token index.
+///
+/// Unlike `determine_sequence`, this does not fail on pending txs, so it can be called at any
+/// time. Receives and txs that are settled but not synced yet count as pending, because they
+/// are not part of the balance proof until the next sync.
+pub async fn determine_balance_breakdown(
+    store_vault_server: &dyn StoreVaultClientInterface,
+    validity_prover: &dyn ValidityProverClientInterface,
+    liquidity_contract: &LiquidityContract,
+    key: KeySet,
+    deposit_timeout: u64,
+    deposit_confirmations: u64,
+    tx_timeout: u64,
+) -> Result<Vec<TokenBalanceBreakdown>, StrategyError> {
+    log::info!("determine_balance_breakdown");
+    let current_time = chrono::Utc::now().timestamp() as u64;
+    let user_data = fetch_user_data(store_vault_server, key).await?;
+
+    let deposit_info = fetch_all_unprocessed_deposit_info(
+        store_vault_server,
+        validity_prover,
+        liquidity_contract,
+        key,
+        current_time,
+        &user_data.deposit_status,
+        deposit_timeout,
+        deposit_confirmations,
+    )
+    .await?;
+    let transfer_info = fetch_all_unprocessed_transfer_info(
+        store_vault_server,
+        validity_prover,
+        key,
+        current_time,
+        &user_data.transfer_status,
+        tx_timeout,
+    )
+    .await?;
+    let tx_info = fetch_all_unprocessed_tx_info(
+        store_vault_server,
+        validity_prover,
+        key,
+        current_time,
+        &user_data.tx_status,
+        tx_timeout,
+    )
+    .await?;
+
+    let mut incoming = Balances(HashMap::new());
+    for deposit_data in deposit_info
+        .settled
+        .iter()
+        .map(|(_, data)| data)
+        .chain(deposit_info.pending.iter().map(|(_, data)| data))
+    {
+        incoming.add_deposit(deposit_data);
+    }
+    for transfer_data in transfer_info
+        .settled
+        .iter()
+        .map(|(_, data)| data)
+        .chain(transfer_info.pending.iter().map(|(_, data)| data))
+    {
+        incoming.add_transfer(transfer_data);
+    }
+
+    let mut outgoing_txs = tx_info
+        .pending
+        .into_iter()
+        .map(|(_, tx_data)| tx_data)
+        .collect_vec();
+    for (tx_meta, tx_data) in tx_info.settled {
+        // failed txs will never be spent
+        let tx_status = get_tx_status(validity_prover, key.pubkey, tx_data.tx_tree_root).await?;
+        if tx_status == TxStatus::Success {
+            outgoing_txs.push(tx_data);
+        } else {
+            log::warn!("tx {} is not success: {}", tx_meta.meta.digest, tx_status);
+        }
+    }
+    let mut outgoing = Balances(HashMap::new());
+    for tx_data in outgoing_txs.iter() {
+        for transfer in tx_data.spent_witness.transfers.iter() {
+            outgoing.add_token(transfer.token_index, transfer.amount);
+        }
+    }
+
+    Ok(combine_balance_breakdown(
+        &user_data.balances(),
+        &incoming,
+        &outgoing,
+    ))
+}
+
+fn combine_balance_breakdown(
+    settled: &Balances,
+    incoming: &Balances,
+    outgoing: &Balances,
+) -> Vec<TokenBalanceBreakdown> {
+    let token_indices = settled
+        .0
+        .keys()
+        .chain(incoming.0.keys())
+        .chain(outgoing.0.keys())
+        .copied()
+        .sorted()
+        .dedup();
+    token_indices
+        .map(|token_index| {
+            let mut spendable = settled.clone();
+            let is_insufficient = settled
+                .0
+                .get(&token_index)
+                .is_some_and(|leaf| leaf.is_insufficient);
+            // saturates at zero if the pending txs exceed the balance
+            spendable.sub_token(token_index, outgoing.get(token_index));
+            TokenBalanceBreakdown {
+                token_index,
+                spendable: spendable.get(token_index),
+                incoming_pending: incoming.get(token_index),
+                outgoing_pending: outgoing.get(token_index),
+                is_insufficient,
+            }
+        })
+        .collect()
+}
+
 /// Determine the sequence of withdrawal tx
 pub async fn determine_withdrawals(
     store_vault_server: &dyn StoreVaultClientInterface,
@@ -410,3 +574,37 @@ pub async fn determine_claims(
 
     Ok(claims)
 }
+
+#[cfg(test)]
+mod tests {
+    use hashbrown::HashMap;
+    use intmax2_interfaces::data::user_data::Balances;
+    use intmax2_zkp::ethereum_types::u256::U256;
+
+    use super::combine_balance_breakdown;
+
+    #[test]
+    fn test_combine_balance_breakdown() {
+        let mut settled = Balances(HashMap::new());
+        settled.add_token(0, 100u32.into());
+        settled.add_token(1, 10u32.into());
+        let mut incoming = Balances(HashMap::new());
+        incoming.add_token(2, 5u32.into());
+        let mut outgoing = Balances(HashMap::new());
+        outgoing.add_token(0, 30u32.into());
+        outgoing.add_token(1, 20u32.into());
+
+        let breakdown = combine_balance_breakdown(&settled, &incoming, &outgoing);
+        assert_eq!(
+            breakdown.iter().map(|b| b.token_index).collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+        assert_eq!(breakdown[0].spendable, U256::from(70u32));
+        assert_eq!(breakdown[0].outgoing_pending, U256::from(30u32));
+        // the pending txs exceed the balance
+        assert_eq!(breakdown[1].spendable, U256::zero());
+        assert!(!breakdown[1].is_insufficient);
+        assert_eq!(breakdown[2].spendable, U256::zero());
+        assert_eq!(breakdown[2].incoming_pending, U256::from(5u32));
+    }
+}
@@ -12,6 +12,8 @@ pub enum TxStatus {
     Pending,
     Success,
     Failed(String),
+    /// Dropped by the block builder after its `valid_until`, before being built into a block
+    Expired,
 }
 
 impl fmt::Display for TxStatus {
@@ -20,6 +22,7 @@ impl fmt::Display for TxStatus {
             TxStatus::Pending => write!(f, "pending"),
             TxStatus::Success => write!(f, "success"),
             TxStatus::Failed(_) => write!(f, "failed"),
+            TxStatus::Expired => write!(f, "expired"),
         }
     }
 }
@@ -37,6 +40,35 @@ pub async fn get_tx_status(
         return Ok(TxStatus::Pending);
     }
     let block_number = block_number.unwrap();
+    tx_status_at_block(validity_prover, sender, block_number).await
+}
+
+/// Get the statuses of multiple txs, aligned by index with `tx_tree_roots`.
+/// Block numbers are resolved in a single batch request to the validity prover.
+pub async fn get_tx_status_batch(
+    validity_prover: &dyn ValidityProverClientInterface,
+    sender: U256,
+    tx_tree_roots: &[Bytes32],
+) -> Result<Vec<TxStatus>, StrategyError> {
+    let block_numbers = validity_prover
+        .get_block_number_by_tx_tree_root_batch(tx_tree_roots)
+        .await?;
+    let mut statuses = Vec::with_capacity(block_numbers.len());
+    for block_number in block_numbers {
+        let status = match block_number {
+            Some(block_number) => tx_status_at_block(validity_prover, sender, block_number).await?,
+            None => TxStatus::Pending,
+        };
+        statuses.push(status);
+    }
+    Ok(statuses)
+}
+
+async fn tx_status_at_block(
+    validity_prover: &dyn ValidityProverClientInterface,
+    sender: U256,
+    block_number: u32,
+) -> Result<TxStatus, StrategyError> {
     let validity_witness = validity_prover.get_validity_witness(block_number).await?;
     let validity_pis = validity_witness.to_validity_pis().map_err(|e| {
         StrategyError::UnexpectedError(format!("failed to convert to validity pis: {e}"))
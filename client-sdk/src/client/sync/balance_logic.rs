@@ -368,9 +368,10 @@ pub async fn update_send_by_receiver(
         .await?;
     let last_block_number = update_witness.get_last_block_number();
     if prev_block_number < last_block_number {
-        return Err(SyncError::InvalidTransferError(format!(
-            "prev_block_number {prev_block_number} is less than last_block_number {last_block_number}"
-        )));
+        return Err(SyncError::SenderLastBlockNumberError {
+            prev_block_number,
+            last_block_number,
+        });
     }
     // prove tx send
     let balance_proof = balance_prover
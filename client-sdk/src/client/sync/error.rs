@@ -5,10 +5,16 @@ use intmax2_interfaces::{
         proof_compression::ProofCompressionError,
     },
 };
-use intmax2_zkp::{circuits::balance::error::BalanceError, ethereum_types::bytes32::Bytes32};
+use intmax2_zkp::{
+    circuits::balance::error::BalanceError,
+    ethereum_types::{bytes32::Bytes32, u256::U256},
+};
 
 use crate::{
-    client::{receive_validation::ReceiveValidationError, strategy::error::StrategyError},
+    client::{
+        receive_validation::ReceiveValidationError,
+        strategy::error::{PendingTxInfo, StrategyError},
+    },
     external_api::contract::error::BlockchainError,
 };
 
@@ -62,6 +68,12 @@ pub enum SyncError {
     #[error("Invalid transfer error: {0}")]
     InvalidTransferError(String),
 
+    #[error("Sender balance proof at block {prev_block_number} is behind the sender's last tx at block {last_block_number}")]
+    SenderLastBlockNumberError {
+        prev_block_number: u32,
+        last_block_number: u32,
+    },
+
     #[error("Block number mismatch balance_proof_block_number: {balance_proof_block_number} != block_number: {block_number}")]
     BalanceProofBlockNumberMismatch {
         balance_proof_block_number: u32,
@@ -71,6 +83,28 @@ pub enum SyncError {
     #[error("Balance proof not found")]
     BalanceProofNotFound,
 
+    #[error(
+        "Insufficient balance for token #{token_index}: required {required}, available {available}"
+    )]
+    InsufficientBalance {
+        token_index: u32,
+        required: U256,
+        available: U256,
+    },
+
     #[error("Balance error: {0}")]
     BalanceError(#[from] BalanceError),
+
+    #[error("Balance prover at {endpoint} is unavailable: {reason}")]
+    BalanceProverUnavailable { endpoint: String, reason: String },
+}
+
+impl SyncError {
+    /// Details of the pending tx blocking the sync, if that is the cause of the error.
+    pub fn pending_tx_info(&self) -> Option<&PendingTxInfo> {
+        match self {
+            SyncError::StrategyError(e) => e.pending_tx_info(),
+            _ => None,
+        }
+    }
 }
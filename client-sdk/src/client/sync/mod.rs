@@ -1,5 +1,8 @@
 pub mod balance_logic;
 pub mod error;
+pub mod progress;
+pub mod receive_retry;
+pub mod resync_progress;
 pub mod sync_balance;
 pub mod sync_claims;
 pub mod sync_withdrawals;
@@ -0,0 +1,83 @@
+use serde::{Deserialize, Serialize};
+
+use crate::client::strategy::strategy::{Action, PendingInfo, ReceiveAction};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SyncActionKind {
+    Deposit,
+    Transfer,
+    Tx,
+}
+
+/// Progress of `Client::sync_with_progress`, emitted after each action is applied.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncEvent {
+    /// 1-indexed position of the applied action
+    pub current: usize,
+    pub total: usize,
+    pub action_kind: SyncActionKind,
+    pub block_number: u32,
+}
+
+/// Work `Client::sync` would do now, returned by `Client::estimate_sync_work`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncWorkEstimate {
+    /// Settled deposits to receive
+    pub num_deposits: usize,
+    /// Settled transfers to receive
+    pub num_transfers: usize,
+    /// Settled txs to incorporate
+    pub num_txs: usize,
+    /// Deposits that are not settled yet, skipped by this sync
+    pub num_pending_deposits: usize,
+    /// Transfers that are not settled yet, skipped by this sync
+    pub num_pending_transfers: usize,
+    /// Rough upper bound of the balance proofs to generate
+    pub estimated_proofs: usize,
+}
+
+impl SyncWorkEstimate {
+    pub fn new(sequence: &[Action], pending_info: &PendingInfo) -> Self {
+        let mut estimate = Self {
+            num_pending_deposits: pending_info.pending_deposit_digests.len(),
+            num_pending_transfers: pending_info.pending_transfer_digests.len(),
+            ..Default::default()
+        };
+        for action in sequence {
+            match action {
+                Action::Receive(receives) => {
+                    if !receives.is_empty() {
+                        // update the balance proof to the block of the receives
+                        estimate.estimated_proofs += 1;
+                    }
+                    for receive in receives {
+                        match receive {
+                            ReceiveAction::Deposit(_, _) => {
+                                estimate.num_deposits += 1;
+                                estimate.estimated_proofs += 1;
+                            }
+                            ReceiveAction::Transfer(_, _) => {
+                                // the sender's balance proof and the receive proof
+                                estimate.num_transfers += 1;
+                                estimate.estimated_proofs += 2;
+                            }
+                        }
+                    }
+                }
+                Action::Tx(_, _) => {
+                    estimate.num_txs += 1;
+                    estimate.estimated_proofs += 1;
+                }
+            }
+        }
+        estimate
+    }
+
+    /// Number of actions, the `total` of the `SyncEvent`s emitted by the sync.
+    pub fn num_actions(&self) -> usize {
+        self.num_deposits + self.num_transfers + self.num_txs
+    }
+}
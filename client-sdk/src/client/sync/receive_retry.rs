@@ -0,0 +1,133 @@
+use intmax2_interfaces::{
+    data::{
+        data_type::DataType,
+        encryption::BlsEncryption,
+        meta_data::MetaDataWithBlockNumber,
+        receive_retry::{DeferredReceive, ReceiveRetryQueue},
+        rw_rights::{RWRights, ReadRights, WriteRights},
+        topic::topic_from_rights,
+    },
+    utils::digest::get_digest,
+};
+use intmax2_zkp::{common::signature_content::key_set::KeySet, ethereum_types::bytes32::Bytes32};
+
+use crate::client::client::Client;
+
+use super::error::SyncError;
+
+pub fn receive_retry_topic() -> String {
+    topic_from_rights(
+        RWRights {
+            read_rights: ReadRights::AuthRead,
+            write_rights: WriteRights::AuthWrite,
+        },
+        "receive_retry_queue",
+    )
+}
+
+/// The retry queue read at the start of a sync with the digest of its saved snapshot
+pub(crate) struct ReceiveRetryMarker {
+    pub queue: ReceiveRetryQueue,
+    pub digest: Option<Bytes32>,
+}
+
+impl Client {
+    /// Transfers and withdrawals of `key` that are no longer retried after repeated failed
+    /// receives.
+    pub async fn get_stuck_receives(&self, key: KeySet) -> Result<Vec<DeferredReceive>, SyncError> {
+        Ok(self.get_receive_retry_marker(key).await?.queue.stuck())
+    }
+
+    pub(crate) async fn get_receive_retry_marker(
+        &self,
+        key: KeySet,
+    ) -> Result<ReceiveRetryMarker, SyncError> {
+        let encrypted = self
+            .store_vault_server
+            .get_snapshot(key, &receive_retry_topic())
+            .await?;
+        let Some(encrypted) = encrypted else {
+            return Ok(ReceiveRetryMarker {
+                queue: ReceiveRetryQueue::default(),
+                digest: None,
+            });
+        };
+        let queue = ReceiveRetryQueue::decrypt(key, Some(key.pubkey), &encrypted).map_err(|e| {
+            SyncError::DecryptionError(format!("failed to decrypt receive retry queue: {e}"))
+        })?;
+        Ok(ReceiveRetryMarker {
+            queue,
+            digest: Some(get_digest(&encrypted)),
+        })
+    }
+
+    async fn save_receive_retry_marker(
+        &self,
+        key: KeySet,
+        marker: &mut ReceiveRetryMarker,
+    ) -> Result<(), SyncError> {
+        let encrypted = marker.queue.encrypt(key.pubkey, Some(key))?;
+        self.store_vault_server
+            .save_snapshot(key, &receive_retry_topic(), marker.digest, &encrypted)
+            .await?;
+        marker.digest = Some(get_digest(&encrypted));
+        Ok(())
+    }
+
+    /// Defers receives that failed because the sender's balance proof was behind, so that the
+    /// next sync fetches them again through the pending digests. A receive that has failed
+    /// `MAX_RECEIVE_ATTEMPTS` times is dropped from the pending digests and reported as stuck.
+    pub(crate) async fn defer_receives(
+        &self,
+        key: KeySet,
+        marker: &mut ReceiveRetryMarker,
+        data_type: DataType,
+        deferred: &[(MetaDataWithBlockNumber, String)],
+    ) -> Result<(), SyncError> {
+        if deferred.is_empty() {
+            return Ok(());
+        }
+        let (mut user_data, prev_digest) = self.get_user_data_and_digest(key).await?;
+        let status = match data_type {
+            DataType::Transfer => &mut user_data.transfer_status,
+            DataType::Withdrawal => &mut user_data.withdrawal_status,
+            _ => {
+                return Err(SyncError::InternalError(format!(
+                    "{data_type} receives cannot be deferred"
+                )))
+            }
+        };
+        for (meta, error) in deferred {
+            let digest = meta.meta.digest;
+            let retried = marker
+                .queue
+                .defer(data_type, digest, meta.block_number, error.clone());
+            status.pending_digests.retain(|d| d != &digest);
+            if retried {
+                log::warn!("Defer {data_type} {digest} to the next sync: {error}");
+                status.pending_digests.push(digest);
+            } else {
+                log::error!("Give up {data_type} {digest} after repeated failures: {error}");
+            }
+        }
+        self.save_user_data(key, prev_digest, &user_data).await?;
+        self.save_receive_retry_marker(key, marker).await
+    }
+
+    /// Removes received or otherwise settled digests from the retry queue.
+    pub(crate) async fn resolve_receives(
+        &self,
+        key: KeySet,
+        marker: &mut ReceiveRetryMarker,
+        digests: &[Bytes32],
+    ) -> Result<(), SyncError> {
+        let mut resolved = false;
+        for digest in digests {
+            resolved |= marker.queue.resolve(*digest);
+        }
+        if resolved {
+            self.save_receive_retry_marker(key, marker).await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,153 @@
+use intmax2_interfaces::{
+    data::{
+        encryption::BlsEncryption,
+        rw_rights::{RWRights, ReadRights, WriteRights},
+        topic::topic_from_rights,
+    },
+    utils::digest::get_digest,
+};
+use intmax2_zkp::{common::signature_content::key_set::KeySet, ethereum_types::bytes32::Bytes32};
+use serde::{Deserialize, Serialize};
+
+use crate::client::client::Client;
+
+use super::{
+    error::SyncError,
+    progress::{SyncActionKind, SyncEvent},
+};
+
+pub fn deep_resync_progress_topic() -> String {
+    topic_from_rights(
+        RWRights {
+            read_rights: ReadRights::AuthRead,
+            write_rights: WriteRights::AuthWrite,
+        },
+        "deep_resync_progress",
+    )
+}
+
+/// Progress of a deep resync, saved to the store vault after each applied action.
+/// `resume_deep_resync` continues from the user data of an uncompleted progress, while a new
+/// deep resync overwrites it with its own.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeepResyncProgress {
+    /// Unix timestamp at which the user data was reset
+    pub started_at: u64,
+    /// Number of actions applied since the reset, across resumes
+    pub num_applied: usize,
+    pub last_block_number: Option<u32>,
+    pub last_action_kind: Option<SyncActionKind>,
+    pub completed: bool,
+}
+
+impl BlsEncryption for DeepResyncProgress {}
+
+impl DeepResyncProgress {
+    pub fn new(started_at: u64) -> Self {
+        Self {
+            started_at,
+            num_applied: 0,
+            last_block_number: None,
+            last_action_kind: None,
+            completed: false,
+        }
+    }
+
+    /// Whether `resume_deep_resync` should continue from the user data instead of resetting it.
+    pub fn is_resumable(&self) -> bool {
+        !self.completed
+    }
+
+    pub fn record(&mut self, event: &SyncEvent) {
+        self.num_applied += 1;
+        self.last_block_number = Some(event.block_number);
+        self.last_action_kind = Some(event.action_kind);
+    }
+}
+
+/// The progress of the running deep resync with the digest of its saved snapshot
+pub(crate) struct DeepResyncMarker {
+    pub progress: DeepResyncProgress,
+    pub digest: Option<Bytes32>,
+}
+
+impl Client {
+    /// The progress of the last deep resync of `key`, if any.
+    pub async fn get_deep_resync_progress(
+        &self,
+        key: KeySet,
+    ) -> Result<Option<DeepResyncProgress>, SyncError> {
+        Ok(self.get_deep_resync_marker(key).await?.map(|m| m.progress))
+    }
+
+    pub(crate) async fn get_deep_resync_marker(
+        &self,
+        key: KeySet,
+    ) -> Result<Option<DeepResyncMarker>, SyncError> {
+        let encrypted = self
+            .store_vault_server
+            .get_snapshot(key, &deep_resync_progress_topic())
+            .await?;
+        let Some(encrypted) = encrypted else {
+            return Ok(None);
+        };
+        let progress =
+            DeepResyncProgress::decrypt(key, Some(key.pubkey), &encrypted).map_err(|e| {
+                SyncError::DecryptionError(format!("failed to decrypt deep resync progress: {e}"))
+            })?;
+        Ok(Some(DeepResyncMarker {
+            progress,
+            digest: Some(get_digest(&encrypted)),
+        }))
+    }
+
+    pub(crate) async fn save_deep_resync_marker(
+        &self,
+        key: KeySet,
+        marker: &mut DeepResyncMarker,
+    ) -> Result<(), SyncError> {
+        let encrypted = marker.progress.encrypt(key.pubkey, Some(key))?;
+        self.store_vault_server
+            .save_snapshot(
+                key,
+                &deep_resync_progress_topic(),
+                marker.digest,
+                &encrypted,
+            )
+            .await?;
+        marker.digest = Some(get_digest(&encrypted));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DeepResyncProgress;
+    use crate::client::sync::progress::{SyncActionKind, SyncEvent};
+
+    #[test]
+    fn test_record_progress() {
+        let mut progress = DeepResyncProgress::new(100);
+        assert!(progress.is_resumable());
+        for (current, action_kind, block_number) in [
+            (1, SyncActionKind::Deposit, 5),
+            (2, SyncActionKind::Tx, 8),
+            // a resumed sync counts its events from 1 again
+            (1, SyncActionKind::Transfer, 12),
+        ] {
+            progress.record(&SyncEvent {
+                current,
+                total: 2,
+                action_kind,
+                block_number,
+            });
+        }
+        assert_eq!(progress.num_applied, 3);
+        assert_eq!(progress.last_block_number, Some(12));
+        assert_eq!(progress.last_action_kind, Some(SyncActionKind::Transfer));
+
+        progress.completed = true;
+        assert!(!progress.is_resumable());
+    }
+}
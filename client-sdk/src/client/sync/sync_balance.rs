@@ -1,3 +1,6 @@
+use std::collections::HashSet;
+
+use futures::{stream, StreamExt as _};
 use intmax2_interfaces::{
     data::{
         data_type::DataType, deposit_data::DepositData, encryption::BlsEncryption as _,
@@ -10,29 +13,46 @@ use intmax2_zkp::{
     circuits::balance::balance_pis::BalancePublicInputs,
     common::signature_content::key_set::KeySet, ethereum_types::bytes32::Bytes32,
 };
+use plonky2::{
+    field::goldilocks_field::GoldilocksField,
+    plonk::{config::PoseidonGoldilocksConfig, proof::ProofWithPublicInputs},
+};
 
 use crate::client::{
     client::Client,
-    strategy::strategy::{determine_sequence, Action, PendingInfo, ReceiveAction},
+    operation::run_operation,
+    strategy::{
+        common::fetch_decrypt_validate_batch,
+        strategy::{determine_sequence, Action, PendingInfo, ReceiveAction},
+    },
     sync::{
         balance_logic::{
             receive_deposit, receive_transfer, update_no_send, update_send_by_receiver,
             update_send_by_sender,
         },
-        utils::{generate_salt, get_balance_proof},
+        progress::{SyncActionKind, SyncEvent, SyncWorkEstimate},
+        receive_retry::ReceiveRetryMarker,
+        resync_progress::{DeepResyncMarker, DeepResyncProgress},
+        utils::{ensure_balance_prover_available, generate_salt, get_balance_proof},
     },
 };
 
 use super::error::SyncError;
 
+type F = GoldilocksField;
+type C = PoseidonGoldilocksConfig;
+const D: usize = 2;
+
 impl Client {
+    /// Get the latest user data, with the receives that are stuck after repeated failures
     pub async fn get_user_data(&self, key: KeySet) -> Result<UserData, SyncError> {
-        let (user_data, _) = self.get_user_data_and_digest(key).await?;
+        let (mut user_data, _) = self.get_user_data_and_digest(key).await?;
+        user_data.stuck_receives = self.get_stuck_receives(key).await?;
         Ok(user_data)
     }
 
     /// Get the latest user data from the data store server
-    pub(super) async fn get_user_data_and_digest(
+    pub(crate) async fn get_user_data_and_digest(
         &self,
         key: KeySet,
     ) -> Result<(UserData, Option<Bytes32>), SyncError> {
@@ -51,13 +71,17 @@ impl Client {
         Ok((user_data, digest))
     }
 
-    pub(super) async fn save_user_data(
+    pub(crate) async fn save_user_data(
         &self,
         key: KeySet,
         prev_digest: Option<Bytes32>,
         user_data: &UserData,
     ) -> Result<(), SyncError> {
-        let encrypted_data = user_data.encrypt(key.pubkey, Some(key))?;
+        let encrypted_data = user_data.encrypt_with_compression(
+            key.pubkey,
+            Some(key),
+            self.config.compress_payloads,
+        )?;
         self.store_vault_server
             .save_snapshot(
                 key,
@@ -69,8 +93,54 @@ impl Client {
         Ok(())
     }
 
+    /// Estimate the work of `sync` from the current sync sequence, e.g. to show progress.
+    /// Neither writes the user data nor generates proofs. Fails like `sync` if a tx is pending.
+    pub async fn estimate_sync_work(&self, key: KeySet) -> Result<SyncWorkEstimate, SyncError> {
+        let (sequence, _, pending_info) = determine_sequence(
+            self.store_vault_server.as_ref(),
+            self.validity_prover.as_ref(),
+            &self.rollup_contract,
+            &self.liquidity_contract,
+            key,
+            self.config.deposit_timeout,
+            self.config.deposit_confirmations,
+            self.config.tx_timeout,
+        )
+        .await?;
+        Ok(SyncWorkEstimate::new(&sequence, &pending_info))
+    }
+
     /// Sync the client's balance proof with the latest block
     pub async fn sync(&self, key: KeySet) -> Result<(), SyncError> {
+        self.sync_with_progress(key, &|_| {}).await
+    }
+
+    /// Same as `sync`, but calls `on_progress` each time an action is applied.
+    pub async fn sync_with_progress(
+        &self,
+        key: KeySet,
+        on_progress: &dyn Fn(SyncEvent),
+    ) -> Result<(), SyncError> {
+        run_operation(
+            "sync",
+            self.sync_with_progress_inner(key, on_progress, None),
+        )
+        .await
+    }
+
+    /// Applies the sync sequence. If `deep_resync` is given, its progress is saved after each
+    /// applied action.
+    async fn sync_with_progress_inner(
+        &self,
+        key: KeySet,
+        on_progress: &dyn Fn(SyncEvent),
+        mut deep_resync: Option<&mut DeepResyncMarker>,
+    ) -> Result<(), SyncError> {
+        ensure_balance_prover_available(
+            self.balance_prover.as_ref(),
+            self.config.balance_prover_warmup_retries,
+        )
+        .await?;
         let (sequence, _, pending_info) = determine_sequence(
             self.store_vault_server.as_ref(),
             self.validity_prover.as_ref(),
@@ -78,11 +148,38 @@ impl Client {
             &self.liquidity_contract,
             key,
             self.config.deposit_timeout,
+            self.config.deposit_confirmations,
             self.config.tx_timeout,
         )
         .await?;
-        // replaces pending receives with the new pending info
-        self.update_pending_receives(key, pending_info).await?;
+        // replaces pending receives with the new pending info and the deferred transfers
+        let mut retries = self.get_receive_retry_marker(key).await?;
+        self.update_pending_receives(
+            key,
+            pending_info,
+            retries.queue.retry_digests(DataType::Transfer),
+        )
+        .await?;
+
+        let total = sequence
+            .iter()
+            .map(|action| match action {
+                Action::Receive(receives) => receives.len(),
+                Action::Tx(_, _) => 1,
+            })
+            .sum();
+        let mut current = 0;
+        let mut emit = |action_kind: SyncActionKind, block_number: u32| {
+            current += 1;
+            let event = SyncEvent {
+                current,
+                total,
+                action_kind,
+                block_number,
+            };
+            on_progress(event.clone());
+            event
+        };
 
         for action in sequence {
             match action {
@@ -96,26 +193,73 @@ impl Client {
                             .unwrap(); // safe to unwrap because receives is not empty
                         self.update_no_send(key, largest_block_number).await?;
 
-                        for receive in receives {
+                        // Sender balance proofs do not depend on the receiver's state, so they
+                        // are generated concurrently. The receives themselves are applied one by
+                        // one in the original order to keep the private commitment chain intact.
+                        let sender_balance_proofs =
+                            self.generate_sender_balance_proofs(key, &receives).await?;
+                        for (receive, sender_balance_proof) in
+                            receives.into_iter().zip(sender_balance_proofs)
+                        {
                             match receive {
                                 ReceiveAction::Deposit(meta, data) => {
+                                    let block_number = meta.block_number;
                                     self.sync_deposit(key, meta, &data).await?;
+                                    let event = emit(SyncActionKind::Deposit, block_number);
+                                    self.record_deep_resync(
+                                        key,
+                                        deep_resync.as_deref_mut(),
+                                        &event,
+                                    )
+                                    .await?;
                                 }
                                 ReceiveAction::Transfer(meta, data) => {
-                                    self.sync_transfer(key, meta, &data).await?;
+                                    let block_number = meta.block_number;
+                                    self.sync_transfer(
+                                        key,
+                                        meta,
+                                        &data,
+                                        sender_balance_proof,
+                                        &mut retries,
+                                    )
+                                    .await?;
+                                    let event = emit(SyncActionKind::Transfer, block_number);
+                                    self.record_deep_resync(
+                                        key,
+                                        deep_resync.as_deref_mut(),
+                                        &event,
+                                    )
+                                    .await?;
                                 }
                             }
                         }
                     }
                 }
                 Action::Tx(meta, tx_data) => {
+                    let block_number = meta.block_number;
                     self.sync_tx(key, meta, &tx_data).await?;
+                    let event = emit(SyncActionKind::Tx, block_number);
+                    self.record_deep_resync(key, deep_resync.as_deref_mut(), &event)
+                        .await?;
                 }
             }
         }
         Ok(())
     }
 
+    async fn record_deep_resync(
+        &self,
+        key: KeySet,
+        deep_resync: Option<&mut DeepResyncMarker>,
+        event: &SyncEvent,
+    ) -> Result<(), SyncError> {
+        if let Some(marker) = deep_resync {
+            marker.progress.record(event);
+            self.save_deep_resync_marker(key, marker).await?;
+        }
+        Ok(())
+    }
+
     // sync deposit without updating the timestamp
     async fn sync_deposit(
         &self,
@@ -168,12 +312,62 @@ impl Client {
         Ok(())
     }
 
-    // sync deposit without updating the timestamp
+    /// Generates the sender balance proofs of the transfers in `receives` with at most
+    /// `receive_concurrency` requests in flight. The result is aligned with `receives`
+    /// and is `None` for deposits, and for transfers that `sync_transfer` ignores because their
+    /// nullifier is already in the nullifier tree or taken by an earlier transfer of `receives`.
+    async fn generate_sender_balance_proofs(
+        &self,
+        key: KeySet,
+        receives: &[ReceiveAction],
+    ) -> Result<Vec<Option<Result<ProofWithPublicInputs<F, C, D>, SyncError>>>, SyncError> {
+        let user_data = self.get_user_data(key).await?;
+        let mut nullifiers: HashSet<Bytes32> = user_data
+            .full_private_state
+            .nullifier_tree
+            .nullifiers()
+            .into_iter()
+            .collect();
+        let needs_proof = receives
+            .iter()
+            .map(|receive| match receive {
+                ReceiveAction::Deposit(_, _) => false,
+                ReceiveAction::Transfer(_, transfer_data) => {
+                    nullifiers.insert(transfer_data.transfer.nullifier())
+                }
+            })
+            .collect::<Vec<_>>();
+        let proofs = stream::iter(receives.iter().zip(needs_proof))
+            .map(|(receive, needs_proof)| async move {
+                match receive {
+                    ReceiveAction::Transfer(meta, transfer_data) if needs_proof => Some(
+                        update_send_by_receiver(
+                            self.validity_prover.as_ref(),
+                            self.balance_prover.as_ref(),
+                            key,
+                            transfer_data.sender,
+                            meta.block_number,
+                            transfer_data,
+                        )
+                        .await,
+                    ),
+                    _ => None,
+                }
+            })
+            .buffered(self.config.receive_concurrency.max(1))
+            .collect()
+            .await;
+        Ok(proofs)
+    }
+
+    // sync transfer without updating the timestamp
     async fn sync_transfer(
         &self,
         key: KeySet,
         meta: MetaDataWithBlockNumber,
         transfer_data: &TransferData,
+        sender_balance_proof: Option<Result<ProofWithPublicInputs<F, C, D>, SyncError>>,
+        retries: &mut ReceiveRetryMarker,
     ) -> Result<(), SyncError> {
         log::info!("sync_transfer: {meta:?}");
         let (mut user_data, prev_digest) = self.get_user_data_and_digest(key).await?;
@@ -190,23 +384,19 @@ impl Client {
                 meta.meta.digest,
                 nullifier
             );
-            return Ok(());
+            return self
+                .resolve_receives(key, retries, &[meta.meta.digest])
+                .await;
         }
 
         // user's balance proof before applying the tx
         let prev_balance_proof = get_balance_proof(&user_data)?;
 
-        // sender balance proof after applying the tx
-        let new_sender_balance_proof = match update_send_by_receiver(
-            self.validity_prover.as_ref(),
-            self.balance_prover.as_ref(),
-            key,
-            transfer_data.sender,
-            meta.block_number,
-            transfer_data,
-        )
-        .await
-        {
+        // sender balance proof after applying the tx. It is only skipped for known nullifiers.
+        let sender_balance_proof = sender_balance_proof.ok_or_else(|| {
+            SyncError::InternalError("sender balance proof was not generated".to_string())
+        })?;
+        let new_sender_balance_proof = match sender_balance_proof {
             Ok(proof) => proof,
             Err(SyncError::InvalidTransferError(e)) => {
                 log::error!(
@@ -214,7 +404,15 @@ impl Client {
                     meta.meta.digest,
                     e
                 );
-                return Ok(());
+                return self
+                    .resolve_receives(key, retries, &[meta.meta.digest])
+                    .await;
+            }
+            Err(e @ SyncError::SenderLastBlockNumberError { .. }) => {
+                // the sender may not have synced yet, so retry on the next sync
+                return self
+                    .defer_receives(key, retries, DataType::Transfer, &[(meta, e.to_string())])
+                    .await;
             }
             Err(e) => return Err(e),
         };
@@ -241,8 +439,10 @@ impl Client {
         // update user data
         let balance_proof = CompressedBalanceProof::new(&new_balance_proof)?;
         user_data.balance_proof = Some(balance_proof);
+        let digest = meta.meta.digest;
         user_data.transfer_status.process(meta.meta);
         self.save_user_data(key, prev_digest, &user_data).await?;
+        self.resolve_receives(key, retries, &[digest]).await?;
 
         Ok(())
     }
@@ -342,8 +542,14 @@ impl Client {
     async fn update_pending_receives(
         &self,
         key: KeySet,
-        pending_info: PendingInfo,
+        mut pending_info: PendingInfo,
+        retry_transfer_digests: Vec<Bytes32>,
     ) -> Result<(), SyncError> {
+        for digest in retry_transfer_digests {
+            if !pending_info.pending_transfer_digests.contains(&digest) {
+                pending_info.pending_transfer_digests.push(digest);
+            }
+        }
         if pending_info.pending_deposit_digests.is_empty()
             && pending_info.pending_transfer_digests.is_empty()
         {
@@ -359,17 +565,170 @@ impl Client {
 
     /// Reset user data and resync. `is_deep` is true if the user wants to reset completely.
     /// Otherwise, only the last processed meta data will be reset.
+    ///
+    /// A deep resync saves its progress to the store vault after each applied action. If it is
+    /// interrupted, `resume_deep_resync` continues it from the user data saved so far, which ends
+    /// in the same balance as an uninterrupted run. A new deep resync always resets the user data
+    /// and discards the progress of an interrupted one.
     pub async fn resync(&self, key: KeySet, is_deep: bool) -> Result<(), SyncError> {
+        self.resync_with_progress(key, is_deep, &|_| {}).await
+    }
+
+    /// Same as `resync`, but calls `on_progress` each time an action is applied.
+    pub async fn resync_with_progress(
+        &self,
+        key: KeySet,
+        is_deep: bool,
+        on_progress: &dyn Fn(SyncEvent),
+    ) -> Result<(), SyncError> {
+        if is_deep {
+            return run_operation("resync", self.deep_resync(key, false, on_progress)).await;
+        }
         let (mut user_data, prev_digest) = self.get_user_data_and_digest(key).await?;
+        user_data.deposit_status.last_processed_meta_data = None;
+        user_data.transfer_status.last_processed_meta_data = None;
+        user_data.withdrawal_status.last_processed_meta_data = None;
+        self.save_user_data(key, prev_digest, &user_data).await?;
+
+        self.sync_with_progress(key, on_progress).await
+    }
+
+    /// Continue the deep resync of `key` that was interrupted, or start a new one if there is
+    /// none.
+    pub async fn resume_deep_resync(&self, key: KeySet) -> Result<(), SyncError> {
+        self.resume_deep_resync_with_progress(key, &|_| {}).await
+    }
+
+    /// Same as `resume_deep_resync`, but calls `on_progress` each time an action is applied.
+    pub async fn resume_deep_resync_with_progress(
+        &self,
+        key: KeySet,
+        on_progress: &dyn Fn(SyncEvent),
+    ) -> Result<(), SyncError> {
+        run_operation("resync", self.deep_resync(key, true, on_progress)).await
+    }
 
+    async fn deep_resync(
+        &self,
+        key: KeySet,
+        resume: bool,
+        on_progress: &dyn Fn(SyncEvent),
+    ) -> Result<(), SyncError> {
+        let marker = self.get_deep_resync_marker(key).await?;
+        let mut marker = match marker {
+            Some(marker) if resume && marker.progress.is_resumable() => {
+                log::info!(
+                    "resuming the deep resync started at {} after {} actions, last block {:?}",
+                    marker.progress.started_at,
+                    marker.progress.num_applied,
+                    marker.progress.last_block_number
+                );
+                marker
+            }
+            marker => {
+                if let Some(marker) = marker.as_ref().filter(|m| m.progress.is_resumable()) {
+                    log::info!(
+                        "discarding the interrupted deep resync started at {}",
+                        marker.progress.started_at
+                    );
+                }
+                let (_, prev_digest) = self.get_user_data_and_digest(key).await?;
+                self.save_user_data(key, prev_digest, &UserData::new(key.pubkey))
+                    .await?;
+                // saved after the reset, so that an interruption in between starts over
+                let mut marker = DeepResyncMarker {
+                    progress: DeepResyncProgress::new(chrono::Utc::now().timestamp() as u64),
+                    digest: marker.and_then(|m| m.digest),
+                };
+                self.save_deep_resync_marker(key, &mut marker).await?;
+                marker
+            }
+        };
+
+        self.sync_with_progress_inner(key, on_progress, Some(&mut marker))
+            .await?;
+
+        marker.progress.completed = true;
+        self.save_deep_resync_marker(key, &mut marker).await
+    }
+
+    /// Resync only the receives of `token_index`.
+    ///
+    /// The balance proof commits to the whole asset tree and nullifier tree, so the proof state
+    /// of a single token cannot be rebuilt in isolation. Instead, the processed deposits and
+    /// transfers of `token_index` are re-fetched and re-validated against the nullifier tree, and
+    /// the ones that are marked as processed but were never applied are received again by the
+    /// following sync. The other tokens and the sent txs are left untouched.
+    ///
+    /// If `is_deep` is true, this falls back to `resync(key, true)`, since rebuilding the token
+    /// from scratch requires rebuilding every other token as well.
+    pub async fn resync_token(
+        &self,
+        key: KeySet,
+        token_index: u32,
+        is_deep: bool,
+    ) -> Result<(), SyncError> {
         if is_deep {
-            user_data = UserData::new(key.pubkey);
-        } else {
-            user_data.deposit_status.last_processed_meta_data = None;
-            user_data.transfer_status.last_processed_meta_data = None;
-            user_data.withdrawal_status.last_processed_meta_data = None;
+            log::warn!(
+                "deep resync of token {token_index} requires a full deep resync of the balance proof"
+            );
+            return self.resync(key, true).await;
+        }
+        let (mut user_data, prev_digest) = self.get_user_data_and_digest(key).await?;
+        let nullifiers = user_data.full_private_state.nullifier_tree.nullifiers();
+
+        let deposits = fetch_decrypt_validate_batch::<DepositData>(
+            self.store_vault_server.as_ref(),
+            key,
+            DataType::Deposit,
+            &user_data.deposit_status.processed_digests,
+        )
+        .await?;
+        let unapplied_deposits = deposits
+            .iter()
+            .filter(|(_, data)| data.token_index == Some(token_index))
+            .filter(|(_, data)| {
+                // safe to unwrap because the token index is set
+                let nullifier: Bytes32 = data.deposit().unwrap().poseidon_hash().into();
+                !nullifiers.contains(&nullifier)
+            })
+            .map(|(meta, _)| meta.digest)
+            .collect::<Vec<_>>();
+
+        let transfers = fetch_decrypt_validate_batch::<TransferData>(
+            self.store_vault_server.as_ref(),
+            key,
+            DataType::Transfer,
+            &user_data.transfer_status.processed_digests,
+        )
+        .await?;
+        let unapplied_transfers = transfers
+            .iter()
+            .filter(|(_, data)| data.transfer.token_index == token_index)
+            .filter(|(_, data)| !nullifiers.contains(&data.transfer.nullifier()))
+            .map(|(meta, _)| meta.digest)
+            .collect::<Vec<_>>();
+
+        log::info!(
+            "resync_token {token_index}: {} deposits and {} transfers to receive again",
+            unapplied_deposits.len(),
+            unapplied_transfers.len()
+        );
+        if unapplied_deposits.is_empty() && unapplied_transfers.is_empty() {
+            return self.sync(key).await;
         }
 
+        // the cursors are reset so that the unmarked receives are fetched again
+        user_data
+            .deposit_status
+            .processed_digests
+            .retain(|digest| !unapplied_deposits.contains(digest));
+        user_data.deposit_status.last_processed_meta_data = None;
+        user_data
+            .transfer_status
+            .processed_digests
+            .retain(|digest| !unapplied_transfers.contains(digest));
+        user_data.transfer_status.last_processed_meta_data = None;
         self.save_user_data(key, prev_digest, &user_data).await?;
 
         self.sync(key).await
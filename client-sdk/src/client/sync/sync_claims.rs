@@ -13,12 +13,16 @@ use intmax2_zkp::{
 use crate::client::{
     client::Client,
     fee_payment::{consume_payment, select_unused_fees, FeeType},
+    operation::run_operation,
     strategy::{
         mining::MiningStatus, strategy::determine_claims, utils::wait_till_validity_prover_synced,
     },
 };
 
-use super::{error::SyncError, utils::quote_withdrawal_claim_fee};
+use super::{
+    error::SyncError,
+    utils::{ensure_balance_prover_available, quote_withdrawal_claim_fee},
+};
 
 impl Client {
     /// Sync the client's withdrawals and relays to the withdrawal server
@@ -29,6 +33,25 @@ impl Client {
         fee_info: &ClaimFeeInfo,
         fee_token_index: u32,
     ) -> Result<(), SyncError> {
+        run_operation(
+            "sync_claims",
+            self.sync_claims_inner(key, recipient, fee_info, fee_token_index),
+        )
+        .await
+    }
+
+    async fn sync_claims_inner(
+        &self,
+        key: KeySet,
+        recipient: Address,
+        fee_info: &ClaimFeeInfo,
+        fee_token_index: u32,
+    ) -> Result<(), SyncError> {
+        ensure_balance_prover_available(
+            self.balance_prover.as_ref(),
+            self.config.balance_prover_warmup_retries,
+        )
+        .await?;
         let fee = quote_withdrawal_claim_fee(Some(fee_token_index), fee_info.fee.clone())?;
         if fee.is_some() && fee_info.beneficiary.is_none() {
             return Err(SyncError::FeeError("fee beneficiary is needed".to_string()));
@@ -1,9 +1,9 @@
+use futures::{stream, StreamExt as _};
 use intmax2_interfaces::{
-    api::{
-        block_builder::interface::Fee,
-        withdrawal_server::interface::{FeeResult, WithdrawalFeeInfo},
+    api::withdrawal_server::interface::{
+        FeeResult, WithdrawalFeeInfo, WithdrawalRequestEntry, MAX_WITHDRAWAL_BATCH_SIZE,
     },
-    data::{meta_data::MetaDataWithBlockNumber, transfer_data::TransferData},
+    data::{data_type::DataType, meta_data::MetaDataWithBlockNumber, transfer_data::TransferData},
 };
 use intmax2_zkp::{
     common::{
@@ -12,16 +12,29 @@ use intmax2_zkp::{
     },
     ethereum_types::{bytes32::Bytes32, u256::U256},
 };
+use plonky2::{
+    field::goldilocks_field::GoldilocksField,
+    plonk::{config::PoseidonGoldilocksConfig, proof::ProofWithPublicInputs},
+};
 
 use crate::client::{
     client::Client,
-    fee_payment::{consume_payment, select_unused_fees, FeeType},
+    fee_payment::{consume_payment, select_unused_fees_excluding, FeeType},
+    operation::run_operation,
     strategy::strategy::determine_withdrawals,
-    sync::{balance_logic::update_send_by_receiver, utils::quote_withdrawal_claim_fee},
+    sync::{
+        balance_logic::update_send_by_receiver,
+        receive_retry::ReceiveRetryMarker,
+        utils::{ensure_balance_prover_available, quote_withdrawal_claim_fee},
+    },
 };
 
 use super::error::SyncError;
 
+type F = GoldilocksField;
+type C = PoseidonGoldilocksConfig;
+const D: usize = 2;
+
 impl Client {
     /// Sync the client's withdrawals and relays to the withdrawal server
     pub async fn sync_withdrawals(
@@ -30,6 +43,24 @@ impl Client {
         withdrawal_fee: &WithdrawalFeeInfo,
         fee_token_index: u32,
     ) -> Result<(), SyncError> {
+        run_operation(
+            "sync_withdrawals",
+            self.sync_withdrawals_inner(key, withdrawal_fee, fee_token_index),
+        )
+        .await
+    }
+
+    async fn sync_withdrawals_inner(
+        &self,
+        key: KeySet,
+        withdrawal_fee: &WithdrawalFeeInfo,
+        fee_token_index: u32,
+    ) -> Result<(), SyncError> {
+        ensure_balance_prover_available(
+            self.balance_prover.as_ref(),
+            self.config.balance_prover_warmup_retries,
+        )
+        .await?;
         if (withdrawal_fee.direct_withdrawal_fee.is_some()
             || withdrawal_fee.claimable_withdrawal_fee.is_some())
             && withdrawal_fee.beneficiary.is_none()
@@ -46,33 +77,208 @@ impl Client {
             self.config.tx_timeout,
         )
         .await?;
-        self.update_pending_withdrawals(key, pending).await?;
-        for (meta, data) in withdrawals {
-            self.sync_withdrawal(
+        let mut retries = self.get_receive_retry_marker(key).await?;
+        self.update_pending_withdrawals(
+            key,
+            pending,
+            retries.queue.retry_digests(DataType::Withdrawal),
+        )
+        .await?;
+        if withdrawals.is_empty() {
+            return Ok(());
+        }
+        let direct_withdrawal_indices = self
+            .withdrawal_contract
+            .get_direct_withdrawal_token_indices()
+            .await?;
+        for batch in withdrawals.chunks(MAX_WITHDRAWAL_BATCH_SIZE) {
+            self.sync_withdrawal_batch(
                 key,
-                meta,
-                &data,
+                batch,
+                &mut retries,
+                &direct_withdrawal_indices,
                 fee_beneficiary,
                 fee_token_index,
-                withdrawal_fee.direct_withdrawal_fee.clone(),
-                withdrawal_fee.claimable_withdrawal_fee.clone(),
+                withdrawal_fee,
             )
             .await?;
         }
         Ok(())
     }
 
+    /// Proves the withdrawals of `batch` concurrently, then requests them from the withdrawal
+    /// server in a single call.
+    ///
+    /// Only the withdrawals before the first failure are marked as processed, since the user data
+    /// keeps the last processed withdrawal as a cursor. Withdrawals accepted by the server after
+    /// a rejected one are skipped by the next sync as already requested. Withdrawals whose sender
+    /// balance proof is behind are deferred to the next sync.
     #[allow(clippy::too_many_arguments)]
-    async fn sync_withdrawal(
+    async fn sync_withdrawal_batch(
         &self,
         key: KeySet,
-        meta: MetaDataWithBlockNumber,
-        withdrawal_data: &TransferData,
+        batch: &[(MetaDataWithBlockNumber, TransferData)],
+        retries: &mut ReceiveRetryMarker,
+        direct_withdrawal_indices: &[u32],
         fee_beneficiary: Option<U256>,
         fee_token_index: u32,
-        direct_withdrawal_fee: Option<Vec<Fee>>,
-        claimable_withdrawal_fee: Option<Vec<Fee>>,
+        withdrawal_fee: &WithdrawalFeeInfo,
     ) -> Result<(), SyncError> {
+        let proofs: Vec<_> = stream::iter(batch)
+            .map(|(meta, withdrawal_data)| self.prove_withdrawal(key, meta, withdrawal_data))
+            .buffered(self.config.receive_concurrency.max(1))
+            .collect()
+            .await;
+
+        let mut first_error = None;
+        // (meta, request, collected fees)
+        let mut requests = Vec::new();
+        let mut selected_fee_digests = Vec::new();
+        let mut deferred = Vec::new();
+        let mut ignored = Vec::new();
+        for ((meta, withdrawal_data), proof) in batch.iter().zip(proofs) {
+            let single_withdrawal_proof = match proof {
+                Ok(Some(proof)) => proof,
+                // ignored because of an invalid transfer
+                Ok(None) => {
+                    ignored.push(meta.meta.digest);
+                    continue;
+                }
+                Err(e @ SyncError::SenderLastBlockNumberError { .. }) => {
+                    deferred.push((meta.clone(), e.to_string()));
+                    continue;
+                }
+                Err(e) => {
+                    first_error = Some(e);
+                    break;
+                }
+            };
+            let fee = if direct_withdrawal_indices.contains(&withdrawal_data.transfer.token_index) {
+                quote_withdrawal_claim_fee(
+                    Some(fee_token_index),
+                    withdrawal_fee.direct_withdrawal_fee.clone(),
+                )?
+            } else {
+                quote_withdrawal_claim_fee(
+                    Some(fee_token_index),
+                    withdrawal_fee.claimable_withdrawal_fee.clone(),
+                )?
+            };
+            let collected_fees = match &fee {
+                Some(fee) => {
+                    let fee_beneficiary = fee_beneficiary.unwrap(); // already validated
+                    match select_unused_fees_excluding(
+                        self.store_vault_server.as_ref(),
+                        self.validity_prover.as_ref(),
+                        key,
+                        fee_beneficiary,
+                        fee.clone(),
+                        FeeType::Withdrawal,
+                        self.config.tx_timeout,
+                        &selected_fee_digests,
+                    )
+                    .await
+                    {
+                        Ok(collected_fees) => collected_fees,
+                        Err(e) => {
+                            first_error = Some(e);
+                            break;
+                        }
+                    }
+                }
+                None => vec![],
+            };
+            let fee_transfer_digests = collected_fees
+                .iter()
+                .map(|fee| fee.meta.digest)
+                .collect::<Vec<_>>();
+            selected_fee_digests.extend(fee_transfer_digests.iter().copied());
+            let request = WithdrawalRequestEntry {
+                single_withdrawal_proof,
+                fee_token_index: Some(fee_token_index),
+                fee_transfer_digests,
+            };
+            requests.push((meta, request, collected_fees));
+        }
+        self.defer_receives(key, retries, DataType::Withdrawal, &deferred)
+            .await?;
+        self.resolve_receives(key, retries, &ignored).await?;
+        if requests.is_empty() {
+            return first_error.map_or(Ok(()), Err);
+        }
+
+        // send withdrawal requests
+        let entries = requests
+            .iter()
+            .map(|(_, request, _)| request.clone())
+            .collect::<Vec<_>>();
+        let fee_results = self
+            .withdrawal_server
+            .request_withdrawal_batch(key, &entries)
+            .await?;
+
+        let mut processed = Vec::new();
+        let mut is_prefix = true;
+        for ((meta, _, collected_fees), fee_result) in requests.iter().zip(fee_results) {
+            let error = match fee_result {
+                FeeResult::Success => {
+                    // consume fees
+                    for used_fee in collected_fees {
+                        consume_payment(
+                            self.store_vault_server.as_ref(),
+                            key,
+                            used_fee,
+                            "used for withdrawal fee",
+                        )
+                        .await?;
+                    }
+                    if is_prefix {
+                        processed.push(meta.meta.clone());
+                    }
+                    continue;
+                }
+                FeeResult::Insufficient => {
+                    SyncError::FeeError("insufficient fee at the request".to_string())
+                }
+                FeeResult::TokenIndexMismatch => {
+                    SyncError::FeeError("token index mismatch at the request".to_string())
+                }
+                _ => {
+                    let reason = format!("fee error at the request: {fee_result:?}");
+                    for used_fee in collected_fees {
+                        consume_payment(self.store_vault_server.as_ref(), key, used_fee, &reason)
+                            .await?;
+                    }
+                    SyncError::FeeError(format!("invalid fee at the request: {fee_result:?}"))
+                }
+            };
+            is_prefix = false;
+            first_error.get_or_insert(error);
+        }
+
+        // update user data
+        if !processed.is_empty() {
+            let (mut user_data, prev_digest) = self.get_user_data_and_digest(key).await?;
+            let digests = processed.iter().map(|meta| meta.digest).collect::<Vec<_>>();
+            for meta in processed {
+                user_data.withdrawal_status.process(meta);
+            }
+            // save user data
+            self.save_user_data(key, prev_digest, &user_data).await?;
+            self.resolve_receives(key, retries, &digests).await?;
+        }
+
+        first_error.map_or(Ok(()), Err)
+    }
+
+    /// Proves a single withdrawal. Returns `None` if the withdrawal is ignored because of an
+    /// invalid transfer.
+    async fn prove_withdrawal(
+        &self,
+        key: KeySet,
+        meta: &MetaDataWithBlockNumber,
+        withdrawal_data: &TransferData,
+    ) -> Result<Option<ProofWithPublicInputs<F, C, D>>, SyncError> {
         log::info!("sync_withdrawal: {meta:?}");
         // sender balance proof after applying the tx
         let balance_proof = match update_send_by_receiver(
@@ -92,7 +298,7 @@ impl Client {
                     meta.meta.digest,
                     e
                 );
-                return Ok(());
+                return Ok(None);
             }
             Err(e) => return Err(e),
         };
@@ -110,98 +316,20 @@ impl Client {
             .balance_prover
             .prove_single_withdrawal(key, &withdrawal_witness)
             .await?;
-
-        let direct_withdrawal_indices = self
-            .withdrawal_contract
-            .get_direct_withdrawal_token_indices()
-            .await?;
-        let fee = if direct_withdrawal_indices.contains(&withdrawal_data.transfer.token_index) {
-            quote_withdrawal_claim_fee(Some(fee_token_index), direct_withdrawal_fee.clone())?
-        } else {
-            quote_withdrawal_claim_fee(Some(fee_token_index), claimable_withdrawal_fee.clone())?
-        };
-
-        let collected_fees = match &fee {
-            Some(fee) => {
-                let fee_beneficiary = fee_beneficiary.unwrap(); // already validated
-                select_unused_fees(
-                    self.store_vault_server.as_ref(),
-                    self.validity_prover.as_ref(),
-                    key,
-                    fee_beneficiary,
-                    fee.clone(),
-                    FeeType::Withdrawal,
-                    self.config.tx_timeout,
-                )
-                .await?
-            }
-            None => vec![],
-        };
-        let fee_transfer_digests = collected_fees
-            .iter()
-            .map(|fee| fee.meta.digest)
-            .collect::<Vec<_>>();
-
-        // send withdrawal request
-        let fee_result = self
-            .withdrawal_server
-            .request_withdrawal(
-                key,
-                &single_withdrawal_proof,
-                Some(fee_token_index),
-                &fee_transfer_digests,
-            )
-            .await?;
-        match fee_result {
-            FeeResult::Success => {}
-            FeeResult::Insufficient => {
-                return Err(SyncError::FeeError(
-                    "insufficient fee at the request".to_string(),
-                ))
-            }
-            FeeResult::TokenIndexMismatch => {
-                return Err(SyncError::FeeError(
-                    "token index mismatch at the request".to_string(),
-                ))
-            }
-            _ => {
-                let reason = format!("fee error at the request: {fee_result:?}");
-                for used_fee in &collected_fees {
-                    consume_payment(self.store_vault_server.as_ref(), key, used_fee, &reason)
-                        .await?;
-                }
-                return Err(SyncError::FeeError(format!(
-                    "invalid fee at the request: {fee_result:?}"
-                )));
-            }
-        }
-
-        // consume fees
-        for used_fee in &collected_fees {
-            consume_payment(
-                self.store_vault_server.as_ref(),
-                key,
-                used_fee,
-                "used for withdrawal fee",
-            )
-            .await?;
-        }
-
-        // update user data
-        let (mut user_data, prev_digest) = self.get_user_data_and_digest(key).await?;
-        user_data.withdrawal_status.process(meta.meta);
-
-        // save user data
-        self.save_user_data(key, prev_digest, &user_data).await?;
-
-        Ok(())
+        Ok(Some(single_withdrawal_proof))
     }
 
     async fn update_pending_withdrawals(
         &self,
         key: KeySet,
-        pending_withdrawal_digests: Vec<Bytes32>,
+        mut pending_withdrawal_digests: Vec<Bytes32>,
+        retry_withdrawal_digests: Vec<Bytes32>,
     ) -> Result<(), SyncError> {
+        for digest in retry_withdrawal_digests {
+            if !pending_withdrawal_digests.contains(&digest) {
+                pending_withdrawal_digests.push(digest);
+            }
+        }
         if pending_withdrawal_digests.is_empty() {
             // no pending withdrawals
             return Ok(());
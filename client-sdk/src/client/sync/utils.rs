@@ -1,5 +1,7 @@
 use intmax2_interfaces::{
-    api::block_builder::interface::Fee, data::user_data::UserData, utils::random::default_rng,
+    api::{balance_prover::interface::BalanceProverClientInterface, block_builder::interface::Fee},
+    data::user_data::UserData,
+    utils::random::default_rng,
 };
 use intmax2_zkp::{
     common::{
@@ -13,12 +15,35 @@ use plonky2::{
     plonk::{config::PoseidonGoldilocksConfig, proof::ProofWithPublicInputs},
 };
 
+use crate::external_api::utils::retry::{with_retry_config, RetryConfig, DEFAULT_INITIAL_DELAY_MS};
+
 use super::error::SyncError;
 
 type F = GoldilocksField;
 type C = PoseidonGoldilocksConfig;
 const D: usize = 2;
 
+/// Pre-flight check that the balance prover is reachable before a sync starts proving.
+///
+/// The health check is retried `warmup_retries` times with exponential backoff, so that a
+/// restarting prover does not abort the sync.
+pub async fn ensure_balance_prover_available(
+    balance_prover: &dyn BalanceProverClientInterface,
+    warmup_retries: u32,
+) -> Result<(), SyncError> {
+    let retry_config = RetryConfig {
+        max_retries: warmup_retries,
+        initial_delay_ms: DEFAULT_INITIAL_DELAY_MS,
+        jitter_ms: 0,
+    };
+    with_retry_config(&retry_config, || balance_prover.health())
+        .await
+        .map_err(|e| SyncError::BalanceProverUnavailable {
+            endpoint: balance_prover.endpoint(),
+            reason: e.to_string(),
+        })
+}
+
 pub fn generate_salt() -> Salt {
     Salt::rand(&mut default_rng())
 }
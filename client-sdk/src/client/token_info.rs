@@ -0,0 +1,136 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use intmax2_interfaces::data::deposit_data::TokenType;
+use intmax2_zkp::ethereum_types::{address::Address, u256::U256};
+use serde::{Deserialize, Serialize};
+
+use crate::external_api::contract::{
+    convert::convert_address_to_alloy, erc20_contract::ERC20Contract, error::BlockchainError,
+    liquidity_contract::LiquidityContract,
+};
+
+const NATIVE_DECIMALS: u8 = 18;
+const NATIVE_SYMBOL: &str = "ETH";
+
+/// Token registered in the liquidity contract under `token_index`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenInfo {
+    pub token_index: u32,
+    pub token_type: TokenType,
+    pub address: Address,
+    pub token_id: U256,
+    /// Decimals of the token. `None` if the ERC20 token does not implement `decimals()`.
+    pub decimals: Option<u8>,
+    /// Symbol of the token. `None` for NFTs and ERC20 tokens without `symbol()`.
+    pub symbol: Option<String>,
+}
+
+impl TokenInfo {
+    /// Format a raw amount of this token in human units, e.g. "1.5" for 1.5 ETH.
+    /// The raw amount is returned as is if the decimals are unknown.
+    pub fn format_amount(&self, amount: U256) -> String {
+        match self.decimals {
+            Some(decimals) => format_units(&amount.to_string(), decimals),
+            None => amount.to_string(),
+        }
+    }
+}
+
+/// Cache of the token infos. Token registrations never change, so entries are never evicted.
+/// Clones share the same cache.
+#[derive(Debug, Clone, Default)]
+pub struct TokenInfoCache(Arc<RwLock<HashMap<u32, TokenInfo>>>);
+
+impl TokenInfoCache {
+    fn get(&self, token_index: u32) -> Option<TokenInfo> {
+        self.0.read().unwrap().get(&token_index).cloned()
+    }
+
+    fn insert(&self, token_info: TokenInfo) {
+        self.0
+            .write()
+            .unwrap()
+            .insert(token_info.token_index, token_info);
+    }
+}
+
+pub async fn get_token_info(
+    liquidity_contract: &LiquidityContract,
+    cache: &TokenInfoCache,
+    token_index: u32,
+) -> Result<TokenInfo, BlockchainError> {
+    if let Some(token_info) = cache.get(token_index) {
+        return Ok(token_info);
+    }
+    let (token_type, address, token_id) = liquidity_contract.get_token_info(token_index).await?;
+    let (decimals, symbol) = match token_type {
+        TokenType::NATIVE => (Some(NATIVE_DECIMALS), Some(NATIVE_SYMBOL.to_string())),
+        TokenType::ERC20 => {
+            let erc20 = ERC20Contract::new(
+                liquidity_contract.provider.clone(),
+                convert_address_to_alloy(address),
+            );
+            // non-standard tokens may not implement the optional metadata functions
+            (erc20.decimals().await.ok(), erc20.symbol().await.ok())
+        }
+        TokenType::ERC721 | TokenType::ERC1155 => (Some(0), None),
+    };
+    let token_info = TokenInfo {
+        token_index,
+        token_type,
+        address,
+        token_id,
+        decimals,
+        symbol,
+    };
+    cache.insert(token_info.clone());
+    Ok(token_info)
+}
+
+pub async fn get_token_infos(
+    liquidity_contract: &LiquidityContract,
+    cache: &TokenInfoCache,
+    token_indices: &[u32],
+) -> Result<Vec<TokenInfo>, BlockchainError> {
+    futures::future::try_join_all(
+        token_indices
+            .iter()
+            .map(|&token_index| get_token_info(liquidity_contract, cache, token_index)),
+    )
+    .await
+}
+
+/// Format a base 10 integer string as a decimal number with `decimals` fractional digits,
+/// trimming the trailing zeros of the fraction.
+pub fn format_units(amount: &str, decimals: u8) -> String {
+    let decimals = decimals as usize;
+    if decimals == 0 {
+        return amount.to_string();
+    }
+    let padded = format!("{amount:0>width$}", width = decimals + 1);
+    let (integer, fraction) = padded.split_at(padded.len() - decimals);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        integer.to_string()
+    } else {
+        format!("{integer}.{fraction}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format_units;
+
+    #[test]
+    fn test_format_units() {
+        assert_eq!(format_units("1500000000000000000", 18), "1.5");
+        assert_eq!(format_units("1000000", 6), "1");
+        assert_eq!(format_units("1", 6), "0.000001");
+        assert_eq!(format_units("0", 18), "0");
+        assert_eq!(format_units("42", 0), "42");
+    }
+}
@@ -0,0 +1,217 @@
+use intmax2_interfaces::{
+    api::store_vault_server::types::{CursorOrder, MetaDataCursor},
+    data::{data_type::DataType, tx_data::TxData},
+};
+use intmax2_zkp::{
+    common::{
+        block::Block,
+        signature_content::{key_set::KeySet, SignatureContent},
+        trees::tx_tree::TxMerkleProof,
+        tx::Tx,
+    },
+    ethereum_types::bytes32::Bytes32,
+    utils::poseidon_hash_out::PoseidonHashOut,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{client::Client, error::ClientError, strategy::common::fetch_decrypt_validate};
+
+/// Proof that a tx is in the tx tree of a posted block, checked by `verify_tx_inclusion`
+/// against the block hash stored on the rollup contract, without trusting the validity prover.
+///
+/// Inclusion does not mean the tx was applied: the sender must also have returned its signature
+/// and the block must be valid, which `Client::get_tx_status` reports.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxInclusionProof {
+    pub tx: Tx,
+    pub tx_index: u32,
+    pub tx_merkle_proof: TxMerkleProof,
+    pub tx_tree_root: Bytes32,
+    pub block_number: u32,
+    /// The posted block, whose hash is stored on the rollup contract
+    pub block: Block,
+    /// The signature content the block commits to, which holds the tx tree root
+    pub signature: SignatureContent,
+}
+
+fn invalid(reason: impl Into<String>) -> ClientError {
+    ClientError::InvalidTxInclusionProof(reason.into())
+}
+
+/// Proves that the tx of `key` with `tx_tree_root` is in a posted block, or returns `None` if no
+/// block with this tx tree root is posted yet. The tx and its merkle path are read from the tx
+/// data saved to the store vault when the tx was sent.
+pub async fn get_tx_inclusion_proof(
+    client: &Client,
+    key: KeySet,
+    tx_tree_root: Bytes32,
+) -> Result<Option<TxInclusionProof>, ClientError> {
+    let Some(block_number) = client
+        .validity_prover
+        .get_block_number_by_tx_tree_root(tx_tree_root)
+        .await?
+    else {
+        return Ok(None);
+    };
+    let tx_data = find_tx_data(client, key, tx_tree_root)
+        .await?
+        .ok_or_else(|| {
+            ClientError::UnexpectedError(format!(
+                "tx data of tx tree root {tx_tree_root} is not found in the store vault"
+            ))
+        })?;
+    let validity_witness = client
+        .validity_prover
+        .get_validity_witness(block_number)
+        .await?;
+    let block_witness = validity_witness.block_witness;
+    Ok(Some(TxInclusionProof {
+        tx: tx_data.spent_witness.tx,
+        tx_index: tx_data.tx_index,
+        tx_merkle_proof: tx_data.tx_merkle_proof,
+        tx_tree_root,
+        block_number,
+        block: block_witness.block,
+        signature: block_witness.signature,
+    }))
+}
+
+/// Finds the tx data with `tx_tree_root`, newest first.
+async fn find_tx_data(
+    client: &Client,
+    key: KeySet,
+    tx_tree_root: Bytes32,
+) -> Result<Option<TxData>, ClientError> {
+    let mut cursor = MetaDataCursor {
+        cursor: None,
+        order: CursorOrder::Desc,
+        limit: None,
+    };
+    loop {
+        let (entries, cursor_response) = fetch_decrypt_validate::<TxData>(
+            client.store_vault_server.as_ref(),
+            key,
+            DataType::Tx,
+            &[],
+            &[],
+            &cursor,
+        )
+        .await?;
+        if let Some((_, tx_data)) = entries
+            .into_iter()
+            .find(|(_, tx_data)| tx_data.tx_tree_root == tx_tree_root)
+        {
+            return Ok(Some(tx_data));
+        }
+        if !cursor_response.has_more {
+            return Ok(None);
+        }
+        cursor.cursor = cursor_response.next_cursor;
+    }
+}
+
+/// Verifies `proof` against `onchain_block_hash`, the hash of block `proof.block_number` read
+/// from the rollup contract.
+pub fn verify_tx_inclusion(
+    proof: &TxInclusionProof,
+    onchain_block_hash: Bytes32,
+) -> Result<(), ClientError> {
+    verify_tx_leaf(
+        &proof.tx,
+        proof.tx_index,
+        &proof.tx_merkle_proof,
+        proof.tx_tree_root,
+    )?;
+    if proof.signature.block_sign_payload.tx_tree_root != proof.tx_tree_root {
+        return Err(invalid(
+            "the signature content does not hold the tx tree root",
+        ));
+    }
+    if proof.block.signature_hash != proof.signature.hash() {
+        return Err(invalid(
+            "the block does not commit to the signature content",
+        ));
+    }
+    if proof.block.block_number != proof.block_number {
+        return Err(invalid(format!(
+            "block number {} does not match {}",
+            proof.block.block_number, proof.block_number
+        )));
+    }
+    if proof.block.hash() != onchain_block_hash {
+        return Err(invalid(format!(
+            "block hash {} does not match the onchain block hash {onchain_block_hash}",
+            proof.block.hash()
+        )));
+    }
+    Ok(())
+}
+
+fn verify_tx_leaf(
+    tx: &Tx,
+    tx_index: u32,
+    tx_merkle_proof: &TxMerkleProof,
+    tx_tree_root: Bytes32,
+) -> Result<(), ClientError> {
+    let root: PoseidonHashOut = tx_tree_root
+        .try_into()
+        .map_err(|e| invalid(format!("invalid tx tree root: {e}")))?;
+    tx_merkle_proof
+        .verify(tx, tx_index as u64, root)
+        .map_err(|e| invalid(format!("invalid tx merkle proof: {e}")))
+}
+
+impl Client {
+    /// Proves that the tx of `key` with `tx_tree_root` is in a posted block. See
+    /// `get_tx_inclusion_proof`.
+    pub async fn get_tx_inclusion_proof(
+        &self,
+        key: KeySet,
+        tx_tree_root: Bytes32,
+    ) -> Result<Option<TxInclusionProof>, ClientError> {
+        get_tx_inclusion_proof(self, key, tx_tree_root).await
+    }
+
+    /// Verifies `proof` against the block hash read from the rollup contract.
+    pub async fn verify_tx_inclusion(&self, proof: &TxInclusionProof) -> Result<(), ClientError> {
+        let onchain_block_hash = self
+            .rollup_contract
+            .get_block_hash(proof.block_number)
+            .await?;
+        verify_tx_inclusion(proof, onchain_block_hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use intmax2_zkp::{
+        common::{trees::tx_tree::TxTree, tx::Tx},
+        constants::TX_TREE_HEIGHT,
+        ethereum_types::bytes32::Bytes32,
+        utils::poseidon_hash_out::PoseidonHashOut,
+    };
+
+    use super::verify_tx_leaf;
+
+    #[test]
+    fn test_verify_tx_leaf() {
+        let mut tx_tree = TxTree::new(TX_TREE_HEIGHT);
+        let txs = (0..3)
+            .map(|nonce| Tx {
+                nonce,
+                transfer_tree_root: PoseidonHashOut::default(),
+            })
+            .collect::<Vec<_>>();
+        for tx in &txs {
+            tx_tree.push(*tx);
+        }
+        let root: Bytes32 = tx_tree.get_root().into();
+        let proof = tx_tree.prove(1);
+
+        assert!(verify_tx_leaf(&txs[1], 1, &proof, root).is_ok());
+        assert!(verify_tx_leaf(&txs[1], 2, &proof, root).is_err());
+        assert!(verify_tx_leaf(&txs[2], 1, &proof, root).is_err());
+        assert!(verify_tx_leaf(&txs[1], 1, &proof, Bytes32::default()).is_err());
+    }
+}
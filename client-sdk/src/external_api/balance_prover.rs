@@ -27,7 +27,10 @@ use plonky2::{
     plonk::{config::PoseidonGoldilocksConfig, proof::ProofWithPublicInputs},
 };
 
-use super::utils::query::post_request;
+use super::utils::{
+    query::{get_request_with_operation_id, post_request_with_operation_id},
+    retry::RetryConfig,
+};
 
 type F = GoldilocksField;
 type C = PoseidonGoldilocksConfig;
@@ -36,18 +39,45 @@ const D: usize = 2;
 #[derive(Debug, Clone)]
 pub struct BalanceProverClient {
     base_url: String,
+    retry_config: RetryConfig,
 }
 
 impl BalanceProverClient {
     pub fn new(base_url: &str) -> Self {
         BalanceProverClient {
             base_url: base_url.to_string(),
+            retry_config: RetryConfig::default(),
         }
     }
+
+    /// Use `retry_config` for every request made by this client.
+    pub fn with_retry_config(mut self, retry_config: RetryConfig) -> Self {
+        self.retry_config = retry_config;
+        self
+    }
 }
 
 #[async_trait(?Send)]
 impl BalanceProverClientInterface for BalanceProverClient {
+    fn endpoint(&self) -> String {
+        self.base_url.clone()
+    }
+
+    async fn health(&self) -> Result<(), ServerError> {
+        let no_retry = RetryConfig {
+            max_retries: 0,
+            ..self.retry_config
+        };
+        let _: serde_json::Value = get_request_with_operation_id::<(), _>(
+            &self.base_url,
+            "/health-check",
+            None,
+            &no_retry,
+        )
+        .await?;
+        Ok(())
+    }
+
     async fn prove_spent(
         &self,
         _key: KeySet,
@@ -56,10 +86,11 @@ impl BalanceProverClientInterface for BalanceProverClient {
         let request = ProveSpentRequest {
             spent_witness: spent_witness.clone(),
         };
-        let response: ProveResponse = post_request(
+        let response: ProveResponse = post_request_with_operation_id(
             &self.base_url,
             "/balance-prover/prove-spent",
             Some(&request),
+            &self.retry_config,
         )
         .await?;
         Ok(response.proof)
@@ -81,8 +112,13 @@ impl BalanceProverClientInterface for BalanceProverClient {
             spent_proof: spent_proof.clone(),
             prev_proof: prev_proof.clone(),
         };
-        let response: ProveResponse =
-            post_request(&self.base_url, "/balance-prover/prove-send", Some(&request)).await?;
+        let response: ProveResponse = post_request_with_operation_id(
+            &self.base_url,
+            "/balance-prover/prove-send",
+            Some(&request),
+            &self.retry_config,
+        )
+        .await?;
         Ok(response.proof)
     }
 
@@ -98,10 +134,11 @@ impl BalanceProverClientInterface for BalanceProverClient {
             update_witness: update_witness.clone(),
             prev_proof: prev_proof.clone(),
         };
-        let response: ProveResponse = post_request(
+        let response: ProveResponse = post_request_with_operation_id(
             &self.base_url,
             "/balance-prover/prove-update",
             Some(&request),
+            &self.retry_config,
         )
         .await?;
         Ok(response.proof)
@@ -119,10 +156,11 @@ impl BalanceProverClientInterface for BalanceProverClient {
             receive_transfer_witness: receive_transfer_witness.clone(),
             prev_proof: prev_proof.clone(),
         };
-        let response: ProveResponse = post_request(
+        let response: ProveResponse = post_request_with_operation_id(
             &self.base_url,
             "/balance-prover/prove-receive-transfer",
             Some(&request),
+            &self.retry_config,
         )
         .await?;
         Ok(response.proof)
@@ -140,10 +178,11 @@ impl BalanceProverClientInterface for BalanceProverClient {
             receive_deposit_witness: receive_deposit_witness.clone(),
             prev_proof: prev_proof.clone(),
         };
-        let response: ProveResponse = post_request(
+        let response: ProveResponse = post_request_with_operation_id(
             &self.base_url,
             "/balance-prover/prove-receive-deposit",
             Some(&request),
+            &self.retry_config,
         )
         .await?;
         Ok(response.proof)
@@ -157,10 +196,11 @@ impl BalanceProverClientInterface for BalanceProverClient {
         let request = ProveSingleWithdrawalRequest {
             withdrawal_witness: withdrawal_witness.clone(),
         };
-        let response: ProveResponse = post_request(
+        let response: ProveResponse = post_request_with_operation_id(
             &self.base_url,
             "/balance-prover/prove-single-withdrawal",
             Some(&request),
+            &self.retry_config,
         )
         .await?;
         Ok(response.proof)
@@ -176,10 +216,11 @@ impl BalanceProverClientInterface for BalanceProverClient {
             is_faster_mining,
             claim_witness: claim_witness.clone(),
         };
-        let response: ProveResponse = post_request(
+        let response: ProveResponse = post_request_with_operation_id(
             &self.base_url,
             "/balance-prover/prove-single-claim",
             Some(&request),
+            &self.retry_config,
         )
         .await?;
         Ok(response.proof)
@@ -10,20 +10,33 @@ use intmax2_interfaces::api::{
     error::ServerError,
 };
 use intmax2_zkp::{
-    common::{block_builder::BlockProposal, signature_content::flatten::FlatG2, tx::Tx},
+    common::{signature_content::flatten::FlatG2, tx::Tx},
     ethereum_types::u256::U256,
 };
 
-use super::utils::query::{get_request, post_request};
+use super::utils::{
+    query::{get_request_with_operation_id, post_request_with_operation_id},
+    retry::RetryConfig,
+};
 
 pub const DEFAULT_BLOCK_EXPIRY: u64 = 80;
 
 #[derive(Debug, Clone)]
-pub struct BlockBuilderClient;
+pub struct BlockBuilderClient {
+    retry_config: RetryConfig,
+}
 
 impl BlockBuilderClient {
     pub fn new() -> Self {
-        BlockBuilderClient
+        BlockBuilderClient {
+            retry_config: RetryConfig::default(),
+        }
+    }
+
+    /// Use `retry_config` for every request made by this client.
+    pub fn with_retry_config(mut self, retry_config: RetryConfig) -> Self {
+        self.retry_config = retry_config;
+        self
     }
 }
 
@@ -39,8 +52,13 @@ impl BlockBuilderClientInterface for BlockBuilderClient {
         &self,
         block_builder_url: &str,
     ) -> Result<BlockBuilderFeeInfo, ServerError> {
-        get_request::<(), BlockBuilderFeeInfo>(block_builder_url, "/block-builder/fee-info", None)
-            .await
+        get_request_with_operation_id::<(), BlockBuilderFeeInfo>(
+            block_builder_url,
+            "/block-builder/fee-info",
+            None,
+            &self.retry_config,
+        )
+        .await
     }
 
     async fn send_tx_request(
@@ -50,17 +68,20 @@ impl BlockBuilderClientInterface for BlockBuilderClient {
         pubkey: U256,
         tx: Tx,
         fee_proof: Option<FeeProof>,
+        valid_until: Option<u64>,
     ) -> Result<String, ServerError> {
         let request = TxRequestRequest {
             is_registration_block,
             pubkey,
             tx,
             fee_proof,
+            valid_until,
         };
-        let response: TxRequestResponse = post_request(
+        let response: TxRequestResponse = post_request_with_operation_id(
             block_builder_url,
             "/block-builder/tx-request",
             Some(&request),
+            &self.retry_config,
         )
         .await?;
         Ok(response.request_id)
@@ -70,17 +91,17 @@ impl BlockBuilderClientInterface for BlockBuilderClient {
         &self,
         block_builder_url: &str,
         request_id: &str,
-    ) -> Result<Option<BlockProposal>, ServerError> {
+    ) -> Result<QueryProposalResponse, ServerError> {
         let request = QueryProposalRequest {
             request_id: request_id.to_string(),
         };
-        let response: QueryProposalResponse = post_request(
+        post_request_with_operation_id(
             block_builder_url,
             "/block-builder/query-proposal",
             Some(&request),
+            &self.retry_config,
         )
-        .await?;
-        Ok(response.block_proposal)
+        .await
     }
 
     async fn post_signature(
@@ -95,10 +116,11 @@ impl BlockBuilderClientInterface for BlockBuilderClient {
             pubkey,
             signature,
         };
-        post_request::<_, ()>(
+        post_request_with_operation_id::<_, ()>(
             block_builder_url,
             "/block-builder/post-signature",
             Some(&request),
+            &self.retry_config,
         )
         .await
     }
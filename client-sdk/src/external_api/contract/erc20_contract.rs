@@ -37,6 +37,18 @@ impl ERC20Contract {
         Ok(Self { provider, address })
     }
 
+    pub async fn decimals(&self) -> Result<u8, BlockchainError> {
+        let contract = ERC20::new(self.address, self.provider.clone());
+        let decimals = contract.decimals().call().await?;
+        Ok(decimals)
+    }
+
+    pub async fn symbol(&self) -> Result<String, BlockchainError> {
+        let contract = ERC20::new(self.address, self.provider.clone());
+        let symbol = contract.symbol().call().await?;
+        Ok(symbol)
+    }
+
     pub async fn balance_of(&self, account: Address) -> Result<U256, BlockchainError> {
         let contract = ERC20::new(self.address, self.provider.clone());
         let balance = contract.balanceOf(account).call().await?;
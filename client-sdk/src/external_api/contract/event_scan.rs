@@ -0,0 +1,171 @@
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use super::error::BlockchainError;
+
+/// Default max number of blocks queried by a single `eth_getLogs` request.
+pub const DEFAULT_EVENT_BLOCK_RANGE: u64 = 10000;
+
+/// Max number of times the block range is halved within a single scan.
+const MAX_RANGE_HALVINGS: u32 = 10;
+
+/// Lowercased fragments of the error messages RPC providers return when the block range of
+/// `eth_getLogs` is too large.
+const RANGE_TOO_LARGE_PATTERNS: &[&str] = &[
+    "block range",
+    "range too large",
+    "range is too large",
+    "too many blocks",
+    "query returned more than",
+    "limit exceeded",
+];
+
+/// Block range used to page event queries.
+///
+/// The range is shared between clones of a contract client, so a range that was narrowed after
+/// a "range too large" error is reused by subsequent scans.
+#[derive(Debug, Clone)]
+pub struct EventBlockRange(Arc<AtomicU64>);
+
+impl EventBlockRange {
+    pub fn new(range: u64) -> Self {
+        Self(Arc::new(AtomicU64::new(range.max(1))))
+    }
+
+    /// The block range currently in effect.
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    fn set(&self, range: u64) {
+        self.0.store(range.max(1), Ordering::Relaxed);
+    }
+}
+
+impl Default for EventBlockRange {
+    fn default() -> Self {
+        Self::new(DEFAULT_EVENT_BLOCK_RANGE)
+    }
+}
+
+pub fn is_range_too_large_error(error: &BlockchainError) -> bool {
+    let message = error.to_string().to_lowercase();
+    RANGE_TOO_LARGE_PATTERNS
+        .iter()
+        .any(|pattern| message.contains(pattern))
+}
+
+/// Query events in `[from_eth_block, to_eth_block]` in pages of at most `block_range` blocks.
+///
+/// When the RPC rejects a page because its range is too large, the range is halved and the
+/// page is retried, at most `MAX_RANGE_HALVINGS` times per scan.
+pub async fn query_events_in_pages<T, F, Fut>(
+    block_range: &EventBlockRange,
+    from_eth_block: u64,
+    to_eth_block: u64,
+    query: F,
+) -> Result<Vec<T>, BlockchainError>
+where
+    F: Fn(u64, u64) -> Fut,
+    Fut: Future<Output = Result<Vec<T>, BlockchainError>>,
+{
+    let mut events = Vec::new();
+    let mut from = from_eth_block;
+    let mut halvings = 0;
+    while from <= to_eth_block {
+        let page_to = to_eth_block.min(from.saturating_add(block_range.get() - 1));
+        match query(from, page_to).await {
+            Ok(page) => {
+                events.extend(page);
+                if page_to == u64::MAX {
+                    break;
+                }
+                from = page_to + 1;
+            }
+            Err(e) if is_range_too_large_error(&e) && page_to > from => {
+                if halvings >= MAX_RANGE_HALVINGS {
+                    log::error!(
+                        "eth_getLogs still rejects block range {} after {halvings} halvings",
+                        page_to - from + 1
+                    );
+                    return Err(e);
+                }
+                halvings += 1;
+                let range = (page_to - from + 1) / 2;
+                block_range.set(range);
+                log::warn!("eth_getLogs rejected the block range, retrying with {range}: {e}");
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    if halvings > 0 {
+        log::warn!(
+            "Settled on event block range {}. Consider configuring this range for the RPC",
+            block_range.get()
+        );
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range_too_large() -> BlockchainError {
+        BlockchainError::TransactionError("block range is too wide".to_string())
+    }
+
+    #[tokio::test]
+    async fn test_query_events_in_pages_covers_range() {
+        let block_range = EventBlockRange::new(3);
+        let events = query_events_in_pages(&block_range, 1, 10, |from, to| async move {
+            Ok((from..=to).collect::<Vec<_>>())
+        })
+        .await
+        .unwrap();
+        assert_eq!(events, (1..=10).collect::<Vec<_>>());
+        assert_eq!(block_range.get(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_query_events_in_pages_halves_range() {
+        let block_range = EventBlockRange::new(100);
+        let events = query_events_in_pages(&block_range, 0, 99, |from, to| async move {
+            if to - from + 1 > 30 {
+                return Err(range_too_large());
+            }
+            Ok((from..=to).collect::<Vec<_>>())
+        })
+        .await
+        .unwrap();
+        assert_eq!(events, (0..=99).collect::<Vec<_>>());
+        assert_eq!(block_range.get(), 25);
+    }
+
+    #[tokio::test]
+    async fn test_query_events_in_pages_is_bounded() {
+        let block_range = EventBlockRange::new(1 << 20);
+        let result = query_events_in_pages(&block_range, 0, 1 << 20, |_, _| async {
+            Err::<Vec<u64>, _>(range_too_large())
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(block_range.get(), 1 << (20 - MAX_RANGE_HALVINGS));
+    }
+
+    #[tokio::test]
+    async fn test_query_events_in_pages_propagates_other_errors() {
+        let block_range = EventBlockRange::new(100);
+        let result = query_events_in_pages(&block_range, 0, 99, |_, _| async {
+            Err::<Vec<u64>, _>(BlockchainError::TxNotFoundBatch)
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(block_range.get(), 100);
+    }
+}
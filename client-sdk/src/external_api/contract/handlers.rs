@@ -1,6 +1,7 @@
 use super::{error::BlockchainError, utils::ProviderWithSigner};
 use alloy::{
     consensus::{Transaction as _, TxEip1559},
+    eips::BlockNumberOrTag,
     primitives::TxHash,
     providers::{PendingTransactionError, Provider as _},
     rpc::types::TransactionRequest,
@@ -10,12 +11,100 @@ use std::time::Duration;
 const TIMEOUT: Duration = Duration::from_secs(20);
 const MAX_GAS_BUMP_ATTEMPTS: u32 = 3;
 const GAS_BUMP_PERCENTAGE: u64 = 25; // Should be above 10 to avoid replacement transaction underpriced error
+const MIN_REPLACEMENT_BUMP_PERCENTAGE: u128 = 10; // minimum fee increase accepted by nodes for a replacement
+
+/// How the EIP-1559 fees of a transaction are chosen. Fees are in wei.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GasStrategy {
+    /// Pay exactly these fees. The gas is never bumped.
+    Fixed {
+        max_fee_per_gas: u128,
+        max_priority_fee_per_gas: u128,
+    },
+    /// Allow up to `multiplier` times the base fee of the latest block, on top of the estimated
+    /// priority fee.
+    Multiplier(f64),
+    /// Use the estimated fees, but never more than `max_fee_per_gas`, even when bumping the gas.
+    Capped { max_fee_per_gas: u128 },
+}
+
+impl GasStrategy {
+    /// Returns `(max_fee_per_gas, max_priority_fee_per_gas)` given the base fee of the latest
+    /// block and the fees estimated by the provider.
+    fn fees(
+        &self,
+        base_fee_per_gas: u128,
+        estimated_max_fee_per_gas: u128,
+        estimated_max_priority_fee_per_gas: u128,
+    ) -> (u128, u128) {
+        let (max_fee_per_gas, max_priority_fee_per_gas) = match *self {
+            GasStrategy::Fixed {
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+            } => (max_fee_per_gas, max_priority_fee_per_gas),
+            GasStrategy::Multiplier(multiplier) => (
+                (base_fee_per_gas as f64 * multiplier).ceil() as u128
+                    + estimated_max_priority_fee_per_gas,
+                estimated_max_priority_fee_per_gas,
+            ),
+            GasStrategy::Capped { max_fee_per_gas } => (
+                estimated_max_fee_per_gas.min(max_fee_per_gas),
+                estimated_max_priority_fee_per_gas,
+            ),
+        };
+        // the priority fee is part of the max fee
+        (
+            max_fee_per_gas,
+            max_priority_fee_per_gas.min(max_fee_per_gas),
+        )
+    }
+
+    /// Upper bound of the max fee per gas, which the gas bump does not exceed.
+    fn max_fee_cap(&self) -> Option<u128> {
+        match *self {
+            GasStrategy::Fixed {
+                max_fee_per_gas, ..
+            } => Some(max_fee_per_gas),
+            GasStrategy::Multiplier(_) => None,
+            GasStrategy::Capped { max_fee_per_gas } => Some(max_fee_per_gas),
+        }
+    }
+}
+
+/// Gas pricing of a transaction.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GasOptions {
+    /// If `None`, the fees estimated by the provider are used.
+    pub strategy: Option<GasStrategy>,
+    /// Send with this nonce instead of the next one of the signer. A pending transaction with
+    /// the same nonce is replaced if the new fees are at least 10% higher.
+    pub nonce: Option<u64>,
+}
 
 pub async fn send_transaction_with_gas_bump(
     signer: ProviderWithSigner,
     tx_request: TransactionRequest,
     tx_name: &str,
 ) -> Result<TxHash, BlockchainError> {
+    send_transaction_with_gas_options(signer, tx_request, GasOptions::default(), tx_name).await
+}
+
+/// Same as `send_transaction_with_gas_bump`, but prices the transaction with `gas_options`.
+pub async fn send_transaction_with_gas_options(
+    signer: ProviderWithSigner,
+    mut tx_request: TransactionRequest,
+    gas_options: GasOptions,
+    tx_name: &str,
+) -> Result<TxHash, BlockchainError> {
+    if let Some(strategy) = gas_options.strategy {
+        let (max_fee_per_gas, max_priority_fee_per_gas) = strategy_fees(&signer, &strategy).await?;
+        tx_request = tx_request
+            .max_fee_per_gas(max_fee_per_gas)
+            .max_priority_fee_per_gas(max_priority_fee_per_gas);
+    }
+    if let Some(nonce) = gas_options.nonce {
+        tx_request = tx_request.nonce(nonce);
+    }
     let sendable_tx = signer.fill(tx_request).await?;
     let tx_envelope = sendable_tx.try_into_envelope().unwrap();
     let tx_hash = *tx_envelope.hash();
@@ -47,7 +136,8 @@ pub async fn send_transaction_with_gas_bump(
         }
         Err(PendingTransactionError::TxWatcher(_)) => {
             // timeout, so we need to bump the gas
-            resend_tx_with_gas_bump(signer, tx_hash, &tx_eip1559, tx_name).await
+            let max_fee_cap = gas_options.strategy.and_then(|s| s.max_fee_cap());
+            resend_tx_with_gas_bump(signer, tx_hash, &tx_eip1559, max_fee_cap, tx_name).await
         }
         Err(e) => Err(BlockchainError::TransactionError(format!(
             "{tx_name} failed with error: {e:?}"
@@ -55,10 +145,57 @@ pub async fn send_transaction_with_gas_bump(
     }
 }
 
+async fn strategy_fees(
+    signer: &ProviderWithSigner,
+    strategy: &GasStrategy,
+) -> Result<(u128, u128), BlockchainError> {
+    if let GasStrategy::Fixed { .. } = strategy {
+        return Ok(strategy.fees(0, 0, 0));
+    }
+    let fee_estimation = signer.estimate_eip1559_fees().await?;
+    let base_fee_per_gas = match strategy {
+        GasStrategy::Multiplier(_) => {
+            let block = signer
+                .get_block_by_number(BlockNumberOrTag::Latest)
+                .await?
+                .ok_or(BlockchainError::TransactionError(
+                    "Latest block not found".to_string(),
+                ))?;
+            block.header.base_fee_per_gas.unwrap_or_default() as u128
+        }
+        _ => 0,
+    };
+    Ok(strategy.fees(
+        base_fee_per_gas,
+        fee_estimation.max_fee_per_gas,
+        fee_estimation.max_priority_fee_per_gas,
+    ))
+}
+
+/// Clamps the bumped fees to `max_fee_cap`. Returns `None` if the clamped fees are not high
+/// enough to replace a transaction paying `current_fees`.
+fn cap_bumped_fees(
+    (max_fee_per_gas, max_priority_fee_per_gas): (u128, u128),
+    current_fees: (u128, u128),
+    max_fee_cap: Option<u128>,
+) -> Option<(u128, u128)> {
+    let Some(cap) = max_fee_cap else {
+        return Some((max_fee_per_gas, max_priority_fee_per_gas));
+    };
+    let max_fee_per_gas = max_fee_per_gas.min(cap);
+    let max_priority_fee_per_gas = max_priority_fee_per_gas.min(max_fee_per_gas);
+    let is_enough =
+        |new: u128, current: u128| new * 100 >= current * (100 + MIN_REPLACEMENT_BUMP_PERCENTAGE);
+    (is_enough(max_fee_per_gas, current_fees.0)
+        && is_enough(max_priority_fee_per_gas, current_fees.1))
+    .then_some((max_fee_per_gas, max_priority_fee_per_gas))
+}
+
 async fn resend_tx_with_gas_bump(
     signer: ProviderWithSigner,
     initial_tx_hash: TxHash,
     tx_eip1559: &TxEip1559,
+    max_fee_cap: Option<u128>,
     tx_name: &str,
 ) -> Result<TxHash, BlockchainError> {
     log::info!("Resending transaction: {tx_name}");
@@ -102,6 +239,19 @@ async fn resend_tx_with_gas_bump(
                     current_tx.max_fee_per_gas * (100 + GAS_BUMP_PERCENTAGE as u128) / 100,
                 )
             };
+        let Some((new_max_fee_per_gas, new_max_priority_fee_per_gas)) = cap_bumped_fees(
+            (new_max_fee_per_gas, new_max_priority_fee_per_gas),
+            (
+                current_tx.max_fee_per_gas,
+                current_tx.max_priority_fee_per_gas,
+            ),
+            max_fee_cap,
+        ) else {
+            return Err(BlockchainError::TransactionError(format!(
+                "{tx_name} with nonce {} is still pending and its gas cannot be bumped within the cap of the gas strategy. Replace it by sending with a higher gas price and the same nonce",
+                current_tx.nonce
+            )));
+        };
 
         let new_tx_request = TransactionRequest::default()
             .max_priority_fee_per_gas(new_max_priority_fee_per_gas)
@@ -147,3 +297,37 @@ async fn resend_tx_with_gas_bump(
     }
     Err(BlockchainError::MaxTxRetriesReached)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{cap_bumped_fees, GasStrategy};
+
+    #[test]
+    fn test_gas_strategy_fees() {
+        let fixed = GasStrategy::Fixed {
+            max_fee_per_gas: 30,
+            max_priority_fee_per_gas: 2,
+        };
+        assert_eq!(fixed.fees(100, 200, 5), (30, 2));
+        assert_eq!(GasStrategy::Multiplier(1.5).fees(100, 205, 5), (155, 5));
+        let capped = GasStrategy::Capped {
+            max_fee_per_gas: 150,
+        };
+        assert_eq!(capped.fees(100, 205, 5), (150, 5));
+        assert_eq!(capped.fees(100, 120, 5), (120, 5));
+        // the priority fee never exceeds the max fee
+        let capped = GasStrategy::Capped { max_fee_per_gas: 3 };
+        assert_eq!(capped.fees(100, 205, 5), (3, 3));
+    }
+
+    #[test]
+    fn test_cap_bumped_fees() {
+        assert_eq!(cap_bumped_fees((125, 5), (100, 4), None), Some((125, 5)));
+        assert_eq!(
+            cap_bumped_fees((125, 5), (100, 4), Some(115)),
+            Some((115, 5))
+        );
+        // a bump under 10% would be rejected as underpriced
+        assert_eq!(cap_bumped_fees((125, 5), (100, 4), Some(105)), None);
+    }
+}
@@ -4,13 +4,15 @@ use super::{
         convert_u256_to_alloy, convert_u256_to_intmax,
     },
     error::BlockchainError,
-    handlers::send_transaction_with_gas_bump,
+    event_scan::{query_events_in_pages, EventBlockRange},
+    handlers::{send_transaction_with_gas_bump, send_transaction_with_gas_options, GasOptions},
     proxy_contract::ProxyContract,
     utils::{get_provider_with_signer, NormalProvider},
 };
 use alloy::{
     network::TransactionBuilder,
     primitives::{Address, Bytes, B256, U256},
+    providers::Provider as _,
     sol,
 };
 use intmax2_interfaces::{
@@ -67,11 +69,22 @@ sol!(
 pub struct LiquidityContract {
     pub provider: NormalProvider,
     pub address: Address,
+    pub event_block_range: EventBlockRange,
 }
 
 impl LiquidityContract {
     pub fn new(provider: NormalProvider, address: Address) -> Self {
-        Self { provider, address }
+        Self {
+            provider,
+            address,
+            event_block_range: EventBlockRange::default(),
+        }
+    }
+
+    /// Set the max number of blocks queried by a single event request.
+    pub fn with_event_block_range(mut self, event_block_range: u64) -> Self {
+        self.event_block_range = EventBlockRange::new(event_block_range);
+        self
     }
 
     pub async fn deploy(provider: NormalProvider, private_key: B256) -> anyhow::Result<Self> {
@@ -80,7 +93,7 @@ impl LiquidityContract {
         let impl_address = *contract.address();
         let proxy = ProxyContract::deploy(provider.clone(), private_key, impl_address, &[]).await?;
         let address = proxy.address;
-        Ok(Self { provider, address })
+        Ok(Self::new(provider, address))
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -174,6 +187,24 @@ impl LiquidityContract {
         Ok((token_type, token_address, token_id))
     }
 
+    /// The latest L1 block number.
+    pub async fn get_block_number(&self) -> Result<u64, BlockchainError> {
+        let block_number = self.provider.get_block_number().await?;
+        Ok(block_number)
+    }
+
+    /// The L1 block number the transaction was included in, or `None` if it is not mined.
+    pub async fn get_tx_block_number(
+        &self,
+        tx_hash: Bytes32,
+    ) -> Result<Option<u64>, BlockchainError> {
+        let receipt = self
+            .provider
+            .get_transaction_receipt(convert_bytes32_to_b256(tx_hash))
+            .await?;
+        Ok(receipt.and_then(|receipt| receipt.block_number))
+    }
+
     pub async fn get_last_deposit_id(&self) -> Result<u64, BlockchainError> {
         let contract = Liquidity::new(self.address, self.provider.clone());
         let deposit_id = contract.getLastDepositId().call().await?;
@@ -201,15 +232,17 @@ impl LiquidityContract {
         Ok(block_number != U256::ZERO)
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn deposit_native(
         &self,
         signer_private_key: B256,
         gas_limit: Option<u64>,
+        gas_options: GasOptions,
         pubkey_salt_hash: Bytes32,
         amount: ZkpU256,
         aml_permission: &[u8],
         eligibility_permission: &[u8],
-    ) -> Result<(), BlockchainError> {
+    ) -> Result<Bytes32, BlockchainError> {
         let signer = get_provider_with_signer(&self.provider, signer_private_key);
         let contract = Liquidity::new(self.address, signer.clone());
         let recipient_salt_hash = convert_bytes32_to_b256(pubkey_salt_hash);
@@ -225,8 +258,14 @@ impl LiquidityContract {
         if let Some(gas_limit) = gas_limit {
             tx_request.set_gas_limit(gas_limit);
         }
-        send_transaction_with_gas_bump(signer, tx_request, "deposit_native_token").await?;
-        Ok(())
+        let tx_hash = send_transaction_with_gas_options(
+            signer,
+            tx_request,
+            gas_options,
+            "deposit_native_token",
+        )
+        .await?;
+        Ok(convert_tx_hash_to_bytes32(tx_hash))
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -234,12 +273,13 @@ impl LiquidityContract {
         &self,
         signer_private_key: B256,
         gas_limit: Option<u64>,
+        gas_options: GasOptions,
         pubkey_salt_hash: Bytes32,
         amount: ZkpU256,
         token_address: ZkpAddress,
         aml_permission: &[u8],
         eligibility_permission: &[u8],
-    ) -> Result<(), BlockchainError> {
+    ) -> Result<Bytes32, BlockchainError> {
         let signer = get_provider_with_signer(&self.provider, signer_private_key);
         let contract = Liquidity::new(self.address, signer.clone());
         let recipient_salt_hash = convert_bytes32_to_b256(pubkey_salt_hash);
@@ -257,8 +297,14 @@ impl LiquidityContract {
         if let Some(gas_limit) = gas_limit {
             tx_request.set_gas_limit(gas_limit);
         }
-        send_transaction_with_gas_bump(signer, tx_request, "deposit_erc20_token").await?;
-        Ok(())
+        let tx_hash = send_transaction_with_gas_options(
+            signer,
+            tx_request,
+            gas_options,
+            "deposit_erc20_token",
+        )
+        .await?;
+        Ok(convert_tx_hash_to_bytes32(tx_hash))
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -266,12 +312,13 @@ impl LiquidityContract {
         &self,
         signer_private_key: B256,
         gas_limit: Option<u64>,
+        gas_options: GasOptions,
         pubkey_salt_hash: Bytes32,
         token_address: ZkpAddress,
         token_id: ZkpU256,
         aml_permission: &[u8],
         eligibility_permission: &[u8],
-    ) -> Result<(), BlockchainError> {
+    ) -> Result<Bytes32, BlockchainError> {
         let signer = get_provider_with_signer(&self.provider, signer_private_key);
         let contract = Liquidity::new(self.address, signer.clone());
         let recipient_salt_hash = convert_bytes32_to_b256(pubkey_salt_hash);
@@ -289,8 +336,14 @@ impl LiquidityContract {
         if let Some(gas_limit) = gas_limit {
             tx_request.set_gas_limit(gas_limit);
         }
-        send_transaction_with_gas_bump(signer, tx_request, "deposit_erc721_token").await?;
-        Ok(())
+        let tx_hash = send_transaction_with_gas_options(
+            signer,
+            tx_request,
+            gas_options,
+            "deposit_erc721_token",
+        )
+        .await?;
+        Ok(convert_tx_hash_to_bytes32(tx_hash))
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -298,13 +351,14 @@ impl LiquidityContract {
         &self,
         signer_private_key: B256,
         gas_limit: Option<u64>,
+        gas_options: GasOptions,
         pubkey_salt_hash: Bytes32,
         token_address: ZkpAddress,
         token_id: ZkpU256,
         amount: ZkpU256,
         aml_permission: &[u8],
         eligibility_permission: &[u8],
-    ) -> Result<(), BlockchainError> {
+    ) -> Result<Bytes32, BlockchainError> {
         let signer = get_provider_with_signer(&self.provider, signer_private_key);
         let contract = Liquidity::new(self.address, signer.clone());
         let recipient_salt_hash = convert_bytes32_to_b256(pubkey_salt_hash);
@@ -324,14 +378,21 @@ impl LiquidityContract {
         if let Some(gas_limit) = gas_limit {
             tx_request.set_gas_limit(gas_limit);
         }
-        send_transaction_with_gas_bump(signer, tx_request, "deposit_erc1155_token").await?;
-        Ok(())
+        let tx_hash = send_transaction_with_gas_options(
+            signer,
+            tx_request,
+            gas_options,
+            "deposit_erc1155_token",
+        )
+        .await?;
+        Ok(convert_tx_hash_to_bytes32(tx_hash))
     }
 
     pub async fn claim_withdrawals(
         &self,
         signer_private_key: B256,
         gas_limit: Option<u64>,
+        gas_options: GasOptions,
         withdrawals: &[ContractWithdrawal],
     ) -> Result<(), BlockchainError> {
         let withdrawals = withdrawals
@@ -351,7 +412,8 @@ impl LiquidityContract {
         if let Some(gas_limit) = gas_limit {
             tx_request.set_gas_limit(gas_limit);
         }
-        send_transaction_with_gas_bump(signer, tx_request, "claim_withdrawals").await?;
+        send_transaction_with_gas_options(signer, tx_request, gas_options, "claim_withdrawals")
+            .await?;
         Ok(())
     }
 
@@ -364,13 +426,24 @@ impl LiquidityContract {
             "get_deposited_event: from_eth_block={from_eth_block}, to_eth_block={to_eth_block}"
         );
         let contract = Liquidity::new(self.address, self.provider.clone());
-        let events = contract
-            .event_filter::<Liquidity::Deposited>()
-            .address(self.address)
-            .from_block(from_eth_block)
-            .to_block(to_eth_block)
-            .query()
-            .await?;
+        let events = query_events_in_pages(
+            &self.event_block_range,
+            from_eth_block,
+            to_eth_block,
+            |from, to| {
+                let contract = &contract;
+                async move {
+                    Ok(contract
+                        .event_filter::<Liquidity::Deposited>()
+                        .address(self.address)
+                        .from_block(from)
+                        .to_block(to)
+                        .query()
+                        .await?)
+                }
+            },
+        )
+        .await?;
         let mut deposited_events = Vec::new();
         for (event, meta) in events {
             deposited_events.push(Deposited {
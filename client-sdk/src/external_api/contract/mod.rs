@@ -6,6 +6,7 @@ pub mod erc1155_contract;
 pub mod erc20_contract;
 pub mod erc721_contract;
 pub mod error;
+pub mod event_scan;
 pub mod handlers;
 pub mod liquidity_contract;
 pub mod proxy_contract;
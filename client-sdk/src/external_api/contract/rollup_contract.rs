@@ -26,6 +26,7 @@ use super::{
         convert_u256_to_alloy, convert_u256_to_intmax,
     },
     error::BlockchainError,
+    event_scan::{query_events_in_pages, EventBlockRange},
     handlers::send_transaction_with_gas_bump,
     proxy_contract::ProxyContract,
     utils::{get_provider_with_signer, NormalProvider},
@@ -74,11 +75,22 @@ pub struct FullBlockWithMeta {
 pub struct RollupContract {
     pub provider: NormalProvider,
     pub address: Address,
+    pub event_block_range: EventBlockRange,
 }
 
 impl RollupContract {
     pub fn new(provider: NormalProvider, address: Address) -> Self {
-        Self { provider, address }
+        Self {
+            provider,
+            address,
+            event_block_range: EventBlockRange::default(),
+        }
+    }
+
+    /// Set the max number of blocks queried by a single event request.
+    pub fn with_event_block_range(mut self, event_block_range: u64) -> Self {
+        self.event_block_range = EventBlockRange::new(event_block_range);
+        self
     }
 
     pub async fn deploy(provider: NormalProvider, private_key: B256) -> anyhow::Result<Self> {
@@ -86,10 +98,7 @@ impl RollupContract {
         let impl_contract = Rollup::deploy(signer).await?;
         let impl_address = *impl_contract.address();
         let proxy = ProxyContract::deploy(provider.clone(), private_key, impl_address, &[]).await?;
-        Ok(Self {
-            provider,
-            address: proxy.address,
-        })
+        Ok(Self::new(provider, proxy.address))
     }
 
     pub async fn initialize(
@@ -315,13 +324,24 @@ impl RollupContract {
     ) -> Result<Vec<BlockPosted>, BlockchainError> {
         log::info!("get_blocks_posted_event: from_block={from_eth_block}, to_block={to_eth_block}");
         let contract = Rollup::new(self.address, self.provider.clone());
-        let events = contract
-            .event_filter::<Rollup::BlockPosted>()
-            .address(self.address)
-            .from_block(from_eth_block)
-            .to_block(to_eth_block)
-            .query()
-            .await?;
+        let events = query_events_in_pages(
+            &self.event_block_range,
+            from_eth_block,
+            to_eth_block,
+            |from, to| {
+                let contract = &contract;
+                async move {
+                    Ok(contract
+                        .event_filter::<Rollup::BlockPosted>()
+                        .address(self.address)
+                        .from_block(from)
+                        .to_block(to)
+                        .query()
+                        .await?)
+                }
+            },
+        )
+        .await?;
         let mut block_posited_events = Vec::new();
         for (event, meta) in events {
             block_posited_events.push(BlockPosted {
@@ -393,13 +413,24 @@ impl RollupContract {
             "get_deposit_leaf_inserted_event: from_eth_block={from_eth_block}, to_eth_block_number={to_eth_block_number}"
         );
         let contract = Rollup::new(self.address, self.provider.clone());
-        let events = contract
-            .event_filter::<Rollup::DepositLeafInserted>()
-            .address(self.address)
-            .from_block(from_eth_block)
-            .to_block(to_eth_block_number)
-            .query()
-            .await?;
+        let events = query_events_in_pages(
+            &self.event_block_range,
+            from_eth_block,
+            to_eth_block_number,
+            |from, to| {
+                let contract = &contract;
+                async move {
+                    Ok(contract
+                        .event_filter::<Rollup::DepositLeafInserted>()
+                        .address(self.address)
+                        .from_block(from)
+                        .to_block(to)
+                        .query()
+                        .await?)
+                }
+            },
+        )
+        .await?;
         let mut deposit_leaf_inserted_events = Vec::new();
         for (event, meta) in events {
             deposit_leaf_inserted_events.push(DepositLeafInserted {
@@ -0,0 +1,423 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use intmax2_interfaces::{
+    api::{
+        error::ServerError,
+        store_vault_server::{
+            interface::{SaveDataEntry, StoreVaultClientInterface, MAX_BATCH_SIZE},
+            types::{
+                CursorOrder, DataWithMetaData, GetDataSequenceRequest, MetaDataCursor,
+                MetaDataCursorResponse, TopicEntryCount,
+            },
+        },
+    },
+    data::{
+        meta_data::MetaData,
+        rw_rights::{ReadRights, WriteRights},
+        topic::extract_rights,
+    },
+    utils::{
+        digest::get_digest,
+        signature::{Auth, Signable as _},
+    },
+};
+use intmax2_zkp::{
+    common::signature_content::key_set::KeySet,
+    ethereum_types::{bytes32::Bytes32, u256::U256, u32limb_trait::U32LimbTrait as _},
+};
+
+use super::store_vault_server::generate_auth_for_get_data_sequence;
+
+#[derive(Debug, Default)]
+struct InMemoryState {
+    // (pubkey hex, topic) -> (meta, data)
+    snapshots: HashMap<(String, String), (MetaData, Vec<u8>)>,
+    // (pubkey hex, topic) -> meta -> data
+    historical_data: HashMap<(String, String), BTreeMap<MetaData, Vec<u8>>>,
+}
+
+/// Store vault backed by in-memory maps, for tests that should not depend on a running store
+/// vault server or S3.
+///
+/// It follows the semantics of the store vault server: snapshots are replaced only if the
+/// given `prev_digest` matches the stored one, historical data is ordered by
+/// `(timestamp, digest)` with exclusive cursors, and the read and write rights of the topics
+/// are enforced. Clones share the same storage.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryStoreVaultClient {
+    state: Arc<Mutex<InMemoryState>>,
+}
+
+impl InMemoryStoreVaultClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+fn map_key(pubkey: U256, topic: &str) -> (String, String) {
+    (pubkey.to_hex(), topic.to_string())
+}
+
+fn now() -> u64 {
+    chrono::Utc::now().timestamp() as u64
+}
+
+fn validate_write(auth_pubkey: U256, pubkey: U256, topic: &str) -> Result<(), ServerError> {
+    let rights = extract_rights(topic)
+        .map_err(|e| ServerError::InvalidRequest(format!("Invalid topic: {e}")))?;
+    match rights.write_rights {
+        WriteRights::SingleAuthWrite | WriteRights::AuthWrite if auth_pubkey != pubkey => Err(
+            ServerError::InvalidRequest("Auth pubkey does not match request pubkey".to_string()),
+        ),
+        _ => Ok(()),
+    }
+}
+
+fn validate_read(auth_pubkey: U256, pubkey: U256, topic: &str) -> Result<(), ServerError> {
+    let rights = extract_rights(topic)
+        .map_err(|e| ServerError::InvalidRequest(format!("Invalid topic: {e}")))?;
+    match rights.read_rights {
+        ReadRights::AuthRead if auth_pubkey != pubkey => Err(ServerError::InvalidRequest(
+            "Auth pubkey does not match request pubkey".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn verify_auth_for_get_data_sequence(auth: &Auth) -> Result<(), ServerError> {
+    let dummy_request = GetDataSequenceRequest {
+        topic: "dummy".to_string(),
+        pubkey: auth.pubkey,
+        cursor: MetaDataCursor {
+            cursor: None,
+            order: CursorOrder::Asc,
+            limit: None,
+        },
+    };
+    dummy_request
+        .verify(auth)
+        .map_err(|e| ServerError::InvalidAuth(e.to_string()))
+}
+
+#[async_trait(?Send)]
+impl StoreVaultClientInterface for InMemoryStoreVaultClient {
+    async fn save_snapshot(
+        &self,
+        key: KeySet,
+        topic: &str,
+        prev_digest: Option<Bytes32>,
+        data: &[u8],
+    ) -> Result<(), ServerError> {
+        validate_write(key.pubkey, key.pubkey, topic)?;
+        let rights = extract_rights(topic)
+            .map_err(|e| ServerError::InvalidRequest(format!("Invalid topic: {e}")))?;
+        if matches!(
+            rights.write_rights,
+            WriteRights::SingleAuthWrite | WriteRights::SingleOpenWrite
+        ) && prev_digest.is_some()
+        {
+            return Err(ServerError::InvalidRequest(format!(
+                "prev_digest is not allowed in {:?}",
+                rights.write_rights
+            )));
+        }
+        let mut state = self.state.lock().unwrap();
+        let snapshot_key = map_key(key.pubkey, topic);
+        let current_digest = state
+            .snapshots
+            .get(&snapshot_key)
+            .map(|(meta, _)| meta.digest);
+        if current_digest != prev_digest {
+            return Err(ServerError::InternalError(format!(
+                "prev_digest mismatch with stored digest: {current_digest:?}"
+            )));
+        }
+        let meta = MetaData {
+            timestamp: now(),
+            digest: get_digest(data),
+        };
+        state.snapshots.insert(snapshot_key, (meta, data.to_vec()));
+        Ok(())
+    }
+
+    async fn get_snapshot(&self, key: KeySet, topic: &str) -> Result<Option<Vec<u8>>, ServerError> {
+        validate_read(key.pubkey, key.pubkey, topic)?;
+        let state = self.state.lock().unwrap();
+        Ok(state
+            .snapshots
+            .get(&map_key(key.pubkey, topic))
+            .map(|(_, data)| data.clone()))
+    }
+
+    async fn save_data_batch(
+        &self,
+        key: KeySet,
+        entries: &[SaveDataEntry],
+    ) -> Result<Vec<Bytes32>, ServerError> {
+        if entries.len() > MAX_BATCH_SIZE {
+            return Err(ServerError::InvalidRequest(format!(
+                "Batch size exceeds maximum limit of {MAX_BATCH_SIZE}"
+            )));
+        }
+        for entry in entries {
+            let rights = extract_rights(&entry.topic)
+                .map_err(|e| ServerError::InvalidRequest(format!("Invalid topic: {e}")))?;
+            if matches!(
+                rights.write_rights,
+                WriteRights::SingleAuthWrite | WriteRights::SingleOpenWrite
+            ) {
+                return Err(ServerError::InvalidRequest(format!(
+                    "{:?} is not allowed in historical data",
+                    rights.write_rights
+                )));
+            }
+            validate_write(key.pubkey, entry.pubkey, &entry.topic)?;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let timestamp = now();
+        let digests = entries
+            .iter()
+            .map(|entry| get_digest(&entry.data))
+            .collect::<Vec<_>>();
+        // the whole batch is rejected if any digest is already stored, as the server does
+        for (entry, digest) in entries.iter().zip(digests.iter()) {
+            let exists = state
+                .historical_data
+                .get(&map_key(entry.pubkey, &entry.topic))
+                .is_some_and(|data| data.keys().any(|meta| meta.digest == *digest));
+            if exists {
+                return Err(ServerError::InvalidRequest(
+                    "data with the specified digest already in history".to_string(),
+                ));
+            }
+        }
+        for (entry, digest) in entries.iter().zip(digests.iter()) {
+            state
+                .historical_data
+                .entry(map_key(entry.pubkey, &entry.topic))
+                .or_default()
+                .insert(
+                    MetaData {
+                        timestamp,
+                        digest: *digest,
+                    },
+                    entry.data.clone(),
+                );
+        }
+        Ok(digests)
+    }
+
+    async fn get_data_batch(
+        &self,
+        key: KeySet,
+        topic: &str,
+        digests: &[Bytes32],
+    ) -> Result<Vec<DataWithMetaData>, ServerError> {
+        validate_read(key.pubkey, key.pubkey, topic)?;
+        let state = self.state.lock().unwrap();
+        let Some(data) = state.historical_data.get(&map_key(key.pubkey, topic)) else {
+            return Ok(Vec::new());
+        };
+        // digests that are not found are omitted
+        let data_with_meta = digests
+            .iter()
+            .filter_map(|digest| {
+                data.iter()
+                    .find(|(meta, _)| meta.digest == *digest)
+                    .map(|(meta, data)| DataWithMetaData {
+                        meta: meta.clone(),
+                        data: data.clone(),
+                    })
+            })
+            .collect();
+        Ok(data_with_meta)
+    }
+
+    async fn get_data_sequence(
+        &self,
+        key: KeySet,
+        topic: &str,
+        cursor: &MetaDataCursor,
+    ) -> Result<(Vec<DataWithMetaData>, MetaDataCursorResponse), ServerError> {
+        let auth = generate_auth_for_get_data_sequence(key);
+        self.get_data_sequence_with_auth(topic, cursor, &auth).await
+    }
+
+    async fn get_data_sequence_with_auth(
+        &self,
+        topic: &str,
+        cursor: &MetaDataCursor,
+        auth: &Auth,
+    ) -> Result<(Vec<DataWithMetaData>, MetaDataCursorResponse), ServerError> {
+        let limit = cursor.limit.unwrap_or(MAX_BATCH_SIZE as u32) as usize;
+        if limit > MAX_BATCH_SIZE {
+            return Err(ServerError::InvalidRequest(
+                "Limit exceeds max batch size".to_string(),
+            ));
+        }
+        verify_auth_for_get_data_sequence(auth)?;
+        auth.check_topic(topic)
+            .map_err(|e| ServerError::InvalidAuth(e.to_string()))?;
+        validate_read(auth.pubkey, auth.pubkey, topic)?;
+
+        let state = self.state.lock().unwrap();
+        let empty = BTreeMap::new();
+        let data = state
+            .historical_data
+            .get(&map_key(auth.pubkey, topic))
+            .unwrap_or(&empty);
+        let entries: Vec<_> = match cursor.order {
+            CursorOrder::Asc => {
+                let cursor_meta = cursor.cursor.clone().unwrap_or_default();
+                data.range(cursor_meta..)
+                    .filter(|(meta, _)| **meta > cursor_meta)
+                    .take(limit + 1)
+                    .collect()
+            }
+            CursorOrder::Desc => match &cursor.cursor {
+                Some(cursor_meta) => data
+                    .range(..cursor_meta.clone())
+                    .rev()
+                    .take(limit + 1)
+                    .collect(),
+                None => data.iter().rev().take(limit + 1).collect(),
+            },
+        };
+        let has_more = entries.len() > limit;
+        let data_with_meta = entries
+            .into_iter()
+            .take(limit)
+            .map(|(meta, data)| DataWithMetaData {
+                meta: meta.clone(),
+                data: data.clone(),
+            })
+            .collect::<Vec<_>>();
+        let cursor_response = MetaDataCursorResponse {
+            next_cursor: data_with_meta.last().map(|entry| entry.meta.clone()),
+            has_more,
+            total_count: data.len() as u32,
+        };
+        Ok((data_with_meta, cursor_response))
+    }
+
+    async fn list_topics(&self, key: KeySet) -> Result<Vec<TopicEntryCount>, ServerError> {
+        let state = self.state.lock().unwrap();
+        let mut counts = BTreeMap::<String, u64>::new();
+        let pubkey_hex = key.pubkey.to_hex();
+        for ((pubkey, topic), data) in state.historical_data.iter() {
+            if *pubkey == pubkey_hex {
+                *counts.entry(topic.clone()).or_default() += data.len() as u64;
+            }
+        }
+        for (pubkey, topic) in state.snapshots.keys() {
+            if *pubkey == pubkey_hex {
+                *counts.entry(topic.clone()).or_default() += 1;
+            }
+        }
+        Ok(counts
+            .into_iter()
+            .map(|(topic, count)| TopicEntryCount { topic, count })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use intmax2_interfaces::{
+        api::store_vault_server::{
+            interface::{SaveDataEntry, StoreVaultClientInterface},
+            types::{CursorOrder, MetaDataCursor},
+        },
+        data::data_type::DataType,
+        utils::{digest::get_digest, random::default_rng},
+    };
+    use intmax2_zkp::common::signature_content::key_set::KeySet;
+
+    use super::InMemoryStoreVaultClient;
+
+    #[tokio::test]
+    async fn test_snapshot_prev_digest() {
+        let client = InMemoryStoreVaultClient::new();
+        let key = KeySet::rand(&mut default_rng());
+        let topic = DataType::UserData.to_topic();
+
+        client
+            .save_snapshot(key, &topic, None, b"v1")
+            .await
+            .unwrap();
+        // a stale prev_digest is rejected
+        assert!(client
+            .save_snapshot(key, &topic, None, b"v2")
+            .await
+            .is_err());
+        client
+            .save_snapshot(key, &topic, Some(get_digest(b"v1")), b"v2")
+            .await
+            .unwrap();
+        assert_eq!(
+            client.get_snapshot(key, &topic).await.unwrap(),
+            Some(b"v2".to_vec())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_data_sequence_cursor() {
+        let client = InMemoryStoreVaultClient::new();
+        let key = KeySet::rand(&mut default_rng());
+        let topic = DataType::Tx.to_topic();
+        let entries = (0..5u8)
+            .map(|i| SaveDataEntry {
+                topic: topic.clone(),
+                pubkey: key.pubkey,
+                data: vec![i],
+            })
+            .collect::<Vec<_>>();
+        let digests = client.save_data_batch(key, &entries).await.unwrap();
+        // saving the same data again is rejected
+        assert!(client.save_data_batch(key, &entries[..1]).await.is_err());
+
+        let fetched = client
+            .get_data_batch(key, &topic, &digests[..2])
+            .await
+            .unwrap();
+        assert_eq!(fetched.len(), 2);
+        assert_eq!(fetched[0].data, vec![0]);
+
+        for order in [CursorOrder::Asc, CursorOrder::Desc] {
+            let mut cursor = MetaDataCursor {
+                cursor: None,
+                order: order.clone(),
+                limit: Some(2),
+            };
+            let mut metas = Vec::new();
+            loop {
+                let (data, response) = client
+                    .get_data_sequence(key, &topic, &cursor)
+                    .await
+                    .unwrap();
+                assert_eq!(response.total_count, 5);
+                metas.extend(data.into_iter().map(|entry| entry.meta));
+                if !response.has_more {
+                    break;
+                }
+                cursor.cursor = response.next_cursor;
+            }
+            assert_eq!(metas.len(), 5);
+            let mut sorted = metas.clone();
+            sorted.sort();
+            if order == CursorOrder::Desc {
+                sorted.reverse();
+            }
+            assert_eq!(metas, sorted);
+        }
+
+        let topics = client.list_topics(key).await.unwrap();
+        assert_eq!(topics.len(), 1);
+        assert_eq!(topics[0].count, 5);
+    }
+}
@@ -4,26 +4,54 @@ use intmax2_interfaces::api::{
     indexer::interface::{BlockBuilderInfo, IndexerClientInterface},
 };
 
-use super::utils::query::get_request;
+use super::utils::{
+    query::get_request_with_rate_limit,
+    rate_limit::{RateLimitConfig, RateLimiter},
+    retry::RetryConfig,
+};
 
 #[derive(Debug, Clone)]
 pub struct IndexerClient {
     base_url: String,
+    retry_config: RetryConfig,
+    rate_limiter: Option<RateLimiter>,
 }
 
 impl IndexerClient {
     pub fn new(base_url: &str) -> Self {
         IndexerClient {
             base_url: base_url.to_string(),
+            retry_config: RetryConfig::default(),
+            rate_limiter: None,
         }
     }
+
+    /// Use `retry_config` for every request made by this client. Its `max_retries` also bounds
+    /// the retries after 429 Too Many Requests.
+    pub fn with_retry_config(mut self, retry_config: RetryConfig) -> Self {
+        self.retry_config = retry_config;
+        self
+    }
+
+    /// Limit the requests made by this client, including the fee info checks of the block
+    /// builders. Clones of this client share the limit.
+    pub fn with_rate_limit(mut self, rate_limit_config: RateLimitConfig) -> Self {
+        self.rate_limiter = Some(RateLimiter::new(rate_limit_config));
+        self
+    }
 }
 
 #[async_trait(?Send)]
 impl IndexerClientInterface for IndexerClient {
     async fn get_block_builder_info(&self) -> Result<BlockBuilderInfo, ServerError> {
-        let block_builders: Vec<BlockBuilderInfo> =
-            get_request::<(), _>(&self.base_url, "/v1/indexer/builders", None).await?;
+        let block_builders: Vec<BlockBuilderInfo> = get_request_with_rate_limit::<(), _>(
+            &self.base_url,
+            "/v1/indexer/builders",
+            None,
+            &self.retry_config,
+            self.rate_limiter.as_ref(),
+        )
+        .await?;
         if block_builders.is_empty() {
             return Err(ServerError::InvalidResponse(
                 "No block builders found".to_string(),
@@ -36,6 +64,9 @@ impl IndexerClientInterface for IndexerClient {
                 continue; // Skip invalid URLs
             }
             let fee_info_url = format!("{}/block-builder/fee-info", block_builder.url);
+            if let Some(rate_limiter) = &self.rate_limiter {
+                rate_limiter.acquire().await;
+            }
             // Query fee info without retry
             let response = client.get(&fee_info_url).send().await;
             match response {
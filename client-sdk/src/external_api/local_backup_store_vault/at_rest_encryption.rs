@@ -0,0 +1,209 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use aes_gcm::{aead::Aead, AeadCore, Aes256Gcm, Key, KeyInit as _};
+use argon2::{Algorithm, Argon2, Params, Version};
+use rand::{rngs::OsRng, RngCore as _};
+use serde::{Deserialize, Serialize};
+use serde_with::{base64::Base64, serde_as};
+
+use super::error::IOError;
+
+const FORMAT_VERSION: u32 = 1;
+const CIPHER: &str = "aes-256-gcm";
+const KEY_LEN: usize = 32;
+const SALT_LEN: usize = 16;
+
+/// Key derivation parameters recorded in every encrypted file, so that files written with
+/// older parameters stay readable after the defaults change.
+#[serde_as]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KdfParams {
+    pub algorithm: String,
+    pub version: u32,
+    pub memory_cost: u32,
+    pub time_cost: u32,
+    pub parallelism: u32,
+    #[serde_as(as = "Base64")]
+    pub salt: Vec<u8>,
+}
+
+impl KdfParams {
+    fn new_argon2id() -> Self {
+        let mut salt = vec![0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        Self {
+            algorithm: Algorithm::Argon2id.as_str().to_string(),
+            version: Version::V0x13.into(),
+            memory_cost: Params::DEFAULT_M_COST,
+            time_cost: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+            salt,
+        }
+    }
+
+    fn derive_key(&self, passphrase: &str) -> Result<[u8; KEY_LEN], IOError> {
+        let algorithm = Algorithm::new(&self.algorithm)
+            .map_err(|e| IOError::DecryptionError(format!("unsupported kdf: {e}")))?;
+        let version = Version::try_from(self.version)
+            .map_err(|e| IOError::DecryptionError(format!("unsupported kdf version: {e}")))?;
+        let params = Params::new(
+            self.memory_cost,
+            self.time_cost,
+            self.parallelism,
+            Some(KEY_LEN),
+        )
+        .map_err(|e| IOError::DecryptionError(format!("invalid kdf params: {e}")))?;
+        let mut key = [0u8; KEY_LEN];
+        Argon2::new(algorithm, version, params)
+            .hash_password_into(passphrase.as_bytes(), &self.salt, &mut key)
+            .map_err(|e| IOError::DecryptionError(format!("key derivation failed: {e}")))?;
+        Ok(key)
+    }
+}
+
+/// On-disk format of an encrypted file.
+#[serde_as]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EncryptedFile {
+    format_version: u32,
+    cipher: String,
+    kdf: KdfParams,
+    #[serde_as(as = "Base64")]
+    nonce: [u8; 12],
+    #[serde_as(as = "Base64")]
+    ciphertext: Vec<u8>,
+}
+
+/// Encrypts the files of the local store vault with a passphrase-derived key.
+///
+/// New files are written with the KDF params fixed at construction, so the key is derived once.
+/// Keys for the params of files written by other instances are derived on demand and cached.
+#[derive(Clone)]
+pub struct FileCipher {
+    passphrase: Arc<String>,
+    kdf: KdfParams,
+    keys: Arc<Mutex<HashMap<KdfParams, [u8; KEY_LEN]>>>,
+}
+
+impl fmt::Debug for FileCipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FileCipher")
+            .field("kdf", &self.kdf)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FileCipher {
+    pub fn new(passphrase: &str) -> Self {
+        Self {
+            passphrase: Arc::new(passphrase.to_string()),
+            kdf: KdfParams::new_argon2id(),
+            keys: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn key(&self, kdf: &KdfParams) -> Result<[u8; KEY_LEN], IOError> {
+        if let Some(key) = self.keys.lock().unwrap().get(kdf) {
+            return Ok(*key);
+        }
+        let key = kdf.derive_key(&self.passphrase)?;
+        self.keys.lock().unwrap().insert(kdf.clone(), key);
+        Ok(key)
+    }
+
+    /// Encrypt `data` into the text stored on disk.
+    pub fn encrypt(&self, data: &[u8]) -> Result<String, IOError> {
+        let key = self.key(&self.kdf)?;
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, data)
+            .map_err(|e| IOError::EncryptionError(e.to_string()))?;
+        let file = EncryptedFile {
+            format_version: FORMAT_VERSION,
+            cipher: CIPHER.to_string(),
+            kdf: self.kdf.clone(),
+            nonce: nonce.into(),
+            ciphertext,
+        };
+        serde_json::to_string(&file).map_err(|e| IOError::SerializeError(e.to_string()))
+    }
+
+    /// Decrypt the text of an encrypted file.
+    pub fn decrypt(&self, content: &str) -> Result<Vec<u8>, IOError> {
+        let file: EncryptedFile =
+            serde_json::from_str(content).map_err(|e| IOError::ParseError(e.to_string()))?;
+        if file.format_version != FORMAT_VERSION || file.cipher != CIPHER {
+            return Err(IOError::DecryptionError(format!(
+                "unsupported format version {} with cipher {}",
+                file.format_version, file.cipher
+            )));
+        }
+        let key = self.key(&file.kdf)?;
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+        cipher
+            .decrypt(&file.nonce.into(), file.ciphertext.as_ref())
+            .map_err(|_| IOError::DecryptionError("wrong passphrase or corrupted file".to_string()))
+    }
+}
+
+/// Returns true if the file content is in the encrypted format. Plaintext files are base64 or
+/// csv, which never start with `{`.
+pub fn is_encrypted(content: &str) -> bool {
+    content.trim_start().starts_with('{')
+}
+
+/// Decode the content of a file written by a client with or without a cipher.
+pub fn open_file(cipher: Option<&FileCipher>, content: &str) -> Result<Option<Vec<u8>>, IOError> {
+    if !is_encrypted(content) {
+        return Ok(None);
+    }
+    let cipher = cipher.ok_or(IOError::DecryptionError(
+        "file is encrypted but no passphrase is given".to_string(),
+    ))?;
+    cipher.decrypt(content).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fast_cipher(passphrase: &str) -> FileCipher {
+        let mut cipher = FileCipher::new(passphrase);
+        // keep the tests fast
+        cipher.kdf.memory_cost = Params::MIN_M_COST * 8;
+        cipher.kdf.time_cost = 1;
+        cipher
+    }
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let cipher = fast_cipher("passphrase");
+        let content = cipher.encrypt(b"hello").unwrap();
+        assert!(is_encrypted(&content));
+        assert_eq!(cipher.decrypt(&content).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn test_decrypt_with_recorded_kdf_params() {
+        // a file written with other kdf params is readable with the same passphrase
+        let writer = fast_cipher("passphrase");
+        let content = writer.encrypt(b"hello").unwrap();
+        let reader = fast_cipher("passphrase");
+        assert_ne!(writer.kdf, reader.kdf);
+        assert_eq!(reader.decrypt(&content).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn test_decrypt_with_wrong_passphrase() {
+        let content = fast_cipher("passphrase").encrypt(b"hello").unwrap();
+        assert!(fast_cipher("wrong").decrypt(&content).is_err());
+        assert!(open_file(None, &content).is_err());
+    }
+}
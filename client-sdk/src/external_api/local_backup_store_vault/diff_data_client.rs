@@ -26,7 +26,11 @@ impl DiffDataClient {
     pub fn read(&self, file_path: &Path) -> Result<Vec<DiffRecord>, IOError> {
         let file_content =
             std::fs::read_to_string(file_path).map_err(|e| IOError::ReadError(e.to_string()))?;
-        let mut reader = csv::Reader::from_reader(file_content.as_bytes());
+        self.parse(&file_content)
+    }
+
+    pub fn parse(&self, csv_content: &str) -> Result<Vec<DiffRecord>, IOError> {
+        let mut reader = csv::Reader::from_reader(csv_content.as_bytes());
         let mut records = Vec::new();
         for result in reader.deserialize() {
             let record: DiffRecord = result.map_err(|e| IOError::ParseError(e.to_string()))?;
@@ -34,6 +38,36 @@ impl DiffDataClient {
         }
         Ok(records)
     }
+
+    /// Reads the records one by one. Unlike `read`, a row that fails to parse does not fail the
+    /// whole file, and is returned as an error at its position instead.
+    pub fn read_each(&self, file_path: &Path) -> Result<Vec<Result<DiffRecord, IOError>>, IOError> {
+        let file_content =
+            std::fs::read_to_string(file_path).map_err(|e| IOError::ReadError(e.to_string()))?;
+        Ok(self.parse_each(&file_content))
+    }
+
+    pub fn parse_each(&self, csv_content: &str) -> Vec<Result<DiffRecord, IOError>> {
+        let mut reader = csv::Reader::from_reader(csv_content.as_bytes());
+        reader
+            .deserialize()
+            .map(|result| result.map_err(|e| IOError::ParseError(e.to_string())))
+            .collect()
+    }
+}
+
+impl DiffRecord {
+    /// Checks that the digest matches the data.
+    pub fn verify(&self) -> Result<(), IOError> {
+        let digest = get_digest(&self.data);
+        if digest != self.digest {
+            return Err(IOError::ParseError(format!(
+                "digest mismatch: expected {}, got {}",
+                self.digest, digest
+            )));
+        }
+        Ok(())
+    }
 }
 
 pub fn make_backup_csv_from_entries(entries: &[SaveDataEntry]) -> Result<String, IOError> {
@@ -12,6 +12,10 @@ pub enum IOError {
     ParseError(String),
     #[error("Serialize error: {0}")]
     SerializeError(String),
+    #[error("Encryption error: {0}")]
+    EncryptionError(String),
+    #[error("Decryption error: {0}")]
+    DecryptionError(String),
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -3,16 +3,29 @@ use std::{fs, path::PathBuf};
 use base64::{prelude::BASE64_STANDARD, Engine};
 use intmax2_zkp::ethereum_types::{bytes32::Bytes32, u256::U256, u32limb_trait::U32LimbTrait};
 
-use super::error::IOError;
+use super::{
+    at_rest_encryption::{open_file, FileCipher},
+    error::IOError,
+};
 
 #[derive(Clone, Debug)]
 pub struct LocalDataClient {
     pub root_path: PathBuf,
+    cipher: Option<FileCipher>,
 }
 
 impl LocalDataClient {
     pub fn new(root_path: PathBuf) -> Self {
-        LocalDataClient { root_path }
+        LocalDataClient {
+            root_path,
+            cipher: None,
+        }
+    }
+
+    /// Encrypt the written files with `cipher`.
+    pub fn with_cipher(mut self, cipher: FileCipher) -> Self {
+        self.cipher = Some(cipher);
+        self
     }
 
     fn dir_path(&self, topic: &str, pubkey: U256) -> PathBuf {
@@ -41,6 +54,9 @@ impl LocalDataClient {
         }
         let data_base64 =
             fs::read_to_string(file_path).map_err(|e| IOError::ReadError(e.to_string()))?;
+        if let Some(data) = open_file(self.cipher.as_ref(), &data_base64)? {
+            return Ok(Some(data));
+        }
         let data = BASE64_STANDARD
             .decode(&data_base64)
             .map_err(|e| IOError::ReadError(e.to_string()))?;
@@ -63,8 +79,11 @@ impl LocalDataClient {
             // If the file already exists, we do not overwrite it.
             return Ok(());
         }
-        let data_base64 = BASE64_STANDARD.encode(data);
-        fs::write(file_path, data_base64).map_err(|e| IOError::WriteError(e.to_string()))?;
+        let content = match &self.cipher {
+            Some(cipher) => cipher.encrypt(data)?,
+            None => BASE64_STANDARD.encode(data),
+        };
+        fs::write(file_path, content).map_err(|e| IOError::WriteError(e.to_string()))?;
         Ok(())
     }
 
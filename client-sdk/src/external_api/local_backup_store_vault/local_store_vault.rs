@@ -1,6 +1,9 @@
 use super::{
-    diff_data_client::DiffDataClient, error::LocalStoreVaultError,
-    local_data_client::LocalDataClient, metadata_client::MetaDataClient,
+    at_rest_encryption::FileCipher,
+    diff_data_client::{DiffDataClient, DiffRecord},
+    error::{IOError, LocalStoreVaultError},
+    local_data_client::LocalDataClient,
+    metadata_client::MetaDataClient,
 };
 use async_trait::async_trait;
 use intmax2_interfaces::{
@@ -8,7 +11,10 @@ use intmax2_interfaces::{
         error::ServerError,
         store_vault_server::{
             interface::{SaveDataEntry, StoreVaultClientInterface},
-            types::{CursorOrder, DataWithMetaData, MetaDataCursor, MetaDataCursorResponse},
+            types::{
+                CursorOrder, DataWithMetaData, MetaDataCursor, MetaDataCursorResponse,
+                TopicEntryCount,
+            },
         },
     },
     data::meta_data::MetaData,
@@ -20,6 +26,12 @@ use intmax2_zkp::{
 };
 use std::path::{Path, PathBuf};
 
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IncorporateReport {
+    pub imported: usize,
+    pub skipped: usize,
+}
+
 #[derive(Clone, Debug)]
 pub struct LocalStoreVaultClient {
     pub data_client: LocalDataClient,
@@ -35,6 +47,17 @@ impl LocalStoreVaultClient {
             diff_data_client: DiffDataClient,
         }
     }
+
+    /// Same as `new`, but every file is encrypted at rest with a key derived from `passphrase`.
+    /// Plaintext files written before enabling the encryption remain readable.
+    pub fn new_encrypted(root_path: PathBuf, passphrase: &str) -> Self {
+        let cipher = FileCipher::new(passphrase);
+        LocalStoreVaultClient {
+            data_client: LocalDataClient::new(root_path.clone()).with_cipher(cipher.clone()),
+            metadata_client: MetaDataClient::new(root_path).with_cipher(cipher),
+            diff_data_client: DiffDataClient,
+        }
+    }
 }
 
 impl LocalStoreVaultClient {
@@ -210,6 +233,14 @@ impl LocalStoreVaultClient {
         Ok((data_with_meta, next_cursor))
     }
 
+    pub fn local_list_topics(
+        &self,
+        pubkey: U256,
+    ) -> Result<Vec<TopicEntryCount>, LocalStoreVaultError> {
+        let topics = self.metadata_client.topics(pubkey)?;
+        Ok(topics)
+    }
+
     pub fn incorporate_diff(&self, diff_file_path: &Path) -> Result<(), LocalStoreVaultError> {
         let records = self.diff_data_client.read(diff_file_path)?;
         log::info!(
@@ -217,22 +248,89 @@ impl LocalStoreVaultClient {
             diff_file_path.display(),
             records.len()
         );
+        self.incorporate_records(records)
+    }
+
+    /// Same as `incorporate_diff`, but takes the CSV content instead of a file path.
+    pub fn incorporate_diff_content(&self, csv_content: &str) -> Result<(), LocalStoreVaultError> {
+        let records = self.diff_data_client.parse(csv_content)?;
+        log::info!("Incorporating diff with {} records", records.len());
+        self.incorporate_records(records)
+    }
+
+    /// Incorporates the records of the diff file one at a time. Records that fail to parse or
+    /// verify are logged and skipped, while the valid ones are still incorporated.
+    pub fn incorporate_diff_incremental(
+        &self,
+        diff_file_path: &Path,
+    ) -> Result<IncorporateReport, LocalStoreVaultError> {
+        let records = self.diff_data_client.read_each(diff_file_path)?;
+        log::info!(
+            "Incorporating diff file: {} with {} records",
+            diff_file_path.display(),
+            records.len()
+        );
+        self.incorporate_records_incremental(records)
+    }
+
+    /// Same as `incorporate_diff_incremental`, but takes the CSV content instead of a file path.
+    pub fn incorporate_diff_content_incremental(
+        &self,
+        csv_content: &str,
+    ) -> Result<IncorporateReport, LocalStoreVaultError> {
+        let records = self.diff_data_client.parse_each(csv_content);
+        log::info!("Incorporating diff with {} records", records.len());
+        self.incorporate_records_incremental(records)
+    }
+
+    /// Verifies every record before writing any, so that a corrupt record rejects the whole diff.
+    fn incorporate_records(&self, records: Vec<DiffRecord>) -> Result<(), LocalStoreVaultError> {
+        for (i, record) in records.iter().enumerate() {
+            record.verify().map_err(|e| {
+                LocalStoreVaultError::IOError(IOError::ParseError(format!("record #{i}: {e}")))
+            })?;
+        }
         for record in records {
-            self.data_client.write(
-                &record.topic,
-                record.pubkey.into(),
-                record.digest,
-                &record.data,
-            )?;
-            self.metadata_client.append(
-                &record.topic,
-                record.pubkey.into(),
-                &[MetaData {
-                    timestamp: record.timestamp,
-                    digest: record.digest,
-                }],
-            )?;
+            self.incorporate_record(&record)?;
+        }
+        Ok(())
+    }
+
+    fn incorporate_records_incremental(
+        &self,
+        records: Vec<Result<DiffRecord, IOError>>,
+    ) -> Result<IncorporateReport, LocalStoreVaultError> {
+        let mut report = IncorporateReport::default();
+        for (i, record) in records.into_iter().enumerate() {
+            let record = match record.and_then(|record| record.verify().map(|_| record)) {
+                Ok(record) => record,
+                Err(e) => {
+                    log::warn!("Skipping corrupt record #{i}: {e}");
+                    report.skipped += 1;
+                    continue;
+                }
+            };
+            self.incorporate_record(&record)?;
+            report.imported += 1;
         }
+        Ok(report)
+    }
+
+    fn incorporate_record(&self, record: &DiffRecord) -> Result<(), LocalStoreVaultError> {
+        self.data_client.write(
+            &record.topic,
+            record.pubkey.into(),
+            record.digest,
+            &record.data,
+        )?;
+        self.metadata_client.append(
+            &record.topic,
+            record.pubkey.into(),
+            &[MetaData {
+                timestamp: record.timestamp,
+                digest: record.digest,
+            }],
+        )?;
         Ok(())
     }
 
@@ -329,4 +427,89 @@ impl StoreVaultClientInterface for LocalStoreVaultClient {
             self.local_get_data_sequence(auth.pubkey, topic, cursor)?;
         Ok((data_with_meta, cursor_response))
     }
+
+    async fn list_topics(&self, key: KeySet) -> Result<Vec<TopicEntryCount>, ServerError> {
+        let topics = self.local_list_topics(key.pubkey)?;
+        Ok(topics)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::external_api::local_backup_store_vault::diff_data_client::make_backup_csv_from_records;
+
+    #[test]
+    fn test_incorporate_diff_incremental() {
+        let root_path = PathBuf::from("test_data/incorporate_diff_incremental");
+        let client = LocalStoreVaultClient::new(root_path.clone());
+
+        let valid_data = b"valid data".to_vec();
+        let valid = DiffRecord {
+            topic: "test_topic".to_string(),
+            pubkey: U256::from(1).into(),
+            digest: get_digest(&valid_data),
+            timestamp: 1,
+            data: valid_data,
+        };
+        let corrupt = DiffRecord {
+            topic: "test_topic".to_string(),
+            pubkey: U256::from(1).into(),
+            digest: get_digest(b"other data"),
+            timestamp: 2,
+            data: b"corrupt data".to_vec(),
+        };
+        let mut csv = make_backup_csv_from_records(&[valid, corrupt]).unwrap();
+        csv.push_str("not,a,valid,row\n");
+        std::fs::create_dir_all(&root_path).unwrap();
+        let diff_file_path = root_path.join("diff.csv");
+        std::fs::write(&diff_file_path, csv).unwrap();
+
+        // strict mode rejects the file as a whole
+        assert!(client.incorporate_diff(&diff_file_path).is_err());
+
+        let report = client
+            .incorporate_diff_incremental(&diff_file_path)
+            .unwrap();
+        assert_eq!(
+            report,
+            IncorporateReport {
+                imported: 1,
+                skipped: 2,
+            }
+        );
+        std::fs::remove_dir_all(&root_path).unwrap();
+    }
+
+    #[test]
+    fn test_incorporate_diff_strict_rejects_tampered_digest() {
+        let root_path = PathBuf::from("test_data/incorporate_diff_strict");
+        let client = LocalStoreVaultClient::new(root_path.clone());
+
+        let make_record = |i: u64| {
+            let data = format!("data {i}").into_bytes();
+            DiffRecord {
+                topic: "test_topic".to_string(),
+                pubkey: U256::from(1).into(),
+                digest: get_digest(&data),
+                timestamp: i,
+                data,
+            }
+        };
+        let records = (1..=3).map(make_record).collect::<Vec<_>>();
+        let mut tampered = (1..=3).map(make_record).collect::<Vec<_>>();
+        tampered[2].digest = get_digest(b"tampered");
+        let csv = make_backup_csv_from_records(&tampered).unwrap();
+
+        // the valid records before the tampered one are not written either
+        assert!(client.incorporate_diff_content(&csv).is_err());
+        assert!(client.local_list_topics(U256::from(1)).unwrap().is_empty());
+
+        let csv = make_backup_csv_from_records(&records).unwrap();
+        client.incorporate_diff_content(&csv).unwrap();
+        let topics = client.local_list_topics(U256::from(1)).unwrap();
+        assert_eq!(topics.len(), 1);
+        assert_eq!(topics[0].count, 3);
+        std::fs::remove_dir_all(&root_path).unwrap();
+    }
 }
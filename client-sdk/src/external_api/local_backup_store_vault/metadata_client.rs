@@ -1,19 +1,34 @@
 use csv;
-use intmax2_interfaces::data::meta_data::MetaData;
+use intmax2_interfaces::{
+    api::store_vault_server::types::TopicEntryCount, data::meta_data::MetaData,
+};
 use intmax2_zkp::ethereum_types::{u256::U256, u32limb_trait::U32LimbTrait};
 use itertools::Itertools;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use super::error::IOError;
+use super::{
+    at_rest_encryption::{open_file, FileCipher},
+    error::IOError,
+};
 
 #[derive(Clone, Debug)]
 pub struct MetaDataClient {
     root_path: PathBuf,
+    cipher: Option<FileCipher>,
 }
 
 impl MetaDataClient {
     pub fn new(root_path: PathBuf) -> Self {
-        MetaDataClient { root_path }
+        MetaDataClient {
+            root_path,
+            cipher: None,
+        }
+    }
+
+    /// Encrypt the written files with `cipher`.
+    pub fn with_cipher(mut self, cipher: FileCipher) -> Self {
+        self.cipher = Some(cipher);
+        self
     }
 
     fn dir_path(&self, topic: &str, pubkey: U256) -> PathBuf {
@@ -39,6 +54,12 @@ impl MetaDataClient {
         }
         let file_content =
             std::fs::read_to_string(&file_path).map_err(|e| IOError::ReadError(e.to_string()))?;
+        let file_content = match open_file(self.cipher.as_ref(), &file_content)? {
+            Some(decrypted) => {
+                String::from_utf8(decrypted).map_err(|e| IOError::ParseError(e.to_string()))?
+            }
+            None => file_content,
+        };
         let mut reader = csv::Reader::from_reader(file_content.as_bytes());
         let mut records = Vec::new();
         for result in reader.deserialize() {
@@ -61,6 +82,55 @@ impl MetaDataClient {
         Ok(())
     }
 
+    /// Returns the topics that have a metadata file for `pubkey`, with their number of
+    /// records, sorted by topic. Every saved version of a snapshot is counted.
+    pub fn topics(&self, pubkey: U256) -> Result<Vec<TopicEntryCount>, IOError> {
+        let mut topics = Vec::new();
+        if self.root_path.is_dir() {
+            self.collect_topics(&self.root_path, &pubkey.to_hex(), &mut topics)?;
+        }
+        let mut entries = Vec::new();
+        for topic in topics {
+            let count = self.read(&topic, pubkey)?.len() as u64;
+            entries.push(TopicEntryCount { topic, count });
+        }
+        entries.sort_by(|a, b| a.topic.cmp(&b.topic));
+        Ok(entries)
+    }
+
+    // Walks `dir` and collects the topics (paths relative to the root) that contain a
+    // `<pubkey_hex>/metadata.csv` file.
+    fn collect_topics(
+        &self,
+        dir: &Path,
+        pubkey_hex: &str,
+        topics: &mut Vec<String>,
+    ) -> Result<(), IOError> {
+        let read_dir = std::fs::read_dir(dir).map_err(|e| IOError::ReadError(e.to_string()))?;
+        for entry in read_dir {
+            let path = entry.map_err(|e| IOError::ReadError(e.to_string()))?.path();
+            if !path.is_dir() {
+                continue;
+            }
+            if path.file_name().and_then(|name| name.to_str()) == Some(pubkey_hex)
+                && path.join("metadata.csv").is_file()
+            {
+                if let Ok(relative) = dir.strip_prefix(&self.root_path) {
+                    let topic = relative
+                        .components()
+                        .map(|c| c.as_os_str().to_string_lossy())
+                        .join("/");
+                    if !topic.is_empty() {
+                        topics.push(topic);
+                    }
+                }
+                continue;
+            }
+            self.collect_topics(&path, pubkey_hex, topics)?;
+        }
+        Ok(())
+    }
+
     fn write(&self, topic: &str, pubkey: U256, records: &[MetaData]) -> Result<(), IOError> {
         let dir_path = self.dir_path(topic, pubkey);
         if !dir_path.exists() {
@@ -77,7 +147,11 @@ impl MetaDataClient {
         let csv_bytes = writer
             .into_inner()
             .map_err(|e| IOError::WriteError(e.to_string()))?;
-        std::fs::write(&file_path, &csv_bytes).map_err(|e| IOError::WriteError(e.to_string()))?;
+        let content = match &self.cipher {
+            Some(cipher) => cipher.encrypt(&csv_bytes)?.into_bytes(),
+            None => csv_bytes,
+        };
+        std::fs::write(&file_path, &content).map_err(|e| IOError::WriteError(e.to_string()))?;
         Ok(())
     }
 }
@@ -101,4 +175,42 @@ mod tests {
         let meta = MetaData { digest, timestamp };
         client.append(topic, pubkey, &[meta]).unwrap();
     }
+
+    #[test]
+    fn test_metadata_client_topics() {
+        let root_path = PathBuf::from("test_data/metadata_client_topics");
+        let client = MetaDataClient::new(root_path.clone());
+
+        let pubkey = U256::from(1);
+        let meta = |i: u64| MetaData {
+            digest: Bytes32::from_hex(&format!("{i:#x}")).unwrap(),
+            timestamp: i,
+        };
+        client
+            .append("v1/aw/deposit", pubkey, &[meta(1), meta(2)])
+            .unwrap();
+        client
+            .append("v1/aw/user_data", pubkey, &[meta(3)])
+            .unwrap();
+        client
+            .append("v1/aw/deposit", U256::from(2), &[meta(4)])
+            .unwrap();
+
+        let topics = client.topics(pubkey).unwrap();
+        assert_eq!(
+            topics,
+            vec![
+                TopicEntryCount {
+                    topic: "v1/aw/deposit".to_string(),
+                    count: 2,
+                },
+                TopicEntryCount {
+                    topic: "v1/aw/user_data".to_string(),
+                    count: 1,
+                },
+            ]
+        );
+        assert!(client.topics(U256::from(3)).unwrap().is_empty());
+        std::fs::remove_dir_all(&root_path).unwrap();
+    }
 }
@@ -1,12 +1,13 @@
 use std::{path::PathBuf, sync::Arc};
 
 use async_trait::async_trait;
+use error::LocalStoreVaultError;
 use intmax2_interfaces::{
     api::{
         error::ServerError,
         store_vault_server::{
             interface::{SaveDataEntry, StoreVaultClientInterface},
-            types::{DataWithMetaData, MetaDataCursor, MetaDataCursorResponse},
+            types::{DataWithMetaData, MetaDataCursor, MetaDataCursorResponse, TopicEntryCount},
         },
     },
     data::meta_data::MetaData,
@@ -15,16 +16,72 @@ use intmax2_interfaces::{
 use intmax2_zkp::{common::signature_content::key_set::KeySet, ethereum_types::bytes32::Bytes32};
 use local_store_vault::LocalStoreVaultClient;
 
+pub mod at_rest_encryption;
 pub mod diff_data_client;
 pub mod error;
 pub mod local_data_client;
 pub mod local_store_vault;
 pub mod metadata_client;
 
+/// Which snapshot `get_snapshot` keeps when the remote snapshot differs from the latest local
+/// one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Always keep the remote snapshot.
+    PreferRemote,
+    /// Always keep the local snapshot if there is one.
+    PreferLocal,
+    /// Keep the local snapshot if the remote one is a version that was recorded locally before
+    /// the latest local one (e.g. the remote was rolled back). Otherwise keep the remote one.
+    #[default]
+    PreferNewest,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SnapshotSide {
+    Local,
+    Remote,
+}
+
+/// Decide which side wins a snapshot conflict. `local_history` is the local metadata of the
+/// topic, and `remote_digest` is the digest of the remote snapshot, if any.
+fn resolve_snapshot_conflict(
+    policy: ConflictPolicy,
+    local_history: &[MetaData],
+    remote_digest: Option<Bytes32>,
+) -> SnapshotSide {
+    let Some(local_latest) = local_history.iter().max() else {
+        return SnapshotSide::Remote;
+    };
+    let Some(remote_digest) = remote_digest else {
+        return match policy {
+            ConflictPolicy::PreferRemote => SnapshotSide::Remote,
+            ConflictPolicy::PreferLocal | ConflictPolicy::PreferNewest => SnapshotSide::Local,
+        };
+    };
+    match policy {
+        ConflictPolicy::PreferRemote => SnapshotSide::Remote,
+        ConflictPolicy::PreferLocal => SnapshotSide::Local,
+        ConflictPolicy::PreferNewest => {
+            // a remote digest unknown locally has not been seen yet, so it is the newer one
+            let remote_timestamp = local_history
+                .iter()
+                .filter(|m| m.digest == remote_digest)
+                .map(|m| m.timestamp)
+                .max();
+            match remote_timestamp {
+                Some(timestamp) if timestamp < local_latest.timestamp => SnapshotSide::Local,
+                _ => SnapshotSide::Remote,
+            }
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct LocalBackupStoreVaultClient {
     pub store_vault: Arc<Box<dyn StoreVaultClientInterface>>,
     pub local_store_vault: LocalStoreVaultClient,
+    pub conflict_policy: ConflictPolicy,
 }
 
 impl LocalBackupStoreVaultClient {
@@ -32,8 +89,28 @@ impl LocalBackupStoreVaultClient {
         LocalBackupStoreVaultClient {
             store_vault,
             local_store_vault: LocalStoreVaultClient::new(root_path),
+            conflict_policy: ConflictPolicy::default(),
+        }
+    }
+
+    /// Same as `new`, but the local backup files are encrypted with a key derived from
+    /// `passphrase`.
+    pub fn new_encrypted(
+        store_vault: Arc<Box<dyn StoreVaultClientInterface>>,
+        root_path: PathBuf,
+        passphrase: &str,
+    ) -> Self {
+        LocalBackupStoreVaultClient {
+            store_vault,
+            local_store_vault: LocalStoreVaultClient::new_encrypted(root_path, passphrase),
+            conflict_policy: ConflictPolicy::default(),
         }
     }
+
+    pub fn with_conflict_policy(mut self, conflict_policy: ConflictPolicy) -> Self {
+        self.conflict_policy = conflict_policy;
+        self
+    }
 }
 
 #[async_trait(?Send)]
@@ -60,21 +137,47 @@ impl StoreVaultClientInterface for LocalBackupStoreVaultClient {
 
     async fn get_snapshot(&self, key: KeySet, topic: &str) -> Result<Option<Vec<u8>>, ServerError> {
         let data = self.store_vault.get_snapshot(key, topic).await?;
-        if let Some(data) = &data {
-            let digest = get_digest(data);
-            let local_prev_digest = self
-                .local_store_vault
-                .local_get_prev_snapshot_digest(key, topic)?;
-            if local_prev_digest != Some(digest) {
-                // save the data to local store vault
-                let meta = MetaData {
-                    timestamp: chrono::Utc::now().timestamp() as u64,
-                    digest,
-                };
-                self.local_store_vault
-                    .local_save_snapshot(key.pubkey, topic, data, &meta)?;
+        let remote_digest = data.as_deref().map(get_digest);
+        let local_history = self
+            .local_store_vault
+            .metadata_client
+            .read(topic, key.pubkey)
+            .map_err(LocalStoreVaultError::from)?;
+        let local_digest = local_history.iter().max().map(|m| m.digest);
+        if local_digest == remote_digest {
+            return Ok(data);
+        }
+        if let Some(local_digest) = local_digest {
+            let winner =
+                resolve_snapshot_conflict(self.conflict_policy, &local_history, remote_digest);
+            log::warn!(
+                "Snapshot conflict on topic {topic}: local digest {local_digest}, remote digest {}. Keeping the {} snapshot ({:?})",
+                remote_digest.map_or("none".to_string(), |d| d.to_string()),
+                match winner {
+                    SnapshotSide::Local => "local",
+                    SnapshotSide::Remote => "remote",
+                },
+                self.conflict_policy,
+            );
+            if winner == SnapshotSide::Local {
+                let local_data = self
+                    .local_store_vault
+                    .local_get_snapshot(key.pubkey, topic)?;
+                if local_data.is_some() {
+                    return Ok(local_data);
+                }
+                log::warn!("Local snapshot data of topic {topic} is missing. Using the remote one");
             }
         }
+        if let (Some(data), Some(digest)) = (&data, remote_digest) {
+            // save the data to local store vault
+            let meta = MetaData {
+                timestamp: chrono::Utc::now().timestamp() as u64,
+                digest,
+            };
+            self.local_store_vault
+                .local_save_snapshot(key.pubkey, topic, data, &meta)?;
+        }
         Ok(data)
     }
 
@@ -180,4 +283,59 @@ impl StoreVaultClientInterface for LocalBackupStoreVaultClient {
             .local_save_data_batch(&entries_with_meta)?;
         Ok((data_with_meta, next_cursor))
     }
+
+    /// Lists the topics in the local backup, e.g. to see what a partial restore brought back.
+    /// The remote store vault is not queried.
+    async fn list_topics(&self, key: KeySet) -> Result<Vec<TopicEntryCount>, ServerError> {
+        let topics = self.local_store_vault.local_list_topics(key.pubkey)?;
+        Ok(topics)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use intmax2_zkp::ethereum_types::u32limb_trait::U32LimbTrait as _;
+
+    fn meta(timestamp: u64, digest: Bytes32) -> MetaData {
+        MetaData { timestamp, digest }
+    }
+
+    #[test]
+    fn test_resolve_snapshot_conflict() {
+        let mut rng = rand::thread_rng();
+        let old = Bytes32::rand(&mut rng);
+        let latest = Bytes32::rand(&mut rng);
+        let unknown = Bytes32::rand(&mut rng);
+        let history = vec![meta(1, old), meta(2, latest)];
+
+        // the remote was rolled back to a version older than the local one
+        assert_eq!(
+            resolve_snapshot_conflict(ConflictPolicy::PreferNewest, &history, Some(old)),
+            SnapshotSide::Local
+        );
+        // the remote has a version not seen locally
+        assert_eq!(
+            resolve_snapshot_conflict(ConflictPolicy::PreferNewest, &history, Some(unknown)),
+            SnapshotSide::Remote
+        );
+        assert_eq!(
+            resolve_snapshot_conflict(ConflictPolicy::PreferRemote, &history, Some(old)),
+            SnapshotSide::Remote
+        );
+        assert_eq!(
+            resolve_snapshot_conflict(ConflictPolicy::PreferLocal, &history, Some(unknown)),
+            SnapshotSide::Local
+        );
+        // the remote lost the snapshot
+        assert_eq!(
+            resolve_snapshot_conflict(ConflictPolicy::PreferNewest, &history, None),
+            SnapshotSide::Local
+        );
+        // nothing to keep locally
+        assert_eq!(
+            resolve_snapshot_conflict(ConflictPolicy::PreferLocal, &[], Some(unknown)),
+            SnapshotSide::Remote
+        );
+    }
 }
@@ -1,6 +1,7 @@
 pub mod balance_prover;
 pub mod block_builder;
 pub mod contract;
+pub mod in_memory_store_vault;
 pub mod indexer;
 pub mod local_backup_store_vault;
 pub mod predicate;
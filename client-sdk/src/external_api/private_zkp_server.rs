@@ -41,7 +41,10 @@ use serde::{Deserialize, Serialize};
 
 use crate::external_api::utils::time::sleep_for;
 
-use super::utils::query::{get_request, post_request};
+use super::utils::{
+    query::{get_request_with_retry, post_request_with_retry},
+    retry::RetryConfig,
+};
 
 type F = GoldilocksField;
 type C = PoseidonGoldilocksConfig;
@@ -58,6 +61,7 @@ pub struct PrivateZKPServerClient {
     base_url: String,
 
     config: PrivateZKPServerConfig,
+    retry_config: RetryConfig,
 
     // rsa public key is used to encrypt the prove request
     // because async OnceLock is not stable, we use RwLock + Option instead
@@ -69,10 +73,17 @@ impl PrivateZKPServerClient {
         PrivateZKPServerClient {
             base_url: base_url.to_string(),
             config: config.clone(),
+            retry_config: RetryConfig::default(),
             pubkey: Arc::new(RwLock::new(None)),
         }
     }
 
+    /// Use `retry_config` for every request made by this client.
+    pub fn with_retry_config(mut self, retry_config: RetryConfig) -> Self {
+        self.retry_config = retry_config;
+        self
+    }
+
     pub async fn get_pubkey(&self) -> Result<RsaPublicKey, ServerError> {
         let is_pubkey_set = self.pubkey.read().unwrap().is_some();
         if !is_pubkey_set {
@@ -83,8 +94,13 @@ impl PrivateZKPServerClient {
     }
 
     async fn fetch_pubkey(&self) -> Result<RsaPublicKey, ServerError> {
-        let response: GetPublicKeyResponse =
-            get_request::<(), _>(&self.base_url, "/v1/public-key", None).await?;
+        let response: GetPublicKeyResponse = get_request_with_retry::<(), _>(
+            &self.base_url,
+            "/v1/public-key",
+            None,
+            &self.retry_config,
+        )
+        .await?;
         let public_key_bytes = BASE64_STANDARD.decode(&response.public_key).map_err(|e| {
             ServerError::DeserializationError(format!("Failed to decode public key: {e:?}"))
         })?;
@@ -97,6 +113,22 @@ impl PrivateZKPServerClient {
 
 #[async_trait(?Send)]
 impl BalanceProverClientInterface for PrivateZKPServerClient {
+    fn endpoint(&self) -> String {
+        self.base_url.clone()
+    }
+
+    async fn health(&self) -> Result<(), ServerError> {
+        // the public key endpoint is served whenever the server is up
+        let no_retry = RetryConfig {
+            max_retries: 0,
+            ..self.retry_config
+        };
+        let _: GetPublicKeyResponse =
+            get_request_with_retry::<(), _>(&self.base_url, "/v1/public-key", None, &no_retry)
+                .await?;
+        Ok(())
+    }
+
     async fn prove_spent(
         &self,
         key: KeySet,
@@ -278,8 +310,13 @@ impl PrivateZKPServerClient {
             ServerError::SerializeError(format!("Failed to serialize encrypted request: {e:?}"))
         })?;
         let request = CreateProveRequest { encrypted_data };
-        let response: CreateProofResponse =
-            post_request(&self.base_url, "/v1/proof/create", Some(&request)).await?;
+        let response: CreateProofResponse = post_request_with_retry(
+            &self.base_url,
+            "/v1/proof/create",
+            Some(&request),
+            &self.retry_config,
+        )
+        .await?;
         Ok(response.request_id)
     }
 
@@ -290,8 +327,13 @@ impl PrivateZKPServerClient {
         let query = ProofResultQuery {
             request_id: request_id.to_string(),
         };
-        let response: ProofResultResponse =
-            get_request(&self.base_url, "/v1/proof/result", Some(&query)).await?;
+        let response: ProofResultResponse = get_request_with_retry(
+            &self.base_url,
+            "/v1/proof/result",
+            Some(&query),
+            &self.retry_config,
+        )
+        .await?;
         Ok(response)
     }
 
@@ -331,7 +373,7 @@ impl PrivateZKPServerClient {
             }
 
             if retries >= self.config.max_retries {
-                return Err(ServerError::UnknownError(format!(
+                return Err(ServerError::Timeout(format!(
                     "Failed to get proof after {} retries",
                     self.config.max_retries
                 )));
@@ -3,14 +3,19 @@ use intmax2_interfaces::{
     api::{
         error::ServerError,
         s3_store_vault::types::{
-            S3GetDataBatchRequest, S3GetDataBatchResponse, S3GetDataSequenceRequest,
-            S3GetDataSequenceResponse, S3GetSnapshotRequest, S3GetSnapshotResponse,
-            S3PreSaveSnapshotRequest, S3PreSaveSnapshotResponse, S3SaveDataBatchRequest,
-            S3SaveDataBatchResponse, S3SaveDataEntry, S3SaveSnapshotRequest,
+            S3CompletedMultipartUpload, S3CompletedPart, S3GetDataBatchRequest,
+            S3GetDataBatchResponse, S3GetDataSequenceRequest, S3GetDataSequenceResponse,
+            S3GetSnapshotRequest, S3GetSnapshotResponse, S3ListTopicsRequest, S3ListTopicsResponse,
+            S3MultipartUpload, S3PreSaveSnapshotRequest, S3PreSaveSnapshotResponse,
+            S3SaveDataBatchRequest, S3SaveDataBatchResponse, S3SaveDataEntry,
+            S3SaveSnapshotRequest,
         },
         store_vault_server::{
             interface::{SaveDataEntry, StoreVaultClientInterface, MAX_BATCH_SIZE},
-            types::{CursorOrder, DataWithMetaData, MetaDataCursor, MetaDataCursorResponse},
+            types::{
+                CursorOrder, DataWithMetaData, MetaDataCursor, MetaDataCursorResponse,
+                TopicEntryCount,
+            },
         },
     },
     utils::{
@@ -20,22 +25,36 @@ use intmax2_interfaces::{
 };
 use intmax2_zkp::{common::signature_content::key_set::KeySet, ethereum_types::bytes32::Bytes32};
 
-use super::utils::{query::post_request, retry::with_retry};
+use super::utils::{
+    query::post_request_with_operation_id,
+    retry::{with_retry_config, RetryConfig},
+};
 
 const TIME_TO_EXPIRY: u64 = 60; // 1 minute for normal requests
 const TIME_TO_EXPIRY_READONLY: u64 = 60 * 60 * 24; // 24 hours for readonly
 
+// snapshots are downloaded in ranges of this size in parallel
+const DOWNLOAD_PART_SIZE: u64 = 8 * 1024 * 1024; // 8 MiB
+
 #[derive(Debug, Clone)]
 pub struct S3StoreVaultClient {
     base_url: String,
+    retry_config: RetryConfig,
 }
 
 impl S3StoreVaultClient {
     pub fn new(base_url: &str) -> Self {
         S3StoreVaultClient {
             base_url: base_url.to_string(),
+            retry_config: RetryConfig::default(),
         }
     }
+
+    /// Use `retry_config` for every request made by this client.
+    pub fn with_retry_config(mut self, retry_config: RetryConfig) -> Self {
+        self.retry_config = retry_config;
+        self
+    }
 }
 
 #[async_trait(?Send)]
@@ -52,17 +71,25 @@ impl StoreVaultClientInterface for S3StoreVaultClient {
             pubkey: key.pubkey,
             topic: topic.to_string(),
             digest,
+            size: Some(data.len() as u64),
         };
         let request_with_auth = request.sign(key, TIME_TO_EXPIRY);
-        let response: S3PreSaveSnapshotResponse = post_request(
+        let response: S3PreSaveSnapshotResponse = post_request_with_operation_id(
             &self.base_url,
             "/s3-store-vault/pre-save-snapshot",
             Some(&request_with_auth),
+            &self.retry_config,
         )
         .await?;
 
-        // upload data to s3
-        upload_s3(&response.presigned_url, data).await?;
+        // upload data to s3, in parts if the server asked for a multipart upload
+        let multipart_upload = match &response.multipart_upload {
+            Some(upload) => Some(upload_s3_multipart(upload, data, &self.retry_config).await?),
+            None => {
+                upload_s3(&response.presigned_url, data, &self.retry_config).await?;
+                None
+            }
+        };
 
         // save snapshot
         let request = S3SaveSnapshotRequest {
@@ -70,12 +97,14 @@ impl StoreVaultClientInterface for S3StoreVaultClient {
             topic: topic.to_string(),
             prev_digest,
             digest,
+            multipart_upload,
         };
         let request_with_auth = request.sign(key, TIME_TO_EXPIRY);
-        let () = post_request(
+        let () = post_request_with_operation_id(
             &self.base_url,
             "/s3-store-vault/save-snapshot",
             Some(&request_with_auth),
+            &self.retry_config,
         )
         .await?;
 
@@ -88,16 +117,17 @@ impl StoreVaultClientInterface for S3StoreVaultClient {
             pubkey: key.pubkey,
         };
         let request_with_auth = request.sign(key, TIME_TO_EXPIRY);
-        let response: S3GetSnapshotResponse = post_request(
+        let response: S3GetSnapshotResponse = post_request_with_operation_id(
             &self.base_url,
             "/s3-store-vault/get-snapshot",
             Some(&request_with_auth),
+            &self.retry_config,
         )
         .await?;
 
         match response.presigned_url {
             Some(url) => {
-                let data = download_s3(&url).await?;
+                let data = download_s3_ranged(&url, &self.retry_config).await?;
                 Ok(Some(data))
             }
             None => Ok(None),
@@ -123,10 +153,11 @@ impl StoreVaultClientInterface for S3StoreVaultClient {
             let digests = data.iter().map(|entry| entry.digest).collect::<Vec<_>>();
             let request = S3SaveDataBatchRequest { data };
             let request_with_auth = request.sign(key, TIME_TO_EXPIRY);
-            let response: S3SaveDataBatchResponse = post_request(
+            let response: S3SaveDataBatchResponse = post_request_with_operation_id(
                 &self.base_url,
                 "/s3-store-vault/save-data-batch",
                 Some(&request_with_auth),
+                &self.retry_config,
             )
             .await?;
 
@@ -134,7 +165,7 @@ impl StoreVaultClientInterface for S3StoreVaultClient {
                 .iter()
                 .map(|entry| entry.data.clone())
                 .collect::<Vec<_>>();
-            batch_upload_s3(&response.presigned_urls, &data).await?;
+            batch_upload_s3(&response.presigned_urls, &data, &self.retry_config).await?;
 
             all_digests.extend(digests);
         }
@@ -155,10 +186,11 @@ impl StoreVaultClientInterface for S3StoreVaultClient {
                 pubkey: key.pubkey,
             };
             let request_with_auth = request.sign(key, TIME_TO_EXPIRY);
-            let response: S3GetDataBatchResponse = post_request(
+            let response: S3GetDataBatchResponse = post_request_with_operation_id(
                 &self.base_url,
                 "/s3-store-vault/get-data-batch",
                 Some(&request_with_auth),
+                &self.retry_config,
             )
             .await?;
             let urls = response
@@ -168,7 +200,7 @@ impl StoreVaultClientInterface for S3StoreVaultClient {
                 .collect::<Vec<_>>();
 
             // download data
-            let data = batch_download_s3(&urls).await?;
+            let data = batch_download_s3(&urls, &self.retry_config).await?;
             let data_with_meta = response
                 .presigned_urls_with_meta
                 .iter()
@@ -220,10 +252,11 @@ impl StoreVaultClientInterface for S3StoreVaultClient {
             },
             auth: auth.clone(),
         };
-        let response: S3GetDataSequenceResponse = post_request(
+        let response: S3GetDataSequenceResponse = post_request_with_operation_id(
             &self.base_url,
             "/s3-store-vault/get-data-sequence",
             Some(&request_with_auth),
+            &self.retry_config,
         )
         .await?;
 
@@ -232,7 +265,7 @@ impl StoreVaultClientInterface for S3StoreVaultClient {
             .iter()
             .map(|x| x.presigned_url.clone())
             .collect::<Vec<_>>();
-        let data = batch_download_s3(&urls).await?;
+        let data = batch_download_s3(&urls, &self.retry_config).await?;
         let data_with_meta = response
             .presigned_urls_with_meta
             .iter()
@@ -244,6 +277,19 @@ impl StoreVaultClientInterface for S3StoreVaultClient {
             .collect::<Vec<_>>();
         Ok((data_with_meta, response.cursor_response))
     }
+
+    async fn list_topics(&self, key: KeySet) -> Result<Vec<TopicEntryCount>, ServerError> {
+        let request = S3ListTopicsRequest { pubkey: key.pubkey };
+        let request_with_auth = request.sign(key, TIME_TO_EXPIRY);
+        let response: S3ListTopicsResponse = post_request_with_operation_id(
+            &self.base_url,
+            "/s3-store-vault/list-topics",
+            Some(&request_with_auth),
+            &self.retry_config,
+        )
+        .await?;
+        Ok(response.topics)
+    }
 }
 
 impl S3StoreVaultClient {
@@ -261,9 +307,9 @@ impl S3StoreVaultClient {
     }
 }
 
-async fn upload_s3(url: &str, data: &[u8]) -> Result<(), ServerError> {
+async fn upload_s3(url: &str, data: &[u8], retry_config: &RetryConfig) -> Result<(), ServerError> {
     let client = reqwest::Client::new();
-    let response = with_retry(|| async {
+    let response = with_retry_config(retry_config, || async {
         client
             .put(url)
             .header("Content-Type", "application/octet-stream")
@@ -282,9 +328,9 @@ async fn upload_s3(url: &str, data: &[u8]) -> Result<(), ServerError> {
     Ok(())
 }
 
-async fn download_s3(url: &str) -> Result<Vec<u8>, ServerError> {
+async fn download_s3(url: &str, retry_config: &RetryConfig) -> Result<Vec<u8>, ServerError> {
     let client = reqwest::Client::new();
-    let response = with_retry(|| async { client.get(url).send().await })
+    let response = with_retry_config(retry_config, || async { client.get(url).send().await })
         .await
         .map_err(|e| ServerError::NetworkError(e.to_string()))?;
     if !response.status().is_success() {
@@ -300,11 +346,223 @@ async fn download_s3(url: &str) -> Result<Vec<u8>, ServerError> {
     Ok(response.to_vec())
 }
 
-async fn batch_upload_s3(urls: &[String], data: &[Vec<u8>]) -> Result<(), ServerError> {
+/// Upload `data` to the part urls of a multipart upload in parallel, retrying each part on its
+/// own. The bucket must expose the `ETag` header to browsers for this to work from wasm.
+async fn upload_s3_multipart(
+    upload: &S3MultipartUpload,
+    data: &[u8],
+    retry_config: &RetryConfig,
+) -> Result<S3CompletedMultipartUpload, ServerError> {
+    let chunks = data
+        .chunks(upload.part_size.max(1) as usize)
+        .collect::<Vec<_>>();
+    if chunks.len() != upload.part_urls.len() {
+        return Err(ServerError::InvalidResponse(format!(
+            "Expected {} parts of {} bytes, got {} part urls",
+            chunks.len(),
+            upload.part_size,
+            upload.part_urls.len()
+        )));
+    }
+    let client = reqwest::Client::new();
+    let upload_futures = upload
+        .part_urls
+        .iter()
+        .zip(chunks)
+        .enumerate()
+        .map(|(index, (url, chunk))| {
+            let client = &client;
+            async move {
+                let etag = upload_s3_part(client, url, chunk, retry_config).await?;
+                Ok::<_, ServerError>(S3CompletedPart {
+                    part_number: index as u32 + 1,
+                    etag,
+                })
+            }
+        })
+        .collect::<Vec<_>>();
+    let parts = futures::future::try_join_all(upload_futures).await?;
+    Ok(S3CompletedMultipartUpload {
+        upload_id: upload.upload_id.clone(),
+        parts,
+    })
+}
+
+/// Upload a single part and return its ETag.
+async fn upload_s3_part(
+    client: &reqwest::Client,
+    url: &str,
+    data: &[u8],
+    retry_config: &RetryConfig,
+) -> Result<String, ServerError> {
+    with_retry_config(retry_config, || async {
+        let response = client
+            .put(url)
+            .body(data.to_vec())
+            .send()
+            .await
+            .map_err(|e| ServerError::NetworkError(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(ServerError::InvalidResponse(format!(
+                "Failed to upload part: {:?}",
+                response.text().await
+            )));
+        }
+        response
+            .headers()
+            .get("ETag")
+            .and_then(|etag| etag.to_str().ok())
+            .map(|etag| etag.to_string())
+            .ok_or_else(|| {
+                ServerError::InvalidResponse("Missing ETag in part upload response".to_string())
+            })
+    })
+    .await
+}
+
+/// Download the object at `url` in ranges of `DOWNLOAD_PART_SIZE` in parallel. Uses the full
+/// body if the server ignores the range, and falls back to a single un-ranged GET if the ranged
+/// request fails or its `Content-Range` is missing or unreadable.
+///
+/// From wasm, ranged downloads need a bucket CORS config that allows the `Range` request header
+/// and exposes the `Content-Range` response header, e.g. `AllowedHeaders: ["Range"]` and
+/// `ExposeHeaders: ["Content-Range", "ETag"]` on S3. Otherwise every download takes the slower
+/// un-ranged fallback.
+async fn download_s3_ranged(url: &str, retry_config: &RetryConfig) -> Result<Vec<u8>, ServerError> {
+    let client = reqwest::Client::new();
+    let (mut data, total) =
+        match download_s3_range(&client, url, 0, DOWNLOAD_PART_SIZE, retry_config).await {
+            Ok(RangeResponse::Part { data, total }) => (data, total),
+            Ok(RangeResponse::Whole(data)) => return Ok(data),
+            Ok(RangeResponse::UnknownTotal) => {
+                log::warn!("Content-Range is not readable, downloading in a single request");
+                return download_s3(url, retry_config).await;
+            }
+            Err(e) => {
+                log::warn!("Ranged download failed: {e}, downloading in a single request");
+                return download_s3(url, retry_config).await;
+            }
+        };
+    let download_futures = part_ranges(data.len() as u64, total, DOWNLOAD_PART_SIZE)
+        .into_iter()
+        .map(|(start, len)| {
+            let client = &client;
+            async move {
+                let RangeResponse::Part { data: part, .. } =
+                    download_s3_range(client, url, start, len, retry_config).await?
+                else {
+                    return Err(ServerError::InvalidResponse(format!(
+                        "Range at offset {start} was not honored"
+                    )));
+                };
+                if part.len() as u64 != len {
+                    return Err(ServerError::InvalidResponse(format!(
+                        "Expected {len} bytes at offset {start}, got {}",
+                        part.len()
+                    )));
+                }
+                Ok(part)
+            }
+        })
+        .collect::<Vec<_>>();
+    for part in futures::future::try_join_all(download_futures).await? {
+        data.extend(part);
+    }
+    if data.len() as u64 != total {
+        return Err(ServerError::InvalidResponse(format!(
+            "Expected {total} bytes, got {}",
+            data.len()
+        )));
+    }
+    Ok(data)
+}
+
+/// Response to a ranged download
+enum RangeResponse {
+    /// The server ignored the range and returned the whole object
+    Whole(Vec<u8>),
+    /// The requested range, and the total size of the object
+    Part { data: Vec<u8>, total: u64 },
+    /// The range was returned, but the total size is unknown because the `Content-Range` header
+    /// is missing or unreadable, e.g. not exposed to the browser
+    UnknownTotal,
+}
+
+/// Download `len` bytes from `start`.
+async fn download_s3_range(
+    client: &reqwest::Client,
+    url: &str,
+    start: u64,
+    len: u64,
+    retry_config: &RetryConfig,
+) -> Result<RangeResponse, ServerError> {
+    with_retry_config(retry_config, || async {
+        let response = client
+            .get(url)
+            .header("Range", format!("bytes={}-{}", start, start + len - 1))
+            .send()
+            .await
+            .map_err(|e| ServerError::NetworkError(e.to_string()))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(ServerError::InvalidResponse(format!(
+                "Failed to download data: {:?}",
+                response.text().await
+            )));
+        }
+        let total = if status == reqwest::StatusCode::PARTIAL_CONTENT {
+            let total = response
+                .headers()
+                .get("Content-Range")
+                .and_then(|value| value.to_str().ok())
+                .and_then(parse_content_range_total);
+            if total.is_none() {
+                return Ok(RangeResponse::UnknownTotal);
+            }
+            total
+        } else {
+            None
+        };
+        let data = response
+            .bytes()
+            .await
+            .map_err(|e| ServerError::InvalidResponse(format!("Failed to read response: {e:?}")))?
+            .to_vec();
+        Ok(match total {
+            Some(total) => RangeResponse::Part { data, total },
+            None => RangeResponse::Whole(data),
+        })
+    })
+    .await
+}
+
+/// Total size in a `Content-Range` header like `bytes 0-99/1000`.
+fn parse_content_range_total(content_range: &str) -> Option<u64> {
+    let (unit, range) = content_range.trim().split_once(' ')?;
+    if unit != "bytes" {
+        return None;
+    }
+    let (_, total) = range.split_once('/')?;
+    total.parse().ok()
+}
+
+/// `(start, len)` of the ranges of at most `part_size` bytes covering `[offset, total)`.
+fn part_ranges(offset: u64, total: u64, part_size: u64) -> Vec<(u64, u64)> {
+    (offset..total)
+        .step_by(part_size as usize)
+        .map(|start| (start, part_size.min(total - start)))
+        .collect()
+}
+
+async fn batch_upload_s3(
+    urls: &[String],
+    data: &[Vec<u8>],
+    retry_config: &RetryConfig,
+) -> Result<(), ServerError> {
     let upload_futures = urls
         .iter()
         .zip(data.iter())
-        .map(|(url, data)| async move { upload_s3(url, data).await })
+        .map(|(url, data)| async move { upload_s3(url, data, retry_config).await })
         .collect::<Vec<_>>();
     let results = futures::future::join_all(upload_futures).await;
     for result in results {
@@ -313,10 +571,13 @@ async fn batch_upload_s3(urls: &[String], data: &[Vec<u8>]) -> Result<(), Server
     Ok(())
 }
 
-async fn batch_download_s3(urls: &[String]) -> Result<Vec<Vec<u8>>, ServerError> {
+async fn batch_download_s3(
+    urls: &[String],
+    retry_config: &RetryConfig,
+) -> Result<Vec<Vec<u8>>, ServerError> {
     let download_futures = urls
         .iter()
-        .map(|url| async move { download_s3(url).await })
+        .map(|url| async move { download_s3(url, retry_config).await })
         .collect::<Vec<_>>();
     let results = futures::future::join_all(download_futures).await;
     let mut all_data = Vec::new();
@@ -340,3 +601,42 @@ pub fn generate_auth_for_get_data_sequence_s3(key: KeySet) -> Auth {
     let dummy_request_with_auth = dummy_request.sign(key, TIME_TO_EXPIRY_READONLY);
     dummy_request_with_auth.auth
 }
+
+/// Same as `generate_auth_for_get_data_sequence_s3`, but the server rejects the auth for any
+/// topic outside `topic_scope`, so it can be handed to a service that should only read those.
+pub fn generate_scoped_auth_for_get_data_sequence_s3(
+    key: KeySet,
+    topic_scope: Vec<String>,
+) -> Auth {
+    let dummy_request = S3GetDataSequenceRequest {
+        topic: "dummy".to_string(),
+        pubkey: key.pubkey,
+        cursor: MetaDataCursor {
+            cursor: None,
+            order: CursorOrder::Asc,
+            limit: None,
+        },
+    };
+    let dummy_request_with_auth =
+        dummy_request.sign_with_topic_scope(key, TIME_TO_EXPIRY_READONLY, topic_scope);
+    dummy_request_with_auth.auth
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_content_range_total, part_ranges};
+
+    #[test]
+    fn test_parse_content_range_total() {
+        assert_eq!(parse_content_range_total("bytes 0-99/1000"), Some(1000));
+        assert_eq!(parse_content_range_total("bytes 0-99/*"), None);
+        assert_eq!(parse_content_range_total("items 0-99/1000"), None);
+        assert_eq!(parse_content_range_total(""), None);
+    }
+
+    #[test]
+    fn test_part_ranges() {
+        assert_eq!(part_ranges(10, 35, 10), vec![(10, 10), (20, 10), (30, 5)]);
+        assert_eq!(part_ranges(10, 10, 10), vec![]);
+    }
+}
@@ -7,8 +7,9 @@ use intmax2_interfaces::{
             types::{
                 CursorOrder, DataWithMetaData, GetDataBatchRequest, GetDataBatchResponse,
                 GetDataSequenceRequest, GetDataSequenceResponse, GetSnapshotRequest,
-                GetSnapshotResponse, MetaDataCursor, MetaDataCursorResponse, SaveDataBatchRequest,
-                SaveDataBatchResponse, SaveSnapshotRequest,
+                GetSnapshotResponse, ListTopicsRequest, ListTopicsResponse, MetaDataCursor,
+                MetaDataCursorResponse, SaveDataBatchRequest, SaveDataBatchResponse,
+                SaveSnapshotRequest, TopicEntryCount,
             },
         },
     },
@@ -16,22 +17,44 @@ use intmax2_interfaces::{
 };
 use intmax2_zkp::{common::signature_content::key_set::KeySet, ethereum_types::bytes32::Bytes32};
 
-use super::utils::query::post_request;
+use super::utils::{query::post_request_with_operation_id, retry::RetryConfig};
 
 const TIME_TO_EXPIRY: u64 = 60; // 1 minute for normal requests
 const TIME_TO_EXPIRY_READONLY: u64 = 60 * 60 * 24; // 24 hours for readonly
 
+// Kept below the 35 MB json payload limit of the store vault server
+pub const DEFAULT_MAX_BATCH_BYTES: usize = 30 * 1024 * 1024;
+
+// Rough size of the json fields of an entry other than its topic and data
+const ENTRY_OVERHEAD_BYTES: usize = 128;
+
 #[derive(Debug, Clone)]
 pub struct StoreVaultServerClient {
     base_url: String,
+    retry_config: RetryConfig,
+    max_batch_bytes: usize,
 }
 
 impl StoreVaultServerClient {
     pub fn new(base_url: &str) -> Self {
         StoreVaultServerClient {
             base_url: base_url.to_string(),
+            retry_config: RetryConfig::default(),
+            max_batch_bytes: DEFAULT_MAX_BATCH_BYTES,
         }
     }
+
+    /// Split `save_data_batch` requests so that each one stays under `max_batch_bytes`.
+    pub fn with_max_batch_bytes(mut self, max_batch_bytes: usize) -> Self {
+        self.max_batch_bytes = max_batch_bytes;
+        self
+    }
+
+    /// Use `retry_config` for every request made by this client.
+    pub fn with_retry_config(mut self, retry_config: RetryConfig) -> Self {
+        self.retry_config = retry_config;
+        self
+    }
 }
 
 #[async_trait(?Send)]
@@ -50,10 +73,11 @@ impl StoreVaultClientInterface for StoreVaultServerClient {
             prev_digest,
         };
         let request_with_auth = request.sign(key, TIME_TO_EXPIRY);
-        post_request::<_, ()>(
+        post_request_with_operation_id::<_, ()>(
             &self.base_url,
             "/store-vault-server/save-snapshot",
             Some(&request_with_auth),
+            &self.retry_config,
         )
         .await?;
         Ok(())
@@ -65,10 +89,11 @@ impl StoreVaultClientInterface for StoreVaultServerClient {
             pubkey: key.pubkey,
         };
         let request_with_auth = request.sign(key, TIME_TO_EXPIRY);
-        let response: GetSnapshotResponse = post_request(
+        let response: GetSnapshotResponse = post_request_with_operation_id(
             &self.base_url,
             "/store-vault-server/get-snapshot",
             Some(&request_with_auth),
+            &self.retry_config,
         )
         .await?;
         Ok(response.data)
@@ -81,15 +106,16 @@ impl StoreVaultClientInterface for StoreVaultServerClient {
     ) -> Result<Vec<Bytes32>, ServerError> {
         let mut all_digests = vec![];
 
-        for chunk in entries.chunks(MAX_BATCH_SIZE) {
+        for chunk in split_by_byte_budget(entries, self.max_batch_bytes) {
             let request = SaveDataBatchRequest {
                 data: chunk.to_vec(),
             };
             let request_with_auth = request.sign(key, TIME_TO_EXPIRY);
-            let response: SaveDataBatchResponse = post_request(
+            let response: SaveDataBatchResponse = post_request_with_operation_id(
                 &self.base_url,
                 "/store-vault-server/save-data-batch",
                 Some(&request_with_auth),
+                &self.retry_config,
             )
             .await?;
             all_digests.extend(response.digests);
@@ -111,10 +137,11 @@ impl StoreVaultClientInterface for StoreVaultServerClient {
                 pubkey: key.pubkey,
             };
             let request_with_auth = request.sign(key, TIME_TO_EXPIRY);
-            let response: GetDataBatchResponse = post_request(
+            let response: GetDataBatchResponse = post_request_with_operation_id(
                 &self.base_url,
                 "/store-vault-server/get-data-batch",
                 Some(&request_with_auth),
+                &self.retry_config,
             )
             .await?;
             all_data.extend(response.data);
@@ -158,14 +185,28 @@ impl StoreVaultClientInterface for StoreVaultServerClient {
             },
             auth: auth.clone(),
         };
-        let response: GetDataSequenceResponse = post_request(
+        let response: GetDataSequenceResponse = post_request_with_operation_id(
             &self.base_url,
             "/store-vault-server/get-data-sequence",
             Some(&request_with_auth),
+            &self.retry_config,
         )
         .await?;
         Ok((response.data, response.cursor_response))
     }
+
+    async fn list_topics(&self, key: KeySet) -> Result<Vec<TopicEntryCount>, ServerError> {
+        let request = ListTopicsRequest { pubkey: key.pubkey };
+        let request_with_auth = request.sign(key, TIME_TO_EXPIRY);
+        let response: ListTopicsResponse = post_request_with_operation_id(
+            &self.base_url,
+            "/store-vault-server/list-topics",
+            Some(&request_with_auth),
+            &self.retry_config,
+        )
+        .await?;
+        Ok(response.topics)
+    }
 }
 
 impl StoreVaultServerClient {
@@ -197,3 +238,83 @@ pub fn generate_auth_for_get_data_sequence(key: KeySet) -> Auth {
     let dummy_request_with_auth = dummy_request.sign(key, TIME_TO_EXPIRY_READONLY);
     dummy_request_with_auth.auth
 }
+
+/// Estimated size of an entry in a json request. The data is base64 encoded.
+fn estimated_entry_bytes(entry: &SaveDataEntry) -> usize {
+    entry.data.len().div_ceil(3) * 4 + entry.topic.len() + ENTRY_OVERHEAD_BYTES
+}
+
+/// Split entries into consecutive sub-batches of at most `MAX_BATCH_SIZE` entries whose
+/// estimated size is within `max_batch_bytes`. An entry larger than the budget is sent alone.
+fn split_by_byte_budget(
+    entries: &[SaveDataEntry],
+    max_batch_bytes: usize,
+) -> Vec<&[SaveDataEntry]> {
+    let mut chunks = vec![];
+    let mut start = 0;
+    let mut chunk_bytes = 0;
+    for (i, entry) in entries.iter().enumerate() {
+        let entry_bytes = estimated_entry_bytes(entry);
+        let len = i - start;
+        if len > 0 && (len == MAX_BATCH_SIZE || chunk_bytes + entry_bytes > max_batch_bytes) {
+            chunks.push(&entries[start..i]);
+            start = i;
+            chunk_bytes = 0;
+        }
+        chunk_bytes += entry_bytes;
+    }
+    if start < entries.len() {
+        chunks.push(&entries[start..]);
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use intmax2_interfaces::api::store_vault_server::interface::{SaveDataEntry, MAX_BATCH_SIZE};
+    use intmax2_zkp::ethereum_types::u256::U256;
+
+    use super::{estimated_entry_bytes, split_by_byte_budget};
+
+    fn entry(data_len: usize) -> SaveDataEntry {
+        SaveDataEntry {
+            topic: "topic".to_string(),
+            pubkey: U256::default(),
+            data: vec![0; data_len],
+        }
+    }
+
+    #[test]
+    fn test_split_by_byte_budget() {
+        let entries = (0..5).map(|_| entry(300)).collect::<Vec<_>>();
+        let entry_bytes = estimated_entry_bytes(&entries[0]);
+        let chunks = split_by_byte_budget(&entries, entry_bytes * 2);
+        assert_eq!(
+            chunks.iter().map(|c| c.len()).collect::<Vec<_>>(),
+            vec![2, 2, 1]
+        );
+    }
+
+    #[test]
+    fn test_split_by_byte_budget_oversized_entry() {
+        let entries = vec![entry(10), entry(10_000), entry(10)];
+        let chunks = split_by_byte_budget(&entries, 1_000);
+        assert_eq!(
+            chunks.iter().map(|c| c.len()).collect::<Vec<_>>(),
+            vec![1, 1, 1]
+        );
+        assert!(split_by_byte_budget(&[], 1_000).is_empty());
+    }
+
+    #[test]
+    fn test_split_by_byte_budget_max_batch_size() {
+        let entries = (0..MAX_BATCH_SIZE + 1)
+            .map(|_| entry(1))
+            .collect::<Vec<_>>();
+        let chunks = split_by_byte_budget(&entries, usize::MAX);
+        assert_eq!(
+            chunks.iter().map(|c| c.len()).collect::<Vec<_>>(),
+            vec![MAX_BATCH_SIZE, 1]
+        );
+    }
+}
@@ -1,3 +1,4 @@
 pub mod query;
+pub mod rate_limit;
 pub mod retry;
 pub mod time;
@@ -1,6 +1,11 @@
-use super::retry::with_retry;
+use super::{
+    rate_limit::{parse_retry_after, RateLimiter},
+    retry::{with_retry_config, RetryConfig},
+    time::sleep_for_millis,
+};
+use crate::client::operation::{current_operation_id, OPERATION_ID_HEADER};
 use intmax2_interfaces::api::error::ServerError;
-use reqwest::{header, Response, Url};
+use reqwest::{header, RequestBuilder, Response, StatusCode, Url};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 #[derive(Debug, Deserialize)]
@@ -15,7 +20,28 @@ pub async fn post_request<B: Serialize, R: DeserializeOwned>(
     endpoint: &str,
     body: Option<&B>,
 ) -> Result<R, ServerError> {
-    post_request_with_bearer_token(base_url, endpoint, None, body).await
+    post_request_with_retry(base_url, endpoint, body, &RetryConfig::default()).await
+}
+
+pub async fn post_request_with_retry<B: Serialize, R: DeserializeOwned>(
+    base_url: &str,
+    endpoint: &str,
+    body: Option<&B>,
+    retry_config: &RetryConfig,
+) -> Result<R, ServerError> {
+    post_request_inner(base_url, endpoint, None, body, retry_config, false).await
+}
+
+/// Same as `post_request_with_retry`, but sends the id of the running client operation in
+/// `OPERATION_ID_HEADER`. Only for the servers of this repository, whose CORS config allows
+/// the header; third party servers would reject the browser preflight.
+pub async fn post_request_with_operation_id<B: Serialize, R: DeserializeOwned>(
+    base_url: &str,
+    endpoint: &str,
+    body: Option<&B>,
+    retry_config: &RetryConfig,
+) -> Result<R, ServerError> {
+    post_request_inner(base_url, endpoint, None, body, retry_config, true).await
 }
 
 pub async fn post_request_with_bearer_token<B: Serialize, R: DeserializeOwned>(
@@ -23,21 +49,42 @@ pub async fn post_request_with_bearer_token<B: Serialize, R: DeserializeOwned>(
     endpoint: &str,
     bearer_token: Option<String>,
     body: Option<&B>,
+) -> Result<R, ServerError> {
+    post_request_inner(
+        base_url,
+        endpoint,
+        bearer_token,
+        body,
+        &RetryConfig::default(),
+        false,
+    )
+    .await
+}
+
+async fn post_request_inner<B: Serialize, R: DeserializeOwned>(
+    base_url: &str,
+    endpoint: &str,
+    bearer_token: Option<String>,
+    body: Option<&B>,
+    retry_config: &RetryConfig,
+    send_operation_id: bool,
 ) -> Result<R, ServerError> {
     let url = format!("{base_url}{endpoint}");
     let _ = Url::parse(&url)
         .map_err(|e| ServerError::MalformedUrl(format!("Failed to parse URL {url}: {e}")))?;
     let client = reqwest::Client::new();
-    let mut request = client.post(url.clone());
+    let mut request = with_operation_id_header(client.post(url.clone()), send_operation_id);
     if let Some(token) = bearer_token {
         request = request.header(header::AUTHORIZATION, token);
     }
     if let Some(body) = body {
         request = request.json(body);
     }
-    let response = with_retry(|| async { request.try_clone().unwrap().send().await })
-        .await
-        .map_err(|e| ServerError::NetworkError(e.to_string()))?;
+    let response = with_retry_config(retry_config, || async {
+        request.try_clone().unwrap().send().await
+    })
+    .await
+    .map_err(|e| ServerError::NetworkError(e.to_string()))?;
 
     // Serialize the body to a string for logging
     let body_str = if let Some(body) = &body {
@@ -62,6 +109,121 @@ where
     Q: Serialize,
     R: DeserializeOwned,
 {
+    get_request_with_retry(base_url, endpoint, query, &RetryConfig::default()).await
+}
+
+pub async fn get_request_with_retry<Q, R>(
+    base_url: &str,
+    endpoint: &str,
+    query: Option<Q>,
+    retry_config: &RetryConfig,
+) -> Result<R, ServerError>
+where
+    Q: Serialize,
+    R: DeserializeOwned,
+{
+    get_request_inner(base_url, endpoint, query, retry_config, false).await
+}
+
+/// Same as `get_request_with_retry`, but sends the id of the running client operation in
+/// `OPERATION_ID_HEADER`. Only for the servers of this repository, like
+/// `post_request_with_operation_id`.
+pub async fn get_request_with_operation_id<Q, R>(
+    base_url: &str,
+    endpoint: &str,
+    query: Option<Q>,
+    retry_config: &RetryConfig,
+) -> Result<R, ServerError>
+where
+    Q: Serialize,
+    R: DeserializeOwned,
+{
+    get_request_inner(base_url, endpoint, query, retry_config, true).await
+}
+
+async fn get_request_inner<Q, R>(
+    base_url: &str,
+    endpoint: &str,
+    query: Option<Q>,
+    retry_config: &RetryConfig,
+    send_operation_id: bool,
+) -> Result<R, ServerError>
+where
+    Q: Serialize,
+    R: DeserializeOwned,
+{
+    let (url, query_str) = build_get_url(base_url, endpoint, query)?;
+    let client = reqwest::Client::new();
+    let response = with_retry_config(retry_config, || async {
+        with_operation_id_header(client.get(&url), send_operation_id)
+            .send()
+            .await
+    })
+    .await
+    .map_err(|e| ServerError::NetworkError(e.to_string()))?;
+    log::debug!("GET request url: {url}");
+    handle_response(response, &url, &query_str).await
+}
+
+/// Same as `get_request_with_retry`, but waits for `rate_limiter` before every attempt, and
+/// retries on 429 Too Many Requests after the delay given by the `Retry-After` header (or the
+/// backoff delay of `retry_config` if the header is missing).
+pub async fn get_request_with_rate_limit<Q, R>(
+    base_url: &str,
+    endpoint: &str,
+    query: Option<Q>,
+    retry_config: &RetryConfig,
+    rate_limiter: Option<&RateLimiter>,
+) -> Result<R, ServerError>
+where
+    Q: Serialize,
+    R: DeserializeOwned,
+{
+    let (url, query_str) = build_get_url(base_url, endpoint, query)?;
+    let client = reqwest::Client::new();
+    let mut throttled = 0;
+    let response = loop {
+        if let Some(rate_limiter) = rate_limiter {
+            rate_limiter.acquire().await;
+        }
+        let response = with_retry_config(retry_config, || async { client.get(&url).send().await })
+            .await
+            .map_err(|e| ServerError::NetworkError(e.to_string()))?;
+        if response.status() != StatusCode::TOO_MANY_REQUESTS
+            || throttled >= retry_config.max_retries
+        {
+            break response;
+        }
+        let wait = response
+            .headers()
+            .get(header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| parse_retry_after(value, chrono::Utc::now()))
+            .unwrap_or_else(|| retry_config.delay(throttled));
+        log::warn!("Rate limited by {url}, retrying in {wait:?}");
+        sleep_for_millis(wait.as_millis() as u64).await;
+        throttled += 1;
+    };
+    log::debug!("GET request url: {url}");
+    handle_response(response, &url, &query_str).await
+}
+
+/// Attach the id of the running client operation, if any and `send_operation_id` is set, so
+/// that the server can log it
+fn with_operation_id_header(request: RequestBuilder, send_operation_id: bool) -> RequestBuilder {
+    match current_operation_id() {
+        Some(operation_id) if send_operation_id => {
+            request.header(OPERATION_ID_HEADER, operation_id)
+        }
+        _ => request,
+    }
+}
+
+fn build_get_url<Q: Serialize>(
+    base_url: &str,
+    endpoint: &str,
+    query: Option<Q>,
+) -> Result<(String, Option<String>), ServerError> {
     let mut url = format!("{base_url}{endpoint}");
     let _ = Url::parse(&url)
         .map_err(|e| ServerError::MalformedUrl(format!("Failed to parse URL {url}: {e}")))?;
@@ -75,12 +237,7 @@ where
     if query_str.is_some() {
         url = format!("{}?{}", url, query_str.as_ref().unwrap());
     }
-    let client = reqwest::Client::new();
-    let response = with_retry(|| async { client.get(&url).send().await })
-        .await
-        .map_err(|e| ServerError::NetworkError(e.to_string()))?;
-    log::debug!("GET request url: {url}");
-    handle_response(response, &url, &query_str).await
+    Ok((url, query_str))
 }
 
 async fn handle_response<R: DeserializeOwned>(
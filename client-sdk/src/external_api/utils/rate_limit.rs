@@ -0,0 +1,160 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::external_api::utils::time::sleep_for_millis;
+
+/// Upper bound of a wait requested by a `Retry-After` header, so that a misbehaving server
+/// cannot stall the client indefinitely.
+pub const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Token bucket policy for client-side rate limiting.
+///
+/// Up to `burst` requests are sent immediately, after which requests are spaced out to
+/// `requests_per_second`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RateLimitConfig {
+    pub requests_per_second: f64,
+    pub burst: u32,
+}
+
+impl RateLimitConfig {
+    /// Build a config from optional overrides. Returns `None` (no limit) unless a positive
+    /// `requests_per_second` is given. The burst defaults to one second worth of requests.
+    pub fn from_options(requests_per_second: Option<f64>, burst: Option<u32>) -> Option<Self> {
+        let requests_per_second = requests_per_second.filter(|rps| *rps > 0.0)?;
+        let burst = burst.unwrap_or(requests_per_second.ceil() as u32).max(1);
+        Some(Self {
+            requests_per_second,
+            burst,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct BucketState {
+    tokens: f64,
+    last_refill_ms: u64,
+}
+
+/// Token bucket rate limiter. Clones share the same bucket.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    state: Arc<Mutex<BucketState>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            state: Arc::new(Mutex::new(BucketState {
+                tokens: config.burst as f64,
+                last_refill_ms: now_millis(),
+            })),
+        }
+    }
+
+    /// Takes a token, waiting until one is available.
+    pub async fn acquire(&self) {
+        let wait = self.reserve(now_millis());
+        if !wait.is_zero() {
+            log::info!("Throttled by the client-side rate limit, waiting {wait:?}");
+            sleep_for_millis(wait.as_millis() as u64).await;
+        }
+    }
+
+    /// Takes a token at `now_ms` and returns how long the caller has to wait for it. The token
+    /// is reserved immediately, so concurrent callers queue up instead of racing for it.
+    fn reserve(&self, now_ms: u64) -> Duration {
+        let mut state = self.state.lock().unwrap();
+        let elapsed_secs = now_ms.saturating_sub(state.last_refill_ms) as f64 / 1000.0;
+        state.tokens = (state.tokens + elapsed_secs * self.config.requests_per_second)
+            .min(self.config.burst as f64);
+        state.last_refill_ms = state.last_refill_ms.max(now_ms);
+        state.tokens -= 1.0;
+        if state.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-state.tokens / self.config.requests_per_second)
+        }
+    }
+}
+
+/// Parses a `Retry-After` header value, given either as delay seconds or as an HTTP date.
+/// The result is capped at `MAX_RETRY_AFTER`.
+pub fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    let wait = match value.parse::<u64>() {
+        Ok(seconds) => Duration::from_secs(seconds),
+        Err(_) => {
+            let date = DateTime::parse_from_rfc2822(value).ok()?;
+            // a date in the past means the request can be retried right away
+            (date.with_timezone(&Utc) - now)
+                .to_std()
+                .unwrap_or(Duration::ZERO)
+        }
+    };
+    Some(wait.min(MAX_RETRY_AFTER))
+}
+
+fn now_millis() -> u64 {
+    chrono::Utc::now().timestamp_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            requests_per_second: 2.0,
+            burst: 2,
+        });
+        let start = limiter.state.lock().unwrap().last_refill_ms;
+        // the burst is sent immediately
+        assert_eq!(limiter.reserve(start), Duration::ZERO);
+        assert_eq!(limiter.reserve(start), Duration::ZERO);
+        // then requests queue up at 2 per second
+        assert_eq!(limiter.reserve(start), Duration::from_millis(500));
+        assert_eq!(limiter.reserve(start), Duration::from_millis(1000));
+        // after the queue drains and the bucket refills, the burst is available again
+        assert_eq!(limiter.reserve(start + 2000), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_from_options() {
+        assert_eq!(RateLimitConfig::from_options(None, Some(10)), None);
+        assert_eq!(RateLimitConfig::from_options(Some(0.0), None), None);
+        assert_eq!(
+            RateLimitConfig::from_options(Some(0.5), None),
+            Some(RateLimitConfig {
+                requests_per_second: 0.5,
+                burst: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now = DateTime::parse_from_rfc2822("Wed, 21 Oct 2015 07:28:00 GMT")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(parse_retry_after("3", now), Some(Duration::from_secs(3)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:10 GMT", now),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:27:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("3600", now), Some(MAX_RETRY_AFTER));
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+}
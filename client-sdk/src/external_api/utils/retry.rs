@@ -1,38 +1,133 @@
 use std::{future::Future, time::Duration};
 
 use log::warn;
+use rand::Rng as _;
+use serde::{Deserialize, Serialize};
 
-use crate::external_api::utils::time::sleep_for;
+use crate::external_api::utils::time::sleep_for_millis;
 
-const MAX_RETRIES: u32 = 5;
-const INITIAL_DELAY: u64 = 1000;
+pub const DEFAULT_MAX_RETRIES: u32 = 5;
+pub const DEFAULT_INITIAL_DELAY_MS: u64 = 1000;
+pub const DEFAULT_JITTER_MS: u64 = 0;
+
+/// Exponential backoff policy for external API calls.
+///
+/// The n-th retry waits `initial_delay_ms * 2^n` plus a random jitter in `[0, jitter_ms]`.
+/// The default (5 retries, 1s initial delay, no jitter) matches the previous fixed behavior.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetryConfig {
+    pub max_retries: u32,
+    pub initial_delay_ms: u64,
+    pub jitter_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: DEFAULT_MAX_RETRIES,
+            initial_delay_ms: DEFAULT_INITIAL_DELAY_MS,
+            jitter_ms: DEFAULT_JITTER_MS,
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Build a config from optional overrides, falling back to the defaults.
+    pub fn from_options(
+        max_retries: Option<u32>,
+        initial_delay_ms: Option<u64>,
+        jitter_ms: Option<u64>,
+    ) -> Self {
+        Self {
+            max_retries: max_retries.unwrap_or(DEFAULT_MAX_RETRIES),
+            initial_delay_ms: initial_delay_ms.unwrap_or(DEFAULT_INITIAL_DELAY_MS),
+            jitter_ms: jitter_ms.unwrap_or(DEFAULT_JITTER_MS),
+        }
+    }
+
+    /// Delay before the retry following `retries` failed retries, without jitter.
+    fn base_delay(&self, retries: u32) -> Duration {
+        let factor = 1u64.checked_shl(retries).unwrap_or(u64::MAX);
+        Duration::from_millis(self.initial_delay_ms.saturating_mul(factor))
+    }
+
+    pub(crate) fn delay(&self, retries: u32) -> Duration {
+        let jitter = if self.jitter_ms > 0 {
+            rand::thread_rng().gen_range(0..=self.jitter_ms)
+        } else {
+            0
+        };
+        self.base_delay(retries) + Duration::from_millis(jitter)
+    }
+}
 
 pub async fn with_retry<'a, T, E, F, Fut>(f: F) -> Result<T, E>
+where
+    E: std::error::Error,
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<T, E>> + 'a,
+{
+    with_retry_config(&RetryConfig::default(), f).await
+}
+
+pub async fn with_retry_config<'a, T, E, F, Fut>(config: &RetryConfig, f: F) -> Result<T, E>
 where
     E: std::error::Error,
     F: Fn() -> Fut,
     Fut: Future<Output = Result<T, E>> + 'a,
 {
     let mut retries = 0;
-    let mut delay = Duration::from_millis(INITIAL_DELAY);
 
     loop {
         match f().await {
             Ok(result) => return Ok(result),
             Err(e) => {
-                if retries >= MAX_RETRIES {
+                if retries >= config.max_retries {
                     return Err(e);
                 }
+                let delay = config.delay(retries);
                 warn!(
                     "Attempt {} failed: {}. Retrying in {:?}...",
                     retries + 1,
                     e,
                     delay
                 );
-                sleep_for(delay.as_secs()).await;
+                sleep_for_millis(delay.as_millis() as u64).await;
                 retries += 1;
-                delay *= 2; // Exponential backoff
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_backoff() {
+        let config = RetryConfig::default();
+        let delays: Vec<u64> = (0..5)
+            .map(|i| config.base_delay(i).as_millis() as u64)
+            .collect();
+        assert_eq!(delays, vec![1000, 2000, 4000, 8000, 16000]);
+    }
+
+    #[test]
+    fn test_jitter_bounds() {
+        let config = RetryConfig {
+            max_retries: 3,
+            initial_delay_ms: 100,
+            jitter_ms: 50,
+        };
+        for _ in 0..100 {
+            let delay = config.delay(1).as_millis() as u64;
+            assert!((200..=250).contains(&delay));
+        }
+    }
+
+    #[test]
+    fn test_backoff_saturates() {
+        let config = RetryConfig::default();
+        assert_eq!(config.base_delay(200), Duration::from_millis(u64::MAX));
+    }
+}
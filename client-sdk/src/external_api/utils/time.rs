@@ -23,3 +23,15 @@ pub async fn sleep_for(seconds: u64) {
     let target = chrono::Utc::now().timestamp() as u64 + seconds;
     sleep_until(target).await;
 }
+
+/// Millisecond variant of `sleep_for`
+pub async fn sleep_for_millis(millis: u64) {
+    let target = (chrono::Utc::now().timestamp_millis() as u64).saturating_add(millis);
+    loop {
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        if now >= target {
+            break;
+        }
+        sleep(Duration::from_millis((target - now).min(1000))).await;
+    }
+}
@@ -1,21 +1,27 @@
 use async_trait::async_trait;
-use intmax2_interfaces::api::{
-    error::ServerError,
-    validity_prover::{
-        interface::{AccountInfo, DepositInfo, ValidityProverClientInterface, MAX_BATCH_SIZE},
-        types::{
-            GetAccountInfoBatchRequest, GetAccountInfoBatchResponse, GetAccountInfoQuery,
-            GetAccountInfoResponse, GetBlockMerkleProofQuery, GetBlockMerkleProofResponse,
-            GetBlockNumberByTxTreeRootBatchRequest, GetBlockNumberByTxTreeRootBatchResponse,
-            GetBlockNumberByTxTreeRootQuery, GetBlockNumberByTxTreeRootResponse,
-            GetBlockNumberResponse, GetDepositInfoBatchRequest, GetDepositInfoBatchResponse,
-            GetDepositInfoQuery, GetDepositInfoResponse, GetDepositMerkleProofQuery,
-            GetDepositMerkleProofResponse, GetLatestIncludedDepositIndexResponse,
-            GetNextDepositIndexResponse, GetUpdateWitnessQuery, GetUpdateWitnessResponse,
-            GetValidityProofQuery, GetValidityProofResponse, GetValidityWitnessQuery,
-            GetValidityWitnessResponse,
+use intmax2_interfaces::{
+    api::{
+        error::ServerError,
+        validity_prover::{
+            interface::{
+                AccountInfo, DepositInfo, SyncStatus, ValidityProverClientInterface, MAX_BATCH_SIZE,
+            },
+            types::{
+                GetAccountInfoBatchRequest, GetAccountInfoBatchResponse, GetAccountInfoQuery,
+                GetAccountInfoResponse, GetBlockMerkleProofQuery, GetBlockMerkleProofResponse,
+                GetBlockNumberByTxTreeRootBatchRequest, GetBlockNumberByTxTreeRootBatchResponse,
+                GetBlockNumberByTxTreeRootQuery, GetBlockNumberByTxTreeRootResponse,
+                GetBlockNumberResponse, GetDepositBloomFilterQuery, GetDepositBloomFilterResponse,
+                GetDepositInfoBatchRequest, GetDepositInfoBatchResponse, GetDepositInfoQuery,
+                GetDepositInfoResponse, GetDepositMerkleProofQuery, GetDepositMerkleProofResponse,
+                GetLatestIncludedDepositIndexResponse, GetNextDepositIndexResponse,
+                GetSyncStatusResponse, GetUpdateWitnessQuery, GetUpdateWitnessResponse,
+                GetValidityProofQuery, GetValidityProofResponse, GetValidityWitnessQuery,
+                GetValidityWitnessResponse,
+            },
         },
     },
+    utils::bloom_filter::BloomFilter,
 };
 use intmax2_zkp::{
     common::{
@@ -29,7 +35,10 @@ use plonky2::{
     plonk::{config::PoseidonGoldilocksConfig, proof::ProofWithPublicInputs},
 };
 
-use super::utils::query::{get_request, post_request};
+use super::utils::{
+    query::{get_request_with_operation_id, post_request_with_operation_id},
+    retry::RetryConfig,
+};
 
 type F = GoldilocksField;
 type C = PoseidonGoldilocksConfig;
@@ -38,17 +47,31 @@ const D: usize = 2;
 #[derive(Debug, Clone)]
 pub struct ValidityProverClient {
     base_url: String,
+    retry_config: RetryConfig,
 }
 
 impl ValidityProverClient {
     pub fn new(base_url: &str) -> Self {
         ValidityProverClient {
             base_url: base_url.to_string(),
+            retry_config: RetryConfig::default(),
         }
     }
 
+    /// Use `retry_config` for every request made by this client.
+    pub fn with_retry_config(mut self, retry_config: RetryConfig) -> Self {
+        self.retry_config = retry_config;
+        self
+    }
+
     pub async fn sync(&self) -> Result<(), ServerError> {
-        get_request::<(), ()>(&self.base_url, "/validity-prover/sync", None).await?;
+        get_request_with_operation_id::<(), ()>(
+            &self.base_url,
+            "/validity-prover/sync",
+            None,
+            &self.retry_config,
+        )
+        .await?;
         Ok(())
     }
 }
@@ -56,38 +79,61 @@ impl ValidityProverClient {
 #[async_trait(?Send)]
 impl ValidityProverClientInterface for ValidityProverClient {
     async fn get_block_number(&self) -> Result<u32, ServerError> {
-        let response: GetBlockNumberResponse =
-            get_request::<(), _>(&self.base_url, "/validity-prover/block-number", None).await?;
+        let response: GetBlockNumberResponse = get_request_with_operation_id::<(), _>(
+            &self.base_url,
+            "/validity-prover/block-number",
+            None,
+            &self.retry_config,
+        )
+        .await?;
         Ok(response.block_number)
     }
 
     async fn get_validity_proof_block_number(&self) -> Result<u32, ServerError> {
-        let response: GetBlockNumberResponse = get_request::<(), _>(
+        let response: GetBlockNumberResponse = get_request_with_operation_id::<(), _>(
             &self.base_url,
             "/validity-prover/validity-proof-block-number",
             None,
+            &self.retry_config,
         )
         .await?;
         Ok(response.block_number)
     }
 
-    async fn get_next_deposit_index(&self) -> Result<u32, ServerError> {
-        let response: GetNextDepositIndexResponse =
-            get_request::<(), _>(&self.base_url, "/validity-prover/next-deposit-index", None)
-                .await?;
-        Ok(response.deposit_index)
+    async fn get_sync_status(&self) -> Result<SyncStatus, ServerError> {
+        let response: GetSyncStatusResponse = get_request_with_operation_id::<(), _>(
+            &self.base_url,
+            "/validity-prover/sync-status",
+            None,
+            &self.retry_config,
+        )
+        .await?;
+        Ok(response.sync_status)
     }
 
-    async fn get_latest_included_deposit_index(&self) -> Result<Option<u32>, ServerError> {
-        let response: GetLatestIncludedDepositIndexResponse = get_request::<(), _>(
+    async fn get_next_deposit_index(&self) -> Result<u32, ServerError> {
+        let response: GetNextDepositIndexResponse = get_request_with_operation_id::<(), _>(
             &self.base_url,
-            "/validity-prover/latest-included-deposit-index",
+            "/validity-prover/next-deposit-index",
             None,
+            &self.retry_config,
         )
         .await?;
         Ok(response.deposit_index)
     }
 
+    async fn get_latest_included_deposit_index(&self) -> Result<Option<u32>, ServerError> {
+        let response: GetLatestIncludedDepositIndexResponse =
+            get_request_with_operation_id::<(), _>(
+                &self.base_url,
+                "/validity-prover/latest-included-deposit-index",
+                None,
+                &self.retry_config,
+            )
+            .await?;
+        Ok(response.deposit_index)
+    }
+
     async fn get_update_witness(
         &self,
         pubkey: U256,
@@ -101,10 +147,11 @@ impl ValidityProverClientInterface for ValidityProverClient {
             leaf_block_number,
             is_prev_account_tree,
         };
-        let response: GetUpdateWitnessResponse = get_request(
+        let response: GetUpdateWitnessResponse = get_request_with_operation_id(
             &self.base_url,
             "/validity-prover/get-update-witness",
             Some(query),
+            &self.retry_config,
         )
         .await?;
         Ok(response.update_witness)
@@ -115,10 +162,11 @@ impl ValidityProverClientInterface for ValidityProverClient {
         pubkey_salt_hash: Bytes32,
     ) -> Result<Option<DepositInfo>, ServerError> {
         let query = GetDepositInfoQuery { pubkey_salt_hash };
-        let response: GetDepositInfoResponse = get_request(
+        let response: GetDepositInfoResponse = get_request_with_operation_id(
             &self.base_url,
             "/validity-prover/get-deposit-info",
             Some(query),
+            &self.retry_config,
         )
         .await?;
         Ok(response.deposit_info)
@@ -135,10 +183,11 @@ impl ValidityProverClientInterface for ValidityProverClient {
                 pubkey_salt_hashes: chunk.to_vec(),
             };
 
-            let response: GetDepositInfoBatchResponse = post_request(
+            let response: GetDepositInfoBatchResponse = post_request_with_operation_id(
                 &self.base_url,
                 "/validity-prover/get-deposit-info-batch",
                 Some(&request),
+                &self.retry_config,
             )
             .await?;
 
@@ -148,15 +197,31 @@ impl ValidityProverClientInterface for ValidityProverClient {
         Ok(all_deposit_info)
     }
 
+    async fn get_deposit_bloom_filter(
+        &self,
+        from_timestamp: u64,
+    ) -> Result<BloomFilter, ServerError> {
+        let query = GetDepositBloomFilterQuery { from_timestamp };
+        let response: GetDepositBloomFilterResponse = get_request_with_operation_id(
+            &self.base_url,
+            "/validity-prover/get-deposit-bloom-filter",
+            Some(query),
+            &self.retry_config,
+        )
+        .await?;
+        Ok(response.bloom_filter)
+    }
+
     async fn get_block_number_by_tx_tree_root(
         &self,
         tx_tree_root: Bytes32,
     ) -> Result<Option<u32>, ServerError> {
         let query = GetBlockNumberByTxTreeRootQuery { tx_tree_root };
-        let response: GetBlockNumberByTxTreeRootResponse = get_request(
+        let response: GetBlockNumberByTxTreeRootResponse = get_request_with_operation_id(
             &self.base_url,
             "/validity-prover/get-block-number-by-tx-tree-root",
             Some(query),
+            &self.retry_config,
         )
         .await?;
         Ok(response.block_number)
@@ -172,10 +237,11 @@ impl ValidityProverClientInterface for ValidityProverClient {
             let request = GetBlockNumberByTxTreeRootBatchRequest {
                 tx_tree_roots: chunk.to_vec(),
             };
-            let response: GetBlockNumberByTxTreeRootBatchResponse = post_request(
+            let response: GetBlockNumberByTxTreeRootBatchResponse = post_request_with_operation_id(
                 &self.base_url,
                 "/validity-prover/get-block-number-by-tx-tree-root-batch",
                 Some(&request),
+                &self.retry_config,
             )
             .await?;
             all_block_numbers.extend(response.block_numbers);
@@ -189,10 +255,11 @@ impl ValidityProverClientInterface for ValidityProverClient {
         block_number: u32,
     ) -> Result<ValidityWitness, ServerError> {
         let query = GetValidityWitnessQuery { block_number };
-        let response: GetValidityWitnessResponse = get_request(
+        let response: GetValidityWitnessResponse = get_request_with_operation_id(
             &self.base_url,
             "/validity-prover/get-validity-witness",
             Some(query),
+            &self.retry_config,
         )
         .await?;
         Ok(response.validity_witness)
@@ -203,10 +270,11 @@ impl ValidityProverClientInterface for ValidityProverClient {
         block_number: u32,
     ) -> Result<ProofWithPublicInputs<F, C, D>, ServerError> {
         let query = GetValidityProofQuery { block_number };
-        let response: GetValidityProofResponse = get_request(
+        let response: GetValidityProofResponse = get_request_with_operation_id(
             &self.base_url,
             "/validity-prover/get-validity-proof",
             Some(query),
+            &self.retry_config,
         )
         .await?;
         let validity_proof = response.validity_proof.decompress().map_err(|e| {
@@ -224,10 +292,11 @@ impl ValidityProverClientInterface for ValidityProverClient {
             root_block_number,
             leaf_block_number,
         };
-        let response: GetBlockMerkleProofResponse = get_request(
+        let response: GetBlockMerkleProofResponse = get_request_with_operation_id(
             &self.base_url,
             "/validity-prover/get-block-merkle-proof",
             Some(query),
+            &self.retry_config,
         )
         .await?;
         Ok(response.block_merkle_proof)
@@ -242,10 +311,11 @@ impl ValidityProverClientInterface for ValidityProverClient {
             block_number,
             deposit_index,
         };
-        let response: GetDepositMerkleProofResponse = get_request(
+        let response: GetDepositMerkleProofResponse = get_request_with_operation_id(
             &self.base_url,
             "/validity-prover/get-deposit-merkle-proof",
             Some(query),
+            &self.retry_config,
         )
         .await?;
         Ok(response.deposit_merkle_proof)
@@ -253,10 +323,11 @@ impl ValidityProverClientInterface for ValidityProverClient {
 
     async fn get_account_info(&self, pubkey: U256) -> Result<AccountInfo, ServerError> {
         let query = GetAccountInfoQuery { pubkey };
-        let response: GetAccountInfoResponse = get_request(
+        let response: GetAccountInfoResponse = get_request_with_operation_id(
             &self.base_url,
             "/validity-prover/get-account-info",
             Some(query),
+            &self.retry_config,
         )
         .await?;
         Ok(response.account_info)
@@ -272,10 +343,11 @@ impl ValidityProverClientInterface for ValidityProverClient {
             let request = GetAccountInfoBatchRequest {
                 pubkeys: chunk.to_vec(),
             };
-            let response: GetAccountInfoBatchResponse = post_request(
+            let response: GetAccountInfoBatchResponse = post_request_with_operation_id(
                 &self.base_url,
                 "/validity-prover/get-account-info-batch",
                 Some(&request),
+                &self.retry_config,
             )
             .await?;
             all_account_info.extend(response.account_info);
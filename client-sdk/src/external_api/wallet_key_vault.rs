@@ -1,6 +1,6 @@
 use super::utils::query::post_request;
 use crate::{
-    client::key_from_eth::generate_intmax_account_from_eth_key,
+    client::key_from_eth::generate_intmax_account_from_eth_key_with_context,
     external_api::contract::utils::get_address_from_private_key,
 };
 use alloy::{
@@ -137,12 +137,24 @@ pub fn mnemonic_to_keyset(
     mnemonic: &Mnemonic<English>,
     redeposit_index: u32,
     wallet_index: u32,
+) -> KeySet {
+    mnemonic_to_keyset_with_context(mnemonic, redeposit_index, wallet_index, None)
+}
+
+/// Same as `mnemonic_to_keyset`, with a custom derivation context folded into the derivation of
+/// the intmax key. See `generate_intmax_account_from_eth_key_with_context`: a different context
+/// gives a completely different account.
+pub fn mnemonic_to_keyset_with_context(
+    mnemonic: &Mnemonic<English>,
+    redeposit_index: u32,
+    wallet_index: u32,
+    derivation_context: Option<&str>,
 ) -> KeySet {
     let derive_path = format!("m/44'/60'/{redeposit_index}'/0/{wallet_index}");
     let derived_priv_key = mnemonic.derive_key(derive_path.as_str(), None).unwrap();
     let key: &SigningKey = derived_priv_key.as_ref();
     let signing_key = PrivateKeySigner::from_signing_key(key.clone());
-    generate_intmax_account_from_eth_key(signing_key.to_bytes())
+    generate_intmax_account_from_eth_key_with_context(signing_key.to_bytes(), derivation_context)
 }
 
 #[cfg(test)]
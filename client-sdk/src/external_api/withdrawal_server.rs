@@ -1,18 +1,30 @@
-use super::utils::query::{get_request, post_request};
+use super::utils::{
+    query::{get_request_with_operation_id, post_request_with_operation_id},
+    retry::RetryConfig,
+};
 use async_trait::async_trait;
 use intmax2_interfaces::{
     api::{
         error::ServerError,
+        store_vault_server::types::{MetaDataCursor, MetaDataCursorResponse},
         withdrawal_server::{
             interface::{
                 ClaimFeeInfo, ClaimInfo, FeeResult, WithdrawalFeeInfo, WithdrawalInfo,
-                WithdrawalServerClientInterface,
+                WithdrawalRequestEntry, WithdrawalServerClientInterface, WithdrawalSummary,
             },
             types::{
+                withdrawal_idempotency_key, GetClaimInfoPagedRequest, GetClaimInfoPagedResponse,
                 GetClaimInfoRequest, GetClaimInfoResponse, GetWithdrawalInfoByRecipientQuery,
-                GetWithdrawalInfoRequest, GetWithdrawalInfoResponse, RequestClaimRequest,
-                RequestClaimResponse, RequestWithdrawalRequest, RequestWithdrawalResponse,
+                GetWithdrawalInfoPagedRequest, GetWithdrawalInfoPagedResponse,
+                GetWithdrawalInfoRequest, GetWithdrawalInfoResponse,
+                GetWithdrawalSummaryByRecipientQuery, GetWithdrawalSummaryByRecipientResponse,
+                RegisterWithdrawalWebhookRequest, RegisterWithdrawalWebhookResponse,
+                RequestClaimRequest, RequestClaimResponse, RequestWithdrawalBatchRequest,
+                RequestWithdrawalBatchResponse, RequestWithdrawalRequest,
+                RequestWithdrawalResponse, UnregisterWithdrawalWebhookRequest,
+                UnregisterWithdrawalWebhookResponse,
             },
+            webhook::WithdrawalWebhook,
         },
     },
     utils::signature::Signable,
@@ -35,27 +47,45 @@ const D: usize = 2;
 #[derive(Debug, Clone)]
 pub struct WithdrawalServerClient {
     base_url: String,
+    retry_config: RetryConfig,
 }
 
 impl WithdrawalServerClient {
     pub fn new(base_url: &str) -> Self {
         WithdrawalServerClient {
             base_url: base_url.to_string(),
+            retry_config: RetryConfig::default(),
         }
     }
+
+    /// Use `retry_config` for every request made by this client.
+    pub fn with_retry_config(mut self, retry_config: RetryConfig) -> Self {
+        self.retry_config = retry_config;
+        self
+    }
 }
 
 #[async_trait(?Send)]
 impl WithdrawalServerClientInterface for WithdrawalServerClient {
     async fn get_withdrawal_fee(&self) -> Result<WithdrawalFeeInfo, ServerError> {
-        let response: WithdrawalFeeInfo =
-            get_request::<(), _>(&self.base_url, "/withdrawal-server/withdrawal-fee", None).await?;
+        let response: WithdrawalFeeInfo = get_request_with_operation_id::<(), _>(
+            &self.base_url,
+            "/withdrawal-server/withdrawal-fee",
+            None,
+            &self.retry_config,
+        )
+        .await?;
         Ok(response)
     }
 
     async fn get_claim_fee(&self) -> Result<ClaimFeeInfo, ServerError> {
-        let response: ClaimFeeInfo =
-            get_request::<(), _>(&self.base_url, "/withdrawal-server/claim-fee", None).await?;
+        let response: ClaimFeeInfo = get_request_with_operation_id::<(), _>(
+            &self.base_url,
+            "/withdrawal-server/claim-fee",
+            None,
+            &self.retry_config,
+        )
+        .await?;
         Ok(response)
     }
 
@@ -70,17 +100,55 @@ impl WithdrawalServerClientInterface for WithdrawalServerClient {
             single_withdrawal_proof: single_withdrawal_proof.clone(),
             fee_token_index,
             fee_transfer_digests: fee_transfer_digests.to_vec(),
+            idempotency_key: Some(withdrawal_idempotency_key(single_withdrawal_proof)),
         };
         let request_with_auth = request.sign(key, TIME_TO_EXPIRY);
-        let result: RequestWithdrawalResponse = post_request(
+        let result: RequestWithdrawalResponse = post_request_with_operation_id(
             &self.base_url,
             "/withdrawal-server/request-withdrawal",
             Some(&request_with_auth),
+            &self.retry_config,
         )
         .await?;
         Ok(result.fee_result)
     }
 
+    async fn request_withdrawal_batch(
+        &self,
+        key: KeySet,
+        withdrawals: &[WithdrawalRequestEntry],
+    ) -> Result<Vec<FeeResult>, ServerError> {
+        let request = RequestWithdrawalBatchRequest {
+            withdrawals: withdrawals
+                .iter()
+                .map(|withdrawal| RequestWithdrawalRequest {
+                    single_withdrawal_proof: withdrawal.single_withdrawal_proof.clone(),
+                    fee_token_index: withdrawal.fee_token_index,
+                    fee_transfer_digests: withdrawal.fee_transfer_digests.clone(),
+                    idempotency_key: Some(withdrawal_idempotency_key(
+                        &withdrawal.single_withdrawal_proof,
+                    )),
+                })
+                .collect(),
+        };
+        let request_with_auth = request.sign(key, TIME_TO_EXPIRY);
+        let result: RequestWithdrawalBatchResponse = post_request_with_operation_id(
+            &self.base_url,
+            "/withdrawal-server/request-withdrawal-batch",
+            Some(&request_with_auth),
+            &self.retry_config,
+        )
+        .await?;
+        if result.fee_results.len() != withdrawals.len() {
+            return Err(ServerError::InvalidResponse(format!(
+                "expected {} fee results, got {}",
+                withdrawals.len(),
+                result.fee_results.len()
+            )));
+        }
+        Ok(result.fee_results)
+    }
+
     async fn request_claim(
         &self,
         key: KeySet,
@@ -94,10 +162,11 @@ impl WithdrawalServerClientInterface for WithdrawalServerClient {
             fee_transfer_digests: fee_transfer_digests.to_vec(),
         };
         let request_with_auth = request.sign(key, TIME_TO_EXPIRY);
-        let result: RequestClaimResponse = post_request(
+        let result: RequestClaimResponse = post_request_with_operation_id(
             &self.base_url,
             "/withdrawal-server/request-claim",
             Some(&request_with_auth),
+            &self.retry_config,
         )
         .await?;
         Ok(result.fee_result)
@@ -106,38 +175,134 @@ impl WithdrawalServerClientInterface for WithdrawalServerClient {
     async fn get_withdrawal_info(&self, key: KeySet) -> Result<Vec<WithdrawalInfo>, ServerError> {
         let request = GetWithdrawalInfoRequest;
         let request_with_auth = request.sign(key, TIME_TO_EXPIRY);
-        let response: GetWithdrawalInfoResponse = post_request(
+        let response: GetWithdrawalInfoResponse = post_request_with_operation_id(
             &self.base_url,
             "/withdrawal-server/get-withdrawal-info",
             Some(&request_with_auth),
+            &self.retry_config,
         )
         .await?;
         Ok(response.withdrawal_info)
     }
 
+    async fn get_withdrawal_info_paged(
+        &self,
+        key: KeySet,
+        cursor: &MetaDataCursor,
+    ) -> Result<(Vec<WithdrawalInfo>, MetaDataCursorResponse), ServerError> {
+        let request = GetWithdrawalInfoPagedRequest {
+            cursor: cursor.clone(),
+        };
+        let request_with_auth = request.sign(key, TIME_TO_EXPIRY);
+        let response: GetWithdrawalInfoPagedResponse = post_request_with_operation_id(
+            &self.base_url,
+            "/withdrawal-server/get-withdrawal-info-paged",
+            Some(&request_with_auth),
+            &self.retry_config,
+        )
+        .await?;
+        Ok((response.withdrawal_info, response.cursor_response))
+    }
+
     async fn get_withdrawal_info_by_recipient(
         &self,
         recipient: Address,
     ) -> Result<Vec<WithdrawalInfo>, ServerError> {
         let query = GetWithdrawalInfoByRecipientQuery { recipient };
-        let response: GetWithdrawalInfoResponse = get_request(
+        let response: GetWithdrawalInfoResponse = get_request_with_operation_id(
             &self.base_url,
             "/withdrawal-server/get-withdrawal-info-by-recipient",
             Some(query),
+            &self.retry_config,
         )
         .await?;
         Ok(response.withdrawal_info)
     }
 
+    async fn get_withdrawal_summary_by_recipient(
+        &self,
+        recipient: Address,
+    ) -> Result<Vec<WithdrawalSummary>, ServerError> {
+        let query = GetWithdrawalSummaryByRecipientQuery { recipient };
+        let response: GetWithdrawalSummaryByRecipientResponse = get_request_with_operation_id(
+            &self.base_url,
+            "/withdrawal-server/get-withdrawal-summary-by-recipient",
+            Some(query),
+            &self.retry_config,
+        )
+        .await?;
+        Ok(response.summary)
+    }
+
     async fn get_claim_info(&self, key: KeySet) -> Result<Vec<ClaimInfo>, ServerError> {
         let request = GetClaimInfoRequest;
         let request_with_auth = request.sign(key, TIME_TO_EXPIRY);
-        let response: GetClaimInfoResponse = post_request(
+        let response: GetClaimInfoResponse = post_request_with_operation_id(
             &self.base_url,
             "/withdrawal-server/get-claim-info",
             Some(&request_with_auth),
+            &self.retry_config,
         )
         .await?;
         Ok(response.claim_info)
     }
+
+    async fn get_claim_info_paged(
+        &self,
+        key: KeySet,
+        cursor: &MetaDataCursor,
+    ) -> Result<(Vec<ClaimInfo>, MetaDataCursorResponse), ServerError> {
+        let request = GetClaimInfoPagedRequest {
+            cursor: cursor.clone(),
+        };
+        let request_with_auth = request.sign(key, TIME_TO_EXPIRY);
+        let response: GetClaimInfoPagedResponse = post_request_with_operation_id(
+            &self.base_url,
+            "/withdrawal-server/get-claim-info-paged",
+            Some(&request_with_auth),
+            &self.retry_config,
+        )
+        .await?;
+        Ok((response.claim_info, response.cursor_response))
+    }
+
+    async fn register_withdrawal_webhook(
+        &self,
+        key: KeySet,
+        url: &str,
+        recipient: Option<Address>,
+    ) -> Result<WithdrawalWebhook, ServerError> {
+        let request = RegisterWithdrawalWebhookRequest {
+            url: url.to_string(),
+            recipient,
+        };
+        let request_with_auth = request.sign(key, TIME_TO_EXPIRY);
+        let response: RegisterWithdrawalWebhookResponse = post_request_with_operation_id(
+            &self.base_url,
+            "/withdrawal-server/register-withdrawal-webhook",
+            Some(&request_with_auth),
+            &self.retry_config,
+        )
+        .await?;
+        Ok(response.webhook)
+    }
+
+    async fn unregister_withdrawal_webhook(
+        &self,
+        key: KeySet,
+        webhook_id: &str,
+    ) -> Result<bool, ServerError> {
+        let request = UnregisterWithdrawalWebhookRequest {
+            webhook_id: webhook_id.to_string(),
+        };
+        let request_with_auth = request.sign(key, TIME_TO_EXPIRY);
+        let response: UnregisterWithdrawalWebhookResponse = post_request_with_operation_id(
+            &self.base_url,
+            "/withdrawal-server/unregister-withdrawal-webhook",
+            Some(&request_with_auth),
+            &self.retry_config,
+        )
+        .await?;
+        Ok(response.removed)
+    }
 }
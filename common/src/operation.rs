@@ -0,0 +1,3 @@
+/// Header carrying the id of the client operation (e.g. a sync) that sent a request, so that
+/// the server logs of the request can be joined with the client logs of the operation.
+pub const OPERATION_ID_HEADER: &str = "x-operation-id";
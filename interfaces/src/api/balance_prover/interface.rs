@@ -24,6 +24,12 @@ const D: usize = 2;
 
 #[async_trait(?Send)]
 pub trait BalanceProverClientInterface: Sync + Send {
+    /// Base URL of the prover, for error reporting.
+    fn endpoint(&self) -> String;
+
+    /// Check that the prover is reachable, without retrying.
+    async fn health(&self) -> Result<(), ServerError>;
+
     async fn prove_spent(
         &self,
         key: KeySet,
@@ -1,14 +1,16 @@
 use async_trait::async_trait;
 use intmax2_zkp::{
     common::{
-        block_builder::BlockProposal, signature_content::flatten::FlatG2, tx::Tx,
-        witness::transfer_witness::TransferWitness,
+        signature_content::flatten::FlatG2, tx::Tx, witness::transfer_witness::TransferWitness,
     },
     ethereum_types::{address::Address, u256::U256},
 };
 use serde::{Deserialize, Serialize};
 
-use crate::{api::error::ServerError, data::transfer_data::TransferData};
+use crate::{
+    api::{block_builder::types::QueryProposalResponse, error::ServerError},
+    data::transfer_data::TransferData,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -16,6 +18,9 @@ pub struct FeeProof {
     pub sender_proof_set_ephemeral_key: U256,
     pub fee_transfer_witness: TransferWitness,
     pub collateral_block: Option<CollateralBlock>,
+    /// Additional fee transfers of the same tx when the fee is split across multiple tokens.
+    #[serde(default)]
+    pub additional_fee_transfer_witnesses: Vec<TransferWitness>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -61,7 +66,8 @@ pub trait BlockBuilderClientInterface: Sync + Send {
         block_builder_url: &str,
     ) -> Result<BlockBuilderFeeInfo, ServerError>;
 
-    // Send tx request to the block builder
+    // Send tx request to the block builder. If `valid_until` is set, the request is dropped
+    // instead of built if it is still queued after that unix timestamp.
     async fn send_tx_request(
         &self,
         block_builder_url: &str,
@@ -69,14 +75,17 @@ pub trait BlockBuilderClientInterface: Sync + Send {
         pubkey: U256,
         tx: Tx,
         fee_proof: Option<FeeProof>,
+        valid_until: Option<u64>,
     ) -> Result<String, ServerError>;
 
-    // Query tx tree root proposal from the block builder
+    // Query tx tree root proposal from the block builder. The proposal must be signed before
+    // `block_sign_payload.expiry`, after which the block builder rejects the signature. The
+    // response tells if the request expired instead.
     async fn query_proposal(
         &self,
         block_builder_url: &str,
         request_id: &str,
-    ) -> Result<Option<BlockProposal>, ServerError>;
+    ) -> Result<QueryProposalResponse, ServerError>;
 
     // Send signature to the block builder
     async fn post_signature(
@@ -13,6 +13,9 @@ pub struct TxRequestRequest {
     pub pubkey: U256,
     pub tx: Tx,
     pub fee_proof: Option<FeeProof>,
+    /// Unix timestamp after which the block builder drops the request instead of building it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valid_until: Option<u64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -31,6 +34,9 @@ pub struct QueryProposalRequest {
 #[serde(rename_all = "camelCase")]
 pub struct QueryProposalResponse {
     pub block_proposal: Option<BlockProposal>,
+    /// The request passed its `valid_until` before it was built into a block, and was dropped
+    #[serde(default)]
+    pub is_expired: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -18,6 +18,9 @@ pub enum ServerError {
     #[error("Unknown error: {0}")]
     UnknownError(String),
 
+    #[error("Timeout: {0}")]
+    Timeout(String),
+
     #[error("Serialization error: {0}")]
     SerializeError(String),
 
@@ -1,5 +1,5 @@
 use crate::{
-    api::store_vault_server::types::{MetaDataCursor, MetaDataCursorResponse},
+    api::store_vault_server::types::{MetaDataCursor, MetaDataCursorResponse, TopicEntryCount},
     data::meta_data::MetaData,
     utils::signature::Signable,
 };
@@ -27,15 +27,20 @@ pub struct S3PreSaveSnapshotRequest {
     pub topic: String,
     pub pubkey: U256,
     pub digest: Bytes32,
+    /// Size of the snapshot in bytes. The server offers a multipart upload for large snapshots
+    /// if it is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
 }
 
 impl Signable for S3PreSaveSnapshotRequest {
     fn content(&self) -> Vec<u8> {
-        [
-            content_prefix("pre_save_snapshot"),
-            bincode::serialize(&(&self.topic, self.pubkey, self.digest)).unwrap(),
-        ]
-        .concat()
+        // the size is only signed if set, so that the signatures of older clients still verify
+        let content = match self.size {
+            Some(size) => bincode::serialize(&(&self.topic, self.pubkey, self.digest, size)),
+            None => bincode::serialize(&(&self.topic, self.pubkey, self.digest)),
+        };
+        [content_prefix("pre_save_snapshot"), content.unwrap()].concat()
     }
 }
 
@@ -43,6 +48,35 @@ impl Signable for S3PreSaveSnapshotRequest {
 #[serde(rename_all = "camelCase")]
 pub struct S3PreSaveSnapshotResponse {
     pub presigned_url: String,
+    /// Set if the snapshot must be uploaded in parts instead of to `presigned_url`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub multipart_upload: Option<S3MultipartUpload>,
+}
+
+/// Multipart upload started by the server. The i-th part is the i-th `part_size` bytes of the
+/// data, uploaded with a PUT to `part_urls[i]`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct S3MultipartUpload {
+    pub upload_id: String,
+    pub part_size: u64,
+    pub part_urls: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct S3CompletedPart {
+    /// 1-indexed
+    pub part_number: u32,
+    /// ETag header of the response to the part upload
+    pub etag: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct S3CompletedMultipartUpload {
+    pub upload_id: String,
+    pub parts: Vec<S3CompletedPart>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -52,15 +86,32 @@ pub struct S3SaveSnapshotRequest {
     pub pubkey: U256,
     pub prev_digest: Option<Bytes32>,
     pub digest: Bytes32,
+    /// Parts of the multipart upload offered by `pre_save_snapshot`, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub multipart_upload: Option<S3CompletedMultipartUpload>,
 }
 
 impl Signable for S3SaveSnapshotRequest {
     fn content(&self) -> Vec<u8> {
-        [
-            content_prefix("save_snapshot"),
-            bincode::serialize(&(&self.topic, self.pubkey, self.digest, self.prev_digest)).unwrap(),
-        ]
-        .concat()
+        let content = match &self.multipart_upload {
+            Some(upload) => {
+                let parts = upload
+                    .parts
+                    .iter()
+                    .map(|part| (part.part_number, &part.etag))
+                    .collect::<Vec<_>>();
+                bincode::serialize(&(
+                    &self.topic,
+                    self.pubkey,
+                    self.digest,
+                    self.prev_digest,
+                    &upload.upload_id,
+                    parts,
+                ))
+            }
+            None => bincode::serialize(&(&self.topic, self.pubkey, self.digest, self.prev_digest)),
+        };
+        [content_prefix("save_snapshot"), content.unwrap()].concat()
     }
 }
 
@@ -160,6 +211,34 @@ pub struct S3GetDataSequenceResponse {
     pub cursor_response: MetaDataCursorResponse,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct S3ListTopicsRequest {
+    pub pubkey: U256,
+}
+
+impl Signable for S3ListTopicsRequest {
+    fn content(&self) -> Vec<u8> {
+        [
+            content_prefix("list_topics"),
+            bincode::serialize(&self.pubkey).unwrap(),
+        ]
+        .concat()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct S3ListTopicsResponse {
+    pub topics: Vec<TopicEntryCount>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct S3CompactSnapshotsResponse {
+    pub deleted: u64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PresignedUrlWithMetaData {
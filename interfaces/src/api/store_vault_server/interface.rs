@@ -8,7 +8,7 @@ use serde_with::{base64::Base64, serde_as};
 
 use crate::{api::error::ServerError, utils::signature::Auth};
 
-use super::types::{DataWithMetaData, MetaDataCursor, MetaDataCursorResponse};
+use super::types::{DataWithMetaData, MetaDataCursor, MetaDataCursorResponse, TopicEntryCount};
 
 pub const MAX_BATCH_SIZE: usize = 256;
 
@@ -60,4 +60,7 @@ pub trait StoreVaultClientInterface: Sync + Send {
         cursor: &MetaDataCursor,
         auth: &Auth,
     ) -> Result<(Vec<DataWithMetaData>, MetaDataCursorResponse), ServerError>;
+
+    /// List the topics stored for `key` with their number of entries, sorted by topic.
+    async fn list_topics(&self, key: KeySet) -> Result<Vec<TopicEntryCount>, ServerError>;
 }
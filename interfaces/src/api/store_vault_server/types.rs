@@ -145,6 +145,37 @@ pub struct GetDataSequenceResponse {
     pub cursor_response: MetaDataCursorResponse,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListTopicsRequest {
+    pub pubkey: U256,
+}
+
+impl Signable for ListTopicsRequest {
+    fn content(&self) -> Vec<u8> {
+        [
+            content_prefix("list_topics"),
+            bincode::serialize(&self.pubkey).unwrap(),
+        ]
+        .concat()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListTopicsResponse {
+    pub topics: Vec<TopicEntryCount>,
+}
+
+/// A topic stored for a pubkey and its number of entries. On the servers a snapshot topic has
+/// one entry, while a local backup counts every saved version of it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TopicEntryCount {
+    pub topic: String,
+    pub count: u64,
+}
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct MetaDataCursor {
@@ -13,7 +13,7 @@ use plonky2::{
 };
 use serde::{Deserialize, Serialize};
 
-use crate::api::error::ServerError;
+use crate::{api::error::ServerError, utils::bloom_filter::BloomFilter};
 
 type F = GoldilocksField;
 type C = PoseidonGoldilocksConfig;
@@ -40,6 +40,37 @@ pub struct AccountInfo {
     pub last_block_number: u32,
 }
 
+/// How far the validity prover is from the chain head.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncStatus {
+    /// True once the latest validity proof is within the sync threshold of the chain head.
+    /// While false, the prover is still catching up and balances may be stale.
+    pub is_synced_to_tip: bool,
+    /// Latest block number posted on chain, when last checked
+    pub chain_head_block_number: u32,
+    /// Latest block number for which a validity proof exists
+    pub validity_proof_block_number: u32,
+    /// Number of blocks between the chain head and the latest validity proof
+    pub block_gap: u32,
+}
+
+impl SyncStatus {
+    pub fn new(
+        chain_head_block_number: u32,
+        validity_proof_block_number: u32,
+        threshold: u32,
+    ) -> Self {
+        let block_gap = chain_head_block_number.saturating_sub(validity_proof_block_number);
+        Self {
+            is_synced_to_tip: block_gap <= threshold,
+            chain_head_block_number,
+            validity_proof_block_number,
+            block_gap,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TransitionProofTask {
@@ -62,6 +93,9 @@ pub trait ValidityProverClientInterface: Sync + Send {
 
     async fn get_validity_proof_block_number(&self) -> Result<u32, ServerError>;
 
+    /// Whether the validity prover has caught up with the chain head.
+    async fn get_sync_status(&self) -> Result<SyncStatus, ServerError>;
+
     async fn get_next_deposit_index(&self) -> Result<u32, ServerError>;
 
     async fn get_latest_included_deposit_index(&self) -> Result<Option<u32>, ServerError>;
@@ -84,6 +118,13 @@ pub trait ValidityProverClientInterface: Sync + Send {
         pubkey_salt_hashes: &[Bytes32],
     ) -> Result<Vec<Option<DepositInfo>>, ServerError>;
 
+    /// Returns a bloom filter of the pubkey salt hashes of all deposits made at or after
+    /// `from_timestamp`. The filter has no false negatives.
+    async fn get_deposit_bloom_filter(
+        &self,
+        from_timestamp: u64,
+    ) -> Result<BloomFilter, ServerError>;
+
     async fn get_block_number_by_tx_tree_root(
         &self,
         tx_tree_root: Bytes32,
@@ -1,6 +1,8 @@
 use super::interface::DepositInfo;
 use crate::{
-    api::validity_prover::interface::AccountInfo, data::proof_compression::CompressedValidityProof,
+    api::validity_prover::interface::{AccountInfo, SyncStatus},
+    data::proof_compression::CompressedValidityProof,
+    utils::bloom_filter::BloomFilter,
 };
 use intmax2_zkp::{
     common::{
@@ -21,6 +23,12 @@ pub struct GetBlockNumberResponse {
     pub block_number: u32,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetSyncStatusResponse {
+    pub sync_status: SyncStatus,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GetNextDepositIndexResponse {
@@ -78,6 +86,18 @@ pub struct GetDepositInfoBatchResponse {
     pub deposit_info: Vec<Option<DepositInfo>>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetDepositBloomFilterQuery {
+    pub from_timestamp: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetDepositBloomFilterResponse {
+    pub bloom_filter: BloomFilter,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GetBlockNumberByTxTreeRootQuery {
@@ -12,12 +12,29 @@ use plonky2::{
 use plonky2_keccak::utils::solidity_keccak256;
 use serde::{Deserialize, Serialize};
 
-use crate::api::{block_builder::interface::Fee, error::ServerError};
+use crate::api::{
+    block_builder::interface::Fee,
+    error::ServerError,
+    store_vault_server::types::{MetaDataCursor, MetaDataCursorResponse},
+};
+
+use super::webhook::WithdrawalWebhook;
 
 type F = GoldilocksField;
 type C = PoseidonGoldilocksConfig;
 const D: usize = 2;
 
+/// Max number of withdrawals in a single `request_withdrawal_batch` call
+pub const MAX_WITHDRAWAL_BATCH_SIZE: usize = 16;
+
+/// A withdrawal of `request_withdrawal_batch`, with the same fields as a single request
+#[derive(Debug, Clone)]
+pub struct WithdrawalRequestEntry {
+    pub single_withdrawal_proof: ProofWithPublicInputs<F, C, D>,
+    pub fee_token_index: Option<u32>,
+    pub fee_transfer_digests: Vec<Bytes32>,
+}
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct WithdrawalFeeInfo {
@@ -31,6 +48,10 @@ pub struct WithdrawalFeeInfo {
 pub struct ClaimFeeInfo {
     pub beneficiary: Option<U256>,
     pub fee: Option<Vec<Fee>>,
+    /// Mining mode that `fee` applies to. `None` if the server does not report it.
+    pub is_faster_mining: Option<bool>,
+    /// Fee for the other mining mode, if the server also accepts claims proved in that mode.
+    pub alternative_mode_fee: Option<Vec<Fee>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -70,6 +91,42 @@ impl ContractWithdrawal {
     }
 }
 
+/// Aggregated withdrawals of a recipient for a single token index.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WithdrawalSummary {
+    pub token_index: u32,
+    /// Sum of withdrawals that have not reached the recipient yet
+    /// (requested, relayed or need_claim)
+    pub pending_amount: U256,
+    /// Sum of successful withdrawals
+    pub completed_amount: U256,
+    /// Number of withdrawals including failed ones
+    pub count: u64,
+}
+
+impl WithdrawalSummary {
+    pub fn new(token_index: u32) -> Self {
+        Self {
+            token_index,
+            pending_amount: U256::default(),
+            completed_amount: U256::default(),
+            count: 0,
+        }
+    }
+
+    pub fn add(&mut self, status: &WithdrawalStatus, amount: U256) {
+        match status {
+            WithdrawalStatus::Requested
+            | WithdrawalStatus::Relayed
+            | WithdrawalStatus::NeedClaim => self.pending_amount += amount,
+            WithdrawalStatus::Success => self.completed_amount += amount,
+            WithdrawalStatus::Failed => {}
+        }
+        self.count += 1;
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub enum WithdrawalStatus {
@@ -139,6 +196,14 @@ pub trait WithdrawalServerClientInterface: Sync + Send {
         fee_transfer_digests: &[Bytes32],
     ) -> Result<FeeResult, ServerError>;
 
+    /// Request up to `MAX_WITHDRAWAL_BATCH_SIZE` withdrawals in a single call. The fee results
+    /// are aligned with `withdrawals`, and each withdrawal keeps its own fee transfers.
+    async fn request_withdrawal_batch(
+        &self,
+        key: KeySet,
+        withdrawals: &[WithdrawalRequestEntry],
+    ) -> Result<Vec<FeeResult>, ServerError>;
+
     async fn request_claim(
         &self,
         key: KeySet,
@@ -149,10 +214,47 @@ pub trait WithdrawalServerClientInterface: Sync + Send {
 
     async fn get_withdrawal_info(&self, key: KeySet) -> Result<Vec<WithdrawalInfo>, ServerError>;
 
+    /// Get a page of the withdrawal infos, ordered by request timestamp then withdrawal hash.
+    async fn get_withdrawal_info_paged(
+        &self,
+        key: KeySet,
+        cursor: &MetaDataCursor,
+    ) -> Result<(Vec<WithdrawalInfo>, MetaDataCursorResponse), ServerError>;
+
     async fn get_withdrawal_info_by_recipient(
         &self,
         recipient: Address,
     ) -> Result<Vec<WithdrawalInfo>, ServerError>;
 
+    async fn get_withdrawal_summary_by_recipient(
+        &self,
+        recipient: Address,
+    ) -> Result<Vec<WithdrawalSummary>, ServerError>;
+
     async fn get_claim_info(&self, key: KeySet) -> Result<Vec<ClaimInfo>, ServerError>;
+
+    /// Get a page of the claim infos, ordered by request timestamp then nullifier.
+    async fn get_claim_info_paged(
+        &self,
+        key: KeySet,
+        cursor: &MetaDataCursor,
+    ) -> Result<(Vec<ClaimInfo>, MetaDataCursorResponse), ServerError>;
+
+    /// Register a webhook that is posted a `WithdrawalCompletedEvent` when a withdrawal to
+    /// `recipient`, or of `key` if `None`, completes. Only withdrawals requested after the
+    /// registration are notified. The posts are signed with the returned secret, see
+    /// `verify_webhook_signature`.
+    async fn register_withdrawal_webhook(
+        &self,
+        key: KeySet,
+        url: &str,
+        recipient: Option<Address>,
+    ) -> Result<WithdrawalWebhook, ServerError>;
+
+    /// Remove a webhook registered by `key`. Returns false if there is no such webhook.
+    async fn unregister_withdrawal_webhook(
+        &self,
+        key: KeySet,
+        webhook_id: &str,
+    ) -> Result<bool, ServerError>;
 }
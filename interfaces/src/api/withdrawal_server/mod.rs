@@ -1,2 +1,3 @@
 pub mod interface;
 pub mod types;
+pub mod webhook;
@@ -1,13 +1,22 @@
-use intmax2_zkp::ethereum_types::{address::Address, bytes32::Bytes32};
+use intmax2_zkp::{
+    ethereum_types::{address::Address, bytes32::Bytes32},
+    utils::conversion::ToU64,
+};
 use plonky2::{
     field::goldilocks_field::GoldilocksField,
     plonk::{config::PoseidonGoldilocksConfig, proof::ProofWithPublicInputs},
 };
 use serde::{Deserialize, Serialize};
 
-use crate::utils::signature::Signable;
+use crate::{
+    api::store_vault_server::types::{MetaDataCursor, MetaDataCursorResponse},
+    utils::{digest::get_digest, signature::Signable},
+};
 
-use super::interface::{ClaimInfo, FeeResult, WithdrawalInfo};
+use super::{
+    interface::{ClaimInfo, FeeResult, WithdrawalInfo, WithdrawalSummary},
+    webhook::WithdrawalWebhook,
+};
 
 type F = GoldilocksField;
 type C = PoseidonGoldilocksConfig;
@@ -26,11 +35,14 @@ pub struct RequestWithdrawalRequest {
     pub single_withdrawal_proof: ProofWithPublicInputs<F, C, D>,
     pub fee_token_index: Option<u32>,
     pub fee_transfer_digests: Vec<Bytes32>,
+    /// Key the server deduplicates retried requests on. See `withdrawal_idempotency_key`.
+    #[serde(default)]
+    pub idempotency_key: Option<Bytes32>,
 }
 
 impl Signable for RequestWithdrawalRequest {
     fn content(&self) -> Vec<u8> {
-        [
+        let mut content = [
             content_prefix("request_withdrawal"),
             bincode::serialize(&(
                 self.single_withdrawal_proof.clone(),
@@ -39,16 +51,64 @@ impl Signable for RequestWithdrawalRequest {
             ))
             .unwrap(),
         ]
-        .concat()
+        .concat();
+        // appended only when present so that requests without a key keep the same content
+        if let Some(idempotency_key) = self.idempotency_key {
+            content.extend_from_slice(&idempotency_key.to_bytes_be());
+        }
+        content
     }
 }
 
+/// Idempotency key of a withdrawal request, derived from the withdrawal in the public inputs of
+/// the single withdrawal proof. Retrying the same withdrawal always yields the same key.
+pub fn withdrawal_idempotency_key(
+    single_withdrawal_proof: &ProofWithPublicInputs<F, C, D>,
+) -> Bytes32 {
+    let withdrawal_digest = single_withdrawal_proof
+        .public_inputs
+        .to_u64_vec()
+        .into_iter()
+        .flat_map(|x| x.to_be_bytes())
+        .collect::<Vec<_>>();
+    get_digest(
+        &[
+            content_prefix("withdrawal_idempotency_key"),
+            withdrawal_digest,
+        ]
+        .concat(),
+    )
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RequestWithdrawalResponse {
     pub fee_result: FeeResult,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestWithdrawalBatchRequest {
+    pub withdrawals: Vec<RequestWithdrawalRequest>,
+}
+
+impl Signable for RequestWithdrawalBatchRequest {
+    fn content(&self) -> Vec<u8> {
+        let mut content = content_prefix("request_withdrawal_batch");
+        for withdrawal in &self.withdrawals {
+            content.extend(withdrawal.content());
+        }
+        content
+    }
+}
+
+/// Fee results of a batch request, aligned with its withdrawals
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestWithdrawalBatchResponse {
+    pub fee_results: Vec<FeeResult>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RequestClaimRequest {
@@ -110,8 +170,113 @@ pub struct GetClaimInfoResponse {
     pub claim_info: Vec<ClaimInfo>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetWithdrawalInfoPagedRequest {
+    pub cursor: MetaDataCursor,
+}
+
+impl Signable for GetWithdrawalInfoPagedRequest {
+    fn content(&self) -> Vec<u8> {
+        [
+            content_prefix("get_withdrawal_info_paged"),
+            bincode::serialize(&self.cursor).unwrap(),
+        ]
+        .concat()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetWithdrawalInfoPagedResponse {
+    pub withdrawal_info: Vec<WithdrawalInfo>,
+    pub cursor_response: MetaDataCursorResponse,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetClaimInfoPagedRequest {
+    pub cursor: MetaDataCursor,
+}
+
+impl Signable for GetClaimInfoPagedRequest {
+    fn content(&self) -> Vec<u8> {
+        [
+            content_prefix("get_claim_info_paged"),
+            bincode::serialize(&self.cursor).unwrap(),
+        ]
+        .concat()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetClaimInfoPagedResponse {
+    pub claim_info: Vec<ClaimInfo>,
+    pub cursor_response: MetaDataCursorResponse,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GetWithdrawalInfoByRecipientQuery {
     pub recipient: Address,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetWithdrawalSummaryByRecipientQuery {
+    pub recipient: Address,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetWithdrawalSummaryByRecipientResponse {
+    pub summary: Vec<WithdrawalSummary>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegisterWithdrawalWebhookRequest {
+    pub url: String,
+    /// Recipient whose withdrawals are notified, or `None` for the withdrawals of the signer
+    pub recipient: Option<Address>,
+}
+
+impl Signable for RegisterWithdrawalWebhookRequest {
+    fn content(&self) -> Vec<u8> {
+        [
+            content_prefix("register_withdrawal_webhook"),
+            bincode::serialize(&(self.url.clone(), self.recipient)).unwrap(),
+        ]
+        .concat()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegisterWithdrawalWebhookResponse {
+    pub webhook: WithdrawalWebhook,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnregisterWithdrawalWebhookRequest {
+    pub webhook_id: String,
+}
+
+impl Signable for UnregisterWithdrawalWebhookRequest {
+    fn content(&self) -> Vec<u8> {
+        [
+            content_prefix("unregister_withdrawal_webhook"),
+            self.webhook_id.as_bytes().to_vec(),
+        ]
+        .concat()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnregisterWithdrawalWebhookResponse {
+    /// Whether a webhook of the signer with this id was removed
+    pub removed: bool,
+}
@@ -0,0 +1,84 @@
+use alloy::hex;
+use hmac::{Hmac, Mac};
+use intmax2_zkp::ethereum_types::{address::Address, bytes32::Bytes32};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use super::interface::ContractWithdrawal;
+
+/// Header holding the unix timestamp at which a webhook post was signed
+pub const WEBHOOK_TIMESTAMP_HEADER: &str = "x-intmax-webhook-timestamp";
+
+/// Header holding `sha256=<hex>`, the HMAC-SHA256 of `<timestamp>.<body>` keyed with the secret
+/// of the webhook
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "x-intmax-webhook-signature";
+
+/// A webhook registered on the withdrawal server. The secret is only returned on registration.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WithdrawalWebhook {
+    pub webhook_id: String,
+    pub secret: String,
+}
+
+/// Body posted to a webhook when a withdrawal it covers completes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WithdrawalCompletedEvent {
+    pub webhook_id: String,
+    pub withdrawal_hash: Bytes32,
+    pub l1_tx_hash: Option<Bytes32>,
+    pub recipient: Address,
+    pub contract_withdrawal: ContractWithdrawal,
+}
+
+fn webhook_mac(secret: &str, timestamp: u64, body: &[u8]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac
+}
+
+/// The value of `WEBHOOK_SIGNATURE_HEADER` for a post of `body` at `timestamp`.
+pub fn sign_webhook_payload(secret: &str, timestamp: u64, body: &[u8]) -> String {
+    let tag = webhook_mac(secret, timestamp, body).finalize().into_bytes();
+    format!("sha256={}", hex::encode(tag))
+}
+
+/// Checks the `WEBHOOK_SIGNATURE_HEADER` of a received post in constant time. Receivers should
+/// also reject timestamps too far from their clock, so that a post cannot be replayed.
+pub fn verify_webhook_signature(
+    secret: &str,
+    timestamp: u64,
+    body: &[u8],
+    signature: &str,
+) -> bool {
+    let Some(tag) = signature
+        .strip_prefix("sha256=")
+        .and_then(|tag| hex::decode(tag).ok())
+    else {
+        return false;
+    };
+    webhook_mac(secret, timestamp, body)
+        .verify_slice(&tag)
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sign_webhook_payload, verify_webhook_signature};
+
+    #[test]
+    fn test_webhook_signature() {
+        let body = br#"{"withdrawalHash":"0x01"}"#;
+        let signature = sign_webhook_payload("secret", 100, body);
+        assert!(signature.starts_with("sha256="));
+        assert!(verify_webhook_signature("secret", 100, body, &signature));
+
+        assert!(!verify_webhook_signature("other", 100, body, &signature));
+        assert!(!verify_webhook_signature("secret", 101, body, &signature));
+        assert!(!verify_webhook_signature("secret", 100, b"{}", &signature));
+        assert!(!verify_webhook_signature("secret", 100, body, "sha256=zz"));
+    }
+}
@@ -3,6 +3,15 @@ use serde::{Deserialize, Serialize};
 
 use crate::data::encryption::bls::v1::singed_encryption::V1SignedEncryption;
 
+/// V1 encryption of the plain payload
+pub const UNCOMPRESSED_VERSION: u8 = 1;
+
+/// V1 encryption of the zstd-compressed payload. Clients that only know version 1 cannot read
+/// these entries.
+pub const ZSTD_COMPRESSED_VERSION: u8 = 2;
+
+const ZSTD_COMPRESSION_LEVEL: i32 = 3;
+
 #[derive(Debug, thiserror::Error)]
 pub enum VersionedBlsEncryptionError {
     #[error("Unsupported version")]
@@ -16,6 +25,9 @@ pub enum VersionedBlsEncryptionError {
 
     #[error("Decryption error: {0}")]
     DecryptionError(String),
+
+    #[error("Compression error: {0}")]
+    CompressionError(String),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,13 +44,23 @@ impl VersionedBlsEncryption {
         data: &[u8],
     ) -> Result<Self, VersionedBlsEncryptionError> {
         match version {
-            1 => {
+            UNCOMPRESSED_VERSION => {
                 let encrypted_data = V1SignedEncryption::encrypt(receiver, sender_key, data);
                 Ok(Self {
                     version,
                     data: bincode::serialize(&encrypted_data)?,
                 })
             }
+            ZSTD_COMPRESSED_VERSION => {
+                // compressed before encryption, since ciphertext does not compress
+                let compressed = zstd::bulk::compress(data, ZSTD_COMPRESSION_LEVEL)
+                    .map_err(|e| VersionedBlsEncryptionError::CompressionError(e.to_string()))?;
+                let encrypted_data = V1SignedEncryption::encrypt(receiver, sender_key, &compressed);
+                Ok(Self {
+                    version,
+                    data: bincode::serialize(&encrypted_data)?,
+                })
+            }
             _ => Err(VersionedBlsEncryptionError::UnsupportedVersion),
         }
     }
@@ -49,14 +71,51 @@ impl VersionedBlsEncryption {
         sender: Option<U256>,
     ) -> Result<Vec<u8>, VersionedBlsEncryptionError> {
         match self.version {
-            1 => {
+            UNCOMPRESSED_VERSION => {
                 let encrypted_data: V1SignedEncryption = bincode::deserialize(&self.data)?;
                 let data = encrypted_data
                     .decrypt(receiver_key, sender)
                     .map_err(|e| VersionedBlsEncryptionError::DecryptionError(e.to_string()))?;
                 Ok(data)
             }
+            ZSTD_COMPRESSED_VERSION => {
+                let encrypted_data: V1SignedEncryption = bincode::deserialize(&self.data)?;
+                let compressed = encrypted_data
+                    .decrypt(receiver_key, sender)
+                    .map_err(|e| VersionedBlsEncryptionError::DecryptionError(e.to_string()))?;
+                let data = zstd::stream::decode_all(compressed.as_slice())
+                    .map_err(|e| VersionedBlsEncryptionError::CompressionError(e.to_string()))?;
+                Ok(data)
+            }
             _ => Err(VersionedBlsEncryptionError::UnsupportedVersion),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use intmax2_zkp::common::signature_content::key_set::KeySet;
+
+    use super::{VersionedBlsEncryption, UNCOMPRESSED_VERSION, ZSTD_COMPRESSED_VERSION};
+    use crate::utils::random::default_rng;
+
+    #[test]
+    fn test_compressed_and_uncompressed_entries() {
+        let mut rng = default_rng();
+        let key = KeySet::rand(&mut rng);
+        let data = [0u8, 1, 2, 3].repeat(1000);
+        for version in [UNCOMPRESSED_VERSION, ZSTD_COMPRESSED_VERSION] {
+            let encrypted =
+                VersionedBlsEncryption::encrypt(version, key.pubkey, Some(key), &data).unwrap();
+            assert_eq!(encrypted.version, version);
+            let decrypted = encrypted.decrypt(key, Some(key.pubkey)).unwrap();
+            assert_eq!(decrypted, data);
+        }
+        let uncompressed =
+            VersionedBlsEncryption::encrypt(UNCOMPRESSED_VERSION, key.pubkey, None, &data).unwrap();
+        let compressed =
+            VersionedBlsEncryption::encrypt(ZSTD_COMPRESSED_VERSION, key.pubkey, None, &data)
+                .unwrap();
+        assert!(compressed.data.len() < uncompressed.data.len());
+    }
+}
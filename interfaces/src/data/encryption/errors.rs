@@ -21,4 +21,7 @@ pub enum RsaEncryptionError {
 
     #[error("Decryption error: {0}")]
     DecryptionError(String),
+
+    #[error("Invalid AES key length: expected 32 bytes, got {0}")]
+    InvalidKeyLength(usize),
 }
@@ -1,5 +1,7 @@
 use ::rsa::RsaPublicKey;
-use bls::versioned_encryption::VersionedBlsEncryption;
+use bls::versioned_encryption::{
+    VersionedBlsEncryption, UNCOMPRESSED_VERSION, ZSTD_COMPRESSED_VERSION,
+};
 use errors::{BlsEncryptionError, RsaEncryptionError};
 use intmax2_zkp::{common::signature_content::key_set::KeySet, ethereum_types::u256::U256};
 use rsa::{decrypt_with_aes_key, encrypt_with_rsa, RsaEncryptedMessage};
@@ -23,9 +25,26 @@ pub trait BlsEncryption: Sized + Serialize + DeserializeOwned {
         &self,
         receiver: U256,
         sender_key: Option<KeySet>,
+    ) -> Result<Vec<u8>, BlsEncryptionError> {
+        self.encrypt_with_compression(receiver, sender_key, false)
+    }
+
+    /// Same as `encrypt`, zstd-compressing the payload before encryption if `compress` is set.
+    /// `decrypt` reads both, but clients older than the compressed format cannot read compressed
+    /// entries, so only compress data read by the user's own clients.
+    fn encrypt_with_compression(
+        &self,
+        receiver: U256,
+        sender_key: Option<KeySet>,
+        compress: bool,
     ) -> Result<Vec<u8>, BlsEncryptionError> {
         let data = self.to_bytes();
-        let encrypted_data = VersionedBlsEncryption::encrypt(1, receiver, sender_key, &data)?;
+        let version = if compress {
+            ZSTD_COMPRESSED_VERSION
+        } else {
+            UNCOMPRESSED_VERSION
+        };
+        let encrypted_data = VersionedBlsEncryption::encrypt(version, receiver, sender_key, &data)?;
         Ok(bincode::serialize(&encrypted_data)?)
     }
 
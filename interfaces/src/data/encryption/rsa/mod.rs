@@ -51,6 +51,9 @@ pub fn decrypt_with_aes_key(
     key: &[u8],
     message: &RsaEncryptedMessage,
 ) -> Result<Vec<u8>, RsaEncryptionError> {
+    if key.len() != 32 {
+        return Err(RsaEncryptionError::InvalidKeyLength(key.len()));
+    }
     let aes_key = Key::<Aes256Gcm>::from_slice(key);
     let cipher = Aes256Gcm::new(aes_key);
     let nonce = message.nonce.into();
@@ -78,4 +81,17 @@ mod test {
 
         assert_eq!(data.to_vec(), decrypted);
     }
+
+    #[test]
+    fn test_decrypt_with_invalid_aes_key_length() {
+        let private_key = RsaPrivateKey::new(&mut OsRng, 2048).unwrap();
+        let encrypted = super::encrypt_with_rsa(&private_key.to_public_key(), b"hello world");
+
+        for len in [0, 16, 31, 33] {
+            assert!(matches!(
+                super::decrypt_with_aes_key(&vec![0u8; len], &encrypted),
+                Err(super::RsaEncryptionError::InvalidKeyLength(l)) if l == len
+            ));
+        }
+    }
 }
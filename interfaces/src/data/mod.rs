@@ -5,6 +5,7 @@ pub mod error;
 pub mod generic_misc_data;
 pub mod meta_data;
 pub mod proof_compression;
+pub mod receive_retry;
 pub mod rw_rights;
 pub mod sender_proof_set;
 pub mod topic;
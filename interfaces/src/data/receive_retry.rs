@@ -0,0 +1,126 @@
+use intmax2_zkp::ethereum_types::bytes32::Bytes32;
+use serde::{Deserialize, Serialize};
+
+use super::{data_type::DataType, encryption::BlsEncryption};
+
+/// Number of times a receive is deferred before it is given up as stuck
+pub const MAX_RECEIVE_ATTEMPTS: u32 = 5;
+
+/// A transfer or withdrawal that could not be received yet because the sender's balance proof
+/// was behind the sender's last tx.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeferredReceive {
+    pub digest: Bytes32,
+    pub data_type: DataType,
+    pub block_number: u32,
+    /// Number of syncs that failed to receive it
+    pub attempts: u32,
+    pub last_error: String,
+}
+
+impl DeferredReceive {
+    /// Whether the receive is no longer retried.
+    pub fn is_stuck(&self) -> bool {
+        self.attempts >= MAX_RECEIVE_ATTEMPTS
+    }
+}
+
+/// Receives deferred to the next sync, kept apart from the user data so that the user data
+/// snapshot format does not change.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReceiveRetryQueue {
+    pub entries: Vec<DeferredReceive>,
+}
+
+impl BlsEncryption for ReceiveRetryQueue {}
+
+impl ReceiveRetryQueue {
+    /// Records a failed attempt to receive `digest`. Returns whether it is retried on the next
+    /// sync, which is false once it has failed `MAX_RECEIVE_ATTEMPTS` times.
+    pub fn defer(
+        &mut self,
+        data_type: DataType,
+        digest: Bytes32,
+        block_number: u32,
+        error: String,
+    ) -> bool {
+        let index = match self.entries.iter().position(|e| e.digest == digest) {
+            Some(index) => index,
+            None => {
+                self.entries.push(DeferredReceive {
+                    digest,
+                    data_type,
+                    block_number,
+                    attempts: 0,
+                    last_error: String::new(),
+                });
+                self.entries.len() - 1
+            }
+        };
+        let entry = &mut self.entries[index];
+        entry.attempts += 1;
+        entry.last_error = error;
+        !entry.is_stuck()
+    }
+
+    /// Removes `digest` once it is received or otherwise settled. Returns whether it was queued.
+    pub fn resolve(&mut self, digest: Bytes32) -> bool {
+        let len = self.entries.len();
+        self.entries.retain(|e| e.digest != digest);
+        self.entries.len() != len
+    }
+
+    /// Digests of `data_type` to fetch again on the next sync.
+    pub fn retry_digests(&self, data_type: DataType) -> Vec<Bytes32> {
+        self.entries
+            .iter()
+            .filter(|e| e.data_type == data_type && !e.is_stuck())
+            .map(|e| e.digest)
+            .collect()
+    }
+
+    pub fn stuck(&self) -> Vec<DeferredReceive> {
+        self.entries
+            .iter()
+            .filter(|e| e.is_stuck())
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use intmax2_zkp::ethereum_types::{bytes32::Bytes32, u32limb_trait::U32LimbTrait as _};
+
+    use super::{ReceiveRetryQueue, MAX_RECEIVE_ATTEMPTS};
+    use crate::data::data_type::DataType;
+
+    #[test]
+    fn test_receive_retry_queue() {
+        let transfer = Bytes32::from_u32_slice(&[1; 8]).unwrap();
+        let withdrawal = Bytes32::from_u32_slice(&[2; 8]).unwrap();
+        let mut queue = ReceiveRetryQueue::default();
+
+        assert!(queue.defer(DataType::Transfer, transfer, 10, "behind".to_string()));
+        assert!(queue.defer(DataType::Withdrawal, withdrawal, 11, "behind".to_string()));
+        assert_eq!(queue.retry_digests(DataType::Transfer), vec![transfer]);
+        assert_eq!(queue.retry_digests(DataType::Withdrawal), vec![withdrawal]);
+
+        for attempt in 2..=MAX_RECEIVE_ATTEMPTS {
+            let retried = queue.defer(DataType::Transfer, transfer, 10, format!("{attempt}"));
+            assert_eq!(retried, attempt < MAX_RECEIVE_ATTEMPTS);
+        }
+        assert!(queue.retry_digests(DataType::Transfer).is_empty());
+        let stuck = queue.stuck();
+        assert_eq!(stuck.len(), 1);
+        assert_eq!(stuck[0].digest, transfer);
+        assert_eq!(stuck[0].attempts, MAX_RECEIVE_ATTEMPTS);
+        assert_eq!(stuck[0].last_error, MAX_RECEIVE_ATTEMPTS.to_string());
+
+        assert!(queue.resolve(withdrawal));
+        assert!(!queue.resolve(withdrawal));
+        assert_eq!(queue.entries.len(), 1);
+    }
+}
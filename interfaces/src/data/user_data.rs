@@ -14,7 +14,8 @@ use intmax2_zkp::{
 
 use super::{
     deposit_data::DepositData, encryption::BlsEncryption, error::DataError, meta_data::MetaData,
-    proof_compression::CompressedBalanceProof, transfer_data::TransferData, tx_data::TxData,
+    proof_compression::CompressedBalanceProof, receive_retry::DeferredReceive,
+    transfer_data::TransferData, tx_data::TxData,
 };
 
 type Result<T> = std::result::Result<T, DataError>;
@@ -30,6 +31,11 @@ pub struct UserData {
     pub tx_status: ProcessStatus,
     pub withdrawal_status: ProcessStatus,
     pub claim_status: ProcessStatus,
+
+    /// Transfers and withdrawals given up after repeated failed receives. They are kept in a
+    /// separate snapshot and filled in by the client, not saved with the user data.
+    #[serde(skip)]
+    pub stuck_receives: Vec<DeferredReceive>,
 }
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
@@ -62,6 +68,8 @@ impl UserData {
             tx_status: ProcessStatus::default(),
             withdrawal_status: ProcessStatus::default(),
             claim_status: ProcessStatus::default(),
+
+            stuck_receives: Vec::new(),
         }
     }
 
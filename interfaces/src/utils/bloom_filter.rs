@@ -0,0 +1,114 @@
+use intmax2_zkp::ethereum_types::{bytes32::Bytes32, u32limb_trait::U32LimbTrait as _};
+use serde::{Deserialize, Serialize};
+use serde_with::{base64::Base64, serde_as};
+
+const MIN_NUM_BITS: u64 = 64;
+const MAX_NUM_HASHES: u32 = 16;
+
+/// A bloom filter over 32-byte hashes.
+///
+/// Items are expected to be uniformly distributed hashes (e.g. pubkey salt hashes), so the bit
+/// positions are derived directly from the item bytes by double hashing instead of rehashing.
+/// The filter may report false positives but never false negatives.
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BloomFilter {
+    pub num_bits: u64,
+    pub num_hashes: u32,
+    #[serde_as(as = "Base64")]
+    pub bits: Vec<u8>,
+}
+
+impl BloomFilter {
+    /// Create an empty filter sized for `num_items` items with the given false positive rate.
+    pub fn new(num_items: usize, false_positive_rate: f64) -> Self {
+        let num_items = num_items.max(1) as f64;
+        let fp_rate = false_positive_rate.clamp(f64::MIN_POSITIVE, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+        let num_bits = (-(num_items * fp_rate.ln()) / (ln2 * ln2)).ceil() as u64;
+        let num_bits = num_bits.max(MIN_NUM_BITS).div_ceil(8) * 8;
+        let num_hashes = ((num_bits as f64 / num_items) * ln2).round() as u32;
+        let num_hashes = num_hashes.clamp(1, MAX_NUM_HASHES);
+        Self {
+            num_bits,
+            num_hashes,
+            bits: vec![0; (num_bits / 8) as usize],
+        }
+    }
+
+    pub fn insert(&mut self, item: &Bytes32) {
+        for position in self.positions(item) {
+            self.bits[(position / 8) as usize] |= 1 << (position % 8);
+        }
+    }
+
+    /// Returns false only if `item` was definitely not inserted.
+    /// A malformed filter is treated as containing every item.
+    pub fn contains(&self, item: &Bytes32) -> bool {
+        if !self.is_well_formed() {
+            return true;
+        }
+        self.positions(item)
+            .all(|position| self.bits[(position / 8) as usize] & (1 << (position % 8)) != 0)
+    }
+
+    fn is_well_formed(&self) -> bool {
+        self.num_bits > 0 && self.num_hashes > 0 && self.bits.len() as u64 * 8 >= self.num_bits
+    }
+
+    fn positions(&self, item: &Bytes32) -> impl Iterator<Item = u64> + '_ {
+        let bytes = item.to_bytes_be();
+        let h1 = u64::from_be_bytes(bytes[0..8].try_into().unwrap());
+        // force h2 to be odd so that the positions do not collapse when it is a multiple of num_bits
+        let h2 = u64::from_be_bytes(bytes[8..16].try_into().unwrap()) | 1;
+        (0..self.num_hashes as u64)
+            .map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % self.num_bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BloomFilter;
+    use intmax2_zkp::ethereum_types::{bytes32::Bytes32, u32limb_trait::U32LimbTrait as _};
+
+    #[test]
+    fn test_bloom_filter_no_false_negatives() {
+        let mut rng = rand::thread_rng();
+        let items = (0..1000)
+            .map(|_| Bytes32::rand(&mut rng))
+            .collect::<Vec<_>>();
+        let mut filter = BloomFilter::new(items.len(), 0.01);
+        for item in &items {
+            filter.insert(item);
+        }
+        for item in &items {
+            assert!(filter.contains(item));
+        }
+        let false_positives = (0..10000)
+            .filter(|_| filter.contains(&Bytes32::rand(&mut rng)))
+            .count();
+        assert!(false_positives < 500, "too many false positives");
+    }
+
+    #[test]
+    fn test_bloom_filter_serialization() {
+        let mut filter = BloomFilter::new(10, 0.01);
+        let item = Bytes32::rand(&mut rand::thread_rng());
+        filter.insert(&item);
+        let json = serde_json::to_string(&filter).unwrap();
+        let deserialized: BloomFilter = serde_json::from_str(&json).unwrap();
+        assert_eq!(filter, deserialized);
+        assert!(deserialized.contains(&item));
+    }
+
+    #[test]
+    fn test_malformed_bloom_filter_contains_everything() {
+        let filter = BloomFilter {
+            num_bits: 1024,
+            num_hashes: 3,
+            bits: vec![],
+        };
+        assert!(filter.contains(&Bytes32::default()));
+    }
+}
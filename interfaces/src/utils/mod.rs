@@ -1,3 +1,4 @@
+pub mod bloom_filter;
 pub mod circuit_verifiers;
 pub mod digest;
 pub mod random;
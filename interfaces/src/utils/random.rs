@@ -1,11 +1,96 @@
+#[cfg(all(feature = "insecure-deterministic-rng", not(debug_assertions)))]
+compile_error!("the insecure-deterministic-rng feature must not be enabled in release builds");
+
+#[cfg(not(feature = "insecure-deterministic-rng"))]
 use rand::Rng;
 
-#[cfg(test)]
+#[cfg(feature = "insecure-deterministic-rng")]
+pub use deterministic::{default_rng, insecure_set_rng_seed};
+
+#[cfg(all(test, not(feature = "insecure-deterministic-rng")))]
 pub fn default_rng() -> impl Rng {
     rand::thread_rng()
 }
 
-#[cfg(not(test))]
+#[cfg(all(not(test), not(feature = "insecure-deterministic-rng")))]
 pub fn default_rng() -> impl Rng {
     rand::rngs::OsRng
 }
+
+/// Seedable `default_rng` for reproducible tests. Only compiled with the
+/// `insecure-deterministic-rng` feature, and `default_rng` stays the OS RNG until
+/// `insecure_set_rng_seed` is called.
+#[cfg(feature = "insecure-deterministic-rng")]
+mod deterministic {
+    use std::cell::RefCell;
+
+    use rand::{
+        rngs::{OsRng, StdRng},
+        Rng, RngCore, SeedableRng as _,
+    };
+
+    thread_local! {
+        static SEEDED_RNG: RefCell<Option<StdRng>> = const { RefCell::new(None) };
+    }
+
+    /// Makes `default_rng` in the calling thread draw from an RNG seeded with `seed`, or
+    /// restores the OS RNG with `None`. Salts, ephemeral keys and operation ids generated
+    /// afterwards are predictable, so this must only be called by tests. The seed is per
+    /// thread, so a test should run on a current thread runtime, which `#[tokio::test]` uses.
+    pub fn insecure_set_rng_seed(seed: Option<u64>) {
+        if let Some(seed) = seed {
+            log::warn!("default_rng is seeded with {seed}. Never use this outside of tests");
+        }
+        SEEDED_RNG.with(|rng| *rng.borrow_mut() = seed.map(StdRng::seed_from_u64));
+    }
+
+    struct DefaultRng;
+
+    fn with_rng<T>(f: impl FnOnce(&mut dyn RngCore) -> T) -> T {
+        SEEDED_RNG.with(|seeded| match seeded.borrow_mut().as_mut() {
+            Some(rng) => f(rng),
+            None => f(&mut OsRng),
+        })
+    }
+
+    impl RngCore for DefaultRng {
+        fn next_u32(&mut self) -> u32 {
+            with_rng(|rng| rng.next_u32())
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            with_rng(|rng| rng.next_u64())
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            with_rng(|rng| rng.fill_bytes(dest))
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+            with_rng(|rng| rng.try_fill_bytes(dest))
+        }
+    }
+
+    pub fn default_rng() -> impl Rng {
+        DefaultRng
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use rand::Rng as _;
+
+        use super::{default_rng, insecure_set_rng_seed};
+
+        #[test]
+        fn test_seeded_default_rng() {
+            insecure_set_rng_seed(Some(42));
+            let first: [u64; 4] = default_rng().gen();
+            insecure_set_rng_seed(Some(42));
+            let second: [u64; 4] = default_rng().gen();
+            insecure_set_rng_seed(None);
+            let unseeded: [u64; 4] = default_rng().gen();
+            assert_eq!(first, second);
+            assert_ne!(first, unseeded);
+        }
+    }
+}
@@ -9,12 +9,47 @@ use intmax2_zkp::{
 use serde::{Deserialize, Serialize};
 use sha2::Digest;
 
+/// Signature scheme of an `Auth`. The id is carried in the auth so that a new scheme can be
+/// rolled out while the old one is still accepted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthAlgorithm {
+    /// BLS signature over the SHA-256 digest of the bincode-serialized `SignContent`
+    Bls,
+}
+
+impl AuthAlgorithm {
+    pub fn id(&self) -> u8 {
+        match self {
+            AuthAlgorithm::Bls => 0,
+        }
+    }
+}
+
+impl TryFrom<u8> for AuthAlgorithm {
+    type Error = anyhow::Error;
+
+    fn try_from(id: u8) -> anyhow::Result<Self> {
+        match id {
+            0 => Ok(AuthAlgorithm::Bls),
+            _ => anyhow::bail!("Unsupported auth algorithm id: {id}"),
+        }
+    }
+}
+
 #[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct Auth {
     pub pubkey: U256,
     pub expiry: u64,
     pub signature: FlatG2,
+    /// Id of the `AuthAlgorithm` of the signature. Missing in auths of older clients, which
+    /// are all BLS.
+    #[serde(default)]
+    pub algorithm: u8,
+    /// Topics the auth is limited to, covered by the signature. `None` grants access to every
+    /// topic of the pubkey, as auths of older clients do.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub topic_scope: Option<Vec<String>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,38 +67,101 @@ pub struct SignContent {
     pub expiry: u64,
 }
 
+/// `SignContent` of a topic scoped auth. Unscoped auths keep signing `SignContent`, so that
+/// their signatures stay the same as those of older clients.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScopedSignContent {
+    pub pubkey: U256,
+    pub content: Vec<u8>,
+    pub expiry: u64,
+    pub topic_scope: Vec<String>,
+}
+
 impl Auth {
     pub fn sign(key: KeySet, time_to_expiry: u64, content: &[u8]) -> Self {
+        Self::sign_inner(key, time_to_expiry, content, None)
+    }
+
+    /// Sign an auth that the store vault server only accepts for the topics in `topic_scope`.
+    pub fn sign_with_topic_scope(
+        key: KeySet,
+        time_to_expiry: u64,
+        content: &[u8],
+        topic_scope: Vec<String>,
+    ) -> Self {
+        Self::sign_inner(key, time_to_expiry, content, Some(topic_scope))
+    }
+
+    fn sign_inner(
+        key: KeySet,
+        time_to_expiry: u64,
+        content: &[u8],
+        topic_scope: Option<Vec<String>>,
+    ) -> Self {
         let expiry = current_time() + time_to_expiry;
-        let sign_content = SignContent {
-            pubkey: key.pubkey,
-            content: content.to_vec(),
-            expiry,
-        };
-        let serialized = bincode::serialize(&sign_content).unwrap();
+        let serialized = serialize_sign_content(key.pubkey, content, expiry, &topic_scope);
         let digest = sha2::Sha256::digest(&serialized);
         let signature = sign_message(key.privkey, &digest).into();
         Auth {
             pubkey: key.pubkey,
             expiry,
             signature,
+            algorithm: AuthAlgorithm::Bls.id(),
+            topic_scope,
         }
     }
 
+    /// Verify the signature with the scheme given by `algorithm`. Unknown schemes are rejected.
     pub fn verify(&self, content: &[u8]) -> anyhow::Result<()> {
         if self.expiry < current_time() {
             anyhow::bail!("Signature expired");
         }
-        let sign_content = SignContent {
-            pubkey: self.pubkey,
-            content: content.to_vec(),
-            expiry: self.expiry,
-        };
-        let serialized = bincode::serialize(&sign_content).unwrap();
-        let digest = sha2::Sha256::digest(&serialized);
+        let serialized =
+            serialize_sign_content(self.pubkey, content, self.expiry, &self.topic_scope);
+        match AuthAlgorithm::try_from(self.algorithm)? {
+            AuthAlgorithm::Bls => self.verify_bls(&serialized),
+        }
+    }
+
+    fn verify_bls(&self, serialized: &[u8]) -> anyhow::Result<()> {
+        let digest = sha2::Sha256::digest(serialized);
         verify_signature(self.signature.clone().into(), self.pubkey, &digest)?;
         Ok(())
     }
+
+    /// Check that the auth grants access to `topic`. Only the signature covers the scope, so
+    /// this must be called after `verify`.
+    pub fn check_topic(&self, topic: &str) -> anyhow::Result<()> {
+        match &self.topic_scope {
+            Some(scope) if !scope.iter().any(|t| t == topic) => {
+                anyhow::bail!("Topic {topic} is out of the auth scope")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn serialize_sign_content(
+    pubkey: U256,
+    content: &[u8],
+    expiry: u64,
+    topic_scope: &Option<Vec<String>>,
+) -> Vec<u8> {
+    match topic_scope {
+        Some(topic_scope) => bincode::serialize(&ScopedSignContent {
+            pubkey,
+            content: content.to_vec(),
+            expiry,
+            topic_scope: topic_scope.clone(),
+        }),
+        None => bincode::serialize(&SignContent {
+            pubkey,
+            content: content.to_vec(),
+            expiry,
+        }),
+    }
+    .unwrap()
 }
 
 pub trait Signable: Sized {
@@ -74,6 +172,16 @@ pub trait Signable: Sized {
         WithAuth { inner: self, auth }
     }
 
+    fn sign_with_topic_scope(
+        self,
+        key: KeySet,
+        time_to_expiry: u64,
+        topic_scope: Vec<String>,
+    ) -> WithAuth<Self> {
+        let auth = Auth::sign_with_topic_scope(key, time_to_expiry, &self.content(), topic_scope);
+        WithAuth { inner: self, auth }
+    }
+
     fn verify(&self, auth: &Auth) -> anyhow::Result<()> {
         auth.verify(&self.content())
     }
@@ -110,4 +218,56 @@ mod test {
         let auth = super::Auth::sign(key, 10, content);
         assert!(auth.verify(content).is_ok());
     }
+
+    #[test]
+    fn test_auth_algorithm_dispatch() {
+        let mut rnd = default_rng();
+        let key = KeySet::rand(&mut rnd);
+        let content = b"test";
+        let auth = super::Auth::sign(key, 10, content);
+        assert_eq!(auth.algorithm, super::AuthAlgorithm::Bls.id());
+
+        // an auth serialized without the algorithm id is treated as BLS
+        let mut json = serde_json::to_value(&auth).unwrap();
+        json.as_object_mut().unwrap().remove("algorithm");
+        let legacy: super::Auth = serde_json::from_value(json).unwrap();
+        assert!(legacy.verify(content).is_ok());
+
+        let unknown = super::Auth {
+            algorithm: 255,
+            ..auth
+        };
+        let err = unknown.verify(content).unwrap_err();
+        assert!(err.to_string().contains("Unsupported auth algorithm"));
+    }
+
+    #[test]
+    fn test_auth_topic_scope() {
+        let mut rnd = default_rng();
+        let key = KeySet::rand(&mut rnd);
+        let content = b"test";
+
+        let unscoped = super::Auth::sign(key, 10, content);
+        assert!(unscoped.check_topic("any").is_ok());
+        let json = serde_json::to_value(&unscoped).unwrap();
+        assert!(json.get("topicScope").is_none());
+
+        let scope = vec!["deposit".to_string(), "transfer".to_string()];
+        let scoped = super::Auth::sign_with_topic_scope(key, 10, content, scope);
+        assert!(scoped.verify(content).is_ok());
+        assert!(scoped.check_topic("transfer").is_ok());
+        assert!(scoped.check_topic("tx").is_err());
+
+        // the scope is signed, so it can neither be dropped nor widened
+        let stripped = super::Auth {
+            topic_scope: None,
+            ..scoped.clone()
+        };
+        assert!(stripped.verify(content).is_err());
+        let widened = super::Auth {
+            topic_scope: Some(vec!["deposit".to_string(), "tx".to_string()]),
+            ..scoped
+        };
+        assert!(widened.verify(content).is_err());
+    }
 }
@@ -10,12 +10,12 @@ use intmax2_interfaces::{
         interface::MAX_BATCH_SIZE,
         types::{
             GetDataBatchRequest, GetDataBatchResponse, GetDataSequenceRequest,
-            GetDataSequenceResponse, GetSnapshotRequest, GetSnapshotResponse, SaveDataBatchRequest,
-            SaveDataBatchResponse, SaveSnapshotRequest,
+            GetDataSequenceResponse, GetSnapshotRequest, GetSnapshotResponse, ListTopicsRequest,
+            ListTopicsResponse, SaveDataBatchRequest, SaveDataBatchResponse, SaveSnapshotRequest,
         },
     },
     data::{rw_rights, topic::extract_rights},
-    utils::signature::{Signable, WithAuth},
+    utils::signature::{Auth, Signable, WithAuth},
 };
 
 #[post("/save-snapshot")]
@@ -27,6 +27,7 @@ pub async fn save_snapshot(
         .inner
         .verify(&request.auth)
         .map_err(ErrorUnauthorized)?;
+    validate_topic_scope(&request.auth, &request.inner.topic)?;
     let auth_pubkey = request.auth.pubkey;
     let request = &request.inner;
 
@@ -85,6 +86,7 @@ pub async fn get_snapshot(
         .inner
         .verify(&request.auth)
         .map_err(ErrorUnauthorized)?;
+    validate_topic_scope(&request.auth, &request.inner.topic)?;
     let auth_pubkey = request.auth.pubkey;
     let request = &request.inner;
 
@@ -129,6 +131,7 @@ pub async fn save_data_batch(
     }
 
     for entry in entries {
+        validate_topic_scope(&request.auth, &entry.topic)?;
         let rw_rights = extract_rights(&entry.topic)
             .map_err(|e| actix_web::error::ErrorBadRequest(format!("Invalid topic: {e}")))?;
         match rw_rights.write_rights {
@@ -170,6 +173,7 @@ pub async fn get_data_batch(
         .inner
         .verify(&request.auth)
         .map_err(ErrorUnauthorized)?;
+    validate_topic_scope(&request.auth, &request.inner.topic)?;
     let auth_pubkey = request.auth.pubkey;
     let request = &request.inner;
 
@@ -210,6 +214,7 @@ pub async fn get_data_sequence(
         .inner
         .verify(&request.auth)
         .map_err(ErrorUnauthorized)?;
+    validate_topic_scope(&request.auth, &request.inner.topic)?;
     let pubkey = request.auth.pubkey;
     let request = &request.inner;
 
@@ -246,6 +251,39 @@ pub async fn get_data_sequence(
     Ok(Json(res))
 }
 
+#[post("/list-topics")]
+pub async fn list_topics(
+    state: Data<State>,
+    request: Json<WithAuth<ListTopicsRequest>>,
+) -> Result<Json<ListTopicsResponse>, Error> {
+    request
+        .inner
+        .verify(&request.auth)
+        .map_err(ErrorUnauthorized)?;
+    let auth_pubkey = request.auth.pubkey;
+    let auth_topic_scope = &request.auth.topic_scope;
+    let request = &request.inner;
+
+    // topic names may reveal what a pubkey stores, so only the owner can list them
+    if auth_pubkey != request.pubkey {
+        return Err(actix_web::error::ErrorBadRequest(
+            "Auth pubkey does not match request pubkey",
+        ));
+    }
+    if auth_topic_scope.is_some() {
+        return Err(actix_web::error::ErrorForbidden(
+            "Topic scoped auth cannot list topics",
+        ));
+    }
+
+    let topics = state
+        .store_vault_server
+        .list_topics(request.pubkey)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(Json(ListTopicsResponse { topics }))
+}
+
 pub fn store_vault_server_scope() -> actix_web::Scope {
     actix_web::web::scope("/store-vault-server")
         .service(save_snapshot)
@@ -253,4 +291,10 @@ pub fn store_vault_server_scope() -> actix_web::Scope {
         .service(save_data_batch)
         .service(get_data_batch)
         .service(get_data_sequence)
+        .service(list_topics)
+}
+
+fn validate_topic_scope(auth: &Auth, topic: &str) -> Result<(), actix_web::Error> {
+    auth.check_topic(topic)
+        .map_err(actix_web::error::ErrorForbidden)
 }
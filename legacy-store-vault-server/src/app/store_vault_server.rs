@@ -1,7 +1,9 @@
 use intmax2_interfaces::{
     api::store_vault_server::{
         interface::{SaveDataEntry, MAX_BATCH_SIZE},
-        types::{CursorOrder, DataWithMetaData, MetaDataCursor, MetaDataCursorResponse},
+        types::{
+            CursorOrder, DataWithMetaData, MetaDataCursor, MetaDataCursorResponse, TopicEntryCount,
+        },
     },
     data::meta_data::MetaData,
     utils::digest::get_digest,
@@ -108,6 +110,35 @@ impl StoreVaultServer {
         Ok(record.map(|r| (r.data, Bytes32::from_hex(&r.digest).unwrap())))
     }
 
+    /// Returns the topics stored for `pubkey` with their number of entries, sorted by topic.
+    pub async fn list_topics(&self, pubkey: U256) -> Result<Vec<TopicEntryCount>> {
+        let records: Vec<(String, i64)> = sqlx::query_as(
+            r#"
+            SELECT topic, SUM(count)::BIGINT FROM (
+                SELECT topic, COUNT(*) AS count FROM historical_data
+                WHERE pubkey = $1
+                GROUP BY topic
+                UNION ALL
+                SELECT topic, 1 AS count FROM snapshot_data
+                WHERE pubkey = $1
+            ) t
+            GROUP BY topic
+            ORDER BY topic
+            "#,
+        )
+        .bind(pubkey.to_hex())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(records
+            .into_iter()
+            .map(|(topic, count)| TopicEntryCount {
+                topic,
+                count: count as u64,
+            })
+            .collect())
+    }
+
     pub async fn batch_save_data(&self, entries: &[SaveDataEntry]) -> Result<Vec<Bytes32>> {
         // Prepare values for bulk insert
         let topics: Vec<String> = entries.iter().map(|entry| entry.topic.clone()).collect();
@@ -402,6 +433,51 @@ mod tests {
         assert!(matches!(result, Err(StoreVaultError::SaveHistoryError(_))));
     }
 
+    /// test case 1: It is expected to count the historical data and the snapshot per topic.
+    ///
+    /// test case 2: It is expected to get an empty list for a pubkey without data.
+    #[sqlx::test]
+    async fn list_topics_test(pool: PgPool) {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let vault = StoreVaultServer {
+            pool: DbPool::new(pool),
+        };
+
+        let pubkey = U256::from(1);
+        let entries = [
+            ("topic-b", b"data 1"),
+            ("topic-a", b"data 2"),
+            ("topic-b", b"data 3"),
+        ]
+        .into_iter()
+        .map(|(topic, data)| SaveDataEntry {
+            topic: topic.to_owned(),
+            pubkey,
+            data: data.to_vec(),
+        })
+        .collect::<Vec<_>>();
+        vault.batch_save_data(&entries).await.unwrap();
+        vault
+            .save_snapshot("topic-c", pubkey, None, b"snapshot")
+            .await
+            .unwrap();
+
+        // test case 1
+        let topics = vault.list_topics(pubkey).await.unwrap();
+        let expected = [("topic-a", 1), ("topic-b", 2), ("topic-c", 1)]
+            .into_iter()
+            .map(|(topic, count)| TopicEntryCount {
+                topic: topic.to_owned(),
+                count,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(topics, expected);
+
+        // test case 2
+        let topics = vault.list_topics(U256::from(2)).await.unwrap();
+        assert!(topics.is_empty());
+    }
+
     /// test case 1: It is expected to get an empty list when requesting a history with existing topic and pubkey, but absent digests.
     ///
     /// test case 2: It is expected to get an empty list when requesting a history with existing digests, but absent topic and pubkey.
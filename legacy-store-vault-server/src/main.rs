@@ -1,4 +1,3 @@
-use actix_cors::Cors;
 use actix_web::{
     web::{Data, JsonConfig},
     App, HttpServer,
@@ -9,6 +8,7 @@ use legacy_store_vault_server::{
     EnvVar,
 };
 use server_common::{
+    cors::CorsConfig,
     health_check::{health_check, set_name_and_version},
     logger,
 };
@@ -29,8 +29,10 @@ async fn main() -> std::io::Result<()> {
         .map_err(|e| io::Error::other(format!("Failed to initialize store_vault_server: {e}")))?;
     let state = Data::new(State::new(store_vault_server));
 
+    let cors_config = CorsConfig::from_env().map_err(io::Error::other)?;
+
     HttpServer::new(move || {
-        let cors = Cors::permissive();
+        let cors = cors_config.cors();
         App::new()
             .wrap(cors)
             .wrap(TracingLogger::<logger::CustomRootSpanBuilder>::new())
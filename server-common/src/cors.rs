@@ -0,0 +1,122 @@
+use actix_cors::Cors;
+use actix_web::http::{
+    header::{self, HeaderName},
+    Method,
+};
+use common::operation::OPERATION_ID_HEADER;
+use thiserror::Error;
+use url::Url;
+
+use crate::env::Env;
+
+// preflight responses are cached by browsers for this long
+const MAX_AGE_SECS: usize = 60 * 60;
+
+#[derive(Error, Debug)]
+pub enum CorsConfigError {
+    #[error("Failed to load environment variables: {0}")]
+    EnvError(#[from] envy::Error),
+
+    #[error("Invalid origin '{0}': must be a scheme, host and optional port")]
+    InvalidOrigin(String),
+
+    #[error("No allowed origins configured, use '*' to allow any origin")]
+    NoOrigins,
+}
+
+/// Origins allowed to make cross-origin requests to a server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CorsConfig {
+    /// Any origin, method and header, like `Cors::permissive()`.
+    Permissive,
+    /// Only the listed origins, with the methods and headers the clients use.
+    Origins(Vec<String>),
+}
+
+impl CorsConfig {
+    /// Load the config from the `ALLOWED_ORIGINS` env var, permissive if it is unset.
+    pub fn from_env() -> Result<Self, CorsConfigError> {
+        dotenvy::dotenv().ok();
+        let env = envy::from_env::<Env>()?;
+        Self::parse(&env.allowed_origins)
+    }
+
+    /// Parse a comma-separated list of origins, or `*` for any origin.
+    pub fn parse(allowed_origins: &str) -> Result<Self, CorsConfigError> {
+        if allowed_origins.trim() == "*" {
+            return Ok(CorsConfig::Permissive);
+        }
+        let mut origins = Vec::new();
+        for origin in allowed_origins.split(',') {
+            let origin = origin.trim().trim_end_matches('/');
+            if origin.is_empty() {
+                continue;
+            }
+            let url = Url::parse(origin)
+                .map_err(|_| CorsConfigError::InvalidOrigin(origin.to_string()))?;
+            // an origin has no path, query or fragment, and browsers send it without a trailing
+            // slash
+            if url.host_str().is_none()
+                || url.path() != "/"
+                || url.query().is_some()
+                || url.fragment().is_some()
+            {
+                return Err(CorsConfigError::InvalidOrigin(origin.to_string()));
+            }
+            origins.push(origin.to_string());
+        }
+        if origins.is_empty() {
+            return Err(CorsConfigError::NoOrigins);
+        }
+        Ok(CorsConfig::Origins(origins))
+    }
+
+    /// Build the CORS middleware. Called once per worker, in the `HttpServer::new` closure.
+    pub fn cors(&self) -> Cors {
+        match self {
+            CorsConfig::Permissive => Cors::permissive(),
+            CorsConfig::Origins(origins) => origins
+                .iter()
+                .fold(Cors::default(), |cors, origin| cors.allowed_origin(origin))
+                .allowed_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
+                .allowed_headers([
+                    header::AUTHORIZATION,
+                    header::ACCEPT,
+                    header::CONTENT_TYPE,
+                    HeaderName::from_static(OPERATION_ID_HEADER),
+                ])
+                // read by the clients to back off when rate limited
+                .expose_headers([header::RETRY_AFTER])
+                .max_age(MAX_AGE_SECS),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cors_config() {
+        assert_eq!(CorsConfig::parse("*").unwrap(), CorsConfig::Permissive);
+        assert_eq!(
+            CorsConfig::parse("https://app.example.com, http://localhost:3000/").unwrap(),
+            CorsConfig::Origins(vec![
+                "https://app.example.com".to_string(),
+                "http://localhost:3000".to_string()
+            ])
+        );
+        assert!(matches!(
+            CorsConfig::parse("https://app.example.com/path"),
+            Err(CorsConfigError::InvalidOrigin(_))
+        ));
+        assert!(matches!(
+            CorsConfig::parse("app.example.com"),
+            Err(CorsConfigError::InvalidOrigin(_))
+        ));
+        assert!(matches!(
+            CorsConfig::parse(" , "),
+            Err(CorsConfigError::NoOrigins)
+        ));
+    }
+}
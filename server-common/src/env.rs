@@ -12,6 +12,10 @@ pub struct Env {
 
     #[serde(default = "default_otlp_collector_endpoint")]
     pub otlp_collector_endpoint: String,
+
+    /// Comma-separated origins allowed to make cross-origin requests, or `*` to allow any
+    #[serde(default = "default_allowed_origins")]
+    pub allowed_origins: String,
 }
 
 fn default_env() -> EnvType {
@@ -25,3 +29,7 @@ fn default_app_log_level() -> String {
 fn default_otlp_collector_endpoint() -> String {
     "".to_string()
 }
+
+fn default_allowed_origins() -> String {
+    "*".to_string()
+}
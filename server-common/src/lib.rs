@@ -1,9 +1,11 @@
 #![feature(once_cell_try)]
 
+pub mod cors;
 pub mod db;
 pub mod env;
 pub mod health_check;
 pub mod logger;
+pub mod metrics;
 pub mod parser;
 pub mod redis;
 pub mod tracer;
@@ -16,7 +16,7 @@ use tracing_subscriber::{
 };
 
 use crate::{env::Env, health_check::load_name_and_version, tracer};
-use common::env::EnvType;
+use common::{env::EnvType, operation::OPERATION_ID_HEADER};
 
 #[derive(Error, Debug)]
 pub enum InitLoggerError {
@@ -91,6 +91,8 @@ impl RootSpanBuilder for CustomRootSpanBuilder {
             "otel.kind" = "server",
             "otel.name" = %format!("{} {}", request.method(), request.path()),
             "request_id" = %uuid::Uuid::new_v4(),
+            // set by the client sdk, to join the request with the client operation that sent it
+            "operation_id" = %request.headers().get(OPERATION_ID_HEADER).and_then(|h| h.to_str().ok()).unwrap_or(""),
             status_code = tracing::field::Empty,
             latency = tracing::field::Empty,
         );
@@ -0,0 +1,305 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    sync::{LazyLock, Mutex},
+    time::Instant,
+};
+
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::{header::CONTENT_TYPE, Method},
+    middleware::Next,
+    Error, HttpResponse,
+};
+
+use crate::health_check::load_name_and_version;
+
+pub const METRICS_PATH: &str = "/metrics";
+
+/// Upper bounds of the request latency histogram, in seconds. Proof requests can take a while,
+/// so the buckets go up to a minute.
+const LATENCY_BUCKETS: [f64; 13] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0,
+];
+
+static REGISTRY: LazyLock<Registry> = LazyLock::new(Registry::default);
+
+type Labels = Vec<(String, String)>;
+
+#[derive(Debug, Clone, Default)]
+struct Histogram {
+    bucket_counts: [u64; LATENCY_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        for (bound, count) in LATENCY_BUCKETS.iter().zip(self.bucket_counts.iter_mut()) {
+            if value <= *bound {
+                *count += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+#[derive(Debug, Default)]
+struct Gauge {
+    help: String,
+    values: BTreeMap<Labels, f64>,
+}
+
+#[derive(Debug, Default)]
+struct Metrics {
+    // (method, path, status) -> count
+    requests: BTreeMap<(String, String, u16), u64>,
+    // (method, path) -> count
+    errors: BTreeMap<(String, String), u64>,
+    // (method, path) -> latency
+    latencies: BTreeMap<(String, String), Histogram>,
+    // name -> gauge
+    gauges: BTreeMap<String, Gauge>,
+}
+
+/// Process-wide metrics registry, rendered in the Prometheus text format.
+#[derive(Debug, Default)]
+struct Registry(Mutex<Metrics>);
+
+impl Registry {
+    fn observe_request(&self, method: &str, path: &str, status: u16, elapsed_secs: f64) {
+        let mut metrics = self.0.lock().unwrap();
+        let key = (method.to_string(), path.to_string());
+        *metrics
+            .requests
+            .entry((key.0.clone(), key.1.clone(), status))
+            .or_default() += 1;
+        if status >= 500 {
+            *metrics.errors.entry(key.clone()).or_default() += 1;
+        }
+        metrics
+            .latencies
+            .entry(key)
+            .or_default()
+            .observe(elapsed_secs);
+    }
+
+    fn set_gauge(&self, name: &str, help: &str, labels: &[(&str, &str)], value: f64) {
+        let mut metrics = self.0.lock().unwrap();
+        let gauge = metrics.gauges.entry(name.to_string()).or_default();
+        gauge.help = help.to_string();
+        let labels = labels
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        gauge.values.insert(labels, value);
+    }
+
+    fn render(&self) -> String {
+        let metrics = self.0.lock().unwrap();
+        let mut out = String::new();
+
+        let (name, version) = load_name_and_version();
+        write_header(
+            &mut out,
+            "service_info",
+            "Name and version of the service",
+            "gauge",
+        );
+        write_sample(
+            &mut out,
+            "service_info",
+            &[("name", name.as_str()), ("version", version.as_str())],
+            1.0,
+        );
+
+        write_header(
+            &mut out,
+            "http_requests_total",
+            "Total number of HTTP requests",
+            "counter",
+        );
+        for ((method, path, status), count) in metrics.requests.iter() {
+            let status = status.to_string();
+            write_sample(
+                &mut out,
+                "http_requests_total",
+                &[
+                    ("method", method.as_str()),
+                    ("path", path.as_str()),
+                    ("status", status.as_str()),
+                ],
+                *count as f64,
+            );
+        }
+
+        write_header(
+            &mut out,
+            "http_request_errors_total",
+            "Total number of HTTP requests that failed with a server error",
+            "counter",
+        );
+        for ((method, path), count) in metrics.errors.iter() {
+            write_sample(
+                &mut out,
+                "http_request_errors_total",
+                &[("method", method.as_str()), ("path", path.as_str())],
+                *count as f64,
+            );
+        }
+
+        write_header(
+            &mut out,
+            "http_request_duration_seconds",
+            "HTTP request latency in seconds",
+            "histogram",
+        );
+        for ((method, path), histogram) in metrics.latencies.iter() {
+            let labels = [("method", method.as_str()), ("path", path.as_str())];
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(histogram.bucket_counts) {
+                let le = bound.to_string();
+                write_sample(
+                    &mut out,
+                    "http_request_duration_seconds_bucket",
+                    &[labels[0], labels[1], ("le", le.as_str())],
+                    count as f64,
+                );
+            }
+            write_sample(
+                &mut out,
+                "http_request_duration_seconds_bucket",
+                &[labels[0], labels[1], ("le", "+Inf")],
+                histogram.count as f64,
+            );
+            write_sample(
+                &mut out,
+                "http_request_duration_seconds_sum",
+                &labels,
+                histogram.sum,
+            );
+            write_sample(
+                &mut out,
+                "http_request_duration_seconds_count",
+                &labels,
+                histogram.count as f64,
+            );
+        }
+
+        for (name, gauge) in metrics.gauges.iter() {
+            write_header(&mut out, name, &gauge.help, "gauge");
+            for (labels, value) in gauge.values.iter() {
+                let labels = labels
+                    .iter()
+                    .map(|(k, v)| (k.as_str(), v.as_str()))
+                    .collect::<Vec<_>>();
+                write_sample(&mut out, name, &labels, *value);
+            }
+        }
+        out
+    }
+}
+
+/// Set a service-specific gauge, e.g. a queue depth. `labels` distinguish the series of the
+/// same gauge.
+pub fn set_gauge(name: &str, help: &str, labels: &[(&str, &str)], value: f64) {
+    REGISTRY.set_gauge(name, help, labels, value);
+}
+
+/// Render all metrics in the Prometheus text exposition format.
+pub fn render() -> String {
+    REGISTRY.render()
+}
+
+/// Middleware that records the count, errors and latency of every request, and serves the
+/// metrics at `GET /metrics`. Mount it with
+/// `App::new().wrap(actix_web::middleware::from_fn(track_requests))`.
+pub async fn track_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    if req.method() == Method::GET && req.path() == METRICS_PATH {
+        let response = HttpResponse::Ok()
+            .insert_header((CONTENT_TYPE, "text/plain; version=0.0.4"))
+            .body(render());
+        return Ok(req.into_response(response).map_into_right_body());
+    }
+
+    let start = Instant::now();
+    let method = req.method().to_string();
+    // use the route pattern instead of the raw path to keep the number of series bounded
+    let path = req
+        .match_pattern()
+        .unwrap_or_else(|| "unmatched".to_string());
+    let result = next.call(req).await;
+    let status = match &result {
+        Ok(response) => response.status().as_u16(),
+        Err(e) => e.as_response_error().status_code().as_u16(),
+    };
+    REGISTRY.observe_request(&method, &path, status, start.elapsed().as_secs_f64());
+    result.map(|response| response.map_into_left_body())
+}
+
+fn write_header(out: &mut String, name: &str, help: &str, metric_type: &str) {
+    // writing to a String never fails
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {metric_type}");
+}
+
+fn write_sample(out: &mut String, name: &str, labels: &[(&str, &str)], value: f64) {
+    out.push_str(name);
+    if !labels.is_empty() {
+        let labels = labels
+            .iter()
+            .map(|(k, v)| format!("{k}=\"{}\"", escape_label_value(v)))
+            .collect::<Vec<_>>()
+            .join(",");
+        let _ = write!(out, "{{{labels}}}");
+    }
+    let _ = writeln!(out, " {value}");
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_requests() {
+        let registry = Registry::default();
+        registry.observe_request("GET", "/health-check", 200, 0.02);
+        registry.observe_request("POST", "/prove", 500, 3.0);
+        let out = registry.render();
+        assert!(out.contains(
+            "http_requests_total{method=\"GET\",path=\"/health-check\",status=\"200\"} 1\n"
+        ));
+        assert!(out.contains("http_request_errors_total{method=\"POST\",path=\"/prove\"} 1\n"));
+        assert!(!out.contains("http_request_errors_total{method=\"GET\""));
+        assert!(out.contains(
+            "http_request_duration_seconds_bucket{method=\"POST\",path=\"/prove\",le=\"2.5\"} 0\n"
+        ));
+        assert!(out.contains(
+            "http_request_duration_seconds_bucket{method=\"POST\",path=\"/prove\",le=\"5\"} 1\n"
+        ));
+        assert!(out.contains(
+            "http_request_duration_seconds_count{method=\"GET\",path=\"/health-check\"} 1\n"
+        ));
+    }
+
+    #[test]
+    fn test_render_gauges() {
+        let registry = Registry::default();
+        registry.set_gauge("queue_depth", "Queue depth", &[("queue", "a\"b")], 3.0);
+        registry.set_gauge("queue_depth", "Queue depth", &[("queue", "a\"b")], 5.0);
+        let out = registry.render();
+        assert!(out.contains("# TYPE queue_depth gauge\n"));
+        assert!(out.contains("queue_depth{queue=\"a\\\"b\"} 5\n"));
+    }
+}
@@ -1,6 +1,6 @@
 use redis::{aio::MultiplexedConnection, AsyncCommands as _, Client};
 use serde::{de::DeserializeOwned, Serialize};
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 #[derive(thiserror::Error, Debug)]
 pub enum RedisCacheError {
@@ -14,6 +14,7 @@ pub enum RedisCacheError {
 pub struct RedisCache {
     client: Client,
     prefix: String,
+    max_entries: Option<usize>,
 }
 
 impl RedisCache {
@@ -22,9 +23,26 @@ impl RedisCache {
         Ok(Self {
             client,
             prefix: prefix.to_owned(),
+            max_entries: None,
         })
     }
 
+    /// Bound the cache to `max_entries` entries. When a new entry exceeds the bound, the least
+    /// recently used entries are evicted, and the evictions are counted (see `evictions`).
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = Some(max_entries);
+        self
+    }
+
+    /// Sorted set of the keys scored by their last access time in milliseconds
+    fn lru_index_key(&self) -> String {
+        format!("{}:__lru_index", self.prefix)
+    }
+
+    fn evictions_key(&self) -> String {
+        format!("{}:__evictions", self.prefix)
+    }
+
     async fn get_connection(&self) -> Result<MultiplexedConnection, RedisCacheError> {
         let conn = self.client.get_multiplexed_async_connection().await?;
         Ok(conn)
@@ -48,6 +66,9 @@ impl RedisCache {
         match result {
             Some(data) => {
                 let value = serde_json::from_str(&data)?;
+                if self.max_entries.is_some() {
+                    let () = conn.zadd(self.lru_index_key(), &key, now_millis()).await?;
+                }
                 Ok(Some(value))
             }
             None => Ok(None),
@@ -67,10 +88,56 @@ impl RedisCache {
         let mut conn = self.get_connection().await?;
         let serialized = serde_json::to_string(value)?;
         let key = format!("{}:{}", self.prefix, key);
-        let () = conn.set_ex(key, serialized, ttl.as_secs()).await?;
+        let Some(max_entries) = self.max_entries else {
+            let () = conn.set_ex(key, serialized, ttl.as_secs()).await?;
+            return Ok(());
+        };
+        let mut pipe = redis::pipe();
+        pipe.atomic().set_ex(&key, serialized, ttl.as_secs()).zadd(
+            self.lru_index_key(),
+            &key,
+            now_millis(),
+        );
+        pipe.query_async::<()>(&mut conn).await?;
+
+        // Entries that already expired are removed from the index without being counted.
+        let script = redis::Script::new(
+            r"
+            local excess = redis.call('ZCARD', KEYS[1]) - tonumber(ARGV[1])
+            if excess <= 0 then
+                return 0
+            end
+            local keys = redis.call('ZRANGE', KEYS[1], 0, excess - 1)
+            redis.call('ZREMRANGEBYRANK', KEYS[1], 0, excess - 1)
+            local evicted = 0
+            for _, key in ipairs(keys) do
+                evicted = evicted + redis.call('DEL', key)
+            end
+            if evicted > 0 then
+                redis.call('INCRBY', KEYS[2], evicted)
+            end
+            return evicted
+        ",
+        );
+        let evicted: u64 = script
+            .key(self.lru_index_key())
+            .key(self.evictions_key())
+            .arg(max_entries)
+            .invoke_async(&mut conn)
+            .await?;
+        if evicted > 0 {
+            log::debug!("Evicted {evicted} entries from the {} cache", self.prefix);
+        }
         Ok(())
     }
 
+    /// Total number of entries evicted to keep the cache within `max_entries`
+    pub async fn evictions(&self) -> Result<u64, RedisCacheError> {
+        let mut conn = self.get_connection().await?;
+        let evictions: Option<u64> = conn.get(self.evictions_key()).await?;
+        Ok(evictions.unwrap_or(0))
+    }
+
     /// Set a key with a value
     pub async fn delete(&self, key: &str) -> Result<bool, RedisCacheError> {
         let mut conn = self.get_connection().await?;
@@ -102,6 +169,12 @@ impl RedisCache {
     }
 }
 
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
 #[cfg(test)]
 mod tests {
     use serde::Deserialize;
@@ -137,4 +210,28 @@ mod tests {
         let result = cache.get::<TestStruct>(key).await.unwrap();
         assert_eq!(result.unwrap().name, value.name);
     }
+
+    #[tokio::test]
+    #[ignore]
+    async fn test_redis_cache_lru_eviction() {
+        let redis_url =
+            env::var("REDIS_URL").unwrap_or_else(|_| "redis://localhost:6379".to_string());
+
+        let cache = RedisCache::new(&redis_url, "test_lru")
+            .unwrap()
+            .with_max_entries(2);
+        cache.reset().await.unwrap();
+        let ttl = Duration::from_secs(10);
+
+        cache.set_with_ttl("a", &1u32, ttl).await.unwrap();
+        cache.set_with_ttl("b", &2u32, ttl).await.unwrap();
+        // touch "a" so that "b" is the least recently used entry
+        assert_eq!(cache.get::<u32>("a").await.unwrap(), Some(1));
+        cache.set_with_ttl("c", &3u32, ttl).await.unwrap();
+
+        assert_eq!(cache.get::<u32>("a").await.unwrap(), Some(1));
+        assert_eq!(cache.get::<u32>("b").await.unwrap(), None);
+        assert_eq!(cache.get::<u32>("c").await.unwrap(), Some(3));
+        assert_eq!(cache.evictions().await.unwrap(), 1);
+    }
 }
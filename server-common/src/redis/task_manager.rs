@@ -230,6 +230,38 @@ impl<T: Serialize + DeserializeOwned, R: Serialize + DeserializeOwned> TaskManag
         Ok(())
     }
 
+    /// Move a running task back to pending so that another worker picks it up, e.g. after it
+    /// timed out. Tasks that have a result or expired after the ttl are not requeued. Returns
+    /// whether the task was requeued.
+    pub async fn requeue_task(&self, task_id: u32) -> Result<bool> {
+        let mut conn = self.get_connection().await?;
+        let script = redis::Script::new(
+            r"
+            if redis.call('HEXISTS', KEYS[1], ARGV[1]) == 0 then
+                return 0
+            end
+            if redis.call('HEXISTS', KEYS[2], ARGV[1]) == 1 then
+                return 0
+            end
+            local moved = redis.call('SMOVE', KEYS[3], KEYS[4], ARGV[1])
+            if moved == 1 then
+                redis.call('EXPIRE', KEYS[4], ARGV[2])
+            end
+            return moved
+        ",
+        );
+        let moved: i32 = script
+            .key(&self.tasks_key)
+            .key(&self.results_key)
+            .key(&self.running_key)
+            .key(&self.pending_key)
+            .arg(task_id)
+            .arg(self.ttl)
+            .invoke_async(&mut conn)
+            .await?;
+        Ok(moved == 1)
+    }
+
     pub async fn submit_heartbeat(&self, worker_id: &str, task_id: u32) -> Result<()> {
         let mut conn = self.get_connection().await?;
         let key = format!("{}:{}", self.heartbeat_prefix, task_id);
@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_s3_snapshot_history_timestamp_desc;
+
+DROP TABLE IF EXISTS s3_snapshot_history;
@@ -0,0 +1,11 @@
+-- Superseded snapshots retained until the compaction removes them.
+CREATE TABLE IF NOT EXISTS s3_snapshot_history (
+    pubkey VARCHAR(66) NOT NULL,
+    topic VARCHAR(255) NOT NULL,
+    digest VARCHAR(66) NOT NULL,
+    "timestamp" BIGINT NOT NULL,
+    UNIQUE (pubkey, topic, digest)
+);
+
+CREATE INDEX IF NOT EXISTS idx_s3_snapshot_history_timestamp_desc
+ON s3_snapshot_history (pubkey, topic, "timestamp" DESC);
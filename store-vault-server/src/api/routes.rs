@@ -1,22 +1,24 @@
 use crate::api::state::State;
 use actix_web::{
     error::ErrorUnauthorized,
+    http::header::AUTHORIZATION,
     post,
     web::{Data, Json},
-    Error,
+    Error, HttpRequest,
 };
 use intmax2_interfaces::{
     api::{
         s3_store_vault::types::{
-            S3GetDataBatchRequest, S3GetDataBatchResponse, S3GetDataSequenceRequest,
-            S3GetDataSequenceResponse, S3GetSnapshotRequest, S3GetSnapshotResponse,
+            S3CompactSnapshotsResponse, S3GetDataBatchRequest, S3GetDataBatchResponse,
+            S3GetDataSequenceRequest, S3GetDataSequenceResponse, S3GetSnapshotRequest,
+            S3GetSnapshotResponse, S3ListTopicsRequest, S3ListTopicsResponse,
             S3PreSaveSnapshotRequest, S3PreSaveSnapshotResponse, S3SaveDataBatchRequest,
             S3SaveDataBatchResponse, S3SaveSnapshotRequest,
         },
         store_vault_server::interface::MAX_BATCH_SIZE,
     },
     data::{rw_rights, topic::extract_rights},
-    utils::signature::{Signable, WithAuth},
+    utils::signature::{Auth, Signable, WithAuth},
 };
 
 #[post("/pre-save-snapshot")]
@@ -28,6 +30,7 @@ pub async fn pre_save_snapshot(
         .inner
         .verify(&request.auth)
         .map_err(ErrorUnauthorized)?;
+    validate_topic_scope(&request.auth, &request.inner.topic)?;
     let auth_pubkey = request.auth.pubkey;
     let request = &request.inner;
 
@@ -54,13 +57,31 @@ pub async fn pre_save_snapshot(
         rw_rights::WriteRights::OpenWrite => {}
     }
 
+    if let Some(size) = request
+        .size
+        .filter(|&size| state.s3_store_vault.is_multipart(size))
+    {
+        let multipart_upload = state
+            .s3_store_vault
+            .pre_save_snapshot_multipart(&request.topic, request.pubkey, request.digest, size)
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+        return Ok(Json(S3PreSaveSnapshotResponse {
+            presigned_url: String::new(),
+            multipart_upload: Some(multipart_upload),
+        }));
+    }
+
     let presigned_url = state
         .s3_store_vault
         .pre_save_snapshot(&request.topic, request.pubkey, request.digest)
         .await
         .map_err(actix_web::error::ErrorInternalServerError)?;
 
-    Ok(Json(S3PreSaveSnapshotResponse { presigned_url }))
+    Ok(Json(S3PreSaveSnapshotResponse {
+        presigned_url,
+        multipart_upload: None,
+    }))
 }
 
 #[post("/save-snapshot")]
@@ -72,6 +93,7 @@ pub async fn save_snapshot(
         .inner
         .verify(&request.auth)
         .map_err(ErrorUnauthorized)?;
+    validate_topic_scope(&request.auth, &request.inner.topic)?;
     let auth_pubkey = request.auth.pubkey;
     let request = &request.inner;
 
@@ -109,6 +131,19 @@ pub async fn save_snapshot(
         rw_rights::WriteRights::OpenWrite => {}
     }
 
+    if let Some(multipart_upload) = &request.multipart_upload {
+        state
+            .s3_store_vault
+            .complete_multipart_snapshot(
+                &request.topic,
+                request.pubkey,
+                request.digest,
+                multipart_upload,
+            )
+            .await
+            .map_err(actix_web::error::ErrorInternalServerError)?;
+    }
+
     state
         .s3_store_vault
         .save_snapshot(
@@ -132,6 +167,7 @@ pub async fn get_snapshot(
         .inner
         .verify(&request.auth)
         .map_err(ErrorUnauthorized)?;
+    validate_topic_scope(&request.auth, &request.inner.topic)?;
     let auth_pubkey = request.auth.pubkey;
     let request = &request.inner;
 
@@ -179,6 +215,7 @@ pub async fn save_data_batch(
 
     for entry in entries {
         validate_topic_length(&entry.topic)?;
+        validate_topic_scope(&request.auth, &entry.topic)?;
         let rw_rights = extract_rights(&entry.topic)
             .map_err(|e| actix_web::error::ErrorBadRequest(format!("Invalid topic: {e}")))?;
         match rw_rights.write_rights {
@@ -220,6 +257,7 @@ pub async fn get_data_batch(
         .inner
         .verify(&request.auth)
         .map_err(ErrorUnauthorized)?;
+    validate_topic_scope(&request.auth, &request.inner.topic)?;
     let auth_pubkey = request.auth.pubkey;
     let request = &request.inner;
 
@@ -264,6 +302,7 @@ pub async fn get_data_sequence(
         .inner
         .verify(&request.auth)
         .map_err(ErrorUnauthorized)?;
+    validate_topic_scope(&request.auth, &request.inner.topic)?;
     let pubkey = request.auth.pubkey;
     let request = &request.inner;
 
@@ -301,6 +340,68 @@ pub async fn get_data_sequence(
     }))
 }
 
+#[post("/list-topics")]
+pub async fn list_topics(
+    state: Data<State>,
+    request: Json<WithAuth<S3ListTopicsRequest>>,
+) -> Result<Json<S3ListTopicsResponse>, Error> {
+    request
+        .inner
+        .verify(&request.auth)
+        .map_err(ErrorUnauthorized)?;
+    let auth_pubkey = request.auth.pubkey;
+    let auth_topic_scope = &request.auth.topic_scope;
+    let request = &request.inner;
+
+    // topic names may reveal what a pubkey stores, so only the owner can list them
+    if auth_pubkey != request.pubkey {
+        return Err(actix_web::error::ErrorBadRequest(
+            "Auth pubkey does not match request pubkey",
+        ));
+    }
+    if auth_topic_scope.is_some() {
+        return Err(actix_web::error::ErrorForbidden(
+            "Topic scoped auth cannot list topics",
+        ));
+    }
+
+    let topics = state
+        .s3_store_vault
+        .list_topics(request.pubkey)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(S3ListTopicsResponse { topics }))
+}
+
+#[post("/admin/compact-snapshots")]
+pub async fn compact_snapshots(
+    state: Data<State>,
+    req: HttpRequest,
+) -> Result<Json<S3CompactSnapshotsResponse>, Error> {
+    let Some(admin_token) = state.admin_token.as_ref() else {
+        return Err(actix_web::error::ErrorForbidden(
+            "Admin endpoints are disabled",
+        ));
+    };
+    let bearer = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if bearer != Some(admin_token.as_str()) {
+        return Err(ErrorUnauthorized("Invalid admin token"));
+    }
+
+    let deleted = state
+        .s3_store_vault
+        .compact_snapshots()
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+
+    Ok(Json(S3CompactSnapshotsResponse { deleted }))
+}
+
 pub fn s3_store_vault_scope() -> actix_web::Scope {
     actix_web::web::scope("/s3-store-vault")
         .service(pre_save_snapshot)
@@ -309,6 +410,8 @@ pub fn s3_store_vault_scope() -> actix_web::Scope {
         .service(save_data_batch)
         .service(get_data_batch)
         .service(get_data_sequence)
+        .service(list_topics)
+        .service(compact_snapshots)
 }
 
 fn validate_topic_length(topic: &str) -> Result<(), actix_web::Error> {
@@ -317,3 +420,8 @@ fn validate_topic_length(topic: &str) -> Result<(), actix_web::Error> {
     }
     Ok(())
 }
+
+fn validate_topic_scope(auth: &Auth, topic: &str) -> Result<(), actix_web::Error> {
+    auth.check_topic(topic)
+        .map_err(actix_web::error::ErrorForbidden)
+}
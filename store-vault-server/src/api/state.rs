@@ -2,10 +2,14 @@ use crate::app::s3_store_vault::S3StoreVault;
 
 pub struct State {
     pub s3_store_vault: S3StoreVault,
+    pub admin_token: Option<String>,
 }
 
 impl State {
-    pub fn new(s3_store_vault: S3StoreVault) -> Self {
-        Self { s3_store_vault }
+    pub fn new(s3_store_vault: S3StoreVault, admin_token: Option<String>) -> Self {
+        Self {
+            s3_store_vault,
+            admin_token,
+        }
     }
 }
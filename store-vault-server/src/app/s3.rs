@@ -1,5 +1,10 @@
 use aws_config::SdkConfig;
-use aws_sdk_s3::{error::SdkError, presigning::PresigningConfig, Client as AwsS3Client};
+use aws_sdk_s3::{
+    error::SdkError,
+    presigning::PresigningConfig,
+    types::{CompletedMultipartUpload, CompletedPart},
+    Client as AwsS3Client,
+};
 use base64::{prelude::BASE64_STANDARD, Engine};
 use serde::Deserialize;
 use std::{io, time::Duration};
@@ -46,6 +51,9 @@ pub enum S3Error {
 
     #[error("Failed to generate CloudFront signed URL: {0}")]
     CloudFrontSigning(String),
+
+    #[error("Multipart upload error: {0}")]
+    MultipartUpload(String),
 }
 
 impl S3Client {
@@ -73,6 +81,97 @@ impl S3Client {
         Ok(presigned_request.uri().to_string())
     }
 
+    /// Start a multipart upload of `key`. Returns the upload id.
+    pub async fn create_multipart_upload(&self, key: &str, content_type: &str) -> Result<String> {
+        let output = self
+            .client
+            .create_multipart_upload()
+            .bucket(&self.config.bucket_name)
+            .key(key)
+            .content_type(content_type)
+            .send()
+            .await
+            .map_err(|e| S3Error::MultipartUpload(format!("{e:?}")))?;
+        let upload_id = output
+            .upload_id()
+            .ok_or_else(|| S3Error::MultipartUpload("no upload id returned".to_string()))?;
+        Ok(upload_id.to_string())
+    }
+
+    /// Presigned PUT URLs of the parts 1..=`num_parts` of a multipart upload.
+    pub async fn generate_upload_part_urls(
+        &self,
+        key: &str,
+        upload_id: &str,
+        num_parts: u32,
+        expiration: Duration,
+    ) -> Result<Vec<String>> {
+        let mut urls = Vec::with_capacity(num_parts as usize);
+        for part_number in 1..=num_parts {
+            let presigning_config = PresigningConfig::builder()
+                .expires_in(expiration)
+                .build()
+                .map_err(|e| S3Error::PresigningConfig(e.to_string()))?;
+            let presigned_request = self
+                .client
+                .upload_part()
+                .bucket(&self.config.bucket_name)
+                .key(key)
+                .upload_id(upload_id)
+                .part_number(part_number as i32)
+                .presigned(presigning_config)
+                .await
+                .map_err(|e| S3Error::PresignedUrlGeneration(e.to_string()))?;
+            urls.push(presigned_request.uri().to_string());
+        }
+        Ok(urls)
+    }
+
+    /// Assemble the uploaded parts, given as (part number, ETag), into the object.
+    pub async fn complete_multipart_upload(
+        &self,
+        key: &str,
+        upload_id: &str,
+        parts: &[(u32, String)],
+    ) -> Result<()> {
+        let parts = parts
+            .iter()
+            .map(|(part_number, etag)| {
+                CompletedPart::builder()
+                    .part_number(*part_number as i32)
+                    .e_tag(etag)
+                    .build()
+            })
+            .collect();
+        self.client
+            .complete_multipart_upload()
+            .bucket(&self.config.bucket_name)
+            .key(key)
+            .upload_id(upload_id)
+            .multipart_upload(
+                CompletedMultipartUpload::builder()
+                    .set_parts(Some(parts))
+                    .build(),
+            )
+            .send()
+            .await
+            .map_err(|e| S3Error::MultipartUpload(format!("{e:?}")))?;
+        Ok(())
+    }
+
+    /// Discard the uploaded parts of an unfinished multipart upload.
+    pub async fn abort_multipart_upload(&self, key: &str, upload_id: &str) -> Result<()> {
+        self.client
+            .abort_multipart_upload()
+            .bucket(&self.config.bucket_name)
+            .key(key)
+            .upload_id(upload_id)
+            .send()
+            .await
+            .map_err(|e| S3Error::MultipartUpload(format!("{e:?}")))?;
+        Ok(())
+    }
+
     pub fn generate_download_url(
         &self,
         resource_path: &str,
@@ -147,6 +246,25 @@ mockall::mock! {
             expiration: Duration,
         ) -> Result<String>;
 
+        pub async fn create_multipart_upload(&self, key: &str, content_type: &str) -> Result<String>;
+
+        pub async fn generate_upload_part_urls(
+            &self,
+            key: &str,
+            upload_id: &str,
+            num_parts: u32,
+            expiration: Duration,
+        ) -> Result<Vec<String>>;
+
+        pub async fn complete_multipart_upload(
+            &self,
+            key: &str,
+            upload_id: &str,
+            parts: &[(u32, String)],
+        ) -> Result<()>;
+
+        pub async fn abort_multipart_upload(&self, key: &str, upload_id: &str) -> Result<()>;
+
         pub fn generate_download_url(
             &self,
             resource_path: &str,
@@ -5,10 +5,13 @@ use crate::EnvVar;
 use aws_config::BehaviorVersion;
 use intmax2_interfaces::{
     api::{
-        s3_store_vault::types::{PresignedUrlWithMetaData, S3SaveDataEntry},
+        s3_store_vault::types::{
+            PresignedUrlWithMetaData, S3CompletedMultipartUpload, S3MultipartUpload,
+            S3SaveDataEntry,
+        },
         store_vault_server::{
             interface::MAX_BATCH_SIZE,
-            types::{CursorOrder, MetaDataCursor, MetaDataCursorResponse},
+            types::{CursorOrder, MetaDataCursor, MetaDataCursorResponse, TopicEntryCount},
         },
     },
     data::meta_data::MetaData,
@@ -28,11 +31,25 @@ pub fn get_path(topic: &str, pubkey: U256, digest: Bytes32) -> String {
 
 type Result<T> = std::result::Result<T, StoreVaultError>;
 
+/// Snapshots of at least this many bytes are uploaded in parts by default.
+pub const DEFAULT_MULTIPART_THRESHOLD: u64 = 32 * 1024 * 1024;
+/// Default size of a part of a multipart upload.
+pub const DEFAULT_MULTIPART_PART_SIZE: u64 = 8 * 1024 * 1024;
+// S3 limits of multipart uploads
+const MIN_MULTIPART_PART_SIZE: u64 = 5 * 1024 * 1024;
+const MAX_MULTIPART_PARTS: u64 = 10_000;
+
 #[derive(Clone)]
 pub struct Config {
     pub s3_upload_timeout: u64,
     pub s3_download_timeout: u64,
     pub cleanup_interval: u64,
+    /// Number of superseded snapshots retained per (pubkey, topic) in addition to the latest
+    /// one. If `None`, the previous snapshot is deleted as soon as a new one is saved.
+    pub snapshot_retention: Option<u64>,
+    /// Snapshots of at least this many bytes are uploaded in parts.
+    pub multipart_threshold: u64,
+    pub multipart_part_size: u64,
 }
 
 #[derive(Clone)]
@@ -63,6 +80,15 @@ impl S3StoreVault {
             s3_upload_timeout: env.s3_upload_timeout,
             s3_download_timeout: env.s3_download_timeout,
             cleanup_interval: env.cleanup_interval,
+            snapshot_retention: env.snapshot_retention,
+            multipart_threshold: env
+                .s3_multipart_threshold
+                .unwrap_or(DEFAULT_MULTIPART_THRESHOLD),
+            // S3 rejects smaller parts, except for the last one
+            multipart_part_size: env
+                .s3_multipart_part_size
+                .unwrap_or(DEFAULT_MULTIPART_PART_SIZE)
+                .max(MIN_MULTIPART_PART_SIZE),
         };
 
         Ok(Self {
@@ -102,7 +128,100 @@ impl S3StoreVault {
             )
             .await?;
 
-        // save pending upload to db
+        self.save_pending_upload(topic, pubkey, digest).await?;
+        Ok(presigned_url)
+    }
+
+    /// Whether a snapshot of `size` bytes should be uploaded with `pre_save_snapshot_multipart`.
+    pub fn is_multipart(&self, size: u64) -> bool {
+        size >= self.config.multipart_threshold
+    }
+
+    /// Same as `pre_save_snapshot`, but starts a multipart upload of a snapshot of `size` bytes,
+    /// so that a slow client can retry a failed part instead of the whole upload. The parts
+    /// are assembled by `complete_multipart_snapshot`.
+    ///
+    /// Each part URL expires after `s3_upload_timeout`, like the URL of a single upload. Parts
+    /// of uploads that are never completed should be removed by a lifecycle rule of the bucket
+    /// aborting incomplete multipart uploads.
+    pub async fn pre_save_snapshot_multipart(
+        &self,
+        topic: &str,
+        pubkey: U256,
+        digest: Bytes32,
+        size: u64,
+    ) -> Result<S3MultipartUpload> {
+        let part_size = self.config.multipart_part_size;
+        let num_parts = size.div_ceil(part_size).max(1);
+        if num_parts > MAX_MULTIPART_PARTS {
+            return Err(StoreVaultError::ValidationError(format!(
+                "snapshot of {size} bytes needs {num_parts} parts, more than {MAX_MULTIPART_PARTS}"
+            )));
+        }
+        let path = get_path(topic, pubkey, digest);
+        let upload_id = self
+            .s3_client
+            .create_multipart_upload(&path, "application/octet-stream")
+            .await?;
+        let part_urls = self
+            .s3_client
+            .generate_upload_part_urls(
+                &path,
+                &upload_id,
+                num_parts as u32,
+                Duration::from_secs(self.config.s3_upload_timeout),
+            )
+            .await?;
+        self.save_pending_upload(topic, pubkey, digest).await?;
+        Ok(S3MultipartUpload {
+            upload_id,
+            part_size,
+            part_urls,
+        })
+    }
+
+    /// Assemble the parts of a multipart upload started by `pre_save_snapshot_multipart`. Must
+    /// be called before `save_snapshot`. The upload is aborted if it can't be completed.
+    pub async fn complete_multipart_snapshot(
+        &self,
+        topic: &str,
+        pubkey: U256,
+        digest: Bytes32,
+        upload: &S3CompletedMultipartUpload,
+    ) -> Result<()> {
+        let path = get_path(topic, pubkey, digest);
+        let is_ordered = upload
+            .parts
+            .windows(2)
+            .all(|pair| pair[0].part_number < pair[1].part_number);
+        if upload.parts.is_empty() || !is_ordered {
+            self.s3_client
+                .abort_multipart_upload(&path, &upload.upload_id)
+                .await?;
+            return Err(StoreVaultError::ValidationError(
+                "parts must be non-empty and ordered by part number".to_string(),
+            ));
+        }
+        let parts = upload
+            .parts
+            .iter()
+            .map(|part| (part.part_number, part.etag.clone()))
+            .collect::<Vec<_>>();
+        if let Err(e) = self
+            .s3_client
+            .complete_multipart_upload(&path, &upload.upload_id, &parts)
+            .await
+        {
+            log::warn!("Failed to complete multipart upload of {path}: {e}");
+            self.s3_client
+                .abort_multipart_upload(&path, &upload.upload_id)
+                .await?;
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    async fn save_pending_upload(&self, topic: &str, pubkey: U256, digest: Bytes32) -> Result<()> {
         sqlx::query!(
             r#"
             INSERT INTO s3_snapshot_pending_uploads (digest, pubkey, topic, timestamp)
@@ -115,8 +234,7 @@ impl S3StoreVault {
         )
         .execute(&self.pool)
         .await?;
-
-        Ok(presigned_url)
+        Ok(())
     }
 
     pub async fn save_snapshot(
@@ -165,6 +283,22 @@ impl S3StoreVault {
         }
 
         let mut tx = self.pool.begin().await?;
+        if self.config.snapshot_retention.is_some() {
+            // keep the superseded snapshot until the compaction removes it
+            sqlx::query(
+                r#"
+                INSERT INTO s3_snapshot_history (pubkey, topic, digest, timestamp)
+                SELECT pubkey, topic, digest, timestamp FROM s3_snapshot_data
+                WHERE pubkey = $1 AND topic = $2
+                ON CONFLICT (pubkey, topic, digest) DO NOTHING
+                "#,
+            )
+            .bind(pubkey.to_hex())
+            .bind(topic)
+            .execute(tx.as_mut())
+            .await?;
+        }
+
         // insert new digest
         sqlx::query!(
             r#"
@@ -194,8 +328,8 @@ impl S3StoreVault {
         .await?;
         tx.commit().await?;
 
-        // delete old data if it exists
-        if let Some(prev_digest) = prev_digest {
+        // delete old data if it exists and is not retained
+        if let (Some(prev_digest), None) = (prev_digest, self.config.snapshot_retention) {
             let prev_path = get_path(topic, pubkey, prev_digest);
             self.s3_client.delete_object(&prev_path).await?;
         }
@@ -217,6 +351,36 @@ impl S3StoreVault {
         }
     }
 
+    /// Returns the topics stored for `pubkey` with their number of entries, sorted by topic.
+    /// Historical data whose upload has not finished is not counted.
+    pub async fn list_topics(&self, pubkey: U256) -> Result<Vec<TopicEntryCount>> {
+        let records: Vec<(String, i64)> = sqlx::query_as(
+            r#"
+            SELECT topic, SUM(count)::BIGINT FROM (
+                SELECT topic, COUNT(*) AS count FROM s3_historical_data
+                WHERE pubkey = $1 AND upload_finished
+                GROUP BY topic
+                UNION ALL
+                SELECT topic, 1 AS count FROM s3_snapshot_data
+                WHERE pubkey = $1
+            ) t
+            GROUP BY topic
+            ORDER BY topic
+            "#,
+        )
+        .bind(pubkey.to_hex())
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(records
+            .into_iter()
+            .map(|(topic, count)| TopicEntryCount {
+                topic,
+                count: count as u64,
+            })
+            .collect())
+    }
+
     pub async fn batch_save_data_url(&self, entries: &[S3SaveDataEntry]) -> Result<Vec<String>> {
         // Prepare values for bulk insert
         let topics: Vec<String> = entries.iter().map(|entry| entry.topic.clone()).collect();
@@ -520,6 +684,86 @@ impl S3StoreVault {
         Ok(())
     }
 
+    /// Deletes the superseded snapshots of every (pubkey, topic), keeping the latest
+    /// `snapshot_retention` of them. Returns the number of deleted snapshots.
+    pub async fn compact_snapshots(&self) -> Result<u64> {
+        let retention = self.config.snapshot_retention.unwrap_or(0);
+        let keys: Vec<(String, String)> = sqlx::query_as(
+            r#"
+            SELECT DISTINCT pubkey, topic FROM s3_snapshot_history
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut deleted = 0;
+        for (pubkey, topic) in keys {
+            deleted += self
+                .compact_snapshot_history(&pubkey, &topic, retention)
+                .await?;
+        }
+        Ok(deleted)
+    }
+
+    async fn compact_snapshot_history(
+        &self,
+        pubkey: &str,
+        topic: &str,
+        retention: u64,
+    ) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+        // Lock the head so that a concurrent save_snapshot waits until the compaction is done.
+        let head: Option<(String, i64)> = sqlx::query_as(
+            r#"
+            SELECT digest, timestamp FROM s3_snapshot_data
+            WHERE pubkey = $1 AND topic = $2
+            FOR UPDATE
+            "#,
+        )
+        .bind(pubkey)
+        .bind(topic)
+        .fetch_optional(tx.as_mut())
+        .await?;
+        let Some((head_digest, head_timestamp)) = head else {
+            return Ok(0);
+        };
+
+        let expired: Vec<(String,)> = sqlx::query_as(
+            r#"
+            SELECT digest FROM s3_snapshot_history
+            WHERE pubkey = $1 AND topic = $2 AND digest <> $3 AND timestamp < $4
+            ORDER BY timestamp DESC, digest DESC
+            OFFSET $5
+            "#,
+        )
+        .bind(pubkey)
+        .bind(topic)
+        .bind(&head_digest)
+        .bind(head_timestamp)
+        .bind(retention as i64)
+        .fetch_all(tx.as_mut())
+        .await?;
+
+        let pubkey_u256 = U256::from_hex(pubkey).unwrap();
+        for (digest,) in expired.iter() {
+            let path = get_path(topic, pubkey_u256, Bytes32::from_hex(digest).unwrap());
+            self.s3_client.delete_object(&path).await?;
+            sqlx::query(
+                r#"
+                DELETE FROM s3_snapshot_history
+                WHERE pubkey = $1 AND topic = $2 AND digest = $3
+                "#,
+            )
+            .bind(pubkey)
+            .bind(topic)
+            .bind(digest)
+            .execute(tx.as_mut())
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(expired.len() as u64)
+    }
+
     pub fn run(&self) {
         let period = Duration::from_secs(self.config.cleanup_interval);
         let self_clone = self.clone();
@@ -543,12 +787,29 @@ impl S3StoreVault {
                 }
             }
         });
+
+        if self.config.snapshot_retention.is_some() {
+            let self_clone = self.clone();
+            actix_web::rt::spawn(async move {
+                let mut interval = tokio::time::interval(period);
+                loop {
+                    interval.tick().await;
+                    match self_clone.compact_snapshots().await {
+                        Ok(0) => {}
+                        Ok(deleted) => log::info!("Compacted {deleted} superseded snapshots"),
+                        Err(e) => log::error!("Error in compact_snapshots: {e:?}"),
+                    }
+                }
+            });
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use intmax2_interfaces::utils::digest::get_digest;
+    use intmax2_interfaces::{
+        api::s3_store_vault::types::S3CompletedPart, utils::digest::get_digest,
+    };
     use intmax2_zkp::ethereum_types::u256::U256;
     use mockall::predicate::eq;
     use sqlx::{Executor, PgPool, Postgres};
@@ -639,6 +900,9 @@ mod tests {
                 s3_upload_timeout: 0,
                 s3_download_timeout: 0,
                 cleanup_interval: 0,
+                snapshot_retention: None,
+                multipart_threshold: DEFAULT_MULTIPART_THRESHOLD,
+                multipart_part_size: DEFAULT_MULTIPART_PART_SIZE,
             },
         );
 
@@ -665,6 +929,9 @@ mod tests {
             s3_upload_timeout: 10,
             s3_download_timeout: 0,
             cleanup_interval: 0,
+            snapshot_retention: None,
+            multipart_threshold: DEFAULT_MULTIPART_THRESHOLD,
+            multipart_part_size: DEFAULT_MULTIPART_PART_SIZE,
         };
         let mut vault = create_vault(pool, config.clone());
 
@@ -760,6 +1027,9 @@ mod tests {
                 s3_upload_timeout: 0,
                 s3_download_timeout: 0,
                 cleanup_interval: 0,
+                snapshot_retention: None,
+                multipart_threshold: DEFAULT_MULTIPART_THRESHOLD,
+                multipart_part_size: DEFAULT_MULTIPART_PART_SIZE,
             },
         );
 
@@ -798,6 +1068,9 @@ mod tests {
                 s3_upload_timeout: 0,
                 s3_download_timeout: 0,
                 cleanup_interval: 0,
+                snapshot_retention: None,
+                multipart_threshold: DEFAULT_MULTIPART_THRESHOLD,
+                multipart_part_size: DEFAULT_MULTIPART_PART_SIZE,
             },
         );
 
@@ -844,6 +1117,9 @@ mod tests {
                 s3_upload_timeout: 0,
                 s3_download_timeout: 0,
                 cleanup_interval: 0,
+                snapshot_retention: None,
+                multipart_threshold: DEFAULT_MULTIPART_THRESHOLD,
+                multipart_part_size: DEFAULT_MULTIPART_PART_SIZE,
             },
         );
 
@@ -919,6 +1195,9 @@ mod tests {
                 s3_upload_timeout: 0,
                 s3_download_timeout: 0,
                 cleanup_interval: 0,
+                snapshot_retention: None,
+                multipart_threshold: DEFAULT_MULTIPART_THRESHOLD,
+                multipart_part_size: DEFAULT_MULTIPART_PART_SIZE,
             },
         );
 
@@ -1111,6 +1390,9 @@ mod tests {
                 s3_upload_timeout: S3_UPLOAD_TIMEOUT,
                 s3_download_timeout: 0,
                 cleanup_interval: 0,
+                snapshot_retention: None,
+                multipart_threshold: DEFAULT_MULTIPART_THRESHOLD,
+                multipart_part_size: DEFAULT_MULTIPART_PART_SIZE,
             },
         );
 
@@ -1195,6 +1477,231 @@ mod tests {
         );
     }
 
+    /// test case 1: It is expected to delete the superseded snapshots beyond the retention.
+    ///
+    /// test case 2: It is expected to keep the snapshots that are not strictly older than the head.
+    #[sqlx::test]
+    async fn compact_snapshots_test(pool: PgPool) {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let mut vault = create_vault(
+            pool,
+            Config {
+                s3_upload_timeout: 0,
+                s3_download_timeout: 0,
+                cleanup_interval: 0,
+                snapshot_retention: Some(1),
+                multipart_threshold: DEFAULT_MULTIPART_THRESHOLD,
+                multipart_part_size: DEFAULT_MULTIPART_PART_SIZE,
+            },
+        );
+
+        let topic = "topic";
+        let pubkey = U256::from(1);
+        insert_snapshot(topic, pubkey, get_digest(b"head"), &vault.pool).await;
+        let (_, head_timestamp) = select_snapshot(pubkey, topic, &vault.pool).await;
+
+        let digests = (1..=4u8).map(|i| get_digest(&[i])).collect::<Vec<_>>();
+        let timestamps = [
+            head_timestamp - 30,
+            head_timestamp - 20,
+            head_timestamp - 10,
+            head_timestamp,
+        ];
+        for (digest, timestamp) in digests.iter().zip(timestamps) {
+            sqlx::query(
+                r#"
+                INSERT INTO s3_snapshot_history (pubkey, topic, digest, timestamp)
+                VALUES ($1, $2, $3, $4)
+                "#,
+            )
+            .bind(pubkey.to_hex())
+            .bind(topic)
+            .bind(digest.to_hex())
+            .bind(timestamp)
+            .execute(&vault.pool)
+            .await
+            .unwrap();
+        }
+
+        for digest in &digests[..2] {
+            vault
+                .s3_client
+                .expect_delete_object()
+                .with(eq(get_path(topic, pubkey, *digest)))
+                .times(1)
+                .returning(|_| Ok(()));
+        }
+
+        // test case 1
+        assert_eq!(vault.compact_snapshots().await.unwrap(), 2);
+
+        // test case 2
+        let remaining: Vec<(String,)> =
+            sqlx::query_as("SELECT digest FROM s3_snapshot_history ORDER BY timestamp")
+                .fetch_all(&vault.pool)
+                .await
+                .unwrap();
+        assert_eq!(
+            remaining,
+            vec![(digests[2].to_hex(),), (digests[3].to_hex(),)]
+        );
+    }
+
+    /// test case 1: It is expected to count the finished historical data and the snapshots per topic.
+    ///
+    /// test case 2: It is expected to not list the topics of other pubkeys.
+    #[sqlx::test]
+    async fn list_topics_test(pool: PgPool) {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let vault = create_vault(
+            pool,
+            Config {
+                s3_upload_timeout: 0,
+                s3_download_timeout: 0,
+                cleanup_interval: 0,
+                snapshot_retention: None,
+                multipart_threshold: DEFAULT_MULTIPART_THRESHOLD,
+                multipart_part_size: DEFAULT_MULTIPART_PART_SIZE,
+            },
+        );
+
+        let pubkey = U256::from(1);
+        let other_pubkey = U256::from(2);
+        insert_snapshot(
+            "v1/aw/user_data",
+            pubkey,
+            get_digest(b"snapshot"),
+            &vault.pool,
+        )
+        .await;
+        insert_snapshot(
+            "v1/aw/user_data",
+            other_pubkey,
+            get_digest(b"other"),
+            &vault.pool,
+        )
+        .await;
+        for (i, (topic, upload_finished)) in [
+            ("v1/aw/deposit", true),
+            ("v1/aw/deposit", true),
+            ("v1/aw/deposit", false),
+            ("v1/aw/transfer", true),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            sqlx::query(
+                r#"
+                INSERT INTO s3_historical_data (digest, pubkey, topic, timestamp, upload_finished)
+                VALUES ($1, $2, $3, $4, $5)
+                "#,
+            )
+            .bind(get_digest(&[i as u8]).to_hex())
+            .bind(pubkey.to_hex())
+            .bind(topic)
+            .bind(i as i64)
+            .bind(upload_finished)
+            .execute(&vault.pool)
+            .await
+            .unwrap();
+        }
+
+        // test case 1
+        let topics = vault.list_topics(pubkey).await.unwrap();
+        let expected = [
+            ("v1/aw/deposit", 2),
+            ("v1/aw/transfer", 1),
+            ("v1/aw/user_data", 1),
+        ]
+        .into_iter()
+        .map(|(topic, count)| TopicEntryCount {
+            topic: topic.to_string(),
+            count,
+        })
+        .collect::<Vec<_>>();
+        assert_eq!(topics, expected);
+
+        // test case 2
+        let topics = vault.list_topics(other_pubkey).await.unwrap();
+        assert_eq!(topics.len(), 1);
+        assert_eq!(topics[0].topic, "v1/aw/user_data");
+    }
+
+    /// test case 1: It is expected to presign one URL per part, the last part being shorter.
+    ///
+    /// test case 2: It is expected to abort the upload if the completed parts are not ordered.
+    #[sqlx::test]
+    async fn pre_save_snapshot_multipart_test(pool: PgPool) {
+        let _ = env_logger::builder().is_test(true).try_init();
+        let config = Config {
+            s3_upload_timeout: 10,
+            s3_download_timeout: 0,
+            cleanup_interval: 0,
+            snapshot_retention: None,
+            multipart_threshold: DEFAULT_MULTIPART_THRESHOLD,
+            multipart_part_size: DEFAULT_MULTIPART_PART_SIZE,
+        };
+        let mut vault = create_vault(pool, config.clone());
+
+        let topic = "topic";
+        let pubkey = U256::from(1);
+        let digest = get_digest(b"large data");
+        let path = get_path(topic, pubkey, digest);
+        let size = 2 * DEFAULT_MULTIPART_PART_SIZE + 1;
+        assert!(vault.is_multipart(size));
+        assert!(!vault.is_multipart(DEFAULT_MULTIPART_PART_SIZE));
+
+        vault
+            .s3_client
+            .expect_create_multipart_upload()
+            .with(eq(path.clone()), eq("application/octet-stream"))
+            .returning(|_, _| Ok("upload_id".to_string()));
+        vault
+            .s3_client
+            .expect_generate_upload_part_urls()
+            .with(
+                eq(path.clone()),
+                eq("upload_id"),
+                eq(3),
+                eq(Duration::from_secs(config.s3_upload_timeout)),
+            )
+            .returning(|_, _, num_parts, _| {
+                Ok((1..=num_parts).map(|i| format!("part_url_{i}")).collect())
+            });
+        let upload = vault
+            .pre_save_snapshot_multipart(topic, pubkey, digest, size)
+            .await
+            .unwrap();
+
+        // test case 1
+        assert_eq!(upload.upload_id, "upload_id");
+        assert_eq!(upload.part_size, DEFAULT_MULTIPART_PART_SIZE);
+        assert_eq!(upload.part_urls.len(), 3);
+
+        vault
+            .s3_client
+            .expect_abort_multipart_upload()
+            .with(eq(path.clone()), eq("upload_id"))
+            .times(1)
+            .returning(|_, _| Ok(()));
+        let completed = S3CompletedMultipartUpload {
+            upload_id: "upload_id".to_string(),
+            parts: [2, 1, 3]
+                .into_iter()
+                .map(|part_number| S3CompletedPart {
+                    part_number,
+                    etag: format!("etag_{part_number}"),
+                })
+                .collect(),
+        };
+        let result = vault
+            .complete_multipart_snapshot(topic, pubkey, digest, &completed)
+            .await;
+
+        // test case 2
+        assert!(matches!(result, Err(StoreVaultError::ValidationError(_))));
+    }
+
     fn create_vault(pool: PgPool, config: Config) -> S3StoreVault {
         let pool = DbPool::new(pool);
         let s3_client = S3Client::default();
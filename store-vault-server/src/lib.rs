@@ -18,6 +18,16 @@ pub struct EnvVar {
 
     pub s3_upload_timeout: u64,
     pub s3_download_timeout: u64,
+    /// Snapshots of at least this many bytes are uploaded in parts. Defaults to 32 MiB.
+    pub s3_multipart_threshold: Option<u64>,
+    /// Size of a part of a multipart upload, at least 5 MiB. Defaults to 8 MiB.
+    pub s3_multipart_part_size: Option<u64>,
 
     pub cleanup_interval: u64,
+
+    /// Number of superseded snapshots retained per (pubkey, topic). Enables the periodic
+    /// snapshot compaction if set.
+    pub snapshot_retention: Option<u64>,
+    /// Bearer token required by the admin endpoints. The admin endpoints are disabled if unset.
+    pub admin_token: Option<String>,
 }
@@ -1,11 +1,13 @@
-use actix_cors::Cors;
 use actix_web::{
+    middleware::from_fn,
     web::{Data, JsonConfig},
     App, HttpServer,
 };
 use server_common::{
+    cors::CorsConfig,
     health_check::{health_check, set_name_and_version},
     logger,
+    metrics::track_requests,
 };
 use std::io::{self};
 use store_vault_server::{
@@ -31,13 +33,16 @@ async fn main() -> std::io::Result<()> {
     // start tasks
     s3_store_vault.run();
 
-    let state = Data::new(State::new(s3_store_vault));
+    let state = Data::new(State::new(s3_store_vault, env.admin_token.clone()));
+
+    let cors_config = CorsConfig::from_env().map_err(io::Error::other)?;
 
     HttpServer::new(move || {
-        let cors = Cors::permissive();
+        let cors = cors_config.cors();
         App::new()
             .wrap(cors)
             .wrap(TracingLogger::<logger::CustomRootSpanBuilder>::new())
+            .wrap(from_fn(track_requests))
             .app_data(JsonConfig::default().limit(35_000_000))
             .app_data(state.clone())
             .service(health_check)
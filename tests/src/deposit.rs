@@ -4,7 +4,8 @@ use intmax2_cli::cli::deposit::fetch_predicate_permission;
 use intmax2_client_sdk::{
     client::{client::Client, key_from_eth::generate_intmax_account_from_eth_key},
     external_api::contract::{
-        convert::convert_address_to_intmax, utils::get_address_from_private_key,
+        convert::convert_address_to_intmax, handlers::GasOptions,
+        utils::get_address_from_private_key,
     },
 };
 use intmax2_interfaces::data::deposit_data::{DepositData, TokenType};
@@ -50,6 +51,7 @@ pub async fn single_deposit(
         .deposit_native(
             eth_private_key,
             None,
+            GasOptions::default(),
             deposit_data.pubkey_salt_hash,
             deposit_data.amount,
             &aml_permission,
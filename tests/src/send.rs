@@ -37,11 +37,12 @@ pub async fn send_transfers(
 
     let memo = client
         .send_tx_request(
-            &block_builder_url,
+            std::slice::from_ref(&block_builder_url),
             key,
             transfers,
             payment_memos,
             &fee_quote,
+            None,
         )
         .await?;
 
@@ -52,12 +53,12 @@ pub async fn send_transfers(
     .await;
 
     let proposal = client
-        .query_proposal(&block_builder_url, &memo.request_id)
+        .query_proposal(&memo.block_builder_url, &memo.request_id)
         .await?;
 
     log::info!("Finalizing tx");
     let result = client
-        .finalize_tx(&block_builder_url, key, &memo, &proposal)
+        .finalize_tx(&memo.block_builder_url, key, &memo, &proposal)
         .await?;
 
     let expiry: u64 = proposal.block_sign_payload.expiry.into();
@@ -86,6 +87,9 @@ pub async fn send_transfers(
             TxStatus::Failed(reason) => {
                 anyhow::bail!("tx failed: {}", reason);
             }
+            TxStatus::Expired => {
+                anyhow::bail!("tx request expired");
+            }
         }
         sleep_for(config.tx_status_check_interval).await;
     }
@@ -0,0 +1,161 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use alloy::primitives::B256;
+use intmax2_cli::cli::client::get_client;
+use intmax2_client_sdk::client::{
+    client::Client, key_from_eth::generate_intmax_account_from_eth_key, sync::progress::SyncEvent,
+};
+use intmax2_interfaces::data::user_data::UserData;
+use intmax2_zkp::{
+    circuits::balance::balance_pis::BalancePublicInputs, common::signature_content::key_set::KeySet,
+};
+use serde::Deserialize;
+use tokio::sync::Notify;
+
+#[derive(Deserialize)]
+struct EnvVar {
+    pub eth_private_key: B256,
+}
+
+/// The parts of the user data a deep resync must reproduce, in a comparable form. The private
+/// commitment is left out because every resync draws new salts.
+fn resync_result(user_data: &UserData) -> anyhow::Result<Vec<String>> {
+    let balance_proof = user_data
+        .balance_proof
+        .as_ref()
+        .expect("the account has no balance proof")
+        .decompress()?;
+    let pis = BalancePublicInputs::from_pis(&balance_proof.public_inputs)?;
+    let balances = user_data
+        .balances()
+        .0
+        .into_iter()
+        .map(|(token_index, leaf)| (token_index, leaf.amount.to_string(), leaf.is_insufficient))
+        .collect::<BTreeMap<_, _>>();
+    let mut nullifiers = user_data
+        .full_private_state
+        .nullifier_tree
+        .nullifiers()
+        .iter()
+        .map(|nullifier| nullifier.to_string())
+        .collect::<Vec<_>>();
+    nullifiers.sort();
+    Ok(vec![
+        format!("{:?}", pis.pubkey),
+        format!("{:?}", pis.last_tx_hash),
+        format!("{:?}", pis.public_state),
+        format!("{balances:?}"),
+        format!("{nullifiers:?}"),
+    ])
+}
+
+/// Interrupts a deep resync after its first applied action and resumes it, then checks that
+/// the resumed run ends in the same balance proof as an uninterrupted deep resync.
+#[tokio::test]
+#[ignore]
+async fn resumed_deep_resync_matches_uninterrupted() -> anyhow::Result<()> {
+    dotenvy::dotenv().ok();
+    let env = envy::from_env::<EnvVar>()?;
+    let client = get_client()?;
+    let key = generate_intmax_account_from_eth_key(env.eth_private_key);
+
+    client.resync(key, true).await?;
+    let progress = client.get_deep_resync_progress(key).await?.unwrap();
+    assert!(progress.completed);
+    assert!(
+        progress.num_applied > 1,
+        "the account needs at least two actions to interrupt a deep resync"
+    );
+    let expected = resync_result(&client.get_user_data(key).await?)?;
+
+    interrupt_deep_resync(&client, key).await?;
+    let progress = client.get_deep_resync_progress(key).await?.unwrap();
+    assert!(progress.is_resumable());
+    assert!(progress.num_applied >= 1);
+
+    client.resume_deep_resync(key).await?;
+    let resumed = client.get_deep_resync_progress(key).await?.unwrap();
+    assert!(resumed.completed);
+    assert_eq!(resumed.started_at, progress.started_at);
+    assert_eq!(resync_result(&client.get_user_data(key).await?)?, expected);
+    Ok(())
+}
+
+/// Interrupts a deep resync, then checks that a new deep resync discards its progress and
+/// replays every action instead of resuming.
+#[tokio::test]
+#[ignore]
+async fn new_deep_resync_discards_interrupted_progress() -> anyhow::Result<()> {
+    dotenvy::dotenv().ok();
+    let env = envy::from_env::<EnvVar>()?;
+    let client = get_client()?;
+    let key = generate_intmax_account_from_eth_key(env.eth_private_key);
+
+    client.resync(key, true).await?;
+    let full = client.get_deep_resync_progress(key).await?.unwrap();
+    let expected = resync_result(&client.get_user_data(key).await?)?;
+
+    interrupt_deep_resync(&client, key).await?;
+    assert!(client
+        .get_deep_resync_progress(key)
+        .await?
+        .unwrap()
+        .is_resumable());
+
+    let applied = AtomicUsize::new(0);
+    client
+        .resync_with_progress(key, true, &|_: SyncEvent| {
+            applied.fetch_add(1, Ordering::SeqCst);
+        })
+        .await?;
+    let restarted = client.get_deep_resync_progress(key).await?.unwrap();
+    assert!(restarted.completed);
+    assert_eq!(applied.load(Ordering::SeqCst), full.num_applied);
+    assert_eq!(restarted.num_applied, full.num_applied);
+    assert_eq!(resync_result(&client.get_user_data(key).await?)?, expected);
+    Ok(())
+}
+
+/// Starts a deep resync and drops it once the first applied action is recorded.
+async fn interrupt_deep_resync(client: &Client, key: KeySet) -> anyhow::Result<()> {
+    let applied = Arc::new(AtomicUsize::new(0));
+    let notify = Arc::new(Notify::new());
+    let on_progress = {
+        let applied = applied.clone();
+        let notify = notify.clone();
+        move |_: SyncEvent| {
+            applied.fetch_add(1, Ordering::SeqCst);
+            notify.notify_one();
+        }
+    };
+    let resync = client.resync_with_progress(key, true, &on_progress);
+    tokio::pin!(resync);
+    tokio::select! {
+        result = &mut resync => {
+            result?;
+            anyhow::bail!("the deep resync completed before it was interrupted");
+        }
+        _ = notify.notified() => {}
+    }
+    // the progress is recorded right after the event, so poll until the marker is saved
+    loop {
+        let progress = client.get_deep_resync_progress(key).await?.unwrap();
+        if progress.num_applied >= applied.load(Ordering::SeqCst) {
+            break;
+        }
+        tokio::select! {
+            result = &mut resync => {
+                result?;
+                anyhow::bail!("the deep resync completed before it was interrupted");
+            }
+            _ = tokio::time::sleep(std::time::Duration::from_millis(100)) => {}
+        }
+    }
+    Ok(())
+}
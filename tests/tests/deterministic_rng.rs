@@ -0,0 +1,22 @@
+use intmax2_client_sdk::client::sync::utils::generate_salt;
+use intmax2_interfaces::utils::random::{default_rng, insecure_set_rng_seed};
+use intmax2_zkp::common::signature_content::key_set::KeySet;
+
+/// Salts and ephemeral keys drawn from `default_rng` are reproducible once it is seeded.
+#[test]
+fn seeded_salts_and_keys_are_reproducible() {
+    let draw = || {
+        let salt = generate_salt();
+        let key = KeySet::rand(&mut default_rng());
+        (format!("{salt:?}"), key.pubkey)
+    };
+    insecure_set_rng_seed(Some(7));
+    let first = draw();
+    insecure_set_rng_seed(Some(7));
+    let second = draw();
+    insecure_set_rng_seed(None);
+    let unseeded = draw();
+
+    assert_eq!(first, second);
+    assert_ne!(first, unseeded);
+}
@@ -0,0 +1,79 @@
+use alloy::primitives::B256;
+use intmax2_cli::cli::client::get_client;
+use intmax2_client_sdk::client::{
+    client::PaymentMemoEntry,
+    key_from_eth::generate_intmax_account_from_eth_key,
+    misc::payment_memo::{get_all_payment_memos, payment_memo_topic},
+    sync::utils::generate_salt,
+};
+use intmax2_interfaces::{
+    api::store_vault_server::types::MetaDataCursor, utils::random::default_rng,
+};
+use intmax2_zkp::{
+    common::{signature_content::key_set::KeySet, transfer::Transfer},
+    ethereum_types::u256::U256,
+};
+use serde::Deserialize;
+use tests::{
+    config::TestConfig,
+    send::send_transfers,
+    utils::{get_balance_on_intmax, get_block_builder_url},
+};
+
+#[derive(Deserialize)]
+struct EnvVar {
+    pub eth_private_key: B256,
+}
+
+/// Sends a zero amount transfer carrying only a payment memo, and checks that the sender pays
+/// the fee only, and that the memo and the transfer reach the store vault.
+#[tokio::test]
+#[ignore]
+async fn memo_only_transfer() -> anyhow::Result<()> {
+    dotenvy::dotenv().ok();
+    let env = envy::from_env::<EnvVar>()?;
+    let config = TestConfig::load_from_env()?;
+    let client = get_client()?;
+
+    let key = generate_intmax_account_from_eth_key(env.eth_private_key);
+    let recipient = KeySet::rand(&mut default_rng());
+    client.sync(key).await?;
+    let balance_before = get_balance_on_intmax(&client, key).await?;
+
+    let block_builder_url = get_block_builder_url(&config.indexer_base_url).await?;
+    let fee_quote = client
+        .quote_transfer_fee(&block_builder_url, key.pubkey, 0)
+        .await?;
+    let fee = fee_quote.fee.map_or(U256::zero(), |fee| fee.amount);
+
+    let memo_name = format!("memo-only-{}", chrono::Utc::now().timestamp());
+    let transfer = Transfer {
+        recipient: recipient.pubkey.into(),
+        token_index: 0,
+        amount: U256::zero(),
+        salt: generate_salt(),
+    };
+    let payment_memo = PaymentMemoEntry {
+        transfer_index: 0,
+        topic: payment_memo_topic(&memo_name),
+        memo: "memo only".to_string(),
+    };
+    send_transfers(&config, &client, key, &[transfer], &[payment_memo], 0).await?;
+
+    client.sync(key).await?;
+    let balance_after = get_balance_on_intmax(&client, key).await?;
+    assert_eq!(balance_before - fee, balance_after);
+
+    let memos = get_all_payment_memos(client.store_vault_server.as_ref(), key, &memo_name).await?;
+    assert_eq!(memos.len(), 1);
+    assert_eq!(memos[0].memo, "memo only");
+    assert_eq!(memos[0].transfer_data.transfer, transfer);
+
+    let (transfers, _) = client
+        .fetch_transfer_history(recipient, &MetaDataCursor::default())
+        .await?;
+    assert!(transfers
+        .iter()
+        .any(|entry| entry.data.transfer.salt == transfer.salt));
+    Ok(())
+}
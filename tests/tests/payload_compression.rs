@@ -0,0 +1,42 @@
+use alloy::primitives::B256;
+use intmax2_cli::cli::client::get_client;
+use intmax2_client_sdk::client::key_from_eth::generate_intmax_account_from_eth_key;
+use intmax2_interfaces::data::{encryption::BlsEncryption as _, user_data::UserData};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct EnvVar {
+    pub eth_private_key: B256,
+}
+
+/// Encrypts the synced user data, which holds a real balance proof, with and without
+/// compression, and checks that compression reduces the payload size and roundtrips.
+#[tokio::test]
+#[ignore]
+async fn payload_compression() -> anyhow::Result<()> {
+    dotenvy::dotenv().ok();
+    let env = envy::from_env::<EnvVar>()?;
+    let client = get_client()?;
+
+    let key = generate_intmax_account_from_eth_key(env.eth_private_key);
+    client.sync(key).await?;
+    let user_data = client.get_user_data(key).await?;
+    assert!(
+        user_data.balance_proof.is_some(),
+        "the account has no balance proof yet"
+    );
+
+    let uncompressed = user_data.encrypt_with_compression(key.pubkey, Some(key), false)?;
+    let compressed = user_data.encrypt_with_compression(key.pubkey, Some(key), true)?;
+    println!(
+        "user data: uncompressed {} bytes, compressed {} bytes ({:.1}%)",
+        uncompressed.len(),
+        compressed.len(),
+        compressed.len() as f64 / uncompressed.len() as f64 * 100.0
+    );
+    assert!(compressed.len() < uncompressed.len());
+
+    let decrypted = UserData::decrypt(key, Some(key.pubkey), &compressed)?;
+    assert_eq!(decrypted.to_bytes(), user_data.to_bytes());
+    Ok(())
+}
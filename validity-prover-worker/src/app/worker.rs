@@ -1,4 +1,4 @@
-use std::{collections::HashSet, sync::Arc};
+use std::{collections::HashSet, sync::Arc, time::Duration};
 
 use intmax2_interfaces::api::validity_prover::interface::{
     TransitionProofTask, TransitionProofTaskResult,
@@ -6,7 +6,7 @@ use intmax2_interfaces::api::validity_prover::interface::{
 use intmax2_zkp::circuits::validity::transition::processor::ValidityTransitionProcessor;
 use plonky2::{field::goldilocks_field::GoldilocksField, plonk::config::PoseidonGoldilocksConfig};
 use server_common::redis::task_manager::TaskManager;
-use tokio::sync::RwLock;
+use tokio::sync::{RwLock, Semaphore};
 use uuid::Uuid;
 
 use crate::EnvVar;
@@ -26,6 +26,7 @@ type Result<T> = std::result::Result<T, WorkerError>;
 struct Config {
     num_process: u32,
     heartbeat_interval: u64,
+    task_timeout: Option<u64>,
 }
 
 #[derive(Clone)]
@@ -35,6 +36,9 @@ pub struct Worker {
     manager: Arc<TaskManager<TransitionProofTask, TransitionProofTaskResult>>,
     worker_id: String,
     running_tasks: Arc<RwLock<HashSet<u32>>>,
+    // bounds the proofs in flight, including the ones abandoned after a timeout that are still
+    // running on a blocking thread
+    proof_semaphore: Arc<Semaphore>,
 }
 
 impl Worker {
@@ -45,7 +49,17 @@ impl Worker {
         let config = Config {
             num_process: env.num_process,
             heartbeat_interval: env.heartbeat_interval,
+            task_timeout: env.task_timeout,
         };
+        let max_concurrent_proofs = env
+            .max_concurrent_proofs
+            .unwrap_or(env.num_process as usize)
+            .max(1);
+        log::info!(
+            "num_process: {}, max_concurrent_proofs: {max_concurrent_proofs}, task_timeout: {:?}",
+            env.num_process,
+            env.task_timeout
+        );
 
         let manager = Arc::new(TaskManager::new(
             &env.redis_url,
@@ -60,6 +74,7 @@ impl Worker {
             manager,
             worker_id,
             running_tasks: Arc::new(RwLock::new(HashSet::new())),
+            proof_semaphore: Arc::new(Semaphore::new(max_concurrent_proofs)),
         })
     }
 
@@ -67,6 +82,14 @@ impl Worker {
         loop {
             tokio::time::sleep(tokio::time::Duration::from_secs(TASK_POLLING_INTERVAL)).await;
 
+            // wait for a free slot before taking a task, so that the backlog stays in redis for
+            // the other workers
+            let permit = self
+                .proof_semaphore
+                .clone()
+                .acquire_owned()
+                .await
+                .expect("proof semaphore is never closed");
             let task = self.manager.assign_task().await?;
             if task.is_none() {
                 continue;
@@ -83,10 +106,28 @@ impl Worker {
                 prev_validity_pis,
                 validity_witness,
             } = task.clone();
-            let result = tokio::task::spawn_blocking(move || {
-                transition_processor.prove(&prev_validity_pis, &validity_witness)
-            })
-            .await
+            let handle = tokio::task::spawn_blocking(move || {
+                let result = transition_processor.prove(&prev_validity_pis, &validity_witness);
+                // the slot is freed only when the proof actually finishes
+                drop(permit);
+                result
+            });
+            let result = match self.config.task_timeout {
+                Some(task_timeout) => {
+                    match tokio::time::timeout(Duration::from_secs(task_timeout), handle).await {
+                        Ok(result) => result,
+                        Err(_) => {
+                            self.running_tasks.write().await.remove(&block_number);
+                            let requeued = self.manager.requeue_task(block_number).await?;
+                            log::warn!(
+                                "proving block_number {block_number} timed out after {task_timeout}s, requeued: {requeued}"
+                            );
+                            continue;
+                        }
+                    }
+                }
+                None => handle.await,
+            }
             .map_err(|e| format!("panic while proving: {e:?}"))
             .and_then(|r| r.map_err(|e| format!("error while proving: {e:?}")));
             if let Err(e) = result {
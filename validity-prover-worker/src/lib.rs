@@ -8,4 +8,9 @@ pub struct EnvVar {
     pub task_ttl: u64,
     pub heartbeat_interval: u64,
     pub num_process: u32,
+
+    // Maximum number of proofs in flight at once, defaults to num_process
+    pub max_concurrent_proofs: Option<usize>,
+    // Seconds after which a proof is abandoned and its task requeued, unbounded if unset
+    pub task_timeout: Option<u64>,
 }
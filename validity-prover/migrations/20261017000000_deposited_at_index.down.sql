@@ -0,0 +1 @@
+DROP INDEX IF EXISTS idx_deposited_events_deposited_at;
@@ -0,0 +1 @@
+CREATE INDEX IF NOT EXISTS idx_deposited_events_deposited_at ON deposited_events(deposited_at);
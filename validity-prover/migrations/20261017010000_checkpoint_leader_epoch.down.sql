@@ -0,0 +1 @@
+ALTER TABLE event_sync_eth_block DROP COLUMN IF EXISTS leader_epoch;
@@ -0,0 +1,2 @@
+-- epoch of the leader that last wrote the checkpoint, to reject the writes of stale leaders
+ALTER TABLE event_sync_eth_block ADD COLUMN IF NOT EXISTS leader_epoch BIGINT NOT NULL DEFAULT 0;
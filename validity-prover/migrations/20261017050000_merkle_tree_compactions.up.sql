@@ -0,0 +1,7 @@
+-- latest history compaction of each SQL merkle tree. States before `cutoff` are only kept at
+-- multiples of `checkpoint_interval`
+CREATE TABLE IF NOT EXISTS merkle_tree_compactions (
+    tag INTEGER PRIMARY KEY,
+    cutoff BIGINT NOT NULL,
+    checkpoint_interval BIGINT NOT NULL
+);
@@ -5,6 +5,7 @@ use crate::{
     app::{
         check_point_store::EventType,
         observer_common::sync_event_key,
+        rate_manager::HaltRecord,
         validity_prover::{
             ADD_TASKS_KEY, CLEANUP_INACTIVE_TASKS_KEY, GENERATE_VALIDITY_PROOF_KEY,
             SYNC_VALIDITY_WITNESS_KEY,
@@ -28,6 +29,17 @@ pub struct HealthCheckResponse {
 pub async fn health_check(state: Data<State>) -> Result<Json<HealthCheckResponse>, Error> {
     let heartbeat_timeout = state.health_check_config.thread_heartbeat_timeout.as_secs();
 
+    let halts =
+        state.rate_manager.get_halts().await.map_err(|_| {
+            actix_web::error::ErrorInternalServerError("Failed to get observer halts")
+        })?;
+    if let Some(halt) = halts.first() {
+        return Err(actix_web::error::ErrorInternalServerError(format!(
+            "{} is halted since {}: {}",
+            halt.key, halt.halted_at, halt.reason
+        )));
+    }
+
     let mut keys = [
         EventType::Deposited,
         EventType::DepositLeafInserted,
@@ -73,3 +85,63 @@ pub async fn health_check(state: Data<State>) -> Result<Json<HealthCheckResponse
         version: env!("CARGO_PKG_VERSION").to_string(),
     }))
 }
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusResponse {
+    pub name: String,
+    pub version: String,
+    /// Latest L2 block number synced by the observer
+    pub observed_block_number: u32,
+    /// Latest block number for which a validity proof exists
+    pub validity_proof_block_number: u32,
+    /// Number of observed blocks that do not have a validity proof yet
+    pub validity_proof_lag: u32,
+    /// Maximum number of entries of the dynamic cache, if bounded
+    pub dynamic_cache_max_entries: Option<usize>,
+    /// Number of entries evicted from the dynamic cache. A steadily growing count means the
+    /// cache is too small for the query pattern.
+    pub dynamic_cache_evictions: u64,
+    /// Whether an observer is halted, in which case the observed block number no longer
+    /// advances until the server is restarted
+    pub observer_halted: bool,
+    /// Halted observers with the reason they were halted
+    pub observer_halts: Vec<HaltRecord>,
+}
+
+#[get("/status")]
+pub async fn status(state: Data<State>) -> Result<Json<StatusResponse>, Error> {
+    // read directly from the db instead of the cache so that monitoring sees the latest values
+    let observed_block_number = state
+        .validity_prover
+        .observer_api
+        .get_local_last_block_number()
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let validity_proof_block_number = state
+        .validity_prover
+        .get_latest_validity_proof_block_number()
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let dynamic_cache_evictions = state
+        .dynamic_cache
+        .evictions()
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    let observer_halts = state
+        .rate_manager
+        .get_halts()
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(Json(StatusResponse {
+        name: env!("CARGO_PKG_NAME").to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        observed_block_number,
+        validity_proof_block_number,
+        validity_proof_lag: observed_block_number.saturating_sub(validity_proof_block_number),
+        dynamic_cache_max_entries: state.cache_config.dynamic_max_entries,
+        dynamic_cache_evictions,
+        observer_halted: !observer_halts.is_empty(),
+        observer_halts,
+    }))
+}
@@ -5,19 +5,21 @@ use intmax2_client_sdk::external_api::contract::{
 };
 use intmax2_interfaces::{
     api::validity_prover::{
-        interface::DepositInfo,
+        interface::{DepositInfo, SyncStatus},
         types::{
             GetAccountInfoBatchRequest, GetAccountInfoBatchResponse, GetAccountInfoQuery,
             GetAccountInfoResponse, GetBlockMerkleProofQuery, GetBlockMerkleProofResponse,
             GetBlockNumberByTxTreeRootBatchRequest, GetBlockNumberByTxTreeRootBatchResponse,
             GetBlockNumberByTxTreeRootQuery, GetBlockNumberByTxTreeRootResponse,
-            GetDepositInfoBatchRequest, GetDepositInfoBatchResponse, GetDepositInfoQuery,
-            GetDepositInfoResponse, GetDepositMerkleProofQuery, GetDepositMerkleProofResponse,
-            GetUpdateWitnessQuery, GetUpdateWitnessResponse, GetValidityProofQuery,
-            GetValidityProofResponse, GetValidityWitnessQuery, GetValidityWitnessResponse,
+            GetDepositBloomFilterQuery, GetDepositBloomFilterResponse, GetDepositInfoBatchRequest,
+            GetDepositInfoBatchResponse, GetDepositInfoQuery, GetDepositInfoResponse,
+            GetDepositMerkleProofQuery, GetDepositMerkleProofResponse, GetUpdateWitnessQuery,
+            GetUpdateWitnessResponse, GetValidityProofQuery, GetValidityProofResponse,
+            GetValidityWitnessQuery, GetValidityWitnessResponse,
         },
     },
     data::proof_compression::CompressedValidityProof,
+    utils::bloom_filter::BloomFilter,
 };
 use intmax2_zkp::common::{
     trees::{block_hash_tree::BlockHashMerkleProof, deposit_tree::DepositMerkleProof},
@@ -44,6 +46,7 @@ pub struct HealthCheckConfig {
 pub struct CacheConfig {
     pub dynamic_ttl: Duration,
     pub static_ttl: Duration,
+    pub dynamic_max_entries: Option<usize>,
 }
 
 /// The state of the server.
@@ -51,7 +54,10 @@ pub struct CacheConfig {
 pub struct State {
     pub validity_prover: ValidityProver,
     pub rate_manager: RateManager,
+    /// Cache of the values that never change once computed, bounded by the tree structure
     pub cache: RedisCache,
+    /// Cache of the values that change as the prover syncs, bounded by LRU eviction
+    pub dynamic_cache: RedisCache,
     pub cache_config: CacheConfig,
     pub health_check_config: HealthCheckConfig,
 }
@@ -62,9 +68,11 @@ impl State {
         let l2_rpc_urls = parse_urls(&env.l2_rpc_url)?;
         let l1_provider = get_provider_with_fallback(l1_rpc_urls.as_ref())?;
         let l2_provider = get_provider_with_fallback(l2_rpc_urls.as_ref())?;
-        let rollup_contract = RollupContract::new(l2_provider, env.rollup_contract_address);
+        let rollup_contract = RollupContract::new(l2_provider, env.rollup_contract_address)
+            .with_event_block_range(env.observer_event_block_interval);
         let liquidity_contract =
-            LiquidityContract::new(l1_provider, env.liquidity_contract_address);
+            LiquidityContract::new(l1_provider, env.liquidity_contract_address)
+                .with_event_block_range(env.observer_event_block_interval);
         let observer_api = ObserverApi::new(env, rollup_contract, liquidity_contract).await?;
         let leader_election = LeaderElection::new(
             &env.redis_url,
@@ -84,9 +92,14 @@ impl State {
         )
         .await?;
         let cache = RedisCache::new(&env.redis_url, "validity_prover:cache")?;
+        let mut dynamic_cache = RedisCache::new(&env.redis_url, "validity_prover:dynamic_cache")?;
+        if let Some(max_entries) = env.dynamic_cache_max_entries {
+            dynamic_cache = dynamic_cache.with_max_entries(max_entries);
+        }
         let cache_config = CacheConfig {
             dynamic_ttl: Duration::from_secs(env.dynamic_cache_ttl),
             static_ttl: Duration::from_secs(env.static_cache_ttl),
+            dynamic_max_entries: env.dynamic_cache_max_entries,
         };
         let health_check_config = HealthCheckConfig {
             thread_heartbeat_timeout: Duration::from_secs(env.thread_heartbeat_timeout),
@@ -126,6 +139,7 @@ impl State {
             validity_prover,
             rate_manager,
             cache,
+            dynamic_cache,
             cache_config,
             health_check_config,
         })
@@ -134,11 +148,11 @@ impl State {
     pub async fn get_block_number(&self) -> anyhow::Result<u32> {
         type V = u32;
         let key = "block_number";
-        if let Some(block_number) = self.cache.get::<V>(key).await? {
+        if let Some(block_number) = self.dynamic_cache.get::<V>(key).await? {
             Ok(block_number)
         } else {
             let block_number = self.validity_prover.get_last_block_number().await?;
-            self.cache
+            self.dynamic_cache
                 .set_with_ttl::<V>(key, &block_number, self.cache_config.dynamic_ttl)
                 .await?;
             Ok(block_number)
@@ -148,24 +162,38 @@ impl State {
     pub async fn get_validity_proof_block_number(&self) -> anyhow::Result<u32> {
         type V = u32;
         let key = "validity_proof_block_number";
-        if let Some(block_number) = self.cache.get::<V>(key).await? {
+        if let Some(block_number) = self.dynamic_cache.get::<V>(key).await? {
             Ok(block_number)
         } else {
             let block_number = self
                 .validity_prover
                 .get_latest_validity_proof_block_number()
                 .await?;
-            self.cache
+            self.dynamic_cache
                 .set_with_ttl::<V>(key, &block_number, self.cache_config.dynamic_ttl)
                 .await?;
             Ok(block_number)
         }
     }
 
+    pub async fn get_sync_status(&self) -> anyhow::Result<SyncStatus> {
+        type V = SyncStatus;
+        let key = "sync_status";
+        if let Some(sync_status) = self.dynamic_cache.get::<V>(key).await? {
+            Ok(sync_status)
+        } else {
+            let sync_status = self.validity_prover.get_sync_status().await?;
+            self.dynamic_cache
+                .set_with_ttl::<V>(key, &sync_status, self.cache_config.dynamic_ttl)
+                .await?;
+            Ok(sync_status)
+        }
+    }
+
     pub async fn get_next_deposit_index(&self) -> anyhow::Result<u32> {
         type V = u32;
         let key = "next_deposit_index";
-        if let Some(deposit_index) = self.cache.get::<V>(key).await? {
+        if let Some(deposit_index) = self.dynamic_cache.get::<V>(key).await? {
             Ok(deposit_index)
         } else {
             let deposit_index = self
@@ -173,7 +201,7 @@ impl State {
                 .observer_api
                 .get_next_deposit_index()
                 .await?;
-            self.cache
+            self.dynamic_cache
                 .set_with_ttl::<V>(key, &deposit_index, self.cache_config.dynamic_ttl)
                 .await?;
             Ok(deposit_index)
@@ -183,7 +211,7 @@ impl State {
     pub async fn get_last_deposit_id(&self) -> anyhow::Result<u64> {
         type V = u64;
         let key = "last_deposit_id";
-        if let Some(deposit_id) = self.cache.get::<V>(key).await? {
+        if let Some(deposit_id) = self.dynamic_cache.get::<V>(key).await? {
             Ok(deposit_id)
         } else {
             let deposit_id = self
@@ -191,7 +219,7 @@ impl State {
                 .observer_api
                 .get_local_last_deposit_id()
                 .await?;
-            self.cache
+            self.dynamic_cache
                 .set_with_ttl::<V>(key, &deposit_id, self.cache_config.dynamic_ttl)
                 .await?;
             Ok(deposit_id)
@@ -201,7 +229,7 @@ impl State {
     pub async fn get_latest_included_deposit_index(&self) -> anyhow::Result<Option<u32>> {
         type V = Option<u32>;
         let key = "latest_included_deposit_index";
-        if let Some(deposit_index) = self.cache.get::<V>(key).await? {
+        if let Some(deposit_index) = self.dynamic_cache.get::<V>(key).await? {
             Ok(deposit_index)
         } else {
             let deposit_index = self
@@ -209,7 +237,7 @@ impl State {
                 .observer_api
                 .get_latest_included_deposit_index()
                 .await?;
-            self.cache
+            self.dynamic_cache
                 .set_with_ttl::<V>(key, &deposit_index, self.cache_config.dynamic_ttl)
                 .await?;
             Ok(deposit_index)
@@ -318,7 +346,7 @@ impl State {
     ) -> anyhow::Result<GetDepositInfoResponse> {
         type V = Option<DepositInfo>;
         let key = format!("get_deposit_info:{}", serde_qs::to_string(&request)?);
-        if let Some(deposit_info) = self.cache.get::<V>(&key).await? {
+        if let Some(deposit_info) = self.dynamic_cache.get::<V>(&key).await? {
             Ok(GetDepositInfoResponse { deposit_info })
         } else {
             let deposit_info = self
@@ -327,7 +355,7 @@ impl State {
                 .get_deposit_info(request.pubkey_salt_hash)
                 .await?;
             // the result is mutable
-            self.cache
+            self.dynamic_cache
                 .set_with_ttl::<V>(&key, &deposit_info, self.cache_config.dynamic_ttl)
                 .await?;
             Ok(GetDepositInfoResponse { deposit_info })
@@ -353,6 +381,31 @@ impl State {
         Ok(GetDepositInfoBatchResponse { deposit_info })
     }
 
+    pub async fn get_deposit_bloom_filter(
+        &self,
+        request: GetDepositBloomFilterQuery,
+    ) -> anyhow::Result<GetDepositBloomFilterResponse> {
+        type V = BloomFilter;
+        let key = format!(
+            "get_deposit_bloom_filter:{}",
+            serde_qs::to_string(&request)?
+        );
+        if let Some(bloom_filter) = self.dynamic_cache.get::<V>(&key).await? {
+            Ok(GetDepositBloomFilterResponse { bloom_filter })
+        } else {
+            let bloom_filter = self
+                .validity_prover
+                .observer_api
+                .get_deposit_bloom_filter(request.from_timestamp)
+                .await?;
+            // the result is mutable because new deposits are observed
+            self.dynamic_cache
+                .set_with_ttl::<V>(&key, &bloom_filter, self.cache_config.dynamic_ttl)
+                .await?;
+            Ok(GetDepositBloomFilterResponse { bloom_filter })
+        }
+    }
+
     pub async fn get_block_number_by_tx_tree_root(
         &self,
         request: GetBlockNumberByTxTreeRootQuery,
@@ -362,7 +415,7 @@ impl State {
             "get_block_number_by_tx_tree_root:{}",
             serde_qs::to_string(&request)?
         );
-        if let Some(block_number) = self.cache.get::<V>(&key).await? {
+        if let Some(block_number) = self.dynamic_cache.get::<V>(&key).await? {
             Ok(GetBlockNumberByTxTreeRootResponse { block_number })
         } else {
             let block_number = self
@@ -370,7 +423,7 @@ impl State {
                 .get_block_number_by_tx_tree_root(request.tx_tree_root)
                 .await?;
             // the result is mutable
-            self.cache
+            self.dynamic_cache
                 .set_with_ttl::<V>(&key, &block_number, self.cache_config.dynamic_ttl)
                 .await?;
             Ok(GetBlockNumberByTxTreeRootResponse { block_number })
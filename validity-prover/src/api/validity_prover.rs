@@ -11,10 +11,11 @@ use intmax2_interfaces::api::validity_prover::{
         GetAccountInfoResponse, GetBlockMerkleProofQuery, GetBlockMerkleProofResponse,
         GetBlockNumberByTxTreeRootBatchRequest, GetBlockNumberByTxTreeRootBatchResponse,
         GetBlockNumberByTxTreeRootQuery, GetBlockNumberByTxTreeRootResponse,
-        GetBlockNumberResponse, GetDepositInfoBatchRequest, GetDepositInfoBatchResponse,
-        GetDepositInfoQuery, GetDepositInfoResponse, GetDepositMerkleProofQuery,
-        GetDepositMerkleProofResponse, GetLastDepositIdResponse,
-        GetLatestIncludedDepositIndexResponse, GetNextDepositIndexResponse, GetUpdateWitnessQuery,
+        GetBlockNumberResponse, GetDepositBloomFilterQuery, GetDepositBloomFilterResponse,
+        GetDepositInfoBatchRequest, GetDepositInfoBatchResponse, GetDepositInfoQuery,
+        GetDepositInfoResponse, GetDepositMerkleProofQuery, GetDepositMerkleProofResponse,
+        GetLastDepositIdResponse, GetLatestIncludedDepositIndexResponse,
+        GetNextDepositIndexResponse, GetSyncStatusResponse, GetUpdateWitnessQuery,
         GetUpdateWitnessResponse, GetValidityProofQuery, GetValidityProofResponse,
         GetValidityWitnessQuery, GetValidityWitnessResponse,
     },
@@ -42,6 +43,15 @@ pub async fn get_validity_proof_block_number(
     Ok(Json(GetBlockNumberResponse { block_number }))
 }
 
+#[get("/sync-status")]
+pub async fn get_sync_status(state: Data<State>) -> Result<Json<GetSyncStatusResponse>, Error> {
+    let sync_status = state
+        .get_sync_status()
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(Json(GetSyncStatusResponse { sync_status }))
+}
+
 #[get("/next-deposit-index")]
 pub async fn get_next_deposit_index(
     state: Data<State>,
@@ -187,6 +197,19 @@ pub async fn get_deposit_info_batch(
     Ok(Json(response))
 }
 
+#[get("/get-deposit-bloom-filter")]
+pub async fn get_deposit_bloom_filter(
+    state: Data<State>,
+    query: QsQuery<GetDepositBloomFilterQuery>,
+) -> Result<Json<GetDepositBloomFilterResponse>, Error> {
+    let query = query.into_inner();
+    let response = state
+        .get_deposit_bloom_filter(query)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(Json(response))
+}
+
 #[get("/get-block-number-by-tx-tree-root")]
 pub async fn get_block_number_by_tx_tree_root(
     state: Data<State>,
@@ -246,6 +269,7 @@ pub fn validity_prover_scope() -> actix_web::Scope {
     actix_web::web::scope("/validity-prover")
         .service(get_block_number)
         .service(get_validity_proof_block_number)
+        .service(get_sync_status)
         .service(get_next_deposit_index)
         .service(get_last_deposit_id)
         .service(get_latest_included_deposit_index)
@@ -257,6 +281,7 @@ pub fn validity_prover_scope() -> actix_web::Scope {
         .service(get_validity_pis)
         .service(get_deposit_info)
         .service(get_deposit_info_batch)
+        .service(get_deposit_bloom_filter)
         .service(get_block_number_by_tx_tree_root)
         .service(get_block_number_by_tx_tree_root_batch)
         .service(get_block_merkle_proof)
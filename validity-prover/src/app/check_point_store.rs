@@ -1,5 +1,6 @@
 use std::fmt;
 
+use intmax2_client_sdk::external_api::utils::retry::with_retry;
 use server_common::db::DbPool;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -77,4 +78,38 @@ impl CheckPointStore {
         .await?;
         Ok(())
     }
+
+    /// Persist a checkpoint written by the leader of `leader_epoch`, retrying on errors.
+    ///
+    /// The write is skipped if the checkpoint was last written by a leader of a later epoch, so
+    /// a stale leader that overlaps with the new one during a network partition cannot move the
+    /// checkpoint. Writing the same checkpoint twice has no further effect, which makes retries
+    /// safe. Returns false if the write was skipped.
+    pub async fn set_check_point_as_leader(
+        &self,
+        event_type: EventType,
+        eth_block_number: u64,
+        leader_epoch: u64,
+    ) -> Result<bool, sqlx::Error> {
+        let result = with_retry(|| async {
+            sqlx::query(
+                r#"
+                INSERT INTO event_sync_eth_block (event_type, eth_block_number, leader_epoch)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (event_type)
+                DO UPDATE SET
+                    eth_block_number = EXCLUDED.eth_block_number,
+                    leader_epoch = EXCLUDED.leader_epoch
+                WHERE event_sync_eth_block.leader_epoch <= EXCLUDED.leader_epoch
+                "#,
+            )
+            .bind(event_type.to_string())
+            .bind(eth_block_number as i64)
+            .bind(leader_epoch as i64)
+            .execute(&self.pool)
+            .await
+        })
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
 }
@@ -58,6 +58,12 @@ pub enum ObserverError {
 
     #[error("Block number mismatch: {0} != {1}")]
     BlockNumberMismatch(u32, u32),
+
+    #[error("Reorg deeper than {max_reorg_depth} blocks detected at block number {block_number}")]
+    ReorgTooDeep {
+        block_number: u32,
+        max_reorg_depth: u32,
+    },
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -151,4 +157,13 @@ pub enum ValidityProverError {
 
     #[error("Input error {0}")]
     InputError(String),
+
+    #[error("Config error {0}")]
+    ConfigError(String),
+
+    #[error("Reorg deeper than {max_reorg_depth} blocks detected at block number {block_number}")]
+    ReorgTooDeep {
+        block_number: u32,
+        max_reorg_depth: u32,
+    },
 }
@@ -1,5 +1,11 @@
 use redis::{Client, Script};
-use std::{sync::Arc, time::Duration};
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 use uuid::Uuid;
 
 use super::error::LeaderError;
@@ -9,7 +15,11 @@ pub struct LeaderElection {
     client: Client,
     node_id: String,
     lock_key: String,
+    // incremented on every new leadership term, used to fence the writes of stale leaders
+    epoch_key: String,
     lock_ttl: Duration,
+    // epoch of the last term this node held, 0 if it never was the leader
+    epoch: Arc<AtomicU64>,
 }
 
 impl LeaderElection {
@@ -21,37 +31,70 @@ impl LeaderElection {
         let client = Client::open(redis_url)?;
         let node_id = Uuid::new_v4().to_string();
         tracing::info!("Node ID = {node_id}");
+        let lock_key = lock_key.into();
         Ok(Self {
             client,
             node_id,
-            lock_key: lock_key.into(),
+            epoch_key: format!("{lock_key}:epoch"),
+            lock_key,
             lock_ttl,
+            epoch: Arc::new(AtomicU64::new(0)),
         })
     }
 
+    /// Epoch of the last leadership term of this node, or None if it never was the leader.
+    ///
+    /// Epochs strictly increase across terms, so a node that lost the lock during a network
+    /// partition holds a lower epoch than the new leader. Persisted state written with the
+    /// epoch can reject the writes of the stale leader, see
+    /// `CheckPointStore::set_check_point_as_leader`.
+    pub fn epoch(&self) -> Option<u64> {
+        match self.epoch.load(Ordering::SeqCst) {
+            0 => None,
+            epoch => Some(epoch),
+        }
+    }
+
     async fn try_acquire_leadership(&self) -> Result<bool, LeaderError> {
         static ACQUIRE_OR_REFRESH: &str = r#"
             local val = redis.call('GET', KEYS[1])
             if not val then
-                -- no lock yet → create it
-                return redis.call('SET', KEYS[1], ARGV[1], 'PX', ARGV[2], 'NX') and 1 or 0
+                -- no lock yet → create it and start a new term
+                if redis.call('SET', KEYS[1], ARGV[1], 'PX', ARGV[2], 'NX') then
+                    return redis.call('INCR', KEYS[2])
+                end
+                return 0
             elseif val == ARGV[1] then
                 -- already my lock → just refresh TTL
-                return redis.call('PEXPIRE', KEYS[1], ARGV[2])
+                redis.call('PEXPIRE', KEYS[1], ARGV[2])
+                local epoch = redis.call('GET', KEYS[2])
+                if not epoch then
+                    return redis.call('INCR', KEYS[2])
+                end
+                return tonumber(epoch)
             else
                 -- someone else's lock
                 return 0
             end
         "#;
         let mut conn = self.client.get_multiplexed_async_connection().await?;
-        // 1 = success, 0 = someone else is leader
-        let ok: i32 = Script::new(ACQUIRE_OR_REFRESH)
+        // epoch of the term = success, 0 = someone else is leader
+        let epoch: u64 = Script::new(ACQUIRE_OR_REFRESH)
             .key(&self.lock_key)
+            .key(&self.epoch_key)
             .arg(&self.node_id)
             .arg(self.lock_ttl.as_millis() as usize)
             .invoke_async(&mut conn)
             .await?;
-        Ok(ok == 1)
+        if epoch == 0 {
+            return Ok(false);
+        }
+        let prev_epoch = self.epoch.swap(epoch, Ordering::SeqCst);
+        if prev_epoch != epoch {
+            // the persisted checkpoints are reloaded by the jobs before each step
+            tracing::info!("acquired leadership, epoch = {epoch}");
+        }
+        Ok(true)
     }
 
     #[tracing::instrument(skip(self))]
@@ -2,7 +2,9 @@ use intmax2_client_sdk::external_api::contract::{
     liquidity_contract::{Deposited, LiquidityContract},
     rollup_contract::{DepositLeafInserted, FullBlockWithMeta, RollupContract},
 };
-use intmax2_interfaces::api::validity_prover::interface::DepositInfo;
+use intmax2_interfaces::{
+    api::validity_prover::interface::DepositInfo, utils::bloom_filter::BloomFilter,
+};
 use intmax2_zkp::{
     common::witness::full_block::FullBlock,
     ethereum_types::{bytes32::Bytes32, u256::U256, u32limb_trait::U32LimbTrait},
@@ -15,6 +17,9 @@ use crate::EnvVar;
 
 use super::{check_point_store::EventType, error::ObserverError};
 
+/// False positive rate of the deposit bloom filter served to clients.
+const DEPOSIT_BLOOM_FILTER_FP_RATE: f64 = 0.01;
+
 #[derive(Clone)]
 pub struct ObserverApi {
     pub(crate) rollup_contract: RollupContract,
@@ -65,6 +70,68 @@ impl ObserverApi {
         Ok(last_block_number.unwrap_or(0))
     }
 
+    /// Find the latest observed block whose hash still matches the rollup contract.
+    /// Returns `None` if the last observed block is still canonical, and an error if the
+    /// divergence goes back more than `max_reorg_depth` blocks.
+    #[instrument(skip(self))]
+    pub async fn find_reorg_ancestor(
+        &self,
+        max_reorg_depth: u32,
+    ) -> Result<Option<u32>, ObserverError> {
+        let last_block_number = self.get_local_last_block_number().await?;
+        let mut block_number = last_block_number;
+        while block_number > 0 {
+            let full_block_with_meta = self
+                .get_full_block_with_meta(block_number)
+                .await?
+                .ok_or(ObserverError::BlockNotFound(block_number))?;
+            let onchain_block_hash = self.rollup_contract.get_block_hash(block_number).await?;
+            if full_block_with_meta.full_block.block.hash() == onchain_block_hash {
+                break;
+            }
+            if last_block_number - block_number >= max_reorg_depth {
+                return Err(ObserverError::ReorgTooDeep {
+                    block_number: last_block_number,
+                    max_reorg_depth,
+                });
+            }
+            block_number -= 1;
+        }
+        if block_number == last_block_number {
+            Ok(None)
+        } else {
+            Ok(Some(block_number))
+        }
+    }
+
+    /// Delete the observed blocks after `block_number` and the deposit leaf events emitted after
+    /// it on L2. Returns the eth block number of `block_number`.
+    #[instrument(skip(self))]
+    pub async fn rollback_blocks(&self, block_number: u32) -> Result<u64, ObserverError> {
+        let eth_block_number = sqlx::query_scalar!(
+            "SELECT eth_block_number FROM full_blocks WHERE block_number = $1",
+            block_number as i32
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(ObserverError::BlockNotFound(block_number))?;
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!(
+            "DELETE FROM full_blocks WHERE block_number > $1",
+            block_number as i32
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query!(
+            "DELETE FROM deposit_leaf_events WHERE eth_block_number > $1",
+            eth_block_number
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(eth_block_number as u64)
+    }
+
     #[instrument(skip(self))]
     pub async fn get_local_next_event_id(
         &self,
@@ -260,6 +327,25 @@ impl ObserverApi {
         }
     }
 
+    /// Build a bloom filter of the pubkey salt hashes of the deposits made at or after
+    /// `from_timestamp`.
+    pub async fn get_deposit_bloom_filter(
+        &self,
+        from_timestamp: u64,
+    ) -> Result<BloomFilter, ObserverError> {
+        let records = sqlx::query!(
+            "SELECT pubkey_salt_hash FROM deposited_events WHERE deposited_at >= $1",
+            from_timestamp as i64
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let mut bloom_filter = BloomFilter::new(records.len(), DEPOSIT_BLOOM_FILTER_FP_RATE);
+        for r in records {
+            bloom_filter.insert(&Bytes32::from_hex(&r.pubkey_salt_hash)?);
+        }
+        Ok(bloom_filter)
+    }
+
     pub async fn get_deposited_event(
         &self,
         pubkey_salt_hash: Bytes32,
@@ -386,3 +472,134 @@ impl ObserverApi {
         }
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use alloy::{
+        providers::{mock::Asserter, ProviderBuilder},
+        sol_types::SolCall as _,
+    };
+    use intmax2_client_sdk::external_api::contract::{
+        convert::convert_bytes32_to_b256,
+        liquidity_contract::LiquidityContract,
+        rollup_contract::{Rollup, RollupContract},
+    };
+    use intmax2_zkp::common::witness::full_block::FullBlock;
+    use server_common::db::DbPool;
+    use sqlx::PgPool;
+
+    use super::ObserverApi;
+    use crate::app::{error::ObserverError, observer_common::initialize_observer_db};
+
+    /// Observer API whose rollup contract answers the block hash queries pushed to `asserter`.
+    pub(crate) fn mock_observer_api(pool: PgPool, asserter: Asserter) -> ObserverApi {
+        let provider = ProviderBuilder::default()
+            .with_gas_estimation()
+            .with_simple_nonce_management()
+            .fetch_chain_id()
+            .connect_mocked_client(asserter);
+        ObserverApi {
+            rollup_contract: RollupContract::new(provider.clone(), Default::default()),
+            liquidity_contract: LiquidityContract::new(provider, Default::default()),
+            pool: DbPool::new(pool),
+        }
+    }
+
+    /// Block `block_number` of the chain `fork`. Blocks of different forks have different hashes.
+    pub(crate) fn fork_block(block_number: u32, fork: u64) -> FullBlock {
+        let mut full_block = FullBlock::genesis();
+        full_block.block.block_number = block_number;
+        full_block.block.timestamp = fork;
+        full_block
+    }
+
+    /// Answer the next block hash query with the hash of `full_block`.
+    pub(crate) fn push_block_hash(asserter: &Asserter, full_block: &FullBlock) {
+        asserter.push_success(&Rollup::getBlockHashCall::abi_encode_returns(
+            &convert_bytes32_to_b256(full_block.block.hash()),
+        ));
+    }
+
+    /// Observe blocks 1 to `last_block_number` of `fork` after the genesis block. Block `n` is
+    /// posted at eth block `10 * n`, together with the deposit leaf of index `n - 1`.
+    pub(crate) async fn observe_blocks(pool: &PgPool, last_block_number: u32, fork: u64) {
+        initialize_observer_db(DbPool::new(pool.clone()))
+            .await
+            .unwrap();
+        for block_number in 1..=last_block_number {
+            let eth_block_number = 10 * block_number as i64;
+            sqlx::query(
+                "INSERT INTO full_blocks (block_number, eth_block_number, eth_tx_index, full_block)
+                 VALUES ($1, $2, 0, $3)",
+            )
+            .bind(block_number as i32)
+            .bind(eth_block_number)
+            .bind(bincode::serialize(&fork_block(block_number, fork)).unwrap())
+            .execute(pool)
+            .await
+            .unwrap();
+            sqlx::query(
+                "INSERT INTO deposit_leaf_events (deposit_index, deposit_hash, eth_block_number, eth_tx_index)
+                 VALUES ($1, $2, $3, 0)",
+            )
+            .bind(block_number as i32 - 1)
+            .bind(vec![0u8; 32])
+            .bind(eth_block_number)
+            .execute(pool)
+            .await
+            .unwrap();
+        }
+    }
+
+    #[sqlx::test]
+    async fn test_rollback_blocks(pool: PgPool) {
+        observe_blocks(&pool, 4, 0).await;
+        let observer_api = mock_observer_api(pool, Asserter::new());
+
+        let eth_block_number = observer_api.rollback_blocks(2).await.unwrap();
+        assert_eq!(eth_block_number, 20);
+        assert_eq!(observer_api.get_local_last_block_number().await.unwrap(), 2);
+        // deposit leaves emitted up to the eth block of the kept block remain
+        assert_eq!(
+            observer_api.get_local_last_deposit_index().await.unwrap(),
+            Some(1)
+        );
+        assert!(observer_api
+            .get_full_block_with_meta(3)
+            .await
+            .unwrap()
+            .is_none());
+
+        assert!(matches!(
+            observer_api.rollback_blocks(3).await,
+            Err(ObserverError::BlockNotFound(3))
+        ));
+    }
+
+    #[sqlx::test]
+    async fn test_find_reorg_ancestor(pool: PgPool) {
+        observe_blocks(&pool, 4, 0).await;
+        let asserter = Asserter::new();
+        let observer_api = mock_observer_api(pool, asserter.clone());
+
+        // the last observed block is canonical
+        push_block_hash(&asserter, &fork_block(4, 0));
+        assert_eq!(observer_api.find_reorg_ancestor(64).await.unwrap(), None);
+
+        // blocks 3 and 4 were replaced on chain
+        push_block_hash(&asserter, &fork_block(4, 1));
+        push_block_hash(&asserter, &fork_block(3, 1));
+        push_block_hash(&asserter, &fork_block(2, 0));
+        assert_eq!(observer_api.find_reorg_ancestor(64).await.unwrap(), Some(2));
+
+        push_block_hash(&asserter, &fork_block(4, 1));
+        push_block_hash(&asserter, &fork_block(3, 1));
+        assert!(matches!(
+            observer_api.find_reorg_ancestor(1).await,
+            Err(ObserverError::ReorgTooDeep {
+                block_number: 4,
+                max_reorg_depth: 1
+            })
+        ));
+    }
+}
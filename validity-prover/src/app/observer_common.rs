@@ -1,18 +1,58 @@
-use std::sync::Arc;
+use std::{future::Future, sync::Arc};
 
+use futures::{StreamExt as _, TryStreamExt as _};
 use intmax2_client_sdk::external_api::contract::rollup_contract::FullBlockWithMeta;
 use intmax2_zkp::common::witness::full_block::FullBlock;
+use serde::Deserialize;
 use server_common::db::DbPool;
 use tracing::{error, info, instrument, warn};
 
 use crate::EnvVar;
 
 use super::{
-    check_point_store::EventType,
+    check_point_store::{CheckPointStore, EventType},
     error::{ObserverError, ObserverSyncError},
+    observer_api::ObserverApi,
     rate_manager::RateManager,
 };
 
+pub const DEFAULT_MAX_REORG_DEPTH: u32 = 64;
+pub const DEFAULT_OBSERVER_QUERY_CONCURRENCY: usize = 1;
+/// Maximum seconds between two restarts of a sync events job that keeps failing
+pub const MAX_OBSERVER_RESTART_BACKOFF: u64 = 600;
+
+/// What an observer does once a sync events job failed `observer_error_threshold` times within
+/// the rate manager window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ObserverOnError {
+    /// Halt the observer and report it in the health check and status endpoints, then switch to
+    /// the secondary observer if there is one
+    #[default]
+    Halt,
+    /// Keep restarting the job, doubling the delay on each failure beyond the threshold
+    Continue,
+}
+
+/// Seconds to wait before restarting a sync events job that failed `fail_count` times.
+pub fn observer_restart_delay(
+    restart_interval: u64,
+    fail_count: usize,
+    error_threshold: usize,
+    on_error: ObserverOnError,
+) -> u64 {
+    match on_error {
+        ObserverOnError::Continue if fail_count >= error_threshold => {
+            let doublings = (fail_count - error_threshold + 1).min(16) as u32;
+            restart_interval
+                .max(1)
+                .saturating_mul(1 << doublings)
+                .min(MAX_OBSERVER_RESTART_BACKOFF.max(restart_interval))
+        }
+        _ => restart_interval,
+    }
+}
+
 pub fn sync_event_key(event_type: EventType) -> String {
     format!("sync_events_{event_type}")
 }
@@ -25,9 +65,12 @@ pub fn sync_event_fail_key(event_type: EventType) -> String {
 pub struct ObserverConfig {
     pub observer_event_block_interval: u64,
     pub observer_max_query_times: usize,
+    pub observer_query_concurrency: usize,
     pub observer_sync_interval: u64,
     pub observer_restart_interval: u64,
     pub observer_error_threshold: u64,
+    pub observer_on_error: ObserverOnError,
+    pub max_reorg_depth: u32,
 
     pub rollup_contract_deployed_block_number: u64,
     pub liquidity_contract_deployed_block_number: u64,
@@ -38,15 +81,68 @@ impl ObserverConfig {
         Self {
             observer_event_block_interval: env.observer_event_block_interval,
             observer_max_query_times: env.observer_max_query_times,
+            observer_query_concurrency: env
+                .observer_query_concurrency
+                .unwrap_or(DEFAULT_OBSERVER_QUERY_CONCURRENCY)
+                .max(1),
             observer_sync_interval: env.observer_sync_interval,
             observer_restart_interval: env.observer_restart_interval,
             observer_error_threshold: env.observer_error_threshold,
+            observer_on_error: env.observer_on_error.unwrap_or_default(),
+            max_reorg_depth: env
+                .observer_max_reorg_depth
+                .unwrap_or(DEFAULT_MAX_REORG_DEPTH),
             rollup_contract_deployed_block_number: env.rollup_contract_deployed_block_number,
             liquidity_contract_deployed_block_number: env.liquidity_contract_deployed_block_number,
         }
     }
 }
 
+/// Split `[from_eth_block, to_eth_block]` into consecutive ranges of at most `interval` blocks.
+pub fn split_block_range(from_eth_block: u64, to_eth_block: u64, interval: u64) -> Vec<(u64, u64)> {
+    let interval = interval.max(1);
+    let mut ranges = Vec::new();
+    let mut from = from_eth_block;
+    while from <= to_eth_block {
+        let to = to_eth_block.min(from.saturating_add(interval - 1));
+        ranges.push((from, to));
+        if to == u64::MAX {
+            break;
+        }
+        from = to + 1;
+    }
+    ranges
+}
+
+/// Query events in `[from_eth_block, to_eth_block]` split into ranges of `interval` blocks, with
+/// up to `concurrency` range queries in flight.
+///
+/// The merged events are sorted by `sort_key`, so the result does not depend on the order in
+/// which the range queries complete.
+pub async fn query_ranges_concurrently<T, K, E, F, Fut>(
+    from_eth_block: u64,
+    to_eth_block: u64,
+    interval: u64,
+    concurrency: usize,
+    query: F,
+    sort_key: impl Fn(&T) -> K,
+) -> Result<Vec<T>, E>
+where
+    K: Ord,
+    F: Fn(u64, u64) -> Fut,
+    Fut: Future<Output = Result<Vec<T>, E>>,
+{
+    let pages: Vec<Vec<T>> =
+        futures::stream::iter(split_block_range(from_eth_block, to_eth_block, interval))
+            .map(|(from, to)| query(from, to))
+            .buffered(concurrency.max(1))
+            .try_collect()
+            .await?;
+    let mut events = pages.into_iter().flatten().collect::<Vec<_>>();
+    events.sort_by_key(sort_key);
+    Ok(events)
+}
+
 #[async_trait::async_trait(?Send)]
 pub trait SyncEvent {
     fn name(&self) -> String;
@@ -86,6 +182,50 @@ pub async fn initialize_observer_db(pool: DbPool) -> Result<(), ObserverError> {
     Ok(())
 }
 
+/// Roll back the observed L2 state if the last observed block is no longer canonical.
+/// If the reorg is deeper than the configured max depth, the observer is stopped because the
+/// validity proofs can no longer be trusted.
+#[instrument(skip(observer, observer_api))]
+pub async fn rollback_on_reorg<O: SyncEvent>(
+    observer: &O,
+    observer_api: &ObserverApi,
+) -> Result<(), ObserverError> {
+    let config = observer.config();
+    let ancestor_block_number = match observer_api
+        .find_reorg_ancestor(config.max_reorg_depth)
+        .await
+    {
+        Ok(Some(block_number)) => block_number,
+        Ok(None) => return Ok(()),
+        Err(e @ ObserverError::ReorgTooDeep { .. }) => {
+            error!("Halting {}: {}", observer.name(), e);
+            observer
+                .rate_manager()
+                .halt(&observer.name(), &e.to_string())
+                .await?;
+            return Err(e);
+        }
+        Err(e) => return Err(e),
+    };
+    let eth_block_number = observer_api.rollback_blocks(ancestor_block_number).await?;
+    // genesis block has no eth block number
+    let eth_block_number = if ancestor_block_number == 0 {
+        config.rollup_contract_deployed_block_number
+    } else {
+        eth_block_number
+    };
+    let check_point_store = CheckPointStore::new(observer_api.pool.clone());
+    for event_type in [EventType::BlockPosted, EventType::DepositLeafInserted] {
+        check_point_store
+            .set_check_point(event_type, eth_block_number)
+            .await?;
+    }
+    warn!(
+        "Reorg detected. Rolled back observed blocks to block number {ancestor_block_number}, eth block number {eth_block_number}"
+    );
+    Ok(())
+}
+
 #[instrument(skip(observer))]
 async fn sync_events_inner_loop<O: SyncEvent>(
     observer: Arc<O>,
@@ -119,23 +259,26 @@ async fn sync_events_job<O: SyncEvent + 'static>(
 ) -> Result<(), ObserverSyncError> {
     let observer_restart_interval = observer.config().observer_sync_interval;
     let observer_error_threshold = observer.config().observer_error_threshold as usize;
+    let observer_on_error = observer.config().observer_on_error;
     let observer_clone = observer.clone();
+    let mut last_error = None;
     // auto restart loop
     loop {
         let fail_count = observer
             .rate_manager()
             .count(&sync_event_fail_key(event_type))
             .await?;
-        if fail_count >= observer_error_threshold {
+        if fail_count >= observer_error_threshold && observer_on_error == ObserverOnError::Halt {
             // stop the job
+            let reason = format!(
+                "sync events job for {event_type} failed {fail_count} times within the rate manager window, last error: {}",
+                last_error.as_deref().unwrap_or("unknown")
+            );
             observer
                 .rate_manager()
-                .set_stop_flag(&observer.name(), true)
+                .halt(&observer.name(), &reason)
                 .await?;
-            warn!(
-                "Stopping sync events job for {} because error limit reached",
-                event_type
-            );
+            warn!("Halting {}: {}", observer.name(), reason);
             return Ok(());
         }
 
@@ -150,9 +293,11 @@ async fn sync_events_job<O: SyncEvent + 'static>(
             }
             Ok(Err(e)) => {
                 error!("Sync events {} job error: {}", event_type, e);
+                last_error = Some(e.to_string());
             }
             Err(e) => {
                 error!("Sync events {} job panic: {}", event_type, e);
+                last_error = Some(format!("panic: {e}"));
             }
         }
         observer
@@ -161,7 +306,23 @@ async fn sync_events_job<O: SyncEvent + 'static>(
             .await?;
 
         // wait for a while before restarting
-        tokio::time::sleep(tokio::time::Duration::from_secs(observer_restart_interval)).await;
+        let fail_count = observer
+            .rate_manager()
+            .count(&sync_event_fail_key(event_type))
+            .await?;
+        let delay = observer_restart_delay(
+            observer_restart_interval,
+            fail_count,
+            observer_error_threshold,
+            observer_on_error,
+        );
+        if delay > observer_restart_interval {
+            warn!(
+                "Sync events {} job failed {} times, backing off for {} seconds",
+                event_type, fail_count, delay
+            );
+        }
+        tokio::time::sleep(tokio::time::Duration::from_secs(delay)).await;
         log::info!("Restarting sync events job for {event_type}");
     }
     Ok(())
@@ -248,16 +409,202 @@ pub async fn run_and_switch_observers<P: SyncEvent + 'static, S: SyncEvent + 'st
         ))
         .await;
 
-        warn!("Clearing rate manager");
-        primary_observer.rate_manager().reset().await?;
-
         if let Some(secondary_observer) = secondary_observer {
+            warn!("Clearing rate manager");
+            primary_observer.rate_manager().reset().await?;
             warn!("Switching to secondary observer");
             start_observer_jobs(secondary_observer).await?;
         } else {
+            // the halt is kept so that the health check and status report the stopped observer
             warn!("No secondary observer to switch to");
         }
         error!("Observer job finished, exiting");
         Ok::<(), ObserverSyncError>(())
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use alloy::providers::mock::Asserter;
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::app::observer_api::tests::{
+        fork_block, mock_observer_api, observe_blocks, push_block_hash,
+    };
+
+    const DEPLOYED_BLOCK_NUMBER: u64 = 5;
+
+    struct TestObserver {
+        config: ObserverConfig,
+        rate_manager: RateManager,
+    }
+
+    impl TestObserver {
+        fn new(max_reorg_depth: u32) -> Self {
+            Self {
+                config: ObserverConfig {
+                    observer_event_block_interval: 1000,
+                    observer_max_query_times: 1,
+                    observer_query_concurrency: DEFAULT_OBSERVER_QUERY_CONCURRENCY,
+                    observer_sync_interval: 1,
+                    observer_restart_interval: 1,
+                    observer_error_threshold: 1,
+                    observer_on_error: ObserverOnError::Halt,
+                    max_reorg_depth,
+                    rollup_contract_deployed_block_number: DEPLOYED_BLOCK_NUMBER,
+                    liquidity_contract_deployed_block_number: DEPLOYED_BLOCK_NUMBER,
+                },
+                rate_manager: RateManager::new(Duration::from_secs(60), Duration::from_secs(5)),
+            }
+        }
+    }
+
+    #[async_trait::async_trait(?Send)]
+    impl SyncEvent for TestObserver {
+        fn name(&self) -> String {
+            "test_observer".to_string()
+        }
+
+        fn config(&self) -> ObserverConfig {
+            self.config.clone()
+        }
+
+        fn rate_manager(&self) -> &RateManager {
+            &self.rate_manager
+        }
+
+        async fn sync_events(&self, _event_type: EventType) -> Result<(), ObserverError> {
+            Ok(())
+        }
+    }
+
+    async fn check_points(observer_api: &ObserverApi) -> [Option<u64>; 2] {
+        let check_point_store = CheckPointStore::new(observer_api.pool.clone());
+        [
+            check_point_store
+                .get_check_point(EventType::BlockPosted)
+                .await
+                .unwrap(),
+            check_point_store
+                .get_check_point(EventType::DepositLeafInserted)
+                .await
+                .unwrap(),
+        ]
+    }
+
+    #[sqlx::test]
+    async fn test_rollback_on_reorg_without_reorg(pool: PgPool) {
+        observe_blocks(&pool, 3, 0).await;
+        let asserter = Asserter::new();
+        let observer_api = mock_observer_api(pool, asserter.clone());
+        let observer = TestObserver::new(DEFAULT_MAX_REORG_DEPTH);
+
+        push_block_hash(&asserter, &fork_block(3, 0));
+        rollback_on_reorg(&observer, &observer_api).await.unwrap();
+        assert_eq!(observer_api.get_local_last_block_number().await.unwrap(), 3);
+        assert_eq!(check_points(&observer_api).await, [None, None]);
+    }
+
+    #[sqlx::test]
+    async fn test_rollback_on_reorg(pool: PgPool) {
+        observe_blocks(&pool, 4, 0).await;
+        let asserter = Asserter::new();
+        let observer_api = mock_observer_api(pool, asserter.clone());
+        let observer = TestObserver::new(DEFAULT_MAX_REORG_DEPTH);
+
+        // blocks 3 and 4 were replaced on chain
+        push_block_hash(&asserter, &fork_block(4, 1));
+        push_block_hash(&asserter, &fork_block(3, 1));
+        push_block_hash(&asserter, &fork_block(2, 0));
+        rollback_on_reorg(&observer, &observer_api).await.unwrap();
+        assert_eq!(observer_api.get_local_last_block_number().await.unwrap(), 2);
+        assert_eq!(
+            observer_api.get_local_last_deposit_index().await.unwrap(),
+            Some(1)
+        );
+        // both block and deposit leaf events are synced again from the eth block of block 2
+        assert_eq!(check_points(&observer_api).await, [Some(20), Some(20)]);
+    }
+
+    #[sqlx::test]
+    async fn test_rollback_on_reorg_to_genesis(pool: PgPool) {
+        observe_blocks(&pool, 2, 0).await;
+        let asserter = Asserter::new();
+        let observer_api = mock_observer_api(pool, asserter.clone());
+        let observer = TestObserver::new(DEFAULT_MAX_REORG_DEPTH);
+
+        push_block_hash(&asserter, &fork_block(2, 1));
+        push_block_hash(&asserter, &fork_block(1, 1));
+        rollback_on_reorg(&observer, &observer_api).await.unwrap();
+        assert_eq!(observer_api.get_local_last_block_number().await.unwrap(), 0);
+        assert_eq!(
+            observer_api.get_local_last_deposit_index().await.unwrap(),
+            None
+        );
+        // the genesis block has no eth block, so events are synced again from the deployment
+        assert_eq!(
+            check_points(&observer_api).await,
+            [Some(DEPLOYED_BLOCK_NUMBER), Some(DEPLOYED_BLOCK_NUMBER)]
+        );
+    }
+
+    #[sqlx::test]
+    async fn test_rollback_on_reorg_too_deep(pool: PgPool) {
+        observe_blocks(&pool, 4, 0).await;
+        let asserter = Asserter::new();
+        let observer_api = mock_observer_api(pool, asserter.clone());
+        let observer = TestObserver::new(1);
+
+        push_block_hash(&asserter, &fork_block(4, 1));
+        push_block_hash(&asserter, &fork_block(3, 1));
+        let result = rollback_on_reorg(&observer, &observer_api).await;
+        assert!(matches!(result, Err(ObserverError::ReorgTooDeep { .. })));
+        // nothing is rolled back and the observer is halted
+        assert_eq!(observer_api.get_local_last_block_number().await.unwrap(), 4);
+        assert_eq!(check_points(&observer_api).await, [None, None]);
+        let halts = observer.rate_manager.get_halts().await.unwrap();
+        assert_eq!(halts.len(), 1);
+        assert_eq!(halts[0].key, observer.name());
+    }
+
+    #[test]
+    fn test_observer_restart_delay() {
+        use ObserverOnError::*;
+        assert_eq!(observer_restart_delay(10, 3, 5, Continue), 10);
+        assert_eq!(observer_restart_delay(10, 5, 5, Continue), 20);
+        assert_eq!(observer_restart_delay(10, 7, 5, Continue), 80);
+        assert_eq!(observer_restart_delay(10, 100, 5, Continue), 600);
+        assert_eq!(observer_restart_delay(10, 100, 5, Halt), 10);
+        // an interval above the cap is never shortened
+        assert_eq!(observer_restart_delay(900, 6, 5, Continue), 900);
+    }
+
+    #[test]
+    fn test_split_block_range() {
+        assert_eq!(split_block_range(1, 10, 4), vec![(1, 4), (5, 8), (9, 10)]);
+        assert_eq!(split_block_range(5, 5, 100), vec![(5, 5)]);
+        assert!(split_block_range(6, 5, 100).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_query_ranges_concurrently_sorts_events() {
+        // Later ranges complete first, events must still come out ordered by block number.
+        let events = query_ranges_concurrently(
+            0,
+            29,
+            10,
+            3,
+            |from, to| async move {
+                tokio::time::sleep(tokio::time::Duration::from_millis(30 - from)).await;
+                Ok::<_, ()>((from..=to).rev().collect::<Vec<_>>())
+            },
+            |block| *block,
+        )
+        .await
+        .unwrap();
+        assert_eq!(events, (0..=29).collect::<Vec<_>>());
+    }
+}
@@ -8,7 +8,7 @@ use server_common::db::{DbPool, DbPoolConfig};
 use tracing::{debug, info, instrument};
 
 use crate::{
-    app::observer_common::{initialize_observer_db, sync_event_key},
+    app::observer_common::{initialize_observer_db, rollback_on_reorg, sync_event_key},
     EnvVar,
 };
 
@@ -264,6 +264,9 @@ impl SyncEvent for TheGraphObserver {
 
     #[instrument(skip(self))]
     async fn sync_events(&self, event_type: EventType) -> Result<(), ObserverError> {
+        if event_type == EventType::BlockPosted {
+            rollback_on_reorg(self, &self.observer_api).await?;
+        }
         // determine whether to sync or not
         let mut local_next_event_id = self
             .observer_api
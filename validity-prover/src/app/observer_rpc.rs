@@ -1,13 +1,13 @@
 use super::{
     check_point_store::{ChainType, CheckPointStore, EventType},
-    error::ObserverError,
+    error::{LeaderError, ObserverError},
     leader_election::LeaderElection,
     observer_api::ObserverApi,
-    observer_common::{ObserverConfig, SyncEvent},
+    observer_common::{query_ranges_concurrently, ObserverConfig, SyncEvent},
     rate_manager::RateManager,
 };
 use crate::{
-    app::observer_common::{initialize_observer_db, sync_event_key},
+    app::observer_common::{initialize_observer_db, rollback_on_reorg, sync_event_key},
     EnvVar,
 };
 use alloy::providers::Provider;
@@ -88,11 +88,19 @@ impl RPCObserver {
         from_eth_block_number: u64,
         to_eth_block_number: u64,
     ) -> Result<u64, ObserverError> {
-        let events = self
-            .rollup_contract
-            .get_deposit_leaf_inserted_events(from_eth_block_number, to_eth_block_number)
-            .await
-            .map_err(|e| ObserverError::EventFetchError(e.to_string()))?;
+        let events = query_ranges_concurrently(
+            from_eth_block_number,
+            to_eth_block_number,
+            self.config.observer_event_block_interval,
+            self.config.observer_query_concurrency,
+            |from, to| {
+                self.rollup_contract
+                    .get_deposit_leaf_inserted_events(from, to)
+            },
+            |e| (e.eth_block_number, e.eth_tx_index, e.deposit_index),
+        )
+        .await
+        .map_err(|e| ObserverError::EventFetchError(e.to_string()))?;
         let events = events
             .into_iter()
             .skip_while(|e| e.deposit_index < expected_next_event_id as u32)
@@ -147,11 +155,16 @@ impl RPCObserver {
         from_eth_block_number: u64,
         to_eth_block_number: u64,
     ) -> Result<u64, ObserverError> {
-        let events = self
-            .liquidity_contract
-            .get_deposited_events(from_eth_block_number, to_eth_block_number)
-            .await
-            .map_err(|e| ObserverError::EventFetchError(e.to_string()))?;
+        let events = query_ranges_concurrently(
+            from_eth_block_number,
+            to_eth_block_number,
+            self.config.observer_event_block_interval,
+            self.config.observer_query_concurrency,
+            |from, to| self.liquidity_contract.get_deposited_events(from, to),
+            |e| (e.eth_block_number, e.eth_tx_index, e.deposit_id),
+        )
+        .await
+        .map_err(|e| ObserverError::EventFetchError(e.to_string()))?;
         let events = events
             .into_iter()
             .skip_while(|e| e.deposit_id < expected_next_event_id)
@@ -215,11 +228,16 @@ impl RPCObserver {
         from_eth_block_number: u64,
         to_eth_block_number: u64,
     ) -> Result<u64, ObserverError> {
-        let events = self
-            .rollup_contract
-            .get_blocks_posted_event(from_eth_block_number, to_eth_block_number)
-            .await
-            .map_err(|e| ObserverError::EventFetchError(e.to_string()))?;
+        let events = query_ranges_concurrently(
+            from_eth_block_number,
+            to_eth_block_number,
+            self.config.observer_event_block_interval,
+            self.config.observer_query_concurrency,
+            |from, to| self.rollup_contract.get_blocks_posted_event(from, to),
+            |e| (e.eth_block_number, e.eth_tx_index, e.block_number),
+        )
+        .await
+        .map_err(|e| ObserverError::EventFetchError(e.to_string()))?;
         let events = events
             .into_iter()
             .skip_while(|b| b.block_number < expected_next_event_id as u32)
@@ -285,9 +303,29 @@ impl RPCObserver {
         warn!(
             "Reset checkpoint. Event type: {event_type}, Local last eth block number: {local_last_eth_block_number:?}, Reset eth block number: {reset_eth_block_number}, Reason: {reason}"
         );
-        self.check_point_store
-            .set_check_point(event_type, reset_eth_block_number)
+        self.save_check_point(event_type, reset_eth_block_number)
+            .await?;
+        Ok(())
+    }
+
+    async fn save_check_point(
+        &self,
+        event_type: EventType,
+        eth_block_number: u64,
+    ) -> Result<(), ObserverError> {
+        let epoch = self
+            .leader_election
+            .epoch()
+            .ok_or(LeaderError::LockAcquisitionError)?;
+        let written = self
+            .check_point_store
+            .set_check_point_as_leader(event_type, eth_block_number, epoch)
             .await?;
+        if !written {
+            warn!(
+                "Checkpoint of {event_type} was not saved: a leader newer than epoch {epoch} owns it"
+            );
+        }
         Ok(())
     }
 
@@ -324,8 +362,13 @@ impl RPCObserver {
                 .await?;
             return Ok(local_next_event_id);
         }
+        // Each sync covers one event block interval per concurrent range query
+        let sync_block_range = self
+            .config
+            .observer_event_block_interval
+            .saturating_mul(self.config.observer_query_concurrency as u64);
         let to_eth_block_number = current_eth_block_number
-            .min(from_eth_block_number + self.config.observer_event_block_interval - 1);
+            .min(from_eth_block_number.saturating_add(sync_block_range - 1));
         // This is asserted because we already checked that from_eth_block_number <= current_eth_block_number
         assert!(
             to_eth_block_number >= from_eth_block_number,
@@ -376,8 +419,7 @@ impl RPCObserver {
                     "Sync success. Local next event id: {}, synced next event id: {}, From eth block number: {}, To eth block number: {}",
                     local_next_event_id, next_event_id, from_eth_block_number, to_eth_block_number
                     );
-                self.check_point_store
-                    .set_check_point(event_type, to_eth_block_number)
+                self.save_check_point(event_type, to_eth_block_number)
                     .await?;
                 Ok(next_event_id)
             }
@@ -428,6 +470,9 @@ impl SyncEvent for RPCObserver {
 
     #[instrument(skip(self))]
     async fn sync_events(&self, event_type: EventType) -> Result<(), ObserverError> {
+        if event_type == EventType::BlockPosted {
+            rollback_on_reorg(self, &self.observer_api).await?;
+        }
         // determine whether to sync or not
         let mut local_next_event_id = self
             .observer_api
@@ -1,4 +1,5 @@
 use chrono::Utc;
+use serde::Serialize;
 use std::{
     collections::HashMap,
     sync::Arc,
@@ -12,6 +13,15 @@ pub enum RateManagerError {
     Timeout(String),
 }
 
+/// Why a job was halted, reported by the status endpoint
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HaltRecord {
+    pub key: String,
+    pub reason: String,
+    pub halted_at: u64,
+}
+
 #[derive(Debug, Clone)]
 pub struct RateManager {
     pub window: Duration,
@@ -25,6 +35,9 @@ pub struct RateManager {
 
     // stop flags
     pub stop_flags: Arc<Mutex<HashMap<String, bool>>>,
+
+    // reasons of the halts
+    pub halts: Arc<Mutex<HashMap<String, HaltRecord>>>,
 }
 
 impl RateManager {
@@ -35,6 +48,7 @@ impl RateManager {
             counts: Arc::new(Mutex::new(HashMap::new())),
             last_timestamps: Arc::new(Mutex::new(HashMap::new())),
             stop_flags: Arc::new(Mutex::new(HashMap::new())),
+            halts: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
@@ -54,6 +68,12 @@ impl RateManager {
             .await
             .map_err(|_| RateManagerError::Timeout("Timeout while resetting keys".to_string()))?;
         last_timestamps.clear();
+        drop(last_timestamps);
+
+        let mut halts = timeout(self.timeout, self.halts.lock())
+            .await
+            .map_err(|_| RateManagerError::Timeout("Timeout while resetting keys".to_string()))?;
+        halts.clear();
         Ok(())
     }
 
@@ -129,6 +149,29 @@ impl RateManager {
         Ok(stop_flags.get(key).cloned().unwrap_or(false))
     }
 
+    /// Set the stop flag of `key` and record why. The first reason is kept until the next reset.
+    pub async fn halt(&self, key: &str, reason: &str) -> Result<(), RateManagerError> {
+        self.set_stop_flag(key, true).await?;
+        let mut halts = timeout(self.timeout, self.halts.lock())
+            .await
+            .map_err(|_| RateManagerError::Timeout("Timeout while halting".to_string()))?;
+        halts.entry(key.to_string()).or_insert_with(|| HaltRecord {
+            key: key.to_string(),
+            reason: reason.to_string(),
+            halted_at: Utc::now().timestamp() as u64,
+        });
+        Ok(())
+    }
+
+    pub async fn get_halts(&self) -> Result<Vec<HaltRecord>, RateManagerError> {
+        let halts = timeout(self.timeout, self.halts.lock())
+            .await
+            .map_err(|_| RateManagerError::Timeout("Timeout while getting halts".to_string()))?;
+        let mut halts = halts.values().cloned().collect::<Vec<_>>();
+        halts.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(halts)
+    }
+
     async fn cleanup(&self) -> Result<(), RateManagerError> {
         let mut counts = timeout(self.timeout, self.counts.lock())
             .await
@@ -1,5 +1,8 @@
 use super::{
-    error::ValidityProverError, leader_election::LeaderElection, observer_api::ObserverApi,
+    error::{ObserverError, ValidityProverError},
+    leader_election::LeaderElection,
+    observer_api::ObserverApi,
+    observer_common::DEFAULT_MAX_REORG_DEPTH,
     rate_manager::RateManager,
 };
 use crate::{
@@ -7,7 +10,7 @@ use crate::{
     trees::{
         deposit_hash::DepositHash,
         merkle_tree::{
-            sql_incremental_merkle_tree::SqlIncrementalMerkleTree,
+            compaction::CompactionConfig, sql_incremental_merkle_tree::SqlIncrementalMerkleTree,
             sql_indexed_merkle_tree::SqlIndexedMerkleTree, IncrementalMerkleTreeClient,
             IndexedMerkleTreeClient,
         },
@@ -16,7 +19,7 @@ use crate::{
     EnvVar,
 };
 use intmax2_interfaces::{
-    api::validity_prover::interface::{TransitionProofTask, TransitionProofTaskResult},
+    api::validity_prover::interface::{SyncStatus, TransitionProofTask, TransitionProofTaskResult},
     utils::circuit_verifiers::CircuitVerifiers,
 };
 use intmax2_zkp::{
@@ -31,11 +34,12 @@ use plonky2::{
 };
 use server_common::{
     db::{DbPool, DbPoolConfig},
+    metrics,
     redis::task_manager::TaskManager,
 };
 use sqlx::Pool;
 use std::{
-    sync::{Arc, OnceLock},
+    sync::{Arc, OnceLock, RwLock},
     time::Duration,
 };
 use tracing::instrument;
@@ -58,6 +62,10 @@ pub const GENERATE_VALIDITY_PROOF_KEY: &str = "generate_validity_proof";
 pub const ADD_TASKS_KEY: &str = "add_tasks";
 pub const CLEANUP_INACTIVE_TASKS_KEY: &str = "cleanup_inactive_tasks";
 
+const DEFAULT_COMPACTION_CHECKPOINT_INTERVAL: u64 = 1000;
+const DEFAULT_COMPACTION_INTERVAL: u64 = 3600;
+const DEFAULT_SYNC_TIP_THRESHOLD: u32 = 2;
+
 #[derive(Clone, Debug)]
 pub struct ValidityProverConfig {
     pub is_sync_mode: bool,
@@ -66,6 +74,28 @@ pub struct ValidityProverConfig {
     pub add_tasks_interval: u64,
     pub cleanup_inactive_tasks_interval: u64,
     pub validity_prover_restart_interval: u64,
+    pub compaction: Option<CompactionConfig>,
+    pub compaction_interval: u64,
+    pub max_reorg_depth: u32,
+    /// Maximum number of blocks the validity proofs may lag behind the chain head to be
+    /// considered synced to the tip
+    pub sync_tip_threshold: u32,
+}
+
+impl ValidityProverConfig {
+    /// Check that a reorg within `max_reorg_depth` can always be rolled back. The trees are reset
+    /// to the reorg ancestor, so its state must not have been compacted away.
+    pub fn validate(&self) -> Result<(), ValidityProverError> {
+        if let Some(compaction) = &self.compaction {
+            if compaction.retain_window < self.max_reorg_depth as u64 {
+                return Err(ValidityProverError::ConfigError(format!(
+                    "tree compaction retain window {} is shorter than the max reorg depth {}",
+                    compaction.retain_window, self.max_reorg_depth
+                )));
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Clone)]
@@ -83,6 +113,8 @@ pub struct ValidityProver {
     pub block_tree_backup: SqlIncrementalMerkleTree<Bytes32>,
     pub deposit_hash_tree_backup: SqlIncrementalMerkleTree<DepositHash>,
     pub pool: DbPool,
+    // updated by the validity proof job, `None` until its first round
+    sync_status: Arc<RwLock<Option<SyncStatus>>>,
 }
 
 impl ValidityProver {
@@ -99,7 +131,24 @@ impl ValidityProver {
             add_tasks_interval: env.add_tasks_interval,
             cleanup_inactive_tasks_interval: env.cleanup_inactive_tasks_interval,
             validity_prover_restart_interval: env.validity_prover_restart_interval,
+            compaction: env
+                .tree_compaction_retain_window
+                .map(|retain_window| CompactionConfig {
+                    retain_window,
+                    checkpoint_interval: env
+                        .tree_compaction_checkpoint_interval
+                        .unwrap_or(DEFAULT_COMPACTION_CHECKPOINT_INTERVAL),
+                    dry_run: env.tree_compaction_dry_run.unwrap_or(false),
+                }),
+            compaction_interval: env
+                .tree_compaction_interval
+                .unwrap_or(DEFAULT_COMPACTION_INTERVAL),
+            max_reorg_depth: env
+                .observer_max_reorg_depth
+                .unwrap_or(DEFAULT_MAX_REORG_DEPTH),
+            sync_tip_threshold: env.sync_tip_threshold.unwrap_or(DEFAULT_SYNC_TIP_THRESHOLD),
         };
+        config.validate()?;
         tracing::info!("ValidityProverConfig: {:?}", config);
         let manager = Arc::new(TaskManager::new(
             &env.redis_url,
@@ -115,9 +164,13 @@ impl ValidityProver {
                 .check_consistency(env.rollup_contract_address, env.liquidity_contract_address)
                 .await?;
         }
-        let account_tree =
+        let mut account_tree =
             SqlIndexedMerkleTree::new(pool.clone(), ACCOUNT_DB_TAG, ACCOUNT_TREE_HEIGHT);
+        if let Some(capacity) = env.account_tree_cache_capacity {
+            account_tree = account_tree.with_cache(capacity);
+        }
         account_tree.initialize().await?;
+        account_tree.warm_cache().await?;
         let block_tree = SqlIncrementalMerkleTree::<Bytes32>::new(
             pool.clone(),
             BLOCK_DB_TAG,
@@ -179,6 +232,7 @@ impl ValidityProver {
             account_tree_backup,
             block_tree_backup,
             deposit_hash_tree_backup,
+            sync_status: Arc::new(RwLock::new(None)),
         })
     }
 
@@ -200,7 +254,16 @@ impl ValidityProver {
             observer_block_number,
             self.get_latest_validity_proof_block_number().await?,
         );
-        let last_block_number = self.get_last_block_number().await?;
+        let mut last_block_number = self.get_last_block_number().await?;
+        if let Some(ancestor_block_number) = self
+            .find_reorg_ancestor(last_block_number, observer_block_number)
+            .await?
+        {
+            self.rollback_state(ancestor_block_number).await?;
+            // drop transition proof tasks and results of the rolled back blocks
+            self.manager.clear_all().await?;
+            last_block_number = ancestor_block_number;
+        }
         let next_block_number = observer_block_number + 1;
         let mut prev_validity_pis = if last_block_number == 0 {
             ValidityWitness::genesis().to_validity_pis().unwrap()
@@ -312,6 +375,82 @@ impl ValidityProver {
         Ok(())
     }
 
+    // Find the latest synced block that still matches the observer's block.
+    // Returns None if the last synced block is unchanged.
+    #[instrument(skip(self))]
+    async fn find_reorg_ancestor(
+        &self,
+        last_block_number: u32,
+        observer_block_number: u32,
+    ) -> Result<Option<u32>, ValidityProverError> {
+        let max_reorg_depth = self.config.max_reorg_depth;
+        let mut block_number = last_block_number.min(observer_block_number);
+        loop {
+            if last_block_number - block_number > max_reorg_depth {
+                return Err(ValidityProverError::ReorgTooDeep {
+                    block_number: last_block_number,
+                    max_reorg_depth,
+                });
+            }
+            if block_number == 0 || self.is_block_consistent(block_number).await? {
+                break;
+            }
+            block_number -= 1;
+        }
+        if block_number == last_block_number {
+            Ok(None)
+        } else {
+            Ok(Some(block_number))
+        }
+    }
+
+    async fn is_block_consistent(&self, block_number: u32) -> Result<bool, ValidityProverError> {
+        let full_block_with_meta = self
+            .observer_api
+            .get_full_block_with_meta(block_number)
+            .await?;
+        let Some(full_block_with_meta) = full_block_with_meta else {
+            return Ok(false);
+        };
+        let validity_witness = self.get_validity_witness(block_number).await?;
+        Ok(validity_witness.block_witness.block.hash()
+            == full_block_with_meta.full_block.block.hash())
+    }
+
+    // Roll back the validity state and the trees to the given block number after a reorg
+    #[instrument(skip(self))]
+    async fn rollback_state(&self, block_number: u32) -> Result<(), ValidityProverError> {
+        tracing::warn!(
+            "Reorg detected: rolling back to block number {}",
+            block_number
+        );
+        let mut tx = self.pool.begin().await?;
+        sqlx::query!(
+            "DELETE FROM validity_state WHERE block_number > $1",
+            block_number as i32
+        )
+        .execute(tx.as_mut())
+        .await?;
+        sqlx::query!(
+            "DELETE FROM tx_tree_roots WHERE block_number > $1",
+            block_number as i32
+        )
+        .execute(tx.as_mut())
+        .await?;
+        sqlx::query!(
+            "DELETE FROM validity_proofs WHERE block_number > $1",
+            block_number as i32
+        )
+        .execute(tx.as_mut())
+        .await?;
+        tx.commit().await?;
+        let reset_block_number = block_number as u64 + 1;
+        self.account_tree.reset(reset_block_number).await?;
+        self.block_tree.reset(reset_block_number).await?;
+        self.deposit_hash_tree.reset(reset_block_number).await?;
+        Ok(())
+    }
+
     // Reset the state of the trees which are not synced with the validity witness
     #[instrument(skip(self))]
     async fn reset_state(&self) -> Result<(), ValidityProverError> {
@@ -483,7 +622,96 @@ impl ValidityProver {
                 .emit_heartbeat(GENERATE_VALIDITY_PROOF_KEY)
                 .await?;
             self.generate_validity_proof().await?;
+            self.record_sync_lag().await?;
+        }
+    }
+
+    /// Whether the validity proofs have caught up with the chain head. Returns the status
+    /// tracked by the validity proof job if it runs in this process, and checks the chain
+    /// otherwise.
+    pub async fn get_sync_status(&self) -> Result<SyncStatus, ValidityProverError> {
+        if let Some(sync_status) = *self.sync_status.read().unwrap() {
+            return Ok(sync_status);
+        }
+        let validity_proof_block_number = self.get_latest_validity_proof_block_number().await?;
+        self.check_sync_status(validity_proof_block_number).await
+    }
+
+    async fn check_sync_status(
+        &self,
+        validity_proof_block_number: u32,
+    ) -> Result<SyncStatus, ValidityProverError> {
+        let chain_head_block_number = self
+            .observer_api
+            .rollup_contract
+            .get_latest_block_number()
+            .await
+            .map_err(ObserverError::from)?;
+        Ok(SyncStatus::new(
+            chain_head_block_number,
+            validity_proof_block_number,
+            self.config.sync_tip_threshold,
+        ))
+    }
+
+    /// Export how far the validity proofs are behind the observed blocks, and track whether
+    /// they have caught up with the chain head
+    async fn record_sync_lag(&self) -> Result<(), ValidityProverError> {
+        let observed_block_number = self.observer_api.get_local_last_block_number().await?;
+        let validity_proof_block_number = self.get_latest_validity_proof_block_number().await?;
+        let sync_status = self.check_sync_status(validity_proof_block_number).await?;
+        let prev_sync_status = self.sync_status.write().unwrap().replace(sync_status);
+        let was_synced = prev_sync_status.map(|s| s.is_synced_to_tip);
+        if was_synced != Some(sync_status.is_synced_to_tip) {
+            if sync_status.is_synced_to_tip {
+                tracing::info!(
+                    "Catch-up complete: validity proofs reached block {} of chain head {}",
+                    validity_proof_block_number,
+                    sync_status.chain_head_block_number
+                );
+            } else {
+                tracing::info!(
+                    "Catching up: validity proofs are {} blocks behind chain head {}",
+                    sync_status.block_gap,
+                    sync_status.chain_head_block_number
+                );
+            }
         }
+        metrics::set_gauge(
+            "validity_prover_synced_to_tip",
+            "1 if the validity proofs are within the sync threshold of the chain head",
+            &[],
+            if sync_status.is_synced_to_tip {
+                1.0
+            } else {
+                0.0
+            },
+        );
+        metrics::set_gauge(
+            "validity_prover_tip_gap_blocks",
+            "Number of blocks between the chain head and the latest validity proof",
+            &[],
+            sync_status.block_gap as f64,
+        );
+        metrics::set_gauge(
+            "validity_prover_observed_block_number",
+            "Latest block number synced by the observer",
+            &[],
+            observed_block_number as f64,
+        );
+        metrics::set_gauge(
+            "validity_prover_validity_proof_block_number",
+            "Latest block number with a validity proof",
+            &[],
+            validity_proof_block_number as f64,
+        );
+        metrics::set_gauge(
+            "validity_prover_sync_lag_blocks",
+            "Number of observed blocks without a validity proof",
+            &[],
+            observed_block_number.saturating_sub(validity_proof_block_number) as f64,
+        );
+        Ok(())
     }
 
     #[instrument(skip(self))]
@@ -511,6 +739,27 @@ impl ValidityProver {
         }
     }
 
+    #[instrument(skip(self))]
+    async fn compact_trees_loop(
+        &self,
+        config: CompactionConfig,
+    ) -> Result<(), ValidityProverError> {
+        let mut interval =
+            tokio::time::interval(Duration::from_secs(self.config.compaction_interval));
+        loop {
+            interval.tick().await;
+            self.leader_election.wait_for_leadership().await?;
+            let block_tree_report = self.block_tree.compact(&config).await?;
+            let deposit_tree_report = self.deposit_hash_tree.compact(&config).await?;
+            tracing::info!(
+                "compact_trees (dry_run: {}): block tree {:?}, deposit tree {:?}",
+                config.dry_run,
+                block_tree_report,
+                deposit_tree_report
+            );
+        }
+    }
+
     pub async fn start_all_jobs(&self) -> Result<(), ValidityProverError> {
         // clear all tasks
         self.manager.clear_all().await?;
@@ -593,6 +842,33 @@ impl ValidityProver {
             }
         });
 
+        // compact trees job
+        if let Some(compaction) = self.config.compaction.clone() {
+            let this_clone = this.clone();
+            actix_web::rt::spawn(async move {
+                // restart loop
+                loop {
+                    let this_clone = this_clone.clone();
+                    let compaction = compaction.clone();
+                    let handler = actix_web::rt::spawn(async move {
+                        this_clone.compact_trees_loop(compaction).await
+                    });
+                    match handler.await {
+                        Ok(Ok(_)) => {
+                            tracing::error!("compact_trees_loop finished");
+                        }
+                        Ok(Err(e)) => {
+                            tracing::error!("compact_trees_loop error: {:?}", e);
+                        }
+                        Err(e) => {
+                            tracing::error!("compact_trees_loop panic: {:?}", e);
+                        }
+                    }
+                    tokio::time::sleep(restart_duration).await;
+                }
+            });
+        }
+
         // cleanup inactive tasks job
         let this_clone = this.clone();
         tokio::spawn(async move {
@@ -619,3 +895,297 @@ impl ValidityProver {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloy::providers::mock::Asserter;
+    use intmax2_zkp::ethereum_types::u256::U256;
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::{
+        app::observer_api::tests::{fork_block, mock_observer_api, observe_blocks},
+        trees::merkle_tree::error::MerkleTreeError,
+    };
+
+    fn test_config(
+        max_reorg_depth: u32,
+        compaction: Option<CompactionConfig>,
+    ) -> ValidityProverConfig {
+        ValidityProverConfig {
+            is_sync_mode: false,
+            witness_sync_interval: 1,
+            validity_proof_interval: 1,
+            add_tasks_interval: 1,
+            cleanup_inactive_tasks_interval: 1,
+            validity_prover_restart_interval: 1,
+            compaction,
+            compaction_interval: DEFAULT_COMPACTION_INTERVAL,
+            max_reorg_depth,
+            sync_tip_threshold: DEFAULT_SYNC_TIP_THRESHOLD,
+        }
+    }
+
+    // The task manager and the leader election are never connected to redis in these tests.
+    fn test_prover(pool: PgPool, config: ValidityProverConfig) -> ValidityProver {
+        let redis_url = "redis://localhost:6379";
+        ValidityProver {
+            config,
+            manager: Arc::new(TaskManager::new(redis_url, "validity_prover", 60, 10).unwrap()),
+            validity_circuit: Arc::new(OnceLock::new()),
+            observer_api: mock_observer_api(pool.clone(), Asserter::new()),
+            leader_election: LeaderElection::new(
+                redis_url,
+                "validity_prover_leader",
+                Duration::from_secs(10),
+            )
+            .unwrap(),
+            rate_manager: RateManager::new(Duration::from_secs(60), Duration::from_secs(5)),
+            account_tree: SqlIndexedMerkleTree::new(
+                pool.clone(),
+                ACCOUNT_DB_TAG,
+                ACCOUNT_TREE_HEIGHT,
+            ),
+            block_tree: SqlIncrementalMerkleTree::new(
+                pool.clone(),
+                BLOCK_DB_TAG,
+                BLOCK_HASH_TREE_HEIGHT,
+            ),
+            deposit_hash_tree: SqlIncrementalMerkleTree::new(
+                pool.clone(),
+                DEPOSIT_DB_TAG,
+                DEPOSIT_TREE_HEIGHT,
+            ),
+            account_tree_backup: SqlIndexedMerkleTree::new(
+                pool.clone(),
+                ACCOUNT_BACKUP_DB_TAG,
+                ACCOUNT_TREE_HEIGHT,
+            ),
+            block_tree_backup: SqlIncrementalMerkleTree::new(
+                pool.clone(),
+                BLOCK_BACKUP_DB_TAG,
+                BLOCK_HASH_TREE_HEIGHT,
+            ),
+            deposit_hash_tree_backup: SqlIncrementalMerkleTree::new(
+                pool.clone(),
+                DEPOSIT_BACKUP_DB_TAG,
+                DEPOSIT_TREE_HEIGHT,
+            ),
+            pool: DbPool::new(pool),
+            sync_status: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    /// Store the validity state of blocks 1 to `last_block_number` of `fork`, with one tx tree
+    /// root, one validity proof and one leaf in every tree per block.
+    async fn sync_blocks(prover: &ValidityProver, last_block_number: u32, fork: u64) {
+        prover.account_tree.initialize().await.unwrap();
+        for block_number in 1..=last_block_number {
+            let mut validity_witness = ValidityWitness::genesis();
+            validity_witness.block_witness.block = fork_block(block_number, fork).block;
+            sqlx::query(
+                "INSERT INTO validity_state (block_number, validity_witness) VALUES ($1, $2)",
+            )
+            .bind(block_number as i32)
+            .bind(bincode::serialize(&validity_witness).unwrap())
+            .execute(&prover.pool)
+            .await
+            .unwrap();
+            sqlx::query("INSERT INTO tx_tree_roots (tx_tree_root, block_number) VALUES ($1, $2)")
+                .bind(vec![block_number as u8; 32])
+                .bind(block_number as i32)
+                .execute(&prover.pool)
+                .await
+                .unwrap();
+            sqlx::query("INSERT INTO validity_proofs (block_number, proof) VALUES ($1, $2)")
+                .bind(block_number as i32)
+                .bind(vec![0u8])
+                .execute(&prover.pool)
+                .await
+                .unwrap();
+            let timestamp = block_number as u64;
+            prover
+                .account_tree
+                .insert(timestamp, U256::from(100 + block_number), timestamp)
+                .await
+                .unwrap();
+            prover
+                .block_tree
+                .push(timestamp, fork_block(block_number, fork).block.hash())
+                .await
+                .unwrap();
+            prover
+                .deposit_hash_tree
+                .push(timestamp, DepositHash(Bytes32::default()))
+                .await
+                .unwrap();
+        }
+    }
+
+    /// Replace the observed block `block_number` with the one of `fork`.
+    async fn replace_observed_block(pool: &PgPool, block_number: u32, fork: u64) {
+        sqlx::query("UPDATE full_blocks SET full_block = $1 WHERE block_number = $2")
+            .bind(bincode::serialize(&fork_block(block_number, fork)).unwrap())
+            .bind(block_number as i32)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    async fn count_rows_after(prover: &ValidityProver, table: &str, block_number: u32) -> i64 {
+        sqlx::query_scalar(&format!(
+            "SELECT COUNT(*) FROM {table} WHERE block_number > $1"
+        ))
+        .bind(block_number as i32)
+        .fetch_one(&prover.pool)
+        .await
+        .unwrap()
+    }
+
+    #[test]
+    fn test_validate_config() {
+        let compaction = |retain_window| {
+            Some(CompactionConfig {
+                retain_window,
+                checkpoint_interval: 10,
+                dry_run: false,
+            })
+        };
+        assert!(test_config(64, None).validate().is_ok());
+        assert!(test_config(64, compaction(64)).validate().is_ok());
+        assert!(matches!(
+            test_config(64, compaction(63)).validate(),
+            Err(ValidityProverError::ConfigError(_))
+        ));
+    }
+
+    #[sqlx::test]
+    async fn test_is_block_consistent(pool: PgPool) {
+        observe_blocks(&pool, 2, 0).await;
+        let prover = test_prover(pool.clone(), test_config(DEFAULT_MAX_REORG_DEPTH, None));
+        sync_blocks(&prover, 3, 0).await;
+
+        assert!(prover.is_block_consistent(1).await.unwrap());
+        assert!(prover.is_block_consistent(2).await.unwrap());
+        // not observed yet
+        assert!(!prover.is_block_consistent(3).await.unwrap());
+        // replaced by a reorg
+        replace_observed_block(&pool, 2, 1).await;
+        assert!(!prover.is_block_consistent(2).await.unwrap());
+    }
+
+    #[sqlx::test]
+    async fn test_find_reorg_ancestor(pool: PgPool) {
+        observe_blocks(&pool, 4, 0).await;
+        let prover = test_prover(pool.clone(), test_config(2, None));
+        sync_blocks(&prover, 4, 0).await;
+
+        assert_eq!(prover.find_reorg_ancestor(4, 4).await.unwrap(), None);
+        // the observer rolled back block 4 and has not observed the new one yet
+        assert_eq!(prover.find_reorg_ancestor(4, 3).await.unwrap(), Some(3));
+
+        replace_observed_block(&pool, 3, 1).await;
+        replace_observed_block(&pool, 4, 1).await;
+        assert_eq!(prover.find_reorg_ancestor(4, 4).await.unwrap(), Some(2));
+
+        replace_observed_block(&pool, 2, 1).await;
+        assert!(matches!(
+            prover.find_reorg_ancestor(4, 4).await,
+            Err(ValidityProverError::ReorgTooDeep {
+                block_number: 4,
+                max_reorg_depth: 2
+            })
+        ));
+    }
+
+    #[sqlx::test]
+    async fn test_rollback_state(pool: PgPool) {
+        let prover = test_prover(pool, test_config(DEFAULT_MAX_REORG_DEPTH, None));
+        sync_blocks(&prover, 5, 0).await;
+        let account_root = prover.account_tree.get_root(3).await.unwrap();
+        let block_root = prover.block_tree.get_root(3).await.unwrap();
+        let deposit_root = prover.deposit_hash_tree.get_root(3).await.unwrap();
+
+        prover.rollback_state(3).await.unwrap();
+
+        assert_eq!(prover.get_last_block_number().await.unwrap(), 3);
+        for table in ["validity_state", "tx_tree_roots", "validity_proofs"] {
+            assert_eq!(count_rows_after(&prover, table, 3).await, 0);
+            assert_eq!(count_rows_after(&prover, table, 0).await, 3);
+        }
+        assert_eq!(prover.block_tree.get_last_timestamp().await.unwrap(), 3);
+        // the states of the rolled back blocks are gone
+        assert_eq!(prover.account_tree.get_root(5).await.unwrap(), account_root);
+        assert_eq!(prover.block_tree.get_root(5).await.unwrap(), block_root);
+        assert_eq!(
+            prover.deposit_hash_tree.get_root(5).await.unwrap(),
+            deposit_root
+        );
+        assert_eq!(
+            prover.account_tree.index(5, U256::from(105)).await.unwrap(),
+            None
+        );
+        assert_eq!(prover.block_tree.len(5).await.unwrap(), 3);
+    }
+
+    #[sqlx::test]
+    async fn test_rollback_state_with_compaction(pool: PgPool) {
+        let max_reorg_depth = 10;
+        let compaction = CompactionConfig {
+            retain_window: max_reorg_depth as u64,
+            checkpoint_interval: 7,
+            dry_run: false,
+        };
+        let config = test_config(max_reorg_depth, Some(compaction.clone()));
+        config.validate().unwrap();
+        let prover = test_prover(pool, config);
+        sync_blocks(&prover, 30, 0).await;
+        // block 20 is the deepest ancestor a reorg can roll back to, and is not a checkpoint
+        let ancestor = 30 - max_reorg_depth;
+        assert_ne!(ancestor as u64 % compaction.checkpoint_interval, 0);
+        let block_root = prover.block_tree.get_root(ancestor as u64).await.unwrap();
+        let deposit_root = prover
+            .deposit_hash_tree
+            .get_root(ancestor as u64)
+            .await
+            .unwrap();
+
+        prover.block_tree.compact(&compaction).await.unwrap();
+        prover.deposit_hash_tree.compact(&compaction).await.unwrap();
+        assert!(matches!(
+            prover.block_tree.get_root(ancestor as u64 - 1).await,
+            Err(MerkleTreeError::CompactedState { .. })
+        ));
+
+        prover.rollback_state(ancestor).await.unwrap();
+
+        // the state of the ancestor was retained, so the new fork is built on the right roots
+        assert_eq!(
+            prover.block_tree.get_root(ancestor as u64).await.unwrap(),
+            block_root
+        );
+        assert_eq!(
+            prover
+                .deposit_hash_tree
+                .get_root(ancestor as u64)
+                .await
+                .unwrap(),
+            deposit_root
+        );
+        let timestamp = ancestor as u64 + 1;
+        prover
+            .block_tree
+            .push(timestamp, fork_block(ancestor + 1, 1).block.hash())
+            .await
+            .unwrap();
+        assert_eq!(
+            prover.block_tree.len(timestamp).await.unwrap(),
+            ancestor as usize + 1
+        );
+        prover
+            .block_tree
+            .prove(timestamp, ancestor as u64)
+            .await
+            .unwrap();
+    }
+}
@@ -1,6 +1,8 @@
 use alloy::primitives::Address;
 use serde::Deserialize;
 
+use crate::app::observer_common::ObserverOnError;
+
 pub mod api;
 pub mod app;
 pub mod trees;
@@ -23,6 +25,10 @@ pub struct EnvVar {
     pub observer_max_query_times: usize,
     pub observer_sync_interval: u64,
     pub observer_restart_interval: u64,
+    pub observer_max_reorg_depth: Option<u32>,
+    // blocks the validity proofs may lag behind the chain head to be considered synced
+    pub sync_tip_threshold: Option<u32>,
+    pub observer_query_concurrency: Option<usize>,
 
     // onchain settings
     pub l1_rpc_url: String,
@@ -51,10 +57,22 @@ pub struct EnvVar {
     // cache
     pub dynamic_cache_ttl: u64,
     pub static_cache_ttl: u64,
+    pub dynamic_cache_max_entries: Option<usize>, // unbounded if not set
 
     // rate manager
     pub observer_error_threshold: u64,
+    // what to do once the error threshold is reached: halt (default) or continue
+    pub observer_on_error: Option<ObserverOnError>,
     pub rate_manager_window: u64,
     pub rate_manager_timeout: u64,
     pub thread_heartbeat_timeout: u64,
+
+    // tree history compaction (disabled if retain window is not set)
+    pub tree_compaction_retain_window: Option<u64>,
+    pub tree_compaction_checkpoint_interval: Option<u64>,
+    pub tree_compaction_interval: Option<u64>,
+    pub tree_compaction_dry_run: Option<bool>,
+
+    // in-memory cache of the latest account tree (disabled if not set)
+    pub account_tree_cache_capacity: Option<usize>,
 }
@@ -1,16 +1,22 @@
 use std::io::{self};
 
-use actix_cors::Cors;
-use actix_web::{web::Data, App, HttpServer};
-use server_common::logger;
+use actix_web::{middleware::from_fn, web::Data, App, HttpServer};
+use server_common::{
+    cors::CorsConfig, health_check::set_name_and_version, logger, metrics::track_requests,
+};
 use tracing_actix_web::TracingLogger;
 use validity_prover::{
-    api::{health::health_check, state::State, validity_prover::validity_prover_scope},
+    api::{
+        health::{health_check, status},
+        state::State,
+        validity_prover::validity_prover_scope,
+    },
     EnvVar,
 };
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
+    set_name_and_version(env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
     logger::init_logger().map_err(io::Error::other)?;
 
     dotenvy::dotenv().ok();
@@ -22,13 +28,17 @@ async fn main() -> std::io::Result<()> {
 
     let data = Data::new(state);
 
+    let cors_config = CorsConfig::from_env().map_err(io::Error::other)?;
+
     HttpServer::new(move || {
-        let cors = Cors::permissive();
+        let cors = cors_config.cors();
         App::new()
             .wrap(cors)
             .wrap(TracingLogger::<logger::CustomRootSpanBuilder>::new())
+            .wrap(from_fn(track_requests))
             .app_data(data.clone())
             .service(health_check)
+            .service(status)
             .service(validity_prover_scope())
     })
     .bind(format!("0.0.0.0:{}", env.port))?
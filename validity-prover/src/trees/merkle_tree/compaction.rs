@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+/// Settings for compacting the history of a SQL merkle tree.
+///
+/// States older than `last_timestamp - retain_window` are only kept at timestamps which are
+/// multiples of `checkpoint_interval`. Every timestamp inside the retained window is still
+/// answered exactly, and queries of other compacted timestamps fail with `CompactedState`.
+/// The checkpoint interval cannot change once a tree is compacted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompactionConfig {
+    pub retain_window: u64,
+    pub checkpoint_interval: u64,
+    /// If true, only report the number of rows that would be removed.
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompactionReport {
+    pub cutoff: u64,
+    pub hash_nodes: u64,
+    pub leaves: u64,
+    pub leaves_len: u64,
+}
+
+impl CompactionReport {
+    pub fn total(&self) -> u64 {
+        self.hash_nodes + self.leaves + self.leaves_len
+    }
+}
@@ -1,3 +1,4 @@
+use intmax2_zkp::ethereum_types::u256::U256;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -17,6 +18,24 @@ pub enum MerkleTreeError {
     #[error("Leaf not found for hash: {0}")]
     LeafNotFoundError(String),
 
+    #[error("Duplicate key: {0}")]
+    DuplicateKey(U256),
+
+    #[error("Invalid snapshot: {0}")]
+    InvalidSnapshot(String),
+
+    #[error("Snapshot root mismatch: expected {expected}, computed {computed}")]
+    SnapshotRootMismatch { expected: String, computed: String },
+
+    #[error(
+        "State at timestamp {timestamp} was removed by compaction (cutoff {cutoff}, checkpoint interval {checkpoint_interval})"
+    )]
+    CompactedState {
+        timestamp: u64,
+        cutoff: u64,
+        checkpoint_interval: u64,
+    },
+
     #[error("Internal error: {0}")]
     InternalError(String),
 }
@@ -141,6 +141,9 @@ impl MockIndexedMerkleTree {
     }
 
     pub async fn insert(&self, timestamp: u64, key: U256, value: u64) -> MTResult<()> {
+        if self.index(timestamp, key).await?.is_some() {
+            return Err(MerkleTreeError::DuplicateKey(key));
+        }
         let index = self.0.len(timestamp).await? as u64;
         let low_index = self.low_index(timestamp, key).await?;
         let prev_low_leaf = self.0.get_leaf(timestamp, low_index).await?;
@@ -162,12 +165,23 @@ impl MockIndexedMerkleTree {
         Ok(())
     }
 
+    pub async fn upsert(&self, timestamp: u64, key: U256, value: u64) -> MTResult<()> {
+        if self.index(timestamp, key).await?.is_some() {
+            self.update(timestamp, key, value).await
+        } else {
+            self.insert(timestamp, key, value).await
+        }
+    }
+
     pub async fn prove_and_insert(
         &self,
         timestamp: u64,
         key: U256,
         value: u64,
     ) -> MTResult<IndexedInsertionProof> {
+        if self.index(timestamp, key).await?.is_some() {
+            return Err(MerkleTreeError::DuplicateKey(key));
+        }
         let index = self.0.len(timestamp).await? as u64;
         let low_index = self.low_index(timestamp, key).await?;
         let prev_low_leaf = self.0.get_leaf(timestamp, low_index).await?;
@@ -270,6 +284,10 @@ impl IndexedMerkleTreeClient for MockIndexedMerkleTree {
         self.insert(timestamp, key, value).await
     }
 
+    async fn upsert(&self, timestamp: u64, key: U256, value: u64) -> MTResult<()> {
+        self.upsert(timestamp, key, value).await
+    }
+
     async fn prove_and_insert(
         &self,
         timestamp: u64,
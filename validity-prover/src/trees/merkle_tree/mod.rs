@@ -18,12 +18,15 @@ use intmax2_zkp::{
 };
 use serde::{de::DeserializeOwned, Serialize};
 
+pub mod compaction;
 pub mod error;
 pub mod mock_incremental_merkle_tree;
 pub mod mock_indexed_merkle_tree;
+pub mod snapshot;
 pub mod sql_incremental_merkle_tree;
 pub mod sql_indexed_merkle_tree;
 pub mod sql_node_hash;
+pub mod tree_cache;
 
 pub type Hasher<V> = <V as Leafable>::LeafableHasher;
 pub type HashOut<V> = <Hasher<V> as LeafableHasher>::HashOut;
@@ -62,7 +65,10 @@ pub trait IndexedMerkleTreeClient: std::fmt::Debug {
         account_id: u64,
     ) -> MTResult<AccountMerkleProof>;
     async fn prove_membership(&self, timestamp: u64, key: U256) -> MTResult<MembershipProof>;
+    /// Insert a new key. Returns `MerkleTreeError::DuplicateKey` if the key already exists.
     async fn insert(&self, timestamp: u64, key: U256, value: u64) -> MTResult<()>;
+    /// Update the value if the key exists, otherwise insert it.
+    async fn upsert(&self, timestamp: u64, key: U256, value: u64) -> MTResult<()>;
     async fn prove_and_insert(
         &self,
         timestamp: u64,
@@ -85,6 +91,7 @@ mod tests {
     use crate::trees::{
         create_partitions_for_test, generate_random_tag,
         merkle_tree::{
+            compaction::CompactionConfig, error::MerkleTreeError,
             sql_incremental_merkle_tree::SqlIncrementalMerkleTree,
             sql_indexed_merkle_tree::SqlIndexedMerkleTree, IncrementalMerkleTreeClient,
             IndexedMerkleTreeClient,
@@ -244,6 +251,62 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_compact_incremental_merkle_tree() -> anyhow::Result<()> {
+        let height = 32;
+        let n = 100;
+
+        let database_url = setup_test();
+        let pool = sqlx::Pool::connect(&database_url).await?;
+        let tag = generate_random_tag();
+        create_partitions_for_test(&pool, tag).await?;
+
+        let tree = SqlIncrementalMerkleTree::<V>::new(pool, tag, height);
+        tree.reset(0).await?;
+        for timestamp in 0..n {
+            tree.push(timestamp, timestamp as u32).await?;
+        }
+        let mut roots = Vec::new();
+        for timestamp in 0..n {
+            roots.push(tree.get_root(timestamp).await?);
+        }
+
+        let mut config = CompactionConfig {
+            retain_window: 20,
+            checkpoint_interval: 10,
+            dry_run: true,
+        };
+        let dry_run_report = tree.compact(&config).await?;
+        assert!(dry_run_report.total() > 0);
+        // dry run does not change anything
+        assert_eq!(tree.compact(&config).await?, dry_run_report);
+
+        config.dry_run = false;
+        let report = tree.compact(&config).await?;
+        assert_eq!(report, dry_run_report);
+
+        // retained window and checkpoints are answered exactly, other timestamps are rejected
+        for timestamp in 0..n {
+            if timestamp >= report.cutoff || timestamp % config.checkpoint_interval == 0 {
+                assert_eq!(tree.get_root(timestamp).await?, roots[timestamp as usize]);
+                tree.prove(timestamp, 0).await?;
+            } else {
+                assert!(matches!(
+                    tree.get_root(timestamp).await,
+                    Err(MerkleTreeError::CompactedState { .. })
+                ));
+                assert!(tree.prove(timestamp, 0).await.is_err());
+                assert!(tree.get_leaf(timestamp, 0).await.is_err());
+            }
+        }
+        assert_eq!(tree.len(n - 1).await?, n as usize);
+
+        // nothing left to compact
+        assert_eq!(tree.compact(&config).await?.total(), 0);
+
+        Ok(())
+    }
+
     #[tokio::test]
     #[ignore]
     async fn test_speed_indexed_merkle_tree() -> anyhow::Result<()> {
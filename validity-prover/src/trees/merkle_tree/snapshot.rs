@@ -0,0 +1,101 @@
+use intmax2_zkp::utils::{
+    leafable::Leafable,
+    leafable_hasher::LeafableHasher,
+    poseidon_hash_out::PoseidonHashOut,
+    trees::{bit_path::BitPath, indexed_merkle_tree::leaf::IndexedMerkleLeaf},
+};
+use serde::{Deserialize, Serialize};
+
+use super::{error::MerkleTreeError, Hasher, MTResult};
+
+type V = IndexedMerkleLeaf;
+
+const SNAPSHOT_VERSION: u8 = 1;
+
+/// Full state of an indexed merkle tree at a timestamp. The leaves are ordered by position, so
+/// the same tree state always serializes to the same bytes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexedTreeSnapshot {
+    pub version: u8,
+    pub height: u32,
+    pub timestamp: u64,
+    pub root: PoseidonHashOut,
+    pub leaves: Vec<V>,
+}
+
+impl IndexedTreeSnapshot {
+    pub fn new(height: usize, timestamp: u64, root: PoseidonHashOut, leaves: Vec<V>) -> Self {
+        Self {
+            version: SNAPSHOT_VERSION,
+            height: height as u32,
+            timestamp,
+            root,
+            leaves,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        bincode::serialize(self).unwrap()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> MTResult<Self> {
+        let snapshot: Self = bincode::deserialize(bytes)?;
+        if snapshot.version != SNAPSHOT_VERSION {
+            return Err(MerkleTreeError::InvalidSnapshot(format!(
+                "unsupported version {}",
+                snapshot.version
+            )));
+        }
+        if snapshot.height > 63 || snapshot.leaves.len() as u64 > 1 << snapshot.height {
+            return Err(MerkleTreeError::InvalidSnapshot(format!(
+                "{} leaves do not fit in a tree of height {}",
+                snapshot.leaves.len(),
+                snapshot.height
+            )));
+        }
+        Ok(snapshot)
+    }
+
+    /// Returns the hashes of every node covering the leaves, from the leaves up to the root,
+    /// which is the last entry. Nodes outside of the leaves keep the zero hash of their depth.
+    pub fn compute_nodes(&self) -> Vec<(BitPath, PoseidonHashOut)> {
+        let height = self.height;
+        let mut zero_hash = V::empty_leaf().hash();
+        let mut level = self
+            .leaves
+            .iter()
+            .map(|leaf| leaf.hash())
+            .collect::<Vec<_>>();
+        let mut nodes = Vec::new();
+        for depth in (0..=height).rev() {
+            for (index, hash) in level.iter().enumerate() {
+                nodes.push((node_path(depth, index as u64), *hash));
+            }
+            if depth == 0 {
+                break;
+            }
+            level = level
+                .chunks(2)
+                .map(|pair| Hasher::<V>::two_to_one(pair[0], *pair.get(1).unwrap_or(&zero_hash)))
+                .collect();
+            zero_hash = Hasher::<V>::two_to_one(zero_hash, zero_hash);
+        }
+        if self.leaves.is_empty() {
+            // the root of an empty tree
+            nodes.push((BitPath::default(), zero_hash));
+        }
+        nodes
+    }
+
+    /// The root recomputed from the leaves.
+    pub fn compute_root(&self) -> PoseidonHashOut {
+        self.compute_nodes().last().unwrap().1
+    }
+}
+
+/// Path of the node at `index` among the nodes of `depth`, big endian like the stored paths.
+fn node_path(depth: u32, index: u64) -> BitPath {
+    let mut path = BitPath::new(depth, index);
+    path.reverse();
+    path
+}
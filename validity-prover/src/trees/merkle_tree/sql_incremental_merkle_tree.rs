@@ -6,7 +6,12 @@ use intmax2_zkp::utils::{
 use serde::{de::DeserializeOwned, Serialize};
 use sqlx::{Pool, Postgres};
 
-use super::{sql_node_hash::SqlNodeHashes, HashOut, Hasher, IncrementalMerkleTreeClient, MTResult};
+use super::{
+    compaction::{CompactionConfig, CompactionReport},
+    error::MerkleTreeError,
+    sql_node_hash::SqlNodeHashes,
+    HashOut, Hasher, IncrementalMerkleTreeClient, MTResult,
+};
 
 #[derive(Clone, Debug)]
 pub struct SqlIncrementalMerkleTree<V: Leafable + Serialize + DeserializeOwned> {
@@ -31,6 +36,141 @@ impl<V: Leafable + Serialize + DeserializeOwned> SqlIncrementalMerkleTree<V> {
         self.sql_node_hashes.height()
     }
 
+    /// Coalesce historical states older than the retained window into sparse checkpoints.
+    pub async fn compact(&self, config: &CompactionConfig) -> MTResult<CompactionReport> {
+        if config.checkpoint_interval == 0 {
+            return Err(MerkleTreeError::InternalError(
+                "checkpoint_interval must be positive".to_string(),
+            ));
+        }
+        let mut tx = self.pool().begin().await?;
+        if let Some((_, checkpoint_interval)) = self.get_compaction(&mut tx).await? {
+            if checkpoint_interval != config.checkpoint_interval {
+                return Err(MerkleTreeError::InternalError(format!(
+                    "checkpoint_interval cannot change from {checkpoint_interval} to {} once compacted",
+                    config.checkpoint_interval
+                )));
+            }
+        }
+        let last_timestamp = self.get_last_timestamp(&mut tx).await;
+        let cutoff = last_timestamp.saturating_sub(config.retain_window);
+        let hash_nodes = self
+            .sql_node_hashes
+            .compact(&mut tx, cutoff, config.checkpoint_interval)
+            .await?;
+        let leaves = sqlx::query!(
+            r#"
+            DELETE FROM leaves l
+            USING (
+                SELECT
+                    timestamp,
+                    position,
+                    LEAD(timestamp) OVER (PARTITION BY position ORDER BY timestamp) AS next_timestamp
+                FROM leaves
+                WHERE tag = $1
+            ) n
+            WHERE
+                l.tag = $1
+                AND l.timestamp = n.timestamp
+                AND l.position = n.position
+                AND n.next_timestamp <= $2
+                AND ((n.timestamp + $3 - 1) / $3) * $3 >= n.next_timestamp
+            "#,
+            self.tag() as i32,
+            cutoff as i64,
+            config.checkpoint_interval as i64,
+        )
+        .execute(tx.as_mut())
+        .await?
+        .rows_affected();
+        let leaves_len = sqlx::query!(
+            r#"
+            DELETE FROM leaves_len l
+            USING (
+                SELECT
+                    timestamp,
+                    LEAD(timestamp) OVER (ORDER BY timestamp) AS next_timestamp
+                FROM leaves_len
+                WHERE tag = $1
+            ) n
+            WHERE
+                l.tag = $1
+                AND l.timestamp = n.timestamp
+                AND n.next_timestamp <= $2
+                AND ((n.timestamp + $3 - 1) / $3) * $3 >= n.next_timestamp
+            "#,
+            self.tag() as i32,
+            cutoff as i64,
+            config.checkpoint_interval as i64,
+        )
+        .execute(tx.as_mut())
+        .await?
+        .rows_affected();
+        sqlx::query!(
+            r#"
+            INSERT INTO merkle_tree_compactions (tag, cutoff, checkpoint_interval)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (tag)
+            DO UPDATE SET cutoff = GREATEST(merkle_tree_compactions.cutoff, $2)
+            "#,
+            self.tag() as i32,
+            cutoff as i64,
+            config.checkpoint_interval as i64,
+        )
+        .execute(tx.as_mut())
+        .await?;
+
+        // dry run reuses the same statements and discards the changes
+        if config.dry_run {
+            tx.rollback().await?;
+        } else {
+            tx.commit().await?;
+        }
+        Ok(CompactionReport {
+            cutoff,
+            hash_nodes,
+            leaves,
+            leaves_len,
+        })
+    }
+
+    /// The cutoff and checkpoint interval of the last compaction, if the tree was compacted.
+    async fn get_compaction(
+        &self,
+        tx: &mut sqlx::Transaction<'_, Postgres>,
+    ) -> MTResult<Option<(u64, u64)>> {
+        let record = sqlx::query!(
+            r#"
+            SELECT cutoff, checkpoint_interval
+            FROM merkle_tree_compactions
+            WHERE tag = $1
+            "#,
+            self.tag() as i32,
+        )
+        .fetch_optional(tx.as_mut())
+        .await?;
+        Ok(record.map(|row| (row.cutoff as u64, row.checkpoint_interval as u64)))
+    }
+
+    /// Fails if the state at `timestamp` was removed by compaction, because the rows left
+    /// would silently answer with an older state.
+    async fn check_not_compacted(
+        &self,
+        tx: &mut sqlx::Transaction<'_, Postgres>,
+        timestamp: u64,
+    ) -> MTResult<()> {
+        if let Some((cutoff, checkpoint_interval)) = self.get_compaction(tx).await? {
+            if timestamp < cutoff && timestamp % checkpoint_interval != 0 {
+                return Err(MerkleTreeError::CompactedState {
+                    timestamp,
+                    cutoff,
+                    checkpoint_interval,
+                });
+            }
+        }
+        Ok(())
+    }
+
     async fn save_leaf(
         &self,
         tx: &mut sqlx::Transaction<'_, Postgres>,
@@ -211,6 +351,19 @@ impl<V: Leafable + Serialize + DeserializeOwned> SqlIncrementalMerkleTree<V> {
         .execute(tx.as_mut())
         .await?;
 
+        // states from `timestamp` on are rebuilt from scratch, so they are exact again
+        sqlx::query!(
+            r#"
+            UPDATE merkle_tree_compactions
+            SET cutoff = LEAST(cutoff, $2)
+            WHERE tag = $1
+            "#,
+            self.tag() as i32,
+            timestamp as i64
+        )
+        .execute(tx.as_mut())
+        .await?;
+
         Ok(())
     }
 
@@ -255,6 +408,7 @@ impl<V: Leafable + Serialize + DeserializeOwned> IncrementalMerkleTreeClient<V>
 
     async fn get_root(&self, timestamp: u64) -> MTResult<HashOut<V>> {
         let mut tx = self.pool().begin().await?;
+        self.check_not_compacted(&mut tx, timestamp).await?;
         let root = self.sql_node_hashes.get_root(&mut tx, timestamp).await?;
         tx.commit().await?;
         Ok(root)
@@ -262,6 +416,7 @@ impl<V: Leafable + Serialize + DeserializeOwned> IncrementalMerkleTreeClient<V>
 
     async fn get_leaf(&self, timestamp: u64, position: u64) -> MTResult<V> {
         let mut tx = self.pool().begin().await?;
+        self.check_not_compacted(&mut tx, timestamp).await?;
         let leaf = self.get_leaf(&mut tx, timestamp, position).await?;
         tx.commit().await?;
         Ok(leaf)
@@ -269,6 +424,7 @@ impl<V: Leafable + Serialize + DeserializeOwned> IncrementalMerkleTreeClient<V>
 
     async fn len(&self, timestamp: u64) -> MTResult<usize> {
         let mut tx = self.pool().begin().await?;
+        self.check_not_compacted(&mut tx, timestamp).await?;
         let len = self.len(&mut tx, timestamp).await?;
         tx.commit().await?;
         Ok(len)
@@ -290,6 +446,7 @@ impl<V: Leafable + Serialize + DeserializeOwned> IncrementalMerkleTreeClient<V>
 
     async fn prove(&self, timestamp: u64, position: u64) -> MTResult<IncrementalMerkleProof<V>> {
         let mut tx = self.pool().begin().await?;
+        self.check_not_compacted(&mut tx, timestamp).await?;
         let proof = self.prove(&mut tx, timestamp, position).await?;
         tx.commit().await?;
         Ok(proof)
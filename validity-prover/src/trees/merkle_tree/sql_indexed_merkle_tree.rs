@@ -1,4 +1,7 @@
-use std::str::FromStr;
+use std::{
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
 
 use bigdecimal::{num_bigint::BigUint, BigDecimal};
 use intmax2_zkp::{
@@ -7,6 +10,7 @@ use intmax2_zkp::{
     utils::{
         leafable::Leafable,
         leafable_hasher::LeafableHasher,
+        poseidon_hash_out::PoseidonHashOut,
         trees::{
             bit_path::BitPath,
             incremental_merkle_tree::IncrementalMerkleProof,
@@ -14,27 +18,108 @@ use intmax2_zkp::{
                 insertion::IndexedInsertionProof, leaf::IndexedMerkleLeaf,
                 membership::MembershipProof, update::UpdateProof, IndexedMerkleProof,
             },
+            merkle_tree::MerkleProof,
         },
     },
 };
 use sqlx::{Pool, Postgres};
 
 use super::{
-    error::MerkleTreeError, sql_node_hash::SqlNodeHashes, HashOut, Hasher, IndexedMerkleTreeClient,
-    MTResult,
+    error::MerkleTreeError, snapshot::IndexedTreeSnapshot, sql_node_hash::SqlNodeHashes,
+    tree_cache::TreeCache, HashOut, Hasher, IndexedMerkleTreeClient, MTResult,
 };
 
 type V = IndexedMerkleLeaf;
 
+// rows per bulk insert when importing a snapshot
+const IMPORT_CHUNK_SIZE: usize = 10_000;
+
 #[derive(Clone, Debug)]
 pub struct SqlIndexedMerkleTree {
     sql_node_hashes: SqlNodeHashes<V>,
+    cache: Option<Arc<Mutex<TreeCache<V, HashOut<V>>>>>,
 }
 
 impl SqlIndexedMerkleTree {
     pub fn new(pool: Pool<Postgres>, tag: u32, height: usize) -> Self {
         let sql_node_hashes = SqlNodeHashes::new(pool, tag, height);
-        SqlIndexedMerkleTree { sql_node_hashes }
+        SqlIndexedMerkleTree {
+            sql_node_hashes,
+            cache: None,
+        }
+    }
+
+    /// Keep up to `capacity` recently accessed leaves and node hashes of the latest state in
+    /// memory. The cache must be warmed with `warm_cache`, and is only correct if this
+    /// instance is the only writer of the tree.
+    pub fn with_cache(mut self, capacity: usize) -> Self {
+        self.cache = Some(Arc::new(Mutex::new(TreeCache::new(capacity))));
+        self
+    }
+
+    /// Load the most recently updated leaves and the nodes nearest to the root at the last
+    /// timestamp into the cache. Does nothing if the cache is disabled.
+    pub async fn warm_cache(&self) -> MTResult<()> {
+        let Some(cache) = &self.cache else {
+            return Ok(());
+        };
+        let capacity = cache.lock().unwrap().capacity();
+        let mut tx = self.pool().begin().await?;
+        let timestamp = self.get_last_timestamp(&mut tx).await;
+
+        // nodes near the root are shared by every proof
+        let mut paths = Vec::new();
+        let mut depth = 0;
+        while depth <= self.height() && paths.len() + (1 << depth) <= capacity {
+            for index in 0..(1u64 << depth) {
+                let mut path = BitPath::new(depth as u32, index);
+                path.reverse();
+                paths.push(path);
+            }
+            depth += 1;
+        }
+        let hashes = self
+            .sql_node_hashes
+            .bulk_get_node_hashes(&mut tx, timestamp, &paths)
+            .await?;
+        let leaves = self.get_latest_leaves(&mut tx, timestamp, capacity).await?;
+        tx.commit().await?;
+
+        tracing::info!(
+            "warmed the cache of tree {} at timestamp {}: {} leaves, {} nodes",
+            self.tag(),
+            timestamp,
+            leaves.len(),
+            paths.len()
+        );
+        cache
+            .lock()
+            .unwrap()
+            .warm(timestamp, leaves, paths.into_iter().zip(hashes));
+        Ok(())
+    }
+
+    fn invalidate_cache(&self) {
+        if let Some(cache) = &self.cache {
+            cache.lock().unwrap().invalidate();
+        }
+    }
+
+    /// Commit a write, dropping the cached entries if `result` or the commit failed, as the
+    /// written entries may not be in the database.
+    async fn commit_write<T>(
+        &self,
+        tx: sqlx::Transaction<'_, Postgres>,
+        result: MTResult<T>,
+    ) -> MTResult<T> {
+        let result = match result {
+            Ok(value) => tx.commit().await.map(|_| value).map_err(Into::into),
+            Err(e) => Err(e),
+        };
+        if result.is_err() {
+            self.invalidate_cache();
+        }
+        result
     }
 
     // add default leaf to the first position of the tree
@@ -52,6 +137,177 @@ impl SqlIndexedMerkleTree {
         Ok(())
     }
 
+    /// Serialize the whole tree at `timestamp`, e.g. to back it up or to seed a replica with
+    /// `import_snapshot`. The same tree state always exports to the same bytes.
+    pub async fn export_snapshot(&self, timestamp: u64) -> MTResult<Vec<u8>> {
+        let mut tx = self.pool().begin().await?;
+        let len = self.len(&mut tx, timestamp).await?;
+        let root = self.sql_node_hashes.get_root(&mut tx, timestamp).await?;
+        let rows = sqlx::query_as::<_, (i64, i64, BigDecimal, BigDecimal, i64)>(
+            r#"
+            SELECT DISTINCT ON (position) position, next_index, key, next_key, value
+            FROM indexed_leaves
+            WHERE tag = $1 AND timestamp <= $2
+            ORDER BY position, timestamp DESC
+            "#,
+        )
+        .bind(self.tag() as i32)
+        .bind(timestamp as i64)
+        .fetch_all(tx.as_mut())
+        .await?;
+        tx.commit().await?;
+
+        let mut leaves = vec![V::empty_leaf(); len];
+        for (position, next_index, key, next_key, value) in rows {
+            let Some(leaf) = leaves.get_mut(position as usize) else {
+                return Err(MerkleTreeError::InternalError(format!(
+                    "leaf at position {position} is beyond the tree length {len}"
+                )));
+            };
+            *leaf = IndexedMerkleLeaf {
+                next_index: next_index as u64,
+                key: from_str_to_u256(&key.to_string()),
+                next_key: from_str_to_u256(&next_key.to_string()),
+                value: value as u64,
+            };
+        }
+        let snapshot = IndexedTreeSnapshot::new(self.height(), timestamp, root, leaves);
+        Ok(snapshot.to_bytes())
+    }
+
+    /// Rebuild the tree from a snapshot exported by `export_snapshot`. The tree must be empty.
+    /// Nothing is written unless the root recomputed from the leaves, both before and after
+    /// writing them, equals `expected_root`.
+    pub async fn import_snapshot(
+        &self,
+        bytes: &[u8],
+        expected_root: PoseidonHashOut,
+    ) -> MTResult<()> {
+        let snapshot = IndexedTreeSnapshot::from_bytes(bytes)?;
+        if snapshot.height as usize != self.height() {
+            return Err(MerkleTreeError::InvalidSnapshot(format!(
+                "height {} does not match the tree height {}",
+                snapshot.height,
+                self.height()
+            )));
+        }
+        let nodes = snapshot.compute_nodes();
+        let computed_root = nodes.last().unwrap().1;
+        check_root(expected_root, computed_root)?;
+
+        let mut tx = self.pool().begin().await?;
+        if self.get_last_timestamp(&mut tx).await != 0 || self.len(&mut tx, 0).await? != 0 {
+            return Err(MerkleTreeError::InvalidSnapshot(
+                "the tree to import into is not empty".to_string(),
+            ));
+        }
+        let result = self.write_snapshot(&mut tx, &snapshot, &nodes).await;
+        let result = match result {
+            Ok(()) => self
+                .sql_node_hashes
+                .get_root(&mut tx, snapshot.timestamp)
+                .await
+                .and_then(|stored_root| check_root(expected_root, stored_root)),
+            Err(e) => Err(e),
+        };
+        if result.is_ok() {
+            tracing::info!(
+                "imported a snapshot of tree {} at timestamp {}: {} leaves",
+                self.tag(),
+                snapshot.timestamp,
+                snapshot.leaves.len()
+            );
+        }
+        // dropping the transaction on error rolls the writes back
+        let result = self.commit_write(tx, result).await;
+        // the imported versions bypassed the cache
+        self.invalidate_cache();
+        result
+    }
+
+    async fn write_snapshot(
+        &self,
+        tx: &mut sqlx::Transaction<'_, Postgres>,
+        snapshot: &IndexedTreeSnapshot,
+        nodes: &[(BitPath, HashOut<V>)],
+    ) -> MTResult<()> {
+        let timestamp = snapshot.timestamp as i64;
+        for (chunk_index, leaves) in snapshot.leaves.chunks(IMPORT_CHUNK_SIZE).enumerate() {
+            let start = (chunk_index * IMPORT_CHUNK_SIZE) as i64;
+            let positions = (start..start + leaves.len() as i64).collect::<Vec<_>>();
+            let leaf_hashes = leaves
+                .iter()
+                .map(|leaf| bincode::serialize(&leaf.hash()).unwrap())
+                .collect::<Vec<_>>();
+            let next_indices = leaves
+                .iter()
+                .map(|leaf| leaf.next_index as i64)
+                .collect::<Vec<_>>();
+            let keys = leaves
+                .iter()
+                .map(|leaf| BigDecimal::from_str(&leaf.key.to_string()).unwrap())
+                .collect::<Vec<_>>();
+            let next_keys = leaves
+                .iter()
+                .map(|leaf| BigDecimal::from_str(&leaf.next_key.to_string()).unwrap())
+                .collect::<Vec<_>>();
+            let values = leaves
+                .iter()
+                .map(|leaf| leaf.value as i64)
+                .collect::<Vec<_>>();
+            sqlx::query(
+                r#"
+                INSERT INTO indexed_leaves (tag, timestamp, position, leaf_hash, next_index, key, next_key, value)
+                SELECT $1, $2, * FROM UNNEST($3::bigint[], $4::bytea[], $5::bigint[], $6::numeric[], $7::numeric[], $8::bigint[])
+                "#,
+            )
+            .bind(self.tag() as i32)
+            .bind(timestamp)
+            .bind(&positions)
+            .bind(&leaf_hashes)
+            .bind(&next_indices)
+            .bind(&keys)
+            .bind(&next_keys)
+            .bind(&values)
+            .execute(tx.as_mut())
+            .await?;
+        }
+        sqlx::query(
+            r#"
+            INSERT INTO leaves_len (tag, timestamp, len)
+            VALUES ($1, $2, $3)
+            "#,
+        )
+        .bind(self.tag() as i32)
+        .bind(timestamp)
+        .bind(snapshot.leaves.len() as i32)
+        .execute(tx.as_mut())
+        .await?;
+        for nodes in nodes.chunks(IMPORT_CHUNK_SIZE) {
+            let bit_paths = nodes
+                .iter()
+                .map(|(path, _)| bincode::serialize(path).unwrap())
+                .collect::<Vec<_>>();
+            let hashes = nodes
+                .iter()
+                .map(|(_, hash)| bincode::serialize(hash).unwrap())
+                .collect::<Vec<_>>();
+            sqlx::query(
+                r#"
+                INSERT INTO hash_nodes (timestamp, tag, bit_path, hash_value)
+                SELECT $1, $2, * FROM UNNEST($3::bytea[], $4::bytea[])
+                "#,
+            )
+            .bind(timestamp)
+            .bind(self.tag() as i32)
+            .bind(&bit_paths)
+            .bind(&hashes)
+            .execute(tx.as_mut())
+            .await?;
+        }
+        Ok(())
+    }
+
     pub fn tag(&self) -> u32 {
         self.sql_node_hashes.tag()
     }
@@ -108,6 +364,9 @@ impl SqlIndexedMerkleTree {
         )
         .execute(tx.as_mut())
         .await?;
+        if let Some(cache) = &self.cache {
+            cache.lock().unwrap().write_leaf(timestamp, position, leaf);
+        }
 
         Ok(())
     }
@@ -117,6 +376,30 @@ impl SqlIndexedMerkleTree {
         tx: &mut sqlx::Transaction<'_, Postgres>,
         timestamp: u64,
         position: u64,
+    ) -> super::MTResult<V> {
+        let Some(cache) = &self.cache else {
+            return self.get_leaf_from_db(tx, timestamp, position).await;
+        };
+        let generation = {
+            let mut cache = cache.lock().unwrap();
+            if let Some(leaf) = cache.get_leaf(timestamp, position) {
+                return Ok(leaf);
+            }
+            cache.generation()
+        };
+        let leaf = self.get_leaf_from_db(tx, timestamp, position).await?;
+        cache
+            .lock()
+            .unwrap()
+            .fill_leaf(generation, timestamp, position, leaf.clone());
+        Ok(leaf)
+    }
+
+    async fn get_leaf_from_db(
+        &self,
+        tx: &mut sqlx::Transaction<'_, Postgres>,
+        timestamp: u64,
+        position: u64,
     ) -> super::MTResult<V> {
         let record = sqlx::query!(
             r#"
@@ -165,23 +448,11 @@ impl SqlIndexedMerkleTree {
         path.reverse();
         let leaf_hash = leaf.hash();
         self.save_leaf(tx, timestamp, index, leaf).await?;
-        self.sql_node_hashes
-            .save_node(tx, timestamp, path, leaf_hash)
-            .await?;
-
-        // collect paths
-        let mut paths = Vec::new();
-        let mut current_path = path;
-        while !current_path.is_empty() {
-            paths.push(current_path);
-            current_path.pop();
-        }
+        self.save_node(tx, timestamp, path, leaf_hash).await?;
 
+        let paths = collect_paths(path);
         if !paths.is_empty() {
-            let sibling_hashes = self
-                .sql_node_hashes
-                .bulk_get_sibling_hashes(tx, timestamp, &paths)
-                .await?;
+            let sibling_hashes = self.get_sibling_hashes(tx, timestamp, &paths).await?;
 
             let mut h = leaf_hash;
             let mut current_path = path;
@@ -193,9 +464,7 @@ impl SqlIndexedMerkleTree {
                 } else {
                     Hasher::<V>::two_to_one(h, sibling)
                 };
-                self.sql_node_hashes
-                    .save_node(tx, timestamp, current_path, new_h)
-                    .await?;
+                self.save_node(tx, timestamp, current_path, new_h).await?;
                 h = new_h;
             }
         }
@@ -203,6 +472,136 @@ impl SqlIndexedMerkleTree {
         Ok(())
     }
 
+    async fn save_node(
+        &self,
+        tx: &mut sqlx::Transaction<'_, Postgres>,
+        timestamp: u64,
+        path: BitPath,
+        hash: HashOut<V>,
+    ) -> MTResult<()> {
+        self.sql_node_hashes
+            .save_node(tx, timestamp, path, hash)
+            .await?;
+        if let Some(cache) = &self.cache {
+            cache.lock().unwrap().write_node(timestamp, path, hash);
+        }
+        Ok(())
+    }
+
+    /// Returns the hashes of the siblings of `paths`, reading through the cache.
+    async fn get_sibling_hashes(
+        &self,
+        tx: &mut sqlx::Transaction<'_, Postgres>,
+        timestamp: u64,
+        paths: &[BitPath],
+    ) -> MTResult<Vec<HashOut<V>>> {
+        let Some(cache) = &self.cache else {
+            return self
+                .sql_node_hashes
+                .bulk_get_sibling_hashes(tx, timestamp, paths)
+                .await;
+        };
+        let (generation, cached) = {
+            let mut cache = cache.lock().unwrap();
+            let cached = paths
+                .iter()
+                .map(|path| cache.get_node(timestamp, path.sibling()))
+                .collect::<Vec<_>>();
+            (cache.generation(), cached)
+        };
+        let missing = paths
+            .iter()
+            .zip(cached.iter())
+            .filter(|(_, hash)| hash.is_none())
+            .map(|(path, _)| *path)
+            .collect::<Vec<_>>();
+        if missing.is_empty() {
+            return Ok(cached.into_iter().flatten().collect());
+        }
+        let fetched = self
+            .sql_node_hashes
+            .bulk_get_sibling_hashes(tx, timestamp, &missing)
+            .await?;
+
+        let mut cache = cache.lock().unwrap();
+        let mut fetched = missing.into_iter().zip(fetched);
+        let hashes = cached
+            .into_iter()
+            .map(|hash| {
+                hash.unwrap_or_else(|| {
+                    // the fetched hashes are in the order of the missing paths
+                    let (path, hash) = fetched.next().unwrap();
+                    cache.fill_node(generation, timestamp, path.sibling(), hash);
+                    hash
+                })
+            })
+            .collect();
+        Ok(hashes)
+    }
+
+    async fn get_root(
+        &self,
+        tx: &mut sqlx::Transaction<'_, Postgres>,
+        timestamp: u64,
+    ) -> MTResult<HashOut<V>> {
+        let Some(cache) = &self.cache else {
+            return self.sql_node_hashes.get_root(tx, timestamp).await;
+        };
+        let generation = {
+            let mut cache = cache.lock().unwrap();
+            if let Some(root) = cache.get_node(timestamp, BitPath::default()) {
+                return Ok(root);
+            }
+            cache.generation()
+        };
+        let root = self.sql_node_hashes.get_root(tx, timestamp).await?;
+        cache
+            .lock()
+            .unwrap()
+            .fill_node(generation, timestamp, BitPath::default(), root);
+        Ok(root)
+    }
+
+    /// Returns the latest version at `timestamp` of the `limit` most recently updated leaves.
+    async fn get_latest_leaves(
+        &self,
+        tx: &mut sqlx::Transaction<'_, Postgres>,
+        timestamp: u64,
+        limit: usize,
+    ) -> MTResult<Vec<(u64, V)>> {
+        let rows = sqlx::query_as::<_, (i64, i64, BigDecimal, BigDecimal, i64)>(
+            r#"
+            SELECT position, next_index, key, next_key, value
+            FROM (
+                SELECT DISTINCT ON (position) position, timestamp, next_index, key, next_key, value
+                FROM indexed_leaves
+                WHERE tag = $1 AND timestamp <= $2
+                ORDER BY position, timestamp DESC
+            ) latest
+            ORDER BY timestamp DESC
+            LIMIT $3
+            "#,
+        )
+        .bind(self.tag() as i32)
+        .bind(timestamp as i64)
+        .bind(limit as i64)
+        .fetch_all(tx.as_mut())
+        .await?;
+        let leaves = rows
+            .into_iter()
+            .map(|(position, next_index, key, next_key, value)| {
+                let leaf = IndexedMerkleLeaf {
+                    next_index: next_index as u64,
+                    key: from_str_to_u256(&key.to_string()),
+                    next_key: from_str_to_u256(&next_key.to_string()),
+                    value: value as u64,
+                };
+                (position as u64, leaf)
+            })
+            .collect();
+        Ok(leaves)
+    }
+
     async fn len(
         &self,
         tx: &mut sqlx::Transaction<'_, Postgres>,
@@ -273,6 +672,10 @@ impl SqlIndexedMerkleTree {
         )
         .execute(tx.as_mut())
         .await?;
+        // cached nodes may be newer than `timestamp`
+        if let Some(cache) = &self.cache {
+            cache.lock().unwrap().reset(timestamp);
+        }
 
         Ok(())
     }
@@ -435,8 +838,12 @@ impl SqlIndexedMerkleTree {
         timestamp: u64,
         index: u64,
     ) -> MTResult<IndexedMerkleProof> {
-        let proof = self.sql_node_hashes.prove(tx, timestamp, index).await?;
-        Ok(IncrementalMerkleProof(proof))
+        let mut path = BitPath::new(self.height() as u32, index);
+        path.reverse(); // path is big endian
+        let siblings = self
+            .get_sibling_hashes(tx, timestamp, &collect_paths(path))
+            .await?;
+        Ok(IncrementalMerkleProof(MerkleProof { siblings }))
     }
 
     async fn prove_membership(
@@ -483,6 +890,9 @@ impl SqlIndexedMerkleTree {
         key: U256,
         value: u64,
     ) -> MTResult<()> {
+        if self.index(tx, timestamp, key).await?.is_some() {
+            return Err(MerkleTreeError::DuplicateKey(key));
+        }
         let index = self.len(tx, timestamp).await? as u64;
         let low_index = self.low_index(tx, timestamp, key).await?;
         let prev_low_leaf = self.get_leaf(tx, timestamp, low_index).await?;
@@ -510,6 +920,9 @@ impl SqlIndexedMerkleTree {
         key: U256,
         value: u64,
     ) -> MTResult<IndexedInsertionProof> {
+        if self.index(tx, timestamp, key).await?.is_some() {
+            return Err(MerkleTreeError::DuplicateKey(key));
+        }
         let index = self.len(tx, timestamp).await? as u64;
         let low_index = self.low_index(tx, timestamp, key).await?;
         let prev_low_leaf = self.get_leaf(tx, timestamp, low_index).await?;
@@ -538,6 +951,23 @@ impl SqlIndexedMerkleTree {
         })
     }
 
+    async fn upsert(
+        &self,
+        tx: &mut sqlx::Transaction<'_, Postgres>,
+        timestamp: u64,
+        key: U256,
+        value: u64,
+    ) -> MTResult<()> {
+        match self.index(tx, timestamp, key).await? {
+            Some(index) => {
+                let prev_leaf = self.get_leaf(tx, timestamp, index).await?;
+                let new_leaf = IndexedMerkleLeaf { value, ..prev_leaf };
+                self.update_leaf(tx, timestamp, index, new_leaf).await
+            }
+            None => self.insert(tx, timestamp, key, value).await,
+        }
+    }
+
     async fn prove_and_update(
         &self,
         tx: &mut sqlx::Transaction<'_, Postgres>,
@@ -563,6 +993,26 @@ impl SqlIndexedMerkleTree {
     }
 }
 
+fn check_root(expected: HashOut<V>, computed: HashOut<V>) -> MTResult<()> {
+    if expected != computed {
+        return Err(MerkleTreeError::SnapshotRootMismatch {
+            expected: format!("{expected:?}"),
+            computed: format!("{computed:?}"),
+        });
+    }
+    Ok(())
+}
+
+/// Returns the paths from `path` up to, but excluding, the root.
+fn collect_paths(mut path: BitPath) -> Vec<BitPath> {
+    let mut paths = Vec::new();
+    while !path.is_empty() {
+        paths.push(path);
+        path.pop();
+    }
+    paths
+}
+
 fn from_str_to_u256(s: &str) -> U256 {
     let biguint = BigUint::from_str(s).unwrap();
     biguint.try_into().unwrap()
@@ -572,7 +1022,7 @@ fn from_str_to_u256(s: &str) -> U256 {
 impl IndexedMerkleTreeClient for SqlIndexedMerkleTree {
     async fn get_root(&self, timestamp: u64) -> MTResult<HashOut<V>> {
         let mut tx = self.pool().begin().await?;
-        let root = self.sql_node_hashes.get_root(&mut tx, timestamp).await?;
+        let root = self.get_root(&mut tx, timestamp).await?;
         tx.commit().await?;
         Ok(root)
     }
@@ -593,9 +1043,8 @@ impl IndexedMerkleTreeClient for SqlIndexedMerkleTree {
 
     async fn push(&self, timestamp: u64, leaf: V) -> MTResult<()> {
         let mut tx = self.pool().begin().await?;
-        self.push(&mut tx, timestamp, leaf).await?;
-        tx.commit().await?;
-        Ok(())
+        let result = self.push(&mut tx, timestamp, leaf).await;
+        self.commit_write(tx, result).await
     }
 
     async fn get_last_timestamp(&self) -> MTResult<u64> {
@@ -607,9 +1056,8 @@ impl IndexedMerkleTreeClient for SqlIndexedMerkleTree {
 
     async fn reset(&self, timestamp: u64) -> MTResult<()> {
         let mut tx = self.pool().begin().await?;
-        self.reset(&mut tx, timestamp).await?;
-        tx.commit().await?;
-        Ok(())
+        let result = self.reset(&mut tx, timestamp).await;
+        self.commit_write(tx, result).await
     }
 
     async fn key(&self, timestamp: u64, index: u64) -> MTResult<U256> {
@@ -646,9 +1094,14 @@ impl IndexedMerkleTreeClient for SqlIndexedMerkleTree {
 
     async fn insert(&self, timestamp: u64, key: U256, value: u64) -> MTResult<()> {
         let mut tx = self.pool().begin().await?;
-        self.insert(&mut tx, timestamp, key, value).await?;
-        tx.commit().await?;
-        Ok(())
+        let result = self.insert(&mut tx, timestamp, key, value).await;
+        self.commit_write(tx, result).await
+    }
+
+    async fn upsert(&self, timestamp: u64, key: U256, value: u64) -> MTResult<()> {
+        let mut tx = self.pool().begin().await?;
+        let result = self.upsert(&mut tx, timestamp, key, value).await;
+        self.commit_write(tx, result).await
     }
 
     async fn prove_and_insert(
@@ -659,7 +1112,10 @@ impl IndexedMerkleTreeClient for SqlIndexedMerkleTree {
     ) -> MTResult<IndexedInsertionProof> {
         let mut tx = self.pool().begin().await?;
         let proof = self.prove_and_insert(&mut tx, timestamp, key, value).await;
-        tx.commit().await?;
+        if proof.is_err() {
+            self.invalidate_cache();
+        }
+        self.commit_write(tx, Ok(())).await?;
         proof
     }
 
@@ -673,7 +1129,10 @@ impl IndexedMerkleTreeClient for SqlIndexedMerkleTree {
         let proof = self
             .prove_and_update(&mut tx, timestamp, key, new_value)
             .await;
-        tx.commit().await?;
+        if proof.is_err() {
+            self.invalidate_cache();
+        }
+        self.commit_write(tx, Ok(())).await?;
         proof
     }
 }
@@ -683,7 +1142,10 @@ mod tests {
     use super::IndexedMerkleTreeClient;
     use crate::trees::{
         create_partitions_for_test, generate_random_tag,
-        merkle_tree::sql_indexed_merkle_tree::{from_str_to_u256, SqlIndexedMerkleTree},
+        merkle_tree::{
+            error::MerkleTreeError,
+            sql_indexed_merkle_tree::{from_str_to_u256, SqlIndexedMerkleTree},
+        },
         setup_test,
     };
     use intmax2_zkp::{
@@ -768,6 +1230,67 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_insert_duplicate_key() -> anyhow::Result<()> {
+        let database_url = setup_test();
+        let pool = sqlx::Pool::connect(&database_url).await?;
+        let tag = generate_random_tag();
+        create_partitions_for_test(&pool, tag).await?;
+        let tree = SqlIndexedMerkleTree::new(pool, tag, ACCOUNT_TREE_HEIGHT);
+        <SqlIndexedMerkleTree as IndexedMerkleTreeClient>::reset(&tree, 0).await?;
+
+        tree.initialize().await?;
+        let client: &dyn IndexedMerkleTreeClient = &tree;
+
+        let timestamp = 1;
+        let key = U256::from(5);
+        client.insert(timestamp, key, 5).await?;
+        let root_before = client.get_root(timestamp).await?;
+        let len_before = client.len(timestamp).await?;
+
+        let result = client.insert(timestamp, key, 6).await;
+        assert!(matches!(result, Err(MerkleTreeError::DuplicateKey(k)) if k == key));
+        let result = client.prove_and_insert(timestamp, key, 6).await;
+        assert!(matches!(result, Err(MerkleTreeError::DuplicateKey(k)) if k == key));
+
+        // the tree must be left untouched
+        assert_eq!(client.get_root(timestamp).await?, root_before);
+        assert_eq!(client.len(timestamp).await?, len_before);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_upsert() -> anyhow::Result<()> {
+        let database_url = setup_test();
+        let pool = sqlx::Pool::connect(&database_url).await?;
+        let tag = generate_random_tag();
+        create_partitions_for_test(&pool, tag).await?;
+        let tree = SqlIndexedMerkleTree::new(pool, tag, ACCOUNT_TREE_HEIGHT);
+        <SqlIndexedMerkleTree as IndexedMerkleTreeClient>::reset(&tree, 0).await?;
+
+        tree.initialize().await?;
+        let client: &dyn IndexedMerkleTreeClient = &tree;
+
+        let timestamp = 1;
+        let key = U256::from(5);
+        // inserts when the key is absent
+        client.upsert(timestamp, key, 5).await?;
+        let len = client.len(timestamp).await?;
+
+        // updates when the key is present
+        client.upsert(timestamp, key, 10).await?;
+        let index = client.index(timestamp, key).await?.unwrap();
+        assert_eq!(client.get_leaf(timestamp, index).await?.value, 10);
+        assert_eq!(client.len(timestamp).await?, len);
+
+        let mut expected = AccountTree::initialize();
+        expected.insert(key, 10)?;
+        assert_eq!(client.get_root(timestamp).await?, expected.get_root());
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_update_leaf_consistency() -> anyhow::Result<()> {
         let database_url = setup_test();
@@ -947,4 +1470,91 @@ mod tests {
         tx.rollback().await?;
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_cached_account_tree() -> anyhow::Result<()> {
+        let database_url = setup_test();
+        let pool = sqlx::Pool::connect(&database_url).await?;
+        let tag = generate_random_tag();
+        create_partitions_for_test(&pool, tag).await?;
+        let tree = SqlIndexedMerkleTree::new(pool, tag, ACCOUNT_TREE_HEIGHT).with_cache(16);
+        <SqlIndexedMerkleTree as IndexedMerkleTreeClient>::reset(&tree, 0).await?;
+
+        tree.initialize().await?;
+        tree.warm_cache().await?;
+        let client: &dyn IndexedMerkleTreeClient = &tree;
+
+        let mut expected = AccountTree::initialize();
+        for i in 2..10u32 {
+            client.insert(1, i.into(), i.into()).await?;
+            expected.insert(i.into(), i.into())?;
+        }
+        let root1 = client.get_root(1).await?;
+        assert_eq!(root1, expected.get_root());
+        let proof = client.prove_inclusion(1, 3).await?;
+        assert!(proof.verify(root1, AccountId(3), U256::from(3)));
+
+        // historical reads bypass the cache
+        let root0 = client.get_root(0).await?;
+        assert_ne!(root0, root1);
+
+        // write at a later timestamp, then roll it back
+        client.upsert(2, U256::from(3), 30).await?;
+        assert_ne!(client.get_root(2).await?, root1);
+        client.reset(2).await?;
+        assert_eq!(client.get_root(2).await?, root1);
+        assert_eq!(client.get_leaf(2, 3).await?.value, 3);
+        let proof = client.prove_inclusion(2, 3).await?;
+        assert!(proof.verify(root1, AccountId(3), U256::from(3)));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_export_import() -> anyhow::Result<()> {
+        let database_url = setup_test();
+        let pool = sqlx::Pool::connect(&database_url).await?;
+        let tag = generate_random_tag();
+        create_partitions_for_test(&pool, tag).await?;
+        let source = SqlIndexedMerkleTree::new(pool.clone(), tag, ACCOUNT_TREE_HEIGHT);
+        <SqlIndexedMerkleTree as IndexedMerkleTreeClient>::reset(&source, 0).await?;
+        source.initialize().await?;
+        let client: &dyn IndexedMerkleTreeClient = &source;
+        for i in 2..10u32 {
+            client.insert(1, i.into(), i.into()).await?;
+        }
+        client.upsert(2, 5u32.into(), 50).await?;
+        let root = client.get_root(2).await?;
+        let bytes = source.export_snapshot(2).await?;
+
+        // a wrong root is rejected and nothing is written
+        let tag = generate_random_tag();
+        create_partitions_for_test(&pool, tag).await?;
+        let target = SqlIndexedMerkleTree::new(pool, tag, ACCOUNT_TREE_HEIGHT);
+        <SqlIndexedMerkleTree as IndexedMerkleTreeClient>::reset(&target, 0).await?;
+        let wrong_root = client.get_root(1).await?;
+        let result = target.import_snapshot(&bytes, wrong_root).await;
+        assert!(matches!(
+            result,
+            Err(MerkleTreeError::SnapshotRootMismatch { .. })
+        ));
+        let target_client: &dyn IndexedMerkleTreeClient = &target;
+        assert_eq!(target_client.len(2).await?, 0);
+
+        target.import_snapshot(&bytes, root).await?;
+        assert_eq!(target_client.get_root(2).await?, root);
+        assert_eq!(target_client.len(2).await?, client.len(2).await?);
+        // the export is deterministic
+        assert_eq!(target.export_snapshot(2).await?, bytes);
+        let index = target_client.index(2, 5u32.into()).await?.unwrap();
+        let proof = target_client.prove_inclusion(2, index).await?;
+        let leaf = target_client.get_leaf(2, index).await?;
+        assert_eq!(leaf.value, 50);
+        assert!(proof.verify(root, AccountId(index), U256::from(5)));
+
+        // importing into a non-empty tree fails
+        let result = target.import_snapshot(&bytes, root).await;
+        assert!(matches!(result, Err(MerkleTreeError::InvalidSnapshot(_))));
+        Ok(())
+    }
 }
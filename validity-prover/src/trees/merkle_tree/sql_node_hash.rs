@@ -131,7 +131,17 @@ impl<V: Leafable + Serialize + DeserializeOwned> SqlNodeHashes<V> {
         paths: &[BitPath],
     ) -> MTResult<Vec<HashOut<V>>> {
         let sibling_paths: Vec<BitPath> = paths.iter().map(|p| p.sibling()).collect();
-        let serialized_paths: Vec<Vec<u8>> = sibling_paths
+        self.bulk_get_node_hashes(tx, timestamp, &sibling_paths)
+            .await
+    }
+
+    pub async fn bulk_get_node_hashes(
+        &self,
+        tx: &mut sqlx::Transaction<'_, Postgres>,
+        timestamp: u64,
+        paths: &[BitPath],
+    ) -> MTResult<Vec<HashOut<V>>> {
+        let serialized_paths: Vec<Vec<u8>> = paths
             .iter()
             .map(|p| bincode::serialize(p).unwrap())
             .collect();
@@ -161,11 +171,11 @@ impl<V: Leafable + Serialize + DeserializeOwned> SqlNodeHashes<V> {
         }
 
         // get results
-        let mut results = Vec::with_capacity(sibling_paths.len());
-        for sibling_path in sibling_paths {
-            let hash = match hash_map.get(&sibling_path) {
+        let mut results = Vec::with_capacity(paths.len());
+        for path in paths {
+            let hash = match hash_map.get(path) {
                 Some(h) => *h,
-                None => self.zero_hashes[sibling_path.len() as usize],
+                None => self.zero_hashes[path.len() as usize],
             };
             results.push(hash);
         }
@@ -219,6 +229,42 @@ impl<V: Leafable + Serialize + DeserializeOwned> SqlNodeHashes<V> {
         .await?;
         Ok(())
     }
+
+    /// Delete node versions older than `cutoff` which are not visible from any checkpoint
+    /// (multiple of `checkpoint_interval`) or from timestamps at or after `cutoff`.
+    /// Returns the number of deleted rows.
+    pub async fn compact(
+        &self,
+        tx: &mut sqlx::Transaction<'_, Postgres>,
+        cutoff: u64,
+        checkpoint_interval: u64,
+    ) -> MTResult<u64> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM hash_nodes h
+            USING (
+                SELECT
+                    timestamp,
+                    bit_path,
+                    LEAD(timestamp) OVER (PARTITION BY bit_path ORDER BY timestamp) AS next_timestamp
+                FROM hash_nodes
+                WHERE tag = $1
+            ) n
+            WHERE
+                h.tag = $1
+                AND h.timestamp = n.timestamp
+                AND h.bit_path = n.bit_path
+                AND n.next_timestamp <= $2
+                AND ((n.timestamp + $3 - 1) / $3) * $3 >= n.next_timestamp
+            "#,
+            self.tag as i32,
+            cutoff as i64,
+            checkpoint_interval as i64,
+        )
+        .execute(tx.as_mut())
+        .await?;
+        Ok(result.rows_affected())
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,248 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::Hash,
+};
+
+use intmax2_zkp::utils::trees::bit_path::BitPath;
+
+/// Map holding at most `capacity` entries, evicting the least recently used one.
+#[derive(Debug)]
+struct LruMap<K, V> {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<K, (V, u64)>,
+    // last used tick -> key
+    order: BTreeMap<u64, K>,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> LruMap<K, V> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            tick: 0,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+        }
+    }
+
+    fn get(&mut self, key: &K) -> Option<V> {
+        self.tick += 1;
+        let (value, last_used) = self.entries.get_mut(key)?;
+        self.order.remove(last_used);
+        *last_used = self.tick;
+        self.order.insert(self.tick, key.clone());
+        Some(value.clone())
+    }
+
+    fn contains(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    fn insert(&mut self, key: K, value: V) {
+        self.tick += 1;
+        if let Some((_, last_used)) = self.entries.insert(key.clone(), (value, self.tick)) {
+            self.order.remove(&last_used);
+        }
+        self.order.insert(self.tick, key);
+        while self.entries.len() > self.capacity {
+            let Some((_, evicted)) = self.order.pop_first() else {
+                break;
+            };
+            self.entries.remove(&evicted);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+}
+
+/// In-memory cache of the latest leaves (by position) and node hashes (by bit path) of a SQL
+/// merkle tree.
+///
+/// The cached entries are the versions visible from `timestamp` onwards, so only reads at or
+/// after it are served, and historical reads go to the database. This only holds if this
+/// process is the only writer of the tree. The cache is inactive until it is warmed.
+#[derive(Debug)]
+pub struct TreeCache<L, H> {
+    capacity: usize,
+    timestamp: Option<u64>,
+    // bumped on every write, so that a read which raced with a write is not cached
+    generation: u64,
+    leaves: LruMap<u64, L>,
+    nodes: LruMap<BitPath, H>,
+}
+
+impl<L: Clone, H: Clone> TreeCache<L, H> {
+    /// Cache holding up to `capacity` leaves and `capacity` node hashes.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            timestamp: None,
+            generation: 0,
+            leaves: LruMap::new(capacity),
+            nodes: LruMap::new(capacity),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Replace the entries with the state of the tree at `timestamp`, the last timestamp of
+    /// the tree.
+    pub fn warm(
+        &mut self,
+        timestamp: u64,
+        leaves: impl IntoIterator<Item = (u64, L)>,
+        nodes: impl IntoIterator<Item = (BitPath, H)>,
+    ) {
+        self.invalidate();
+        self.timestamp = Some(timestamp);
+        for (position, leaf) in leaves {
+            self.leaves.insert(position, leaf);
+        }
+        for (path, hash) in nodes {
+            self.nodes.insert(path, hash);
+        }
+    }
+
+    fn serves(&self, timestamp: u64) -> bool {
+        self.timestamp.is_some_and(|t| timestamp >= t)
+    }
+
+    pub fn get_leaf(&mut self, timestamp: u64, position: u64) -> Option<L> {
+        if !self.serves(timestamp) {
+            return None;
+        }
+        self.leaves.get(&position)
+    }
+
+    pub fn get_node(&mut self, timestamp: u64, path: BitPath) -> Option<H> {
+        if !self.serves(timestamp) {
+            return None;
+        }
+        self.nodes.get(&path)
+    }
+
+    /// Cache a leaf read from the database at `timestamp`, unless a write happened since
+    /// `generation`.
+    pub fn fill_leaf(&mut self, generation: u64, timestamp: u64, position: u64, leaf: L) {
+        if generation == self.generation
+            && self.serves(timestamp)
+            && !self.leaves.contains(&position)
+        {
+            self.leaves.insert(position, leaf);
+        }
+    }
+
+    /// Cache a node hash read from the database at `timestamp`, unless a write happened since
+    /// `generation`.
+    pub fn fill_node(&mut self, generation: u64, timestamp: u64, path: BitPath, hash: H) {
+        if generation == self.generation && self.serves(timestamp) && !self.nodes.contains(&path) {
+            self.nodes.insert(path, hash);
+        }
+    }
+
+    pub fn write_leaf(&mut self, timestamp: u64, position: u64, leaf: L) {
+        if self.advance(timestamp) {
+            self.leaves.insert(position, leaf);
+        }
+    }
+
+    pub fn write_node(&mut self, timestamp: u64, path: BitPath, hash: H) {
+        if self.advance(timestamp) {
+            self.nodes.insert(path, hash);
+        }
+    }
+
+    /// Move the cache forward to a write at `timestamp`. Returns false if the written entry
+    /// must not be cached.
+    fn advance(&mut self, timestamp: u64) -> bool {
+        self.generation += 1;
+        match self.timestamp {
+            Some(t) if timestamp >= t => {
+                self.timestamp = Some(timestamp);
+                true
+            }
+            Some(_) => {
+                // a write into the past may be shadowed by later versions
+                self.clear();
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Drop every entry, e.g. after a failed write whose entries may not be in the database.
+    pub fn invalidate(&mut self) {
+        self.generation += 1;
+        self.clear();
+    }
+
+    /// Drop every entry after the versions at or after `timestamp` are deleted.
+    pub fn reset(&mut self, timestamp: u64) {
+        self.invalidate();
+        self.timestamp = self.timestamp.map(|t| t.min(timestamp));
+    }
+
+    fn clear(&mut self) {
+        self.leaves.clear();
+        self.nodes.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LruMap, TreeCache};
+    use intmax2_zkp::utils::trees::bit_path::BitPath;
+
+    #[test]
+    fn test_lru_eviction() {
+        let mut map = LruMap::new(2);
+        map.insert(1, "a");
+        map.insert(2, "b");
+        assert_eq!(map.get(&1), Some("a"));
+        // 2 is the least recently used
+        map.insert(3, "c");
+        assert_eq!(map.get(&2), None);
+        assert_eq!(map.get(&1), Some("a"));
+        assert_eq!(map.get(&3), Some("c"));
+    }
+
+    #[test]
+    fn test_tree_cache_timestamps() {
+        let mut cache = TreeCache::<u32, u32>::new(10);
+        // inactive until warmed
+        cache.write_leaf(5, 0, 1);
+        assert_eq!(cache.get_leaf(5, 0), None);
+
+        cache.warm(5, [(0, 1)], [(BitPath::default(), 100)]);
+        assert_eq!(cache.get_leaf(5, 0), Some(1));
+        assert_eq!(cache.get_node(6, BitPath::default()), Some(100));
+        // historical reads bypass the cache
+        assert_eq!(cache.get_leaf(4, 0), None);
+
+        // a write moves the cache forward
+        cache.write_leaf(7, 0, 2);
+        assert_eq!(cache.get_leaf(7, 0), Some(2));
+        assert_eq!(cache.get_leaf(6, 0), None);
+
+        // a read that raced with a write is not cached
+        let generation = cache.generation();
+        cache.write_leaf(7, 1, 3);
+        cache.fill_leaf(generation, 7, 2, 4);
+        assert_eq!(cache.get_leaf(7, 2), None);
+
+        // reset drops the entries
+        cache.reset(6);
+        assert_eq!(cache.get_leaf(7, 0), None);
+        cache.fill_leaf(cache.generation(), 6, 0, 1);
+        assert_eq!(cache.get_leaf(6, 0), Some(1));
+    }
+}
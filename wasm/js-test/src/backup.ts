@@ -1,4 +1,4 @@
-import { generate_intmax_account_from_eth_key, make_history_backup, } from '../pkg';
+import { decode_history_backup_chunk, generate_intmax_account_from_eth_key, make_history_backup, } from '../pkg';
 import { env, config } from './setup';
 
 async function main() {
@@ -8,6 +8,13 @@ async function main() {
 
     const backup = await make_history_backup(config, privkey, 0n, 1000);
     console.log(backup);
+
+    for (const chunk of backup) {
+        const entries = decode_history_backup_chunk(privkey, chunk);
+        for (const entry of entries) {
+            console.log(`${entry.entry_type} ${entry.meta.digest} at ${entry.meta.timestamp}`);
+        }
+    }
 }
 
 main().then(() => {
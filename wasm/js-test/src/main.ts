@@ -124,12 +124,14 @@ async function sendTx(config: Config, block_builder_base_url: string, publicKey:
   await await_tx_sendable(config, privateKey, transfers, fee_quote);
   console.log("Tx sendable");
 
-  let memo: JsTxRequestMemo = await send_tx_request(config, block_builder_base_url, privateKey, transfers, payment_memos, fee_quote);
+  let memo: JsTxRequestMemo = await send_tx_request(config, [block_builder_base_url], privateKey, transfers, payment_memos, fee_quote);
   console.log("Transfer root of tx: ", memo.tx().transfer_tree_root);
   // wait for the block builder to propose the block
   await sleep(env.BLOCK_BUILDER_QUERY_WAIT_TIME);
   const result = await query_and_finalize(config, env.BLOCK_BUILDER_BASE_URL, privateKey, memo);
 
+  console.log("Handled by block builder: ", result.block_builder_url);
+
   const tx_tree_root = result.tx_tree_root;
   while (true) {
     let status = await get_tx_status(config, publicKey, tx_tree_root);
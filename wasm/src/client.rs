@@ -1,5 +1,12 @@
 use intmax2_client_sdk::{
-    client::{client::Client, config::ClientConfig},
+    client::{
+        client::Client,
+        config::{
+            ClientConfig, DEFAULT_BALANCE_PROVER_WARMUP_RETRIES, DEFAULT_PROPOSAL_EXPIRY_MARGIN,
+            DEFAULT_RECEIVE_CONCURRENCY,
+        },
+        token_info::TokenInfoCache,
+    },
     external_api::{
         balance_prover::BalanceProverClient,
         block_builder::BlockBuilderClient,
@@ -10,6 +17,7 @@ use intmax2_client_sdk::{
         private_zkp_server::{PrivateZKPServerClient, PrivateZKPServerConfig},
         s3_store_vault::S3StoreVaultClient,
         store_vault_server::StoreVaultServerClient,
+        utils::retry::RetryConfig,
         validity_prover::ValidityProverClient,
         withdrawal_server::WithdrawalServerClient,
     },
@@ -19,7 +27,7 @@ use intmax2_interfaces::api::{
     store_vault_server::interface::StoreVaultClientInterface,
 };
 use serde::{Deserialize, Serialize};
-use wasm_bindgen::prelude::wasm_bindgen;
+use wasm_bindgen::{prelude::wasm_bindgen, JsError};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -78,6 +86,34 @@ pub struct Config {
     pub private_zkp_server_max_retires: Option<usize>,
 
     pub private_zkp_server_retry_interval: Option<u64>,
+
+    /// Maximum number of balance proofs generated concurrently during sync
+    pub receive_concurrency: Option<usize>,
+
+    /// Maximum number of retries for external API calls (default: 5)
+    pub retry_max_retries: Option<u32>,
+
+    /// Initial backoff delay in milliseconds, doubled on every retry (default: 1000)
+    pub retry_initial_delay_ms: Option<u64>,
+
+    /// Maximum random jitter in milliseconds added to each backoff delay (default: 0)
+    pub retry_jitter_ms: Option<u64>,
+
+    /// Number of balance prover health check retries before a sync gives up (default: 3)
+    pub balance_prover_warmup_retries: Option<u32>,
+
+    /// Number of L1 blocks to wait before a deposit is treated as settled (default: 0)
+    pub deposit_confirmations: Option<u64>,
+
+    /// Whether to compress the user data and tx data before encryption (default: false)
+    pub compress_payloads: Option<bool>,
+
+    /// Seconds before its expiry from which a block proposal is treated as expired (default: 5)
+    pub proposal_expiry_margin: Option<u64>,
+
+    /// Comma separated caps on the amount of a single transfer or withdrawal, each either
+    /// `<amount>` for every token or `<token_index>:<amount>` (default: no cap)
+    pub max_transfer_amount: Option<String>,
 }
 
 #[wasm_bindgen]
@@ -107,6 +143,15 @@ impl Config {
 
         private_zkp_server_max_retires: Option<usize>,
         private_zkp_server_retry_interval: Option<u64>,
+        receive_concurrency: Option<usize>,
+        retry_max_retries: Option<u32>,
+        retry_initial_delay_ms: Option<u64>,
+        retry_jitter_ms: Option<u64>,
+        balance_prover_warmup_retries: Option<u32>,
+        deposit_confirmations: Option<u64>,
+        compress_payloads: Option<bool>,
+        proposal_expiry_margin: Option<u64>,
+        max_transfer_amount: Option<String>,
     ) -> Config {
         Config {
             store_vault_server_url,
@@ -128,31 +173,56 @@ impl Config {
             use_s3,
             private_zkp_server_max_retires,
             private_zkp_server_retry_interval,
+            receive_concurrency,
+            retry_max_retries,
+            retry_initial_delay_ms,
+            retry_jitter_ms,
+            balance_prover_warmup_retries,
+            deposit_confirmations,
+            compress_payloads,
+            proposal_expiry_margin,
+            max_transfer_amount,
         }
     }
 }
 
-pub fn get_client(config: &Config) -> Client {
-    let block_builder = Box::new(BlockBuilderClient::new());
+pub fn get_client(config: &Config) -> Result<Client, JsError> {
+    let retry_config = RetryConfig::from_options(
+        config.retry_max_retries,
+        config.retry_initial_delay_ms,
+        config.retry_jitter_ms,
+    );
+    let block_builder = Box::new(BlockBuilderClient::new().with_retry_config(retry_config));
     let store_vault_server: Box<dyn StoreVaultClientInterface> = if config.use_s3 {
-        Box::new(S3StoreVaultClient::new(&config.store_vault_server_url))
+        Box::new(
+            S3StoreVaultClient::new(&config.store_vault_server_url).with_retry_config(retry_config),
+        )
     } else {
-        Box::new(StoreVaultServerClient::new(&config.store_vault_server_url))
+        Box::new(
+            StoreVaultServerClient::new(&config.store_vault_server_url)
+                .with_retry_config(retry_config),
+        )
     };
-    let validity_prover = Box::new(ValidityProverClient::new(&config.validity_prover_url));
+    let validity_prover = Box::new(
+        ValidityProverClient::new(&config.validity_prover_url).with_retry_config(retry_config),
+    );
     let balance_prover: Box<dyn BalanceProverClientInterface> = if config.use_private_zkp_server {
         let private_zkp_server_config = PrivateZKPServerConfig {
             max_retries: config.private_zkp_server_max_retires.unwrap_or(30),
             retry_interval: config.private_zkp_server_retry_interval.unwrap_or(5),
         };
-        Box::new(PrivateZKPServerClient::new(
-            &config.balance_prover_url,
-            &private_zkp_server_config,
-        ))
+        Box::new(
+            PrivateZKPServerClient::new(&config.balance_prover_url, &private_zkp_server_config)
+                .with_retry_config(retry_config),
+        )
     } else {
-        Box::new(BalanceProverClient::new(&config.balance_prover_url))
+        Box::new(
+            BalanceProverClient::new(&config.balance_prover_url).with_retry_config(retry_config),
+        )
     };
-    let withdrawal_server = Box::new(WithdrawalServerClient::new(&config.withdrawal_server_url));
+    let withdrawal_server = Box::new(
+        WithdrawalServerClient::new(&config.withdrawal_server_url).with_retry_config(retry_config),
+    );
 
     let client_config = ClientConfig {
         deposit_timeout: config.deposit_timeout,
@@ -161,6 +231,23 @@ pub fn get_client(config: &Config) -> Client {
         block_builder_query_wait_time: config.block_builder_query_wait_time,
         block_builder_query_interval: config.block_builder_query_interval,
         block_builder_query_limit: config.block_builder_query_limit,
+        receive_concurrency: config
+            .receive_concurrency
+            .unwrap_or(DEFAULT_RECEIVE_CONCURRENCY),
+        balance_prover_warmup_retries: config
+            .balance_prover_warmup_retries
+            .unwrap_or(DEFAULT_BALANCE_PROVER_WARMUP_RETRIES),
+        deposit_confirmations: config.deposit_confirmations.unwrap_or(0),
+        compress_payloads: config.compress_payloads.unwrap_or(false),
+        proposal_expiry_margin: config
+            .proposal_expiry_margin
+            .unwrap_or(DEFAULT_PROPOSAL_EXPIRY_MARGIN),
+        max_transfer_amount: config
+            .max_transfer_amount
+            .as_deref()
+            .map(|caps| caps.parse())
+            .transpose()
+            .map_err(|e| JsError::new(&format!("Invalid max transfer amount: {e}")))?,
     };
 
     let l1_provider = get_provider(&config.l1_rpc_url).unwrap();
@@ -180,7 +267,7 @@ pub fn get_client(config: &Config) -> Client {
         config.withdrawal_contract_address.parse().unwrap(),
     );
 
-    Client {
+    Ok(Client {
         block_builder,
         store_vault_server,
         validity_prover,
@@ -189,6 +276,7 @@ pub fn get_client(config: &Config) -> Client {
         liquidity_contract,
         rollup_contract,
         withdrawal_contract,
+        token_info_cache: TokenInfoCache::default(),
         config: client_config,
-    }
+    })
 }
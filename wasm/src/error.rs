@@ -0,0 +1,289 @@
+use intmax2_client_sdk::{
+    client::{
+        error::ClientError,
+        operation::current_operation_id,
+        strategy::error::{PendingTxInfo, StrategyError},
+        sync::error::SyncError,
+    },
+    external_api::contract::error::BlockchainError,
+};
+use intmax2_interfaces::api::error::ServerError;
+use wasm_bindgen::{prelude::wasm_bindgen, JsError, JsValue};
+
+/// Stable error codes exposed to JS as `err.code`. Codes are only ever added, never renamed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    InvalidInput,
+    PendingTx,
+    PendingReceives,
+    PendingWithdrawal,
+    InsufficientBalance,
+    DepositNotSettled,
+    ProverTimeout,
+    ProverUnavailable,
+    ValidityProverNotSynced,
+    FeeError,
+    ProposalExpired,
+    NetworkError,
+    ServerError,
+    BlockchainError,
+    TxRequestExpired,
+    DepositTxNotMined,
+    DepositNotObserved,
+    Aborted,
+    AmountExceedsLimit,
+    Unknown,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::InvalidInput => "INVALID_INPUT",
+            ErrorCode::PendingTx => "PENDING_TX",
+            ErrorCode::PendingReceives => "PENDING_RECEIVES",
+            ErrorCode::PendingWithdrawal => "PENDING_WITHDRAWAL",
+            ErrorCode::InsufficientBalance => "INSUFFICIENT_BALANCE",
+            ErrorCode::DepositNotSettled => "DEPOSIT_NOT_SETTLED",
+            ErrorCode::ProverTimeout => "PROVER_TIMEOUT",
+            ErrorCode::ProverUnavailable => "PROVER_UNAVAILABLE",
+            ErrorCode::ValidityProverNotSynced => "VALIDITY_PROVER_NOT_SYNCED",
+            ErrorCode::FeeError => "FEE_ERROR",
+            ErrorCode::ProposalExpired => "PROPOSAL_EXPIRED",
+            ErrorCode::NetworkError => "NETWORK_ERROR",
+            ErrorCode::ServerError => "SERVER_ERROR",
+            ErrorCode::BlockchainError => "BLOCKCHAIN_ERROR",
+            ErrorCode::TxRequestExpired => "TX_REQUEST_EXPIRED",
+            ErrorCode::DepositTxNotMined => "DEPOSIT_TX_NOT_MINED",
+            ErrorCode::DepositNotObserved => "DEPOSIT_NOT_OBSERVED",
+            ErrorCode::Aborted => "ABORTED",
+            ErrorCode::AmountExceedsLimit => "AMOUNT_EXCEEDS_LIMIT",
+            ErrorCode::Unknown => "UNKNOWN",
+        }
+    }
+
+    pub fn from_client_error(error: &ClientError) -> Self {
+        match error {
+            ClientError::ServerError(e) => Self::from_server_error(e),
+            ClientError::BlockchainError(e) => Self::from_blockchain_error(e),
+            ClientError::StrategyError(e) => Self::from_strategy_error(e),
+            ClientError::SyncError(e) => Self::from_sync_error(e),
+            ClientError::CannotSendTxByZeroBalanceAccount => Self::InsufficientBalance,
+            ClientError::TransferLenError(_) | ClientError::InvalidMiningDepositCriteria => {
+                Self::InvalidInput
+            }
+            ClientError::BlockBuilderFeeError(_) => Self::FeeError,
+            ClientError::ValidityProverError(_) => Self::ValidityProverNotSynced,
+            ClientError::ProposalExpired { .. } => Self::ProposalExpired,
+            ClientError::TxRequestExpired(_) => Self::TxRequestExpired,
+            ClientError::DepositTxNotMined { .. } => Self::DepositTxNotMined,
+            ClientError::DepositNotObserved { .. } => Self::DepositNotObserved,
+            ClientError::DepositWaitAborted => Self::Aborted,
+            ClientError::TransferAmountExceedsLimit { .. } => Self::AmountExceedsLimit,
+            _ => Self::Unknown,
+        }
+    }
+
+    pub fn from_sync_error(error: &SyncError) -> Self {
+        match error {
+            SyncError::ServerError(e) => Self::from_server_error(e),
+            SyncError::StrategyError(e) => Self::from_strategy_error(e),
+            SyncError::BlockchainError(e) => Self::from_blockchain_error(e),
+            SyncError::InsufficientBalance { .. } => Self::InsufficientBalance,
+            SyncError::PendingWithdrawalError(_) => Self::PendingWithdrawal,
+            SyncError::DepositIsNotSettled(_) => Self::DepositNotSettled,
+            SyncError::FeeError(_) => Self::FeeError,
+            SyncError::InvalidTransferError(_) | SyncError::SenderLastBlockNumberError { .. } => {
+                Self::InvalidInput
+            }
+            SyncError::BalanceProverUnavailable { .. } => Self::ProverUnavailable,
+            _ => Self::Unknown,
+        }
+    }
+
+    pub fn from_strategy_error(error: &StrategyError) -> Self {
+        match error {
+            StrategyError::ServerError(e) => Self::from_server_error(e),
+            StrategyError::BlockchainError(e) => Self::from_blockchain_error(e),
+            StrategyError::PendingTxError(_) => Self::PendingTx,
+            StrategyError::PendingReceivesError(_) => Self::PendingReceives,
+            StrategyError::BalanceInsufficientBeforeSync
+            | StrategyError::BalanceInsufficientDuringSync => Self::InsufficientBalance,
+            StrategyError::ValidityProverIsNotSynced(_) => Self::ValidityProverNotSynced,
+            _ => Self::Unknown,
+        }
+    }
+
+    pub fn from_server_error(error: &ServerError) -> Self {
+        match error {
+            ServerError::Timeout(_) => Self::ProverTimeout,
+            ServerError::NetworkError(_) => Self::NetworkError,
+            ServerError::ServerError(..) => Self::ServerError,
+            ServerError::InvalidRequest(_) | ServerError::MalformedUrl(_) => Self::InvalidInput,
+            _ => Self::Unknown,
+        }
+    }
+
+    pub fn from_blockchain_error(error: &BlockchainError) -> Self {
+        match error {
+            BlockchainError::InsufficientFunds(_) => Self::InsufficientBalance,
+            _ => Self::BlockchainError,
+        }
+    }
+}
+
+/// Error returned by the WASM functions. JS callers can branch on the stable `code` instead of
+/// matching on `message`.
+#[derive(Debug, Clone)]
+#[wasm_bindgen(getter_with_clone)]
+pub struct JsIntmaxError {
+    pub code: String,
+    pub message: String,
+    /// Unix timestamp by which a `PENDING_TX` or `PENDING_RECEIVES` error is expected to
+    /// resolve, after which the sync can be retried
+    pub retry_at: Option<u64>,
+    /// Id of the client operation that failed, to quote in a bug report. It is logged by the
+    /// client and by the servers the operation sent requests to.
+    pub operation_id: Option<String>,
+}
+
+impl JsIntmaxError {
+    /// The error takes the id of the operation it is created in, if any.
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code: code.as_str().to_string(),
+            message: message.into(),
+            retry_at: None,
+            operation_id: current_operation_id(),
+        }
+    }
+
+    fn with_pending_tx_info(mut self, info: Option<&PendingTxInfo>) -> Self {
+        self.retry_at = info.map(|info| info.expected_resolution_time);
+        self
+    }
+
+    /// Prefix the message with `context`, keeping the code.
+    pub fn context(self, context: &str) -> Self {
+        Self {
+            code: self.code,
+            message: format!("{context}: {}", self.message),
+            retry_at: self.retry_at,
+            operation_id: self.operation_id,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl JsIntmaxError {
+    #[wasm_bindgen(js_name = toString)]
+    pub fn to_js_string(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl std::fmt::Display for JsIntmaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for JsIntmaxError {}
+
+impl From<ClientError> for JsIntmaxError {
+    fn from(error: ClientError) -> Self {
+        Self::new(ErrorCode::from_client_error(&error), error.to_string())
+            .with_pending_tx_info(error.pending_tx_info())
+    }
+}
+
+impl From<SyncError> for JsIntmaxError {
+    fn from(error: SyncError) -> Self {
+        Self::new(ErrorCode::from_sync_error(&error), error.to_string())
+            .with_pending_tx_info(error.pending_tx_info())
+    }
+}
+
+impl From<StrategyError> for JsIntmaxError {
+    fn from(error: StrategyError) -> Self {
+        Self::new(ErrorCode::from_strategy_error(&error), error.to_string())
+            .with_pending_tx_info(error.pending_tx_info())
+    }
+}
+
+impl From<ServerError> for JsIntmaxError {
+    fn from(error: ServerError) -> Self {
+        Self::new(ErrorCode::from_server_error(&error), error.to_string())
+    }
+}
+
+impl From<BlockchainError> for JsIntmaxError {
+    fn from(error: BlockchainError) -> Self {
+        Self::new(ErrorCode::from_blockchain_error(&error), error.to_string())
+    }
+}
+
+/// The `JsError`s raised inside this crate come from parsing the arguments.
+impl From<JsError> for JsIntmaxError {
+    fn from(error: JsError) -> Self {
+        let error = js_sys::Error::from(JsValue::from(error));
+        Self::new(ErrorCode::InvalidInput, String::from(error.message()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use intmax2_client_sdk::client::strategy::error::PendingTxKind;
+    use intmax2_zkp::ethereum_types::bytes32::Bytes32;
+
+    use super::*;
+
+    #[test]
+    fn test_nested_errors_map_to_innermost_code() {
+        let error = ClientError::SyncError(SyncError::StrategyError(
+            StrategyError::PendingTxError(PendingTxInfo {
+                kind: PendingTxKind::Send,
+                tx_digest: Bytes32::default(),
+                block_number: None,
+                expected_resolution_time: 100,
+            }),
+        ));
+        let js_error = JsIntmaxError::from(error);
+        assert_eq!(js_error.code, "PENDING_TX");
+        assert_eq!(js_error.retry_at, Some(100));
+
+        let error = ClientError::ServerError(ServerError::Timeout("proof".to_string()));
+        assert_eq!(
+            ErrorCode::from_client_error(&error),
+            ErrorCode::ProverTimeout
+        );
+    }
+
+    #[test]
+    fn test_deposit_wait_errors() {
+        let error = ClientError::DepositNotObserved {
+            tx_hash: Bytes32::default(),
+            l1_block_number: 10,
+            timeout: 60,
+        };
+        let js_error = JsIntmaxError::from(error);
+        assert_eq!(js_error.code, "DEPOSIT_NOT_OBSERVED");
+        assert!(js_error.message.contains("The deposit is safe"));
+
+        let error = ClientError::DepositTxNotMined {
+            tx_hash: Bytes32::default(),
+            timeout: 60,
+        };
+        assert_eq!(
+            ErrorCode::from_client_error(&error),
+            ErrorCode::DepositTxNotMined
+        );
+    }
+
+    #[test]
+    fn test_context_keeps_code() {
+        let error = JsIntmaxError::new(ErrorCode::InsufficientBalance, "not enough")
+            .context("failed to send tx request");
+        assert_eq!(error.code, "INSUFFICIENT_BALANCE");
+        assert_eq!(error.message, "failed to send tx request: not enough");
+    }
+}
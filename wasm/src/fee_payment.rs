@@ -19,7 +19,7 @@ pub async fn generate_withdrawal_transfers(
     with_claim_fee: bool,
 ) -> Result<JsWithdrawalTransfers, JsError> {
     init_logger();
-    let client = get_client(config);
+    let client = get_client(config)?;
     let withdrawal_transfer = Transfer::try_from(withdrawal_transfer.clone())?;
     let withdrawal_transfers =
         intmax2_client_sdk::client::fee_payment::generate_withdrawal_transfers(
@@ -1,5 +1,6 @@
 use crate::{
     client::{get_client, Config},
+    error::JsIntmaxError,
     init_logger,
     js_types::{
         cursor::{JsMetaDataCursor, JsMetaDataCursorResponse},
@@ -8,7 +9,7 @@ use crate::{
     utils::str_privkey_to_keyset,
 };
 use intmax2_interfaces::api::store_vault_server::types::MetaDataCursor;
-use wasm_bindgen::{prelude::wasm_bindgen, JsError};
+use wasm_bindgen::prelude::wasm_bindgen;
 
 #[wasm_bindgen(getter_with_clone)]
 pub struct JsDepositHistory {
@@ -33,12 +34,12 @@ pub async fn fetch_deposit_history(
     config: &Config,
     private_key: &str,
     cursor: &JsMetaDataCursor,
-) -> Result<JsDepositHistory, JsError> {
+) -> Result<JsDepositHistory, JsIntmaxError> {
     init_logger();
 
     let cursor: MetaDataCursor = cursor.clone().try_into()?;
     let key = str_privkey_to_keyset(private_key)?;
-    let client = get_client(config);
+    let client = get_client(config)?;
     let (history, cursor_response) = client.fetch_deposit_history(key, &cursor).await?;
     let js_history = history.into_iter().map(JsDepositEntry::from).collect();
     let js_cursor_response = JsMetaDataCursorResponse::from(cursor_response);
@@ -53,12 +54,12 @@ pub async fn fetch_transfer_history(
     config: &Config,
     private_key: &str,
     cursor: &JsMetaDataCursor,
-) -> Result<JsTransferHistory, JsError> {
+) -> Result<JsTransferHistory, JsIntmaxError> {
     init_logger();
 
     let cursor: MetaDataCursor = cursor.clone().try_into()?;
     let key = str_privkey_to_keyset(private_key)?;
-    let client = get_client(config);
+    let client = get_client(config)?;
     let (history, cursor_response) = client.fetch_transfer_history(key, &cursor).await?;
     let js_history = history.into_iter().map(JsTransferEntry::from).collect();
     let js_cursor_response = JsMetaDataCursorResponse::from(cursor_response);
@@ -73,12 +74,12 @@ pub async fn fetch_tx_history(
     config: &Config,
     private_key: &str,
     cursor: &JsMetaDataCursor,
-) -> Result<JsTxHistory, JsError> {
+) -> Result<JsTxHistory, JsIntmaxError> {
     init_logger();
 
     let cursor: MetaDataCursor = cursor.clone().try_into()?;
     let key = str_privkey_to_keyset(private_key)?;
-    let client = get_client(config);
+    let client = get_client(config)?;
     let (history, cursor_response) = client.fetch_tx_history(key, &cursor).await?;
     let js_history = history.into_iter().map(JsTxEntry::from).collect();
     let js_cursor_response = JsMetaDataCursorResponse::from(cursor_response);
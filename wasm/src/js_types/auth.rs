@@ -61,6 +61,8 @@ pub struct JsAuth {
     pub pubkey: String, // hex string
     pub expiry: u64,
     pub signature: JsFlatG2, // hex string
+    pub algorithm: u8,
+    pub topic_scope: Option<Vec<String>>,
 }
 
 impl From<Auth> for JsAuth {
@@ -69,6 +71,8 @@ impl From<Auth> for JsAuth {
             pubkey: auth.pubkey.to_hex(),
             expiry: auth.expiry,
             signature: JsFlatG2::from(auth.signature),
+            algorithm: auth.algorithm,
+            topic_scope: auth.topic_scope,
         }
     }
 }
@@ -79,6 +83,8 @@ impl From<&Auth> for JsAuth {
             pubkey: auth.pubkey.to_hex(),
             expiry: auth.expiry,
             signature: JsFlatG2::from(&auth.signature),
+            algorithm: auth.algorithm,
+            topic_scope: auth.topic_scope.clone(),
         }
     }
 }
@@ -91,6 +97,8 @@ impl TryFrom<JsAuth> for Auth {
             pubkey: U256::from_hex(&js_auth.pubkey).map_err(|_| "Invalid hex string")?,
             expiry: js_auth.expiry,
             signature: FlatG2::try_from(js_auth.signature)?,
+            algorithm: js_auth.algorithm,
+            topic_scope: js_auth.topic_scope,
         })
     }
 }
@@ -181,6 +189,8 @@ mod tests {
             pubkey: U256::from(12345),
             expiry: 999999,
             signature: FlatG2([U256::from(1), U256::from(2), U256::from(3), U256::from(4)]),
+            algorithm: 0,
+            topic_scope: Some(vec!["transfer".to_string()]),
         };
 
         let js_auth: JsAuth = JsAuth::from(auth.clone());
@@ -195,6 +205,8 @@ mod tests {
             pubkey: "not_hex".to_string(),
             expiry: 123,
             signature: JsFlatG2::new((1..=4).map(|n| format!("{n:#066x}")).collect()),
+            algorithm: 0,
+            topic_scope: None,
         };
 
         let result = Auth::try_from(js_auth);
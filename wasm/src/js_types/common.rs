@@ -1,6 +1,11 @@
-use intmax2_client_sdk::client::strategy::mining::Mining;
+use intmax2_client_sdk::client::{
+    backup::HistoryBackupChunk,
+    strategy::mining::{Mining, MiningPrediction},
+};
 use intmax2_interfaces::{
-    api::withdrawal_server::interface::{ClaimInfo, ContractWithdrawal, WithdrawalInfo},
+    api::withdrawal_server::interface::{
+        ClaimInfo, ContractWithdrawal, WithdrawalInfo, WithdrawalSummary,
+    },
     data::meta_data::MetaData,
 };
 use intmax2_zkp::{
@@ -282,6 +287,22 @@ impl From<MetaData> for JsMetaData {
     }
 }
 
+#[derive(Debug, Clone)]
+#[wasm_bindgen(getter_with_clone)]
+pub struct JsHistoryBackupChunk {
+    pub content: String, // CSV, or the encrypted chunk if encrypted to an RSA public key
+    pub cursor: JsMetaData, // pass this to resume the backup after this chunk
+}
+
+impl From<HistoryBackupChunk> for JsHistoryBackupChunk {
+    fn from(chunk: HistoryBackupChunk) -> Self {
+        Self {
+            content: chunk.content,
+            cursor: chunk.cursor.into(),
+        }
+    }
+}
+
 impl TryFrom<&JsMetaData> for MetaData {
     type Error = JsError;
 
@@ -321,6 +342,26 @@ impl From<WithdrawalInfo> for JsWithdrawalInfo {
     }
 }
 
+#[derive(Debug, Clone)]
+#[wasm_bindgen(getter_with_clone)]
+pub struct JsWithdrawalSummary {
+    pub token_index: u32,
+    pub pending_amount: String,
+    pub completed_amount: String,
+    pub count: u64,
+}
+
+impl From<WithdrawalSummary> for JsWithdrawalSummary {
+    fn from(summary: WithdrawalSummary) -> Self {
+        Self {
+            token_index: summary.token_index,
+            pending_amount: summary.pending_amount.to_string(),
+            completed_amount: summary.completed_amount.to_string(),
+            count: summary.count,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 #[wasm_bindgen(getter_with_clone)]
 pub struct JsClaimInfo {
@@ -373,6 +414,7 @@ pub struct JsMining {
     pub block: Option<JsBlock>,
     pub maturity: Option<u64>,
     pub status: String,
+    pub prediction: Option<JsMiningPrediction>,
 }
 
 impl From<Mining> for JsMining {
@@ -383,6 +425,25 @@ impl From<Mining> for JsMining {
             block: mining.block.map(|b| b.into()),
             maturity: mining.maturity,
             status: mining.status.to_string(),
+            prediction: mining.prediction.map(JsMiningPrediction::from),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[wasm_bindgen(getter_with_clone)]
+pub struct JsMiningPrediction {
+    pub claimable_at: u64,
+    pub earliest_block_number: u32,
+    pub amount: String, // 10 base string
+}
+
+impl From<MiningPrediction> for JsMiningPrediction {
+    fn from(prediction: MiningPrediction) -> Self {
+        Self {
+            claimable_at: prediction.claimable_at,
+            earliest_block_number: prediction.earliest_block_number,
+            amount: prediction.amount.to_string(),
         }
     }
 }
@@ -468,4 +529,17 @@ mod tests {
         assert!(hash.is_ok());
         assert_eq!(hash.unwrap().len(), 66); // 0x + 64 hex digits
     }
+
+    #[test]
+    fn test_mining_prediction_conversion() {
+        let prediction = MiningPrediction {
+            claimable_at: 1_700_000_000,
+            earliest_block_number: 42,
+            amount: U256::from(1000),
+        };
+        let js = JsMiningPrediction::from(prediction);
+        assert_eq!(js.claimable_at, 1_700_000_000);
+        assert_eq!(js.earliest_block_number, 42);
+        assert_eq!(js.amount, "1000");
+    }
 }
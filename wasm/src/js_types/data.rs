@@ -1,7 +1,16 @@
-use intmax2_client_sdk::client::client::{DepositResult, TxResult};
+use intmax2_client_sdk::client::{
+    audit::{AuditEntry, BalanceProofChainBreak, ConsistencyReport},
+    balance_at_block::BalanceAtBlock,
+    client::{DepositResult, TxResult},
+    receipt::ValidatedTransferReceipt,
+    strategy::strategy::TokenBalanceBreakdown,
+    sync::progress::SyncWorkEstimate,
+    token_info::TokenInfo,
+};
 use intmax2_interfaces::data::{
     deposit_data::DepositData,
     meta_data::MetaData,
+    receive_retry::DeferredReceive,
     transfer_data::TransferData,
     tx_data::TxData,
     user_data::{Balances, UserData},
@@ -51,6 +60,10 @@ impl From<DepositData> for JsDepositData {
 pub struct JsTransferData {
     pub sender: String,
     pub transfer: JsTransfer,
+    // only set by receipt validation, if the sender is bound by the sender's balance proof
+    pub verified_sender: Option<String>,
+    // only set by receipt validation
+    pub block_number: Option<u32>,
 }
 
 impl From<TransferData> for JsTransferData {
@@ -58,6 +71,18 @@ impl From<TransferData> for JsTransferData {
         Self {
             sender: transfer_data.sender.to_hex(),
             transfer: transfer_data.transfer.into(),
+            verified_sender: None,
+            block_number: None,
+        }
+    }
+}
+
+impl From<ValidatedTransferReceipt> for JsTransferData {
+    fn from(receipt: ValidatedTransferReceipt) -> Self {
+        Self {
+            verified_sender: receipt.verified_sender.map(|s| s.to_hex()),
+            block_number: Some(receipt.block_number),
+            ..receipt.data.into()
         }
     }
 }
@@ -105,6 +130,8 @@ pub struct JsDepositResult {
     pub deposit_data: JsDepositData,
     pub deposit_digest: String,
     pub backup_csv: String,
+    /// Id of the operation, to quote in a bug report
+    pub operation_id: String,
 }
 
 impl From<DepositResult> for JsDepositResult {
@@ -113,6 +140,7 @@ impl From<DepositResult> for JsDepositResult {
             deposit_data: deposit_result.deposit_data.into(),
             deposit_digest: deposit_result.deposit_digest.to_string(),
             backup_csv: deposit_result.backup_csv,
+            operation_id: deposit_result.operation_id,
         }
     }
 }
@@ -125,6 +153,10 @@ pub struct JsTxResult {
     pub tx_data: JsTxData,
     pub transfer_data_vec: Vec<JsTransferData>,
     pub backup_csv: String,
+    /// URL of the block builder that handled the tx
+    pub block_builder_url: String,
+    /// Id of the operation, to quote in a bug report
+    pub operation_id: String,
 }
 
 impl From<TxResult> for JsTxResult {
@@ -139,6 +171,8 @@ impl From<TxResult> for JsTxResult {
                 .map(Into::into)
                 .collect(),
             backup_csv: tx_result.backup_csv,
+            block_builder_url: tx_result.block_builder_url,
+            operation_id: tx_result.operation_id,
         }
     }
 }
@@ -178,6 +212,35 @@ pub struct JsUserData {
 
     /// Digests of processed withdrawals
     pub processed_withdrawal_digests: Vec<String>,
+
+    /// Spendable and pending amounts per token, ordered by token index
+    pub balance_breakdown: Vec<JsTokenBalanceBreakdown>,
+
+    /// Transfers and withdrawals that could not be received after repeated retries
+    pub stuck_receives: Vec<JsDeferredReceive>,
+}
+
+#[derive(Debug, Clone)]
+#[wasm_bindgen(getter_with_clone)]
+pub struct JsDeferredReceive {
+    pub digest: String, // hex string
+    /// "transfer" or "withdrawal"
+    pub data_type: String,
+    pub block_number: u32,
+    pub attempts: u32,
+    pub last_error: String,
+}
+
+impl From<DeferredReceive> for JsDeferredReceive {
+    fn from(receive: DeferredReceive) -> Self {
+        Self {
+            digest: receive.digest.to_hex(),
+            data_type: receive.data_type.to_string(),
+            block_number: receive.block_number,
+            attempts: receive.attempts,
+            last_error: receive.last_error,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -194,6 +257,62 @@ pub struct TokenBalance {
     pub is_insufficient: bool,
 }
 
+#[derive(Debug, Clone)]
+#[wasm_bindgen(getter_with_clone)]
+pub struct JsTokenBalanceBreakdown {
+    pub token_index: u32,
+
+    /// Amount that can be sent now. 10 base string
+    pub spendable: String,
+
+    /// Unconfirmed deposits and transfers not received yet. 10 base string
+    pub incoming_pending: String,
+
+    /// Amount of sent txs, including fees, that are not finalized yet. 10 base string
+    pub outgoing_pending: String,
+
+    /// Flag indicating whether the balance is insufficient for that token index.
+    pub is_insufficient: bool,
+}
+
+impl From<TokenBalanceBreakdown> for JsTokenBalanceBreakdown {
+    fn from(breakdown: TokenBalanceBreakdown) -> Self {
+        Self {
+            token_index: breakdown.token_index,
+            spendable: breakdown.spendable.to_string(),
+            incoming_pending: breakdown.incoming_pending.to_string(),
+            outgoing_pending: breakdown.outgoing_pending.to_string(),
+            is_insufficient: breakdown.is_insufficient,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[wasm_bindgen(getter_with_clone)]
+pub struct JsTokenInfo {
+    pub token_index: u32,
+    pub token_type: u8,
+    pub token_address: String, // hex string
+    pub token_id: String,      // 10 base string
+    /// Decimals of the token. Undefined if the ERC20 token does not implement `decimals()`.
+    pub decimals: Option<u8>,
+    /// Symbol of the token. Undefined for NFTs and ERC20 tokens without `symbol()`.
+    pub symbol: Option<String>,
+}
+
+impl From<TokenInfo> for JsTokenInfo {
+    fn from(token_info: TokenInfo) -> Self {
+        Self {
+            token_index: token_info.token_index,
+            token_type: token_info.token_type as u8,
+            token_address: token_info.address.to_hex(),
+            token_id: token_info.token_id.to_string(),
+            decimals: token_info.decimals,
+            symbol: token_info.symbol,
+        }
+    }
+}
+
 fn extract_timestamp(opt: &Option<MetaData>) -> u64 {
     opt.as_ref().map(|x| x.timestamp).unwrap_or(0)
 }
@@ -202,8 +321,8 @@ fn convert_bytes32_vec_to_hex(digests: Vec<Bytes32>) -> Vec<String> {
     digests.into_iter().map(|x| x.to_hex()).collect()
 }
 
-impl From<UserData> for JsUserData {
-    fn from(user_data: UserData) -> Self {
+impl JsUserData {
+    pub fn new(user_data: UserData, balance_breakdown: Vec<TokenBalanceBreakdown>) -> Self {
         Self {
             pubkey: user_data.pubkey.to_hex(),
             balances: balances_to_token_balances(user_data.balances()),
@@ -228,6 +347,153 @@ impl From<UserData> for JsUserData {
             processed_withdrawal_digests: convert_bytes32_vec_to_hex(
                 user_data.withdrawal_status.processed_digests,
             ),
+            balance_breakdown: balance_breakdown.into_iter().map(Into::into).collect(),
+            stuck_receives: user_data
+                .stuck_receives
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[wasm_bindgen(getter_with_clone)]
+pub struct JsSyncWorkEstimate {
+    /// Settled deposits to receive
+    pub num_deposits: u32,
+
+    /// Settled transfers to receive
+    pub num_transfers: u32,
+
+    /// Settled txs to incorporate
+    pub num_txs: u32,
+
+    /// Deposits that are not settled yet, skipped by the sync
+    pub num_pending_deposits: u32,
+
+    /// Transfers that are not settled yet, skipped by the sync
+    pub num_pending_transfers: u32,
+
+    /// Rough upper bound of the proofs the sync generates
+    pub estimated_proofs: u32,
+
+    /// Number of items the sync processes, e.g. for a progress bar
+    pub num_actions: u32,
+}
+
+impl From<SyncWorkEstimate> for JsSyncWorkEstimate {
+    fn from(estimate: SyncWorkEstimate) -> Self {
+        Self {
+            num_deposits: estimate.num_deposits as u32,
+            num_transfers: estimate.num_transfers as u32,
+            num_txs: estimate.num_txs as u32,
+            num_pending_deposits: estimate.num_pending_deposits as u32,
+            num_pending_transfers: estimate.num_pending_transfers as u32,
+            estimated_proofs: estimate.estimated_proofs as u32,
+            num_actions: estimate.num_actions() as u32,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[wasm_bindgen(getter_with_clone)]
+pub struct JsBalanceAtBlock {
+    /// Block number of the balance proof the balances are anchored to
+    pub proof_block_number: u32,
+
+    /// Balances as of the proof block, estimated from the settled history unless the proof block
+    /// is the latest one
+    pub balances: Vec<TokenBalance>,
+}
+
+impl From<BalanceAtBlock> for JsBalanceAtBlock {
+    fn from(balance_at_block: BalanceAtBlock) -> Self {
+        Self {
+            proof_block_number: balance_at_block.proof_block_number,
+            balances: balances_to_token_balances(balance_at_block.balances),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[wasm_bindgen(getter_with_clone)]
+pub struct JsAuditEntry {
+    /// "deposit", "transfer" or "tx"
+    pub data_type: String,
+    pub digest: String,
+    pub block_number: Option<u32>,
+    pub reason: String,
+}
+
+impl From<AuditEntry> for JsAuditEntry {
+    fn from(entry: AuditEntry) -> Self {
+        Self {
+            data_type: entry.data_type.to_string(),
+            digest: entry.digest.to_hex(),
+            block_number: entry.block_number,
+            reason: entry.reason,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[wasm_bindgen(getter_with_clone)]
+pub struct JsBalanceProofChainBreak {
+    /// Digest of the tx, or undefined if the latest balance proof is older than the last tx
+    pub tx_digest: Option<String>,
+    pub block_number: u32,
+    pub prev_balance_proof_block_number: Option<u32>,
+    pub prev_tx_block_number: Option<u32>,
+}
+
+impl From<BalanceProofChainBreak> for JsBalanceProofChainBreak {
+    fn from(chain_break: BalanceProofChainBreak) -> Self {
+        Self {
+            tx_digest: chain_break.tx_digest.map(|digest| digest.to_hex()),
+            block_number: chain_break.block_number,
+            prev_balance_proof_block_number: chain_break.prev_balance_proof_block_number,
+            prev_tx_block_number: chain_break.prev_tx_block_number,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[wasm_bindgen(getter_with_clone)]
+pub struct JsConsistencyReport {
+    pub validity_prover_block_number: u32,
+    pub balance_proof_block_number: Option<u32>,
+
+    /// Processed entries whose block the validity prover does not know about
+    pub unknown_block_entries: Vec<JsAuditEntry>,
+
+    /// Processed entries that could not be fetched or decrypted from the store vault
+    pub missing_entries: Vec<JsAuditEntry>,
+
+    /// Breaks in the balance proof chain formed by the sent txs
+    pub balance_proof_chain_breaks: Vec<JsBalanceProofChainBreak>,
+
+    /// Whether all of the above lists are empty
+    pub is_consistent: bool,
+}
+
+impl From<ConsistencyReport> for JsConsistencyReport {
+    fn from(report: ConsistencyReport) -> Self {
+        Self {
+            validity_prover_block_number: report.validity_prover_block_number,
+            balance_proof_block_number: report.balance_proof_block_number,
+            is_consistent: report.is_consistent(),
+            unknown_block_entries: report
+                .unknown_block_entries
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            missing_entries: report.missing_entries.into_iter().map(Into::into).collect(),
+            balance_proof_chain_breaks: report
+                .balance_proof_chain_breaks
+                .into_iter()
+                .map(Into::into)
+                .collect(),
         }
     }
 }
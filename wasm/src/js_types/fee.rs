@@ -1,5 +1,5 @@
 use intmax2_client_sdk::client::{
-    client::{FeeQuote, TransferFeeQuote},
+    client::{FeeQuote, TokenFeeQuote, TransferFeeQuote, TxSimulationResult},
     fee_payment::WithdrawalTransfers,
 };
 use intmax2_interfaces::api::block_builder::interface::{BlockBuilderFeeInfo, Fee};
@@ -54,6 +54,7 @@ pub struct JsTransferFeeQuote {
     pub fee: Option<JsFee>,
     pub collateral_fee: Option<JsFee>,
     pub block_builder_address: String,
+    pub fee_contributions: Option<Vec<JsFee>>,
 }
 
 impl From<TransferFeeQuote> for JsTransferFeeQuote {
@@ -63,6 +64,9 @@ impl From<TransferFeeQuote> for JsTransferFeeQuote {
             fee: fee_quote.fee.map(JsFee::from),
             collateral_fee: fee_quote.collateral_fee.map(JsFee::from),
             block_builder_address: fee_quote.block_builder_address.to_hex(),
+            fee_contributions: fee_quote
+                .fee_contributions
+                .map(|fees| fees.into_iter().map(JsFee::from).collect()),
         }
     }
 }
@@ -84,16 +88,65 @@ impl TryFrom<JsTransferFeeQuote> for TransferFeeQuote {
                 .transpose()?,
             block_builder_address: Address::from_hex(&js_fee_quote.block_builder_address)
                 .map_err(|e| JsError::new(&format!("Invalid block builder address: {e}")))?,
+            fee_contributions: js_fee_quote
+                .fee_contributions
+                .map(|fees| {
+                    fees.into_iter()
+                        .map(JsFee::try_into)
+                        .collect::<Result<Vec<_>, _>>()
+                })
+                .transpose()?,
         })
     }
 }
 
+/// Transfer fee quote in one of the requested fee tokens.
+#[derive(Debug, Clone)]
+#[wasm_bindgen(getter_with_clone)]
+pub struct JsTokenFeeQuote {
+    pub token_index: u32,
+    /// Whether the block builder accepts the token as fee
+    pub is_accepted: bool,
+    /// Quote to pass to `send_tx_request`, set if the token is accepted
+    pub fee_quote: Option<JsTransferFeeQuote>,
+}
+
+impl From<TokenFeeQuote> for JsTokenFeeQuote {
+    fn from(quote: TokenFeeQuote) -> Self {
+        Self {
+            token_index: quote.token_index,
+            is_accepted: quote.fee_quote.is_some(),
+            fee_quote: quote.fee_quote.map(JsTransferFeeQuote::from),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[wasm_bindgen(getter_with_clone)]
+pub struct JsTxSimulationResult {
+    pub fee_quote: JsTransferFeeQuote,
+    pub is_sufficient_balance: bool,
+    pub balance_error: Option<String>,
+}
+
+impl From<TxSimulationResult> for JsTxSimulationResult {
+    fn from(result: TxSimulationResult) -> Self {
+        Self {
+            fee_quote: result.fee_quote.into(),
+            is_sufficient_balance: result.is_sufficient_balance,
+            balance_error: result.balance_error,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 #[wasm_bindgen(getter_with_clone)]
 pub struct JsFeeQuote {
     pub beneficiary: Option<String>,
     pub fee: Option<JsFee>,
     pub collateral_fee: Option<JsFee>,
+    pub is_faster_mining: Option<bool>,
+    pub alternative_mode_fee: Option<JsFee>,
 }
 
 impl From<FeeQuote> for JsFeeQuote {
@@ -102,6 +155,8 @@ impl From<FeeQuote> for JsFeeQuote {
             beneficiary: fee_quote.beneficiary.map(|b| b.to_hex()),
             fee: fee_quote.fee.map(JsFee::from),
             collateral_fee: fee_quote.collateral_fee.map(JsFee::from),
+            is_faster_mining: fee_quote.is_faster_mining,
+            alternative_mode_fee: fee_quote.alternative_mode_fee.map(JsFee::from),
         }
     }
 }
@@ -174,13 +229,16 @@ impl TryFrom<JsWithdrawalTransfers> for WithdrawalTransfers {
 mod fee_tests {
     use std::str::FromStr;
 
-    use intmax2_client_sdk::client::{client::FeeQuote, fee_payment::WithdrawalTransfers};
+    use intmax2_client_sdk::client::{
+        client::{FeeQuote, TokenFeeQuote, TransferFeeQuote},
+        fee_payment::WithdrawalTransfers,
+    };
     use intmax2_interfaces::api::block_builder::interface::{BlockBuilderFeeInfo, Fee};
     use intmax2_zkp::ethereum_types::{address::Address, u256::U256};
 
     use crate::js_types::{
         common::{JsGenericAddress, JsTransfer},
-        fee::{JsFee, JsFeeInfo, JsFeeQuote, JsWithdrawalTransfers},
+        fee::{JsFee, JsFeeInfo, JsFeeQuote, JsTokenFeeQuote, JsWithdrawalTransfers},
     };
 
     fn fee(amount: &str, token_index: u32) -> Fee {
@@ -222,6 +280,8 @@ mod fee_tests {
             ),
             fee: Some(fee("100", 1)),
             collateral_fee: Some(fee("200", 2)),
+            is_faster_mining: Some(true),
+            alternative_mode_fee: Some(fee("50", 1)),
         };
 
         let js_quote = JsFeeQuote::from(quote);
@@ -234,6 +294,32 @@ mod fee_tests {
         assert_eq!(js_quote.fee.as_ref().unwrap().token_index, 1);
         assert_eq!(js_quote.collateral_fee.as_ref().unwrap().amount, "200");
         assert_eq!(js_quote.collateral_fee.as_ref().unwrap().token_index, 2);
+        assert_eq!(js_quote.is_faster_mining, Some(true));
+        assert_eq!(js_quote.alternative_mode_fee.as_ref().unwrap().amount, "50");
+    }
+
+    #[test]
+    fn test_tokenfeequote_to_jstokenfeequote() {
+        let accepted = JsTokenFeeQuote::from(TokenFeeQuote {
+            token_index: 1,
+            fee_quote: Some(TransferFeeQuote {
+                beneficiary: Some(U256::from(1)),
+                fee: Some(fee("100", 1)),
+                collateral_fee: None,
+                block_builder_address: Address::default(),
+                fee_contributions: None,
+            }),
+        });
+        assert!(accepted.is_accepted);
+        assert_eq!(accepted.fee_quote.unwrap().fee.unwrap().amount, "100");
+
+        let rejected = JsTokenFeeQuote::from(TokenFeeQuote {
+            token_index: 2,
+            fee_quote: None,
+        });
+        assert_eq!(rejected.token_index, 2);
+        assert!(!rejected.is_accepted);
+        assert!(rejected.fee_quote.is_none());
     }
 
     #[test]
@@ -1,4 +1,7 @@
-use intmax2_client_sdk::client::history::{EntryStatus, HistoryEntry};
+use intmax2_client_sdk::client::{
+    backup::BackupHistoryEntry,
+    history::{EntryStatus, HistoryEntry},
+};
 use intmax2_interfaces::data::{
     deposit_data::DepositData, transfer_data::TransferData, tx_data::TxData,
 };
@@ -96,3 +99,42 @@ impl From<HistoryEntry<TxData>> for JsTxEntry {
         }
     }
 }
+
+/// A history entry decoded offline from a backup chunk. Exactly one of `deposit`, `transfer`
+/// and `tx` is set, according to `entry_type`.
+#[derive(Clone, Debug)]
+#[wasm_bindgen(getter_with_clone)]
+pub struct JsHistoryEntry {
+    /// "deposit", "transfer", "withdrawal" or "tx". Withdrawals are set in `transfer`.
+    pub entry_type: String,
+    pub meta: JsMetaData,
+    pub deposit: Option<JsDepositData>,
+    pub transfer: Option<JsTransferData>,
+    pub tx: Option<JsTxData>,
+}
+
+impl From<BackupHistoryEntry> for JsHistoryEntry {
+    fn from(entry: BackupHistoryEntry) -> Self {
+        let (entry_type, meta) = match &entry {
+            BackupHistoryEntry::Deposit(meta, _) => ("deposit", meta.clone()),
+            BackupHistoryEntry::Transfer(meta, _) => ("transfer", meta.clone()),
+            BackupHistoryEntry::Withdrawal(meta, _) => ("withdrawal", meta.clone()),
+            BackupHistoryEntry::Tx(meta, _) => ("tx", meta.clone()),
+        };
+        let mut js_entry = Self {
+            entry_type: entry_type.to_owned(),
+            meta: meta.into(),
+            deposit: None,
+            transfer: None,
+            tx: None,
+        };
+        match entry {
+            BackupHistoryEntry::Deposit(_, data) => js_entry.deposit = Some(data.into()),
+            BackupHistoryEntry::Transfer(_, data) | BackupHistoryEntry::Withdrawal(_, data) => {
+                js_entry.transfer = Some(data.into())
+            }
+            BackupHistoryEntry::Tx(_, data) => js_entry.tx = Some(data.into()),
+        }
+        js_entry
+    }
+}
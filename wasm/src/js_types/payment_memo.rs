@@ -10,6 +10,20 @@ pub struct JsPaymentMemoEntry {
     pub memo: String,
 }
 
+#[wasm_bindgen]
+impl JsPaymentMemoEntry {
+    /// Memo for the transfer at `transfer_index`. The transfer may have a zero amount to send
+    /// the memo only.
+    #[wasm_bindgen(constructor)]
+    pub fn new(transfer_index: u32, topic: String, memo: String) -> Self {
+        Self {
+            transfer_index,
+            topic,
+            memo,
+        }
+    }
+}
+
 impl From<PaymentMemoEntry> for JsPaymentMemoEntry {
     fn from(payment_memo_entry: PaymentMemoEntry) -> Self {
         Self {
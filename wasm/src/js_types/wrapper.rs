@@ -35,6 +35,24 @@ impl JsTxRequestMemo {
         let memo = self.to_tx_request_memo()?;
         Ok(memo.is_registration_block)
     }
+
+    /// The block builder that accepted the request
+    pub fn block_builder_url(&self) -> Result<String, JsError> {
+        let memo = self.to_tx_request_memo()?;
+        Ok(memo.block_builder_url)
+    }
+
+    /// Unix timestamp after which the block builder drops the request, if any
+    pub fn valid_until(&self) -> Result<Option<u64>, JsError> {
+        let memo = self.to_tx_request_memo()?;
+        Ok(memo.valid_until)
+    }
+
+    /// Id of the operation that sent the request, to quote in a bug report
+    pub fn operation_id(&self) -> Result<String, JsError> {
+        let memo = self.to_tx_request_memo()?;
+        Ok(memo.operation_id)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -63,4 +81,15 @@ impl JsBlockProposal {
         let proposal = self.to_block_proposal()?;
         Ok(proposal.block_sign_payload.tx_tree_root.to_string())
     }
+
+    /// JSON of the proposal, to hand it to an offline signer
+    pub fn to_json(&self) -> String {
+        self.data.clone()
+    }
+
+    pub fn from_json(json: &str) -> Result<JsBlockProposal, JsError> {
+        let proposal: BlockProposal = serde_json::from_str(json)
+            .map_err(|e| JsError::new(&format!("failed to parse block proposal {e}")))?;
+        Ok(Self::from_block_proposal(&proposal))
+    }
 }
@@ -1,30 +1,49 @@
 use client::{get_client, Config};
+use error::{ErrorCode, JsIntmaxError};
 use intmax2_client_sdk::client::{
+    backup::{
+        decode_history_backup_chunk as inner_decode_history_backup_chunk, EncryptedBackupChunk,
+    },
     client::{PaymentMemoEntry, TransferFeeQuote},
     key_from_eth::generate_intmax_account_from_eth_key as inner_generate_intmax_account_from_eth_key,
+    offline_signing::{sign_proposal as inner_sign_proposal, SignedProposal},
+    operation::run_operation,
+};
+use intmax2_interfaces::{
+    api::store_vault_server::types::MetaDataCursor,
+    data::{deposit_data::TokenType, meta_data::MetaData},
 };
-use intmax2_interfaces::data::deposit_data::TokenType;
 use intmax2_zkp::{
-    common::{deposit::Deposit, transfer::Transfer},
-    ethereum_types::{u256::U256, u32limb_trait::U32LimbTrait},
+    common::{
+        deposit::{get_pubkey_salt_hash, Deposit},
+        transfer::Transfer,
+    },
+    ethereum_types::{bytes32::Bytes32, u256::U256, u32limb_trait::U32LimbTrait},
     utils::leafable::Leafable,
 };
 use js_types::{
-    common::{JsClaimInfo, JsMining, JsTransfer, JsWithdrawalInfo},
+    common::{
+        JsClaimInfo, JsHistoryBackupChunk, JsMetaData, JsMining, JsTransfer, JsWithdrawalInfo,
+        JsWithdrawalSummary,
+    },
+    cursor::{JsMetaDataCursor, JsMetaDataCursorResponse},
     data::{
-        balances_to_token_balances, JsDepositResult, JsTransferData, JsTxResult, JsUserData,
-        TokenBalance,
+        balances_to_token_balances, JsBalanceAtBlock, JsConsistencyReport, JsDepositResult,
+        JsSyncWorkEstimate, JsTokenInfo, JsTransferData, JsTxResult, JsUserData, TokenBalance,
     },
-    fee::{JsFeeQuote, JsTransferFeeQuote},
+    deposit::JsDepositInfo,
+    fee::{JsFeeQuote, JsTokenFeeQuote, JsTransferFeeQuote, JsTxSimulationResult},
+    history::JsHistoryEntry,
     payment_memo::JsPaymentMemoEntry,
-    utils::{parse_address, parse_bytes32, parse_u256},
-    wrapper::JsTxRequestMemo,
+    utils::{parse_address, parse_bytes32, parse_salt, parse_u256},
+    wrapper::{JsBlockProposal, JsTxRequestMemo},
 };
 use num_bigint::BigUint;
 use utils::{parse_h256, str_privkey_to_keyset};
 use wasm_bindgen::{prelude::wasm_bindgen, JsError, JsValue};
 
 pub mod client;
+pub mod error;
 pub mod fee_payment;
 pub mod history;
 pub mod js_types;
@@ -43,7 +62,7 @@ pub struct IntmaxAccount {
 #[wasm_bindgen]
 pub async fn generate_intmax_account_from_eth_key(
     eth_private_key: &str,
-) -> Result<IntmaxAccount, JsError> {
+) -> Result<IntmaxAccount, JsIntmaxError> {
     init_logger();
     let eth_private_key = parse_h256(eth_private_key)?;
     let key_set = inner_generate_intmax_account_from_eth_key(eth_private_key);
@@ -62,22 +81,70 @@ pub fn get_deposit_hash(
     token_index: u32,
     amount: &str,
     is_eligible: bool,
-) -> Result<String, JsError> {
+) -> Result<String, JsIntmaxError> {
+    init_logger();
+    let deposit_hash = compute_deposit_hash(
+        depositor,
+        recipient_salt_hash,
+        token_index,
+        amount,
+        is_eligible,
+    )?;
+    Ok(deposit_hash.to_hex())
+}
+
+/// Compute the recipient salt hash of a deposit to `recipient` (public key) with `salt`, the
+/// same as the `pubkey_salt_hash` returned by `prepare_deposit` for the same salt. Both are
+/// hex strings.
+#[wasm_bindgen]
+pub fn compute_pubkey_salt_hash(recipient: &str, salt: &str) -> Result<String, JsIntmaxError> {
+    init_logger();
+    let recipient: U256 = parse_bytes32(recipient)?.into();
+    let salt = parse_salt(salt)?;
+    Ok(get_pubkey_salt_hash(recipient, salt).to_hex())
+}
+
+/// Verify that the deposit hash (e.g. of an on-chain deposit event) matches the given deposit
+/// parameters. The parameters are encoded in the same way as `get_deposit_hash`.
+#[wasm_bindgen]
+pub fn verify_deposit_hash(
+    depositor: &str,
+    recipient_salt_hash: &str,
+    token_index: u32,
+    amount: &str,
+    is_eligible: bool,
+    expected_hash: &str,
+) -> Result<bool, JsIntmaxError> {
     init_logger();
+    let expected_hash = parse_bytes32(expected_hash)?;
+    let deposit_hash = compute_deposit_hash(
+        depositor,
+        recipient_salt_hash,
+        token_index,
+        amount,
+        is_eligible,
+    )?;
+    Ok(deposit_hash == expected_hash)
+}
+
+fn compute_deposit_hash(
+    depositor: &str,
+    recipient_salt_hash: &str,
+    token_index: u32,
+    amount: &str,
+    is_eligible: bool,
+) -> Result<Bytes32, JsError> {
     let depositor = parse_address(depositor)?;
     let recipient_salt_hash = parse_bytes32(recipient_salt_hash)?;
     let amount = parse_u256(amount)?;
-    let is_eligible = is_eligible as u32;
-
     let deposit = Deposit {
         depositor,
         pubkey_salt_hash: recipient_salt_hash,
         amount,
         token_index,
-        is_eligible: is_eligible != 0,
+        is_eligible,
     };
-    let deposit_hash = deposit.hash();
-    Ok(deposit_hash.to_hex())
+    Ok(deposit.hash())
 }
 
 /// Function to take a backup before calling the deposit function of the liquidity contract.
@@ -93,30 +160,65 @@ pub async fn prepare_deposit(
     token_address: &str,
     token_id: &str,
     is_mining: bool,
-) -> Result<JsDepositResult, JsError> {
+) -> Result<JsDepositResult, JsIntmaxError> {
     init_logger();
     let depositor = parse_address(depositor)?;
     let recipient: U256 = parse_bytes32(recipient)?.into();
     let amount = parse_u256(amount)?;
-    let token_type = TokenType::try_from(token_type).map_err(|e| JsError::new(&e))?;
+    let token_type = TokenType::try_from(token_type)
+        .map_err(|e| JsIntmaxError::new(ErrorCode::InvalidInput, e))?;
     let token_address = parse_address(token_address)?;
     let token_id = parse_u256(token_id)?;
-    let client = get_client(config);
-    let deposit_result = client
-        .prepare_deposit(
-            depositor,
-            recipient,
-            amount,
-            token_type,
-            token_address,
-            token_id,
-            is_mining,
-        )
-        .await
-        .map_err(|e| JsError::new(&format!("failed to prepare deposit call: {e}")))?;
+    let client = get_client(config)?;
+    let deposit_result = run_operation("prepare_deposit", async {
+        client
+            .prepare_deposit(
+                depositor,
+                recipient,
+                amount,
+                token_type,
+                token_address,
+                token_id,
+                is_mining,
+            )
+            .await
+            .map_err(|e| JsIntmaxError::from(e).context("failed to prepare deposit call"))
+    })
+    .await?;
     Ok(deposit_result.into())
 }
 
+/// Wait until the validity prover observes the deposit to `pubkey_salt_hash` sent in the L1 tx
+/// `tx_hash`, for `timeout` seconds or `deposit_timeout` of the config if omitted.
+/// `should_abort` is called before each poll, and the wait fails with `ABORTED` once it returns
+/// true. A timeout fails with `DEPOSIT_TX_NOT_MINED` if the L1 tx is not mined, or with
+/// `DEPOSIT_NOT_OBSERVED` if it is mined but the validity prover has not caught up, in which case
+/// the deposit is safe and appears after the validity prover syncs.
+#[wasm_bindgen]
+pub async fn wait_for_deposit(
+    config: &Config,
+    pubkey_salt_hash: &str,
+    tx_hash: &str,
+    timeout: Option<u64>,
+    should_abort: Option<js_sys::Function>,
+) -> Result<JsDepositInfo, JsIntmaxError> {
+    init_logger();
+    let pubkey_salt_hash = parse_bytes32(pubkey_salt_hash)?;
+    let tx_hash = parse_bytes32(tx_hash)?;
+    let client = get_client(config)?;
+    let should_abort = || {
+        should_abort.as_ref().is_some_and(|f| {
+            f.call0(&JsValue::NULL)
+                .map(|aborted| aborted.is_truthy())
+                .unwrap_or(false)
+        })
+    };
+    let deposit_info = client
+        .wait_for_deposit(pubkey_salt_hash, tx_hash, timeout, &should_abort)
+        .await?;
+    Ok(deposit_info.into())
+}
+
 /// Wait for the tx to be sendable. Wait for the sync of validity prover and balance proof.
 #[wasm_bindgen]
 pub async fn await_tx_sendable(
@@ -124,10 +226,15 @@ pub async fn await_tx_sendable(
     private_key: &str,
     transfers: &JsValue, // same as Vec<JsTransfer> but use JsValue to avoid moving the ownership
     fee_quote: &JsTransferFeeQuote, // same as Vec<JsPaymentMemoEntry> but use JsValue to avoid moving the ownership
-) -> Result<(), JsError> {
+) -> Result<(), JsIntmaxError> {
     init_logger();
-    let transfers: Vec<JsTransfer> = serde_wasm_bindgen::from_value(transfers.clone())
-        .map_err(|e| JsError::new(&format!("failed to deserialize transfers: {e}")))?;
+    let transfers: Vec<JsTransfer> =
+        serde_wasm_bindgen::from_value(transfers.clone()).map_err(|e| {
+            JsIntmaxError::new(
+                ErrorCode::InvalidInput,
+                format!("failed to deserialize transfers: {e}"),
+            )
+        })?;
     let transfers: Vec<Transfer> = transfers
         .iter()
         .map(|transfer| transfer.clone().try_into())
@@ -135,74 +242,224 @@ pub async fn await_tx_sendable(
 
     let key = str_privkey_to_keyset(private_key)?;
     let fee_quote: TransferFeeQuote = fee_quote.clone().try_into()?;
-    let client = get_client(config);
+    let client = get_client(config)?;
     client
         .await_tx_sendable(key, &transfers, &fee_quote)
         .await?;
     Ok(())
 }
 
-/// Function to send a tx request to the block builder. The return value contains information to take a backup.
+/// Function to send a tx request to the block builders, tried in order until one accepts it.
+/// The return value contains information to take a backup, and the block builder that accepted
+/// the request. If `valid_until` (unix timestamp) is given, the block builder drops the request
+/// instead of building it after that time.
 #[wasm_bindgen]
 pub async fn send_tx_request(
     config: &Config,
-    block_builder_url: &str,
+    block_builder_urls: Vec<String>,
     private_key: &str,
     transfers: &JsValue, // same as Vec<JsTransfer> but use JsValue to avoid moving the ownership
     payment_memos: &JsValue, // same as Vec<JsPaymentMemoEntry> but use JsValue to avoid moving the ownership
     fee_quote: &JsTransferFeeQuote,
-) -> Result<JsTxRequestMemo, JsError> {
+    valid_until: Option<u64>,
+) -> Result<JsTxRequestMemo, JsIntmaxError> {
     init_logger();
     let key = str_privkey_to_keyset(private_key)?;
-    let transfers: Vec<JsTransfer> = serde_wasm_bindgen::from_value(transfers.clone())
-        .map_err(|e| JsError::new(&format!("failed to deserialize transfers: {e}")))?;
+    let transfers: Vec<JsTransfer> =
+        serde_wasm_bindgen::from_value(transfers.clone()).map_err(|e| {
+            JsIntmaxError::new(
+                ErrorCode::InvalidInput,
+                format!("failed to deserialize transfers: {e}"),
+            )
+        })?;
     let transfers: Vec<Transfer> = transfers
         .iter()
         .map(|transfer| transfer.clone().try_into())
         .collect::<Result<Vec<_>, JsError>>()?;
     let payment_memos: Vec<JsPaymentMemoEntry> =
-        serde_wasm_bindgen::from_value(payment_memos.clone())
-            .map_err(|e| JsError::new(&format!("failed to deserialize payment memos: {e}")))?;
+        serde_wasm_bindgen::from_value(payment_memos.clone()).map_err(|e| {
+            JsIntmaxError::new(
+                ErrorCode::InvalidInput,
+                format!("failed to deserialize payment memos: {e}"),
+            )
+        })?;
     let payment_memos: Vec<PaymentMemoEntry> = payment_memos
         .iter()
         .map(|e| e.clone().try_into())
         .collect::<Result<Vec<_>, JsError>>()?;
 
     let fee_quote: TransferFeeQuote = fee_quote.clone().try_into()?;
-    let client = get_client(config);
-    let memo = client
-        .send_tx_request(
+    let client = get_client(config)?;
+    let memo = run_operation("send_tx_request", async {
+        client
+            .send_tx_request(
+                &block_builder_urls,
+                key,
+                &transfers,
+                &payment_memos,
+                &fee_quote,
+                valid_until,
+            )
+            .await
+            .map_err(|e| JsIntmaxError::from(e).context("failed to send tx request"))
+    })
+    .await?;
+
+    Ok(JsTxRequestMemo::from_tx_request_memo(&memo))
+}
+
+/// Dry run of `send_tx_request`. Validates the transfers, quotes the fee, and checks the balance
+/// without sending the request to the block builder. It does not reserve a nonce or take any backup.
+#[wasm_bindgen]
+pub async fn simulate_tx_request(
+    config: &Config,
+    block_builder_url: &str,
+    private_key: &str,
+    transfers: &JsValue, // same as Vec<JsTransfer> but use JsValue to avoid moving the ownership
+    payment_memos: &JsValue, // same as Vec<JsPaymentMemoEntry> but use JsValue to avoid moving the ownership
+    fee_token_index: u32,
+) -> Result<JsTxSimulationResult, JsIntmaxError> {
+    init_logger();
+    let key = str_privkey_to_keyset(private_key)?;
+    let transfers: Vec<JsTransfer> =
+        serde_wasm_bindgen::from_value(transfers.clone()).map_err(|e| {
+            JsIntmaxError::new(
+                ErrorCode::InvalidInput,
+                format!("failed to deserialize transfers: {e}"),
+            )
+        })?;
+    let transfers: Vec<Transfer> = transfers
+        .iter()
+        .map(|transfer| transfer.clone().try_into())
+        .collect::<Result<Vec<_>, JsError>>()?;
+    let payment_memos: Vec<JsPaymentMemoEntry> =
+        serde_wasm_bindgen::from_value(payment_memos.clone()).map_err(|e| {
+            JsIntmaxError::new(
+                ErrorCode::InvalidInput,
+                format!("failed to deserialize payment memos: {e}"),
+            )
+        })?;
+    let payment_memos: Vec<PaymentMemoEntry> = payment_memos
+        .iter()
+        .map(|e| e.clone().try_into())
+        .collect::<Result<Vec<_>, JsError>>()?;
+
+    let client = get_client(config)?;
+    let result = client
+        .simulate_tx_request(
             block_builder_url,
             key,
             &transfers,
             &payment_memos,
-            &fee_quote,
+            fee_token_index,
         )
         .await
-        .map_err(|e| JsError::new(&format!("failed to send tx request {e}")))?;
-
-    Ok(JsTxRequestMemo::from_tx_request_memo(&memo))
+        .map_err(|e| JsIntmaxError::from(e).context("failed to simulate tx request"))?;
+    Ok(result.into())
 }
 
 /// Function to query the block proposal from the block builder, and
 /// send the signed tx tree root to the block builder during taking a backup of the tx.
+/// The block builder that accepted the request in `send_tx_request` is used. `block_builder_url`
+/// is only used for memos that do not record it.
 #[wasm_bindgen]
 pub async fn query_and_finalize(
     config: &Config,
     block_builder_url: &str,
     private_key: &str,
     tx_request_memo: &JsTxRequestMemo,
-) -> Result<JsTxResult, JsError> {
+) -> Result<JsTxResult, JsIntmaxError> {
     init_logger();
     let key = str_privkey_to_keyset(private_key)?;
-    let client = get_client(config);
+    let client = get_client(config)?;
+    let tx_request_memo = tx_request_memo.to_tx_request_memo()?;
+    let block_builder_url = if tx_request_memo.block_builder_url.is_empty() {
+        block_builder_url
+    } else {
+        &tx_request_memo.block_builder_url
+    };
+    let tx_result = run_operation("query_and_finalize", async {
+        let proposal = client
+            .query_proposal(block_builder_url, &tx_request_memo.request_id)
+            .await?;
+        let tx_result = client
+            .finalize_tx(block_builder_url, key, &tx_request_memo, &proposal)
+            .await?;
+        Ok::<_, JsIntmaxError>(tx_result)
+    })
+    .await?;
+    Ok(tx_result.into())
+}
+
+/// First step of the offline signing flow, on the online machine: query the block proposal for
+/// the request. Hand `proposal.to_json()` to the machine holding the key.
+#[wasm_bindgen]
+pub async fn query_proposal(
+    config: &Config,
+    block_builder_url: &str,
+    tx_request_memo: &JsTxRequestMemo,
+) -> Result<JsBlockProposal, JsIntmaxError> {
+    init_logger();
+    let client = get_client(config)?;
     let tx_request_memo = tx_request_memo.to_tx_request_memo()?;
+    let block_builder_url = if tx_request_memo.block_builder_url.is_empty() {
+        block_builder_url
+    } else {
+        &tx_request_memo.block_builder_url
+    };
     let proposal = client
         .query_proposal(block_builder_url, &tx_request_memo.request_id)
         .await?;
-    let tx_result = client
-        .finalize_tx(block_builder_url, key, &tx_request_memo, &proposal)
-        .await?;
+    Ok(JsBlockProposal::from_block_proposal(&proposal))
+}
+
+/// Second step of the offline signing flow, on the machine holding the key: sign the proposal.
+/// This needs no network. Returns the signed proposal as JSON, to hand back to the online
+/// machine.
+#[wasm_bindgen]
+pub fn sign_proposal(private_key: &str, proposal: &JsBlockProposal) -> Result<String, JsError> {
+    init_logger();
+    let key = str_privkey_to_keyset(private_key)?;
+    let proposal = proposal.to_block_proposal()?;
+    Ok(inner_sign_proposal(key, &proposal).to_json())
+}
+
+/// Last step of the offline signing flow, on the online machine: verify the signed proposal made
+/// by `sign_proposal`, back up the tx, and send the signature to the block builder. The
+/// proposal must be signed and submitted before its expiry.
+#[wasm_bindgen]
+pub async fn submit_signed_proposal(
+    config: &Config,
+    block_builder_url: &str,
+    private_key: &str,
+    tx_request_memo: &JsTxRequestMemo,
+    proposal: &JsBlockProposal,
+    signed_proposal: &str,
+) -> Result<JsTxResult, JsIntmaxError> {
+    init_logger();
+    let key = str_privkey_to_keyset(private_key)?;
+    let client = get_client(config)?;
+    let tx_request_memo = tx_request_memo.to_tx_request_memo()?;
+    let proposal = proposal.to_block_proposal()?;
+    let signed_proposal = SignedProposal::from_json(signed_proposal)?;
+    let block_builder_url = if tx_request_memo.block_builder_url.is_empty() {
+        block_builder_url
+    } else {
+        &tx_request_memo.block_builder_url
+    };
+    let tx_result = run_operation("submit_signed_proposal", async {
+        let tx_result = client
+            .submit_signed_proposal(
+                block_builder_url,
+                key,
+                &tx_request_memo,
+                &proposal,
+                &signed_proposal,
+            )
+            .await?;
+        Ok::<_, JsIntmaxError>(tx_result)
+    })
+    .await?;
     Ok(tx_result.into())
 }
 
@@ -211,38 +468,142 @@ pub async fn get_tx_status(
     config: &Config,
     pubkey: &str,
     tx_tree_root: &str,
-) -> Result<String, JsError> {
+) -> Result<String, JsIntmaxError> {
     init_logger();
-    let client = get_client(config);
+    let client = get_client(config)?;
     let pubkey = parse_bytes32(pubkey)?.into();
     let tx_tree_root = parse_bytes32(tx_tree_root)?;
     let status = client
         .get_tx_status(pubkey, tx_tree_root)
         .await
-        .map_err(|e| JsError::new(&format!("failed to get tx status: {e}")))?;
+        .map_err(|e| JsIntmaxError::from(e).context("failed to get tx status"))?;
     Ok(status.to_string())
 }
 
+/// Get the status of a sent tx request: "expired" if the block builder dropped it after its
+/// `valid_until`, "pending" until it is built into a block, then the status of the tx.
+#[wasm_bindgen]
+pub async fn get_tx_request_status(
+    config: &Config,
+    pubkey: &str,
+    tx_request_memo: &JsTxRequestMemo,
+) -> Result<String, JsIntmaxError> {
+    init_logger();
+    let client = get_client(config)?;
+    let pubkey = parse_bytes32(pubkey)?.into();
+    let tx_request_memo = tx_request_memo.to_tx_request_memo()?;
+    let status = client
+        .get_tx_request_status(pubkey, &tx_request_memo)
+        .await
+        .map_err(|e| JsIntmaxError::from(e).context("failed to get tx request status"))?;
+    Ok(status.to_string())
+}
+
+/// Get the statuses of multiple txs in one call. The result is aligned by index with `tx_tree_roots`.
+/// An entry that cannot be parsed is returned as an error string instead of failing the whole batch.
+#[wasm_bindgen]
+pub async fn get_tx_status_batch(
+    config: &Config,
+    pubkey: &str,
+    tx_tree_roots: Vec<String>,
+) -> Result<Vec<String>, JsIntmaxError> {
+    init_logger();
+    let client = get_client(config)?;
+    let pubkey = parse_bytes32(pubkey)?.into();
+    let parsed_roots = tx_tree_roots
+        .iter()
+        .map(|root| root.parse::<Bytes32>())
+        .collect::<Vec<_>>();
+    let valid_roots = parsed_roots
+        .iter()
+        .filter_map(|root| root.as_ref().ok().copied())
+        .collect::<Vec<_>>();
+    let statuses = client
+        .get_tx_status_batch(pubkey, &valid_roots)
+        .await
+        .map_err(|e| JsIntmaxError::from(e).context("failed to get tx status batch"))?;
+    let mut statuses = statuses.into_iter();
+    let result = parsed_roots
+        .into_iter()
+        .map(|root| match root {
+            Ok(_) => statuses
+                .next()
+                .map(|status| status.to_string())
+                .unwrap_or_else(|| "error: missing status".to_string()),
+            Err(e) => format!("error: failed to parse bytes32 {e}"),
+        })
+        .collect();
+    Ok(result)
+}
+
 /// Synchronize the user's balance proof. It may take a long time to generate ZKP.
 #[wasm_bindgen]
-pub async fn sync(config: &Config, private_key: &str) -> Result<(), JsError> {
+pub async fn sync(config: &Config, private_key: &str) -> Result<(), JsIntmaxError> {
     init_logger();
     let key = str_privkey_to_keyset(private_key)?;
-    let client = get_client(config);
-    client.sync(key).await?;
-    Ok(())
+    let client = get_client(config)?;
+    run_operation("sync", async {
+        client.sync(key).await?;
+        Ok(())
+    })
+    .await
+}
+
+/// Estimate the work of `sync` without generating proofs or changing any state, so that the
+/// UI can show the expected duration. Fails like `sync` if a tx is pending.
+#[wasm_bindgen]
+pub async fn estimate_sync_work(
+    config: &Config,
+    private_key: &str,
+) -> Result<JsSyncWorkEstimate, JsIntmaxError> {
+    init_logger();
+    let key = str_privkey_to_keyset(private_key)?;
+    let client = get_client(config)?;
+    let estimate = client.estimate_sync_work(key).await?;
+    Ok(estimate.into())
 }
 
 /// Resynchronize the user's balance proof.
 #[wasm_bindgen]
-pub async fn resync(config: &Config, private_key: &str, is_deep: bool) -> Result<(), JsError> {
+pub async fn resync(
+    config: &Config,
+    private_key: &str,
+    is_deep: bool,
+) -> Result<(), JsIntmaxError> {
     init_logger();
     let key = str_privkey_to_keyset(private_key)?;
-    let client = get_client(config);
+    let client = get_client(config)?;
     client.resync(key, is_deep).await?;
     Ok(())
 }
 
+/// Continue the deep resync that was interrupted, or start a new one if there is none.
+/// `resync` with `is_deep` always starts over.
+#[wasm_bindgen]
+pub async fn resume_deep_resync(config: &Config, private_key: &str) -> Result<(), JsIntmaxError> {
+    init_logger();
+    let key = str_privkey_to_keyset(private_key)?;
+    let client = get_client(config)?;
+    client.resume_deep_resync(key).await?;
+    Ok(())
+}
+
+/// Resynchronize only the receives of `token_index`. A deep resync of a token falls back to a
+/// full deep resync, because the balance proof covers all tokens at once.
+#[wasm_bindgen]
+pub async fn resync_token(
+    config: &Config,
+    private_key: &str,
+    token_index: u32,
+    is_deep: bool,
+) -> Result<(), JsIntmaxError> {
+    init_logger();
+    let key = str_privkey_to_keyset(private_key)?;
+    let client = get_client(config)?;
+    client.resync_token(key, token_index, is_deep).await?;
+    Ok(())
+}
+
 /// Synchronize the user's withdrawal proof, and send request to the withdrawal aggregator.
 /// It may take a long time to generate ZKP.
 #[wasm_bindgen]
@@ -250,15 +611,18 @@ pub async fn sync_withdrawals(
     config: &Config,
     private_key: &str,
     fee_token_index: u32,
-) -> Result<(), JsError> {
+) -> Result<(), JsIntmaxError> {
     init_logger();
     let key = str_privkey_to_keyset(private_key)?;
-    let client = get_client(config);
-    let withdrawal_fee = client.withdrawal_server.get_withdrawal_fee().await?;
-    client
-        .sync_withdrawals(key, &withdrawal_fee, fee_token_index)
-        .await?;
-    Ok(())
+    let client = get_client(config)?;
+    run_operation("sync_withdrawals", async {
+        let withdrawal_fee = client.withdrawal_server.get_withdrawal_fee().await?;
+        client
+            .sync_withdrawals(key, &withdrawal_fee, fee_token_index)
+            .await?;
+        Ok(())
+    })
+    .await
 }
 
 /// Synchronize the user's claim of staking mining, and send request to the withdrawal aggregator.
@@ -269,59 +633,111 @@ pub async fn sync_claims(
     private_key: &str,
     recipient: &str,
     fee_token_index: u32,
-) -> Result<(), JsError> {
+) -> Result<(), JsIntmaxError> {
     init_logger();
     let key = str_privkey_to_keyset(private_key)?;
-    let client = get_client(config);
+    let client = get_client(config)?;
     let recipient = parse_address(recipient)?;
-    let claim_fee = client.withdrawal_server.get_claim_fee().await?;
-    client
-        .sync_claims(key, recipient, &claim_fee, fee_token_index)
-        .await?;
-    Ok(())
+    run_operation("sync_claims", async {
+        let claim_fee = client.withdrawal_server.get_claim_fee().await?;
+        client
+            .sync_claims(key, recipient, &claim_fee, fee_token_index)
+            .await?;
+        Ok(())
+    })
+    .await
 }
 
 /// Get the user's data. It is recommended to sync before calling this function.
+/// `balance_breakdown` tells which part of the balances is spendable now and which is pending.
 #[wasm_bindgen]
-pub async fn get_user_data(config: &Config, private_key: &str) -> Result<JsUserData, JsError> {
+pub async fn get_user_data(
+    config: &Config,
+    private_key: &str,
+) -> Result<JsUserData, JsIntmaxError> {
     init_logger();
     let key = str_privkey_to_keyset(private_key)?;
-    let client = get_client(config);
+    let client = get_client(config)?;
     let user_data = client.get_user_data(key).await?;
-    Ok(user_data.into())
+    let balance_breakdown = client.get_balance_breakdown(key).await?;
+    Ok(JsUserData::new(user_data, balance_breakdown))
 }
 
 #[wasm_bindgen]
 pub async fn get_withdrawal_info(
     config: &Config,
     private_key: &str,
-) -> Result<Vec<JsWithdrawalInfo>, JsError> {
+) -> Result<Vec<JsWithdrawalInfo>, JsIntmaxError> {
     init_logger();
     let key = str_privkey_to_keyset(private_key)?;
-    let client = get_client(config);
+    let client = get_client(config)?;
     let info = client.get_withdrawal_info(key).await?;
     let js_info = info.into_iter().map(JsWithdrawalInfo::from).collect();
     Ok(js_info)
 }
 
+#[wasm_bindgen(getter_with_clone)]
+pub struct JsWithdrawalInfoPage {
+    pub info: Vec<JsWithdrawalInfo>,
+    pub cursor_response: JsMetaDataCursorResponse,
+}
+
+/// Get a page of the withdrawal infos, ordered by request timestamp then withdrawal hash.
+/// Pass `cursor_response.next_cursor` as the cursor of the next page.
+#[wasm_bindgen]
+pub async fn get_withdrawal_info_paged(
+    config: &Config,
+    private_key: &str,
+    cursor: &JsMetaDataCursor,
+) -> Result<JsWithdrawalInfoPage, JsIntmaxError> {
+    init_logger();
+    let cursor: MetaDataCursor = cursor.try_into()?;
+    let key = str_privkey_to_keyset(private_key)?;
+    let client = get_client(config)?;
+    let (info, cursor_response) = client.get_withdrawal_info_paged(key, &cursor).await?;
+    Ok(JsWithdrawalInfoPage {
+        info: info.into_iter().map(JsWithdrawalInfo::from).collect(),
+        cursor_response: cursor_response.into(),
+    })
+}
+
 #[wasm_bindgen]
 pub async fn get_withdrawal_info_by_recipient(
     config: &Config,
     recipient: &str,
-) -> Result<Vec<JsWithdrawalInfo>, JsError> {
+) -> Result<Vec<JsWithdrawalInfo>, JsIntmaxError> {
     init_logger();
-    let client = get_client(config);
+    let client = get_client(config)?;
     let recipient = parse_address(recipient)?;
     let info = client.get_withdrawal_info_by_recipient(recipient).await?;
     let js_info = info.into_iter().map(JsWithdrawalInfo::from).collect();
     Ok(js_info)
 }
 
+/// Get per token index totals of the withdrawals sent to the recipient.
+#[wasm_bindgen]
+pub async fn get_withdrawal_summary_by_recipient(
+    config: &Config,
+    recipient: &str,
+) -> Result<Vec<JsWithdrawalSummary>, JsIntmaxError> {
+    init_logger();
+    let client = get_client(config)?;
+    let recipient = parse_address(recipient)?;
+    let summary = client
+        .get_withdrawal_summary_by_recipient(recipient)
+        .await?;
+    let js_summary = summary.into_iter().map(JsWithdrawalSummary::from).collect();
+    Ok(js_summary)
+}
+
 #[wasm_bindgen]
-pub async fn get_mining_list(config: &Config, private_key: &str) -> Result<Vec<JsMining>, JsError> {
+pub async fn get_mining_list(
+    config: &Config,
+    private_key: &str,
+) -> Result<Vec<JsMining>, JsIntmaxError> {
     init_logger();
     let key = str_privkey_to_keyset(private_key)?;
-    let client = get_client(config);
+    let client = get_client(config)?;
     let minings = client.get_mining_list(key).await?;
     let js_minings = minings.into_iter().map(JsMining::from).collect();
     Ok(js_minings)
@@ -331,39 +747,83 @@ pub async fn get_mining_list(config: &Config, private_key: &str) -> Result<Vec<J
 pub async fn get_claim_info(
     config: &Config,
     private_key: &str,
-) -> Result<Vec<JsClaimInfo>, JsError> {
+) -> Result<Vec<JsClaimInfo>, JsIntmaxError> {
     init_logger();
     let key = str_privkey_to_keyset(private_key)?;
-    let client = get_client(config);
+    let client = get_client(config)?;
     let info = client.get_claim_info(key).await?;
     let js_info = info.into_iter().map(JsClaimInfo::from).collect();
     Ok(js_info)
 }
 
+#[wasm_bindgen(getter_with_clone)]
+pub struct JsClaimInfoPage {
+    pub info: Vec<JsClaimInfo>,
+    pub cursor_response: JsMetaDataCursorResponse,
+}
+
+/// Get a page of the claim infos, ordered by request timestamp then nullifier.
+/// Pass `cursor_response.next_cursor` as the cursor of the next page.
+#[wasm_bindgen]
+pub async fn get_claim_info_paged(
+    config: &Config,
+    private_key: &str,
+    cursor: &JsMetaDataCursor,
+) -> Result<JsClaimInfoPage, JsIntmaxError> {
+    init_logger();
+    let cursor: MetaDataCursor = cursor.try_into()?;
+    let key = str_privkey_to_keyset(private_key)?;
+    let client = get_client(config)?;
+    let (info, cursor_response) = client.get_claim_info_paged(key, &cursor).await?;
+    Ok(JsClaimInfoPage {
+        info: info.into_iter().map(JsClaimInfo::from).collect(),
+        cursor_response: cursor_response.into(),
+    })
+}
+
 #[wasm_bindgen]
 pub async fn quote_transfer_fee(
     config: &Config,
     block_builder_url: &str,
     pubkey: &str,
     fee_token_index: u32,
-) -> Result<JsTransferFeeQuote, JsError> {
+) -> Result<JsTransferFeeQuote, JsIntmaxError> {
     init_logger();
     let pubkey = parse_bytes32(pubkey)?.into();
-    let client = get_client(config);
+    let client = get_client(config)?;
     let fee_quote = client
         .quote_transfer_fee(block_builder_url, pubkey, fee_token_index)
         .await?;
     Ok(fee_quote.into())
 }
 
+/// Quote the transfer fee in each of `fee_token_indices` with a single request to the block
+/// builder. The result is aligned by index with `fee_token_indices`; `is_accepted` is false for
+/// the tokens the block builder does not accept as fee.
+#[wasm_bindgen]
+pub async fn quote_transfer_fee_multi(
+    config: &Config,
+    block_builder_url: &str,
+    pubkey: &str,
+    fee_token_indices: Vec<u32>,
+) -> Result<Vec<JsTokenFeeQuote>, JsIntmaxError> {
+    init_logger();
+    let pubkey = parse_bytes32(pubkey)?.into();
+    let client = get_client(config)?;
+    let quotes = client
+        .quote_transfer_fee_multi(block_builder_url, pubkey, &fee_token_indices)
+        .await?;
+    Ok(quotes.into_iter().map(JsTokenFeeQuote::from).collect())
+}
+
 #[wasm_bindgen]
 pub async fn quote_withdrawal_fee(
     config: &Config,
     withdrawal_token_index: u32,
     fee_token_index: u32,
-) -> Result<JsFeeQuote, JsError> {
+) -> Result<JsFeeQuote, JsIntmaxError> {
     init_logger();
-    let client = get_client(config);
+    let client = get_client(config)?;
     let fee_quote = client
         .quote_withdrawal_fee(withdrawal_token_index, fee_token_index)
         .await?;
@@ -371,40 +831,118 @@ pub async fn quote_withdrawal_fee(
 }
 
 #[wasm_bindgen]
-pub async fn quote_claim_fee(config: &Config, fee_token_index: u32) -> Result<JsFeeQuote, JsError> {
+pub async fn quote_claim_fee(
+    config: &Config,
+    fee_token_index: u32,
+) -> Result<JsFeeQuote, JsIntmaxError> {
     init_logger();
-    let client = get_client(config);
+    let client = get_client(config)?;
     let fee_quote = client.quote_claim_fee(fee_token_index).await?;
     Ok(fee_quote.into())
 }
 
+/// Makes a history backup as CSV chunks. If `rsa_public_key_pem` is given, each chunk is
+/// encrypted to the RSA public key instead.
 #[wasm_bindgen]
 pub async fn make_history_backup(
     config: &Config,
     private_key: &str,
     from: u64,
     chunk_size: u32,
-) -> Result<Vec<String>, JsError> {
+    rsa_public_key_pem: Option<String>,
+) -> Result<Vec<String>, JsIntmaxError> {
     init_logger();
     let key = str_privkey_to_keyset(private_key)?;
-    let client = get_client(config);
+    let client = get_client(config)?;
     let csvs = client
-        .make_history_backup(key, from, chunk_size as usize)
+        .make_history_backup(
+            key,
+            from,
+            chunk_size as usize,
+            rsa_public_key_pem.as_deref(),
+        )
         .await?;
     Ok(csvs)
 }
 
+/// Same as `make_history_backup`, but starts right after `cursor` and returns the cursor of
+/// each chunk so that an interrupted backup can be resumed.
+#[wasm_bindgen]
+pub async fn make_history_backup_from_cursor(
+    config: &Config,
+    private_key: &str,
+    cursor: &JsMetaData,
+    chunk_size: u32,
+    rsa_public_key_pem: Option<String>,
+) -> Result<Vec<JsHistoryBackupChunk>, JsIntmaxError> {
+    init_logger();
+    let key = str_privkey_to_keyset(private_key)?;
+    let cursor: MetaData = cursor.try_into()?;
+    let client = get_client(config)?;
+    let chunks = client
+        .make_history_backup_from_cursor(
+            key,
+            cursor,
+            chunk_size as usize,
+            rsa_public_key_pem.as_deref(),
+        )
+        .await?;
+    Ok(chunks.into_iter().map(JsHistoryBackupChunk::from).collect())
+}
+
+/// Decrypts an RSA-encrypted backup chunk with the AES key unwrapped by the holder of the RSA
+/// private key, and returns the CSV. Plain CSV chunks are returned as is.
+#[wasm_bindgen]
+pub fn decrypt_backup_chunk(content: &str, aes_key: &str) -> Result<String, JsIntmaxError> {
+    init_logger();
+    match EncryptedBackupChunk::parse(content) {
+        Some(encrypted) => {
+            let aes_key = hex::decode(aes_key.trim_start_matches("0x")).map_err(|e| {
+                JsIntmaxError::new(
+                    ErrorCode::InvalidInput,
+                    format!("failed to parse AES key: {e}"),
+                )
+            })?;
+            let csv = encrypted
+                .decrypt_with_aes_key(&aes_key)
+                .map_err(|e| JsIntmaxError::new(ErrorCode::InvalidInput, e.to_string()))?;
+            Ok(csv)
+        }
+        None => Ok(content.to_string()),
+    }
+}
+
+/// Decrypts the entries of a backup chunk made by `make_history_backup` with the private key, and
+/// returns them without importing them into a client. RSA-encrypted chunks must be decrypted with
+/// `decrypt_backup_chunk` first.
+#[wasm_bindgen]
+pub fn decode_history_backup_chunk(
+    private_key: &str,
+    chunk: &str,
+) -> Result<Vec<JsHistoryEntry>, JsIntmaxError> {
+    init_logger();
+    if EncryptedBackupChunk::parse(chunk).is_some() {
+        return Err(JsIntmaxError::new(
+            ErrorCode::InvalidInput,
+            "the backup chunk is RSA-encrypted, decrypt it with decrypt_backup_chunk first",
+        ));
+    }
+    let key = str_privkey_to_keyset(private_key)?;
+    let entries = inner_decode_history_backup_chunk(key, chunk)?;
+    Ok(entries.into_iter().map(JsHistoryEntry::from).collect())
+}
+
 #[wasm_bindgen]
 pub async fn generate_transfer_receipt(
     config: &Config,
     private_key: &str,
     tx_digest: &str,
     transfer_index: u32,
-) -> Result<String, JsError> {
+) -> Result<String, JsIntmaxError> {
     init_logger();
     let key = str_privkey_to_keyset(private_key)?;
     let transfer_digest = parse_bytes32(tx_digest)?;
-    let client = get_client(config);
+    let client = get_client(config)?;
     let receipt = client
         .generate_transfer_receipt(key, transfer_digest, transfer_index)
         .await?;
@@ -416,10 +954,10 @@ pub async fn validate_transfer_receipt(
     config: &Config,
     private_key: &str,
     transfer_receipt: &str,
-) -> Result<JsTransferData, JsError> {
+) -> Result<JsTransferData, JsIntmaxError> {
     init_logger();
     let key = str_privkey_to_keyset(private_key)?;
-    let client = get_client(config);
+    let client = get_client(config)?;
     let transfer_data = client
         .validate_transfer_receipt(key, transfer_receipt)
         .await?;
@@ -430,23 +968,96 @@ pub async fn validate_transfer_receipt(
 pub async fn get_balances_without_sync(
     config: &Config,
     private_key: &str,
-) -> Result<Vec<TokenBalance>, JsError> {
+) -> Result<Vec<TokenBalance>, JsIntmaxError> {
     init_logger();
     let key = str_privkey_to_keyset(private_key)?;
-    let client = get_client(config);
+    let client = get_client(config)?;
     let balances = client.get_balances_without_sync(key).await?;
     Ok(balances_to_token_balances(balances))
 }
 
+/// Get the balances as of the given block number, reconstructed from the balance proof at or
+/// before that block. Before the latest balance proof, the balances are estimated from the
+/// settled history rather than proven.
+#[wasm_bindgen]
+pub async fn get_balance_at_block(
+    config: &Config,
+    private_key: &str,
+    block_number: u32,
+) -> Result<JsBalanceAtBlock, JsIntmaxError> {
+    init_logger();
+    let key = str_privkey_to_keyset(private_key)?;
+    let client = get_client(config)?;
+    let balance_at_block = client.get_balance_at_block(key, block_number).await?;
+    Ok(balance_at_block.into())
+}
+
+/// Get the token registered under the token index, with its decimals and symbol to display
+/// balances in human units.
+#[wasm_bindgen]
+pub async fn get_token_info(
+    config: &Config,
+    token_index: u32,
+) -> Result<JsTokenInfo, JsIntmaxError> {
+    init_logger();
+    let client = get_client(config)?;
+    let token_info = client.get_token_info(token_index).await?;
+    Ok(token_info.into())
+}
+
+/// Batch form of `get_token_info`. The result is aligned by index with `token_indices`.
 #[wasm_bindgen]
-pub async fn check_validity_prover(config: &Config) -> Result<(), JsError> {
+pub async fn get_token_infos(
+    config: &Config,
+    token_indices: Vec<u32>,
+) -> Result<Vec<JsTokenInfo>, JsIntmaxError> {
     init_logger();
-    let client = get_client(config);
+    let client = get_client(config)?;
+    let token_infos = client.get_token_infos(&token_indices).await?;
+    Ok(token_infos.into_iter().map(JsTokenInfo::from).collect())
+}
+
+#[wasm_bindgen]
+pub async fn check_validity_prover(config: &Config) -> Result<(), JsIntmaxError> {
+    init_logger();
+    let client = get_client(config)?;
     client.check_validity_prover().await?;
     Ok(())
 }
 
+/// Checks the processed entries and the balance proof chain of the user data against the
+/// validity prover. Run this before reporting a wrong balance.
+#[wasm_bindgen]
+pub async fn audit_consistency(
+    config: &Config,
+    private_key: &str,
+) -> Result<JsConsistencyReport, JsIntmaxError> {
+    init_logger();
+    let key = str_privkey_to_keyset(private_key)?;
+    let client = get_client(config)?;
+    let report = client.audit_consistency(key).await?;
+    Ok(report.into())
+}
+
 fn init_logger() {
     console_error_panic_hook::set_once();
     // wasm_logger::init(wasm_logger::Config::default());
 }
+
+#[cfg(test)]
+mod tests {
+    use intmax2_client_sdk::client::sync::utils::generate_salt;
+    use intmax2_interfaces::utils::random::default_rng;
+    use intmax2_zkp::common::signature_content::key_set::KeySet;
+
+    use super::*;
+
+    #[test]
+    fn test_compute_pubkey_salt_hash_matches_deposit_data() {
+        let pubkey = KeySet::rand(&mut default_rng()).pubkey;
+        let salt = generate_salt();
+        // the salt and the hash are encoded as in JsDepositData
+        let hash = compute_pubkey_salt_hash(&pubkey.to_hex(), &salt.to_string()).unwrap();
+        assert_eq!(hash, get_pubkey_salt_hash(pubkey, salt).to_hex());
+    }
+}
@@ -56,7 +56,7 @@ pub async fn save_derive_path(
 ) -> Result<String, JsError> {
     init_logger();
     let key = str_privkey_to_keyset(private_key)?;
-    let client = get_client(config);
+    let client = get_client(config)?;
     let generic_misc_data = GenericMiscData {
         data: bincode::serialize(derive).unwrap(),
     };
@@ -80,7 +80,7 @@ pub async fn get_derive_path_list(
 ) -> Result<Vec<JsDerive>, JsError> {
     init_logger();
     let key = str_privkey_to_keyset(private_key)?;
-    let client = get_client(config);
+    let client = get_client(config)?;
 
     let mut encrypted_data = vec![];
     let mut cursor = MetaDataCursor {
@@ -19,7 +19,9 @@ use crate::{
 use intmax2_client_sdk::{
     client::multisig,
     external_api::{
-        s3_store_vault::generate_auth_for_get_data_sequence_s3,
+        s3_store_vault::{
+            generate_auth_for_get_data_sequence_s3, generate_scoped_auth_for_get_data_sequence_s3,
+        },
         store_vault_server::generate_auth_for_get_data_sequence,
     },
 };
@@ -94,6 +96,28 @@ pub async fn generate_auth_for_store_vault(
     Ok(auth.into())
 }
 
+/// Generate a read-only auth for the s3 store vault that only grants access to `data_types`
+/// (e.g. "deposit", "transfer").
+#[wasm_bindgen]
+pub async fn generate_scoped_auth_for_store_vault(
+    private_key: &str,
+    data_types: Vec<String>,
+) -> Result<JsAuth, JsError> {
+    init_logger();
+    let key = str_privkey_to_keyset(private_key)?;
+    let topic_scope = data_types
+        .iter()
+        .map(|data_type| {
+            let data_type: DataType = data_type
+                .parse()
+                .map_err(|e| JsError::new(&format!("invalid data type: {e}")))?;
+            Ok(data_type.to_topic())
+        })
+        .collect::<Result<Vec<_>, JsError>>()?;
+    let auth = generate_scoped_auth_for_get_data_sequence_s3(key, topic_scope);
+    Ok(auth.into())
+}
+
 #[wasm_bindgen]
 pub async fn fetch_encrypted_data(
     config: &Config,
@@ -101,7 +125,7 @@ pub async fn fetch_encrypted_data(
     cursor: &JsMetaDataCursor,
 ) -> Result<Vec<JsEncryptedData>, JsError> {
     init_logger();
-    let client = get_client(config);
+    let client = get_client(config)?;
     let sv = client.store_vault_server;
     let auth: Auth = auth
         .clone()
@@ -109,30 +133,20 @@ pub async fn fetch_encrypted_data(
         .map_err(|e| JsError::new(&format!("failed to convert JsAuth to Auth: {e}")))?;
     let cursor: MetaDataCursor = cursor.clone().try_into()?;
     let mut data_array = Vec::new();
-    let (deposit_data, _) = sv
-        .get_data_sequence_with_auth(&DataType::Deposit.to_topic(), &cursor, &auth)
-        .await?;
-    data_array.extend(
-        deposit_data
-            .into_iter()
-            .map(|data| JsEncryptedData::new(DataType::Deposit, data)),
-    );
-    let (transfer_data, _) = sv
-        .get_data_sequence_with_auth(&DataType::Transfer.to_topic(), &cursor, &auth)
-        .await?;
-    data_array.extend(
-        transfer_data
-            .into_iter()
-            .map(|data| JsEncryptedData::new(DataType::Transfer, data)),
-    );
-    let (tx_data, _) = sv
-        .get_data_sequence_with_auth(&DataType::Tx.to_topic(), &cursor, &auth)
-        .await?;
-    data_array.extend(
-        tx_data
-            .into_iter()
-            .map(|data| JsEncryptedData::new(DataType::Tx, data)),
-    );
+    for data_type in [DataType::Deposit, DataType::Transfer, DataType::Tx] {
+        let topic = data_type.to_topic();
+        // a topic scoped auth only fetches the data types in its scope
+        if auth.check_topic(&topic).is_err() {
+            continue;
+        }
+        let (data, _) = sv
+            .get_data_sequence_with_auth(&topic, &cursor, &auth)
+            .await?;
+        data_array.extend(
+            data.into_iter()
+                .map(|data| JsEncryptedData::new(data_type, data)),
+        );
+    }
     data_array.sort_by_key(|data| (data.timestamp, data.digest.clone()));
     if cursor.order == CursorOrder::Desc {
         data_array.reverse();
@@ -145,7 +159,7 @@ pub async fn fetch_encrypted_data(
 pub async fn get_account_info(config: &Config, public_key: &str) -> Result<JsAccountInfo, JsError> {
     init_logger();
     let pubkey = parse_bytes32(public_key)?.into();
-    let client = get_client(config);
+    let client = get_client(config)?;
     let account_info = client.validity_prover.get_account_info(pubkey).await?;
     Ok(account_info.into())
 }
@@ -157,7 +171,7 @@ pub async fn get_deposit_info(
 ) -> Result<Option<JsDepositInfo>, JsError> {
     init_logger();
     let pubkey_salt_hash = parse_bytes32(pubkey_salt_hash)?;
-    let client = get_client(config);
+    let client = get_client(config)?;
     let deposit_info = client
         .validity_prover
         .get_deposit_info(pubkey_salt_hash)
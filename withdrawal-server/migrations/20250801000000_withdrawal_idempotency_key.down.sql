@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS idx_withdrawals_idempotency_key;
+
+ALTER TABLE withdrawals DROP COLUMN IF EXISTS idempotency_key;
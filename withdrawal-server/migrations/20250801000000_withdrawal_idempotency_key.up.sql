@@ -0,0 +1,3 @@
+ALTER TABLE withdrawals ADD COLUMN IF NOT EXISTS idempotency_key CHAR(66);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_withdrawals_idempotency_key ON withdrawals (idempotency_key);
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS withdrawal_webhook_deliveries;
+DROP TABLE IF EXISTS withdrawal_webhooks;
@@ -0,0 +1,29 @@
+CREATE TABLE IF NOT EXISTS withdrawal_webhooks (
+    id TEXT NOT NULL,
+    -- the pubkey that registered the webhook and can unregister it
+    owner_pubkey CHAR(66) NOT NULL,
+    -- exactly one of pubkey and recipient is set
+    pubkey CHAR(66),
+    recipient CHAR(42),
+    url TEXT NOT NULL,
+    secret TEXT NOT NULL,
+    created_at timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY (id),
+    CHECK ((pubkey IS NULL) <> (recipient IS NULL))
+);
+
+CREATE TABLE IF NOT EXISTS withdrawal_webhook_deliveries (
+    webhook_id TEXT NOT NULL REFERENCES withdrawal_webhooks (id) ON DELETE CASCADE,
+    withdrawal_uuid TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at timestamptz NOT NULL DEFAULT now(),
+    delivered_at timestamptz,
+    last_error TEXT,
+    created_at timestamptz NOT NULL DEFAULT now(),
+    PRIMARY KEY (webhook_id, withdrawal_uuid)
+);
+
+CREATE INDEX IF NOT EXISTS idx_withdrawal_webhooks_pubkey ON withdrawal_webhooks (pubkey);
+CREATE INDEX IF NOT EXISTS idx_withdrawal_webhooks_recipient ON withdrawal_webhooks (recipient);
+CREATE INDEX IF NOT EXISTS idx_withdrawal_webhook_deliveries_pending
+    ON withdrawal_webhook_deliveries (next_attempt_at) WHERE delivered_at IS NULL;
@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_withdrawals_webhooks_pending;
+ALTER TABLE withdrawals DROP COLUMN IF EXISTS webhooks_enqueued;
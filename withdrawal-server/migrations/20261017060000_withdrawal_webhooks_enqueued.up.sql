@@ -0,0 +1,6 @@
+-- set once the webhook deliveries of a successful withdrawal are queued, so that each run only
+-- scans the withdrawals completed since the last one
+ALTER TABLE withdrawals ADD COLUMN IF NOT EXISTS webhooks_enqueued BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE INDEX IF NOT EXISTS idx_withdrawals_webhooks_pending
+    ON withdrawals (uuid) WHERE status = 'success' AND NOT webhooks_enqueued;
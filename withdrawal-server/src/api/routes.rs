@@ -1,6 +1,6 @@
-use crate::api::state::State;
+use crate::{api::state::State, app::error::WithdrawalServerError};
 use actix_web::{
-    error::ErrorUnauthorized,
+    error::{ErrorBadRequest, ErrorUnauthorized},
     get, post,
     web::{Data, Json},
     Error, Scope,
@@ -9,9 +9,14 @@ use intmax2_interfaces::{
     api::withdrawal_server::{
         interface::{ClaimFeeInfo, WithdrawalFeeInfo},
         types::{
-            GetClaimInfoRequest, GetClaimInfoResponse, GetWithdrawalInfoByRecipientQuery,
-            GetWithdrawalInfoRequest, GetWithdrawalInfoResponse, RequestClaimRequest,
-            RequestClaimResponse, RequestWithdrawalRequest, RequestWithdrawalResponse,
+            GetClaimInfoPagedRequest, GetClaimInfoPagedResponse, GetClaimInfoRequest,
+            GetClaimInfoResponse, GetWithdrawalInfoByRecipientQuery, GetWithdrawalInfoPagedRequest,
+            GetWithdrawalInfoPagedResponse, GetWithdrawalInfoRequest, GetWithdrawalInfoResponse,
+            GetWithdrawalSummaryByRecipientQuery, GetWithdrawalSummaryByRecipientResponse,
+            RegisterWithdrawalWebhookRequest, RegisterWithdrawalWebhookResponse,
+            RequestClaimRequest, RequestClaimResponse, RequestWithdrawalBatchRequest,
+            RequestWithdrawalBatchResponse, RequestWithdrawalRequest, RequestWithdrawalResponse,
+            UnregisterWithdrawalWebhookRequest, UnregisterWithdrawalWebhookResponse,
         },
     },
     utils::signature::{Signable as _, WithAuth},
@@ -47,12 +52,34 @@ pub async fn request_withdrawal(
             &request.inner.single_withdrawal_proof,
             request.inner.fee_token_index,
             &request.inner.fee_transfer_digests,
+            request.inner.idempotency_key,
         )
         .await
         .map_err(actix_web::error::ErrorInternalServerError)?;
     Ok(Json(RequestWithdrawalResponse { fee_result }))
 }
 
+#[post("/request-withdrawal-batch")]
+pub async fn request_withdrawal_batch(
+    state: Data<State>,
+    request: Json<WithAuth<RequestWithdrawalBatchRequest>>,
+) -> Result<Json<RequestWithdrawalBatchResponse>, Error> {
+    request
+        .inner
+        .verify(&request.auth)
+        .map_err(ErrorUnauthorized)?;
+    let pubkey = request.auth.pubkey;
+    let fee_results = state
+        .withdrawal_server
+        .request_withdrawal_batch(pubkey, &request.inner.withdrawals)
+        .await
+        .map_err(|e| match e {
+            WithdrawalServerError::TooManyWithdrawals(_) => ErrorBadRequest(e),
+            e => actix_web::error::ErrorInternalServerError(e),
+        })?;
+    Ok(Json(RequestWithdrawalBatchResponse { fee_results }))
+}
+
 #[post("/request-claim")]
 pub async fn request_claim(
     state: Data<State>,
@@ -112,6 +139,48 @@ pub async fn get_claim_info(
     Ok(Json(GetClaimInfoResponse { claim_info }))
 }
 
+#[post("/get-withdrawal-info-paged")]
+pub async fn get_withdrawal_info_paged(
+    state: Data<State>,
+    request: Json<WithAuth<GetWithdrawalInfoPagedRequest>>,
+) -> Result<Json<GetWithdrawalInfoPagedResponse>, Error> {
+    request
+        .inner
+        .verify(&request.auth)
+        .map_err(ErrorUnauthorized)?;
+    let pubkey = request.auth.pubkey;
+    let (withdrawal_info, cursor_response) = state
+        .withdrawal_server
+        .get_withdrawal_info_paged(pubkey, &request.inner.cursor)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(Json(GetWithdrawalInfoPagedResponse {
+        withdrawal_info,
+        cursor_response,
+    }))
+}
+
+#[post("/get-claim-info-paged")]
+pub async fn get_claim_info_paged(
+    state: Data<State>,
+    request: Json<WithAuth<GetClaimInfoPagedRequest>>,
+) -> Result<Json<GetClaimInfoPagedResponse>, Error> {
+    request
+        .inner
+        .verify(&request.auth)
+        .map_err(ErrorUnauthorized)?;
+    let pubkey = request.auth.pubkey;
+    let (claim_info, cursor_response) = state
+        .withdrawal_server
+        .get_claim_info_paged(pubkey, &request.inner.cursor)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(Json(GetClaimInfoPagedResponse {
+        claim_info,
+        cursor_response,
+    }))
+}
+
 #[get("/get-withdrawal-info-by-recipient")]
 pub async fn get_withdrawal_info_by_recipient(
     state: Data<State>,
@@ -125,13 +194,72 @@ pub async fn get_withdrawal_info_by_recipient(
     Ok(Json(GetWithdrawalInfoResponse { withdrawal_info }))
 }
 
+#[get("/get-withdrawal-summary-by-recipient")]
+pub async fn get_withdrawal_summary_by_recipient(
+    state: Data<State>,
+    query: QsQuery<GetWithdrawalSummaryByRecipientQuery>,
+) -> Result<Json<GetWithdrawalSummaryByRecipientResponse>, Error> {
+    let summary = state
+        .withdrawal_server
+        .get_withdrawal_summary_by_recipient(query.recipient)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(Json(GetWithdrawalSummaryByRecipientResponse { summary }))
+}
+
+#[post("/register-withdrawal-webhook")]
+pub async fn register_withdrawal_webhook(
+    state: Data<State>,
+    request: Json<WithAuth<RegisterWithdrawalWebhookRequest>>,
+) -> Result<Json<RegisterWithdrawalWebhookResponse>, Error> {
+    request
+        .inner
+        .verify(&request.auth)
+        .map_err(ErrorUnauthorized)?;
+    let pubkey = request.auth.pubkey;
+    let webhook = state
+        .withdrawal_server
+        .register_withdrawal_webhook(pubkey, &request.inner.url, request.inner.recipient)
+        .await
+        .map_err(|e| match e {
+            WithdrawalServerError::InvalidWebhookUrl(_)
+            | WithdrawalServerError::TooManyWebhooks(_) => ErrorBadRequest(e),
+            e => actix_web::error::ErrorInternalServerError(e),
+        })?;
+    Ok(Json(RegisterWithdrawalWebhookResponse { webhook }))
+}
+
+#[post("/unregister-withdrawal-webhook")]
+pub async fn unregister_withdrawal_webhook(
+    state: Data<State>,
+    request: Json<WithAuth<UnregisterWithdrawalWebhookRequest>>,
+) -> Result<Json<UnregisterWithdrawalWebhookResponse>, Error> {
+    request
+        .inner
+        .verify(&request.auth)
+        .map_err(ErrorUnauthorized)?;
+    let pubkey = request.auth.pubkey;
+    let removed = state
+        .withdrawal_server
+        .unregister_withdrawal_webhook(pubkey, &request.inner.webhook_id)
+        .await
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(Json(UnregisterWithdrawalWebhookResponse { removed }))
+}
+
 pub fn withdrawal_server_scope() -> Scope {
     actix_web::web::scope("/withdrawal-server")
         .service(get_withdrawal_fee)
         .service(get_claim_fee)
         .service(request_withdrawal)
+        .service(request_withdrawal_batch)
         .service(request_claim)
         .service(get_withdrawal_info)
+        .service(get_withdrawal_info_paged)
         .service(get_withdrawal_info_by_recipient)
+        .service(get_withdrawal_summary_by_recipient)
         .service(get_claim_info)
+        .service(get_claim_info_paged)
+        .service(register_withdrawal_webhook)
+        .service(unregister_withdrawal_webhook)
 }
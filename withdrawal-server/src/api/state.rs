@@ -14,9 +14,8 @@ impl State {
     pub async fn new(env: &Env) -> anyhow::Result<Self> {
         let l2_rpc_urls = parse_urls(&env.l2_rpc_url)?;
         let provider = get_provider_with_fallback(l2_rpc_urls.as_ref())?;
-        let withdrawal_server = WithdrawalServer::new(env, provider).await?;
-        Ok(State {
-            withdrawal_server: Arc::new(withdrawal_server),
-        })
+        let withdrawal_server = Arc::new(WithdrawalServer::new(env, provider).await?);
+        withdrawal_server.clone().webhook_job();
+        Ok(State { withdrawal_server })
     }
 }
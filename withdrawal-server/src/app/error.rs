@@ -36,6 +36,18 @@ pub enum WithdrawalServerError {
     #[error("Invalid fee: {0}")]
     InvalidFee(String),
 
+    #[error("Too many withdrawals in a batch: {0}")]
+    TooManyWithdrawals(usize),
+
+    #[error("Idempotency key mismatch: expected {expected}, got {got}")]
+    IdempotencyKeyMismatch { expected: String, got: String },
+
+    #[error("Invalid webhook url: {0}")]
+    InvalidWebhookUrl(String),
+
+    #[error("Too many webhooks registered: the limit is {0}")]
+    TooManyWebhooks(i64),
+
     #[error("Parse error: {0}")]
     ParseError(String),
 
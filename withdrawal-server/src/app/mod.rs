@@ -1,4 +1,5 @@
 pub mod error;
 pub mod fee;
 pub mod status;
+pub mod webhook;
 pub mod withdrawal_server;
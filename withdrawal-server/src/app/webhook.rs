@@ -0,0 +1,473 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use intmax2_interfaces::{
+    api::withdrawal_server::{
+        interface::ContractWithdrawal,
+        webhook::{
+            sign_webhook_payload, WithdrawalCompletedEvent, WithdrawalWebhook,
+            WEBHOOK_SIGNATURE_HEADER, WEBHOOK_TIMESTAMP_HEADER,
+        },
+    },
+    utils::random::default_rng,
+};
+use intmax2_zkp::ethereum_types::{
+    address::Address, bytes32::Bytes32, u256::U256, u32limb_trait::U32LimbTrait as _,
+};
+use tokio::task::JoinHandle;
+
+use super::{error::WithdrawalServerError, withdrawal_server::WithdrawalServer};
+
+pub const WEBHOOK_POLLING_INTERVAL: u64 = 5;
+
+/// Deliveries that failed this many times are given up
+pub const MAX_WEBHOOK_ATTEMPTS: i32 = 10;
+
+const WEBHOOK_DELIVERY_BATCH_SIZE: i64 = 20;
+const WEBHOOK_REQUEST_TIMEOUT: u64 = 10;
+
+/// Seconds a due delivery is leased for while it is posted, so that another instance does not
+/// post it at the same time. Covers a whole batch of timed out posts.
+const WEBHOOK_LEASE: u64 = 300;
+
+const MAX_WEBHOOK_URL_LEN: usize = 2048;
+
+/// Webhooks a pubkey can have registered at the same time
+pub const MAX_WEBHOOKS_PER_OWNER: i64 = 10;
+
+/// Seconds before retrying a delivery that failed `attempts` times: 30 seconds, doubled on each
+/// failure up to an hour.
+pub fn webhook_retry_delay(attempts: i32) -> u64 {
+    (30u64 << (attempts.clamp(1, 8) - 1)).min(3600)
+}
+
+/// Whether `ip` is reachable on the public internet. Webhooks must not reach the loopback,
+/// private, link-local or other special purpose addresses of the server's network.
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || a == 0
+                // shared address space 100.64.0.0/10
+                || (a == 100 && b & 0xc0 == 64))
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(ip));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // unique local fc00::/7 and link-local fe80::/10
+                || first & 0xfe00 == 0xfc00
+                || first & 0xffc0 == 0xfe80)
+        }
+    }
+}
+
+/// Checks the form of a webhook url: an https url whose host is not a local name or a
+/// non-public ip address. The addresses a domain resolves to are checked by
+/// `resolve_webhook_url`.
+pub fn validate_webhook_url(url: &str) -> Result<reqwest::Url, WithdrawalServerError> {
+    if url.len() > MAX_WEBHOOK_URL_LEN {
+        return Err(WithdrawalServerError::InvalidWebhookUrl(format!(
+            "longer than {MAX_WEBHOOK_URL_LEN} characters"
+        )));
+    }
+    let parsed = reqwest::Url::parse(url)
+        .map_err(|e| WithdrawalServerError::InvalidWebhookUrl(e.to_string()))?;
+    if parsed.scheme() != "https" {
+        return Err(WithdrawalServerError::InvalidWebhookUrl(
+            "expected an https url".to_string(),
+        ));
+    }
+    let host = parsed.host_str().unwrap_or_default();
+    let is_public = match host.trim_start_matches('[').trim_end_matches(']').parse() {
+        Ok(ip) => is_public_ip(ip),
+        Err(_) => {
+            let domain = host.trim_end_matches('.').to_ascii_lowercase();
+            !domain.is_empty() && domain != "localhost" && !domain.ends_with(".localhost")
+        }
+    };
+    if !is_public {
+        return Err(WithdrawalServerError::InvalidWebhookUrl(
+            "host is not a public address".to_string(),
+        ));
+    }
+    Ok(parsed)
+}
+
+/// Validates `url` and resolves its host, failing unless every resolved address is public.
+/// Returns the host and the address to connect to, so that the post cannot be pointed at
+/// another address by a second resolution.
+pub async fn resolve_webhook_url(url: &str) -> Result<(String, SocketAddr), WithdrawalServerError> {
+    let parsed = validate_webhook_url(url)?;
+    let host = parsed.host_str().unwrap_or_default().to_string();
+    let port = parsed.port_or_known_default().unwrap_or(443);
+    let lookup_host = host.trim_start_matches('[').trim_end_matches(']');
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((lookup_host, port))
+        .await
+        .map_err(|e| WithdrawalServerError::InvalidWebhookUrl(format!("unresolvable host: {e}")))?
+        .collect();
+    if addrs.is_empty() || addrs.iter().any(|addr| !is_public_ip(addr.ip())) {
+        return Err(WithdrawalServerError::InvalidWebhookUrl(
+            "host does not resolve to public addresses only".to_string(),
+        ));
+    }
+    Ok((host, addrs[0]))
+}
+
+impl WithdrawalServer {
+    /// Register a webhook notified of the completed withdrawals to `recipient`, or of `pubkey` if
+    /// `None`, requested from now on. A pubkey can register up to `MAX_WEBHOOKS_PER_OWNER`.
+    pub async fn register_withdrawal_webhook(
+        &self,
+        pubkey: U256,
+        url: &str,
+        recipient: Option<Address>,
+    ) -> Result<WithdrawalWebhook, WithdrawalServerError> {
+        resolve_webhook_url(url).await?;
+        let webhook_id = uuid::Uuid::new_v4().to_string();
+        let secret = Bytes32::rand(&mut default_rng()).to_hex();
+        let (target_pubkey, target_recipient) = match recipient {
+            Some(recipient) => (None, Some(recipient.to_hex())),
+            None => (Some(pubkey.to_hex()), None),
+        };
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO withdrawal_webhooks (id, owner_pubkey, pubkey, recipient, url, secret)
+            SELECT $1, $2, $3, $4, $5, $6
+            WHERE (SELECT COUNT(*) FROM withdrawal_webhooks WHERE owner_pubkey = $2) < $7
+            "#,
+            webhook_id,
+            pubkey.to_hex(),
+            target_pubkey,
+            target_recipient,
+            url,
+            secret,
+            MAX_WEBHOOKS_PER_OWNER
+        )
+        .execute(&self.pool)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(WithdrawalServerError::TooManyWebhooks(
+                MAX_WEBHOOKS_PER_OWNER,
+            ));
+        }
+        Ok(WithdrawalWebhook { webhook_id, secret })
+    }
+
+    /// Remove a webhook registered by `pubkey`, with its pending deliveries.
+    pub async fn unregister_withdrawal_webhook(
+        &self,
+        pubkey: U256,
+        webhook_id: &str,
+    ) -> Result<bool, WithdrawalServerError> {
+        let result = sqlx::query!(
+            "DELETE FROM withdrawal_webhooks WHERE id = $1 AND owner_pubkey = $2",
+            webhook_id,
+            pubkey.to_hex()
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Queue a delivery to each webhook for each withdrawal completed since the last call, and
+    /// mark those withdrawals so that they are not scanned again. Returns the number of queued
+    /// deliveries.
+    pub async fn enqueue_webhook_deliveries(&self) -> Result<u64, WithdrawalServerError> {
+        let result = sqlx::query!(
+            r#"
+            WITH completed AS (
+                UPDATE withdrawals
+                SET webhooks_enqueued = TRUE
+                WHERE status = 'success' AND NOT webhooks_enqueued
+                RETURNING uuid, pubkey, recipient, created_at
+            )
+            INSERT INTO withdrawal_webhook_deliveries (webhook_id, withdrawal_uuid)
+            SELECT h.id, w.uuid
+            FROM withdrawal_webhooks h
+            JOIN completed w ON w.pubkey = h.pubkey
+            WHERE w.created_at >= h.created_at
+            UNION ALL
+            SELECT h.id, w.uuid
+            FROM withdrawal_webhooks h
+            JOIN completed w ON w.recipient = h.recipient
+            WHERE w.created_at >= h.created_at
+            ON CONFLICT (webhook_id, withdrawal_uuid) DO NOTHING
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Post the due deliveries, and schedule a retry of the failed ones. A delivery whose
+    /// withdrawal cannot be read is given up without stopping the others.
+    pub async fn deliver_webhooks(&self) -> Result<(), WithdrawalServerError> {
+        let deliveries = sqlx::query!(
+            r#"
+            WITH due AS (
+                SELECT webhook_id, withdrawal_uuid
+                FROM withdrawal_webhook_deliveries
+                WHERE delivered_at IS NULL AND next_attempt_at <= now() AND attempts < $1
+                ORDER BY next_attempt_at
+                LIMIT $2
+                FOR UPDATE SKIP LOCKED
+            )
+            UPDATE withdrawal_webhook_deliveries d
+            SET next_attempt_at = now() + make_interval(secs => $3)
+            FROM due, withdrawal_webhooks h, withdrawals w
+            WHERE d.webhook_id = due.webhook_id
+                AND d.withdrawal_uuid = due.withdrawal_uuid
+                AND h.id = d.webhook_id
+                AND w.uuid = d.withdrawal_uuid
+            RETURNING d.webhook_id, d.withdrawal_uuid, d.attempts, h.url, h.secret,
+                w.withdrawal_hash, w.l1_tx_hash, w.contract_withdrawal
+            "#,
+            MAX_WEBHOOK_ATTEMPTS,
+            WEBHOOK_DELIVERY_BATCH_SIZE,
+            WEBHOOK_LEASE as f64
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        for delivery in deliveries {
+            let webhook_id = delivery.webhook_id;
+            let withdrawal_uuid = delivery.withdrawal_uuid;
+            let attempts = delivery.attempts + 1;
+            let event = completed_event(
+                &webhook_id,
+                &delivery.withdrawal_hash,
+                delivery.l1_tx_hash.as_deref(),
+                delivery.contract_withdrawal,
+            )
+            .and_then(|event| {
+                let body = serde_json::to_vec(&event)
+                    .map_err(|e| WithdrawalServerError::SerializationError(e.to_string()))?;
+                Ok((event, body))
+            });
+            let (event, body) = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    // the stored withdrawal does not change, so retrying cannot succeed
+                    log::error!(
+                        "giving up webhook {webhook_id} delivery of withdrawal {withdrawal_uuid}: {e}"
+                    );
+                    self.record_failed_delivery(
+                        &webhook_id,
+                        &withdrawal_uuid,
+                        MAX_WEBHOOK_ATTEMPTS,
+                        &e.to_string(),
+                    )
+                    .await?;
+                    continue;
+                }
+            };
+            let result = post_webhook(&delivery.url, &delivery.secret, body).await;
+
+            match result {
+                Ok(()) => {
+                    sqlx::query!(
+                        r#"
+                        UPDATE withdrawal_webhook_deliveries
+                        SET attempts = $3, delivered_at = now(), last_error = NULL
+                        WHERE webhook_id = $1 AND withdrawal_uuid = $2
+                        "#,
+                        webhook_id,
+                        withdrawal_uuid,
+                        attempts
+                    )
+                    .execute(&self.pool)
+                    .await?;
+                }
+                Err(e) => {
+                    if attempts >= MAX_WEBHOOK_ATTEMPTS {
+                        log::error!(
+                            "giving up webhook {webhook_id} delivery of withdrawal {}: {e}",
+                            event.withdrawal_hash
+                        );
+                    } else {
+                        log::warn!(
+                            "webhook {webhook_id} delivery of withdrawal {} failed: {e}",
+                            event.withdrawal_hash
+                        );
+                    }
+                    self.record_failed_delivery(&webhook_id, &withdrawal_uuid, attempts, &e)
+                        .await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Record a failed delivery attempt and schedule its retry. A delivery with
+    /// `MAX_WEBHOOK_ATTEMPTS` attempts is not retried.
+    async fn record_failed_delivery(
+        &self,
+        webhook_id: &str,
+        withdrawal_uuid: &str,
+        attempts: i32,
+        error: &str,
+    ) -> Result<(), WithdrawalServerError> {
+        sqlx::query!(
+            r#"
+            UPDATE withdrawal_webhook_deliveries
+            SET attempts = $3,
+                last_error = $4,
+                next_attempt_at = now() + make_interval(secs => $5)
+            WHERE webhook_id = $1 AND withdrawal_uuid = $2
+            "#,
+            webhook_id,
+            withdrawal_uuid,
+            attempts,
+            error,
+            webhook_retry_delay(attempts) as f64
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Periodically queue and post the webhook deliveries.
+    pub fn webhook_job(self: Arc<Self>) -> JoinHandle<()> {
+        actix_web::rt::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(WEBHOOK_POLLING_INTERVAL));
+            loop {
+                interval.tick().await;
+                let result = async {
+                    self.enqueue_webhook_deliveries().await?;
+                    self.deliver_webhooks().await
+                }
+                .await;
+                if let Err(e) = result {
+                    log::error!("webhook job failed: {e}");
+                }
+            }
+        })
+    }
+}
+
+/// Build the event posted for a completed withdrawal from its stored columns.
+fn completed_event(
+    webhook_id: &str,
+    withdrawal_hash: &str,
+    l1_tx_hash: Option<&str>,
+    contract_withdrawal: serde_json::Value,
+) -> Result<WithdrawalCompletedEvent, WithdrawalServerError> {
+    let contract_withdrawal: ContractWithdrawal = serde_json::from_value(contract_withdrawal)
+        .map_err(|e| WithdrawalServerError::SerializationError(e.to_string()))?;
+    Ok(WithdrawalCompletedEvent {
+        webhook_id: webhook_id.to_string(),
+        withdrawal_hash: Bytes32::from_hex(withdrawal_hash)
+            .map_err(|e| WithdrawalServerError::ParseError(e.to_string()))?,
+        l1_tx_hash: l1_tx_hash
+            .map(Bytes32::from_hex)
+            .transpose()
+            .map_err(|e| WithdrawalServerError::ParseError(e.to_string()))?,
+        recipient: contract_withdrawal.recipient,
+        contract_withdrawal,
+    })
+}
+
+/// Post the signed `body`, returning the error to record on failure. The host is resolved and
+/// checked again on every post, and redirects are not followed, so a webhook cannot be moved
+/// to a non-public address after registration.
+async fn post_webhook(url: &str, secret: &str, body: Vec<u8>) -> Result<(), String> {
+    let (host, addr) = resolve_webhook_url(url).await.map_err(|e| e.to_string())?;
+    let http = reqwest::Client::builder()
+        .timeout(Duration::from_secs(WEBHOOK_REQUEST_TIMEOUT))
+        .redirect(reqwest::redirect::Policy::none())
+        .resolve(&host, addr)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let timestamp = chrono::Utc::now().timestamp() as u64;
+    let signature = sign_webhook_payload(secret, timestamp, &body);
+    let response = http
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(WEBHOOK_TIMESTAMP_HEADER, timestamp.to_string())
+        .header(WEBHOOK_SIGNATURE_HEADER, signature)
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("status {}", response.status()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+
+    use super::{is_public_ip, validate_webhook_url, webhook_retry_delay};
+
+    #[test]
+    fn test_webhook_retry_delay() {
+        assert_eq!(webhook_retry_delay(1), 30);
+        assert_eq!(webhook_retry_delay(2), 60);
+        assert_eq!(webhook_retry_delay(3), 120);
+        assert_eq!(webhook_retry_delay(7), 1920);
+        assert_eq!(webhook_retry_delay(8), 3600);
+        assert_eq!(webhook_retry_delay(20), 3600);
+    }
+
+    #[test]
+    fn test_validate_webhook_url() {
+        assert!(validate_webhook_url("https://bridge.example.com/hooks/withdrawal").is_ok());
+        assert!(validate_webhook_url("https://93.184.216.34/hook").is_ok());
+        assert!(validate_webhook_url("http://bridge.example.com/hooks/withdrawal").is_err());
+        assert!(validate_webhook_url("http://localhost:8080").is_err());
+        assert!(validate_webhook_url("https://localhost:8080").is_err());
+        assert!(validate_webhook_url("https://api.localhost").is_err());
+        assert!(validate_webhook_url("https://127.0.0.1/hook").is_err());
+        assert!(validate_webhook_url("https://10.0.0.1/hook").is_err());
+        assert!(validate_webhook_url("https://169.254.169.254/latest/meta-data").is_err());
+        assert!(validate_webhook_url("https://[::1]/hook").is_err());
+        assert!(validate_webhook_url("ftp://example.com").is_err());
+        assert!(validate_webhook_url("not a url").is_err());
+        assert!(
+            validate_webhook_url(&format!("https://example.com/{}", "a".repeat(2048))).is_err()
+        );
+    }
+
+    #[test]
+    fn test_is_public_ip() {
+        let public = ["93.184.216.34", "8.8.8.8", "2606:2800:220:1::1"];
+        for ip in public {
+            assert!(is_public_ip(ip.parse::<IpAddr>().unwrap()), "{ip}");
+        }
+        let non_public = [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ];
+        for ip in non_public {
+            assert!(!is_public_ip(ip.parse::<IpAddr>().unwrap()), "{ip}");
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use crate::{
     app::status::{SqlClaimStatus, SqlWithdrawalStatus},
     Env,
@@ -5,12 +7,16 @@ use crate::{
 use alloy::primitives::B256;
 use intmax2_interfaces::{
     api::{
-        store_vault_server::interface::StoreVaultClientInterface,
+        store_vault_server::{
+            interface::StoreVaultClientInterface,
+            types::{CursorOrder, MetaDataCursor, MetaDataCursorResponse},
+        },
         withdrawal_server::interface::FeeResult,
     },
     data::{
         data_type::DataType,
         encryption::{errors::BlsEncryptionError, BlsEncryption},
+        meta_data::MetaData,
         transfer_data::TransferData,
     },
 };
@@ -35,8 +41,12 @@ use intmax2_client_sdk::{
 use intmax2_interfaces::{
     api::{
         block_builder::interface::Fee,
-        withdrawal_server::interface::{
-            ClaimFeeInfo, ClaimInfo, ContractWithdrawal, WithdrawalFeeInfo, WithdrawalInfo,
+        withdrawal_server::{
+            interface::{
+                ClaimFeeInfo, ClaimInfo, ContractWithdrawal, WithdrawalFeeInfo, WithdrawalInfo,
+                WithdrawalStatus, WithdrawalSummary, MAX_WITHDRAWAL_BATCH_SIZE,
+            },
+            types::{withdrawal_idempotency_key, RequestWithdrawalRequest},
         },
     },
     data::proof_compression::{CompressedSingleClaimProof, CompressedSingleWithdrawalProof},
@@ -61,6 +71,11 @@ type F = GoldilocksField;
 type C = PoseidonGoldilocksConfig;
 const D: usize = 2;
 
+const WITHDRAWAL_SUMMARY_PAGE_SIZE: i64 = 1000;
+
+/// Max number of infos returned by a single paged info query.
+const MAX_INFO_PAGE_SIZE: u32 = 100;
+
 struct Config {
     is_faster_mining: bool,
     withdrawal_beneficiary_key: Option<KeySet>,
@@ -175,6 +190,8 @@ impl WithdrawalServer {
         ClaimFeeInfo {
             beneficiary: self.config.claim_beneficiary_key.map(|k| k.pubkey),
             fee: self.config.claim_fee.clone(),
+            is_faster_mining: Some(self.config.is_faster_mining),
+            alternative_mode_fee: None,
         }
     }
 
@@ -184,6 +201,7 @@ impl WithdrawalServer {
         single_withdrawal_proof: &ProofWithPublicInputs<F, C, D>,
         fee_token_index: Option<u32>,
         fee_transfer_digests: &[Bytes32],
+        idempotency_key: Option<Bytes32>,
     ) -> Result<FeeResult, WithdrawalServerError> {
         // Verify the single withdrawal proof
         let single_withdrawal_vd = CircuitVerifiers::load().get_single_withdrawal_vd();
@@ -191,6 +209,33 @@ impl WithdrawalServer {
             .verify(single_withdrawal_proof.clone())
             .map_err(|_| WithdrawalServerError::SingleWithdrawalVerificationError)?;
 
+        // A retry of a request that already succeeded returns the original result. This is
+        // checked before the fee validation, which would reject the already spent fee transfers.
+        let expected_idempotency_key = withdrawal_idempotency_key(single_withdrawal_proof);
+        if let Some(idempotency_key) = idempotency_key {
+            if idempotency_key != expected_idempotency_key {
+                return Err(WithdrawalServerError::IdempotencyKeyMismatch {
+                    expected: expected_idempotency_key.to_hex(),
+                    got: idempotency_key.to_hex(),
+                });
+            }
+        }
+        let idempotency_key_str = expected_idempotency_key.to_hex();
+        let already_requested: (bool,) = sqlx::query_as::<_, (bool,)>(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM withdrawals
+                WHERE idempotency_key = $1
+            )
+            "#,
+        )
+        .bind(&idempotency_key_str)
+        .fetch_one(&self.pool)
+        .await?;
+        if already_requested.0 {
+            return Ok(FeeResult::Success);
+        }
+
         let withdrawal =
             Withdrawal::from_u64_slice(&single_withdrawal_proof.public_inputs.to_u64_vec())
                 .map_err(|e| WithdrawalServerError::SerializationError(e.to_string()))?;
@@ -270,9 +315,11 @@ impl WithdrawalServer {
                 withdrawal_hash,
                 single_withdrawal_proof,
                 contract_withdrawal,
-                status
+                status,
+                idempotency_key
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7::withdrawal_status)
+            VALUES ($1, $2, $3, $4, $5, $6, $7::withdrawal_status, $8)
+            ON CONFLICT (idempotency_key) DO NOTHING
             "#,
             uuid_str,
             pubkey_str,
@@ -280,7 +327,8 @@ impl WithdrawalServer {
             withdrawal_hash_str,
             proof_bytes,
             withdrawal_value,
-            SqlWithdrawalStatus::Requested as SqlWithdrawalStatus
+            SqlWithdrawalStatus::Requested as SqlWithdrawalStatus,
+            idempotency_key_str
         )
         .execute(&self.pool)
         .await?;
@@ -288,6 +336,33 @@ impl WithdrawalServer {
         Ok(FeeResult::Success)
     }
 
+    /// Processes the withdrawals one by one like `request_withdrawal`, returning their fee
+    /// results in order. An error aborts the rest of the batch, and since each withdrawal is
+    /// deduplicated by its idempotency key, the client can retry the whole batch.
+    pub async fn request_withdrawal_batch(
+        &self,
+        pubkey: U256,
+        withdrawals: &[RequestWithdrawalRequest],
+    ) -> Result<Vec<FeeResult>, WithdrawalServerError> {
+        if withdrawals.len() > MAX_WITHDRAWAL_BATCH_SIZE {
+            return Err(WithdrawalServerError::TooManyWithdrawals(withdrawals.len()));
+        }
+        let mut fee_results = Vec::with_capacity(withdrawals.len());
+        for withdrawal in withdrawals {
+            let fee_result = self
+                .request_withdrawal(
+                    pubkey,
+                    &withdrawal.single_withdrawal_proof,
+                    withdrawal.fee_token_index,
+                    &withdrawal.fee_transfer_digests,
+                    withdrawal.idempotency_key,
+                )
+                .await?;
+            fee_results.push(fee_result);
+        }
+        Ok(fee_results)
+    }
+
     pub async fn request_claim(
         &self,
         pubkey: U256,
@@ -448,6 +523,244 @@ impl WithdrawalServer {
         Ok(claim_infos)
     }
 
+    /// Get a page of the withdrawal infos of `pubkey`.
+    ///
+    /// Infos are ordered by (request timestamp, withdrawal hash), which are used as the cursor,
+    /// so cursors stay valid while new withdrawals are requested.
+    pub async fn get_withdrawal_info_paged(
+        &self,
+        pubkey: U256,
+        cursor: &MetaDataCursor,
+    ) -> Result<(Vec<WithdrawalInfo>, MetaDataCursorResponse), WithdrawalServerError> {
+        let pubkey_str = pubkey.to_hex();
+        let limit = page_limit(cursor);
+        let (timestamp, digest) = cursor_bound(cursor);
+        let records = match cursor.order {
+            CursorOrder::Asc => sqlx::query!(
+                r#"
+                SELECT
+                    status as "status: SqlWithdrawalStatus",
+                    contract_withdrawal,
+                    l1_tx_hash,
+                    withdrawal_hash,
+                    EXTRACT(EPOCH FROM created_at)::bigint as "timestamp!"
+                FROM withdrawals
+                WHERE pubkey = $1
+                AND (EXTRACT(EPOCH FROM created_at)::bigint, withdrawal_hash) > ($2, $3)
+                ORDER BY EXTRACT(EPOCH FROM created_at)::bigint ASC, withdrawal_hash ASC
+                LIMIT $4
+                "#,
+                pubkey_str,
+                timestamp,
+                digest,
+                limit + 1
+            )
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|r| {
+                (
+                    r.status,
+                    r.contract_withdrawal,
+                    r.l1_tx_hash,
+                    r.withdrawal_hash,
+                    r.timestamp,
+                )
+            })
+            .collect::<Vec<_>>(),
+            CursorOrder::Desc => sqlx::query!(
+                r#"
+                SELECT
+                    status as "status: SqlWithdrawalStatus",
+                    contract_withdrawal,
+                    l1_tx_hash,
+                    withdrawal_hash,
+                    EXTRACT(EPOCH FROM created_at)::bigint as "timestamp!"
+                FROM withdrawals
+                WHERE pubkey = $1
+                AND (EXTRACT(EPOCH FROM created_at)::bigint, withdrawal_hash) < ($2, $3)
+                ORDER BY EXTRACT(EPOCH FROM created_at)::bigint DESC, withdrawal_hash DESC
+                LIMIT $4
+                "#,
+                pubkey_str,
+                timestamp,
+                digest,
+                limit + 1
+            )
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|r| {
+                (
+                    r.status,
+                    r.contract_withdrawal,
+                    r.l1_tx_hash,
+                    r.withdrawal_hash,
+                    r.timestamp,
+                )
+            })
+            .collect::<Vec<_>>(),
+        };
+        let has_more = records.len() > limit as usize;
+
+        let mut withdrawal_infos = Vec::new();
+        let mut next_cursor = None;
+        for (status, contract_withdrawal, l1_tx_hash, withdrawal_hash, timestamp) in
+            records.into_iter().take(limit as usize)
+        {
+            let contract_withdrawal: ContractWithdrawal =
+                serde_json::from_value(contract_withdrawal)
+                    .map_err(|e| WithdrawalServerError::SerializationError(e.to_string()))?;
+            withdrawal_infos.push(WithdrawalInfo {
+                status: status.into(),
+                contract_withdrawal,
+                l1_tx_hash: l1_tx_hash.map(|h| Bytes32::from_hex(&h).unwrap()),
+            });
+            next_cursor = Some(MetaData {
+                timestamp: timestamp as u64,
+                digest: Bytes32::from_hex(&withdrawal_hash).unwrap(),
+            });
+        }
+        let total_count = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) FROM withdrawals
+            WHERE pubkey = $1
+            "#,
+            pubkey_str,
+        )
+        .fetch_one(&self.pool)
+        .await?
+        .unwrap_or(0) as u32;
+        Ok((
+            withdrawal_infos,
+            MetaDataCursorResponse {
+                next_cursor,
+                has_more,
+                total_count,
+            },
+        ))
+    }
+
+    /// Get a page of the claim infos of `pubkey`, ordered by (request timestamp, nullifier).
+    pub async fn get_claim_info_paged(
+        &self,
+        pubkey: U256,
+        cursor: &MetaDataCursor,
+    ) -> Result<(Vec<ClaimInfo>, MetaDataCursorResponse), WithdrawalServerError> {
+        let pubkey_str = pubkey.to_hex();
+        let limit = page_limit(cursor);
+        let (timestamp, digest) = cursor_bound(cursor);
+        let records = match cursor.order {
+            CursorOrder::Asc => sqlx::query!(
+                r#"
+                SELECT
+                    status as "status: SqlClaimStatus",
+                    claim,
+                    submit_claim_proof_tx_hash,
+                    l1_tx_hash,
+                    nullifier,
+                    EXTRACT(EPOCH FROM created_at)::bigint as "timestamp!"
+                FROM claims
+                WHERE pubkey = $1
+                AND (EXTRACT(EPOCH FROM created_at)::bigint, nullifier) > ($2, $3)
+                ORDER BY EXTRACT(EPOCH FROM created_at)::bigint ASC, nullifier ASC
+                LIMIT $4
+                "#,
+                pubkey_str,
+                timestamp,
+                digest,
+                limit + 1
+            )
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|r| {
+                (
+                    r.status,
+                    r.claim,
+                    r.submit_claim_proof_tx_hash,
+                    r.l1_tx_hash,
+                    r.nullifier,
+                    r.timestamp,
+                )
+            })
+            .collect::<Vec<_>>(),
+            CursorOrder::Desc => sqlx::query!(
+                r#"
+                SELECT
+                    status as "status: SqlClaimStatus",
+                    claim,
+                    submit_claim_proof_tx_hash,
+                    l1_tx_hash,
+                    nullifier,
+                    EXTRACT(EPOCH FROM created_at)::bigint as "timestamp!"
+                FROM claims
+                WHERE pubkey = $1
+                AND (EXTRACT(EPOCH FROM created_at)::bigint, nullifier) < ($2, $3)
+                ORDER BY EXTRACT(EPOCH FROM created_at)::bigint DESC, nullifier DESC
+                LIMIT $4
+                "#,
+                pubkey_str,
+                timestamp,
+                digest,
+                limit + 1
+            )
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|r| {
+                (
+                    r.status,
+                    r.claim,
+                    r.submit_claim_proof_tx_hash,
+                    r.l1_tx_hash,
+                    r.nullifier,
+                    r.timestamp,
+                )
+            })
+            .collect::<Vec<_>>(),
+        };
+        let has_more = records.len() > limit as usize;
+
+        let mut claim_infos = Vec::new();
+        let mut next_cursor = None;
+        for (status, claim, submit_claim_proof_tx_hash, l1_tx_hash, nullifier, timestamp) in
+            records.into_iter().take(limit as usize)
+        {
+            let claim: Claim = serde_json::from_value(claim)
+                .map_err(|e| WithdrawalServerError::SerializationError(e.to_string()))?;
+            claim_infos.push(ClaimInfo {
+                status: status.into(),
+                claim,
+                submit_claim_proof_tx_hash: submit_claim_proof_tx_hash
+                    .map(|h| Bytes32::from_hex(&h).unwrap()),
+                l1_tx_hash: l1_tx_hash.map(|h| Bytes32::from_hex(&h).unwrap()),
+            });
+            next_cursor = Some(MetaData {
+                timestamp: timestamp as u64,
+                digest: Bytes32::from_hex(&nullifier).unwrap(),
+            });
+        }
+        let total_count = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) FROM claims
+            WHERE pubkey = $1
+            "#,
+            pubkey_str,
+        )
+        .fetch_one(&self.pool)
+        .await?
+        .unwrap_or(0) as u32;
+        Ok((
+            claim_infos,
+            MetaDataCursorResponse {
+                next_cursor,
+                has_more,
+                total_count,
+            },
+        ))
+    }
+
     pub async fn get_withdrawal_info_by_recipient(
         &self,
         recipient: Address,
@@ -481,6 +794,55 @@ impl WithdrawalServer {
         Ok(withdrawal_infos)
     }
 
+    /// Aggregate the withdrawals of `recipient` per token index.
+    ///
+    /// Records are read in pages of `WITHDRAWAL_SUMMARY_PAGE_SIZE` so that recipients with
+    /// many withdrawals are never loaded into memory at once.
+    pub async fn get_withdrawal_summary_by_recipient(
+        &self,
+        recipient: Address,
+    ) -> Result<Vec<WithdrawalSummary>, WithdrawalServerError> {
+        let recipient_str = recipient.to_hex();
+        let mut summaries: BTreeMap<u32, WithdrawalSummary> = BTreeMap::new();
+        let mut last_uuid = String::new();
+        loop {
+            let records = sqlx::query!(
+                r#"
+                SELECT
+                    uuid,
+                    status as "status: SqlWithdrawalStatus",
+                    contract_withdrawal
+                FROM withdrawals
+                WHERE recipient = $1 AND uuid > $2
+                ORDER BY uuid
+                LIMIT $3
+                "#,
+                recipient_str,
+                last_uuid,
+                WITHDRAWAL_SUMMARY_PAGE_SIZE
+            )
+            .fetch_all(&self.pool)
+            .await?;
+
+            let is_last_page = (records.len() as i64) < WITHDRAWAL_SUMMARY_PAGE_SIZE;
+            for record in records {
+                let contract_withdrawal: ContractWithdrawal =
+                    serde_json::from_value(record.contract_withdrawal)
+                        .map_err(|e| WithdrawalServerError::SerializationError(e.to_string()))?;
+                let status: WithdrawalStatus = record.status.into();
+                summaries
+                    .entry(contract_withdrawal.token_index)
+                    .or_insert_with(|| WithdrawalSummary::new(contract_withdrawal.token_index))
+                    .add(&status, contract_withdrawal.amount);
+                last_uuid = record.uuid;
+            }
+            if is_last_page {
+                break;
+            }
+        }
+        Ok(summaries.into_values().collect())
+    }
+
     async fn fee_validation(
         &self,
         fee_type: FeeType,
@@ -640,6 +1002,22 @@ pub fn privkey_to_keyset(privkey: B256) -> KeySet {
     KeySet::new(privkey.into())
 }
 
+fn page_limit(cursor: &MetaDataCursor) -> i64 {
+    cursor
+        .limit
+        .unwrap_or(MAX_INFO_PAGE_SIZE)
+        .clamp(1, MAX_INFO_PAGE_SIZE) as i64
+}
+
+/// The exclusive (timestamp, digest) bound of the page, as stored in the db.
+fn cursor_bound(cursor: &MetaDataCursor) -> (i64, String) {
+    match (&cursor.cursor, &cursor.order) {
+        (Some(meta), _) => (meta.timestamp as i64, meta.digest.to_hex()),
+        (None, CursorOrder::Asc) => (0, Bytes32::default().to_hex()),
+        (None, CursorOrder::Desc) => (i64::MAX, Bytes32::default().to_hex()),
+    }
+}
+
 #[cfg(test)]
 pub mod test_withdrawal_server_helper {
     use std::{fs, io::Read, panic};
@@ -875,6 +1253,9 @@ mod tests {
                 let fee = parse_fee_str(&env.claim_fee.unwrap()).unwrap();
                 assert_and_stop(cont_name, || assert_eq!(claim_fee.fee.unwrap(), fee));
             }
+            assert_and_stop(cont_name, || {
+                assert_eq!(claim_fee.is_faster_mining, Some(env.is_faster_mining))
+            });
 
             let withdrawal_fee = server.get_withdrawal_fee();
             if withdrawal_fee.beneficiary.is_some()
@@ -1120,3 +1501,46 @@ mod keyset_tests {
         assert_keyset_valid(h);
     }
 }
+
+#[cfg(test)]
+mod cursor_tests {
+    use super::*;
+
+    #[test]
+    fn test_page_limit_is_bounded() {
+        let cursor = |limit| MetaDataCursor {
+            cursor: None,
+            order: CursorOrder::Asc,
+            limit,
+        };
+        assert_eq!(page_limit(&cursor(None)), MAX_INFO_PAGE_SIZE as i64);
+        assert_eq!(page_limit(&cursor(Some(0))), 1);
+        assert_eq!(page_limit(&cursor(Some(10))), 10);
+        assert_eq!(
+            page_limit(&cursor(Some(u32::MAX))),
+            MAX_INFO_PAGE_SIZE as i64
+        );
+    }
+
+    #[test]
+    fn test_cursor_bound() {
+        let mut cursor = MetaDataCursor {
+            cursor: None,
+            order: CursorOrder::Asc,
+            limit: None,
+        };
+        assert_eq!(cursor_bound(&cursor), (0, Bytes32::default().to_hex()));
+        cursor.order = CursorOrder::Desc;
+        assert_eq!(
+            cursor_bound(&cursor),
+            (i64::MAX, Bytes32::default().to_hex())
+        );
+
+        let digest = Bytes32::from_u32_slice(&[1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+        cursor.cursor = Some(MetaData {
+            timestamp: 100,
+            digest,
+        });
+        assert_eq!(cursor_bound(&cursor), (100, digest.to_hex()));
+    }
+}
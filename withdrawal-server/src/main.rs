@@ -1,10 +1,11 @@
 use std::io;
 
-use actix_cors::Cors;
-use actix_web::{web::Data, App, HttpServer};
+use actix_web::{middleware::from_fn, web::Data, App, HttpServer};
 use server_common::{
+    cors::CorsConfig,
     health_check::{health_check, set_name_and_version},
     logger,
+    metrics::track_requests,
 };
 use tracing_actix_web::TracingLogger;
 use withdrawal_server::{
@@ -24,11 +25,14 @@ async fn main() -> std::io::Result<()> {
         .await
         .map_err(|e| io::Error::other(format!("state error: {e}")))?;
     let state = Data::new(state);
+    let cors_config = CorsConfig::from_env().map_err(io::Error::other)?;
+
     HttpServer::new(move || {
-        let cors = Cors::permissive();
+        let cors = cors_config.cors();
         App::new()
             .wrap(cors)
             .wrap(TracingLogger::<logger::CustomRootSpanBuilder>::new())
+            .wrap(from_fn(track_requests))
             .app_data(state.clone())
             .service(health_check)
             .service(withdrawal_server_scope())